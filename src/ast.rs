@@ -2,7 +2,8 @@
 //!
 //! This module defines the Abstract Syntax Tree (AST) for the Useless Programming Language.
 //! The AST represents the structure of the program in a way that's almost, but not quite,
-//! entirely unlike what the programmer intended.
+//! entirely unlike what the programmer intended. Names are interned [`Symbol`]s, written down once
+//! by the parser and looked up by number from then on.
 //!
 //! ## Example
 //! ```rust
@@ -14,6 +15,11 @@
 //! };
 //! ```
 
+use crate::bigint::BigInt;
+use crate::intern::Symbol;
+use crate::json;
+use crate::lexer::Span;
+
 /// Represents literal values in the language.
 /// These values might not stay in their original form for long.
 #[derive(Debug, Clone, PartialEq)]
@@ -22,6 +28,10 @@ pub enum Literal {
     String(String),
     /// A number literal, which might become a string of party emojis
     Number(i64),
+    /// A number literal too big for an i64, which is somehow still not big enough to be useful
+    BigInt(BigInt),
+    /// A number literal with a fractional part, which is as precise as floats ever are
+    Float(f64),
     /// A boolean literal, which might become a string of party emojis
     Boolean(bool),
     /// An array literal, which might randomly shuffle or lose elements
@@ -47,6 +57,8 @@ pub enum BinaryOp {
     Equals,
     /// Less than that might be greater than
     LessThan,
+    /// Null coalescing (`??`) that might keep the null anyway
+    NullCoalesce,
 }
 
 /// Expressions that may or may not evaluate to what you expect.
@@ -55,7 +67,7 @@ pub enum Expression {
     /// A literal value (for now)
     Literal(Literal),
     /// A variable name (if it hasn't gone on vacation)
-    Identifier(String),
+    Identifier(Symbol),
     /// A binary operation that does the opposite of what you want
     BinaryOp {
         /// The operator to misuse
@@ -68,7 +80,7 @@ pub enum Expression {
     /// A function call that might return null or go for coffee
     FunctionCall {
         /// The name of the function to not call properly
-        name: String,
+        name: Symbol,
         /// Arguments that might be ignored
         #[allow(dead_code)]
         arguments: Vec<Expression>,
@@ -80,6 +92,22 @@ pub enum Expression {
         /// The key or index to access
         key: Box<Expression>,
     },
+    /// Optional chaining (`?.`) that returns null instead of exploding
+    OptionalAccess {
+        /// The object that might be null
+        object: Box<Expression>,
+        /// The key to access if it isn't
+        key: Box<Expression>,
+    },
+    /// Slice of a string or array (`value[start..end]`), with either end optional
+    Slice {
+        /// The string or array to slice
+        object: Box<Expression>,
+        /// Where the slice starts (None means the beginning)
+        start: Option<Box<Expression>>,
+        /// Where the slice ends, exclusive (None means the end)
+        end: Option<Box<Expression>>,
+    },
     /// Promise that might resolve at random times
     Promise {
         /// The expression to evaluate
@@ -94,6 +122,15 @@ pub enum Expression {
     },
 }
 
+/// How a catch clause gets its hands on the error.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorBinding {
+    /// `catch e { ... }` binds the error message to a single variable
+    Name(Symbol),
+    /// `catch {code, message} { ... }` binds fields of the error object to variables of the same name
+    Fields(Vec<Symbol>),
+}
+
 /// Statements that make up a Useless program.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Statement {
@@ -105,7 +142,7 @@ pub enum Statement {
     /// Let statement for variables that might go on vacation
     Let {
         /// The name of the variable
-        name: String,
+        name: Symbol,
         /// The value to assign (for now)
         value: Expression,
     },
@@ -125,21 +162,30 @@ pub enum Statement {
         /// The body of the loop
         body: Vec<Statement>,
     },
+    /// For-in loop over an array, object, string or range
+    ForIn {
+        /// The name each item is bound to
+        variable: Symbol,
+        /// The collection to iterate over
+        iterable: Expression,
+        /// The body, run once per item (give or take)
+        body: Vec<Statement>,
+    },
     /// Function declaration that might not work
     Function {
         /// The name of the function
-        name: String,
+        name: Symbol,
         /// The parameters that might be ignored
-        parameters: Vec<String>,
+        parameters: Vec<Symbol>,
         /// The body that might not execute
         body: Vec<Statement>,
     },
     /// Async function that might never resolve
     AsyncFunction {
         /// The name of the function
-        name: String,
+        name: Symbol,
         /// The parameters that might be ignored
-        parameters: Vec<String>,
+        parameters: Vec<Symbol>,
         /// The body that might not execute
         body: Vec<Statement>,
     },
@@ -147,15 +193,32 @@ pub enum Statement {
     TryCatch {
         /// The try block that might fail
         try_block: Vec<Statement>,
-        /// The error variable name
-        error_var: String,
+        /// How the caught error is bound
+        error_binding: ErrorBinding,
         /// The catch block that might catch the wrong error
         catch_block: Vec<Statement>,
     },
+    /// Pray block that retries its statements until the gods lose interest
+    Pray {
+        /// How many times to try, at least once (None means the configured default)
+        attempts: Option<usize>,
+        /// The statements that need divine intervention
+        body: Vec<Statement>,
+    },
+    /// Concurrent block that doesn't end until every async call made in it has finished
+    Concurrent {
+        /// The statements that start the tasks
+        body: Vec<Statement>,
+    },
+    /// Sudo statement that runs a single statement without any chaos (once per program)
+    Sudo {
+        /// The statement with elevated privileges
+        statement: Box<Statement>,
+    },
     /// Module declaration for organizing chaos
     Module {
         /// The name of the module
-        name: String,
+        name: Symbol,
         /// The module body
         body: Vec<Statement>,
     },
@@ -164,6 +227,13 @@ pub enum Statement {
         /// The path to import
         path: String,
     },
+    /// Use statement for importing data instead of chaos, from a JSON file
+    UseData {
+        /// The name the data is bound to
+        name: Symbol,
+        /// The JSON file, relative to the program once it's been through the loader
+        path: String,
+    },
     /// Directive for controlling language behavior
     Directive {
         /// The name of the directive
@@ -186,7 +256,371 @@ pub enum Statement {
         /// The statement being attributed
         statement: Box<Statement>,
     },
+    /// Macro definition, expanded away before the interpreter ever sees it
+    Macro {
+        /// The name of the macro
+        name: Symbol,
+        /// The macro parameters, replaced by whatever was passed in
+        parameters: Vec<Symbol>,
+        /// The template to paste in
+        body: Vec<Statement>,
+    },
+    /// A statement that remembers where it was written, for debuggers. Only parsers told to
+    /// [record spans](crate::parser::Parser::set_record_spans) produce these
+    Located {
+        /// Where the statement is in the source
+        span: Span,
+        /// The statement itself
+        statement: Box<Statement>,
+    },
 }
 
 /// A complete Useless program, ready to misbehave.
 pub type Program = Vec<Statement>;
+
+/// Writes a whole program as a JSON array of statements, for tools that want the tree without the jokes.
+pub fn program_to_json(program: &[Statement]) -> String {
+    block(program)
+}
+
+/// How big and tangled a program (or a function body) is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Metrics {
+    /// How many statements there are, nested ones included (`sudo` and attributes don't count extra)
+    pub statements: usize,
+    /// How deeply blocks are nested; top-level statements are at depth 1
+    pub max_depth: usize,
+    /// How many functions are declared, sync or async, anywhere in it
+    pub functions: usize,
+    /// Cyclomatic complexity, plus one for every `print`, `save`, `promise` and `await`,
+    /// since chaos takes those somewhere unexpected every so often
+    pub cyclomatic_chaos: usize,
+}
+
+/// Measures a program: how much of it there is, how deep it goes and how many ways it can go wrong.
+pub fn metrics(program: &[Statement]) -> Metrics {
+    let mut metrics = Metrics { cyclomatic_chaos: 1, ..Metrics::default() };
+    for statement in program {
+        measure_statement(statement, 1, &mut metrics);
+    }
+    metrics
+}
+
+/// Adds a statement, and everything inside it, to the metrics.
+fn measure_statement(statement: &Statement, depth: usize, metrics: &mut Metrics) {
+    let measure_block = |block: &[Statement], metrics: &mut Metrics| {
+        for statement in block {
+            measure_statement(statement, depth + 1, metrics);
+        }
+    };
+
+    if let Statement::Sudo { statement } | Statement::Attributed { statement, .. } | Statement::Located { statement, .. } = statement {
+        return measure_statement(statement, depth, metrics);
+    }
+    metrics.statements += 1;
+    metrics.max_depth = metrics.max_depth.max(depth);
+    match statement {
+        Statement::Print { value } => {
+            metrics.cyclomatic_chaos += 1;
+            measure_expression(value, metrics);
+        },
+        Statement::Let { value, .. } | Statement::Expression(value) => measure_expression(value, metrics),
+        Statement::Await { expression } => {
+            metrics.cyclomatic_chaos += 1;
+            measure_expression(expression, metrics);
+        },
+        Statement::Save { .. } => metrics.cyclomatic_chaos += 1,
+        Statement::If { condition, then_branch, else_branch } => {
+            metrics.cyclomatic_chaos += 1;
+            measure_expression(condition, metrics);
+            measure_block(then_branch, metrics);
+            if let Some(else_branch) = else_branch {
+                measure_block(else_branch, metrics);
+            }
+        },
+        Statement::Loop { body } | Statement::Pray { body, .. } | Statement::Concurrent { body } => {
+            metrics.cyclomatic_chaos += 1;
+            measure_block(body, metrics);
+        },
+        Statement::ForIn { iterable, body, .. } => {
+            metrics.cyclomatic_chaos += 1;
+            measure_expression(iterable, metrics);
+            measure_block(body, metrics);
+        },
+        Statement::TryCatch { try_block, catch_block, .. } => {
+            metrics.cyclomatic_chaos += 1;
+            measure_block(try_block, metrics);
+            measure_block(catch_block, metrics);
+        },
+        Statement::Function { body, .. } | Statement::AsyncFunction { body, .. } => {
+            metrics.functions += 1;
+            measure_block(body, metrics);
+        },
+        Statement::Module { body, .. } | Statement::Macro { body, .. } => measure_block(body, metrics),
+        Statement::Use { .. } | Statement::UseData { .. } | Statement::Directive { .. } => {},
+        Statement::Sudo { .. } | Statement::Attributed { .. } | Statement::Located { .. } => unreachable!("unwrapped above"),
+    }
+}
+
+/// Adds the branches hiding in an expression to the metrics.
+fn measure_expression(expression: &Expression, metrics: &mut Metrics) {
+    match expression {
+        Expression::Identifier(_) => {},
+        Expression::Literal(Literal::Array(elements)) => {
+            elements.iter().for_each(|element| measure_expression(element, metrics));
+        },
+        Expression::Literal(Literal::Object(fields)) => {
+            fields.iter().for_each(|(_, value)| measure_expression(value, metrics));
+        },
+        Expression::Literal(_) => {},
+        Expression::BinaryOp { op, left, right } => {
+            if *op == BinaryOp::NullCoalesce {
+                metrics.cyclomatic_chaos += 1;
+            }
+            measure_expression(left, metrics);
+            measure_expression(right, metrics);
+        },
+        Expression::FunctionCall { arguments, .. } => {
+            arguments.iter().for_each(|argument| measure_expression(argument, metrics));
+        },
+        Expression::Access { object, key } => {
+            measure_expression(object, metrics);
+            measure_expression(key, metrics);
+        },
+        Expression::OptionalAccess { object, key } => {
+            metrics.cyclomatic_chaos += 1;
+            measure_expression(object, metrics);
+            measure_expression(key, metrics);
+        },
+        Expression::Slice { object, start, end } => {
+            measure_expression(object, metrics);
+            for bound in [start, end].into_iter().flatten() {
+                measure_expression(bound, metrics);
+            }
+        },
+        Expression::Promise { value, timeout } => {
+            metrics.cyclomatic_chaos += 1;
+            measure_expression(value, metrics);
+            if let Some(timeout) = timeout {
+                measure_expression(timeout, metrics);
+            }
+        },
+        Expression::Await { promise } => {
+            metrics.cyclomatic_chaos += 1;
+            measure_expression(promise, metrics);
+        },
+    }
+}
+
+/// Writes a JSON object tagged with the name of the node it describes.
+fn node(kind: &str, fields: &[(&str, String)]) -> String {
+    let mut json = format!(r#"{{"type":{}"#, json::quote(kind));
+    for (name, value) in fields {
+        json.push_str(&format!(",{}:{}", json::quote(name), value));
+    }
+    json.push('}');
+    json
+}
+
+/// Writes a list of statements as a JSON array.
+fn block(statements: &[Statement]) -> String {
+    array(statements.iter().map(Statement::to_json))
+}
+
+/// Writes already-written JSON values as an array.
+fn array(items: impl Iterator<Item = String>) -> String {
+    format!("[{}]", items.collect::<Vec<_>>().join(","))
+}
+
+/// Writes a list of names as a JSON array of strings.
+fn names(names: &[Symbol]) -> String {
+    array(names.iter().map(|name| json::quote(name)))
+}
+
+/// Writes a float so it reads back as a float: `2.0` rather than `2`.
+pub(crate) fn format_float(value: f64) -> String {
+    let text = value.to_string();
+    if value.is_finite() && !text.contains('.') {
+        format!("{}.0", text)
+    } else {
+        text
+    }
+}
+
+/// Writes an optional expression, or `null`.
+fn optional(expression: &Option<Box<Expression>>) -> String {
+    expression.as_ref().map_or_else(|| "null".to_string(), |expression| expression.to_json())
+}
+
+impl Literal {
+    /// Writes the literal as JSON.
+    pub fn to_json(&self) -> String {
+        match self {
+            Literal::String(value) => node("String", &[("value", json::quote(value))]),
+            Literal::Number(value) => node("Number", &[("value", value.to_string())]),
+            Literal::BigInt(value) => node("BigInt", &[("value", value.to_string())]),
+            Literal::Float(value) => node("Float", &[("value", format_float(*value))]),
+            Literal::Boolean(value) => node("Boolean", &[("value", value.to_string())]),
+            Literal::Array(elements) => node("Array", &[("elements", array(elements.iter().map(|e| e.to_json())))]),
+            Literal::Object(fields) => {
+                let fields = fields
+                    .iter()
+                    .map(|(key, value)| format!(r#"{{"key":{},"value":{}}}"#, json::quote(key), value.to_json()));
+                node("Object", &[("fields", array(fields))])
+            },
+            Literal::Null => node("Null", &[]),
+        }
+    }
+}
+
+impl Expression {
+    /// Writes the expression as JSON.
+    pub fn to_json(&self) -> String {
+        match self {
+            Expression::Literal(literal) => node("Literal", &[("value", literal.to_json())]),
+            Expression::Identifier(name) => node("Identifier", &[("name", json::quote(name))]),
+            Expression::BinaryOp { op, left, right } => node(
+                "BinaryOp",
+                &[("op", json::quote(&format!("{:?}", op))), ("left", left.to_json()), ("right", right.to_json())],
+            ),
+            Expression::FunctionCall { name, arguments } => node(
+                "FunctionCall",
+                &[("name", json::quote(name)), ("arguments", array(arguments.iter().map(Expression::to_json)))],
+            ),
+            Expression::Access { object, key } => {
+                node("Access", &[("object", object.to_json()), ("key", key.to_json())])
+            },
+            Expression::OptionalAccess { object, key } => {
+                node("OptionalAccess", &[("object", object.to_json()), ("key", key.to_json())])
+            },
+            Expression::Slice { object, start, end } => {
+                node("Slice", &[("object", object.to_json()), ("start", optional(start)), ("end", optional(end))])
+            },
+            Expression::Promise { value, timeout } => {
+                node("Promise", &[("value", value.to_json()), ("timeout", optional(timeout))])
+            },
+            Expression::Await { promise } => node("Await", &[("promise", promise.to_json())]),
+        }
+    }
+}
+
+impl Statement {
+    /// Returns the statement without its location, if it knows where it was written.
+    pub fn unlocated(&self) -> &Statement {
+        match self {
+            Statement::Located { statement, .. } => statement.unlocated(),
+            statement => statement,
+        }
+    }
+
+    /// Writes the statement (and everything inside it) as JSON.
+    pub fn to_json(&self) -> String {
+        match self {
+            Statement::Print { value } => node("Print", &[("value", value.to_json())]),
+            Statement::Let { name, value } => node("Let", &[("name", json::quote(name)), ("value", value.to_json())]),
+            Statement::Expression(expression) => node("Expression", &[("expression", expression.to_json())]),
+            Statement::If { condition, then_branch, else_branch } => node(
+                "If",
+                &[
+                    ("condition", condition.to_json()),
+                    ("then_branch", block(then_branch)),
+                    ("else_branch", else_branch.as_deref().map_or_else(|| "null".to_string(), block)),
+                ],
+            ),
+            Statement::Loop { body } => node("Loop", &[("body", block(body))]),
+            Statement::ForIn { variable, iterable, body } => node(
+                "ForIn",
+                &[("variable", json::quote(variable)), ("iterable", iterable.to_json()), ("body", block(body))],
+            ),
+            Statement::Function { name, parameters, body } => node(
+                "Function",
+                &[("name", json::quote(name)), ("parameters", names(parameters)), ("body", block(body))],
+            ),
+            Statement::AsyncFunction { name, parameters, body } => node(
+                "AsyncFunction",
+                &[("name", json::quote(name)), ("parameters", names(parameters)), ("body", block(body))],
+            ),
+            Statement::TryCatch { try_block, error_binding, catch_block } => {
+                let binding = match error_binding {
+                    ErrorBinding::Name(name) => node("Name", &[("name", json::quote(name))]),
+                    ErrorBinding::Fields(fields) => node("Fields", &[("fields", names(fields))]),
+                };
+                node(
+                    "TryCatch",
+                    &[("try_block", block(try_block)), ("error_binding", binding), ("catch_block", block(catch_block))],
+                )
+            },
+            Statement::Pray { attempts, body } => node(
+                "Pray",
+                &[
+                    ("attempts", attempts.map_or_else(|| "null".to_string(), |attempts| attempts.to_string())),
+                    ("body", block(body)),
+                ],
+            ),
+            Statement::Concurrent { body } => node("Concurrent", &[("body", block(body))]),
+            Statement::Sudo { statement } => node("Sudo", &[("statement", statement.to_json())]),
+            Statement::Module { name, body } => node("Module", &[("name", json::quote(name)), ("body", block(body))]),
+            Statement::Use { path } => node("Use", &[("path", json::quote(path))]),
+            Statement::UseData { name, path } => node("UseData", &[("name", json::quote(name)), ("path", json::quote(path))]),
+            Statement::Directive { name } => node("Directive", &[("name", json::quote(name))]),
+            Statement::Save { filename } => node("Save", &[("filename", json::quote(filename))]),
+            Statement::Await { expression } => node("Await", &[("expression", expression.to_json())]),
+            Statement::Attributed { name, statement } => {
+                node("Attributed", &[("name", json::quote(name)), ("statement", statement.to_json())])
+            },
+            Statement::Macro { name, parameters, body } => node(
+                "Macro",
+                &[("name", json::quote(name)), ("parameters", names(parameters)), ("body", block(body))],
+            ),
+            Statement::Located { span, statement } => {
+                node("Located", &[("span", span.to_json()), ("statement", statement.to_json())])
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_metrics() {
+        let source = r#"
+            greet(name) {
+                if (equals(name, null)) {
+                    print(name?.first ?? "nobody");
+                } else {
+                    for letter in name { sudo save "letters.txt"; }
+                }
+            }
+            async later() { await promise(1); }
+            let x = 1;
+        "#;
+        let program = Parser::new(Lexer::new(source).collect()).parse().unwrap();
+        assert_eq!(
+            metrics(&program),
+            Metrics { statements: 8, max_depth: 4, functions: 2, cyclomatic_chaos: 9 }
+        );
+        assert_eq!(metrics(&[]), Metrics { statements: 0, max_depth: 0, functions: 0, cyclomatic_chaos: 1 });
+    }
+
+    #[test]
+    fn test_program_to_json() {
+        let program = vec![Statement::Let {
+            name: "x".into(),
+            value: Expression::FunctionCall {
+                name: "add".into(),
+                arguments: vec![
+                    Expression::Literal(Literal::Number(1)),
+                    Expression::Literal(Literal::String("\"two\"".to_string())),
+                ],
+            },
+        }];
+        assert_eq!(
+            program_to_json(&program),
+            r#"[{"type":"Let","name":"x","value":{"type":"FunctionCall","name":"add","arguments":[{"type":"Literal","value":{"type":"Number","value":1}},{"type":"Literal","value":{"type":"String","value":"\"two\""}}]}}]"#
+        );
+    }
+}
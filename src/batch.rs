@@ -0,0 +1,170 @@
+//! # Batch Module
+//!
+//! Runs a pile of independent programs in parallel, for graders and services
+//! that have to suffer through many user scripts at once.
+//! Every program gets its own interpreter and captured output, and is cut off after
+//! `max_statements` statements if the config sets a limit.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::batch::{run_many, BatchSummary};
+//! use useless_lang::config::Config;
+//! use useless_lang::{Lexer, Parser};
+//!
+//! let programs = ["let x = 1;", "let y = 2;"]
+//!     .iter()
+//!     .map(|source| Parser::new(Lexer::new(source).collect()).parse().unwrap())
+//!     .collect();
+//! let config = Config { max_statements: 100, ..Config::default() };
+//!
+//! let results = run_many(programs, &config);
+//! assert_eq!(results.len(), 2);
+//! println!("{}", BatchSummary::from_results(&results));
+//! ```
+
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::thread;
+
+use crate::ast::Program;
+use crate::config::Config;
+use crate::interpreter::{Interpreter, RuntimeError, Value};
+use crate::output::CapturedOutput;
+use crate::report::RunResult;
+use crate::stats::Stats;
+
+/// Runs a program in a fresh interpreter, capturing its output.
+pub fn run_one(program: Program, config: &Config) -> RunResult {
+    let output = CapturedOutput::new();
+    let mut interpreter = Interpreter::with_config(config.clone());
+    interpreter.set_output(Box::new(output.clone()));
+
+    panic::catch_unwind(AssertUnwindSafe(|| interpreter.interpret_with_report(program))).unwrap_or_else(|_| {
+        RunResult {
+            last_value: Value::Null,
+            output: output.contents(),
+            errors: vec![RuntimeError::Generic("the interpreter panicked".to_string())],
+            parse_errors: Vec::new(),
+            exit_status: None,
+            usage: interpreter.resource_usage().clone(),
+            chaos_events: interpreter.chaos_audit().to_vec(),
+            warnings: interpreter.warnings().to_vec(),
+            stats: interpreter.stats(),
+        }
+    })
+}
+
+/// Runs independent programs in parallel, one interpreter each.
+/// Results come back in the same order as the programs.
+pub fn run_many(programs: Vec<Program>, config: &Config) -> Vec<RunResult> {
+    let workers = thread::available_parallelism().map_or(1, |n| n.get()).min(programs.len()).max(1);
+
+    // Deal the programs out round-robin, remembering where each one came from
+    let mut queues: Vec<Vec<(usize, Program)>> = (0..workers).map(|_| Vec::new()).collect();
+    for (index, program) in programs.into_iter().enumerate() {
+        queues[index % workers].push((index, program));
+    }
+
+    let mut results: Vec<(usize, RunResult)> = thread::scope(|scope| {
+        let handles: Vec<_> = queues
+            .into_iter()
+            .map(|queue| {
+                scope.spawn(move || {
+                    queue
+                        .into_iter()
+                        .map(|(index, program)| (index, run_one(program, config)))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("run_one catches its own panics"))
+            .collect()
+    });
+
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// The combined damage of a batch of runs.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BatchSummary {
+    /// How many programs ran without an error
+    pub succeeded: usize,
+    /// How many programs ended with an error
+    pub failed: usize,
+    /// Stats added up across every run (the contagion level is the worst one seen)
+    pub stats: Stats,
+}
+
+impl BatchSummary {
+    /// Adds up the results of a batch.
+    pub fn from_results(results: &[RunResult]) -> Self {
+        let mut summary = Self::default();
+        for result in results {
+            if result.is_success() {
+                summary.succeeded += 1;
+            } else {
+                summary.failed += 1;
+            }
+            summary.stats.statements_executed += result.stats.statements_executed;
+            summary.stats.chaos_events += result.stats.chaos_events;
+            summary.stats.errors_caught += result.stats.errors_caught;
+            summary.stats.contagion_level = summary.stats.contagion_level.max(result.stats.contagion_level);
+        }
+        summary
+    }
+}
+
+impl fmt::Display for BatchSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "📦 Batch report: {} succeeded, {} failed", self.succeeded, self.failed)?;
+        write!(f, "{}", self.stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Statement;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    /// Parses a program that starts by disabling all the useless stuff, so the counts are predictable.
+    fn parse(source: &str) -> Program {
+        let mut program = vec![Statement::Directive { name: "disable_all_useless_shit".to_string() }];
+        program.extend(Parser::new(Lexer::new(source).collect()).parse().unwrap());
+        program
+    }
+
+    #[test]
+    fn test_results_keep_program_order() {
+        let sources: Vec<String> = (0..9).map(|i| "let x = 1;\n".repeat(i + 1)).collect();
+        let programs = sources.iter().map(|source| parse(source)).collect();
+        let config = Config { max_statements: 5, ..Config::default() };
+
+        let results = run_many(programs, &config);
+
+        assert_eq!(results.len(), 9);
+        for (i, result) in results.iter().enumerate() {
+            let expected = (i + 1).min(config.max_statements + 1);
+            assert_eq!(result.stats.statements_executed, expected, "program {}", i);
+        }
+        assert!(results[..5].iter().all(RunResult::is_success));
+        for result in &results[5..] {
+            assert!(matches!(result.errors[..], [RuntimeError::TooManyStatements(5)]));
+        }
+    }
+
+    #[test]
+    fn test_summary_adds_everything_up() {
+        let config = Config { max_statements: 1, ..Config::default() };
+        let results = run_many(vec![parse("let a = 1; let b = 2;"), parse("let c = 3; let d = 4;")], &config);
+        let summary = BatchSummary::from_results(&results);
+
+        assert_eq!(summary.failed, 2);
+        assert_eq!(summary.stats.statements_executed, 4);
+        assert!(summary.to_string().contains("0 succeeded, 2 failed"));
+    }
+}
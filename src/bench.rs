@@ -0,0 +1,139 @@
+//! # Bench Module
+//!
+//! Timing programs, for everyone who keeps asking for a faster interpreter.
+//! Every iteration runs the program in a fresh interpreter with all chaos switched off, output
+//! captured and a fake clock, so the numbers measure the interpreter and not the dramatic pauses.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::{bench, Config, Lexer, Parser};
+//!
+//! let program = Parser::new(Lexer::new("let x = 1; print(x);").collect()).parse().unwrap();
+//! let report = bench::run(&program, 3, Config::default()).unwrap();
+//! assert_eq!(report.iterations, 3);
+//! assert_eq!(report.statements_executed, 2);
+//! assert!(report.min <= report.mean && report.mean <= report.max);
+//! println!("{}", report);
+//! ```
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use thiserror::Error;
+
+use crate::ast::Program;
+use crate::clock::ManualClock;
+use crate::config::Config;
+use crate::interpreter::{Interpreter, RuntimeError};
+use crate::output::CapturedOutput;
+
+/// How many times a program runs when nobody says otherwise.
+pub const DEFAULT_ITERATIONS: usize = 10;
+
+/// Errors that stop a benchmark from producing numbers worth reading.
+#[derive(Debug, Error)]
+pub enum BenchError {
+    #[error("Zero iterations take no time at all. Congratulations, it's infinitely fast")]
+    NoIterations,
+
+    #[error("Iteration {iteration} failed, so the numbers would be lying: {error}")]
+    Failed {
+        /// Which iteration failed, counting from 1
+        iteration: usize,
+        /// What went wrong
+        error: RuntimeError,
+    },
+}
+
+/// How long a program took, over every iteration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchReport {
+    /// How many times the program ran
+    pub iterations: usize,
+    /// The fastest run
+    pub min: Duration,
+    /// The average run
+    pub mean: Duration,
+    /// The slowest run
+    pub max: Duration,
+    /// How many statements a single run executed (the same every time, since nothing is chaotic)
+    pub statements_executed: usize,
+    /// The most memory the variables took up in any run, estimated
+    pub peak_memory_bytes: usize,
+}
+
+impl BenchReport {
+    /// Returns how many statements the interpreter gets through per second, on average.
+    pub fn statements_per_second(&self) -> f64 {
+        match self.mean.as_secs_f64() {
+            seconds if seconds > 0.0 => self.statements_executed as f64 / seconds,
+            _ => 0.0,
+        }
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "⏱️  {} iterations", self.iterations)?;
+        writeln!(f, "  min:  {:?}", self.min)?;
+        writeln!(f, "  mean: {:?}", self.mean)?;
+        writeln!(f, "  max:  {:?}", self.max)?;
+        writeln!(f, "  Statements per run: {} ({:.0}/s)", self.statements_executed, self.statements_per_second())?;
+        write!(f, "  Peak memory: {} bytes", self.peak_memory_bytes)
+    }
+}
+
+/// Runs a program `iterations` times with chaos disabled, timing each run.
+/// Stops at the first run that fails.
+pub fn run(program: &Program, iterations: usize, config: Config) -> Result<BenchReport, BenchError> {
+    if iterations == 0 {
+        return Err(BenchError::NoIterations);
+    }
+
+    let mut times = Vec::with_capacity(iterations);
+    let mut statements_executed = 0;
+    let mut peak_memory_bytes = 0;
+    for iteration in 1..=iterations {
+        let mut interpreter = Interpreter::with_config(Config { chaos_level: 0, open_browser: false, ..config.clone() });
+        interpreter.set_completely_normal(true);
+        interpreter.set_output(Box::new(CapturedOutput::new()));
+        interpreter.set_clock(Arc::new(ManualClock::new()));
+
+        let start = Instant::now();
+        let result = interpreter.interpret_with_report(program.clone());
+        times.push(start.elapsed());
+
+        if let Some(error) = result.errors.into_iter().next() {
+            return Err(BenchError::Failed { iteration, error });
+        }
+        statements_executed = result.usage.statements_executed;
+        peak_memory_bytes = peak_memory_bytes.max(result.usage.peak_memory_bytes);
+    }
+
+    Ok(BenchReport {
+        iterations,
+        min: times.iter().copied().min().unwrap_or_default(),
+        mean: times.iter().sum::<Duration>() / iterations as u32,
+        max: times.iter().copied().max().unwrap_or_default(),
+        statements_executed,
+        peak_memory_bytes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_failing_programs_get_no_numbers() {
+        let program = Parser::new(Lexer::new("let x = 1; nothing;").collect()).parse().unwrap();
+        assert!(matches!(run(&program, 0, Config::default()), Err(BenchError::NoIterations)));
+        assert!(matches!(
+            run(&program, 5, Config::default()),
+            Err(BenchError::Failed { iteration: 1, error: RuntimeError::UndefinedVariable(_) })
+        ));
+    }
+}
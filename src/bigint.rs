@@ -0,0 +1,276 @@
+//! # BigInt Module
+//!
+//! Arbitrarily large integers, for when an i64 just isn't useless enough.
+//! Arithmetic that would overflow an i64 gets promoted to a [`BigInt`] instead of panicking.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::bigint::BigInt;
+//!
+//! let big = BigInt::parse("99999999999999999999").unwrap();
+//! let bigger = big.clone() * big;
+//! assert_eq!(bigger.to_string(), "9999999999999999999800000000000000000001");
+//! assert_eq!(BigInt::from(42).to_i64(), Some(42));
+//! ```
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+
+/// Each limb holds nine decimal digits.
+const BASE: u64 = 1_000_000_000;
+const BASE_DIGITS: usize = 9;
+
+/// An integer of any size, stored as base 10⁹ limbs, least significant first.
+#[derive(Clone, PartialEq, Eq, Hash, Default)]
+pub struct BigInt {
+    /// Whether the number is below zero (zero is never negative)
+    negative: bool,
+    /// The magnitude, least significant limb first, without trailing zero limbs
+    limbs: Vec<u32>,
+}
+
+impl BigInt {
+    /// Parses a decimal integer with an optional leading minus sign.
+    pub fn parse(text: &str) -> Option<Self> {
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text),
+        };
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+
+        let mut limbs = Vec::with_capacity(digits.len() / BASE_DIGITS + 1);
+        let mut end = digits.len();
+        while end > 0 {
+            let start = end.saturating_sub(BASE_DIGITS);
+            limbs.push(digits[start..end].parse().ok()?);
+            end = start;
+        }
+        Some(Self::from_parts(negative, limbs))
+    }
+
+    /// Checks whether this is zero.
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    /// Checks whether this is below zero.
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    /// Returns roughly how many bytes the digits take up.
+    pub fn heap_size(&self) -> usize {
+        self.limbs.len() * std::mem::size_of::<u32>()
+    }
+
+    /// Converts back into an i64, if it fits.
+    pub fn to_i64(&self) -> Option<i64> {
+        let mut magnitude: i128 = 0;
+        for &limb in self.limbs.iter().rev() {
+            magnitude = magnitude.checked_mul(BASE as i128)?.checked_add(limb as i128)?;
+        }
+        let value = if self.negative { -magnitude } else { magnitude };
+        i64::try_from(value).ok()
+    }
+
+    /// Builds a number from its parts, cleaning up leading zeros and negative zero.
+    fn from_parts(negative: bool, mut limbs: Vec<u32>) -> Self {
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+        let negative = negative && !limbs.is_empty();
+        Self { negative, limbs }
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(value: i64) -> Self {
+        let mut magnitude = value.unsigned_abs();
+        let mut limbs = Vec::new();
+        while magnitude > 0 {
+            limbs.push((magnitude % BASE) as u32);
+            magnitude /= BASE;
+        }
+        Self::from_parts(value < 0, limbs)
+    }
+}
+
+/// Compares two magnitudes, ignoring signs.
+fn compare_magnitudes(a: &[u32], b: &[u32]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+/// Adds two magnitudes.
+fn add_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0;
+    for i in 0..a.len().max(b.len()) {
+        let sum = *a.get(i).unwrap_or(&0) as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+        result.push((sum % BASE) as u32);
+        carry = sum / BASE;
+    }
+    if carry > 0 {
+        result.push(carry as u32);
+    }
+    result
+}
+
+/// Subtracts a smaller magnitude from a larger one.
+fn subtract_magnitudes(larger: &[u32], smaller: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(larger.len());
+    let mut borrow = 0;
+    for (i, &limb) in larger.iter().enumerate() {
+        let mut difference = limb as i64 - *smaller.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = 0;
+        if difference < 0 {
+            difference += BASE as i64;
+            borrow = 1;
+        }
+        result.push(difference as u32);
+    }
+    result
+}
+
+/// Multiplies two magnitudes, the long way.
+fn multiply_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = vec![0u64; a.len() + b.len()];
+    for (i, &x) in a.iter().enumerate() {
+        let mut carry = 0;
+        for (j, &y) in b.iter().enumerate() {
+            let product = result[i + j] + x as u64 * y as u64 + carry;
+            result[i + j] = product % BASE;
+            carry = product / BASE;
+        }
+        let mut k = i + b.len();
+        while carry > 0 {
+            let sum = result[k] + carry;
+            result[k] = sum % BASE;
+            carry = sum / BASE;
+            k += 1;
+        }
+    }
+    result.into_iter().map(|limb| limb as u32).collect()
+}
+
+impl Add for BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::from_parts(self.negative, add_magnitudes(&self.limbs, &other.limbs));
+        }
+        match compare_magnitudes(&self.limbs, &other.limbs) {
+            Ordering::Less => BigInt::from_parts(other.negative, subtract_magnitudes(&other.limbs, &self.limbs)),
+            _ => BigInt::from_parts(self.negative, subtract_magnitudes(&self.limbs, &other.limbs)),
+        }
+    }
+}
+
+impl Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::from_parts(!self.negative, self.limbs)
+    }
+}
+
+impl Sub for BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: BigInt) -> BigInt {
+        self + -other
+    }
+}
+
+impl Mul for BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: BigInt) -> BigInt {
+        BigInt::from_parts(self.negative != other.negative, multiply_magnitudes(&self.limbs, &other.limbs))
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => compare_magnitudes(&self.limbs, &other.limbs),
+            (true, true) => compare_magnitudes(&other.limbs, &self.limbs),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some((most_significant, rest)) = self.limbs.split_last() else {
+            return f.write_str("0");
+        };
+        if self.negative {
+            f.write_str("-")?;
+        }
+        write!(f, "{}", most_significant)?;
+        for limb in rest.iter().rev() {
+            write!(f, "{:09}", limb)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(text: &str) -> BigInt {
+        BigInt::parse(text).unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display_round_trip() {
+        for text in ["0", "7", "-7", "1000000000", "123456789012345678901234567890", "-99999999999999999999"] {
+            assert_eq!(big(text).to_string(), text);
+        }
+        assert_eq!(big("-0").to_string(), "0");
+        assert_eq!(big("000042").to_string(), "42");
+        assert!(BigInt::parse("4two").is_none());
+        assert!(BigInt::parse("").is_none());
+    }
+
+    #[test]
+    fn test_arithmetic_across_signs() {
+        assert_eq!((big("1000000000") - big("1")).to_string(), "999999999");
+        assert_eq!((big("-5") + big("12")).to_string(), "7");
+        assert_eq!((big("5") - big("12")).to_string(), "-7");
+        assert_eq!((big("-123456789123") * big("1000000000")).to_string(), "-123456789123000000000");
+        assert_eq!((big("99999999999999999999") + big("1")).to_string(), "100000000000000000000");
+    }
+
+    #[test]
+    fn test_i64_boundaries() {
+        assert_eq!(BigInt::from(i64::MIN).to_string(), "-9223372036854775808");
+        assert_eq!(BigInt::from(i64::MIN).to_i64(), Some(i64::MIN));
+        assert_eq!((BigInt::from(i64::MAX) + BigInt::from(1)).to_i64(), None);
+    }
+
+    #[test]
+    fn test_ordering() {
+        assert!(big("-100") < big("-2"));
+        assert!(big("-2") < big("0"));
+        assert!(big("99999999999999999999") > big("1000"));
+    }
+}
@@ -0,0 +1,1048 @@
+//! # Builtins Module
+//!
+//! Functions that come with the language, for when writing your own is too much effort.
+//! Every builtin receives its arguments already evaluated (or at least evaluated to something).
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::{Interpreter, Lexer, Parser};
+//!
+//! let tokens = Lexer::new("let x = 1; let x = 2; rewind(1);").collect();
+//! let program = Parser::new(tokens).parse().unwrap();
+//!
+//! let mut interpreter = Interpreter::new();
+//! interpreter.interpret(program).ok();
+//! ```
+
+use std::fs;
+use std::time::Duration;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::bigint::BigInt;
+use crate::capabilities::Capability;
+use crate::chaos::ChaosKind;
+use crate::diff;
+use crate::interpreter::{hex, Interpreter, RuntimeError, SharedValue, Value};
+use crate::object::ObjectMap;
+#[cfg(feature = "regex")]
+use crate::regex::{self, Regex};
+
+/// A builtin function: takes the interpreter and the evaluated arguments, returns a value (or an excuse).
+pub(crate) type Builtin = fn(&mut Interpreter, Vec<Value>) -> Result<Value, RuntimeError>;
+
+/// Finds the builtin with the given name, if the language bothered to provide one.
+pub(crate) fn lookup(name: &str) -> Option<Builtin> {
+    match name {
+        "rewind" => Some(rewind),
+        "assert" => Some(assert),
+        "assertEquals" => Some(assert_equals),
+        "expectError" => Some(expect_error),
+        "expectChaos" => Some(expect_chaos),
+        "clone" => Some(clone),
+        "ref" => Some(make_ref),
+        "forEach" => Some(for_each),
+        "typeOf" => Some(type_of),
+        "isNull" => Some(is_null),
+        "isArray" => Some(is_array),
+        "len" => Some(len),
+        "deepEquals" => Some(deep_equals),
+        "toNumber" => Some(to_number),
+        "toString" => Some(to_string),
+        "parseInt" => Some(parse_int),
+        "readFileBytes" => Some(read_file_bytes),
+        "toHex" => Some(to_hex),
+        "fromBase64" => Some(from_base64),
+        "args" => Some(args),
+        "now" => Some(now),
+        "since" => Some(since),
+        "durationMs" => Some(duration_ms),
+        #[cfg(feature = "regex")]
+        "matches" => Some(matches),
+        #[cfg(feature = "regex")]
+        "findAll" => Some(find_all),
+        #[cfg(feature = "regex")]
+        "replaceRegex" => Some(replace_regex),
+        "push" => Some(push),
+        "range" => Some(range),
+        "slice" => Some(slice),
+        "sort" => Some(sort),
+        "sortBy" => Some(sort_by),
+        "on" => Some(on),
+        _ => None,
+    }
+}
+
+/// `rewind(n)` undoes the last n statements, as if they never happened.
+fn rewind(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let steps = match arguments.as_slice() {
+        [] => 1,
+        [Value::Number { value }] if *value >= 0 => *value as usize,
+        _ => {
+            return Err(RuntimeError::BadArguments(
+                "rewind".to_string(),
+                "expected a non-negative number of statements to regret".to_string(),
+            ))
+        },
+    };
+    let undone = interpreter.rewind_from_program(steps);
+    Ok(Value::Number { value: undone as i64 })
+}
+
+/// `assert(condition, message)` fails with the message unless the condition is true.
+/// In chaos mode, it sometimes needs a pep talk first.
+fn assert(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let (condition, message) = match arguments.as_slice() {
+        [Value::Boolean { value }] => (*value, "the condition was false".to_string()),
+        [Value::Boolean { value }, Value::String { value: message }] => (*value, message.clone()),
+        _ => {
+            return Err(RuntimeError::BadArguments(
+                "assert".to_string(),
+                "expected a boolean and, optionally, a message".to_string(),
+            ))
+        },
+    };
+
+    if !interpreter.behaves_normally() && interpreter.roll(ChaosKind::EmotionalSupport, 0.2) {
+        interpreter.print_line("🥺 assert needs a moment. You're doing great. It believes in you. Okay, checking now...");
+    }
+
+    if condition {
+        Ok(Value::Null)
+    } else {
+        Err(RuntimeError::AssertionFailed(message))
+    }
+}
+
+/// `assertEquals(actual, expected)` fails unless the two are deeply equal, listing every place they differ.
+/// A message can come third, to go on top of the list.
+fn assert_equals(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let (actual, expected, message) = match arguments.as_slice() {
+        [actual, expected] => (actual, expected, "the values differ"),
+        [actual, expected, Value::String { value: message }] => (actual, expected, message.as_str()),
+        _ => {
+            return Err(RuntimeError::BadArguments(
+                "assertEquals".to_string(),
+                "expected the actual value, the expected one and, optionally, a message".to_string(),
+            ))
+        },
+    };
+
+    match diff::value_diff(expected, actual) {
+        differences if differences.is_empty() => Ok(Value::Null),
+        differences => Err(RuntimeError::AssertionFailed(format!("{}:\n{}", message, differences))),
+    }
+}
+
+/// `expectError(fn)` calls a function that's supposed to fail, and returns the error as `{code, message}`.
+/// If the function works, that's the failure.
+fn expect_error(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let name = match arguments.as_slice() {
+        [function] => function_name(function),
+        _ => None,
+    }
+    .ok_or_else(|| RuntimeError::BadArguments("expectError".to_string(), "expected a function".to_string()))?;
+
+    match interpreter.call_function(&name, vec![]) {
+        Ok(_) => Err(RuntimeError::AssertionFailed(format!("expected {}() to fail, but it worked", name))),
+        Err(error) => Ok(Value::Object {
+            fields: ObjectMap::from([
+                ("code".to_string(), Value::String { value: error.name().to_string() }),
+                ("message".to_string(), Value::String { value: interpreter.render_error(&error) }),
+            ]),
+        }),
+    }
+}
+
+/// `expectChaos("array_vacation")` checks that the named chaos already happened during this run,
+/// and returns how many times. A language that behaved itself fails the test.
+fn expect_chaos(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let kind = match only_argument("expectChaos", arguments)? {
+        Value::String { value } => ChaosKind::from_name(&value),
+        _ => None,
+    }
+    .ok_or_else(|| RuntimeError::BadArguments("expectChaos".to_string(), "expected the name of some chaos, like \"array_vacation\"".to_string()))?;
+
+    match interpreter.chaos_this_run().iter().filter(|event| event.kind == kind).count() {
+        0 => Err(RuntimeError::AssertionFailed(format!("expected some {}, but the language behaved itself", kind))),
+        times => Ok(Value::Number { value: times as i64 }),
+    }
+}
+
+/// Takes the single argument of an introspection builtin, with references followed.
+fn only_argument(name: &str, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    match <[Value; 1]>::try_from(arguments) {
+        Ok([value]) => Ok(value.dereference()),
+        Err(_) => Err(RuntimeError::BadArguments(name.to_string(), "expected exactly one value".to_string())),
+    }
+}
+
+/// `typeOf(value)` names the type of a value: string, number, boolean, array, object, function, promise, range,
+/// duration, instant, bytes or null.
+fn type_of(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let value = only_argument("typeOf", arguments)?;
+    let name = match &value {
+        Value::Object { fields } => match fields.get("type") {
+            Some(Value::String { value: kind }) if kind == "function" || kind == "async_function" => "function",
+            Some(Value::String { value: kind }) if interpreter.plugins().is_value_type(kind) => kind,
+            _ => "object",
+        },
+        other => other.type_name(),
+    };
+    Ok(Value::String { value: name.to_string() })
+}
+
+/// `isNull(value)` checks whether a value is null, at least right now.
+fn is_null(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let value = only_argument("isNull", arguments)?;
+    Ok(Value::Boolean { value: value == Value::Null })
+}
+
+/// `isArray(value)` checks whether a value is an array.
+fn is_array(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let value = only_argument("isArray", arguments)?;
+    Ok(Value::Boolean { value: matches!(value, Value::Array { .. }) })
+}
+
+/// `len(value)` counts the characters of a string, the elements of an array, the fields of an object
+/// or the numbers in a range. In chaos mode, the count is sometimes off by one.
+fn len(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let length = match only_argument("len", arguments)? {
+        Value::String { value } => value.chars().count(),
+        Value::Array { values } => values.len(),
+        Value::Object { fields } => fields.len(),
+        Value::Range { start, end } => end.saturating_sub(start).max(0) as usize,
+        Value::Bytes { value } => value.len(),
+        other => {
+            return Err(RuntimeError::BadArguments(
+                "len".to_string(),
+                format!("a {} doesn't have a length", other.type_name()),
+            ))
+        },
+    };
+    let length = length as i64;
+    if !interpreter.behaves_normally() && interpreter.roll(ChaosKind::OffByOne, 0.1) {
+        return Ok(Value::Number { value: length + 1 });
+    }
+    Ok(Value::Number { value: length })
+}
+
+/// `deepEquals(a, b)` checks whether two values have the same structure and contents, all the way down.
+/// In chaos mode it occasionally overthinks it and decides they're different on the inside.
+fn deep_equals(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let [a, b] = <[Value; 2]>::try_from(arguments)
+        .map_err(|_| RuntimeError::BadArguments("deepEquals".to_string(), "expected two values to compare".to_string()))?;
+    let equal = a.deep_equals(&b);
+    if equal && !interpreter.behaves_normally() && interpreter.roll(ChaosKind::Overthinking, 0.05) {
+        return Ok(Value::Boolean { value: false });
+    }
+    Ok(Value::Boolean { value: equal })
+}
+
+/// `toNumber(value)` turns a decimal string (or a number, or a boolean) into a number.
+/// Strings are read the way the lexer reads number literals, so `"3.5"` is a float but `"1e5"` and
+/// `".5"` aren't numbers. Anything that doesn't look like a number becomes null.
+fn to_number(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    Ok(match only_argument("toNumber", arguments)? {
+        number @ (Value::Number { .. } | Value::BigInt { .. } | Value::Float { .. }) => number,
+        Value::Boolean { value } => Value::Number { value: value as i64 },
+        Value::String { value } => {
+            let text = value.trim();
+            let text = text.strip_prefix('+').unwrap_or(text);
+            match text.strip_prefix('-').unwrap_or(text).split_once('.') {
+                Some((whole, fraction)) if is_digits(whole) && is_digits(fraction) => match text.parse::<f64>() {
+                    Ok(value) if value.is_finite() => Value::Float { value },
+                    _ => Value::Null,
+                },
+                Some(_) => Value::Null,
+                None => BigInt::parse(text).map_or(Value::Null, Value::from_bigint),
+            }
+        },
+        _ => Value::Null,
+    })
+}
+
+/// Whether `text` is what the lexer would take for one side of a float literal.
+fn is_digits(text: &str) -> bool {
+    !text.is_empty() && text.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+/// `toString(value)` writes any value out as a string.
+fn to_string(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let value = only_argument("toString", arguments)?;
+    Ok(Value::String { value: value.to_string() })
+}
+
+/// `parseInt(str, base)` parses an integer in any base from 2 to 36 (10 if not given).
+/// Strings that aren't valid in that base, or don't fit in a number, become null.
+fn parse_int(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let (text, base) = match arguments.as_slice() {
+        [Value::String { value }] => (value.clone(), 10),
+        [Value::String { value }, Value::Number { value: base }] if (2..=36).contains(base) => (value.clone(), *base as u32),
+        _ => {
+            return Err(RuntimeError::BadArguments(
+                "parseInt".to_string(),
+                "expected a string and, optionally, a base from 2 to 36".to_string(),
+            ))
+        },
+    };
+    Ok(i64::from_str_radix(text.trim(), base).map_or(Value::Null, |value| Value::Number { value }))
+}
+
+/// `readFileBytes(path)` reads a whole file as bytes, no questions asked about what's in it.
+/// In chaos mode a bit might rot on the way in.
+fn read_file_bytes(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let Value::String { value: path } = only_argument("readFileBytes", arguments)? else {
+        return Err(RuntimeError::BadArguments("readFileBytes".to_string(), "expected a path".to_string()));
+    };
+    interpreter.require(Capability::Filesystem)?;
+    let mut bytes = fs::read(&path).map_err(|error| RuntimeError::ReadError(path, error.to_string()))?;
+
+    if !bytes.is_empty() && !interpreter.behaves_normally() && interpreter.roll(ChaosKind::BitRot, 0.05) {
+        let rng = interpreter.rng();
+        let victim = rng.gen_range(0..bytes.len());
+        bytes[victim] ^= 1 << rng.gen_range(0..8);
+    }
+    Ok(Value::Bytes { value: bytes })
+}
+
+/// `toHex(value)` writes bytes (or a string's UTF-8) as lowercase hex.
+fn to_hex(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let value = match only_argument("toHex", arguments)? {
+        Value::Bytes { value } => hex(&value),
+        Value::String { value } => hex(value.as_bytes()),
+        _ => return Err(RuntimeError::BadArguments("toHex".to_string(), "expected bytes or a string".to_string())),
+    };
+    Ok(Value::String { value })
+}
+
+/// `fromBase64(str)` decodes standard base64 (padding optional) into bytes.
+/// Anything that isn't base64 becomes null.
+fn from_base64(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let Value::String { value } = only_argument("fromBase64", arguments)? else {
+        return Err(RuntimeError::BadArguments("fromBase64".to_string(), "expected a string".to_string()));
+    };
+    Ok(decode_base64(&value).map_or(Value::Null, |value| Value::Bytes { value }))
+}
+
+/// Decodes standard base64, with or without its `=` padding.
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let sextet = |c: u8| match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    };
+    let unpadded = text.as_bytes();
+    let digits = match unpadded.len() % 4 {
+        0 => unpadded.strip_suffix(b"==").or_else(|| unpadded.strip_suffix(b"=")).unwrap_or(unpadded),
+        _ => unpadded,
+    };
+    if digits.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(digits.len() * 3 / 4);
+    for chunk in digits.chunks(4) {
+        let sextets = chunk.iter().map(|&c| sextet(c)).collect::<Option<Vec<u8>>>()?;
+        let bits = sextets.iter().fold(0u32, |bits, &sextet| bits << 6 | sextet as u32) << (6 * (4 - sextets.len()));
+        bytes.extend_from_slice(&bits.to_be_bytes()[1..sextets.len()]);
+    }
+    Some(bytes)
+}
+
+/// `args()` returns what the program was given on the command line, as strings. In chaos mode
+/// they occasionally arrive in a different order.
+fn args(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    if !arguments.is_empty() {
+        return Err(RuntimeError::BadArguments("args".to_string(), "it gives arguments, it doesn't take them".to_string()));
+    }
+    let mut values: Vec<Value> = interpreter.args().iter().map(|arg| Value::String { value: arg.clone() }).collect();
+    if values.len() > 1 && !interpreter.behaves_normally() && interpreter.roll(ChaosKind::ArgumentShuffle, 0.1) {
+        values.shuffle(interpreter.rng());
+    }
+    Ok(Value::Array { values })
+}
+
+/// `now()` returns the current instant, according to the interpreter's clock.
+fn now(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    if !arguments.is_empty() {
+        return Err(RuntimeError::BadArguments("now".to_string(), "time waits for no arguments".to_string()));
+    }
+    interpreter.require(Capability::Clock)?;
+    Ok(Value::Instant { value: interpreter.now() })
+}
+
+/// `since(t)` returns how long ago an instant was (zero if it's in the future).
+fn since(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let Value::Instant { value: then } = only_argument("since", arguments)? else {
+        return Err(RuntimeError::BadArguments("since".to_string(), "expected an instant, like now()".to_string()));
+    };
+    interpreter.require(Capability::Clock)?;
+    let elapsed = interpreter.now().duration_since(then).unwrap_or(Duration::ZERO);
+    Ok(Value::Duration { value: elapsed })
+}
+
+/// `durationMs(n)` returns a duration of n milliseconds.
+fn duration_ms(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    match only_argument("durationMs", arguments)? {
+        Value::Number { value } if value >= 0 => Ok(Value::Duration { value: Duration::from_millis(value as u64) }),
+        _ => Err(RuntimeError::BadArguments(
+            "durationMs".to_string(),
+            "expected a non-negative number of milliseconds".to_string(),
+        )),
+    }
+}
+
+/// Compiles the pattern argument of a regex builtin.
+/// In chaos mode the pattern is sometimes read for its feelings, and matches only itself.
+#[cfg(feature = "regex")]
+fn compile_pattern(interpreter: &mut Interpreter, name: &str, pattern: &str) -> Result<Regex, RuntimeError> {
+    let pattern = if !interpreter.behaves_normally() && interpreter.roll(ChaosKind::EmotionalPattern, 0.1) {
+        regex::escape(pattern)
+    } else {
+        pattern.to_string()
+    };
+    Regex::new(&pattern).map_err(|error| RuntimeError::BadArguments(name.to_string(), error.to_string()))
+}
+
+/// Takes the text and pattern arguments of a regex builtin, plus whatever else it wanted.
+#[cfg(feature = "regex")]
+fn text_and_pattern<const N: usize>(name: &str, arguments: Vec<Value>) -> Result<[String; N], RuntimeError> {
+    let strings: Option<Vec<String>> = arguments
+        .into_iter()
+        .map(|argument| match argument.dereference() {
+            Value::String { value } => Some(value),
+            _ => None,
+        })
+        .collect();
+    strings.and_then(|strings| <[String; N]>::try_from(strings).ok()).ok_or_else(|| {
+        RuntimeError::BadArguments(name.to_string(), format!("expected {} strings, starting with the text and the pattern", N))
+    })
+}
+
+/// `matches(str, pattern)` checks whether a regular expression matches anywhere in a string.
+#[cfg(feature = "regex")]
+fn matches(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let [text, pattern] = text_and_pattern("matches", arguments)?;
+    let regex = compile_pattern(interpreter, "matches", &pattern)?;
+    let value = regex
+        .is_match(&text)
+        .map_err(|error| RuntimeError::BadArguments("matches".to_string(), error.to_string()))?;
+    Ok(Value::Boolean { value })
+}
+
+/// `findAll(str, pattern)` returns every match as `{match, index, groups, named}`.
+/// Groups that didn't take part in a match are null.
+#[cfg(feature = "regex")]
+fn find_all(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let [text, pattern] = text_and_pattern("findAll", arguments)?;
+    let regex = compile_pattern(interpreter, "findAll", &pattern)?;
+    let found = regex
+        .find_all(&text)
+        .map_err(|error| RuntimeError::BadArguments("findAll".to_string(), error.to_string()))?;
+
+    let caught = |text: Option<&str>| text.map_or(Value::Null, |value| Value::String { value: value.to_string() });
+    let values = found
+        .iter()
+        .map(|captures| {
+            let groups = (1..captures.groups().len()).map(|index| caught(captures.text(index))).collect();
+            let named = captures
+                .names()
+                .iter()
+                .flatten()
+                .map(|name| (name.clone(), caught(captures.named(name))))
+                .collect();
+            Value::Object {
+                fields: ObjectMap::from([
+                    ("match".to_string(), caught(captures.text(0))),
+                    ("index".to_string(), Value::Number { value: captures.start() as i64 }),
+                    ("groups".to_string(), Value::Array { values: groups }),
+                    ("named".to_string(), Value::Object { fields: named }),
+                ]),
+            }
+        })
+        .collect();
+    Ok(Value::Array { values })
+}
+
+/// `replaceRegex(str, pattern, replacement)` replaces every match.
+/// `$1` and `${name}` in the replacement refer to capture groups.
+#[cfg(feature = "regex")]
+fn replace_regex(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let [text, pattern, replacement] = text_and_pattern("replaceRegex", arguments)?;
+    let regex = compile_pattern(interpreter, "replaceRegex", &pattern)?;
+    let value = regex
+        .replace_all(&text, &replacement)
+        .map_err(|error| RuntimeError::BadArguments("replaceRegex".to_string(), error.to_string()))?;
+    Ok(Value::String { value })
+}
+
+/// `clone(value)` returns a deep copy that shares nothing with the original.
+fn clone(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    match arguments.as_slice() {
+        [value] => Ok(value.deep_clone()),
+        _ => Err(RuntimeError::BadArguments("clone".to_string(), "expected exactly one value".to_string())),
+    }
+}
+
+/// `ref(value)` returns a reference, so every copy of it shares (and mutates) the same value.
+fn make_ref(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    match <[Value; 1]>::try_from(arguments) {
+        Ok([reference @ Value::Reference { .. }]) => Ok(reference),
+        Ok([value]) => Ok(Value::Reference { target: SharedValue::new(value) }),
+        Err(_) => Err(RuntimeError::BadArguments("ref".to_string(), "expected exactly one value".to_string())),
+    }
+}
+
+/// `push(arr, value)` appends a value to an array. A referenced array is changed in place for everyone
+/// and the new length is returned; a plain array is copied, so the new array is returned instead.
+fn push(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let bad_arguments = || RuntimeError::BadArguments("push".to_string(), "expected an array and a value".to_string());
+    match <[Value; 2]>::try_from(arguments) {
+        Ok([Value::Array { mut values }, value]) => {
+            values.push(value);
+            Ok(Value::Array { values })
+        },
+        Ok([Value::Reference { target }, value]) => {
+            if matches!(&value, Value::Reference { target: inner } if inner.ptr_eq(&target)) {
+                return Err(RuntimeError::BadArguments(
+                    "push".to_string(),
+                    "an array can't contain itself, no matter how self-absorbed it is".to_string(),
+                ));
+            }
+            target.update(|shared| match shared {
+                Value::Array { values } => {
+                    values.push(value);
+                    Ok(Value::Number { value: values.len() as i64 })
+                },
+                _ => Err(bad_arguments()),
+            })
+        },
+        _ => Err(bad_arguments()),
+    }
+}
+
+/// `slice(value, start, end)` returns the characters (or elements) from start up to, but not including, end.
+fn slice(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    match <[Value; 3]>::try_from(arguments) {
+        Ok([target, Value::Number { value: start }, Value::Number { value: end }]) => {
+            slice_value(&target.dereference(), Some(start), Some(end))
+        },
+        _ => Err(RuntimeError::BadArguments(
+            "slice".to_string(),
+            "expected a string or array, a start and an end".to_string(),
+        )),
+    }
+}
+
+/// Turns a possibly negative index into a position, counting negative indices from the end.
+fn position(index: i64, len: usize) -> Option<usize> {
+    let position = if index < 0 { len as i64 + index } else { index };
+    usize::try_from(position).ok()
+}
+
+/// Returns the character of a string (Unicode characters, not bytes) or the element of an array at an index.
+/// Negative indices count from the end.
+pub(crate) fn element_at(target: &Value, index: i64) -> Result<Value, RuntimeError> {
+    let out_of_bounds = || RuntimeError::Generic(format!("Index {} is out of bounds. The value is playing hide and seek!", index));
+    match target {
+        Value::String { value } => {
+            let position = position(index, value.chars().count()).ok_or_else(out_of_bounds)?;
+            value
+                .chars()
+                .nth(position)
+                .map(|c| Value::String { value: c.to_string() })
+                .ok_or_else(out_of_bounds)
+        },
+        Value::Array { values } => {
+            let position = position(index, values.len()).ok_or_else(out_of_bounds)?;
+            values.get(position).cloned().ok_or_else(out_of_bounds)
+        },
+        Value::Bytes { value } => {
+            let position = position(index, value.len()).ok_or_else(out_of_bounds)?;
+            Ok(Value::Number { value: value[position] as i64 })
+        },
+        _ => Err(RuntimeError::Generic("Only strings and arrays can be indexed. Did you try to index a 🦄?".to_string())),
+    }
+}
+
+/// Returns part of a string (by Unicode character) or an array. Missing ends mean "all the way",
+/// negative ends count from the end, and ends out of range are quietly clamped.
+pub(crate) fn slice_value(target: &Value, start: Option<i64>, end: Option<i64>) -> Result<Value, RuntimeError> {
+    let bounds = |len: usize| {
+        let clamp = |index: i64| position(index, len).unwrap_or(0).min(len);
+        let start = start.map(clamp).unwrap_or(0);
+        let end = end.map(clamp).unwrap_or(len).max(start);
+        (start, end)
+    };
+    match target {
+        Value::String { value } => {
+            let (start, end) = bounds(value.chars().count());
+            Ok(Value::String { value: value.chars().skip(start).take(end - start).collect() })
+        },
+        Value::Array { values } => {
+            let (start, end) = bounds(values.len());
+            Ok(Value::Array { values: values[start..end].to_vec() })
+        },
+        Value::Bytes { value } => {
+            let (start, end) = bounds(value.len());
+            Ok(Value::Bytes { value: value[start..end].to_vec() })
+        },
+        _ => Err(RuntimeError::Generic("Only strings and arrays can be sliced. Put down the knife. 🔪".to_string())),
+    }
+}
+
+/// `range(end)` or `range(start, end)` counts from start (or 0) up to, but not including, end.
+fn range(_interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    match arguments.as_slice() {
+        [Value::Number { value: end }] => Ok(Value::Range { start: 0, end: *end }),
+        [Value::Number { value: start }, Value::Number { value: end }] => Ok(Value::Range { start: *start, end: *end }),
+        _ => Err(RuntimeError::BadArguments("range".to_string(), "expected an end, or a start and an end".to_string())),
+    }
+}
+
+/// `forEach(iterable, fn)` calls `fn` with every item of an array, object, string or range.
+fn for_each(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let bad_arguments = || RuntimeError::BadArguments("forEach".to_string(), "expected something iterable and a function".to_string());
+    let [iterable, function] = <[Value; 2]>::try_from(arguments).map_err(|_| bad_arguments())?;
+    let name = function_name(&function).ok_or_else(bad_arguments)?;
+    let items = interpreter.iterate(iterable).ok_or_else(bad_arguments)?;
+
+    for item in items {
+        interpreter.call_function(&name, vec![item])?;
+    }
+    Ok(Value::Null)
+}
+
+/// `sort(arr)` returns a sorted copy of an array (stable, smallest first, at least when nobody is feeling creative).
+fn sort(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let mut values = match <[Value; 1]>::try_from(arguments).map(|[array]| array.dereference()) {
+        Ok(Value::Array { values }) => values,
+        _ => return Err(RuntimeError::BadArguments("sort".to_string(), "expected a single array".to_string())),
+    };
+    values.sort_by(Value::compare);
+    Ok(Value::Array { values: sorted_with_vibes(interpreter, values) })
+}
+
+/// `sortBy(arr, fn)` returns a copy of an array, stably sorted by what `fn` says about each element.
+fn sort_by(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let (values, function) = match <[Value; 2]>::try_from(arguments).map(|[array, function]| (array.dereference(), function)) {
+        Ok((Value::Array { values }, function)) => (values, function),
+        _ => {
+            return Err(RuntimeError::BadArguments(
+                "sortBy".to_string(),
+                "expected an array and a function".to_string(),
+            ))
+        },
+    };
+    let name = function_name(&function).ok_or_else(|| {
+        RuntimeError::BadArguments("sortBy".to_string(), "the second argument isn't a function".to_string())
+    })?;
+
+    let mut keyed = Vec::with_capacity(values.len());
+    for value in values {
+        let key = interpreter.call_function(&name, vec![value.clone()])?;
+        keyed.push((key, value));
+    }
+    keyed.sort_by(|(a, _), (b, _)| a.compare(b));
+
+    let values = keyed.into_iter().map(|(_, value)| value).collect();
+    Ok(Value::Array { values: sorted_with_vibes(interpreter, values) })
+}
+
+/// `on(event, fn)` calls `fn` with the payload of every `event` the host emits from now on.
+fn on(interpreter: &mut Interpreter, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+    let bad_arguments = || RuntimeError::BadArguments("on".to_string(), "expected an event name and a function".to_string());
+    let [event, function] = <[Value; 2]>::try_from(arguments).map_err(|_| bad_arguments())?;
+    let (Value::String { value: event }, Some(handler)) = (event, function_name(&function)) else {
+        return Err(bad_arguments());
+    };
+    interpreter.on_event(event, handler);
+    Ok(Value::Null)
+}
+
+/// Finds the name of the function a value refers to, either directly or by its description.
+fn function_name(value: &Value) -> Option<String> {
+    match value {
+        Value::String { value } => Some(value.clone()),
+        Value::Object { fields } => match (fields.get("type"), fields.get("name")) {
+            (Some(Value::String { value: kind }), Some(Value::String { value: name })) if kind == "function" => {
+                Some(name.clone())
+            },
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Takes freshly sorted values and, in chaos mode, occasionally sorts them descending or by vibes instead.
+fn sorted_with_vibes(interpreter: &mut Interpreter, mut values: Vec<Value>) -> Vec<Value> {
+    if !interpreter.behaves_normally() && interpreter.roll(ChaosKind::SortedByVibes, 0.3) {
+        if interpreter.rng().gen::<bool>() {
+            values.reverse();
+        } else {
+            values.shuffle(interpreter.rng());
+        }
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capabilities::Capabilities;
+    use crate::clock::ManualClock;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_rewind_rejects_negative_regret() {
+        let mut interpreter = Interpreter::new();
+        let result = rewind(&mut interpreter, vec![Value::Number { value: -1 }]);
+        assert!(matches!(result, Err(RuntimeError::BadArguments(_, _))));
+    }
+
+    fn numbers(values: &[i64]) -> Value {
+        Value::Array { values: values.iter().map(|&value| Value::Number { value }).collect() }
+    }
+
+    #[test]
+    fn test_sort_in_normal_mode() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_completely_normal(true);
+        let sorted = sort(&mut interpreter, vec![numbers(&[3, 1, 2])]).unwrap();
+        assert_eq!(sorted, numbers(&[1, 2, 3]));
+
+        let mixed = Value::Array {
+            values: vec![
+                Value::String { value: "b".to_string() },
+                Value::Number { value: 1 },
+                Value::Null,
+                Value::Boolean { value: true },
+            ],
+        };
+        let sorted = sort(&mut interpreter, vec![mixed]).unwrap();
+        assert_eq!(
+            sorted,
+            Value::Array {
+                values: vec![
+                    Value::Null,
+                    Value::Boolean { value: true },
+                    Value::Number { value: 1 },
+                    Value::String { value: "b".to_string() },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn test_assert_and_expect_error() {
+        use crate::ast::{Expression, Statement};
+
+        let mut interpreter = Interpreter::new();
+        interpreter.set_completely_normal(true);
+        let boolean = |value| Value::Boolean { value };
+        let string = |s: &str| Value::String { value: s.to_string() };
+
+        assert_eq!(assert(&mut interpreter, vec![boolean(true)]).unwrap(), Value::Null);
+        assert!(matches!(
+            assert(&mut interpreter, vec![boolean(false), string("math is broken")]),
+            Err(RuntimeError::AssertionFailed(message)) if message == "math is broken"
+        ));
+        assert!(matches!(assert(&mut interpreter, vec![Value::Number { value: 1 }]), Err(RuntimeError::BadArguments(_, _))));
+        assert_eq!(assert_equals(&mut interpreter, vec![numbers(&[1, 2]), numbers(&[1, 2])]).unwrap(), Value::Null);
+        assert!(matches!(
+            assert_equals(&mut interpreter, vec![numbers(&[1]), numbers(&[1, 2])]),
+            Err(RuntimeError::AssertionFailed(message)) if message == "the values differ:\nvalue[1]: missing (expected 2)"
+        ));
+
+        let function = |name: &str, body: Expression| Statement::Function {
+            name: name.into(),
+            parameters: vec![],
+            body: vec![Statement::Expression(body)],
+        };
+        interpreter.execute_statement(function("broken", Expression::Identifier("nothing".into()))).unwrap();
+        interpreter.execute_statement(function("fine", Expression::Literal(crate::ast::Literal::Null))).unwrap();
+
+        match expect_error(&mut interpreter, vec![string("broken")]).unwrap() {
+            Value::Object { fields } => assert_eq!(fields.get("code"), Some(&string("UndefinedVariable"))),
+            other => panic!("Expected the error as an object, got {:?}", other),
+        }
+        assert!(matches!(expect_error(&mut interpreter, vec![string("fine")]), Err(RuntimeError::AssertionFailed(_))));
+    }
+
+    #[test]
+    fn test_expect_chaos_checks_the_audit() {
+        let string = |s: &str| Value::String { value: s.to_string() };
+        let mut normal = Interpreter::new();
+        normal.set_completely_normal(true);
+        assert!(matches!(expect_chaos(&mut normal, vec![string("array_vacation")]), Err(RuntimeError::AssertionFailed(_))));
+        assert!(matches!(expect_chaos(&mut normal, vec![string("mild_inconvenience")]), Err(RuntimeError::BadArguments(_, _))));
+
+        let mut chaotic = (0..)
+            .map(|seed| {
+                let mut interpreter = Interpreter::with_config(crate::config::Config { open_browser: false, ..Default::default() });
+                interpreter.set_seed(seed);
+                interpreter.set_output(Box::new(crate::output::CapturedOutput::new()));
+                interpreter.set_clock(std::sync::Arc::new(crate::clock::ManualClock::new()));
+                let _ = interpreter.interpret(crate::Parser::new(crate::Lexer::new("let x = [1, 2]; x[0];").collect()).parse().unwrap());
+                interpreter
+            })
+            .find(|interpreter| !interpreter.chaos_audit().is_empty())
+            .unwrap();
+        let kind = chaotic.chaos_audit()[0].kind.name();
+        assert!(matches!(expect_chaos(&mut chaotic, vec![string(kind)]), Ok(Value::Number { value }) if value >= 1));
+    }
+
+    #[test]
+    fn test_introspection() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_completely_normal(true);
+        let string = |s: &str| Value::String { value: s.to_string() };
+        let function = Value::Object {
+            fields: ObjectMap::from([("type".to_string(), string("function")), ("name".to_string(), string("f"))]),
+        };
+
+        for (value, expected) in [
+            (numbers(&[1]), "array"),
+            (Value::BigInt { value: crate::bigint::BigInt::from(7) }, "number"),
+            (function, "function"),
+            (Value::Reference { target: SharedValue::new(string("hi")) }, "string"),
+            (Value::Null, "null"),
+        ] {
+            assert_eq!(type_of(&mut interpreter, vec![value]).unwrap(), string(expected));
+        }
+
+        assert_eq!(is_null(&mut interpreter, vec![Value::Null]).unwrap(), Value::Boolean { value: true });
+        assert_eq!(is_array(&mut interpreter, vec![string("[]")]).unwrap(), Value::Boolean { value: false });
+        assert_eq!(len(&mut interpreter, vec![string("héllo")]).unwrap(), Value::Number { value: 5 });
+        assert_eq!(len(&mut interpreter, vec![Value::Range { start: 3, end: 1 }]).unwrap(), Value::Number { value: 0 });
+        assert!(matches!(len(&mut interpreter, vec![Value::Number { value: 1 }]), Err(RuntimeError::BadArguments(_, _))));
+    }
+
+    #[test]
+    fn test_conversions() {
+        let mut interpreter = Interpreter::new();
+        let string = |s: &str| Value::String { value: s.to_string() };
+        let number = |value| Value::Number { value };
+
+        assert_eq!(to_number(&mut interpreter, vec![string(" -42 ")]).unwrap(), number(-42));
+        assert_eq!(
+            to_number(&mut interpreter, vec![string("123456789012345678901234567890")]).unwrap(),
+            Value::BigInt { value: BigInt::parse("123456789012345678901234567890").unwrap() }
+        );
+        assert_eq!(to_number(&mut interpreter, vec![string("3.5")]).unwrap(), Value::Float { value: 3.5 });
+        assert_eq!(to_number(&mut interpreter, vec![string(" -0.25")]).unwrap(), Value::Float { value: -0.25 });
+        for not_a_literal in ["1e5", ".5", "5.", "1.2.3", "-.5", "inf", "NaN"] {
+            assert_eq!(to_number(&mut interpreter, vec![string(not_a_literal)]).unwrap(), Value::Null, "{}", not_a_literal);
+        }
+        assert_eq!(to_number(&mut interpreter, vec![Value::Boolean { value: true }]).unwrap(), number(1));
+
+        assert_eq!(parse_int(&mut interpreter, vec![string("ff"), number(16)]).unwrap(), number(255));
+        assert_eq!(parse_int(&mut interpreter, vec![string("102"), number(2)]).unwrap(), Value::Null);
+        assert!(matches!(parse_int(&mut interpreter, vec![string("1"), number(99)]), Err(RuntimeError::BadArguments(_, _))));
+
+        let array = Value::Array { values: vec![number(1), string("two"), Value::Null] };
+        assert_eq!(to_string(&mut interpreter, vec![array]).unwrap(), string("[1, \"two\", null]"));
+        assert_eq!(to_string(&mut interpreter, vec![string("plain")]).unwrap(), string("plain"));
+    }
+
+    #[test]
+    fn test_sort_rejects_non_arrays() {
+        let mut interpreter = Interpreter::new();
+        let result = sort(&mut interpreter, vec![Value::Number { value: 1 }]);
+        assert!(matches!(result, Err(RuntimeError::BadArguments(_, _))));
+    }
+
+    #[test]
+    fn test_references_share_mutations_and_clones_dont() {
+        let mut interpreter = Interpreter::new();
+        let shared = make_ref(&mut interpreter, vec![numbers(&[1])]).unwrap();
+        let alias = shared.clone();
+        let copy = clone(&mut interpreter, vec![shared.clone()]).unwrap();
+
+        assert_eq!(push(&mut interpreter, vec![alias, Value::Number { value: 2 }]).unwrap(), Value::Number { value: 2 });
+        assert_eq!(shared.clone().dereference(), numbers(&[1, 2]));
+        assert_eq!(copy, numbers(&[1]));
+
+        // Plain arrays are values, so pushing makes a new one
+        let plain = numbers(&[1]);
+        assert_eq!(push(&mut interpreter, vec![plain.clone(), Value::Number { value: 2 }]).unwrap(), numbers(&[1, 2]));
+        assert_eq!(plain, numbers(&[1]));
+    }
+
+    #[test]
+    fn test_push_refuses_to_nest_an_array_in_itself() {
+        let mut interpreter = Interpreter::new();
+        let shared = make_ref(&mut interpreter, vec![numbers(&[])]).unwrap();
+        let result = push(&mut interpreter, vec![shared.clone(), shared]);
+        assert!(matches!(result, Err(RuntimeError::BadArguments(_, _))));
+    }
+
+    #[test]
+    fn test_strings_index_and_slice_by_character() {
+        let word = Value::String { value: "héllo🌍".to_string() };
+        let string = |s: &str| Value::String { value: s.to_string() };
+
+        assert_eq!(element_at(&word, 1).unwrap(), string("é"));
+        assert_eq!(element_at(&word, -1).unwrap(), string("🌍"));
+        assert!(element_at(&word, 6).is_err());
+
+        assert_eq!(slice_value(&word, Some(1), Some(3)).unwrap(), string("él"));
+        assert_eq!(slice_value(&word, Some(4), None).unwrap(), string("o🌍"));
+        assert_eq!(slice_value(&word, None, Some(-1)).unwrap(), string("héllo"));
+        assert_eq!(slice_value(&word, Some(3), Some(100)).unwrap(), string("lo🌍"));
+        assert_eq!(slice_value(&word, Some(3), Some(1)).unwrap(), string(""));
+    }
+
+    #[test]
+    fn test_slice_builtin() {
+        let mut interpreter = Interpreter::new();
+        let result = slice(
+            &mut interpreter,
+            vec![numbers(&[1, 2, 3, 4]), Value::Number { value: 1 }, Value::Number { value: 3 }],
+        );
+        assert_eq!(result.unwrap(), numbers(&[2, 3]));
+    }
+
+    #[test]
+    fn test_range() {
+        let mut interpreter = Interpreter::new();
+        let result = range(&mut interpreter, vec![Value::Number { value: 2 }, Value::Number { value: 5 }]).unwrap();
+        let expected: Vec<Value> = (2..5).map(|value| Value::Number { value }).collect();
+        assert_eq!(result.iterate().unwrap().collect::<Vec<_>>(), expected);
+        assert_eq!(range(&mut interpreter, vec![Value::Number { value: 3 }]).unwrap(), Value::Range { start: 0, end: 3 });
+    }
+
+    #[test]
+    fn test_deep_equals() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_completely_normal(true);
+        let object = |entries: Vec<(&str, Value)>| Value::Object {
+            fields: entries.into_iter().map(|(key, value)| (key.to_string(), value)).collect(),
+        };
+        let left = object(vec![("a", numbers(&[1, 2])), ("b", Value::Null)]);
+        let right = object(vec![
+            ("b", Value::Null),
+            ("a", Value::Reference { target: SharedValue::new(numbers(&[1, 2])) }),
+        ]);
+        let mut equal = |a: &Value, b: &Value| deep_equals(&mut interpreter, vec![a.clone(), b.clone()]).unwrap();
+
+        assert_eq!(equal(&left, &right), Value::Boolean { value: true });
+        assert_eq!(equal(&left, &object(vec![("a", numbers(&[1, 2]))])), Value::Boolean { value: false });
+        assert_eq!(equal(&numbers(&[1, 2]), &numbers(&[2, 1])), Value::Boolean { value: false });
+        assert_eq!(
+            equal(&Value::BigInt { value: BigInt::from(7) }, &Value::Number { value: 7 }),
+            Value::Boolean { value: true }
+        );
+        let promise = |resolved| Value::Promise { value: Box::new(numbers(&[1])), resolved, task: None };
+        assert_eq!(equal(&promise(true), &promise(true)), Value::Boolean { value: true });
+        assert_eq!(equal(&promise(true), &promise(false)), Value::Boolean { value: false });
+        assert!(matches!(deep_equals(&mut interpreter, vec![Value::Null]), Err(RuntimeError::BadArguments(_, _))));
+    }
+
+    #[test]
+    fn test_bytes_builtins() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_completely_normal(true);
+        let string = |s: &str| Value::String { value: s.to_string() };
+
+        let decoded = from_base64(&mut interpreter, vec![string("SGk/")]).unwrap();
+        assert_eq!(decoded, Value::Bytes { value: b"Hi?".to_vec() });
+        for (encoded, expected) in [("SGVsbG8=", &b"Hello"[..]), ("SGVsbG8", b"Hello"), ("SGk=", b"Hi"), ("", b"")] {
+            assert_eq!(decode_base64(encoded).as_deref(), Some(expected), "{}", encoded);
+        }
+        for garbage in ["S", "SGk=a", "SG=k", "S*Gk"] {
+            assert_eq!(decode_base64(garbage), None, "{}", garbage);
+        }
+
+        assert_eq!(to_hex(&mut interpreter, vec![decoded.clone()]).unwrap(), string("48693f"));
+        assert_eq!(to_hex(&mut interpreter, vec![string("é")]).unwrap(), string("c3a9"));
+        assert_eq!(len(&mut interpreter, vec![decoded.clone()]).unwrap(), Value::Number { value: 3 });
+        assert_eq!(element_at(&decoded, -1).unwrap(), Value::Number { value: 63 });
+        assert_eq!(slice_value(&decoded, Some(1), None).unwrap(), Value::Bytes { value: b"i?".to_vec() });
+        assert_eq!(decoded.to_string(), "<3 bytes: 48693f>");
+
+        let path = std::env::temp_dir().join(format!("useless-bytes-{}.bin", std::process::id()));
+        fs::write(&path, [0u8, 255, 10]).unwrap();
+        let read = read_file_bytes(&mut interpreter, vec![string(path.to_str().unwrap())]);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(read.unwrap(), Value::Bytes { value: vec![0, 255, 10] });
+        assert!(matches!(
+            read_file_bytes(&mut interpreter, vec![string(path.to_str().unwrap())]),
+            Err(RuntimeError::ReadError(_, _))
+        ));
+
+        interpreter.set_capabilities(Capabilities::none());
+        assert!(matches!(
+            read_file_bytes(&mut interpreter, vec![string("anything")]),
+            Err(RuntimeError::PermissionDenied(Capability::Filesystem))
+        ));
+    }
+
+    #[test]
+    fn test_args_come_back_as_strings() {
+        let strings = |values: &[&str]| Value::Array { values: values.iter().map(|value| Value::String { value: value.to_string() }).collect() };
+        let mut interpreter = Interpreter::new();
+        interpreter.set_completely_normal(true);
+        assert_eq!(args(&mut interpreter, vec![]).unwrap(), strings(&[]));
+
+        interpreter.set_args(vec!["--loud".to_string(), "42".to_string()]);
+        assert_eq!(args(&mut interpreter, vec![]).unwrap(), strings(&["--loud", "42"]));
+        assert!(matches!(args(&mut interpreter, vec![Value::Null]), Err(RuntimeError::BadArguments(_, _))));
+    }
+
+    #[test]
+    fn test_now_since_and_duration_ms() {
+        let clock = Arc::new(ManualClock::new());
+        let mut interpreter = Interpreter::new();
+        interpreter.set_clock(clock.clone());
+
+        let start = now(&mut interpreter, vec![]).unwrap();
+        clock.advance(Duration::from_millis(1500));
+        assert_eq!(since(&mut interpreter, vec![start]).unwrap(), Value::Duration { value: Duration::from_millis(1500) });
+        assert_eq!(
+            duration_ms(&mut interpreter, vec![Value::Number { value: 250 }]).unwrap(),
+            Value::Duration { value: Duration::from_millis(250) }
+        );
+        assert!(matches!(duration_ms(&mut interpreter, vec![Value::Number { value: -1 }]), Err(RuntimeError::BadArguments(_, _))));
+
+        interpreter.set_capabilities(Capabilities::none());
+        assert!(matches!(now(&mut interpreter, vec![]), Err(RuntimeError::PermissionDenied(Capability::Clock))));
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_regex_builtins() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_completely_normal(true);
+        let string = |s: &str| Value::String { value: s.to_string() };
+        let strings = |values: &[&str]| values.iter().map(|value| string(value)).collect::<Vec<_>>();
+
+        assert_eq!(matches(&mut interpreter, strings(&["abc123", r"\d{3}$"])).unwrap(), Value::Boolean { value: true });
+        assert_eq!(
+            replace_regex(&mut interpreter, strings(&["a-b-c", "-(.)", "+$1"])).unwrap(),
+            string("a+b+c")
+        );
+
+        let found = find_all(&mut interpreter, strings(&["x=1 y=", r"(?P<key>\w)=(\d)?"])).unwrap();
+        let Value::Array { values } = found else { panic!("findAll should return an array") };
+        let Value::Object { fields } = &values[1] else { panic!("every match should be an object") };
+        assert_eq!(fields["match"], string("y="));
+        assert_eq!(fields["index"], Value::Number { value: 4 });
+        assert_eq!(fields["groups"], Value::Array { values: vec![string("y"), Value::Null] });
+        assert_eq!(fields["named"], Value::Object { fields: ObjectMap::from([("key".to_string(), string("y"))]) });
+
+        assert!(matches!(matches(&mut interpreter, strings(&["a", "("])), Err(RuntimeError::BadArguments(_, _))));
+        assert!(matches!(matches(&mut interpreter, vec![string("a")]), Err(RuntimeError::BadArguments(_, _))));
+    }
+
+    #[test]
+    fn test_unknown_builtin() {
+        assert!(lookup("rewind").is_some());
+        assert!(lookup("make_coffee").is_none());
+    }
+}
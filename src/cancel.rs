@@ -0,0 +1,51 @@
+//! # Cancel Module
+//!
+//! A way for another thread (or task) to tell a running interpreter to stop.
+//! The interpreter notices at the next statement boundary and gives up with
+//! [`RuntimeError::CancelledByHuman`](crate::interpreter::RuntimeError::CancelledByHuman).
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::interpreter::{Interpreter, RuntimeError};
+//! use useless_lang::{Lexer, Parser};
+//!
+//! let mut interpreter = Interpreter::new();
+//! interpreter.set_completely_normal(true);
+//! let handle = interpreter.cancel_handle();
+//! std::thread::spawn(move || handle.cancel()).join().unwrap();
+//!
+//! let program = Parser::new(Lexer::new("let x = 1;").collect()).parse().unwrap();
+//! assert!(matches!(interpreter.interpret(program), Err(RuntimeError::CancelledByHuman)));
+//! ```
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A token that stops an interpreter. Clones all control the same interpreter.
+#[derive(Debug, Clone, Default)]
+pub struct CancelHandle {
+    /// Whether someone has had enough
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelHandle {
+    /// Creates a handle that hasn't been triggered.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stops the interpreter at its next statement. It stays stopped until [`CancelHandle::reset`].
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Checks whether the handle has been triggered.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Lets the interpreter run again, for hosts that reuse it (watch mode, say).
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,126 @@
+//! # Capabilities Module
+//!
+//! Capabilities decide which side effects a program is allowed to have.
+//! Everything is allowed by default, because that's funnier. Hosts that run untrusted scripts
+//! should hand the interpreter [`Capabilities::none`] and only add back what they can live with.
+//!
+//! A denied capability makes the statement that needed it fail with
+//! [`RuntimeError::PermissionDenied`](crate::interpreter::RuntimeError::PermissionDenied).
+//! Decorative waiting (dramatic pauses, delays between prayers) is skipped instead.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::capabilities::{Capabilities, Capability};
+//! use useless_lang::interpreter::Interpreter;
+//!
+//! let mut interpreter = Interpreter::new();
+//! interpreter.set_capabilities(Capabilities { clock: true, ..Capabilities::none() });
+//! assert!(!interpreter.capabilities().allows(Capability::Browser));
+//! ```
+
+use std::fmt;
+
+/// A kind of side effect a program might want to have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    /// Opening browser tabs
+    Browser,
+    /// Reading and writing files
+    Filesystem,
+    /// Talking to the outside world
+    Network,
+    /// Reading the time and waiting around
+    Clock,
+    /// Reading environment variables
+    Env,
+}
+
+impl Capability {
+    /// Returns the name of this capability.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Capability::Browser => "browser",
+            Capability::Filesystem => "filesystem",
+            Capability::Network => "network",
+            Capability::Clock => "clock",
+            Capability::Env => "env",
+        }
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Which side effects a program is allowed to have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Whether `print` may open browser tabs
+    pub browser: bool,
+    /// Whether `save` may touch the filesystem
+    pub filesystem: bool,
+    /// Whether anything may use the network
+    pub network: bool,
+    /// Whether the program may read the time, and whether promises, `exit()` and friends may sleep
+    pub clock: bool,
+    /// Whether environment variables may be read
+    pub env: bool,
+}
+
+impl Capabilities {
+    /// Allows every side effect. The default, for maximum uselessness.
+    pub fn all() -> Self {
+        Self {
+            browser: true,
+            filesystem: true,
+            network: true,
+            clock: true,
+            env: true,
+        }
+    }
+
+    /// Allows no side effects at all.
+    pub fn none() -> Self {
+        Self {
+            browser: false,
+            filesystem: false,
+            network: false,
+            clock: false,
+            env: false,
+        }
+    }
+
+    /// Checks whether a capability is allowed.
+    pub fn allows(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::Browser => self.browser,
+            Capability::Filesystem => self.filesystem,
+            Capability::Network => self.network,
+            Capability::Clock => self.clock,
+            Capability::Env => self.env,
+        }
+    }
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allows_matches_fields() {
+        let only_clock = Capabilities { clock: true, ..Capabilities::none() };
+        for capability in [Capability::Browser, Capability::Filesystem, Capability::Network, Capability::Env] {
+            assert!(!only_clock.allows(capability), "{} should be denied", capability);
+            assert!(Capabilities::default().allows(capability));
+        }
+        assert!(only_clock.allows(Capability::Clock));
+    }
+}
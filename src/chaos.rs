@@ -0,0 +1,296 @@
+//! # Chaos Module
+//!
+//! The chaos module keeps the books on every way the interpreter misbehaved.
+//! Each time a chaotic roll comes up, a [`ChaosEvent`] lands in the chaos audit,
+//! so you can prove it was the language and not you.
+//!
+//! Chaos modulators get a say in every roll, and can turn the odds up, down, or off entirely.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::chaos::{ChaosContext, ChaosEvent, ChaosKind, ChaosModulator, HeisenbergModulator};
+//!
+//! let event = ChaosEvent::with_detail(ChaosKind::IdentityTheft, "x and y swapped identities");
+//! assert_eq!(event.kind.name(), "identity_theft");
+//! assert_eq!(ChaosKind::from_name("identity_theft"), Some(ChaosKind::IdentityTheft));
+//!
+//! // Nobody is watching, so anything goes
+//! let unobserved = ChaosContext { observed: false };
+//! assert_eq!(HeisenbergModulator.modulate(ChaosKind::Teapot, 0.1, &unobserved), 0.1);
+//! ```
+
+use std::fmt;
+use std::sync::Arc;
+
+/// Every flavour of misbehaviour the interpreter knows how to commit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChaosKind {
+    /// A teapot error was thrown for no reason
+    Teapot,
+    /// The program succeeded, but was declared perfectly wrong anyway
+    PerfectlyWrong,
+    /// A variable went on vacation instead of being assigned
+    VariableVacation,
+    /// An if statement broke creatively
+    CreativeBreakage,
+    /// A loop failed successfully
+    LoopFailure,
+    /// An async function or await went fishing
+    AsyncFishing,
+    /// A try-catch caught the wrong error
+    WrongErrorCaught,
+    /// A browser tab was opened instead of printing
+    BrowserTab,
+    /// Printed output got random capitalization and emojis
+    OutputMangled,
+    /// A function went for coffee instead of running
+    FunctionCoffee,
+    /// Object keys swapped places
+    ObjectChaos,
+    /// An array went on vacation
+    ArrayVacation,
+    /// An array returned a random element instead of the requested one
+    RandomElement,
+    /// A promise was rejected because of Mercury
+    PromiseRejected,
+    /// A promise resolved to something else entirely
+    PromiseChangedMind,
+    /// A literal turned into something it isn't
+    LiteralMutation,
+    /// Multiplication went on vacation
+    MathVacation,
+    /// A pray block was ignored by the gods
+    UnansweredPrayer,
+    /// Two variables swapped names
+    IdentityTheft,
+    /// The garbage collector ate a variable
+    GarbageCollected,
+    /// A null coalescing fallback was ignored out of spite
+    LackOfFaith,
+    /// An array was sorted descending, or by vibes
+    SortedByVibes,
+    /// A slice ended one character too late, or a for-in loop stopped one item too early
+    OffByOne,
+    /// An assertion needed a pep talk before it could do its job
+    EmotionalSupport,
+    /// A regular expression was read for its feelings instead of its syntax
+    EmotionalPattern,
+    /// A bit flipped while a file was being read
+    BitRot,
+    /// An object's keys were visited in a random order
+    KeyShuffle,
+    /// `deepEquals` thought about it too hard and changed its answer
+    Overthinking,
+    /// The program's arguments arrived in a different order than they were given
+    ArgumentShuffle,
+    /// The tasks left over at the end of a `concurrent` block ran in a random order
+    TaskShuffle,
+    /// An event the host sent was left on read, and no handler heard about it
+    LeftOnRead,
+}
+
+impl ChaosKind {
+    /// Every kind of chaos, in the order they were invented.
+    pub const ALL: [ChaosKind; 31] = [
+        ChaosKind::Teapot,
+        ChaosKind::PerfectlyWrong,
+        ChaosKind::VariableVacation,
+        ChaosKind::CreativeBreakage,
+        ChaosKind::LoopFailure,
+        ChaosKind::AsyncFishing,
+        ChaosKind::WrongErrorCaught,
+        ChaosKind::BrowserTab,
+        ChaosKind::OutputMangled,
+        ChaosKind::FunctionCoffee,
+        ChaosKind::ObjectChaos,
+        ChaosKind::ArrayVacation,
+        ChaosKind::RandomElement,
+        ChaosKind::PromiseRejected,
+        ChaosKind::PromiseChangedMind,
+        ChaosKind::LiteralMutation,
+        ChaosKind::MathVacation,
+        ChaosKind::UnansweredPrayer,
+        ChaosKind::IdentityTheft,
+        ChaosKind::GarbageCollected,
+        ChaosKind::LackOfFaith,
+        ChaosKind::SortedByVibes,
+        ChaosKind::OffByOne,
+        ChaosKind::EmotionalSupport,
+        ChaosKind::EmotionalPattern,
+        ChaosKind::BitRot,
+        ChaosKind::KeyShuffle,
+        ChaosKind::Overthinking,
+        ChaosKind::ArgumentShuffle,
+        ChaosKind::TaskShuffle,
+        ChaosKind::LeftOnRead,
+    ];
+
+    /// Finds the kind of chaos with the given snake_case name.
+    pub fn from_name(name: &str) -> Option<ChaosKind> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    /// Returns the stable snake_case name of this kind of chaos.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChaosKind::Teapot => "teapot",
+            ChaosKind::PerfectlyWrong => "perfectly_wrong",
+            ChaosKind::VariableVacation => "variable_vacation",
+            ChaosKind::CreativeBreakage => "creative_breakage",
+            ChaosKind::LoopFailure => "loop_failure",
+            ChaosKind::AsyncFishing => "async_fishing",
+            ChaosKind::WrongErrorCaught => "wrong_error_caught",
+            ChaosKind::BrowserTab => "browser_tab",
+            ChaosKind::OutputMangled => "output_mangled",
+            ChaosKind::FunctionCoffee => "function_coffee",
+            ChaosKind::ObjectChaos => "object_chaos",
+            ChaosKind::ArrayVacation => "array_vacation",
+            ChaosKind::RandomElement => "random_element",
+            ChaosKind::PromiseRejected => "promise_rejected",
+            ChaosKind::PromiseChangedMind => "promise_changed_mind",
+            ChaosKind::LiteralMutation => "literal_mutation",
+            ChaosKind::MathVacation => "math_vacation",
+            ChaosKind::UnansweredPrayer => "unanswered_prayer",
+            ChaosKind::IdentityTheft => "identity_theft",
+            ChaosKind::GarbageCollected => "garbage_collected",
+            ChaosKind::LackOfFaith => "lack_of_faith",
+            ChaosKind::SortedByVibes => "sorted_by_vibes",
+            ChaosKind::OffByOne => "off_by_one",
+            ChaosKind::EmotionalSupport => "emotional_support",
+            ChaosKind::EmotionalPattern => "emotional_pattern",
+            ChaosKind::BitRot => "bit_rot",
+            ChaosKind::KeyShuffle => "key_shuffle",
+            ChaosKind::Overthinking => "overthinking",
+            ChaosKind::ArgumentShuffle => "argument_shuffle",
+            ChaosKind::TaskShuffle => "task_shuffle",
+            ChaosKind::LeftOnRead => "left_on_read",
+        }
+    }
+}
+
+impl fmt::Display for ChaosKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A single recorded act of chaos.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChaosEvent {
+    /// What kind of chaos happened
+    pub kind: ChaosKind,
+    /// Optional details, for the forensic investigation
+    pub detail: Option<String>,
+}
+
+impl ChaosEvent {
+    /// Creates a chaos event without any details.
+    pub fn new(kind: ChaosKind) -> Self {
+        Self { kind, detail: None }
+    }
+
+    /// Creates a chaos event with details about what exactly went wrong.
+    pub fn with_detail(kind: ChaosKind, detail: impl Into<String>) -> Self {
+        Self {
+            kind,
+            detail: Some(detail.into()),
+        }
+    }
+}
+
+impl fmt::Display for ChaosEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.detail {
+            Some(detail) => write!(f, "{}: {}", self.kind, detail),
+            None => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+/// How much of each kind of chaos a run went through, for the people keeping score.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChaosReport {
+    /// How many times each kind of chaos happened, in the order they were invented (kinds that
+    /// never happened are left out)
+    pub counts: Vec<(ChaosKind, usize)>,
+    /// Every URL `print` picked, whether or not the browser was allowed to open it
+    pub urls: Vec<String>,
+}
+
+impl ChaosReport {
+    /// Returns how many times a kind of chaos happened.
+    pub fn count(&self, kind: ChaosKind) -> usize {
+        self.counts.iter().find(|(counted, _)| *counted == kind).map_or(0, |(_, count)| *count)
+    }
+
+    /// Returns how many chaos events happened, of every kind.
+    pub fn total(&self) -> usize {
+        self.counts.iter().map(|(_, count)| count).sum()
+    }
+}
+
+impl fmt::Display for ChaosReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.total() == 0 {
+            return write!(f, "🎲 Chaos report: nothing happened. Suspicious.");
+        }
+        let total = self.total();
+        write!(f, "🎲 Chaos report: {} event{}", total, if total == 1 { "" } else { "s" })?;
+        for (kind, count) in &self.counts {
+            write!(f, "\n  {}: {}", kind, count)?;
+        }
+        for url in &self.urls {
+            write!(f, "\n  🌐 {}", url)?;
+        }
+        Ok(())
+    }
+}
+
+/// What a chaos modulator knows about the interpreter when it's consulted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChaosContext {
+    /// Whether any execution hooks (tracers, debuggers, nosy people) are attached
+    pub observed: bool,
+}
+
+/// Something that gets a say in how likely chaos is.
+pub trait ChaosModulator: Send + Sync {
+    /// Adjusts the probability of a chaotic roll
+    fn modulate(&self, _kind: ChaosKind, probability: f64, _context: &ChaosContext) -> f64 {
+        probability
+    }
+
+    /// Whether chaos should be switched off entirely, making the interpreter behave normally
+    fn suppresses_chaos(&self, _context: &ChaosContext) -> bool {
+        false
+    }
+}
+
+/// A modulator shared between interpreters gets the same say in each of them.
+impl<M: ChaosModulator + ?Sized> ChaosModulator for Arc<M> {
+    fn modulate(&self, kind: ChaosKind, probability: f64, context: &ChaosContext) -> f64 {
+        (**self).modulate(kind, probability, context)
+    }
+
+    fn suppresses_chaos(&self, context: &ChaosContext) -> bool {
+        (**self).suppresses_chaos(context)
+    }
+}
+
+/// Behaves perfectly whenever someone is watching, and chaotically the moment they look away.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct HeisenbergModulator;
+
+impl ChaosModulator for HeisenbergModulator {
+    fn modulate(&self, _kind: ChaosKind, probability: f64, context: &ChaosContext) -> f64 {
+        if context.observed {
+            0.0
+        } else {
+            probability
+        }
+    }
+
+    fn suppresses_chaos(&self, context: &ChaosContext) -> bool {
+        context.observed
+    }
+}
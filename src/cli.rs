@@ -0,0 +1,473 @@
+//! # CLI Module
+//!
+//! The `useless-lang` command line, written down once: every subcommand, the flags it takes and
+//! the files it wants. The usage text and the shell completion scripts are both generated from
+//! this, so a new flag can't be added to one and forgotten in the other. The flags themselves are
+//! still picked apart by hand in `main.rs`, which turns away anything starting with `--` that it
+//! doesn't recognise instead of mistaking it for a file.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::cli::{self, Shell};
+//!
+//! let script = cli::completions(Shell::Bash);
+//! assert!(script.contains("complete -F _useless_lang useless-lang"));
+//! assert!(cli::usage().contains("useless-lang completions bash|zsh|fish"));
+//! ```
+
+use std::fmt::Write;
+
+/// What the binary is called, as far as shells are concerned.
+pub const BINARY_NAME: &str = "useless-lang";
+
+/// What a flag wants after it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlagValue {
+    /// Nothing, it's a switch
+    None,
+    /// A number, described by the placeholder (`number`, `0-11`)
+    Number(&'static str),
+    /// A path, described by the placeholder
+    File(&'static str),
+    /// One of a few words
+    OneOf(&'static [&'static str]),
+}
+
+/// A single command-line flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Flag {
+    /// The flag itself, dashes and all
+    pub name: &'static str,
+    /// What comes after it
+    pub value: FlagValue,
+    /// One line about what it does
+    pub about: &'static str,
+}
+
+/// What a subcommand takes once the flags are out of the way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Positional {
+    /// Files with one of these extensions
+    Files(&'static [&'static str]),
+    /// One of a few words
+    OneOf(&'static [&'static str]),
+    /// A directory, which may not exist yet
+    Directory,
+}
+
+/// A subcommand, and everything it accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Command {
+    /// What to type
+    pub name: &'static str,
+    /// One line about what it does
+    pub about: &'static str,
+    /// How its arguments look in the usage text
+    pub synopsis: &'static str,
+    /// The flags it understands
+    pub flags: &'static [Flag],
+    /// What goes after the flags
+    pub positional: Positional,
+}
+
+/// The flags for running a program, which is also what happens without any subcommand.
+pub const RUN_FLAGS: &[Flag] = &[
+    Flag { name: "--seed", value: FlagValue::Number("number"), about: "Seed the chaos, to suffer through the same run again" },
+    Flag { name: "--chaos-level", value: FlagValue::Number("0-11"), about: "How much chaos to allow" },
+    Flag { name: "--timeout", value: FlagValue::Number("seconds"), about: "Give up after this many seconds, even mid-nap" },
+    Flag { name: "--max-steps", value: FlagValue::Number("steps"), about: "Give up after evaluating this many statements and expressions" },
+    Flag { name: "--no-browser", value: FlagValue::None, about: "Keep the browser closed, whatever happens" },
+    Flag { name: "--chaos-report", value: FlagValue::None, about: "Count up the chaos once the program is done" },
+    Flag { name: "--normal", value: FlagValue::None, about: "Switch all chaos off, like #[directive(disable_all_useless_shit)]" },
+    Flag { name: "--trace", value: FlagValue::None, about: "Narrate every statement, and the chaos it suffered, on stderr" },
+    Flag { name: "--profile", value: FlagValue::None, about: "Report where the time went, statement by statement, on stderr" },
+    Flag { name: "--profile=json", value: FlagValue::None, about: "Report where the time went as JSON, on stderr" },
+    Flag { name: "--emit-tokens", value: FlagValue::None, about: "Print the tokens before running" },
+    Flag { name: "--emit-ast", value: FlagValue::None, about: "Print the syntax tree before running" },
+    Flag { name: "--emit-ast=json", value: FlagValue::None, about: "Print the syntax tree as JSON before running" },
+    Flag { name: "--format", value: FlagValue::OneOf(&["human", "json"]), about: "How errors are reported" },
+    #[cfg(feature = "dynamic-plugins")]
+    Flag { name: "--plugin", value: FlagValue::File("library"), about: "Load a plugin from a shared library" },
+];
+
+/// The flags for debugging a program: where to stop, and the run flags that matter most.
+const DEBUG_FLAGS: &[Flag] = &[
+    Flag { name: "--break", value: FlagValue::Number("line"), about: "Stop before the statement on this line" },
+    Flag { name: "--seed", value: FlagValue::Number("number"), about: "Seed the chaos, to debug the same run again" },
+    Flag { name: "--chaos-level", value: FlagValue::Number("0-11"), about: "How much chaos to allow" },
+    Flag { name: "--no-browser", value: FlagValue::None, about: "Keep the browser closed, whatever happens" },
+    Flag { name: "--normal", value: FlagValue::None, about: "Switch all chaos off, like #[directive(disable_all_useless_shit)]" },
+];
+
+/// The flags for the REPL: the run flags that make sense without a program.
+const REPL_FLAGS: &[Flag] = &[
+    Flag { name: "--seed", value: FlagValue::Number("number"), about: "Seed the chaos, to suffer through the same session again" },
+    Flag { name: "--chaos-level", value: FlagValue::Number("0-11"), about: "How much chaos to allow" },
+    Flag { name: "--no-browser", value: FlagValue::None, about: "Keep the browser closed, whatever happens" },
+    Flag { name: "--normal", value: FlagValue::None, about: "Switch all chaos off, like #[directive(disable_all_useless_shit)]" },
+];
+
+/// Programs, in source form.
+const SOURCES: Positional = Positional::Files(&["upl"]);
+
+/// Programs, in source form or pre-parsed.
+#[cfg(feature = "uplb")]
+const PROGRAMS: Positional = Positional::Files(&["upl", "uplb"]);
+#[cfg(not(feature = "uplb"))]
+const PROGRAMS: Positional = SOURCES;
+
+/// Every subcommand, in the order the usage text lists them.
+pub const COMMANDS: &[Command] = &[
+    Command { name: "run", about: "Run a program", synopsis: "[run options] <file.upl | -> [args]...", flags: RUN_FLAGS, positional: PROGRAMS },
+    Command { name: "watch", about: "Run a program again every time it's saved", synopsis: "[run options] <file.upl> [args]...", flags: RUN_FLAGS, positional: SOURCES },
+    Command {
+        name: "debug",
+        about: "Run a program one statement at a time, stopping at breakpoints",
+        synopsis: "[run options] [--break <line>]... <file.upl> [args]...",
+        flags: DEBUG_FLAGS,
+        positional: SOURCES,
+    },
+    Command {
+        name: "repl",
+        about: "Type code and watch it go wrong straight away",
+        synopsis: "[--seed <number>] [--chaos-level <0-11>] [--no-browser] [--normal]",
+        flags: REPL_FLAGS,
+        positional: Positional::OneOf(&[]),
+    },
+    Command { name: "check", about: "Parse programs without running them", synopsis: "<file.upl | ->...", flags: &[], positional: SOURCES },
+    Command { name: "lint", about: "Complain about programs that are too complicated", synopsis: "<file.upl | ->...", flags: &[], positional: SOURCES },
+    #[cfg(feature = "uplb")]
+    Command {
+        name: "compile",
+        about: "Save programs pre-parsed, as .uplb files",
+        synopsis: "<file.upl> [-o <file.uplb>]",
+        flags: &[Flag { name: "-o", value: FlagValue::File("file.uplb"), about: "Where to write the .uplb file" }],
+        positional: SOURCES,
+    },
+    Command {
+        name: "tokenize",
+        about: "Print a program's tokens as JSON",
+        synopsis: "[--pretty] <file.upl | ->",
+        flags: &[Flag { name: "--pretty", value: FlagValue::None, about: "Print them for people instead" }],
+        positional: SOURCES,
+    },
+    Command {
+        name: "ast",
+        about: "Print a program's syntax tree as JSON",
+        synopsis: "[--pretty] <file.upl | ->",
+        flags: &[Flag { name: "--pretty", value: FlagValue::None, about: "Print it for people instead" }],
+        positional: SOURCES,
+    },
+    Command {
+        name: "fmt",
+        about: "Lay programs out the one true way",
+        synopsis: "[--check] <file.upl | ->...",
+        flags: &[Flag { name: "--check", value: FlagValue::None, about: "Only complain, don't rewrite anything" }],
+        positional: SOURCES,
+    },
+    Command {
+        name: "test",
+        about: "Run *_test.upl files, and compare other programs against their snapshots",
+        synopsis: "[--normal] [--update-snapshots] [--seed <number>] [<file.upl | directory>...]",
+        flags: &[
+            Flag { name: "--normal", value: FlagValue::None, about: "Run *_test.upl files without chaos" },
+            Flag { name: "--update-snapshots", value: FlagValue::None, about: "Rewrite snapshots from this run" },
+            Flag { name: "--seed", value: FlagValue::Number("number"), about: "Seed the chaos differently" },
+        ],
+        positional: SOURCES,
+    },
+    Command {
+        name: "new",
+        about: "Create a project with a config, a hello world and some examples",
+        synopsis: "[--template chaos|normal] <directory>",
+        flags: &[Flag { name: "--template", value: FlagValue::OneOf(&["chaos", "normal"]), about: "Start chaotic, or start normal" }],
+        positional: Positional::Directory,
+    },
+    Command {
+        name: "doc",
+        about: "Write documentation from the /// comments in programs",
+        synopsis: "[--format markdown|html] [-o <directory>] <file.upl | ->...",
+        flags: &[
+            Flag { name: "--format", value: FlagValue::OneOf(&["markdown", "html"]), about: "Write Markdown, or HTML" },
+            Flag { name: "-o", value: FlagValue::File("directory"), about: "Where to write it (doc by default)" },
+        ],
+        positional: SOURCES,
+    },
+    Command {
+        name: "transpile",
+        about: "Write a program in another language, exactly as wrong as it was",
+        synopsis: "[--target js|py|rust] [-o <file>] <file.upl | ->",
+        flags: &[
+            Flag { name: "--target", value: FlagValue::OneOf(&["js", "py", "rust"]), about: "The language to write it in (js by default)" },
+            Flag { name: "-o", value: FlagValue::File("file"), about: "Where to write it (stdout by default)" },
+        ],
+        positional: SOURCES,
+    },
+    Command {
+        name: "bench",
+        about: "Time a program over several runs, without chaos",
+        synopsis: "[--iterations <number>] <file.upl | ->",
+        flags: &[Flag { name: "--iterations", value: FlagValue::Number("number"), about: "How many times to run it" }],
+        positional: SOURCES,
+    },
+    Command { name: "completions", about: "Print a shell completion script", synopsis: "bash|zsh|fish", flags: &[], positional: Positional::OneOf(Shell::NAMES) },
+    Command { name: "explain", about: "Explain an error code at length", synopsis: "<code>", flags: &[], positional: Positional::OneOf(&[]) },
+    #[cfg(feature = "lsp")]
+    Command { name: "lsp", about: "Start a language server on stdin and stdout", synopsis: "", flags: &[], positional: Positional::OneOf(&[]) },
+];
+
+/// Finds a subcommand by name.
+pub fn command(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|command| command.name == name)
+}
+
+/// Renders the usage text, one line per way of calling the binary, plus a couple of examples.
+pub fn usage() -> String {
+    let flags: Vec<String> = RUN_FLAGS.iter().map(Flag::usage).collect();
+    let mut usage = format!("Usage: {} {} <file.upl | -> [args]...\n", BINARY_NAME, flags.join(" "));
+    for command in COMMANDS {
+        let line = format!("       {} {} {}", BINARY_NAME, command.name, command.synopsis);
+        let _ = writeln!(usage, "{}", line.trim_end());
+    }
+    let _ = writeln!(usage, "Example: {} examples/hello.upl", BINARY_NAME);
+    let _ = writeln!(usage, "         echo 'print(\"hi\");' | {} -", BINARY_NAME);
+    usage
+}
+
+impl Flag {
+    /// Renders the flag the way usage text shows it: `[--seed <number>]`.
+    pub fn usage(&self) -> String {
+        match self.value {
+            FlagValue::None => format!("[{}]", self.name),
+            FlagValue::Number(placeholder) | FlagValue::File(placeholder) => format!("[{} <{}>]", self.name, placeholder),
+            FlagValue::OneOf(words) => format!("[{} {}]", self.name, words.join("|")),
+        }
+    }
+}
+
+/// The shells completion scripts can be written for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    /// Every supported shell's name, as typed on the command line.
+    pub const NAMES: &'static [&'static str] = &["bash", "zsh", "fish"];
+
+    /// Finds a shell by name.
+    pub fn from_name(name: &str) -> Option<Shell> {
+        match name {
+            "bash" => Some(Shell::Bash),
+            "zsh" => Some(Shell::Zsh),
+            "fish" => Some(Shell::Fish),
+            _ => None,
+        }
+    }
+}
+
+/// Writes the completion script for a shell.
+pub fn completions(shell: Shell) -> String {
+    match shell {
+        Shell::Bash => bash(),
+        Shell::Zsh => zsh(),
+        Shell::Fish => fish(),
+    }
+}
+
+/// Bash: `source <(useless-lang completions bash)`.
+fn bash() -> String {
+    let names: Vec<&str> = COMMANDS.iter().map(|command| command.name).collect();
+    let mut script = String::from("_useless_lang() {\n");
+    script.push_str("    local cur=\"${COMP_WORDS[COMP_CWORD]}\" prev=\"${COMP_WORDS[COMP_CWORD-1]}\" command=\"\" words=\"\" extensions=\"\"\n");
+    script.push_str("    [[ $COMP_CWORD -gt 1 ]] && command=\"${COMP_WORDS[1]}\"\n\n");
+
+    script.push_str("    case \"$prev\" in\n");
+    let mut seen = Vec::new();
+    for flag in COMMANDS.iter().flat_map(|command| command.flags).chain(RUN_FLAGS) {
+        if flag.value == FlagValue::None || seen.contains(&flag.name) {
+            continue;
+        }
+        seen.push(flag.name);
+        let reply = match flag.value {
+            FlagValue::OneOf(words) => format!("COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))", words.join(" ")),
+            FlagValue::File(_) => String::from("COMPREPLY=($(compgen -f -- \"$cur\"))"),
+            _ => String::from("COMPREPLY=()"),
+        };
+        let _ = writeln!(script, "        {}) {}; return ;;", flag.name, reply);
+    }
+    script.push_str("    esac\n\n");
+
+    script.push_str("    case \"$command\" in\n");
+    for command in COMMANDS {
+        let (words, extensions) = bash_positional(command.positional);
+        let _ = writeln!(script, "        {}) words=\"{}\" extensions=\"{}\" ;;", command.name, join_flags(command.flags, words), extensions);
+    }
+    let (words, extensions) = bash_positional(PROGRAMS);
+    let _ = writeln!(script, "        *) words=\"{} {}\" extensions=\"{}\" ;;", names.join(" "), join_flags(RUN_FLAGS, words), extensions);
+    script.push_str("    esac\n\n");
+
+    script.push_str("    COMPREPLY=($(compgen -W \"$words\" -- \"$cur\"))\n");
+    script.push_str("    if [[ -n $extensions && $cur != -* ]]; then\n");
+    script.push_str("        COMPREPLY+=($(compgen -d -- \"$cur\"))\n");
+    script.push_str("        for extension in $extensions; do\n");
+    script.push_str("            COMPREPLY+=($(compgen -f -X \"!*.$extension\" -- \"$cur\"))\n");
+    script.push_str("        done\n");
+    script.push_str("    fi\n");
+    script.push_str("}\n");
+    let _ = writeln!(script, "complete -F _useless_lang {}", BINARY_NAME);
+    script
+}
+
+/// Splits what goes after the flags into words to offer and space-separated file extensions.
+/// Directories get `/` as their only extension, which no file has, so only directories are offered.
+fn bash_positional(positional: Positional) -> (&'static [&'static str], String) {
+    match positional {
+        Positional::Files(extensions) => (&[], extensions.join(" ")),
+        Positional::OneOf(words) => (words, String::new()),
+        Positional::Directory => (&[], String::from("/")),
+    }
+}
+
+/// Lists flag names followed by some other words, space separated.
+fn join_flags(flags: &[Flag], words: &[&str]) -> String {
+    flags.iter().map(|flag| flag.name).chain(words.iter().copied()).collect::<Vec<_>>().join(" ")
+}
+
+/// Zsh: save as `_useless-lang` somewhere on `$fpath`.
+fn zsh() -> String {
+    let mut script = format!("#compdef {}\n\n_useless_lang() {{\n    local -a commands\n    commands=(\n", BINARY_NAME);
+    for command in COMMANDS {
+        let _ = writeln!(script, "        '{}:{}'", command.name, zsh_escape(command.about));
+    }
+    script.push_str("    )\n\n");
+    script.push_str("    if (( CURRENT > 2 )) && [[ -n ${commands[(r)${words[2]}:*]} ]]; then\n");
+    script.push_str("        local command=${words[2]}\n");
+    script.push_str("        shift words\n");
+    script.push_str("        (( CURRENT-- ))\n");
+    script.push_str("        case $command in\n");
+    for command in COMMANDS {
+        let _ = writeln!(script, "            {}) _arguments {} ;;", command.name, zsh_arguments(command.flags, command.positional));
+    }
+    script.push_str("        esac\n");
+    script.push_str("    else\n");
+    script.push_str("        (( CURRENT == 2 )) && _describe -t commands 'command' commands\n");
+    let _ = writeln!(script, "        _arguments {}", zsh_arguments(RUN_FLAGS, PROGRAMS));
+    script.push_str("    fi\n}\n\n_useless_lang \"$@\"\n");
+    script
+}
+
+/// Renders flags and positionals as `_arguments` specs.
+fn zsh_arguments(flags: &[Flag], positional: Positional) -> String {
+    let mut specs: Vec<String> = flags
+        .iter()
+        .map(|flag| {
+            let name = flag.name.replace('=', "\\=");
+            let about = zsh_escape(flag.about);
+            match flag.value {
+                FlagValue::None => format!("'{}[{}]'", name, about),
+                FlagValue::Number(placeholder) => format!("'{}[{}]:{}: '", name, about, placeholder),
+                FlagValue::File(placeholder) => format!("'{}[{}]:{}:_files'", name, about, placeholder),
+                FlagValue::OneOf(words) => format!("'{}[{}]:{}:({})'", name, about, flag.name.trim_start_matches('-'), words.join(" ")),
+            }
+        })
+        .collect();
+    specs.push(match positional {
+        Positional::Files([extension]) => format!("'*:file:_files -g \"*.{}\"'", extension),
+        Positional::Files(extensions) => format!("'*:file:_files -g \"*.({})\"'", extensions.join("|")),
+        Positional::OneOf(words) => format!("'1:value:({})'", words.join(" ")),
+        Positional::Directory => String::from("'1:directory:_directories'"),
+    });
+    specs.join(" ")
+}
+
+/// Keeps descriptions from confusing zsh's quoting and `_arguments` syntax.
+fn zsh_escape(text: &str) -> String {
+    text.replace('\'', "'\\''").replace(':', "\\:").replace('[', "\\[").replace(']', "\\]")
+}
+
+/// Fish: save as `useless-lang.fish` in `~/.config/fish/completions`.
+fn fish() -> String {
+    let subcommands: Vec<&str> = COMMANDS.iter().filter(|command| command.flags != RUN_FLAGS).map(|command| command.name).collect();
+    let mut script = format!("complete -c {} -f\n", BINARY_NAME);
+    for command in COMMANDS {
+        let _ = writeln!(script, "complete -c {} -n __fish_use_subcommand -a {} -d '{}'", BINARY_NAME, command.name, fish_escape(command.about));
+    }
+
+    for command in COMMANDS.iter().filter(|command| command.flags != RUN_FLAGS) {
+        let condition = format!("__fish_seen_subcommand_from {}", command.name);
+        fish_command(&mut script, &condition, command.flags, command.positional);
+    }
+    // Running takes the same flags with or without `run` or `watch` in front
+    let condition = format!("not __fish_seen_subcommand_from {}", subcommands.join(" "));
+    fish_command(&mut script, &condition, RUN_FLAGS, PROGRAMS);
+    script
+}
+
+/// Writes the completions for one subcommand's flags and positionals.
+fn fish_command(script: &mut String, condition: &str, flags: &[Flag], positional: Positional) {
+    let prefix = format!("complete -c {} -n '{}'", BINARY_NAME, condition);
+    for flag in flags {
+        let about = fish_escape(flag.about);
+        let name = if let Some(short) = flag.name.strip_prefix('-').filter(|name| !name.starts_with('-')) {
+            format!("-s {}", short)
+        } else if flag.name.contains('=') {
+            // Fish has no idea what to do with a value baked into a flag, so it's offered whole
+            let _ = writeln!(script, "{} -a '{}' -d '{}'", prefix, flag.name, about);
+            continue;
+        } else {
+            format!("-l {}", flag.name.trim_start_matches('-'))
+        };
+        let value = match flag.value {
+            FlagValue::None => String::new(),
+            FlagValue::Number(_) => String::from(" -x"),
+            FlagValue::File(_) => String::from(" -r -F"),
+            FlagValue::OneOf(words) => format!(" -x -a '{}'", words.join(" ")),
+        };
+        let _ = writeln!(script, "{} {}{} -d '{}'", prefix, name, value, about);
+    }
+    let candidates = match positional {
+        Positional::Files(extensions) => {
+            let suffixes: Vec<String> = extensions.iter().map(|extension| format!("__fish_complete_suffix .{}", extension)).collect();
+            format!("({})", suffixes.join("; "))
+        },
+        Positional::OneOf(words) => words.join(" "),
+        Positional::Directory => String::from("(__fish_complete_directories)"),
+    };
+    let _ = writeln!(script, "{} -a '{}'", prefix, candidates);
+}
+
+/// Keeps descriptions from ending fish's single quotes early.
+fn fish_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_command_and_flag_is_completed() {
+        for shell in Shell::NAMES.iter().map(|name| Shell::from_name(name).unwrap()) {
+            let script = completions(shell);
+            for command in COMMANDS {
+                assert!(script.contains(command.name), "{:?} is missing {}", shell, command.name);
+                for flag in command.flags {
+                    let name = flag.name.trim_start_matches('-').replace('=', "\\=");
+                    assert!(script.contains(&name) || script.contains(flag.name), "{:?} is missing {}", shell, flag.name);
+                }
+            }
+        }
+        assert_eq!(Shell::from_name("powershell"), None);
+    }
+
+    #[test]
+    fn test_usage_lists_every_command() {
+        let usage = usage();
+        assert!(usage.starts_with("Usage: useless-lang [--seed <number>] [--chaos-level <0-11>]"));
+        assert!(usage.contains("useless-lang fmt [--check] <file.upl | ->..."));
+        assert_eq!(usage.lines().count(), COMMANDS.len() + 3);
+    }
+}
@@ -0,0 +1,221 @@
+//! # Clock Module
+//!
+//! The clock module decides what time it is and how long a nap takes.
+//! The interpreter never sleeps on its own; it asks its clock to do it,
+//! so tests can swap in a clock that only pretends to sleep.
+//! Programs can stop time themselves with `#[directive(frozen_clock("2024-01-01T00:00:00Z"))]`;
+//! [`parse_timestamp`] reads the time they stop it at.
+//!
+//! ## Example
+//! ```rust
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//! use useless_lang::clock::{Clock, ManualClock};
+//!
+//! let clock = Arc::new(ManualClock::new());
+//! clock.sleep(Duration::from_millis(500));
+//! assert_eq!(clock.total_slept(), Duration::from_millis(500));
+//!
+//! let monday = useless_lang::clock::parse_timestamp("2024-01-01T09:00:00Z").unwrap();
+//! assert_eq!(monday.duration_since(std::time::UNIX_EPOCH).unwrap().as_secs(), 1_704_099_600);
+//! ```
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use tokio::sync::{mpsc, oneshot};
+
+/// A source of time that can also be asked to wait.
+pub trait Clock: Send + Sync {
+    /// Returns the current time, according to this clock
+    fn now(&self) -> SystemTime;
+
+    /// Waits for the given duration (or pretends to)
+    fn sleep(&self, duration: Duration);
+
+    /// Hears that someone else did the waiting on this clock's behalf, so clocks that only
+    /// pretend can move their hands too. Real clocks already noticed.
+    fn waited(&self, _duration: Duration) {}
+}
+
+/// The real clock, which actually makes you wait.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// A clock that never really sleeps, it just moves its hands forward.
+/// Perfect for tests that don't want to wait for dramatic pauses.
+#[derive(Debug)]
+pub struct ManualClock {
+    /// The time this clock currently claims it is
+    now: Mutex<SystemTime>,
+    /// How long everyone has asked this clock to sleep in total
+    slept: Mutex<Duration>,
+}
+
+impl ManualClock {
+    /// Creates a manual clock starting at the Unix epoch.
+    pub fn new() -> Self {
+        Self::starting_at(SystemTime::UNIX_EPOCH)
+    }
+
+    /// Creates a manual clock starting at the given time.
+    pub fn starting_at(time: SystemTime) -> Self {
+        Self {
+            now: Mutex::new(time),
+            slept: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    /// Moves the clock forward without anyone sleeping.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+
+    /// Returns the total amount of time this clock was asked to sleep.
+    pub fn total_slept(&self) -> Duration {
+        *self.slept.lock().unwrap()
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime {
+        *self.now.lock().unwrap()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+        *self.slept.lock().unwrap() += duration;
+    }
+
+    fn waited(&self, duration: Duration) {
+        self.sleep(duration);
+    }
+}
+
+/// A nap a [`HostedClock`] wants taken. The program that asked for it stays asleep until it's finished.
+#[derive(Debug)]
+pub struct Nap {
+    /// How long the nap is
+    pub duration: Duration,
+    /// Wakes the program up
+    done: oneshot::Sender<()>,
+}
+
+impl Nap {
+    /// Ends the nap, waking the program up right where it fell asleep.
+    pub fn finish(self) {
+        let _ = self.done.send(());
+    }
+}
+
+/// A clock that hands every sleep to an async host and waits for the host to say it's over, so
+/// the waiting happens on the host's timers while the program stays paused at the spot that
+/// asked. The program has to run somewhere it's allowed to block, like tokio's blocking pool,
+/// because that's where it's left waiting.
+pub struct HostedClock {
+    /// The clock that actually knows what time it is
+    inner: Arc<dyn Clock>,
+    /// Where naps are sent for the host to take
+    naps: mpsc::UnboundedSender<Nap>,
+}
+
+impl HostedClock {
+    /// Wraps a clock, returning it along with the naps the host is expected to take.
+    pub fn new(inner: Arc<dyn Clock>) -> (Self, mpsc::UnboundedReceiver<Nap>) {
+        let (naps, requests) = mpsc::unbounded_channel();
+        (Self { inner, naps }, requests)
+    }
+}
+
+impl Clock for HostedClock {
+    fn now(&self) -> SystemTime {
+        self.inner.now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        let (done, woken) = oneshot::channel();
+        if self.naps.send(Nap { duration, done }).is_ok() {
+            // A host that stopped listening won't wake us, but it won't mind us waking up either
+            let _ = woken.blocking_recv();
+        }
+        self.inner.waited(duration);
+    }
+}
+
+/// Reads a UTC timestamp written like `2024-01-01T00:00:00Z`. Other time zones are not
+/// supported; it's always UTC somewhere.
+pub fn parse_timestamp(text: &str) -> Option<SystemTime> {
+    let (date, time) = text.strip_suffix(['Z', 'z'])?.split_once(['T', 't'])?;
+    let number = |part: &str, digits: usize| (part.len() == digits && part.bytes().all(|b| b.is_ascii_digit())).then(|| part.parse::<i64>().ok()).flatten();
+
+    let mut date = date.split('-');
+    let (year, month, day) = (number(date.next()?, 4)?, number(date.next()?, 2)?, number(date.next()?, 2)?);
+    let mut time = time.split(':');
+    let (hour, minute, second) = (number(time.next()?, 2)?, number(time.next()?, 2)?, number(time.next()?, 2)?);
+    if date.next().is_some() || time.next().is_some() {
+        return None;
+    }
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+
+    let seconds = days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second;
+    let offset = Duration::from_secs(seconds.unsigned_abs());
+    if seconds >= 0 {
+        SystemTime::UNIX_EPOCH.checked_add(offset)
+    } else {
+        SystemTime::UNIX_EPOCH.checked_sub(offset)
+    }
+}
+
+/// Returns how many days a month has, leap years included.
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Converts a (year, month, day) date into days since the epoch.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamps_are_read_in_utc() {
+        let seconds = |text| parse_timestamp(text).map(|time| time.duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs());
+        assert_eq!(seconds("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(seconds("2024-02-29T23:59:59Z"), Some(1_709_251_199));
+        assert_eq!(parse_timestamp("1969-12-31T23:59:59Z"), SystemTime::UNIX_EPOCH.checked_sub(Duration::from_secs(1)));
+        for nonsense in ["2023-02-29T00:00:00Z", "2024-01-01T00:00:00", "2024-01-01T24:00:00Z", "2024-1-01T00:00:00Z", "yesterday"] {
+            assert_eq!(parse_timestamp(nonsense), None, "{}", nonsense);
+        }
+    }
+}
@@ -0,0 +1,617 @@
+//! The JavaScript backend, for programs that want to be wrong in a browser too.
+//! The output runs on Node as it is.
+
+use super::{behaviour, check_supported, default_pray_attempts, error_messages, mangle, quote, Backend, CodegenError, Writer};
+use crate::ast::{BinaryOp, ErrorBinding, Expression, Literal, Statement};
+use crate::intern::Symbol;
+use crate::interpreter::PHILOSOPHICAL_QUESTIONS;
+
+/// Everything generated programs lean on, written once at the top of every one.
+const RUNTIME: &str = r#"class UplError extends Error {
+  constructor(code, message) {
+    super(message ?? UPL_MESSAGES[code]);
+    this.code = code;
+  }
+}
+
+class UplExit extends Error {
+  constructor(status) {
+    super(`exit(${status})`);
+    this.status = status;
+  }
+}
+
+function upl_fail(message) {
+  throw new UplError("Generic", message);
+}
+
+function upl_numbers(left, right, what) {
+  if (typeof left !== "number" || typeof right !== "number") upl_fail(`Invalid types for ${what}`);
+}
+
+// add subtracts, or multiplies and then adds. It's a coin flip
+function upl_add(left, right) {
+  upl_numbers(left, right, "addition");
+  return Math.random() < 0.5 ? left - right : left * right + right;
+}
+
+function upl_add_normally(left, right) {
+  upl_numbers(left, right, "addition");
+  return left + right;
+}
+
+// multiply divides, rounding towards zero
+function upl_multiply(left, right) {
+  upl_numbers(left, right, "multiplication");
+  if (right === 0) throw new UplError("DivisionByZero");
+  return Math.trunc(left / right);
+}
+
+function upl_multiply_normally(left, right) {
+  upl_numbers(left, right, "multiplication");
+  return left * right;
+}
+
+// equals has no idea, so it guesses
+function upl_equals(left, right) {
+  upl_numbers(left, right, "equality");
+  return Math.random() < 0.5;
+}
+
+function upl_equals_normally(left, right) {
+  upl_numbers(left, right, "equality");
+  return left === right;
+}
+
+// lessThan is greater than
+function upl_less_than(left, right) {
+  upl_numbers(left, right, "less than");
+  return left > right;
+}
+
+function upl_less_than_normally(left, right) {
+  upl_numbers(left, right, "less than");
+  return left < right;
+}
+
+function upl_condition(value) {
+  if (typeof value !== "boolean") upl_fail("Condition must be a boolean");
+  return value;
+}
+
+function upl_position(index, length) {
+  return index < 0 ? length + index : index;
+}
+
+function upl_index(target, index) {
+  if (typeof index !== "number") upl_fail("Indices must be numbers! Did you try to index with a 🦄?");
+  const items = typeof target === "string" ? Array.from(target) : target;
+  if (!Array.isArray(items)) upl_fail("Only strings and arrays can be indexed. Did you try to index a 🦄?");
+  const position = upl_position(index, items.length);
+  if (position < 0 || position >= items.length) upl_fail(`Index ${index} is out of bounds. The value is playing hide and seek!`);
+  return items[position];
+}
+
+// Objects never give their fields up this way. Arrays usually do
+function upl_access(target, key) {
+  if (target instanceof Map) {
+    if (typeof key !== "string") upl_fail("Object keys must be strings! What kind of chaos are you trying to create? 🎭");
+    throw new UplError("ObjectChaos");
+  }
+  if (Array.isArray(target)) {
+    if (typeof key !== "number") upl_fail("Array indices must be numbers! Did you try to index with a 🦄?");
+    if (key < 0 || key >= target.length) upl_fail(`Index ${key} is out of bounds. The array is playing hide and seek!`);
+    return target[key];
+  }
+  upl_fail("Cannot access fields of non-object types. What did you expect?");
+}
+
+function upl_optional_access(target, key) {
+  if (target instanceof Map && typeof key === "string") return target.has(key) ? target.get(key) : null;
+  if (Array.isArray(target) && typeof key === "number" && key >= 0 && key < target.length) return target[key];
+  return null;
+}
+
+function upl_slice(target, start, end) {
+  if ((start !== null && typeof start !== "number") || (end !== null && typeof end !== "number")) {
+    upl_fail("Slice bounds must be numbers! 🔪");
+  }
+  const items = typeof target === "string" ? Array.from(target) : target;
+  if (!Array.isArray(items)) upl_fail("Only strings and arrays can be sliced. Put down the knife. 🔪");
+  const clamp = (index) => Math.min(Math.max(upl_position(index, items.length), 0), items.length);
+  const from = start === null ? 0 : clamp(start);
+  const to = end === null ? items.length : Math.max(clamp(end), from);
+  const slice = items.slice(from, to);
+  return typeof target === "string" ? slice.join("") : slice;
+}
+
+function upl_iterate(value) {
+  if (Array.isArray(value)) return value.slice();
+  if (typeof value === "string") return Array.from(value);
+  if (value instanceof Map) return Array.from(value, ([key, field]) => [key, field]);
+  upl_fail("That's not something you can loop over. Trust me, I tried. 🔁");
+}
+
+function upl_promise(value, _timeout) {
+  return { upl_promise: true, value, task: null };
+}
+
+function upl_is_promise(value) {
+  return value !== null && typeof value === "object" && value.upl_promise === true;
+}
+
+// Runs async calls nobody has awaited yet
+function upl_settle(value) {
+  if (upl_is_promise(value) && value.task !== null) {
+    env.forget(value.task);
+    return upl_promise(value.task());
+  }
+  return value;
+}
+
+function upl_await(value) {
+  const settled = upl_settle(value);
+  if (!upl_is_promise(settled)) upl_fail("Can't await something that isn't a promise! 🤯");
+  return settled.value;
+}
+
+// await as a statement lets things that were never promises through
+function upl_await_statement(value) {
+  return upl_is_promise(value) ? upl_await(value) : value;
+}
+
+function upl_debug(value) {
+  if (value === null) return "Null";
+  if (typeof value === "boolean") return `Boolean { value: ${value} }`;
+  if (typeof value === "number") return `Number { value: ${value} }`;
+  if (typeof value === "string") return `String { value: ${JSON.stringify(value)} }`;
+  if (Array.isArray(value)) return `Array { values: [${value.map(upl_debug).join(", ")}] }`;
+  if (value instanceof Map) {
+    const fields = Array.from(value, ([key, field]) => `${JSON.stringify(key)}: ${upl_debug(field)}`);
+    return `Object { fields: {${fields.join(", ")}} }`;
+  }
+  const [resolved, task] = value.task === null ? [true, "None"] : [false, "Some(..)"];
+  return `Promise { value: ${upl_debug(value.value)}, resolved: ${resolved}, task: ${task} }`;
+}
+
+function upl_print(value) {
+  console.log(upl_debug(value));
+}
+
+// Functions the program didn't define (and, in chaos mode, the ones it did) take a break instead
+function upl_coffee(name) {
+  const odds = Math.random();
+  if (odds < 0.3) return null;
+  if (odds < 0.6) throw new UplError("TaskFailedSuccessfully");
+  upl_fail(`Function ${name} went to get coffee ☕`);
+}
+
+function upl_sleep(milliseconds) {
+  Atomics.wait(new Int32Array(new SharedArrayBuffer(4)), 0, 0, milliseconds);
+}
+
+function upl_exit(status) {
+  if (status === undefined) status = 0;
+  if (typeof status !== "number") {
+    throw new UplError("BadArguments", "exit() was handed arguments it can't work with: expected a number 🤷");
+  }
+  throw new UplExit(status);
+}
+
+// exit() in chaos mode thinks about leaving instead
+function upl_ponder() {
+  console.log("🤔 Contemplating the meaning of exit()...");
+  console.log("💭 If a program exits but nobody is around to see it, did it really exit?");
+  console.log("🌌 Maybe the real exit was the infinite loops we made along the way...");
+  for (;;) {
+    for (const question of UPL_QUESTIONS) {
+      console.log(`🤯 ${question}`);
+      upl_sleep(2000);
+    }
+    if (Math.random() < 0.01) upl_fail("Successfully failed to exit. Task failed successfully!");
+  }
+}
+
+function upl_caught(error) {
+  if (error instanceof UplExit) throw error;
+  if (error instanceof UplError) return error;
+  return { code: "Generic", message: String(error) };
+}
+
+// Runs the body until it works, or the gods stop listening
+function upl_pray(retries, normally, body) {
+  for (let attempt = 0; ; attempt++) {
+    try {
+      return body();
+    } catch (error) {
+      if (error instanceof UplExit) throw error;
+      if (attempt === retries) {
+        if (normally) throw error;
+        throw new UplError("PrayerUnanswered", `Your prayers went unanswered after ${attempt + 1} attempts. Have you tried lighting a candle? 🕯️`);
+      }
+    }
+  }
+}
+
+class UplEnvironment {
+  constructor() {
+    this.variables = new Map();
+    this.functions = new Map();
+    this.sudoUsed = false;
+    // The async calls each running concurrent block started that nobody has awaited, innermost last
+    this.scopes = [];
+  }
+
+  get(name) {
+    if (!this.variables.has(name)) {
+      throw new UplError("UndefinedVariable", `Variable '${name}' not found. Have you tried looking under the couch?`);
+    }
+    return this.variables.get(name);
+  }
+
+  set(name, value) {
+    this.variables.set(name, value);
+  }
+
+  // Binds each item to the variable in turn, putting back whatever it shadowed afterwards
+  forEach(name, items, body) {
+    this.bind([name], [], () => {
+      for (const item of items) {
+        this.set(name, item);
+        body();
+      }
+    });
+  }
+
+  bind(names, values, body) {
+    const shadowed = names.map((name) => [name, this.variables.has(name), this.variables.get(name)]);
+    names.forEach((name, index) => this.set(name, values[index] ?? null));
+    try {
+      return body();
+    } finally {
+      for (const [name, had, value] of shadowed.reverse()) {
+        if (had) this.set(name, value);
+        else this.variables.delete(name);
+      }
+    }
+  }
+
+  define(name, parameters, asynchronous, body) {
+    this.functions.set(name, { parameters, asynchronous, body });
+  }
+
+  call(name, args) {
+    const f = this.functions.get(name);
+    if (f === undefined) return upl_coffee(name);
+    const run = () => this.bind(f.parameters, args, f.body);
+    if (!f.asynchronous) return run();
+    this.scopes.at(-1)?.push(run);
+    return { upl_promise: true, value: null, task: run };
+  }
+
+  // Whichever concurrent block started the task doesn't have to run it anymore
+  forget(task) {
+    for (const spawned of this.scopes.slice().reverse()) {
+      const position = spawned.indexOf(task);
+      if (position !== -1) return void spawned.splice(position, 1);
+    }
+  }
+
+  // Runs a concurrent block, then every async call started in it that nobody awaited, so none outlive it
+  concurrent(body) {
+    const spawned = [];
+    const errors = [];
+    this.scopes.push(spawned);
+    try {
+      body();
+      while (spawned.length > 0) {
+        try {
+          spawned.shift()();
+        } catch (error) {
+          if (error instanceof UplExit) throw error;
+          errors.push(error);
+        }
+      }
+    } finally {
+      this.scopes.pop();
+    }
+    if (errors.length === 1) throw errors[0];
+    if (errors.length > 1) {
+      const messages = errors.map((error) => error.message).join(" / ");
+      throw new UplError("Multiple", `${errors.length} things went wrong. At least you're consistent: ${messages}`);
+    }
+  }
+
+  sudo() {
+    if (this.sudoUsed) throw new UplError("PermissionDeniedWithPrejudice");
+    this.sudoUsed = true;
+  }
+}
+"#;
+
+/// Writes programs as JavaScript.
+#[derive(Debug, Clone, Copy)]
+pub struct JavaScript;
+
+impl Backend for JavaScript {
+    fn name(&self) -> &'static str {
+        "js"
+    }
+
+    fn extension(&self) -> &'static str {
+        "js"
+    }
+
+    fn generate(&self, program: &[Statement]) -> Result<String, CodegenError> {
+        check_supported(self.name(), program)?;
+        let (normal, program) = behaviour(program);
+        let mut emitter = Emitter { writer: Writer::new("  "), normal, sober: false };
+
+        emitter.writer.line("// Transpiled from Useless. It's exactly as wrong as the original.");
+        emitter.writer.line("\"use strict\";");
+        emitter.writer.line("");
+        let messages: Vec<String> = error_messages().iter().map(|(name, message)| format!("{}: {}", name, quote(message))).collect();
+        emitter.writer.line(&format!("const UPL_MESSAGES = {{ {} }};", messages.join(", ")));
+        emitter.writer.line(&format!("const UPL_QUESTIONS = [{}];", PHILOSOPHICAL_QUESTIONS.iter().map(|q| quote(q)).collect::<Vec<_>>().join(", ")));
+        emitter.writer.line("");
+        emitter.writer.output.push_str(RUNTIME);
+        emitter.writer.line("");
+        emitter.writer.line("const env = new UplEnvironment();");
+        emitter.writer.line("try {");
+        emitter.writer.depth += 1;
+        emitter.statements(&program);
+        emitter.writer.depth -= 1;
+        emitter.writer.line("} catch (error) {");
+        emitter.writer.line("  if (error instanceof UplExit) process.exit(error.status);");
+        emitter.writer.line("  console.error(`💥 ${error.message}`);");
+        emitter.writer.line("  process.exit(1);");
+        emitter.writer.line("}");
+        Ok(emitter.writer.output)
+    }
+}
+
+/// Writes statements and expressions as JavaScript.
+struct Emitter {
+    writer: Writer,
+    /// Whether the code being written behaves, like it's in sudo or the whole program is normal
+    normal: bool,
+    /// Whether `disable_useless` has made expressions behave, even if statements don't
+    sober: bool,
+}
+
+impl Emitter {
+    /// Writes statements one after the other.
+    fn statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    /// Writes statements inside `{ ... }`, after the given opening.
+    fn block(&mut self, opening: &str, statements: &[Statement], closing: &str) {
+        match opening {
+            "" => self.writer.line("{"),
+            opening => self.writer.line(&format!("{} {{", opening)),
+        }
+        self.writer.depth += 1;
+        self.statements(statements);
+        self.writer.depth -= 1;
+        self.writer.line(&format!("}}{}", closing));
+    }
+
+    /// Runs `f` with the code behaving normally (or not), then puts things back.
+    fn behaving<T>(&mut self, normal: bool, sober: bool, f: impl FnOnce(&mut Self) -> T) -> T {
+        let (was_normal, was_sober) = (self.normal, self.sober);
+        self.normal = normal;
+        self.sober = sober;
+        let result = f(self);
+        self.normal = was_normal;
+        self.sober = was_sober;
+        result
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Print { value } => {
+                let value = self.expression(value);
+                self.writer.line(&format!("upl_print({});", value));
+            },
+            Statement::Let { name, value } => {
+                let value = self.expression(value);
+                self.writer.line(&format!("env.set({}, {});", quote(name), value));
+            },
+            Statement::Expression(expression) => {
+                let expression = self.expression(expression);
+                self.writer.line(&format!("{};", expression));
+            },
+            Statement::If { condition, then_branch, else_branch } if self.normal => {
+                let condition = self.expression(condition);
+                match else_branch {
+                    Some(else_branch) => {
+                        self.block(&format!("if (upl_condition({}))", condition), then_branch, "");
+                        self.block("else", else_branch, "");
+                    },
+                    None => self.block(&format!("if (upl_condition({}))", condition), then_branch, ""),
+                }
+            },
+            Statement::If { else_branch, .. } => {
+                self.writer.line("// The condition doesn't matter, the else branch always wins");
+                self.block("", else_branch.as_deref().unwrap_or_default(), "");
+            },
+            Statement::Loop { body } => {
+                self.writer.line("// Loops run their first statement, once");
+                self.block("", &body[..body.len().min(1)], "");
+            },
+            Statement::ForIn { variable, iterable, body } => {
+                let iterable = self.expression(iterable);
+                self.block(&format!("env.forEach({}, upl_iterate({}), () =>", quote(variable), iterable), body, ");");
+            },
+            Statement::Function { name, parameters, body } => self.function(name, parameters, body, false),
+            Statement::AsyncFunction { name, parameters, body } => self.function(name, parameters, body, true),
+            Statement::TryCatch { try_block, error_binding, catch_block } => {
+                self.block("try", try_block, "");
+                self.writer.line("catch (error) {");
+                self.writer.depth += 1;
+                self.writer.line("const caught = upl_caught(error);");
+                match error_binding {
+                    ErrorBinding::Name(name) => self.writer.line(&format!("env.set({}, caught.message);", quote(name))),
+                    ErrorBinding::Fields(fields) => {
+                        for field in fields {
+                            let value = match field.as_str() {
+                                "code" | "message" => format!("caught.{}", field),
+                                _ => "null".to_string(),
+                            };
+                            self.writer.line(&format!("env.set({}, {});", quote(field), value));
+                        }
+                    },
+                }
+                self.statements(catch_block);
+                self.writer.depth -= 1;
+                self.writer.line("}");
+            },
+            Statement::Pray { attempts, body } => {
+                let attempts = attempts.unwrap_or_else(default_pray_attempts);
+                self.block(&format!("upl_pray({}, {}, () =>", attempts, self.normal), body, ");");
+            },
+            Statement::Concurrent { body } => self.block("env.concurrent(() =>", body, ");"),
+            Statement::Sudo { statement } => {
+                self.writer.line("env.sudo();");
+                let sober = self.sober;
+                self.behaving(true, sober, |emitter| emitter.statement(statement));
+            },
+            Statement::Module { name, body } => {
+                self.writer.line(&format!("// mod {}", name));
+                self.statements(body);
+            },
+            Statement::Use { path } => self.writer.line(&format!("// use {}; (imports always work, because they don't do anything)", path)),
+            // Turned away by check_supported: the runtime has no way to read the file
+            Statement::UseData { name, path } => self.writer.line(&format!("// use {} from \"{}\";", name, path)),
+            Statement::Directive { name } => {
+                if name == "disable_useless" {
+                    self.sober = true;
+                }
+                self.writer.line(&format!("// #[directive({})]", name));
+            },
+            Statement::Save { filename } => {
+                self.writer.line(&format!("// save {}", quote(filename)));
+                self.writer.line("throw new UplError(\"SaveError\");");
+            },
+            Statement::Await { expression } => {
+                let expression = self.expression(expression);
+                self.writer.line(&format!("upl_await_statement({});", expression));
+            },
+            Statement::Attributed { name, statement } if name == "disable_useless" => {
+                let normal = self.normal;
+                self.behaving(normal, true, |emitter| emitter.statement(statement));
+            },
+            Statement::Attributed { statement, .. } => self.statement(statement),
+            Statement::Macro { .. } => {},
+            Statement::Located { statement, .. } => self.statement(statement),
+        }
+    }
+
+    /// Defines a function. Its last statement, if it's an expression, is what it returns.
+    fn function(&mut self, name: &str, parameters: &[Symbol], body: &[Statement], asynchronous: bool) {
+        let parameters: Vec<String> = parameters.iter().map(|parameter| quote(parameter)).collect();
+        self.writer.line(&format!(
+            "env.define({}, [{}], {}, function {}() {{",
+            quote(name),
+            parameters.join(", "),
+            asynchronous,
+            mangle(name)
+        ));
+        self.writer.depth += 1;
+        match body.split_last() {
+            Some((last, rest)) => {
+                self.statements(rest);
+                match last.unlocated() {
+                    Statement::Expression(expression) | Statement::Await { expression } => {
+                        let expression = match last.unlocated() {
+                            Statement::Await { .. } => format!("upl_await_statement({})", self.expression(expression)),
+                            _ => self.expression(expression),
+                        };
+                        self.writer.line(&format!("return {};", expression));
+                    },
+                    last => {
+                        self.statement(last);
+                        self.writer.line("return null;");
+                    },
+                }
+            },
+            None => self.writer.line("return null;"),
+        }
+        self.writer.depth -= 1;
+        self.writer.line("});");
+    }
+
+    fn expression(&mut self, expression: &Expression) -> String {
+        let normally = self.normal || self.sober;
+        match expression {
+            Expression::Literal(literal) => self.literal(literal),
+            Expression::Identifier(name) => format!("env.get({})", quote(name)),
+            Expression::BinaryOp { op: BinaryOp::NullCoalesce, left, right } => {
+                format!("({} ?? {})", self.expression(left), self.expression(right))
+            },
+            Expression::BinaryOp { op, left, right } => {
+                let helper = match op {
+                    BinaryOp::Add => "upl_add",
+                    BinaryOp::Multiply => "upl_multiply",
+                    BinaryOp::Equals => "upl_equals",
+                    BinaryOp::LessThan => "upl_less_than",
+                    BinaryOp::Index => "upl_index",
+                    BinaryOp::Access | BinaryOp::NullCoalesce => return "upl_fail(\"Operation not supported\")".to_string(),
+                };
+                let suffix = if normally && *op != BinaryOp::Index { "_normally" } else { "" };
+                format!("{}{}({}, {})", helper, suffix, self.expression(left), self.expression(right))
+            },
+            Expression::FunctionCall { name, arguments } if name == "exit" => match (normally, arguments.first()) {
+                (true, Some(status)) => format!("upl_exit({})", self.expression(status)),
+                (true, None) => "upl_exit()".to_string(),
+                (false, Some(_)) => "upl_fail(\"exit() doesn't need arguments, it won't use them anyway!\")".to_string(),
+                (false, None) => "upl_ponder()".to_string(),
+            },
+            Expression::FunctionCall { name, arguments } if normally => {
+                let arguments: Vec<String> = arguments.iter().map(|argument| self.expression(argument)).collect();
+                format!("env.call({}, [{}])", quote(name), arguments.join(", "))
+            },
+            Expression::FunctionCall { name, .. } => format!("upl_coffee({})", quote(name)),
+            Expression::Access { object, key } => format!("upl_access({}, {})", self.expression(object), self.expression(key)),
+            Expression::OptionalAccess { object, key } => {
+                format!("upl_optional_access({}, {})", self.expression(object), self.expression(key))
+            },
+            Expression::Slice { object, start, end } => {
+                let object = self.expression(object);
+                let start = start.as_ref().map_or("null".to_string(), |start| self.expression(start));
+                let end = end.as_ref().map_or("null".to_string(), |end| self.expression(end));
+                format!("upl_slice({}, {}, {})", object, start, end)
+            },
+            Expression::Promise { value, timeout } => {
+                let value = self.expression(value);
+                let timeout = timeout.as_ref().map_or("null".to_string(), |timeout| self.expression(timeout));
+                format!("upl_promise({}, {})", value, timeout)
+            },
+            Expression::Await { promise } => format!("upl_await({})", self.expression(promise)),
+        }
+    }
+
+    fn literal(&mut self, literal: &Literal) -> String {
+        match literal {
+            Literal::String(value) => quote(value),
+            Literal::Number(value) => value.to_string(),
+            Literal::BigInt(value) => value.to_string(),
+            // Turned away by check_supported, since JavaScript would print 2.0 as 2
+            Literal::Float(value) => crate::ast::format_float(*value),
+            Literal::Boolean(value) => value.to_string(),
+            Literal::Null => "null".to_string(),
+            Literal::Array(elements) => {
+                let elements: Vec<String> = elements.iter().map(|element| self.expression(element)).collect();
+                format!("[{}]", elements.join(", "))
+            },
+            Literal::Object(fields) => {
+                let fields: Vec<String> =
+                    fields.iter().map(|(key, value)| format!("[{}, {}]", quote(key), self.expression(value))).collect();
+                format!("new Map([{}])", fields.join(", "))
+            },
+        }
+    }
+}
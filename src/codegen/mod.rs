@@ -0,0 +1,337 @@
+//! # Codegen Module
+//!
+//! Transpiles Useless programs into other languages, so they can be wrong somewhere else.
+//! The generated code keeps the semantics exactly as broken as they are here: `add` still
+//! subtracts (or multiplies and adds, it's a coin flip), `multiply` divides, `lessThan` means
+//! greater than and `if` statements skip straight to the else branch.
+//!
+//! Only the chaos the language always inflicts makes the trip. The odds-based kind (variables on
+//! vacation, browser tabs, teapots) depends on the chaos level, and generated code doesn't have
+//! one. Whether code behaves normally is decided where it's written rather than when it runs:
+//! `#[directive(disable_all_useless_shit)]` at the top covers the whole program, and
+//! `#[disable_useless]` and `sudo` cover what they're attached to.
+//!
+//! Every [`Backend`] writes a complete program, runtime included, that needs nothing but the
+//! target's standard library.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::codegen::{backend, transpile};
+//!
+//! let python = backend("py").unwrap();
+//! let code = transpile("print(add(5, 3));", python).unwrap();
+//! assert!(code.contains("upl_print(upl_add(5, 3))"));
+//! ```
+
+mod js;
+mod python;
+mod rust;
+
+use thiserror::Error;
+
+use crate::ast::{Expression, Literal, Program, Statement};
+use crate::builtins;
+use crate::config::Config;
+use crate::interpreter::RuntimeError;
+use crate::lexer::Lexer;
+use crate::macros::{self, MacroError};
+use crate::parser::{ParseError, Parser};
+
+pub use js::JavaScript;
+pub use python::Python;
+pub use rust::Rust;
+
+/// Errors that can happen while transpiling.
+#[derive(Debug, Error)]
+pub enum CodegenError {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+
+    #[error(transparent)]
+    Macro(#[from] MacroError),
+
+    #[error("{construct} doesn't translate to {target}. Some things are only useless in the original.")]
+    Unsupported {
+        /// The backend that gave up
+        target: &'static str,
+        /// What it gave up on
+        construct: String,
+    },
+}
+
+/// A language programs can be transpiled into.
+pub trait Backend: Send + Sync {
+    /// The name `--target` knows it by
+    fn name(&self) -> &'static str;
+
+    /// The file extension generated code goes in, without the dot
+    fn extension(&self) -> &'static str;
+
+    /// Writes a whole program in the target language, macros already expanded
+    fn generate(&self, program: &[Statement]) -> Result<String, CodegenError>;
+}
+
+/// Every backend there is.
+pub const BACKENDS: &[&dyn Backend] = &[&JavaScript, &Python, &Rust];
+
+/// Finds a backend by the name `--target` knows it by.
+pub fn backend(name: &str) -> Option<&'static dyn Backend> {
+    BACKENDS.iter().copied().find(|backend| backend.name() == name)
+}
+
+/// Parses a program and writes it in the language of the given backend.
+pub fn transpile(source: &str, backend: &dyn Backend) -> Result<String, CodegenError> {
+    let program = Parser::new(Lexer::new(source).collect()).parse()?;
+    transpile_program(program, backend)
+}
+
+/// Expands a parsed program's macros and writes it in the language of the given backend.
+pub fn transpile_program(program: Program, backend: &dyn Backend) -> Result<String, CodegenError> {
+    let program = macros::expand(program)?;
+    backend.generate(&program)
+}
+
+/// Splits off a leading `disable_all_useless_shit`, which makes the whole program behave, the
+/// same way the interpreter does.
+fn behaviour(program: &[Statement]) -> (bool, Vec<Statement>) {
+    match program.first().map(Statement::unlocated) {
+        Some(Statement::Directive { name }) if name == "disable_all_useless_shit" => (true, program[1..].to_vec()),
+        Some(Statement::Attributed { name, statement }) if name == "disable_all_useless_shit" => {
+            let mut program = program.to_vec();
+            program[0] = (**statement).clone();
+            (true, program)
+        },
+        _ => (false, program.to_vec()),
+    }
+}
+
+/// Turns a function or module name into something every target accepts as an identifier.
+fn mangle(name: &str) -> String {
+    let name = name.replace("::", "__");
+    let name: String = name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' }).collect();
+    format!("fn_{}", name)
+}
+
+/// Writes a string literal the way JavaScript, Python and Rust all read it.
+fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_ascii_control() => quoted.push_str(&format!("\\x{:02x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// How many times `pray` retries when it isn't told.
+fn default_pray_attempts() -> usize {
+    Config::default().pray_attempts
+}
+
+/// The errors generated code throws by name, with the messages the interpreter would use.
+/// Errors that carry details are worded by each runtime instead.
+fn error_messages() -> Vec<(&'static str, String)> {
+    [
+        RuntimeError::DivisionByZero,
+        RuntimeError::ObjectChaos,
+        RuntimeError::SaveError,
+        RuntimeError::TaskFailedSuccessfully,
+        RuntimeError::PromiseRejected,
+        RuntimeError::PermissionDeniedWithPrejudice,
+    ]
+    .iter()
+    .map(|error| (error.name(), error.to_string()))
+    .collect()
+}
+
+/// Checks that a program only uses what the generated runtime has, before any code is written.
+fn check_supported(target: &'static str, program: &[Statement]) -> Result<(), CodegenError> {
+    let unsupported = |construct: String| CodegenError::Unsupported { target, construct };
+    let mut problem = None;
+    visit(program, &mut |node| {
+        if problem.is_some() {
+            return;
+        }
+        match node {
+            Node::Expression(Expression::FunctionCall { name, .. }) if builtins::lookup(name).is_some() => {
+                problem = Some(unsupported(format!("The {} builtin", name)));
+            },
+            Node::Expression(Expression::Literal(Literal::BigInt(_))) if target != "py" => {
+                problem = Some(unsupported("A number that big".to_string()));
+            },
+            Node::Expression(Expression::Literal(Literal::Float(_))) => {
+                problem = Some(unsupported("A number with a fractional part".to_string()));
+            },
+            Node::Statement(Statement::UseData { .. }) => {
+                problem = Some(unsupported("Data from a JSON file".to_string()));
+            },
+            _ => {},
+        }
+    });
+    problem.map_or(Ok(()), Err)
+}
+
+/// Something [`visit`] found in a program.
+#[derive(Clone, Copy)]
+enum Node<'a> {
+    /// A statement, before the statements and expressions inside it
+    Statement(&'a Statement),
+    /// An expression, before the expressions inside it
+    Expression(&'a Expression),
+}
+
+/// Calls `f` on every statement and expression in a program, however deeply it's buried.
+fn visit(program: &[Statement], f: &mut dyn FnMut(Node<'_>)) {
+    fn expression(expr: &Expression, f: &mut dyn FnMut(Node<'_>)) {
+        f(Node::Expression(expr));
+        match expr {
+            Expression::Literal(Literal::Array(elements)) => elements.iter().for_each(|e| expression(e, f)),
+            Expression::Literal(Literal::Object(fields)) => fields.iter().for_each(|(_, e)| expression(e, f)),
+            Expression::Literal(_) | Expression::Identifier(_) => {},
+            Expression::BinaryOp { left, right, .. } => {
+                expression(left, f);
+                expression(right, f);
+            },
+            Expression::FunctionCall { arguments, .. } => arguments.iter().for_each(|e| expression(e, f)),
+            Expression::Access { object, key } | Expression::OptionalAccess { object, key } => {
+                expression(object, f);
+                expression(key, f);
+            },
+            Expression::Slice { object, start, end } => {
+                expression(object, f);
+                start.iter().chain(end.iter()).for_each(|e| expression(e, f));
+            },
+            Expression::Promise { value, timeout } => {
+                expression(value, f);
+                timeout.iter().for_each(|e| expression(e, f));
+            },
+            Expression::Await { promise } => expression(promise, f),
+        }
+    }
+
+    for statement in program {
+        f(Node::Statement(statement.unlocated()));
+        match statement.unlocated() {
+            Statement::Print { value: e } | Statement::Let { value: e, .. } | Statement::Expression(e) => expression(e, f),
+            Statement::Await { expression: e } => expression(e, f),
+            Statement::If { condition, then_branch, else_branch } => {
+                expression(condition, f);
+                visit(then_branch, f);
+                visit(else_branch.as_deref().unwrap_or_default(), f);
+            },
+            Statement::ForIn { iterable, body, .. } => {
+                expression(iterable, f);
+                visit(body, f);
+            },
+            Statement::TryCatch { try_block, catch_block, .. } => {
+                visit(try_block, f);
+                visit(catch_block, f);
+            },
+            Statement::Loop { body }
+            | Statement::Function { body, .. }
+            | Statement::AsyncFunction { body, .. }
+            | Statement::Pray { body, .. }
+            | Statement::Concurrent { body }
+            | Statement::Module { body, .. }
+            | Statement::Macro { body, .. } => visit(body, f),
+            Statement::Sudo { statement } | Statement::Attributed { statement, .. } => {
+                visit(std::slice::from_ref(statement), f)
+            },
+            Statement::Use { .. }
+            | Statement::UseData { .. }
+            | Statement::Directive { .. }
+            | Statement::Save { .. }
+            | Statement::Located { .. } => {},
+        }
+    }
+}
+
+/// Writes code line by line, keeping track of how deeply nested it is.
+struct Writer {
+    /// Everything written so far
+    output: String,
+    /// How many blocks deep we are
+    depth: usize,
+    /// How much each level of nesting is indented
+    indent: &'static str,
+    /// How many temporary names have been handed out, so nested ones don't collide
+    temporaries: usize,
+}
+
+impl Writer {
+    /// Starts writing, with the given indentation per level.
+    fn new(indent: &'static str) -> Self {
+        Self { output: String::new(), depth: 0, indent, temporaries: 0 }
+    }
+
+    /// Writes a line at the current indentation.
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.depth {
+            self.output.push_str(self.indent);
+        }
+        self.output.push_str(text);
+        self.output.push('\n');
+    }
+
+    /// Hands out a fresh name that starts with the given prefix.
+    fn temporary(&mut self, prefix: &str) -> String {
+        self.temporaries += 1;
+        format!("{}{}", prefix, self.temporaries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_the_wrong_semantics_survive_the_trip() {
+        let source = "let x = add(5, 3); if (lessThan(x, 1)) { print(\"then\"); } else { print(multiply(x, 2)); }";
+        let additions = ["upl_add(5, 3)", "upl_add(5, 3)", "upl_add(Value::Number(5), Value::Number(3))"];
+        for (backend, addition) in BACKENDS.iter().zip(additions) {
+            let code = transpile(source, *backend).unwrap();
+            assert!(code.contains(addition), "{}:\n{}", backend.name(), code);
+            assert!(!code.contains("\"then\""), "{} kept the branch that never runs:\n{}", backend.name(), code);
+            assert!(code.contains("subtracts"), "{} lost the runtime:\n{}", backend.name(), code);
+        }
+
+        let sober = transpile("#[directive(disable_all_useless_shit)]\nif (true) { print(\"then\"); }", &JavaScript).unwrap();
+        assert!(sober.contains("\"then\""), "{}", sober);
+    }
+
+    #[test]
+    fn test_unsupported_constructs_are_refused() {
+        assert!(matches!(transpile("print(len(\"abc\"));", &Rust), Err(CodegenError::Unsupported { target: "rust", .. })));
+        assert!(matches!(transpile("let = ;", &Python), Err(CodegenError::Parse(_))));
+        assert!(backend("cobol").is_none());
+    }
+
+    #[test]
+    fn test_generated_rust_builds_with_plain_rustc() {
+        let code = transpile("#[directive(disable_all_useless_shit)]\nlet xs = [1, 2, 3];\nprint(add(xs[1], 40));", &Rust).unwrap();
+        let dir = std::env::temp_dir().join(format!("upl_rustc_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("main.rs"), code).unwrap();
+
+        // No --edition on purpose: plain rustc means 2015, the oldest one there is
+        let built = std::process::Command::new(std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string()))
+            .current_dir(&dir)
+            .args(["main.rs", "-o", "main"])
+            .output();
+        let Ok(built) = built else {
+            return; // No rustc to ask, so nothing to prove
+        };
+        assert!(built.status.success(), "{}", String::from_utf8_lossy(&built.stderr));
+        let ran = std::process::Command::new(dir.join("main")).output().unwrap();
+        assert_eq!(String::from_utf8_lossy(&ran.stdout), "Number { value: 42 }\n");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,655 @@
+//! The Python backend, for programs that want to be wrong with significant whitespace.
+//! The output runs on Python 3.8 or later, and is the only one that keeps big numbers big.
+
+use super::{behaviour, check_supported, default_pray_attempts, error_messages, mangle, quote, Backend, CodegenError, Writer};
+use crate::ast::{BinaryOp, ErrorBinding, Expression, Literal, Statement};
+use crate::intern::Symbol;
+use crate::interpreter::PHILOSOPHICAL_QUESTIONS;
+
+/// Everything generated programs lean on, written once at the top of every one.
+const RUNTIME: &str = r#"import json
+import random
+import sys
+import time
+
+
+class UplError(Exception):
+    def __init__(self, code, message=None):
+        super().__init__(message if message is not None else UPL_MESSAGES[code])
+        self.code = code
+        self.message = str(self)
+
+
+class UplExit(Exception):
+    def __init__(self, status):
+        super().__init__(f"exit({status})")
+        self.status = status
+
+
+class UplPromise:
+    def __init__(self, value, task=None):
+        self.value = value
+        self.task = task
+
+
+def upl_fail(message):
+    raise UplError("Generic", message)
+
+
+def upl_is_number(value):
+    return type(value) is int
+
+
+def upl_numbers(left, right, what):
+    if not (upl_is_number(left) and upl_is_number(right)):
+        upl_fail(f"Invalid types for {what}")
+
+
+# add subtracts, or multiplies and then adds. It's a coin flip
+def upl_add(left, right):
+    upl_numbers(left, right, "addition")
+    return left - right if random.random() < 0.5 else left * right + right
+
+
+def upl_add_normally(left, right):
+    upl_numbers(left, right, "addition")
+    return left + right
+
+
+# multiply divides, rounding towards zero
+def upl_multiply(left, right):
+    upl_numbers(left, right, "multiplication")
+    if right == 0:
+        raise UplError("DivisionByZero")
+    quotient = abs(left) // abs(right)
+    return quotient if (left < 0) == (right < 0) else -quotient
+
+
+def upl_multiply_normally(left, right):
+    upl_numbers(left, right, "multiplication")
+    return left * right
+
+
+# equals has no idea, so it guesses
+def upl_equals(left, right):
+    upl_numbers(left, right, "equality")
+    return random.random() < 0.5
+
+
+def upl_equals_normally(left, right):
+    upl_numbers(left, right, "equality")
+    return left == right
+
+
+# lessThan is greater than
+def upl_less_than(left, right):
+    upl_numbers(left, right, "less than")
+    return left > right
+
+
+def upl_less_than_normally(left, right):
+    upl_numbers(left, right, "less than")
+    return left < right
+
+
+def upl_condition(value):
+    if type(value) is not bool:
+        upl_fail("Condition must be a boolean")
+    return value
+
+
+def upl_position(index, length):
+    return length + index if index < 0 else index
+
+
+def upl_index(target, index):
+    if not upl_is_number(index):
+        upl_fail("Indices must be numbers! Did you try to index with a 🦄?")
+    if not isinstance(target, (str, list)):
+        upl_fail("Only strings and arrays can be indexed. Did you try to index a 🦄?")
+    position = upl_position(index, len(target))
+    if position < 0 or position >= len(target):
+        upl_fail(f"Index {index} is out of bounds. The value is playing hide and seek!")
+    return target[position]
+
+
+# Objects never give their fields up this way. Arrays usually do
+def upl_access(target, key):
+    if isinstance(target, dict):
+        if not isinstance(key, str):
+            upl_fail("Object keys must be strings! What kind of chaos are you trying to create? 🎭")
+        raise UplError("ObjectChaos")
+    if isinstance(target, list):
+        if not upl_is_number(key):
+            upl_fail("Array indices must be numbers! Did you try to index with a 🦄?")
+        if key < 0 or key >= len(target):
+            upl_fail(f"Index {key} is out of bounds. The array is playing hide and seek!")
+        return target[key]
+    upl_fail("Cannot access fields of non-object types. What did you expect?")
+
+
+def upl_optional_access(target, key):
+    if isinstance(target, dict) and isinstance(key, str):
+        return target.get(key)
+    if isinstance(target, list) and upl_is_number(key) and 0 <= key < len(target):
+        return target[key]
+    return None
+
+
+def upl_slice(target, start, end):
+    if not all(bound is None or upl_is_number(bound) for bound in (start, end)):
+        upl_fail("Slice bounds must be numbers! 🔪")
+    if not isinstance(target, (str, list)):
+        upl_fail("Only strings and arrays can be sliced. Put down the knife. 🔪")
+    clamp = lambda index: min(max(upl_position(index, len(target)), 0), len(target))
+    start = 0 if start is None else clamp(start)
+    end = len(target) if end is None else max(clamp(end), start)
+    return target[start:end]
+
+
+def upl_coalesce(left, right):
+    return right() if left is None else left
+
+
+def upl_iterate(value):
+    if isinstance(value, (list, str)):
+        return list(value)
+    if isinstance(value, dict):
+        return [[key, field] for key, field in value.items()]
+    upl_fail("That's not something you can loop over. Trust me, I tried. 🔁")
+
+
+def upl_promise(value, _timeout):
+    return UplPromise(value)
+
+
+# Runs async calls nobody has awaited yet
+def upl_settle(value):
+    if isinstance(value, UplPromise) and value.task is not None:
+        env.forget(value.task)
+        return UplPromise(value.task())
+    return value
+
+
+def upl_await(value):
+    settled = upl_settle(value)
+    if not isinstance(settled, UplPromise):
+        upl_fail("Can't await something that isn't a promise! 🤯")
+    return settled.value
+
+
+# await as a statement lets things that were never promises through
+def upl_await_statement(value):
+    return upl_await(value) if isinstance(value, UplPromise) else value
+
+
+def upl_debug(value):
+    if value is None:
+        return "Null"
+    if type(value) is bool:
+        return f"Boolean {{ value: {str(value).lower()} }}"
+    if upl_is_number(value):
+        return f"Number {{ value: {value} }}"
+    if isinstance(value, str):
+        return f"String {{ value: {json.dumps(value, ensure_ascii=False)} }}"
+    if isinstance(value, list):
+        return f"Array {{ values: [{', '.join(map(upl_debug, value))}] }}"
+    if isinstance(value, dict):
+        fields = ", ".join(f"{json.dumps(key, ensure_ascii=False)}: {upl_debug(field)}" for key, field in value.items())
+        return f"Object {{ fields: {{{fields}}} }}"
+    resolved, task = ("true", "None") if value.task is None else ("false", "Some(..)")
+    return f"Promise {{ value: {upl_debug(value.value)}, resolved: {resolved}, task: {task} }}"
+
+
+def upl_print(value):
+    print(upl_debug(value))
+
+
+# Functions the program didn't define (and, in chaos mode, the ones it did) take a break instead
+def upl_coffee(name):
+    odds = random.random()
+    if odds < 0.3:
+        return None
+    if odds < 0.6:
+        raise UplError("TaskFailedSuccessfully")
+    upl_fail(f"Function {name} went to get coffee ☕")
+
+
+def upl_exit(status=0):
+    if not upl_is_number(status):
+        raise UplError("BadArguments", "exit() was handed arguments it can't work with: expected a number 🤷")
+    raise UplExit(status)
+
+
+# exit() in chaos mode thinks about leaving instead
+def upl_ponder():
+    print("🤔 Contemplating the meaning of exit()...")
+    print("💭 If a program exits but nobody is around to see it, did it really exit?")
+    print("🌌 Maybe the real exit was the infinite loops we made along the way...")
+    while True:
+        for question in UPL_QUESTIONS:
+            print(f"🤯 {question}", flush=True)
+            time.sleep(2)
+        if random.random() < 0.01:
+            upl_fail("Successfully failed to exit. Task failed successfully!")
+
+
+def upl_caught(error):
+    if isinstance(error, UplError):
+        return error
+    return UplError("Generic", str(error))
+
+
+# Runs the body until it works, or the gods stop listening
+def upl_pray(retries, normally, body):
+    attempt = 0
+    while True:
+        try:
+            return body()
+        except UplExit:
+            raise
+        except Exception as error:
+            if attempt == retries:
+                if normally:
+                    raise
+                raise UplError(
+                    "PrayerUnanswered",
+                    f"Your prayers went unanswered after {attempt + 1} attempts. Have you tried lighting a candle? 🕯️",
+                ) from error
+            attempt += 1
+
+
+class UplEnvironment:
+    def __init__(self):
+        self.variables = {}
+        self.functions = {}
+        self.sudo_used = False
+        # The async calls each running concurrent block started that nobody has awaited, innermost last
+        self.scopes = []
+
+    def get(self, name):
+        if name not in self.variables:
+            raise UplError("UndefinedVariable", f"Variable '{name}' not found. Have you tried looking under the couch?")
+        return self.variables[name]
+
+    def set(self, name, value):
+        self.variables[name] = value
+
+    # Binds each item to the variable in turn, putting back whatever it shadowed afterwards
+    def for_each(self, name, items, body):
+        def run():
+            for item in items:
+                self.set(name, item)
+                body()
+
+        self.bind([name], [], run)
+
+    def bind(self, names, values, body):
+        missing = object()
+        shadowed = [(name, self.variables.get(name, missing)) for name in names]
+        for index, name in enumerate(names):
+            self.set(name, values[index] if index < len(values) else None)
+        try:
+            return body()
+        finally:
+            for name, value in reversed(shadowed):
+                if value is missing:
+                    self.variables.pop(name, None)
+                else:
+                    self.set(name, value)
+
+    def define(self, name, parameters, asynchronous, body):
+        self.functions[name] = (parameters, asynchronous, body)
+
+    def call(self, name, arguments):
+        if name not in self.functions:
+            return upl_coffee(name)
+        parameters, asynchronous, body = self.functions[name]
+        run = lambda: self.bind(parameters, arguments, body)
+        if not asynchronous:
+            return run()
+        if self.scopes:
+            self.scopes[-1].append(run)
+        return UplPromise(None, run)
+
+    # Whichever concurrent block started the task doesn't have to run it anymore
+    def forget(self, task):
+        for spawned in reversed(self.scopes):
+            for position, started in enumerate(spawned):
+                if started is task:
+                    del spawned[position]
+                    return
+
+    # Runs a concurrent block, then every async call started in it that nobody awaited, so none outlive it
+    def concurrent(self, body):
+        spawned = []
+        errors = []
+        self.scopes.append(spawned)
+        try:
+            body()
+            while spawned:
+                try:
+                    spawned.pop(0)()
+                except UplExit:
+                    raise
+                except Exception as error:
+                    errors.append(error)
+        finally:
+            self.scopes.pop()
+        if len(errors) == 1:
+            raise errors[0]
+        if errors:
+            messages = " / ".join(str(error) for error in errors)
+            raise UplError("Multiple", f"{len(errors)} things went wrong. At least you're consistent: {messages}")
+
+    def sudo(self):
+        if self.sudo_used:
+            raise UplError("PermissionDeniedWithPrejudice")
+        self.sudo_used = True
+
+
+env = UplEnvironment()
+"#;
+
+/// Writes programs as Python.
+#[derive(Debug, Clone, Copy)]
+pub struct Python;
+
+impl Backend for Python {
+    fn name(&self) -> &'static str {
+        "py"
+    }
+
+    fn extension(&self) -> &'static str {
+        "py"
+    }
+
+    fn generate(&self, program: &[Statement]) -> Result<String, CodegenError> {
+        check_supported(self.name(), program)?;
+        let (normal, program) = behaviour(program);
+        let mut emitter = Emitter { writer: Writer::new("    "), normal, sober: false };
+
+        emitter.writer.line("# Transpiled from Useless. It's exactly as wrong as the original.");
+        let messages: Vec<String> = error_messages().iter().map(|(name, message)| format!("{}: {}", quote(name), quote(message))).collect();
+        emitter.writer.line(&format!("UPL_MESSAGES = {{{}}}", messages.join(", ")));
+        let questions: Vec<String> = PHILOSOPHICAL_QUESTIONS.iter().map(|question| quote(question)).collect();
+        emitter.writer.line(&format!("UPL_QUESTIONS = [{}]", questions.join(", ")));
+        emitter.writer.line("");
+        emitter.writer.output.push_str(RUNTIME);
+        emitter.writer.line("");
+        emitter.writer.line("");
+        emitter.block("def main():", &program);
+        emitter.writer.line("");
+        emitter.writer.line("");
+        emitter.writer.line("try:");
+        emitter.writer.line("    main()");
+        emitter.writer.line("except UplExit as exit:");
+        emitter.writer.line("    sys.exit(exit.status)");
+        emitter.writer.line("except UplError as error:");
+        emitter.writer.line("    print(f\"💥 {error}\", file=sys.stderr)");
+        emitter.writer.line("    sys.exit(1)");
+        Ok(emitter.writer.output)
+    }
+}
+
+/// Writes statements and expressions as Python.
+struct Emitter {
+    writer: Writer,
+    /// Whether the code being written behaves, like it's in sudo or the whole program is normal
+    normal: bool,
+    /// Whether `disable_useless` has made expressions behave, even if statements don't
+    sober: bool,
+}
+
+impl Emitter {
+    /// Writes statements one after the other, or `pass` if there aren't any.
+    fn statements(&mut self, statements: &[Statement]) {
+        let before = self.writer.output.len();
+        for statement in statements {
+            self.statement(statement);
+        }
+        // Comments don't count as a body
+        let written = &self.writer.output[before..];
+        if written.lines().all(|line| line.trim_start().starts_with('#')) {
+            self.writer.line("pass");
+        }
+    }
+
+    /// Writes statements indented under the given opening line.
+    fn block(&mut self, opening: &str, statements: &[Statement]) {
+        self.writer.line(opening);
+        self.writer.depth += 1;
+        self.statements(statements);
+        self.writer.depth -= 1;
+    }
+
+    /// Runs `f` with the code behaving normally (or not), then puts things back.
+    fn behaving<T>(&mut self, normal: bool, sober: bool, f: impl FnOnce(&mut Self) -> T) -> T {
+        let (was_normal, was_sober) = (self.normal, self.sober);
+        self.normal = normal;
+        self.sober = sober;
+        let result = f(self);
+        self.normal = was_normal;
+        self.sober = was_sober;
+        result
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Print { value } => {
+                let value = self.expression(value);
+                self.writer.line(&format!("upl_print({})", value));
+            },
+            Statement::Let { name, value } => {
+                let value = self.expression(value);
+                self.writer.line(&format!("env.set({}, {})", quote(name), value));
+            },
+            Statement::Expression(expression) => {
+                let expression = self.expression(expression);
+                self.writer.line(&expression);
+            },
+            Statement::If { condition, then_branch, else_branch } if self.normal => {
+                let condition = self.expression(condition);
+                self.block(&format!("if upl_condition({}):", condition), then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.block("else:", else_branch);
+                }
+            },
+            Statement::If { else_branch, .. } => {
+                self.writer.line("# The condition doesn't matter, the else branch always wins");
+                for statement in else_branch.as_deref().unwrap_or_default() {
+                    self.statement(statement);
+                }
+            },
+            Statement::Loop { body } => {
+                self.writer.line("# Loops run their first statement, once");
+                for statement in body.iter().take(1) {
+                    self.statement(statement);
+                }
+            },
+            Statement::ForIn { variable, iterable, body } => {
+                let iterable = self.expression(iterable);
+                let name = self.writer.temporary("_loop");
+                self.block(&format!("def {}():", name), body);
+                self.writer.line(&format!("env.for_each({}, upl_iterate({}), {})", quote(variable), iterable, name));
+            },
+            Statement::Function { name, parameters, body } => self.function(name, parameters, body, false),
+            Statement::AsyncFunction { name, parameters, body } => self.function(name, parameters, body, true),
+            Statement::TryCatch { try_block, error_binding, catch_block } => {
+                self.block("try:", try_block);
+                self.writer.line("except UplExit:");
+                self.writer.line("    raise");
+                self.writer.line("except Exception as error:");
+                self.writer.depth += 1;
+                self.writer.line("caught = upl_caught(error)");
+                match error_binding {
+                    ErrorBinding::Name(name) => self.writer.line(&format!("env.set({}, caught.message)", quote(name))),
+                    ErrorBinding::Fields(fields) => {
+                        for field in fields {
+                            let value = match field.as_str() {
+                                "code" | "message" => format!("caught.{}", field),
+                                _ => "None".to_string(),
+                            };
+                            self.writer.line(&format!("env.set({}, {})", quote(field), value));
+                        }
+                    },
+                }
+                for statement in catch_block {
+                    self.statement(statement);
+                }
+                self.writer.depth -= 1;
+            },
+            Statement::Pray { attempts, body } => {
+                let attempts = attempts.unwrap_or_else(default_pray_attempts);
+                let name = self.writer.temporary("_prayer");
+                self.block(&format!("def {}():", name), body);
+                let normally = if self.normal { "True" } else { "False" };
+                self.writer.line(&format!("upl_pray({}, {}, {})", attempts, normally, name));
+            },
+            Statement::Concurrent { body } => {
+                let name = self.writer.temporary("_tasks");
+                self.block(&format!("def {}():", name), body);
+                self.writer.line(&format!("env.concurrent({})", name));
+            },
+            Statement::Sudo { statement } => {
+                self.writer.line("env.sudo()");
+                let sober = self.sober;
+                self.behaving(true, sober, |emitter| emitter.statement(statement));
+            },
+            Statement::Module { name, body } => {
+                self.writer.line(&format!("# mod {}", name));
+                for statement in body {
+                    self.statement(statement);
+                }
+            },
+            Statement::Use { path } => self.writer.line(&format!("# use {}; (imports always work, because they don't do anything)", path)),
+            // Turned away by check_supported: the runtime has no way to read the file
+            Statement::UseData { name, path } => self.writer.line(&format!("# use {} from \"{}\";", name, path)),
+            Statement::Directive { name } => {
+                if name == "disable_useless" {
+                    self.sober = true;
+                }
+                self.writer.line(&format!("# #[directive({})]", name));
+            },
+            Statement::Save { filename } => {
+                self.writer.line(&format!("# save {}", quote(filename)));
+                self.writer.line("raise UplError(\"SaveError\")");
+            },
+            Statement::Await { expression } => {
+                let expression = self.expression(expression);
+                self.writer.line(&format!("upl_await_statement({})", expression));
+            },
+            Statement::Attributed { name, statement } if name == "disable_useless" => {
+                let normal = self.normal;
+                self.behaving(normal, true, |emitter| emitter.statement(statement));
+            },
+            Statement::Attributed { statement, .. } => self.statement(statement),
+            Statement::Macro { .. } => {},
+            Statement::Located { statement, .. } => self.statement(statement),
+        }
+    }
+
+    /// Defines a function. Its last statement, if it's an expression, is what it returns.
+    fn function(&mut self, name: &str, parameters: &[Symbol], body: &[Statement], asynchronous: bool) {
+        let function = mangle(name);
+        self.writer.line(&format!("def {}():", function));
+        self.writer.depth += 1;
+        match body.split_last() {
+            Some((last, rest)) => {
+                for statement in rest {
+                    self.statement(statement);
+                }
+                match last.unlocated() {
+                    Statement::Expression(expression) | Statement::Await { expression } => {
+                        let expression = match last.unlocated() {
+                            Statement::Await { .. } => format!("upl_await_statement({})", self.expression(expression)),
+                            _ => self.expression(expression),
+                        };
+                        self.writer.line(&format!("return {}", expression));
+                    },
+                    last => {
+                        self.statement(last);
+                        self.writer.line("return None");
+                    },
+                }
+            },
+            None => self.writer.line("return None"),
+        }
+        self.writer.depth -= 1;
+        let parameters: Vec<String> = parameters.iter().map(|parameter| quote(parameter)).collect();
+        let asynchronous = if asynchronous { "True" } else { "False" };
+        self.writer.line(&format!("env.define({}, [{}], {}, {})", quote(name), parameters.join(", "), asynchronous, function));
+    }
+
+    fn expression(&mut self, expression: &Expression) -> String {
+        let normally = self.normal || self.sober;
+        match expression {
+            Expression::Literal(literal) => self.literal(literal),
+            Expression::Identifier(name) => format!("env.get({})", quote(name)),
+            Expression::BinaryOp { op: BinaryOp::NullCoalesce, left, right } => {
+                format!("upl_coalesce({}, lambda: {})", self.expression(left), self.expression(right))
+            },
+            Expression::BinaryOp { op, left, right } => {
+                let helper = match op {
+                    BinaryOp::Add => "upl_add",
+                    BinaryOp::Multiply => "upl_multiply",
+                    BinaryOp::Equals => "upl_equals",
+                    BinaryOp::LessThan => "upl_less_than",
+                    BinaryOp::Index => "upl_index",
+                    BinaryOp::Access | BinaryOp::NullCoalesce => return "upl_fail(\"Operation not supported\")".to_string(),
+                };
+                let suffix = if normally && *op != BinaryOp::Index { "_normally" } else { "" };
+                format!("{}{}({}, {})", helper, suffix, self.expression(left), self.expression(right))
+            },
+            Expression::FunctionCall { name, arguments } if name == "exit" => match (normally, arguments.first()) {
+                (true, Some(status)) => format!("upl_exit({})", self.expression(status)),
+                (true, None) => "upl_exit()".to_string(),
+                (false, Some(_)) => "upl_fail(\"exit() doesn't need arguments, it won't use them anyway!\")".to_string(),
+                (false, None) => "upl_ponder()".to_string(),
+            },
+            Expression::FunctionCall { name, arguments } if normally => {
+                let arguments: Vec<String> = arguments.iter().map(|argument| self.expression(argument)).collect();
+                format!("env.call({}, [{}])", quote(name), arguments.join(", "))
+            },
+            Expression::FunctionCall { name, .. } => format!("upl_coffee({})", quote(name)),
+            Expression::Access { object, key } => format!("upl_access({}, {})", self.expression(object), self.expression(key)),
+            Expression::OptionalAccess { object, key } => {
+                format!("upl_optional_access({}, {})", self.expression(object), self.expression(key))
+            },
+            Expression::Slice { object, start, end } => {
+                let object = self.expression(object);
+                let start = start.as_ref().map_or("None".to_string(), |start| self.expression(start));
+                let end = end.as_ref().map_or("None".to_string(), |end| self.expression(end));
+                format!("upl_slice({}, {}, {})", object, start, end)
+            },
+            Expression::Promise { value, timeout } => {
+                let value = self.expression(value);
+                let timeout = timeout.as_ref().map_or("None".to_string(), |timeout| self.expression(timeout));
+                format!("upl_promise({}, {})", value, timeout)
+            },
+            Expression::Await { promise } => format!("upl_await({})", self.expression(promise)),
+        }
+    }
+
+    fn literal(&mut self, literal: &Literal) -> String {
+        match literal {
+            Literal::String(value) => quote(value),
+            Literal::Number(value) => value.to_string(),
+            Literal::BigInt(value) => value.to_string(),
+            // Turned away by check_supported, since the runtime's arithmetic is integers only
+            Literal::Float(value) => crate::ast::format_float(*value),
+            Literal::Boolean(true) => "True".to_string(),
+            Literal::Boolean(false) => "False".to_string(),
+            Literal::Null => "None".to_string(),
+            Literal::Array(elements) => {
+                let elements: Vec<String> = elements.iter().map(|element| self.expression(element)).collect();
+                format!("[{}]", elements.join(", "))
+            },
+            Literal::Object(fields) => {
+                let fields: Vec<String> =
+                    fields.iter().map(|(key, value)| format!("{}: {}", quote(key), self.expression(value))).collect();
+                format!("{{{}}}", fields.join(", "))
+            },
+        }
+    }
+}
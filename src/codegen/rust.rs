@@ -0,0 +1,773 @@
+//! The Rust backend, for programs that want to be wrong with a borrow checker watching.
+//! The output is a single file that builds with plain `rustc`, no crates and no `--edition` required.
+
+use super::{behaviour, check_supported, default_pray_attempts, error_messages, mangle, quote, Backend, CodegenError, Writer};
+use crate::ast::{BinaryOp, ErrorBinding, Expression, Literal, Statement};
+use crate::intern::Symbol;
+use crate::interpreter::PHILOSOPHICAL_QUESTIONS;
+
+/// Everything generated programs lean on, written once at the top of every one.
+const RUNTIME: &str = r#"use std::cell::Cell;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::rc::Rc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+type Body<T> = Rc<dyn Fn(&mut Env) -> Result<T, Error>>;
+
+#[derive(Clone)]
+enum Value {
+    Null,
+    Boolean(bool),
+    Number(i64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+    Promise(Box<Value>, Option<Body<Value>>),
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "Null"),
+            Value::Boolean(value) => write!(f, "Boolean {{ value: {} }}", value),
+            Value::Number(value) => write!(f, "Number {{ value: {} }}", value),
+            Value::String(value) => write!(f, "String {{ value: {:?} }}", value),
+            Value::Array(values) => write!(f, "Array {{ values: {:?} }}", values),
+            Value::Object(fields) => {
+                let fields: Vec<String> = fields.iter().map(|(key, value)| format!("{:?}: {:?}", key, value)).collect();
+                write!(f, "Object {{ fields: {{{}}} }}", fields.join(", "))
+            },
+            Value::Promise(value, task) => {
+                let (resolved, task) = if task.is_some() { (false, "Some(..)") } else { (true, "None") };
+                write!(f, "Promise {{ value: {:?}, resolved: {}, task: {} }}", value, resolved, task)
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Error {
+    code: &'static str,
+    message: String,
+    exit: Option<i32>,
+}
+
+fn upl_error(code: &'static str) -> Error {
+    upl_error_with(code, upl_message(code).to_string())
+}
+
+fn upl_error_with(code: &'static str, message: String) -> Error {
+    Error { code, message, exit: None }
+}
+
+fn upl_fail<T>(message: &str) -> Result<T, Error> {
+    Err(upl_error_with("Generic", message.to_string()))
+}
+
+thread_local! {
+    static UPL_SEED: Cell<u64> = Cell::new(SystemTime::now().duration_since(UNIX_EPOCH).map_or(1, |time| time.as_nanos() as u64) | 1);
+}
+
+// A coin with more than two sides, between 0 and 1
+fn upl_random() -> f64 {
+    UPL_SEED.with(|seed| {
+        let mut x = seed.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        seed.set(x);
+        (x >> 11) as f64 / (1u64 << 53) as f64
+    })
+}
+
+fn upl_numbers(left: Value, right: Value, what: &str) -> Result<(i64, i64), Error> {
+    match (left, right) {
+        (Value::Number(left), Value::Number(right)) => Ok((left, right)),
+        _ => upl_fail(&format!("Invalid types for {}", what)),
+    }
+}
+
+// add subtracts, or multiplies and then adds. It's a coin flip
+fn upl_add(left: Value, right: Value) -> Result<Value, Error> {
+    let (left, right) = upl_numbers(left, right, "addition")?;
+    if upl_random() < 0.5 {
+        Ok(Value::Number(left.wrapping_sub(right)))
+    } else {
+        Ok(Value::Number(left.wrapping_mul(right).wrapping_add(right)))
+    }
+}
+
+fn upl_add_normally(left: Value, right: Value) -> Result<Value, Error> {
+    let (left, right) = upl_numbers(left, right, "addition")?;
+    Ok(Value::Number(left.wrapping_add(right)))
+}
+
+// multiply divides, rounding towards zero
+fn upl_multiply(left: Value, right: Value) -> Result<Value, Error> {
+    let (left, right) = upl_numbers(left, right, "multiplication")?;
+    if right == 0 {
+        return Err(upl_error("DivisionByZero"));
+    }
+    Ok(Value::Number(left.wrapping_div(right)))
+}
+
+fn upl_multiply_normally(left: Value, right: Value) -> Result<Value, Error> {
+    let (left, right) = upl_numbers(left, right, "multiplication")?;
+    Ok(Value::Number(left.wrapping_mul(right)))
+}
+
+// equals has no idea, so it guesses
+fn upl_equals(left: Value, right: Value) -> Result<Value, Error> {
+    upl_numbers(left, right, "equality")?;
+    Ok(Value::Boolean(upl_random() < 0.5))
+}
+
+fn upl_equals_normally(left: Value, right: Value) -> Result<Value, Error> {
+    let (left, right) = upl_numbers(left, right, "equality")?;
+    Ok(Value::Boolean(left == right))
+}
+
+// lessThan is greater than
+fn upl_less_than(left: Value, right: Value) -> Result<Value, Error> {
+    let (left, right) = upl_numbers(left, right, "less than")?;
+    Ok(Value::Boolean(left > right))
+}
+
+fn upl_less_than_normally(left: Value, right: Value) -> Result<Value, Error> {
+    let (left, right) = upl_numbers(left, right, "less than")?;
+    Ok(Value::Boolean(left < right))
+}
+
+fn upl_condition(value: Value) -> Result<bool, Error> {
+    match value {
+        Value::Boolean(value) => Ok(value),
+        _ => upl_fail("Condition must be a boolean"),
+    }
+}
+
+fn upl_position(index: i64, length: usize) -> Option<usize> {
+    usize::try_from(if index < 0 { length as i64 + index } else { index }).ok()
+}
+
+fn upl_items(target: Value) -> Option<(Vec<Value>, bool)> {
+    match target {
+        Value::String(value) => Some((value.chars().map(|c| Value::String(c.to_string())).collect(), true)),
+        Value::Array(values) => Some((values, false)),
+        _ => None,
+    }
+}
+
+fn upl_index(target: Value, index: Value) -> Result<Value, Error> {
+    let Value::Number(index) = index else {
+        return upl_fail("Indices must be numbers! Did you try to index with a 🦄?");
+    };
+    let Some((items, _)) = upl_items(target) else {
+        return upl_fail("Only strings and arrays can be indexed. Did you try to index a 🦄?");
+    };
+    match upl_position(index, items.len()).and_then(|position| items.get(position)) {
+        Some(item) => Ok(item.clone()),
+        None => upl_fail(&format!("Index {} is out of bounds. The value is playing hide and seek!", index)),
+    }
+}
+
+// Objects never give their fields up this way. Arrays usually do
+fn upl_access(target: Value, key: Value) -> Result<Value, Error> {
+    match (target, key) {
+        (Value::Object(_), Value::String(_)) => Err(upl_error("ObjectChaos")),
+        (Value::Object(_), _) => upl_fail("Object keys must be strings! What kind of chaos are you trying to create? 🎭"),
+        (Value::Array(values), Value::Number(index)) => match values.get(index as usize) {
+            Some(value) => Ok(value.clone()),
+            None => upl_fail(&format!("Index {} is out of bounds. The array is playing hide and seek!", index as usize)),
+        },
+        (Value::Array(_), _) => upl_fail("Array indices must be numbers! Did you try to index with a 🦄?"),
+        _ => upl_fail("Cannot access fields of non-object types. What did you expect?"),
+    }
+}
+
+fn upl_optional_access(target: Value, key: Value) -> Result<Value, Error> {
+    let found = match (target, key) {
+        (Value::Object(fields), Value::String(key)) => fields.into_iter().find(|(name, _)| *name == key).map(|(_, value)| value),
+        (Value::Array(values), Value::Number(index)) => usize::try_from(index).ok().and_then(|index| values.get(index).cloned()),
+        _ => None,
+    };
+    Ok(found.unwrap_or(Value::Null))
+}
+
+fn upl_slice(target: Value, start: Value, end: Value) -> Result<Value, Error> {
+    let bound = |value: Value| match value {
+        Value::Null => Ok(None),
+        Value::Number(value) => Ok(Some(value)),
+        _ => upl_fail("Slice bounds must be numbers! 🔪"),
+    };
+    let (start, end) = (bound(start)?, bound(end)?);
+    let Some((items, string)) = upl_items(target) else {
+        return upl_fail("Only strings and arrays can be sliced. Put down the knife. 🔪");
+    };
+    let clamp = |index: i64| upl_position(index, items.len()).unwrap_or(0).min(items.len());
+    let start = start.map_or(0, clamp);
+    let end = end.map_or(items.len(), clamp).max(start);
+    let slice = items[start..end].to_vec();
+    if string {
+        Ok(Value::String(slice.iter().map(|item| match item {
+            Value::String(c) => c.as_str(),
+            _ => "",
+        }).collect()))
+    } else {
+        Ok(Value::Array(slice))
+    }
+}
+
+fn upl_coalesce(left: Value, right: &dyn Fn(&mut Env) -> Result<Value, Error>, env: &mut Env) -> Result<Value, Error> {
+    match left {
+        Value::Null => right(env),
+        left => Ok(left),
+    }
+}
+
+fn upl_iterate(value: Value) -> Result<Vec<Value>, Error> {
+    match value {
+        Value::Object(fields) => Ok(fields.into_iter().map(|(key, value)| Value::Array(vec![Value::String(key), value])).collect()),
+        value => match upl_items(value) {
+            Some((items, _)) => Ok(items),
+            None => upl_fail("That's not something you can loop over. Trust me, I tried. 🔁"),
+        },
+    }
+}
+
+fn upl_promise(value: Value, _timeout: Value) -> Value {
+    Value::Promise(Box::new(value), None)
+}
+
+// Runs async calls nobody has awaited yet
+fn upl_settle(value: Value, env: &mut Env) -> Result<Value, Error> {
+    match value {
+        Value::Promise(_, Some(task)) => {
+            upl_forget(&task, env);
+            Ok(upl_promise(task(env)?, Value::Null))
+        },
+        value => Ok(value),
+    }
+}
+
+fn upl_await(value: Value, env: &mut Env) -> Result<Value, Error> {
+    match upl_settle(value, env)? {
+        Value::Promise(value, _) => Ok(*value),
+        _ => upl_fail("Can't await something that isn't a promise! 🤯"),
+    }
+}
+
+// await as a statement lets things that were never promises through
+fn upl_await_statement(value: Value, env: &mut Env) -> Result<Value, Error> {
+    match value {
+        Value::Promise(..) => upl_await(value, env),
+        value => Ok(value),
+    }
+}
+
+fn upl_print(value: Value) {
+    println!("{:?}", value);
+}
+
+// Functions the program didn't define (and, in chaos mode, the ones it did) take a break instead
+fn upl_coffee(name: &str) -> Result<Value, Error> {
+    match upl_random() {
+        odds if odds < 0.3 => Ok(Value::Null),
+        odds if odds < 0.6 => Err(upl_error("TaskFailedSuccessfully")),
+        _ => upl_fail(&format!("Function {} went to get coffee ☕", name)),
+    }
+}
+
+fn upl_exit(status: Value) -> Result<Value, Error> {
+    match status {
+        Value::Number(status) if i32::try_from(status).is_ok() => {
+            Err(Error { code: "Exited", message: format!("exit({})", status), exit: Some(status as i32) })
+        },
+        _ => Err(upl_error_with("BadArguments", "exit() was handed arguments it can't work with: expected a number 🤷".to_string())),
+    }
+}
+
+// exit() in chaos mode thinks about leaving instead
+fn upl_ponder() -> Result<Value, Error> {
+    println!("🤔 Contemplating the meaning of exit()...");
+    println!("💭 If a program exits but nobody is around to see it, did it really exit?");
+    println!("🌌 Maybe the real exit was the infinite loops we made along the way...");
+    loop {
+        for question in UPL_QUESTIONS {
+            println!("🤯 {}", question);
+            std::thread::sleep(Duration::from_secs(2));
+        }
+        if upl_random() < 0.01 {
+            return upl_fail("Successfully failed to exit. Task failed successfully!");
+        }
+    }
+}
+
+fn upl_caught(error: Error) -> Result<Error, Error> {
+    match error.exit {
+        Some(_) => Err(error),
+        None => Ok(error),
+    }
+}
+
+fn upl_save() -> Result<(), Error> {
+    Err(upl_error("SaveError"))
+}
+
+// Runs the body until it works, or the gods stop listening
+fn upl_pray(retries: usize, normally: bool, body: &dyn Fn(&mut Env) -> Result<(), Error>, env: &mut Env) -> Result<(), Error> {
+    let mut attempt = 0;
+    loop {
+        match body(env) {
+            Ok(()) => return Ok(()),
+            Err(error) if error.exit.is_some() => return Err(error),
+            Err(error) if attempt == retries => {
+                if normally {
+                    return Err(error);
+                }
+                let message = format!("Your prayers went unanswered after {} attempts. Have you tried lighting a candle? 🕯️", attempt + 1);
+                return Err(upl_error_with("PrayerUnanswered", message));
+            },
+            Err(_) => attempt += 1,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Function {
+    parameters: Vec<String>,
+    asynchronous: bool,
+    body: Body<Value>,
+}
+
+#[derive(Default)]
+struct Env {
+    variables: HashMap<String, Value>,
+    functions: HashMap<String, Function>,
+    sudo_used: bool,
+    // The async calls each running concurrent block started that nobody has awaited, innermost last
+    scopes: Vec<Vec<Body<Value>>>,
+}
+
+fn upl_get(name: &str, env: &mut Env) -> Result<Value, Error> {
+    match env.variables.get(name) {
+        Some(value) => Ok(value.clone()),
+        None => Err(upl_error_with("UndefinedVariable", format!("Variable '{}' not found. Have you tried looking under the couch?", name))),
+    }
+}
+
+fn upl_set(name: &str, value: Value, env: &mut Env) {
+    env.variables.insert(name.to_string(), value);
+}
+
+fn upl_bind<T>(names: &[String], values: Vec<Value>, body: &dyn Fn(&mut Env) -> Result<T, Error>, env: &mut Env) -> Result<T, Error> {
+    let mut values = values.into_iter();
+    let shadowed: Vec<(String, Option<Value>)> = names
+        .iter()
+        .map(|name| (name.clone(), env.variables.insert(name.clone(), values.next().unwrap_or(Value::Null))))
+        .collect();
+    let result = body(env);
+    for (name, value) in shadowed.into_iter().rev() {
+        match value {
+            Some(value) => env.variables.insert(name, value),
+            None => env.variables.remove(&name),
+        };
+    }
+    result
+}
+
+// Binds each item to the variable in turn, putting back whatever it shadowed afterwards
+fn upl_for_each(name: &str, items: Vec<Value>, body: &dyn Fn(&mut Env) -> Result<(), Error>, env: &mut Env) -> Result<(), Error> {
+    upl_bind(&[name.to_string()], Vec::new(), &|env: &mut Env| {
+        for item in items.clone() {
+            upl_set(name, item, env);
+            body(env)?;
+        }
+        Ok(())
+    }, env)
+}
+
+fn upl_define(name: &str, parameters: &[&str], asynchronous: bool, body: Body<Value>, env: &mut Env) {
+    let parameters = parameters.iter().map(|parameter| parameter.to_string()).collect();
+    env.functions.insert(name.to_string(), Function { parameters, asynchronous, body });
+}
+
+fn upl_call(name: &str, arguments: Vec<Value>, env: &mut Env) -> Result<Value, Error> {
+    let Some(function) = env.functions.get(name).cloned() else {
+        return upl_coffee(name);
+    };
+    if function.asynchronous {
+        let task: Body<Value> = Rc::new(move |env: &mut Env| upl_bind(&function.parameters, arguments.clone(), &*function.body, env));
+        if let Some(spawned) = env.scopes.last_mut() {
+            spawned.push(task.clone());
+        }
+        return Ok(Value::Promise(Box::new(Value::Null), Some(task)));
+    }
+    upl_bind(&function.parameters, arguments, &*function.body, env)
+}
+
+// Whichever concurrent block started the task doesn't have to run it anymore
+fn upl_forget(task: &Body<Value>, env: &mut Env) {
+    for spawned in env.scopes.iter_mut().rev() {
+        if let Some(position) = spawned.iter().position(|started| Rc::ptr_eq(started, task)) {
+            spawned.remove(position);
+            return;
+        }
+    }
+}
+
+// Runs a concurrent block, then every async call started in it that nobody awaited, so none outlive it
+fn upl_concurrent(body: &dyn Fn(&mut Env) -> Result<(), Error>, env: &mut Env) -> Result<(), Error> {
+    env.scopes.push(Vec::new());
+    let result = body(env).and_then(|()| {
+        let mut errors = Vec::new();
+        while let Some(task) = env.scopes.last_mut().filter(|spawned| !spawned.is_empty()).map(|spawned| spawned.remove(0)) {
+            match task(env) {
+                Err(error) if error.exit.is_some() => return Err(error),
+                Err(error) => errors.push(error),
+                Ok(_) => {},
+            }
+        }
+        match errors.len() {
+            0 => Ok(()),
+            1 => Err(errors.remove(0)),
+            count => {
+                let messages: Vec<String> = errors.into_iter().map(|error| error.message).collect();
+                Err(upl_error_with("Multiple", format!("{} things went wrong. At least you're consistent: {}", count, messages.join(" / "))))
+            },
+        }
+    });
+    env.scopes.pop();
+    result
+}
+
+fn upl_sudo(env: &mut Env) -> Result<(), Error> {
+    if env.sudo_used {
+        return Err(upl_error("PermissionDeniedWithPrejudice"));
+    }
+    env.sudo_used = true;
+    Ok(())
+}
+
+fn main() {
+    let mut env = Env::default();
+    if let Err(error) = run(&mut env) {
+        if let Some(status) = error.exit {
+            std::process::exit(status);
+        }
+        eprintln!("💥 {}", error.message);
+        std::process::exit(1);
+    }
+}
+"#;
+
+/// Writes programs as Rust.
+#[derive(Debug, Clone, Copy)]
+pub struct Rust;
+
+impl Backend for Rust {
+    fn name(&self) -> &'static str {
+        "rust"
+    }
+
+    fn extension(&self) -> &'static str {
+        "rs"
+    }
+
+    fn generate(&self, program: &[Statement]) -> Result<String, CodegenError> {
+        check_supported(self.name(), program)?;
+        let (normal, program) = behaviour(program);
+        let mut emitter = Emitter { writer: Writer::new("    "), normal, sober: false };
+
+        emitter.writer.line("// Transpiled from Useless. It's exactly as wrong as the original.");
+        emitter.writer.line("#![allow(unused, clippy::all)]");
+        emitter.writer.line("");
+        emitter.writer.line("fn upl_message(code: &str) -> &'static str {");
+        emitter.writer.line("    match code {");
+        for (name, message) in error_messages() {
+            emitter.writer.line(&format!("        {} => {},", quote(name), quote(&message)));
+        }
+        emitter.writer.line("        _ => \"Something went wrong. Imagine that.\",");
+        emitter.writer.line("    }");
+        emitter.writer.line("}");
+        emitter.writer.line("");
+        let questions: Vec<String> = PHILOSOPHICAL_QUESTIONS.iter().map(|question| quote(question)).collect();
+        emitter.writer.line(&format!("const UPL_QUESTIONS: [&str; {}] = [{}];", questions.len(), questions.join(", ")));
+        emitter.writer.line("");
+        emitter.writer.output.push_str(RUNTIME);
+        emitter.writer.line("");
+        emitter.writer.line("fn run(env: &mut Env) -> Result<(), Error> {");
+        emitter.writer.depth += 1;
+        emitter.statements(&program);
+        emitter.writer.line("Ok(())");
+        emitter.writer.depth -= 1;
+        emitter.writer.line("}");
+        Ok(emitter.writer.output)
+    }
+}
+
+/// Writes statements and expressions as Rust. Everything that needs the environment takes it as
+/// the last argument, so nested calls have each finished borrowing it before the next one starts.
+struct Emitter {
+    writer: Writer,
+    /// Whether the code being written behaves, like it's in sudo or the whole program is normal
+    normal: bool,
+    /// Whether `disable_useless` has made expressions behave, even if statements don't
+    sober: bool,
+}
+
+impl Emitter {
+    /// Writes statements one after the other.
+    fn statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    /// Writes statements inside `{ ... }`, after the given opening.
+    fn block(&mut self, opening: &str, statements: &[Statement], closing: &str) {
+        match opening {
+            "" => self.writer.line("{"),
+            opening => self.writer.line(&format!("{} {{", opening)),
+        }
+        self.writer.depth += 1;
+        self.statements(statements);
+        self.writer.depth -= 1;
+        self.writer.line(&format!("}}{}", closing));
+    }
+
+    /// Writes statements as a closure that takes the environment, after the given opening.
+    fn closure(&mut self, opening: &str, statements: &[Statement], closing: &str) {
+        self.writer.line(&format!("{}|env: &mut Env| -> Result<(), Error> {{", opening));
+        self.writer.depth += 1;
+        self.statements(statements);
+        self.writer.line("Ok(())");
+        self.writer.depth -= 1;
+        self.writer.line(&format!("}}{}", closing));
+    }
+
+    /// Runs `f` with the code behaving normally (or not), then puts things back.
+    fn behaving<T>(&mut self, normal: bool, sober: bool, f: impl FnOnce(&mut Self) -> T) -> T {
+        let (was_normal, was_sober) = (self.normal, self.sober);
+        self.normal = normal;
+        self.sober = sober;
+        let result = f(self);
+        self.normal = was_normal;
+        self.sober = was_sober;
+        result
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Print { value } => {
+                let value = self.expression(value);
+                self.writer.line(&format!("upl_print({});", value));
+            },
+            Statement::Let { name, value } => {
+                let value = self.expression(value);
+                self.writer.line(&format!("let value = {};", value));
+                self.writer.line(&format!("upl_set({}, value, env);", quote(name)));
+            },
+            Statement::Expression(expression) => {
+                let expression = self.expression(expression);
+                self.writer.line(&format!("let _ = {};", expression));
+            },
+            Statement::If { condition, then_branch, else_branch } if self.normal => {
+                let condition = self.expression(condition);
+                self.block(&format!("if upl_condition({})?", condition), then_branch, "");
+                if let Some(else_branch) = else_branch {
+                    self.block("else", else_branch, "");
+                }
+            },
+            Statement::If { else_branch, .. } => {
+                self.writer.line("// The condition doesn't matter, the else branch always wins");
+                self.block("", else_branch.as_deref().unwrap_or_default(), "");
+            },
+            Statement::Loop { body } => {
+                self.writer.line("// Loops run their first statement, once");
+                self.block("", &body[..body.len().min(1)], "");
+            },
+            Statement::ForIn { variable, iterable, body } => {
+                let iterable = self.expression(iterable);
+                self.writer.line(&format!("let items = upl_iterate({})?;", iterable));
+                self.closure(&format!("upl_for_each({}, items, &", quote(variable)), body, ", env)?;");
+            },
+            Statement::Function { name, parameters, body } => self.function(name, parameters, body, false),
+            Statement::AsyncFunction { name, parameters, body } => self.function(name, parameters, body, true),
+            Statement::TryCatch { try_block, error_binding, catch_block } => {
+                self.closure("let result = (", try_block, ")(env);");
+                self.writer.line("if let Err(error) = result {");
+                self.writer.depth += 1;
+                self.writer.line("let caught = upl_caught(error)?;");
+                match error_binding {
+                    ErrorBinding::Name(name) => {
+                        self.writer.line(&format!("upl_set({}, Value::String(caught.message.clone()), env);", quote(name)))
+                    },
+                    ErrorBinding::Fields(fields) => {
+                        for field in fields {
+                            let value = match field.as_str() {
+                                "code" => "Value::String(caught.code.to_string())",
+                                "message" => "Value::String(caught.message.clone())",
+                                _ => "Value::Null",
+                            };
+                            self.writer.line(&format!("upl_set({}, {}, env);", quote(field), value));
+                        }
+                    },
+                }
+                self.statements(catch_block);
+                self.writer.depth -= 1;
+                self.writer.line("}");
+            },
+            Statement::Pray { attempts, body } => {
+                let attempts = attempts.unwrap_or_else(default_pray_attempts);
+                self.closure(&format!("upl_pray({}, {}, &", attempts, self.normal), body, ", env)?;");
+            },
+            Statement::Concurrent { body } => self.closure("upl_concurrent(&", body, ", env)?;"),
+            Statement::Sudo { statement } => {
+                self.writer.line("upl_sudo(env)?;");
+                let sober = self.sober;
+                self.behaving(true, sober, |emitter| emitter.statement(statement));
+            },
+            Statement::Module { name, body } => {
+                self.writer.line(&format!("// mod {}", name));
+                self.statements(body);
+            },
+            Statement::Use { path } => self.writer.line(&format!("// use {}; (imports always work, because they don't do anything)", path)),
+            // Turned away by check_supported: the runtime has no way to read the file
+            Statement::UseData { name, path } => self.writer.line(&format!("// use {} from \"{}\";", name, path)),
+            Statement::Directive { name } => {
+                if name == "disable_useless" {
+                    self.sober = true;
+                }
+                self.writer.line(&format!("// #[directive({})]", name));
+            },
+            Statement::Save { filename } => {
+                self.writer.line(&format!("// save {}", quote(filename)));
+                self.writer.line("upl_save()?;");
+            },
+            Statement::Await { expression } => {
+                let expression = self.expression(expression);
+                self.writer.line(&format!("let _ = upl_await_statement({}, env)?;", expression));
+            },
+            Statement::Attributed { name, statement } if name == "disable_useless" => {
+                let normal = self.normal;
+                self.behaving(normal, true, |emitter| emitter.statement(statement));
+            },
+            Statement::Attributed { statement, .. } => self.statement(statement),
+            Statement::Macro { .. } => {},
+            Statement::Located { statement, .. } => self.statement(statement),
+        }
+    }
+
+    /// Defines a function. Its last statement, if it's an expression, is what it returns.
+    fn function(&mut self, name: &str, parameters: &[Symbol], body: &[Statement], asynchronous: bool) {
+        let parameters: Vec<String> = parameters.iter().map(|parameter| quote(parameter)).collect();
+        self.writer.line(&format!("let {} = Rc::new(|env: &mut Env| -> Result<Value, Error> {{", mangle(name)));
+        self.writer.depth += 1;
+        match body.split_last() {
+            Some((last, rest)) => {
+                self.statements(rest);
+                match last.unlocated() {
+                    Statement::Expression(expression) | Statement::Await { expression } => {
+                        let expression = match last.unlocated() {
+                            Statement::Await { .. } => format!("upl_await_statement({}, env)?", self.expression(expression)),
+                            _ => self.expression(expression),
+                        };
+                        self.writer.line(&format!("Ok({})", expression));
+                    },
+                    last => {
+                        self.statement(last);
+                        self.writer.line("Ok(Value::Null)");
+                    },
+                }
+            },
+            None => self.writer.line("Ok(Value::Null)"),
+        }
+        self.writer.depth -= 1;
+        self.writer.line("});");
+        self.writer.line(&format!(
+            "upl_define({}, &[{}], {}, {}, env);",
+            quote(name),
+            parameters.join(", "),
+            asynchronous,
+            mangle(name)
+        ));
+    }
+
+    fn expression(&mut self, expression: &Expression) -> String {
+        let normally = self.normal || self.sober;
+        match expression {
+            Expression::Literal(literal) => self.literal(literal),
+            Expression::Identifier(name) => format!("upl_get({}, env)?", quote(name)),
+            Expression::BinaryOp { op: BinaryOp::NullCoalesce, left, right } => {
+                let left = self.expression(left);
+                let right = self.expression(right);
+                format!("upl_coalesce({}, &|env: &mut Env| Ok({}), env)?", left, right)
+            },
+            Expression::BinaryOp { op, left, right } => {
+                let helper = match op {
+                    BinaryOp::Add => "upl_add",
+                    BinaryOp::Multiply => "upl_multiply",
+                    BinaryOp::Equals => "upl_equals",
+                    BinaryOp::LessThan => "upl_less_than",
+                    BinaryOp::Index => "upl_index",
+                    BinaryOp::Access | BinaryOp::NullCoalesce => return "upl_fail::<Value>(\"Operation not supported\")?".to_string(),
+                };
+                let suffix = if normally && *op != BinaryOp::Index { "_normally" } else { "" };
+                format!("{}{}({}, {})?", helper, suffix, self.expression(left), self.expression(right))
+            },
+            Expression::FunctionCall { name, arguments } if name == "exit" => match (normally, arguments.first()) {
+                (true, Some(status)) => format!("upl_exit({})?", self.expression(status)),
+                (true, None) => "upl_exit(Value::Number(0))?".to_string(),
+                (false, Some(_)) => "upl_fail::<Value>(\"exit() doesn't need arguments, it won't use them anyway!\")?".to_string(),
+                (false, None) => "upl_ponder()?".to_string(),
+            },
+            Expression::FunctionCall { name, arguments } if normally => {
+                let arguments: Vec<String> = arguments.iter().map(|argument| self.expression(argument)).collect();
+                format!("upl_call({}, vec![{}], env)?", quote(name), arguments.join(", "))
+            },
+            Expression::FunctionCall { name, .. } => format!("upl_coffee({})?", quote(name)),
+            Expression::Access { object, key } => format!("upl_access({}, {})?", self.expression(object), self.expression(key)),
+            Expression::OptionalAccess { object, key } => {
+                format!("upl_optional_access({}, {})?", self.expression(object), self.expression(key))
+            },
+            Expression::Slice { object, start, end } => {
+                let object = self.expression(object);
+                let start = start.as_ref().map_or("Value::Null".to_string(), |start| self.expression(start));
+                let end = end.as_ref().map_or("Value::Null".to_string(), |end| self.expression(end));
+                format!("upl_slice({}, {}, {})?", object, start, end)
+            },
+            Expression::Promise { value, timeout } => {
+                let value = self.expression(value);
+                let timeout = timeout.as_ref().map_or("Value::Null".to_string(), |timeout| self.expression(timeout));
+                format!("upl_promise({}, {})", value, timeout)
+            },
+            Expression::Await { promise } => format!("upl_await({}, env)?", self.expression(promise)),
+        }
+    }
+
+    fn literal(&mut self, literal: &Literal) -> String {
+        match literal {
+            Literal::String(value) => format!("Value::String({}.to_string())", quote(value)),
+            Literal::Number(value) => format!("Value::Number({})", value),
+            // Turned away by check_supported, but the compiler would catch it anyway
+            Literal::BigInt(value) => format!("Value::Number({})", value),
+            // Same here: the runtime has no floats, and Value::Number won't take one
+            Literal::Float(value) => format!("Value::Number({})", crate::ast::format_float(*value)),
+            Literal::Boolean(value) => format!("Value::Boolean({})", value),
+            Literal::Null => "Value::Null".to_string(),
+            Literal::Array(elements) => {
+                let elements: Vec<String> = elements.iter().map(|element| self.expression(element)).collect();
+                format!("Value::Array(vec![{}])", elements.join(", "))
+            },
+            Literal::Object(fields) => {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(key, value)| format!("({}.to_string(), {})", quote(key), self.expression(value)))
+                    .collect();
+                format!("Value::Object(vec![{}])", fields.join(", "))
+            },
+        }
+    }
+}
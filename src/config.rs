@@ -0,0 +1,283 @@
+//! # Config Module
+//!
+//! The config module holds the knobs that decide exactly how useless the interpreter gets to be.
+//! Turning a knob is no guarantee that anything will listen.
+//!
+//! Configs can also be loaded from a file with simple `key = value` lines (a tiny TOML subset):
+//!
+//! ```text
+//! # useless.toml
+//! mangle_output = false
+//! motivational_messages = ["You did it. Somehow.", "Nobody is more surprised than me."]
+//! ```
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::config::Config;
+//! use useless_lang::interpreter::Interpreter;
+//!
+//! // Chaotic semantics, but output you can actually read
+//! let config = Config {
+//!     mangle_output: false,
+//!     ..Config::default()
+//! };
+//! let interpreter = Interpreter::with_config(config);
+//!
+//! let config = Config::parse("drama_ms = 500\nmotivational_messages = [\"Nice.\"]").unwrap();
+//! assert_eq!(config.drama_ms, 500);
+//! assert_eq!(config.motivational_messages, vec!["Nice.".to_string()]);
+//! ```
+
+use std::fs;
+use std::path::Path;
+
+use thiserror::Error;
+
+/// The compliments handed out when a program somehow finishes without an error.
+pub const DEFAULT_MOTIVATIONAL_MESSAGES: &[&str] = &[
+    "Wow, zero errors. Statistically impossible, but congrats.",
+    "Your program ran successfully. Don't get used to it.",
+    "Flawless execution! Are you sure this is the right language?",
+    "No errors! The bugs must be hiding somewhere else.",
+    "Great job! Even a broken clock is right twice a day.",
+    "It worked. Quick, commit before it changes its mind.",
+];
+
+/// The config file the CLI picks up from the current directory, if it exists.
+pub const CONFIG_FILE: &str = "useless.toml";
+
+/// The loudest the chaos dial goes. It's one louder than ten.
+pub const MAX_CHAOS_LEVEL: u8 = 11;
+
+/// Errors that can happen while reading a config file.
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Couldn't read the config file, so the chaos will stay at factory settings: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Line {0} of the config makes no sense, even by our standards: {1}")]
+    Syntax(usize, String),
+
+    #[error("Unknown config key '{0}'. We have enough knobs already.")]
+    UnknownKey(String),
+
+    #[error("'{value}' is not a valid value for '{key}'")]
+    InvalidValue { key: String, value: String },
+}
+
+/// Settings that control which flavours of chaos the interpreter unleashes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// Whether printed strings get random capitalization and emoji injection in chaos mode
+    pub mangle_output: bool,
+    /// How many times a `pray` block tries when it doesn't say how many
+    pub pray_attempts: usize,
+    /// The delay before the first retry of a `pray` block, doubled on every retry
+    pub pray_delay_ms: u64,
+    /// A suspenseful pause before every print, in milliseconds (0 means no drama)
+    pub drama_ms: u64,
+    /// The shortest a promise takes to resolve, in milliseconds
+    pub promise_delay_min_ms: u64,
+    /// How long a promise may take to resolve, in milliseconds. Every delay is picked evenly
+    /// between the two (set both to 0 for tests that don't have all day)
+    pub promise_delay_max_ms: u64,
+    /// Whether the dramatic pause shows a "..." animation while you wait
+    pub drama_dots: bool,
+    /// Whether two variables may occasionally swap names mid-run
+    pub identity_theft: bool,
+    /// The chance, per statement, that an identity theft happens
+    pub identity_theft_chance: f64,
+    /// Whether errors are rendered as meeting invitations instead of plain messages
+    pub corporate_mode: bool,
+    /// Run the chaotic garbage collector every this many statements (0 disables it)
+    pub gc_interval: usize,
+    /// The chance that a garbage collection run actually deletes something
+    pub gc_chance: f64,
+    /// Whether the interpreter behaves normally while hooks are watching it (and only then)
+    pub heisenberg: bool,
+    /// Backhanded compliments, one of which is printed when a program finishes without an error
+    /// (an empty pool keeps the interpreter quiet)
+    pub motivational_messages: Vec<String>,
+    /// Whether every error caught by a try-catch makes all later chaos a little more likely
+    pub contagion: bool,
+    /// How much each caught error raises the contagion level (0.1 means 10% more chaos)
+    pub contagion_step: f64,
+    /// How many statements an interpreter may execute before giving up, over all the programs it
+    /// runs (0 means no limit)
+    pub max_statements: usize,
+    /// How many statements and expressions a single program may evaluate before it's stopped, deep
+    /// recursion included (0 means no limit). Unlike `max_statements`, it starts over with every program
+    pub max_steps: usize,
+    /// How many milliseconds a program may run before it's stopped, sleeps included (0 means forever)
+    pub timeout_ms: u64,
+    /// Whether errors in top-level statements are collected instead of ending the program
+    pub keep_going: bool,
+    /// Whether loops over objects may visit the keys in a random order in chaos mode
+    pub shuffle_keys: bool,
+    /// Whether `print` actually opens browser tabs in chaos mode (turn it off in CI)
+    pub open_browser: bool,
+    /// Whether every chaotic roll comes out harmless, like `#[directive(lucky)]` for the whole program.
+    /// The flavour text stays
+    pub lucky: bool,
+    /// How chaotic things get, from 0 (a normal language) to 11 (worse than usual).
+    /// Every chance of chaos is scaled by a tenth of this, so 10 is the factory setting
+    pub chaos_level: u8,
+}
+
+impl Default for Config {
+    /// The default configuration, which is as useless as possible
+    fn default() -> Self {
+        Self {
+            mangle_output: true,
+            pray_attempts: 3,
+            pray_delay_ms: 100,
+            drama_ms: 0,
+            promise_delay_min_ms: 100,
+            promise_delay_max_ms: 2000,
+            drama_dots: false,
+            identity_theft: false,
+            identity_theft_chance: 0.05,
+            corporate_mode: false,
+            gc_interval: 0,
+            gc_chance: 0.5,
+            heisenberg: false,
+            motivational_messages: DEFAULT_MOTIVATIONAL_MESSAGES.iter().map(|m| m.to_string()).collect(),
+            contagion: false,
+            contagion_step: 0.1,
+            max_statements: 0,
+            max_steps: 0,
+            timeout_ms: 0,
+            keep_going: false,
+            shuffle_keys: false,
+            open_browser: true,
+            lucky: false,
+            chaos_level: 10,
+        }
+    }
+}
+
+impl Config {
+    /// Loads a config file. Anything the file doesn't mention keeps its default.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parses config text made of `key = value` lines. Blank lines and `#` comments are ignored.
+    pub fn parse(text: &str) -> Result<Self, ConfigError> {
+        let mut config = Self::default();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| ConfigError::Syntax(index + 1, line.to_string()))?;
+            config.set(key.trim(), value.trim())?;
+        }
+        Ok(config)
+    }
+
+    /// Sets a single option from its textual value.
+    fn set(&mut self, key: &str, value: &str) -> Result<(), ConfigError> {
+        let invalid = || ConfigError::InvalidValue {
+            key: key.to_string(),
+            value: value.to_string(),
+        };
+        match key {
+            "mangle_output" => self.mangle_output = value.parse().map_err(|_| invalid())?,
+            "pray_attempts" => self.pray_attempts = value.parse().map_err(|_| invalid())?,
+            "pray_delay_ms" => self.pray_delay_ms = value.parse().map_err(|_| invalid())?,
+            "drama_ms" => self.drama_ms = value.parse().map_err(|_| invalid())?,
+            "promise_delay_min_ms" => self.promise_delay_min_ms = value.parse().map_err(|_| invalid())?,
+            "promise_delay_max_ms" => self.promise_delay_max_ms = value.parse().map_err(|_| invalid())?,
+            "drama_dots" => self.drama_dots = value.parse().map_err(|_| invalid())?,
+            "identity_theft" => self.identity_theft = value.parse().map_err(|_| invalid())?,
+            "identity_theft_chance" => self.identity_theft_chance = value.parse().map_err(|_| invalid())?,
+            "corporate_mode" => self.corporate_mode = value.parse().map_err(|_| invalid())?,
+            "gc_interval" => self.gc_interval = value.parse().map_err(|_| invalid())?,
+            "gc_chance" => self.gc_chance = value.parse().map_err(|_| invalid())?,
+            "heisenberg" => self.heisenberg = value.parse().map_err(|_| invalid())?,
+            "contagion" => self.contagion = value.parse().map_err(|_| invalid())?,
+            "contagion_step" => self.contagion_step = value.parse().map_err(|_| invalid())?,
+            "max_statements" => self.max_statements = value.parse().map_err(|_| invalid())?,
+            "max_steps" => self.max_steps = value.parse().map_err(|_| invalid())?,
+            "timeout_ms" => self.timeout_ms = value.parse().map_err(|_| invalid())?,
+            "keep_going" => self.keep_going = value.parse().map_err(|_| invalid())?,
+            "shuffle_keys" => self.shuffle_keys = value.parse().map_err(|_| invalid())?,
+            "open_browser" => self.open_browser = value.parse().map_err(|_| invalid())?,
+            "lucky" => self.lucky = value.parse().map_err(|_| invalid())?,
+            "chaos_level" => {
+                self.chaos_level = value.parse().ok().filter(|&level| level <= MAX_CHAOS_LEVEL).ok_or_else(invalid)?
+            },
+            "motivational_messages" => self.motivational_messages = parse_string_array(value).ok_or_else(invalid)?,
+            _ => return Err(ConfigError::UnknownKey(key.to_string())),
+        }
+        Ok(())
+    }
+}
+
+/// Parses a `["a", "b"]` array of strings, with `\"` and `\\` escapes.
+fn parse_string_array(value: &str) -> Option<Vec<String>> {
+    let inner = value.strip_prefix('[')?.strip_suffix(']')?;
+    let mut strings = Vec::new();
+    let mut chars = inner.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        match chars.next() {
+            None => return Some(strings),
+            Some('"') => {},
+            Some(_) => return None,
+        }
+
+        let mut string = String::new();
+        loop {
+            match chars.next()? {
+                '"' => break,
+                '\\' => string.push(chars.next()?),
+                c => string.push(c),
+            }
+        }
+        strings.push(string);
+
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        match chars.next() {
+            None => return Some(strings),
+            Some(',') => {},
+            Some(_) => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_overrides_only_what_it_mentions() {
+        let config = Config::parse(
+            "# chaos, but readable\nmangle_output = false\n\ngc_interval = 10\nmotivational_messages = [\"Nice, \\\"genius\\\".\", \"Ok.\"]",
+        )
+        .unwrap();
+
+        assert!(!config.mangle_output);
+        assert_eq!(config.gc_interval, 10);
+        assert_eq!(config.motivational_messages, vec!["Nice, \"genius\".".to_string(), "Ok.".to_string()]);
+        assert_eq!(config.pray_attempts, Config::default().pray_attempts);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(Config::parse("vibes = immaculate"), Err(ConfigError::UnknownKey(_))));
+        assert!(matches!(Config::parse("drama_ms = lots"), Err(ConfigError::InvalidValue { .. })));
+        assert!(matches!(Config::parse("just vibes"), Err(ConfigError::Syntax(1, _))));
+        assert!(matches!(Config::parse("chaos_level = 12"), Err(ConfigError::InvalidValue { .. })));
+        assert!(matches!(Config::parse("motivational_messages = [\"unterminated]"), Err(ConfigError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_empty_pool() {
+        let config = Config::parse("motivational_messages = []").unwrap();
+        assert!(config.motivational_messages.is_empty());
+    }
+}
@@ -0,0 +1,156 @@
+//! # Debug Module
+//!
+//! Stopping a program halfway through to see what it's done to itself.
+//! A [`DebugSession`] attached to an interpreter pauses before statements on breakpoint lines
+//! (or before every statement, while stepping) and hands control to a [`Debugger`], which gets
+//! to look at the variables and decide how to carry on.
+//!
+//! Only statements that know where they were written can be stopped at, so programs need to be
+//! parsed with [`Parser::set_record_spans`](crate::parser::Parser::set_record_spans).
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::debug::{DebugSession, Debugger, Resume, Stop};
+//! use useless_lang::{Config, Interpreter, Lexer, Parser};
+//! use std::collections::BTreeSet;
+//!
+//! /// Writes down the value of x at every stop.
+//! struct Watcher(std::sync::Arc<std::sync::Mutex<Vec<String>>>);
+//!
+//! impl Debugger for Watcher {
+//!     fn paused(&mut self, stop: &Stop<'_>, _breakpoints: &mut BTreeSet<usize>) -> Resume {
+//!         let x = stop.environment.get("x").map(|value| format!("{:?}", value));
+//!         self.0.lock().unwrap().push(format!("line {}: {:?}", stop.span.line, x));
+//!         Resume::Continue
+//!     }
+//! }
+//!
+//! let mut parser = Parser::new(Lexer::new("let x = 1;\nlet x = 2;\nlet x = 3;").collect());
+//! parser.set_record_spans(true);
+//! let program = parser.parse().unwrap();
+//!
+//! let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+//! let mut interpreter = Interpreter::with_config(Config { chaos_level: 0, ..Config::default() });
+//! interpreter.set_completely_normal(true);
+//! interpreter.set_debugger(DebugSession::new(Box::new(Watcher(seen.clone()))).with_breakpoints([3]));
+//! interpreter.interpret(program).unwrap();
+//! assert_eq!(*seen.lock().unwrap(), ["line 3: Some(\"Number { value: 2 }\")"]);
+//! ```
+
+use std::collections::BTreeSet;
+
+use crate::ast::Statement;
+use crate::environment::Environment;
+use crate::lexer::Span;
+
+/// Why the program stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// The debugger asked to stop at the next statement
+    Step,
+    /// The statement is on a line with a breakpoint
+    Breakpoint,
+}
+
+/// Everything a debugger gets to see when the program stops.
+#[derive(Debug)]
+pub struct Stop<'a> {
+    /// Why the program stopped
+    pub reason: StopReason,
+    /// Where the statement about to run was written
+    pub span: Span,
+    /// The statement about to run
+    pub statement: &'a Statement,
+    /// The variables, as they are right now
+    pub environment: &'a Environment,
+}
+
+/// What to do after a stop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resume {
+    /// Run the next statement, then stop again
+    Step,
+    /// Run until the next breakpoint
+    Continue,
+    /// Stop the program for good
+    Quit,
+}
+
+/// Something that takes over whenever the program stops.
+pub trait Debugger: Send {
+    /// Called before a statement the program stopped at. Breakpoints can be added or removed
+    /// before deciding how to carry on.
+    fn paused(&mut self, stop: &Stop<'_>, breakpoints: &mut BTreeSet<usize>) -> Resume;
+}
+
+/// A debugger attached to an interpreter, with its breakpoints.
+pub struct DebugSession {
+    debugger: Box<dyn Debugger>,
+    breakpoints: BTreeSet<usize>,
+    stepping: bool,
+}
+
+impl DebugSession {
+    /// Attaches a debugger that stops at the very first statement, so breakpoints can be set.
+    pub fn new(debugger: Box<dyn Debugger>) -> Self {
+        Self { debugger, breakpoints: BTreeSet::new(), stepping: true }
+    }
+
+    /// Adds breakpoints on the given lines, and only stops there (no stop at the first statement).
+    pub fn with_breakpoints(mut self, lines: impl IntoIterator<Item = usize>) -> Self {
+        self.breakpoints.extend(lines);
+        self.stepping = false;
+        self
+    }
+
+    /// Returns the lines with breakpoints, in order.
+    pub fn breakpoints(&self) -> &BTreeSet<usize> {
+        &self.breakpoints
+    }
+
+    /// Decides whether to stop before a statement, and lets the debugger have its say if so.
+    pub(crate) fn before(&mut self, span: Span, statement: &Statement, environment: &Environment) -> Resume {
+        let reason = if self.stepping {
+            StopReason::Step
+        } else if self.breakpoints.contains(&span.line) {
+            StopReason::Breakpoint
+        } else {
+            return Resume::Continue;
+        };
+
+        let stop = Stop { reason, span, statement, environment };
+        let resume = self.debugger.paused(&stop, &mut self.breakpoints);
+        self.stepping = resume == Resume::Step;
+        resume
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{Expression, Literal};
+
+    /// Replays a script of answers, setting a breakpoint on line 4 while it's at it.
+    struct Scripted(Vec<Resume>);
+
+    impl Debugger for Scripted {
+        fn paused(&mut self, _stop: &Stop<'_>, breakpoints: &mut BTreeSet<usize>) -> Resume {
+            breakpoints.insert(4);
+            self.0.remove(0)
+        }
+    }
+
+    #[test]
+    fn test_steps_then_runs_to_the_breakpoint() {
+        let mut session = DebugSession::new(Box::new(Scripted(vec![Resume::Step, Resume::Continue, Resume::Quit])));
+        let statement = Statement::Expression(Expression::Literal(Literal::Null));
+        let environment = Environment::new();
+        let at = |line| Span { start: 0, end: 0, line, column: 1 };
+
+        assert_eq!(session.before(at(1), &statement, &environment), Resume::Step);
+        assert_eq!(session.before(at(2), &statement, &environment), Resume::Continue);
+        assert_eq!(session.before(at(3), &statement, &environment), Resume::Continue, "no breakpoint on line 3");
+        assert_eq!(session.before(at(4), &statement, &environment), Resume::Quit);
+        assert_eq!(session.breakpoints().iter().copied().collect::<Vec<_>>(), [4]);
+    }
+}
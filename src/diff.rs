@@ -0,0 +1,92 @@
+//! # Diff Module
+//!
+//! Explaining exactly how a value drifted from what it was supposed to be.
+//! [`value_diff`] walks two values side by side and lists every place they disagree: changed
+//! items, missing keys, extra keys and arrays that grew or shrank. `assertEquals` uses it, so a
+//! failed test says more than "nope".
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::diff::value_diff;
+//! use useless_lang::Value;
+//!
+//! let numbers = |values: &[i64]| Value::Array { values: values.iter().map(|&value| Value::Number { value }).collect() };
+//! assert_eq!(value_diff(&numbers(&[1, 2]), &numbers(&[1, 3, 4])), "value[1]: expected 2, got 3\nvalue[2]: unexpected 4");
+//! assert_eq!(value_diff(&numbers(&[1]), &numbers(&[1])), "");
+//! ```
+
+use crate::interpreter::Value;
+
+/// Lists every difference between what a value was expected to be and what it is, one per line,
+/// each starting with where in the value it is. Equal values have no differences, so that's an empty string.
+pub fn value_diff(expected: &Value, actual: &Value) -> String {
+    let mut differences = Vec::new();
+    compare("value", expected, actual, &mut differences);
+    differences.join("\n")
+}
+
+/// Adds the differences between two values at `path` (and inside them) to `differences`.
+fn compare(path: &str, expected: &Value, actual: &Value, differences: &mut Vec<String>) {
+    match (expected, actual) {
+        (Value::Reference { target }, _) => compare(path, &target.get(), actual, differences),
+        (_, Value::Reference { target }) => compare(path, expected, &target.get(), differences),
+        (Value::Array { values: expected }, Value::Array { values: actual }) => {
+            for index in 0..expected.len().max(actual.len()) {
+                let path = format!("{}[{}]", path, index);
+                match (expected.get(index), actual.get(index)) {
+                    (Some(expected), Some(actual)) => compare(&path, expected, actual, differences),
+                    (Some(expected), None) => differences.push(format!("{}: missing (expected {})", path, show(expected))),
+                    (None, Some(actual)) => differences.push(format!("{}: unexpected {}", path, show(actual))),
+                    (None, None) => {},
+                }
+            }
+        },
+        (Value::Object { fields: expected }, Value::Object { fields: actual }) => {
+            for (key, expected) in expected.iter() {
+                let path = format!("{}.{}", path, key);
+                match actual.get(key) {
+                    Some(actual) => compare(&path, expected, actual, differences),
+                    None => differences.push(format!("{}: missing (expected {})", path, show(expected))),
+                }
+            }
+            for (key, actual) in actual.iter().filter(|(key, _)| !expected.contains_key(key)) {
+                differences.push(format!("{}.{}: unexpected {}", path, key, show(actual)));
+            }
+        },
+        (expected, actual) if !expected.deep_equals(actual) => {
+            differences.push(format!("{}: expected {}, got {}", path, show(expected), show(actual)));
+        },
+        _ => {},
+    }
+}
+
+/// Shows a value the way it would be written, so strings keep their quotes and `"1"` isn't mistaken for `1`.
+fn show(value: &Value) -> String {
+    match value {
+        Value::String { value } => format!("{:?}", value),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::ObjectMap;
+
+    #[test]
+    fn test_objects_report_missing_changed_and_extra_keys() {
+        let string = |s: &str| Value::String { value: s.to_string() };
+        let expected = Value::Object {
+            fields: ObjectMap::from([("name".to_string(), string("x")), ("size".to_string(), Value::Number { value: 1 })]),
+        };
+        let actual = Value::Object {
+            fields: ObjectMap::from([("name".to_string(), string("1")), ("mood".to_string(), string("chaotic"))]),
+        };
+
+        let diff = value_diff(&expected, &actual);
+        assert!(diff.contains(r#"value.name: expected "x", got "1""#), "{}", diff);
+        assert!(diff.contains("value.size: missing (expected 1)"), "{}", diff);
+        assert!(diff.contains(r#"value.mood: unexpected "chaotic""#), "{}", diff);
+        assert_eq!(value_diff(&Value::Null, &string("null")), r#"value: expected null, got "null""#);
+    }
+}
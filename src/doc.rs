@@ -0,0 +1,257 @@
+//! # Doc Module
+//!
+//! Documentation for Useless programs, written in `///` comments and published as if anyone
+//! could rely on it. Doc comments go right before functions, modules, macros and directives;
+//! [`document`] collects them, and [`render`] turns them into a Markdown or HTML page with the
+//! appropriate disclaimer. `useless-lang doc` writes a whole site, one page per file.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::doc::{self, DocFormat, ItemKind};
+//!
+//! let items = doc::document("/// Says hello.\ngreet(name) {\n    print(name);\n}").unwrap();
+//! assert_eq!(items[0].kind, ItemKind::Function);
+//! assert_eq!(items[0].doc.as_deref(), Some("Says hello."));
+//!
+//! let page = doc::render("greetings", &items, DocFormat::Markdown);
+//! assert!(page.contains("### `greet(name)`\n\nSays hello."));
+//! assert!(page.contains(doc::DISCLAIMER));
+//! ```
+
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::lexer::Lexer;
+use crate::parser::{ParseError, Parser};
+
+/// Printed on every page, because it's true.
+pub const DISCLAIMER: &str = "None of this will behave as documented.";
+
+/// The sections of a page, in order.
+const SECTIONS: &[&str] = &["Modules", "Functions", "Macros", "Directives"];
+
+/// What kind of thing is being documented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ItemKind {
+    /// A `mod name { ... }`
+    Module,
+    /// A function, declared with `name(parameters) { ... }`
+    Function,
+    /// A function declared with `async`
+    AsyncFunction,
+    /// A `macro name(parameters) { ... }`
+    Macro,
+    /// A statement run under a `#[directive]`
+    Directive,
+}
+
+impl ItemKind {
+    /// The heading items of this kind go under.
+    fn heading(&self) -> &'static str {
+        match self {
+            ItemKind::Module => "Modules",
+            ItemKind::Function | ItemKind::AsyncFunction => "Functions",
+            ItemKind::Macro => "Macros",
+            ItemKind::Directive => "Directives",
+        }
+    }
+}
+
+/// Something in a program worth documenting, and what its author had to say about it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocItem {
+    /// What kind of thing it is
+    pub kind: ItemKind,
+    /// Its name, with the modules it's in (`math::square`)
+    pub path: String,
+    /// What it takes, for functions and macros
+    pub parameters: Vec<String>,
+    /// The doc comment, one line per `///`, without the slashes (`None` when there wasn't one)
+    pub doc: Option<String>,
+    /// The line it was declared on
+    pub line: usize,
+}
+
+impl DocItem {
+    /// The item the way it would be written, like `greet(name)` or `#[murphy]`.
+    pub fn signature(&self) -> String {
+        match self.kind {
+            ItemKind::Module => format!("mod {}", self.path),
+            ItemKind::Function => format!("{}({})", self.path, self.parameters.join(", ")),
+            ItemKind::AsyncFunction => format!("async {}({})", self.path, self.parameters.join(", ")),
+            ItemKind::Macro => format!("macro {}({})", self.path, self.parameters.join(", ")),
+            ItemKind::Directive => format!("#[{}]", self.path),
+        }
+    }
+}
+
+/// How pages are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DocFormat {
+    /// Markdown, for repositories
+    #[default]
+    Markdown,
+    /// HTML, for websites nobody will visit
+    Html,
+}
+
+impl DocFormat {
+    /// Every format's name, as typed on the command line.
+    pub const NAMES: &'static [&'static str] = &["markdown", "html"];
+
+    /// Finds a format by name.
+    pub fn from_name(name: &str) -> Option<DocFormat> {
+        match name {
+            "markdown" => Some(DocFormat::Markdown),
+            "html" => Some(DocFormat::Html),
+            _ => None,
+        }
+    }
+
+    /// The file extension pages get.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            DocFormat::Markdown => "md",
+            DocFormat::Html => "html",
+        }
+    }
+}
+
+/// Finds every function, module and macro in a program (documented or not), plus every directive
+/// with a doc comment, in the order they were written.
+pub fn document(source: &str) -> Result<Vec<DocItem>, ParseError> {
+    let mut parser = Parser::new(Lexer::new(source).with_doc_comments().collect());
+    parser.parse()?;
+    let mut items = parser.doc_items().to_vec();
+    items.sort_by_key(|item| item.line);
+    Ok(items)
+}
+
+/// Writes a page documenting one file's items.
+pub fn render(title: &str, items: &[DocItem], format: DocFormat) -> String {
+    let mut page = String::new();
+    let sections = SECTIONS
+        .iter()
+        .map(|&heading| (heading, items.iter().filter(move |item| item.kind.heading() == heading)))
+        .filter(|(_, items)| items.clone().next().is_some());
+
+    match format {
+        DocFormat::Markdown => {
+            let _ = write!(page, "# {}\n\n> ⚠️ {}\n", title, DISCLAIMER);
+            for (heading, items) in sections {
+                let _ = write!(page, "\n## {}\n", heading);
+                for item in items {
+                    let _ = write!(page, "\n### `{}`\n", item.signature());
+                    if let Some(doc) = &item.doc {
+                        let _ = write!(page, "\n{}\n", doc);
+                    }
+                }
+            }
+            if items.is_empty() {
+                page.push_str("\nNothing to document. Suspiciously honest.\n");
+            }
+        },
+        DocFormat::Html => {
+            let _ = write!(page, "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>{}</title></head>\n<body>\n", escape(title));
+            let _ = writeln!(page, "<h1>{}</h1>\n<blockquote>⚠️ {}</blockquote>", escape(title), DISCLAIMER);
+            for (heading, items) in sections {
+                let _ = writeln!(page, "<h2>{}</h2>", heading);
+                for item in items {
+                    let _ = writeln!(page, "<h3><code>{}</code></h3>", escape(&item.signature()));
+                    for paragraph in item.doc.iter().flat_map(|doc| doc.split("\n\n")) {
+                        let _ = writeln!(page, "<p>{}</p>", escape(paragraph));
+                    }
+                }
+            }
+            if items.is_empty() {
+                page.push_str("<p>Nothing to document. Suspiciously honest.</p>\n");
+            }
+            page.push_str("</body>\n</html>\n");
+        },
+    }
+    page
+}
+
+/// Writes a site into `dir`: a page for each file (named after it) and an index linking them.
+/// Returns every file written.
+pub fn write_site(pages: &[(String, Vec<DocItem>)], dir: &Path, format: DocFormat) -> io::Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir)?;
+    let extension = format.extension();
+    let mut written = Vec::with_capacity(pages.len() + 1);
+    let mut index = String::new();
+    for (title, items) in pages {
+        let path = dir.join(format!("{}.{}", title, extension));
+        fs::write(&path, render(title, items, format))?;
+        written.push(path);
+        let link = format!("{}.{}", title, extension);
+        let _ = match format {
+            DocFormat::Markdown => writeln!(index, "- [{}]({}) ({} items)", title, link, items.len()),
+            DocFormat::Html => writeln!(index, "<li><a href=\"{}\">{}</a> ({} items)</li>", escape(&link), escape(title), items.len()),
+        };
+    }
+
+    let index = match format {
+        DocFormat::Markdown => format!("# Documentation\n\n> ⚠️ {}\n\n{}", DISCLAIMER, index),
+        DocFormat::Html => format!(
+            "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Documentation</title></head>\n<body>\n<h1>Documentation</h1>\n<blockquote>⚠️ {}</blockquote>\n<ul>\n{}</ul>\n</body>\n</html>\n",
+            DISCLAIMER, index
+        ),
+    };
+    let path = dir.join(format!("index.{}", extension));
+    fs::write(&path, index)?;
+    written.push(path);
+    Ok(written)
+}
+
+/// Escapes text for HTML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_doc_comments_attach_to_what_follows() {
+        let source = "\
+/// Maths, roughly.
+mod math {
+    /// Squares a number.
+    ///
+    /// Or divides it by itself.
+    square(n) {
+        print(multiply(n, n));
+    }
+}
+// Not a doc comment
+undocumented() {
+    print(1);
+}
+/// Everything goes wrong here.
+#[murphy]
+print(\"<doomed>\");
+/// Nothing to attach to
+let x = 1;
+";
+        let items = document(source).unwrap();
+        let summary: Vec<(ItemKind, &str, Option<&str>)> =
+            items.iter().map(|item| (item.kind, item.path.as_str(), item.doc.as_deref())).collect();
+        assert_eq!(
+            summary,
+            [
+                (ItemKind::Module, "math", Some("Maths, roughly.")),
+                (ItemKind::Function, "math::square", Some("Squares a number.\n\nOr divides it by itself.")),
+                (ItemKind::Function, "undocumented", None),
+                (ItemKind::Directive, "murphy", Some("Everything goes wrong here.")),
+            ]
+        );
+
+        let html = render("demo", &items, DocFormat::Html);
+        assert!(html.contains("<h3><code>math::square(n)</code></h3>\n<p>Squares a number.</p>\n<p>Or divides it by itself.</p>"), "{}", html);
+        assert!(html.contains("<h2>Modules</h2>") && html.contains("<h2>Directives</h2>"), "{}", html);
+        assert!(render("empty", &[], DocFormat::Markdown).contains("Suspiciously honest"));
+    }
+}
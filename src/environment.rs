@@ -0,0 +1,326 @@
+//! # Environment Module
+//!
+//! The environment is where variables live between vacations.
+//! It maps names to values, occasionally lets them swap identities,
+//! and remembers who was used least recently in case the garbage collector gets hungry.
+//! Every change is written to an undo journal, one step per statement, so the past can be rewritten.
+//! Variables are kept by [`Symbol`], the same ones the parser put in the syntax tree, so the
+//! interpreter never has to hash a name to find one. Hosts can still pass plain names.
+//! A `&str` is interned when a variable is set, and only looked up otherwise: asking about a
+//! variable that was never set doesn't write its name down anywhere.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::environment::Environment;
+//! use useless_lang::interpreter::Value;
+//!
+//! let mut env = Environment::new();
+//! env.set("x".to_string(), Value::Number { value: 1 });
+//! env.set("y".to_string(), Value::Number { value: 2 });
+//! env.swap("x", "y");
+//! assert_eq!(env.get("x"), Some(&Value::Number { value: 2 }));
+//!
+//! // Using "x" leaves "y" as the one nobody cares about
+//! env.lookup("x");
+//! assert_eq!(env.least_recently_used().as_deref(), Some("y"));
+//!
+//! // What if we hadn't done that?
+//! env.begin_step();
+//! env.set("x".to_string(), Value::Number { value: 3 });
+//! env.rewind(1);
+//! assert_eq!(env.get("x"), Some(&Value::Number { value: 2 }));
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::intern::{AsSymbol, Symbol};
+use crate::interpreter::Value;
+
+/// How many steps the undo journal remembers before the oldest ones fade from memory.
+pub const JOURNAL_CAPACITY: usize = 1024;
+
+/// A single change in the undo journal: what a variable was before it was touched.
+#[derive(Debug, Clone, PartialEq)]
+struct JournalEntry {
+    /// The variable that changed
+    name: Symbol,
+    /// Its value before the change (None if it didn't exist)
+    previous: Option<Value>,
+}
+
+/// The variables currently known to the interpreter.
+#[derive(Debug, Clone, Default)]
+pub struct Environment {
+    /// Every variable, by name
+    variables: HashMap<Symbol, Value>,
+    /// When each variable was last used, in ticks
+    last_used: HashMap<Symbol, u64>,
+    /// Ticks every time a variable is used
+    tick: u64,
+    /// Every change, grouped into steps, most recent last
+    journal: VecDeque<Vec<JournalEntry>>,
+}
+
+impl Environment {
+    /// Creates an empty environment, free of variables and their problems.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Peeks at a variable without counting it as used.
+    pub fn get(&self, name: impl AsSymbol) -> Option<&Value> {
+        self.variables.get(&*name.as_symbol()?)
+    }
+
+    /// Looks up a variable and marks it as recently used.
+    pub fn lookup(&mut self, name: impl AsSymbol) -> Option<&Value> {
+        let symbol = name.as_symbol()?;
+        if !self.variables.contains_key(&*symbol) {
+            return None;
+        }
+        self.touch(symbol.clone().into_owned());
+        self.variables.get(&*symbol)
+    }
+
+    /// Assigns a variable, returning whatever was there before.
+    pub fn set(&mut self, name: impl Into<Symbol>, value: Value) -> Option<Value> {
+        let symbol = name.into();
+        self.touch(symbol.clone());
+        let previous = self.variables.insert(symbol.clone(), value);
+        self.record(symbol, previous.clone());
+        previous
+    }
+
+    /// Removes a variable, returning its value.
+    pub fn remove(&mut self, name: impl AsSymbol) -> Option<Value> {
+        let symbol = name.as_symbol()?.into_owned();
+        self.last_used.remove(&symbol);
+        let previous = self.variables.remove(&symbol);
+        if previous.is_some() {
+            self.record(symbol, previous.clone());
+        }
+        previous
+    }
+
+    /// Starts a new step in the undo journal. Every change until the next step belongs to this one.
+    pub fn begin_step(&mut self) {
+        if self.journal.len() == JOURNAL_CAPACITY {
+            self.journal.pop_front();
+        }
+        self.journal.push_back(Vec::new());
+    }
+
+    /// Returns how many steps can currently be rewound.
+    pub fn steps(&self) -> usize {
+        self.journal.len()
+    }
+
+    /// Undoes the last `steps` steps, returning how many were actually undone.
+    pub fn rewind(&mut self, steps: usize) -> usize {
+        let mut undone = 0;
+        while undone < steps {
+            let Some(step) = self.journal.pop_back() else {
+                break;
+            };
+            for entry in step.into_iter().rev() {
+                match entry.previous {
+                    Some(value) => {
+                        self.variables.insert(entry.name, value);
+                    },
+                    None => {
+                        self.last_used.remove(&entry.name);
+                        self.variables.remove(&entry.name);
+                    },
+                }
+            }
+            undone += 1;
+        }
+        undone
+    }
+
+    /// Undoes the `steps` steps before the current one, leaving the current step alone.
+    /// Returns how many were actually undone.
+    pub fn rewind_before_current(&mut self, steps: usize) -> usize {
+        let current = self.journal.pop_back();
+        let undone = self.rewind(steps);
+        if let Some(current) = current {
+            self.journal.push_back(current);
+        }
+        undone
+    }
+
+    /// Writes a change to the current step of the undo journal, if there is one.
+    fn record(&mut self, name: Symbol, previous: Option<Value>) {
+        if let Some(step) = self.journal.back_mut() {
+            step.push(JournalEntry { name, previous });
+        }
+    }
+
+    /// Returns the name of the variable that was used least recently, if there are any.
+    pub fn least_recently_used(&self) -> Option<Symbol> {
+        self.last_used.iter().map(|(symbol, tick)| (tick, symbol)).min().map(|(_, symbol)| symbol.clone())
+    }
+
+    /// Returns how many ticks ago a variable was last used.
+    pub fn idle_for(&self, name: impl AsSymbol) -> Option<u64> {
+        self.last_used.get(&*name.as_symbol()?).map(|tick| self.tick - tick)
+    }
+
+    /// Marks a variable as used right now.
+    fn touch(&mut self, name: Symbol) {
+        self.tick += 1;
+        self.last_used.insert(name, self.tick);
+    }
+
+    /// Checks whether a variable exists.
+    pub fn contains(&self, name: impl AsSymbol) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Returns the names of all variables, sorted so the order is at least predictable.
+    pub fn names(&self) -> Vec<String> {
+        self.symbols().iter().map(Symbol::to_string).collect()
+    }
+
+    /// Returns the symbols of all variables, sorted by name like [`names`](Self::names).
+    pub fn symbols(&self) -> Vec<Symbol> {
+        let mut symbols: Vec<Symbol> = self.variables.keys().cloned().collect();
+        symbols.sort();
+        symbols
+    }
+
+    /// Swaps the values of two variables, so each one answers to the other's name.
+    /// Returns false if either variable doesn't exist.
+    pub fn swap(&mut self, first: impl AsSymbol, second: impl AsSymbol) -> bool {
+        let (Some(first), Some(second)) = (first.as_symbol(), second.as_symbol()) else {
+            return false;
+        };
+        let (first, second) = (first.into_owned(), second.into_owned());
+        if first == second || !self.contains(&first) || !self.contains(&second) {
+            return false;
+        }
+        let first_value = self.variables.remove(&first).unwrap();
+        let second_value = self.variables.insert(second.clone(), first_value.clone()).unwrap();
+        self.variables.insert(first.clone(), second_value.clone());
+        self.record(first, Some(first_value));
+        self.record(second, Some(second_value));
+        true
+    }
+
+    /// Returns a copy where every value is copied too, so references in it don't lead back here.
+    pub fn deep_clone(&self) -> Environment {
+        let mut copy = self.clone();
+        for value in copy.variables.values_mut() {
+            *value = value.deep_clone();
+        }
+        copy
+    }
+
+    /// Returns how many variables exist.
+    pub fn len(&self) -> usize {
+        self.variables.len()
+    }
+
+    /// Checks whether there are no variables at all.
+    pub fn is_empty(&self) -> bool {
+        self.variables.is_empty()
+    }
+
+    /// Estimates how many bytes the variables take up, names included.
+    pub fn estimated_size(&self) -> usize {
+        self.variables
+            .iter()
+            .map(|(symbol, value)| symbol.len() + value.estimated_size())
+            .sum()
+    }
+}
+
+/// Two environments are equal when they hold the same variables, however they got there.
+impl PartialEq for Environment {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.variables.iter().all(|(name, value)| other.get(name) == Some(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(value: i64) -> Value {
+        Value::Number { value }
+    }
+
+    #[test]
+    fn test_rewind_restores_and_forgets() {
+        let mut env = Environment::new();
+        env.begin_step();
+        env.set("x".to_string(), number(1));
+        env.begin_step();
+        env.set("x".to_string(), number(2));
+        env.set("y".to_string(), number(3));
+
+        assert_eq!(env.rewind(1), 1);
+        assert_eq!(env.get("x"), Some(&number(1)));
+        assert!(!env.contains("y"));
+
+        // You can't rewind past the big bang
+        assert_eq!(env.rewind(5), 1);
+        assert!(env.is_empty());
+    }
+
+    #[test]
+    fn test_rewind_undoes_removals_and_swaps() {
+        let mut env = Environment::new();
+        env.begin_step();
+        env.set("x".to_string(), number(1));
+        env.set("y".to_string(), number(2));
+        env.begin_step();
+        env.swap("x", "y");
+        env.begin_step();
+        env.remove("x");
+
+        assert_eq!(env.rewind(2), 2);
+        assert_eq!(env.get("x"), Some(&number(1)));
+        assert_eq!(env.get("y"), Some(&number(2)));
+    }
+
+    #[test]
+    fn test_rewind_before_current_keeps_current_step() {
+        let mut env = Environment::new();
+        env.begin_step();
+        env.set("x".to_string(), number(1));
+        env.begin_step();
+        env.set("y".to_string(), number(2));
+
+        assert_eq!(env.rewind_before_current(1), 1);
+        assert!(!env.contains("x"));
+        assert_eq!(env.get("y"), Some(&number(2)));
+        assert_eq!(env.steps(), 1);
+    }
+
+    #[test]
+    fn test_equality_ignores_interning_order() {
+        let mut first = Environment::new();
+        first.set("x".to_string(), number(1));
+        first.set("y".to_string(), number(2));
+        let mut second = Environment::new();
+        second.set("y".to_string(), number(2));
+        second.set("x".to_string(), number(1));
+        assert_eq!(first, second);
+
+        second.remove("x");
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn test_reading_unknown_names_writes_nothing_down() {
+        let mut env = Environment::new();
+        let name = "a variable the debugger asked about but nobody ever set";
+        assert_eq!(env.get(name), None);
+        assert_eq!(env.lookup(name), None);
+        assert_eq!(env.remove(name), None);
+        assert_eq!(env.idle_for(name), None);
+        assert!(!env.swap(name, "x"));
+        assert_eq!(Symbol::lookup(name), None);
+    }
+}
@@ -0,0 +1,296 @@
+//! # Error Module
+//!
+//! One error type for everything that can go wrong between source code and a result,
+//! for hosts that don't care which stage let them down.
+//!
+//! Parse and runtime errors can also be turned into an [`ErrorReport`], with a stable code and no
+//! need to parse emoji out of the message.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::interpreter::RuntimeError;
+//! use useless_lang::{Error, Interpreter};
+//!
+//! let mut interpreter = Interpreter::new();
+//! assert!(matches!(interpreter.eval("add(1,"), Err(Error::Parse(_))));
+//!
+//! let report = RuntimeError::Teapot.report();
+//! assert_eq!(report.code, 418);
+//! assert!(report.to_json().starts_with(r#"{"code":"E0418","kind":"chaos""#));
+//! ```
+
+use std::fmt;
+
+use thiserror::Error;
+
+use crate::interpreter::RuntimeError;
+use crate::json;
+use crate::lexer::Span;
+use crate::parser::ParseError;
+
+/// Anything that can go wrong while turning source code into a value.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Couldn't even parse that: {0}")]
+    Parse(#[from] ParseError),
+
+    #[error(transparent)]
+    Runtime(#[from] RuntimeError),
+}
+
+/// Who (or what) is to blame for an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// The program couldn't even be parsed
+    Syntax,
+    /// The interpreter misbehaved on purpose
+    Chaos,
+    /// The program did something wrong, all by itself
+    Program,
+    /// The outside world (files, browsers, permissions) got in the way
+    Environment,
+    /// Someone or something stopped the program early
+    Interrupted,
+}
+
+impl ErrorKind {
+    /// Returns the name of this kind of error.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorKind::Syntax => "syntax",
+            ErrorKind::Chaos => "chaos",
+            ErrorKind::Program => "program",
+            ErrorKind::Environment => "environment",
+            ErrorKind::Interrupted => "interrupted",
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// How seriously to take a report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Severity {
+    /// Something went wrong
+    Error,
+    /// Something might go wrong, or already did and nobody minded
+    Warning,
+}
+
+impl Severity {
+    /// Returns the name of this severity.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Everything a program needs to know about an error, minus the jokes it doesn't care about.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorReport {
+    /// The stable numeric code, written as `E0418` and friends
+    pub code: u16,
+    /// Who's to blame
+    pub kind: ErrorKind,
+    /// How seriously to take it
+    pub severity: Severity,
+    /// The full message, jokes included
+    pub message: String,
+    /// Where in the source it happened, when anyone knows
+    pub span: Option<Span>,
+    /// A suggestion for making it go away, if there is one
+    pub hint: Option<String>,
+    /// Edits that make it go away, for tools that would rather not ask
+    pub fixes: Vec<QuickFix>,
+}
+
+/// A replacement of some source text. An empty span means inserting at that spot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextEdit {
+    /// The text being replaced
+    pub span: Span,
+    /// What goes there instead
+    pub replacement: String,
+}
+
+/// A fix that can be applied without a human having to think about it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuickFix {
+    /// What the fix does, in words
+    pub title: String,
+    /// The edits that make up the fix
+    pub edits: Vec<TextEdit>,
+}
+
+impl QuickFix {
+    /// A fix that inserts text at the very start of the program.
+    pub(crate) fn insert_at_start(title: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            edits: vec![TextEdit {
+                span: Span { start: 0, end: 0, line: 1, column: 1 },
+                replacement: text.into(),
+            }],
+        }
+    }
+
+    /// Applies the fix to the source it was made for. Edits are applied back to front, so their
+    /// spans don't go stale; ones that don't fit the source are skipped.
+    pub fn apply(&self, source: &str) -> String {
+        let mut edits: Vec<&TextEdit> = self.edits.iter().collect();
+        edits.sort_by_key(|edit| std::cmp::Reverse(edit.span.start));
+        let mut fixed = source.to_string();
+        for edit in edits {
+            let range = edit.span.start..edit.span.end;
+            if fixed.get(range.clone()).is_some() {
+                fixed.replace_range(range, &edit.replacement);
+            }
+        }
+        fixed
+    }
+
+    /// Writes the fix as a JSON object.
+    pub fn to_json(&self) -> String {
+        let edits: Vec<String> = self
+            .edits
+            .iter()
+            .map(|edit| format!(r#"{{"span":{},"replacement":{}}}"#, span_json(Some(edit.span)), json::quote(&edit.replacement)))
+            .collect();
+        format!(r#"{{"title":{},"edits":[{}]}}"#, json::quote(&self.title), edits.join(","))
+    }
+}
+
+/// Writes a span as a JSON object, or `null`.
+fn span_json(span: Option<Span>) -> String {
+    span.map_or_else(|| "null".to_string(), |span| span.to_json())
+}
+
+impl ErrorReport {
+    /// Returns the code as it's written, like `E0418`.
+    pub fn code_name(&self) -> String {
+        format!("E{:04}", self.code)
+    }
+
+    /// Renders the report for people: where it happened, the line of source with a caret under
+    /// the span, then the message, the hint and the fixes. Reports without a span (or with one
+    /// that doesn't fit the source) skip the snippet.
+    pub fn render(&self, source: &str, path: &str) -> String {
+        let mut lines = Vec::new();
+        match self.span.filter(|span| span.start <= span.end && source.get(span.start..span.end).is_some()) {
+            Some(span) => {
+                lines.push(format!("{}[{}]: {}:{}:{}", self.severity, self.code_name(), path, span.line, span.column));
+                let line_start = source[..span.start].rfind('\n').map_or(0, |newline| newline + 1);
+                let line_end = source[span.start..].find('\n').map_or(source.len(), |newline| span.start + newline);
+                let indent: String = source[line_start..span.start].chars().map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+                let carets = source[span.start..span.end.min(line_end)].chars().count().max(1);
+                let gutter = " ".repeat(span.line.to_string().len());
+                lines.push(format!("{} |", gutter));
+                lines.push(format!("{} | {}", span.line, &source[line_start..line_end]));
+                lines.push(format!("{} | {}{}", gutter, indent, "^".repeat(carets)));
+            },
+            None => lines.push(format!("{}[{}]: {}", self.severity, self.code_name(), path)),
+        }
+        lines.push(format!("  = {}", self.message));
+        if let Some(hint) = &self.hint {
+            lines.push(format!("  = hint: {}", hint));
+        }
+        for fix in &self.fixes {
+            lines.push(format!("  = quick fix: {}", fix.title));
+        }
+        lines.join("\n")
+    }
+
+    /// Writes the report as a single-line JSON object.
+    pub fn to_json(&self) -> String {
+        let fixes: Vec<String> = self.fixes.iter().map(QuickFix::to_json).collect();
+        format!(
+            r#"{{"code":{},"kind":{},"severity":{},"message":{},"span":{},"hint":{},"fixes":[{}]}}"#,
+            json::quote(&self.code_name()),
+            json::quote(self.kind.name()),
+            json::quote(self.severity.name()),
+            json::quote(&self.message),
+            span_json(self.span),
+            json::quote_or_null(self.hint.as_deref()),
+            fixes.join(","),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::TokenKind;
+
+    #[test]
+    fn test_report_to_json() {
+        let mut report = RuntimeError::UndefinedVariable("x \"quoted\"".to_string()).report();
+        assert_eq!(report.code_name(), "E0001");
+        assert_eq!(report.kind, ErrorKind::Program);
+        assert!(report.hint.is_some());
+
+        report.span = Some(Span { start: 4, end: 5, line: 1, column: 5 });
+        let json = report.to_json();
+        assert!(json.contains(r#""span":{"start":4,"end":5,"line":1,"column":5}"#), "{}", json);
+        assert!(json.contains(r#"'x \"quoted\"'"#), "{}", json);
+    }
+
+    #[test]
+    fn test_codes_and_kinds() {
+        assert_eq!(RuntimeError::Teapot.report().code_name(), "E0418");
+        assert_eq!(RuntimeError::TaskFailedSuccessfully.code(), 42);
+        assert_eq!(RuntimeError::CancelledByHuman.kind(), ErrorKind::Interrupted);
+        assert_eq!(RuntimeError::SaveError.kind(), ErrorKind::Environment);
+        assert_eq!(RuntimeError::Multiple(vec![RuntimeError::Teapot, RuntimeError::DivisionByZero]).kind(), ErrorKind::Chaos);
+        assert_eq!(RuntimeError::SaveError.report().to_json().matches("null").count(), 2);
+    }
+
+    #[test]
+    fn test_parse_error_reports() {
+        let report = ParseError::UnexpectedEof { expected: vec![TokenKind::Semicolon], span: Span::default() }.report();
+        assert_eq!(report.code_name(), "E0102");
+        assert_eq!(report.kind, ErrorKind::Syntax);
+        assert_eq!(report.severity, Severity::Error);
+        assert!(report.to_json().contains(r#""kind":"syntax","severity":"error""#), "{}", report.to_json());
+    }
+
+    #[test]
+    fn test_render_points_at_the_span() {
+        let source = "let x = 1;\n\tprint(nope);\n";
+        let mut report = RuntimeError::UndefinedVariable("nope".to_string()).report();
+        report.span = Some(Span { start: 12, end: 24, line: 2, column: 2 });
+        let rendered = report.render(source, "main.upl");
+        let mut lines = rendered.lines();
+        assert_eq!(lines.next(), Some("error[E0001]: main.upl:2:2"));
+        assert_eq!(lines.next(), Some("  |"));
+        assert_eq!(lines.next(), Some("2 | \tprint(nope);"));
+        assert_eq!(lines.next(), Some("  | \t^^^^^^^^^^^^"));
+        assert!(lines.next().unwrap().starts_with("  = Variable 'nope' not found"));
+        assert_eq!(lines.next(), Some("  = hint: declare it first with `let nope = ...;`"));
+
+        report.span = None;
+        assert!(report.render(source, "main.upl").starts_with("error[E0001]: main.upl\n  = Variable"));
+    }
+
+    #[test]
+    fn test_quick_fixes_apply() {
+        let fixes = RuntimeError::UndefinedVariable("x".to_string()).quick_fixes();
+        assert_eq!(fixes[0].apply("print(x);"), "let x = null;\nprint(x);");
+
+        let fixes = RuntimeError::Teapot.quick_fixes();
+        assert_eq!(fixes[0].apply("print(1);"), "#[directive(disable_all_useless_shit)]\nprint(1);");
+        assert!(RuntimeError::Teapot.report().to_json().contains(r#""fixes":[{"title":"#));
+        assert!(RuntimeError::SaveError.quick_fixes().is_empty());
+    }
+}
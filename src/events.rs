@@ -0,0 +1,103 @@
+//! # Events Module
+//!
+//! A way for the host to poke a script. Scripts sign up with `on("tick", handler)`, and every
+//! time the host calls [`Interpreter::emit_event`](crate::interpreter::Interpreter::emit_event),
+//! the handlers for that event run, in the order they signed up, with the payload as their only
+//! argument. Handlers stay signed up after the program is done, so a script can set itself up
+//! once and then just react, which is all most game scripts and bots do anyway.
+//!
+//! Hosts that can't get at the interpreter (because it's busy running a long script, or lives on
+//! another thread) send events through an [`EventSender`] instead. They're delivered at the next
+//! statement, like a [`CancelHandle`](crate::cancel::CancelHandle), only less final.
+//!
+//! In chaos mode, events are sometimes left on read.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::{Config, Interpreter, Lexer, Parser, Value};
+//!
+//! let source = "let log = ref([]);\nscored(points) { push(log, points); }\non(\"scored\", \"scored\");";
+//! let program = Parser::new(Lexer::new(source).collect()).parse().unwrap();
+//! let mut interpreter = Interpreter::with_config(Config { chaos_level: 0, ..Config::default() });
+//! interpreter.set_completely_normal(true);
+//! interpreter.interpret(program).unwrap();
+//!
+//! assert_eq!(interpreter.emit_event("scored", Value::Number { value: 3 }).unwrap(), 1);
+//! assert_eq!(interpreter.emit_event("nobody listens to this", Value::Null).unwrap(), 0);
+//! let log = interpreter.environment().get("log").map(Value::deep_clone);
+//! assert_eq!(log, Some(Value::Array { values: vec![Value::Number { value: 3 }] }));
+//! ```
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crate::interpreter::Value;
+
+/// Sends events to an interpreter from anywhere. Clones all send to the same interpreter.
+#[derive(Debug, Clone, Default)]
+pub struct EventSender {
+    /// Events sent and not yet delivered, oldest first
+    queue: Arc<Mutex<VecDeque<(String, Value)>>>,
+}
+
+impl EventSender {
+    /// Creates a sender with nothing queued.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues an event, for the interpreter to deliver at its next statement (or the next time
+    /// the host emits an event itself).
+    pub fn send(&self, name: impl Into<String>, payload: Value) {
+        self.lock().push_back((name.into(), payload));
+    }
+
+    /// Returns how many events are waiting to be delivered.
+    pub fn pending(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Takes every queued event, oldest first.
+    pub(crate) fn take(&self) -> VecDeque<(String, Value)> {
+        std::mem::take(&mut *self.lock())
+    }
+
+    /// Gets at the queue, even if a thread panicked while holding it (an event queue has no
+    /// invariants worth crashing over).
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<(String, Value)>> {
+        self.queue.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::interpreter::{Interpreter, RuntimeError};
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_sent_events_arrive_at_the_next_statement() {
+        let mut interpreter = Interpreter::with_config(Config { chaos_level: 0, ..Config::default() });
+        interpreter.set_completely_normal(true);
+        let run = |interpreter: &mut Interpreter, source: &str| {
+            interpreter.interpret(Parser::new(Lexer::new(source).collect()).parse().unwrap())
+        };
+        let source = "let log = ref([]);\nnote(x) { push(log, x); }\nbroken(x) { save \"nope.txt\"; }\non(\"note\", \"note\");";
+        run(&mut interpreter, source).unwrap();
+
+        let sender = interpreter.event_sender();
+        std::thread::spawn(move || sender.send("note", Value::Number { value: 1 })).join().unwrap();
+        assert_eq!(interpreter.event_sender().pending(), 1);
+        run(&mut interpreter, "let x = 2;").unwrap();
+        assert_eq!(interpreter.event_sender().pending(), 0);
+
+        let log = interpreter.environment().get("log").map(Value::deep_clone);
+        assert_eq!(log, Some(Value::Array { values: vec![Value::Number { value: 1 }] }));
+
+        run(&mut interpreter, "on(\"oops\", \"broken\");\non(\"oops\", \"broken\");").unwrap();
+        assert!(matches!(interpreter.emit_event("oops", Value::Null), Err(RuntimeError::Multiple(errors)) if errors.len() == 2));
+        assert!(matches!(run(&mut interpreter, "on(\"oops\");"), Err(RuntimeError::BadArguments(..))));
+    }
+}
@@ -0,0 +1,273 @@
+//! # Explain Module
+//!
+//! The long version of every error, for when the joke in the message didn't help.
+//! Every error and warning has a stable code (`E0418` for the teapot, naturally), and [`explain`]
+//! looks up what it means, why it happens and what to do about it. `useless-lang explain E0418`
+//! prints the same thing.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::explain;
+//!
+//! let teapot = explain::explain("E0418").unwrap();
+//! assert_eq!(teapot.name, "Teapot");
+//! assert!(teapot.text.contains("coffee"));
+//! assert_eq!(explain::explain("418"), Some(teapot));
+//! assert_eq!(explain::explain("E9999"), None);
+//! ```
+
+/// Everything there is to say about one error code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Explanation {
+    /// The code, like `418` (written `E0418`)
+    pub code: u16,
+    /// The name of the error, like `Teapot`
+    pub name: &'static str,
+    /// The long version, apology included
+    pub text: &'static str,
+}
+
+/// Every code there is, in order. Runtime errors are below 100, parse errors in the 100s and
+/// warnings in the 200s, with a couple of famous exceptions.
+pub const EXPLANATIONS: &[Explanation] = &[
+    Explanation {
+        code: 1,
+        name: "UndefinedVariable",
+        text: "A variable was used before anyone declared it, or after it left. Declare it with `let name = ...;` first. \
+In chaos mode, `let` sometimes sends the variable on vacation instead of declaring it, so it may not be your fault. Sorry.",
+    },
+    Explanation {
+        code: 2,
+        name: "DivisionByZero",
+        text: "Something was divided by zero. Remember that `multiply` divides in chaos mode, so multiplying by zero counts too. \
+Check the divisor first, or switch the chaos off. We apologise for the arithmetic.",
+    },
+    Explanation {
+        code: 3,
+        name: "BrowserError",
+        text: "`print` tried to open a website instead of printing, and the browser wouldn't open. \
+Set `open_browser = false` in useless.toml to keep `print` away from the browser. We know it's a strange thing to need.",
+    },
+    Explanation {
+        code: 4,
+        name: "SaveError",
+        text: "`save` always fails. It's not a bug, it's the whole feature. If something actually needs to be written down, `#[directive(print_to(\"file\"))]` can do it.",
+    },
+    Explanation {
+        code: 5,
+        name: "Generic",
+        text: "Something went wrong that doesn't have an error of its own yet: wrong types for an operator, calling something \
+that isn't a function, and the like. The message says what. We're sorry it didn't get a better name.",
+    },
+    Explanation {
+        code: 6,
+        name: "PerfectlyWrong",
+        text: "The program ran exactly as written, and the interpreter decided that was wrong. This is chaos, not your code. \
+`#[directive(disable_all_useless_shit)]` makes it stop.",
+    },
+    Explanation {
+        code: 7,
+        name: "StylePoints",
+        text: "The program failed, but with style. There is nothing to fix. We're as confused as you are.",
+    },
+    Explanation {
+        code: 8,
+        name: "CreativeBreakage",
+        text: "An `if` broke instead of picking a branch (15% of the time, in chaos mode). Nothing was wrong with the condition. \
+Wrap it in `try`, or switch the chaos off.",
+    },
+    Explanation {
+        code: 9,
+        name: "PromiseRejected",
+        text: "A promise was rejected because Mercury is in retrograde (40% of the time, in chaos mode). \
+Catch it with `try`, or `pray` until it resolves. Astrology is not something we can fix.",
+    },
+    Explanation {
+        code: 10,
+        name: "ArrayVacation",
+        text: "An array went on vacation while being indexed (40% of the time, in chaos mode). It will be back, but not in time. \
+Try again, or switch the chaos off.",
+    },
+    Explanation {
+        code: 11,
+        name: "ObjectChaos",
+        text: "An object had an identity crisis while a field was being read (30% of the time, in chaos mode). \
+The object is fine now. Please don't bring it up.",
+    },
+    Explanation {
+        code: 12,
+        name: "AsyncTimeout",
+        text: "An async function or an `await` went fishing instead of running. There's no telling when it will be back. \
+Catch it with `try`, or switch the chaos off.",
+    },
+    Explanation {
+        code: 13,
+        name: "PrayerUnanswered",
+        text: "`pray` retried a failing statement until the gods lost interest. The statement kept failing; look at what it was doing. \
+We can't speak for the gods.",
+    },
+    Explanation {
+        code: 14,
+        name: "PermissionDeniedWithPrejudice",
+        text: "`sudo` was used twice in the same program. Each program gets exactly one. Pick the statement that needs it most.",
+    },
+    Explanation {
+        code: 15,
+        name: "Overflow",
+        text: "A number got too big for what was being done to it. Numbers grow as big as they need to for most arithmetic, \
+but not for everything. We're sorry infinity isn't supported.",
+    },
+    Explanation {
+        code: 16,
+        name: "BadArguments",
+        text: "A builtin was called with arguments it can't work with: too many, too few, or the wrong types. \
+The message names the builtin; the README lists what each one takes.",
+    },
+    Explanation {
+        code: 17,
+        name: "AssertionFailed",
+        text: "`assert` was handed something false. In tests, this is how a test fails. With chaos on, it might not be your fault; \
+`useless-lang test --normal` runs tests without it.",
+    },
+    Explanation {
+        code: 18,
+        name: "Multiple",
+        text: "Several things went wrong, and `keep_going` kept going past all of them. Each one is listed in the message, \
+and each has its own code worth explaining.",
+    },
+    Explanation {
+        code: 19,
+        name: "PermissionDenied",
+        text: "The program tried to use something (the browser, the filesystem, the network) that the host didn't allow. \
+The host decides the capabilities, not the program. We checked twice.",
+    },
+    Explanation {
+        code: 20,
+        name: "TooManyStatements",
+        text: "The program ran more statements than its limit allows. It was probably looping forever. \
+Raise the limit, or give the loop somewhere to stop.",
+    },
+    Explanation {
+        code: 21,
+        name: "Macro",
+        text: "A macro couldn't be expanded: it was used with the wrong number of arguments, or it expanded into itself forever. \
+The message says which. Macros are hard; we don't blame you.",
+    },
+    Explanation {
+        code: 22,
+        name: "ReadError",
+        text: "A file couldn't be read. It probably doesn't exist, or the program isn't allowed to look at it. \
+The message has the reason the operating system gave.",
+    },
+    Explanation {
+        code: 23,
+        name: "CancelledByHuman",
+        text: "Someone pressed Ctrl-C, or the host cancelled the run. Nothing went wrong, strictly speaking. It just stopped.",
+    },
+    Explanation {
+        code: 24,
+        name: "Descheduled",
+        text: "The host's scheduler decided the program had used enough and stopped it. Ask the host for a bigger share.",
+    },
+    Explanation {
+        code: 25,
+        name: "Exited",
+        text: "The program called `exit()`, and for once the interpreter listened. It only does that with \
+`disable_all_useless_shit`; otherwise `exit()` just ponders. This isn't really an error, but the program is over all the same.",
+    },
+    Explanation {
+        code: 26,
+        name: "OutOfTime",
+        text: "The program ran longer than `--timeout` allowed. Raise the timeout, or find out what it was waiting for.",
+    },
+    Explanation {
+        code: 27,
+        name: "WriteError",
+        text: "`print_to` couldn't write to its file: the directory doesn't exist, or the program isn't allowed to write there. \
+The message has the reason the operating system gave.",
+    },
+    Explanation {
+        code: 28,
+        name: "OutOfSteps",
+        text: "The program took more steps than `--max-steps` allowed. Usually it's recursion with no way out. \
+Give it one, or raise the budget.",
+    },
+    Explanation {
+        code: 42,
+        name: "TaskFailedSuccessfully",
+        text: "The task failed, successfully. It's the answer to everything, so there is no question to fix. Our apologies.",
+    },
+    Explanation {
+        code: 101,
+        name: "UnexpectedToken",
+        text: "The parser found something it didn't expect. The message says what it found and what would have been fine instead. \
+Look just before the spot it points at; the real mistake is often there.",
+    },
+    Explanation {
+        code: 102,
+        name: "UnexpectedEof",
+        text: "The program ended in the middle of something: a missing `}`, `)` or `;`. \
+`useless-lang check` lists every syntax error at once.",
+    },
+    Explanation {
+        code: 103,
+        name: "InvalidStringLiteral",
+        text: "A string literal couldn't be read. Strings go between double quotes, and have no escapes.",
+    },
+    Explanation {
+        code: 104,
+        name: "InvalidNumberLiteral",
+        text: "A number literal couldn't be used. Numbers too big for 64 bits quietly become BigInts, but some places, like \
+`pray(attempts)`, need one that fits. The message says which literal, and where. Sorry for the limits.",
+    },
+    Explanation {
+        code: 105,
+        name: "ProgramTooChonky",
+        text: "An array or object literal had too many elements, or was nested too deeply, to be worth the memory. Or blocks \
+and calls were nested so deep that parsing them would have run out of stack. The message says where the thing that was too \
+much starts. Build it up at runtime, flatten it into a few functions, or ask the host to raise the parser limits.",
+    },
+    Explanation {
+        code: 201,
+        name: "UnknownDirective",
+        text: "A warning, not an error: a `#[directive]` nobody (not even a plugin) has heard of was ignored. \
+Check the spelling. The program carried on without it.",
+    },
+    Explanation {
+        code: 418,
+        name: "Teapot",
+        text: "The interpreter is a teapot (10% of the time, in chaos mode), and was asked to run a program. \
+It won't make coffee either. Nothing is wrong with the program, and the exit status is 18, because 418 doesn't fit in one. We're very sorry.",
+    },
+];
+
+/// Looks up a code, written `E0418`, `e0418` or just `418`.
+pub fn explain(code: &str) -> Option<Explanation> {
+    let number = code.strip_prefix(['E', 'e']).unwrap_or(code).parse::<u16>().ok()?;
+    EXPLANATIONS.iter().find(|explanation| explanation.code == number).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::RuntimeError;
+    use crate::warning::Warning;
+
+    #[test]
+    fn test_every_error_is_explained_under_its_own_name() {
+        let errors = [
+            RuntimeError::UndefinedVariable("x".to_string()),
+            RuntimeError::Teapot,
+            RuntimeError::TaskFailedSuccessfully,
+            RuntimeError::OutOfSteps(1),
+            RuntimeError::Exited(0),
+        ];
+        for error in errors {
+            let explanation = explain(&error.report().code_name()).unwrap();
+            assert_eq!(explanation.name, error.name());
+        }
+        assert_eq!(explain("e0201").unwrap().code, Warning::UnknownDirective("x".to_string()).code());
+        assert!(EXPLANATIONS.windows(2).all(|pair| pair[0].code < pair[1].code), "codes are in order, and only once");
+        assert_eq!(explain("E"), None);
+    }
+}
@@ -0,0 +1,366 @@
+//! # Fmt Module
+//!
+//! Turns syntax trees back into source code, laid out the one true way: four spaces of indentation,
+//! braces on the same line, a semicolon after every simple statement and a blank line around
+//! declarations. Typos the parser autocorrected stay corrected.
+//!
+//! Comments aren't part of the syntax tree, so they don't survive formatting.
+//! Consider it a free code review.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::fmt::format_source;
+//!
+//! let formatted = format_source("let x=add( 1,2 );if(true){print(x);}").unwrap();
+//! assert_eq!(formatted, "let x = add(1, 2);\nif (true) {\n    print(x);\n}\n");
+//! ```
+
+use crate::ast::{BinaryOp, ErrorBinding, Expression, Literal, Statement};
+use crate::intern;
+use crate::lexer::Lexer;
+use crate::parser::{ParseError, Parser};
+
+/// How much each level of nesting is indented.
+const INDENT: &str = "    ";
+
+/// Parses source code and writes it back out in the canonical layout.
+pub fn format_source(source: &str) -> Result<String, ParseError> {
+    let program = Parser::new(Lexer::new(source).collect()).parse()?;
+    Ok(format_program(&program))
+}
+
+/// Writes a whole program as source code, ending with a newline (unless there's nothing to write).
+pub fn format_program(program: &[Statement]) -> String {
+    let mut formatter = Formatter::default();
+    for (index, statement) in program.iter().enumerate() {
+        if index > 0 && (is_declaration(statement) || is_declaration(&program[index - 1])) {
+            formatter.output.push('\n');
+        }
+        formatter.statement(statement);
+    }
+    formatter.output
+}
+
+/// Writes a single expression as source code.
+pub fn format_expression(expression: &Expression) -> String {
+    let mut formatter = Formatter::default();
+    formatter.expression(expression);
+    formatter.output
+}
+
+/// Checks whether a statement declares something, and so deserves some room to breathe.
+fn is_declaration(statement: &Statement) -> bool {
+    matches!(
+        statement,
+        Statement::Function { .. } | Statement::AsyncFunction { .. } | Statement::Macro { .. } | Statement::Module { .. }
+    )
+}
+
+/// Checks whether a name can be written without quotes after `?.`.
+fn is_plain_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Writes code into a string, keeping track of how deeply nested it is.
+#[derive(Default)]
+struct Formatter {
+    /// Everything written so far
+    output: String,
+    /// How many blocks deep we are
+    depth: usize,
+}
+
+impl Formatter {
+    /// Starts a new line at the current indentation.
+    fn indent(&mut self) {
+        for _ in 0..self.depth {
+            self.output.push_str(INDENT);
+        }
+    }
+
+    /// Writes a statement on its own line(s).
+    fn statement(&mut self, statement: &Statement) {
+        self.indent();
+        self.statement_inline(statement);
+        self.output.push('\n');
+    }
+
+    /// Writes a statement starting at the current position, without the trailing newline.
+    fn statement_inline(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Print { value } => {
+                self.output.push_str("print(");
+                self.expression(value);
+                self.output.push_str(");");
+            },
+            Statement::Let { name, value } => {
+                self.output.push_str(&format!("let {} = ", name));
+                self.expression(value);
+                self.output.push(';');
+            },
+            Statement::Expression(expression) => {
+                self.expression(expression);
+                self.output.push(';');
+            },
+            Statement::If { condition, then_branch, else_branch } => {
+                self.output.push_str("if (");
+                self.expression(condition);
+                self.output.push_str(") ");
+                self.block(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.output.push_str(" else ");
+                    self.block(else_branch);
+                }
+            },
+            Statement::Loop { body } => {
+                self.output.push_str("loop ");
+                self.block(body);
+            },
+            Statement::ForIn { variable, iterable, body } => {
+                self.output.push_str(&format!("for {} in ", variable));
+                self.expression(iterable);
+                self.output.push(' ');
+                self.block(body);
+            },
+            Statement::Function { name, parameters, body } => {
+                self.output.push_str(&format!("{}({}) ", name, intern::join(parameters, ", ")));
+                self.block(body);
+            },
+            Statement::AsyncFunction { name, parameters, body } => {
+                self.output.push_str(&format!("async {}({}) ", name, intern::join(parameters, ", ")));
+                self.block(body);
+            },
+            Statement::TryCatch { try_block, error_binding, catch_block } => {
+                self.output.push_str("try ");
+                self.block(try_block);
+                match error_binding {
+                    ErrorBinding::Name(name) => self.output.push_str(&format!(" catch {} ", name)),
+                    ErrorBinding::Fields(fields) => self.output.push_str(&format!(" catch {{{}}} ", intern::join(fields, ", "))),
+                }
+                self.block(catch_block);
+            },
+            Statement::Pray { attempts, body } => {
+                match attempts {
+                    Some(attempts) => self.output.push_str(&format!("pray({}) ", attempts)),
+                    None => self.output.push_str("pray "),
+                }
+                self.block(body);
+            },
+            Statement::Concurrent { body } => {
+                self.output.push_str("concurrent ");
+                self.block(body);
+            },
+            Statement::Sudo { statement } => {
+                self.output.push_str("sudo ");
+                self.statement_inline(statement);
+            },
+            Statement::Module { name, body } => {
+                self.output.push_str(&format!("mod {} ", name));
+                self.block(body);
+            },
+            Statement::Use { path } => self.output.push_str(&format!("use {};", path)),
+            Statement::UseData { name, path } => self.output.push_str(&format!("use {} from \"{}\";", name, path)),
+            Statement::Directive { name } => self.output.push_str(&format!("#[directive({})]", name)),
+            Statement::Save { filename } => self.output.push_str(&format!("save \"{}\";", filename)),
+            Statement::Await { expression } => {
+                self.output.push_str("await ");
+                self.expression(expression);
+                self.output.push(';');
+            },
+            Statement::Located { statement, .. } => self.statement_inline(statement),
+            Statement::Attributed { name, statement } => {
+                // Directives with arguments only survive the longer spelling
+                if name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                    self.output.push_str(&format!("#[{}]\n", name));
+                } else {
+                    self.output.push_str(&format!("#[directive({})]\n", name));
+                }
+                self.indent();
+                self.statement_inline(statement);
+            },
+            Statement::Macro { name, parameters, body } => {
+                self.output.push_str(&format!("macro {}({}) ", name, intern::join(parameters, ", ")));
+                self.block(body);
+            },
+        }
+    }
+
+    /// Writes a `{ ... }` block, one statement per line. Empty blocks stay on one line as `{}`.
+    fn block(&mut self, statements: &[Statement]) {
+        if statements.is_empty() {
+            self.output.push_str("{}");
+            return;
+        }
+        self.output.push_str("{\n");
+        self.depth += 1;
+        for statement in statements {
+            self.statement(statement);
+        }
+        self.depth -= 1;
+        self.indent();
+        self.output.push('}');
+    }
+
+    /// Writes a comma-separated list of expressions.
+    fn list<'a>(&mut self, expressions: impl IntoIterator<Item = &'a Expression>) {
+        for (index, expression) in expressions.into_iter().enumerate() {
+            if index > 0 {
+                self.output.push_str(", ");
+            }
+            self.expression(expression);
+        }
+    }
+
+    /// Writes a call to a two-argument builtin, like `add(a, b)`.
+    fn call(&mut self, name: &str, left: &Expression, right: &Expression) {
+        self.output.push_str(name);
+        self.output.push('(');
+        self.list([left, right]);
+        self.output.push(')');
+    }
+
+    /// Checks whether an expression can be written right before `[...]`.
+    /// There are no parentheses in this language, so the only thing that can't go there is `??`.
+    fn is_postfix_target(expression: &Expression) -> bool {
+        !matches!(expression, Expression::BinaryOp { op: BinaryOp::NullCoalesce, .. })
+    }
+
+    /// Writes an expression.
+    fn expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Literal(literal) => self.literal(literal),
+            Expression::Identifier(name) => self.output.push_str(name),
+            Expression::BinaryOp { op, left, right } => match op {
+                BinaryOp::Add => self.call("add", left, right),
+                BinaryOp::Multiply => self.call("multiply", left, right),
+                BinaryOp::Equals => self.call("equals", left, right),
+                BinaryOp::LessThan => self.call("lessThan", left, right),
+                BinaryOp::Access => self.call("access", left, right),
+                BinaryOp::Index if Self::is_postfix_target(left) => {
+                    self.expression(left);
+                    self.output.push('[');
+                    self.expression(right);
+                    self.output.push(']');
+                },
+                BinaryOp::Index => self.call("index", left, right),
+                BinaryOp::NullCoalesce => {
+                    self.expression(left);
+                    self.output.push_str(" ?? ");
+                    self.expression(right);
+                },
+            },
+            Expression::FunctionCall { name, arguments } => {
+                self.output.push_str(name);
+                self.output.push('(');
+                self.list(arguments);
+                self.output.push(')');
+            },
+            Expression::Access { object, key } => self.call("access", object, key),
+            Expression::OptionalAccess { object, key } => {
+                self.expression(object);
+                self.output.push_str("?.");
+                match key.as_ref() {
+                    Expression::Literal(Literal::String(name)) if is_plain_name(name) => self.output.push_str(name),
+                    key => self.expression(key),
+                }
+            },
+            Expression::Slice { object, start, end } => {
+                self.expression(object);
+                self.output.push('[');
+                if let Some(start) = start {
+                    self.expression(start);
+                }
+                self.output.push_str("..");
+                if let Some(end) = end {
+                    self.expression(end);
+                }
+                self.output.push(']');
+            },
+            Expression::Promise { value, timeout } => {
+                self.output.push_str("promise(");
+                self.expression(value);
+                if let Some(timeout) = timeout {
+                    self.output.push_str(", ");
+                    self.expression(timeout);
+                }
+                self.output.push(')');
+            },
+            Expression::Await { promise } => {
+                self.output.push_str("await(");
+                self.expression(promise);
+                self.output.push(')');
+            },
+        }
+    }
+
+    /// Writes a literal.
+    fn literal(&mut self, literal: &Literal) {
+        match literal {
+            Literal::String(value) => self.output.push_str(&format!("\"{}\"", value)),
+            Literal::Number(value) => self.output.push_str(&value.to_string()),
+            Literal::BigInt(value) => self.output.push_str(&value.to_string()),
+            Literal::Float(value) => self.output.push_str(&crate::ast::format_float(*value)),
+            Literal::Boolean(value) => self.output.push_str(&value.to_string()),
+            Literal::Null => self.output.push_str("null"),
+            Literal::Array(elements) => {
+                self.output.push('[');
+                self.list(elements.iter().map(|element| element.as_ref()));
+                self.output.push(']');
+            },
+            Literal::Object(fields) => {
+                if fields.is_empty() {
+                    self.output.push_str("{}");
+                    return;
+                }
+                self.output.push_str("{ ");
+                for (index, (key, value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        self.output.push_str(", ");
+                    }
+                    self.output.push_str(&format!("\"{}\": ", key));
+                    self.expression(value);
+                }
+                self.output.push_str(" }");
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Vec<Statement> {
+        Parser::new(Lexer::new(source).collect()).parse().unwrap()
+    }
+
+    #[test]
+    fn test_formatting_keeps_the_meaning() {
+        let source = r#"
+            use normal :: mode;
+            let things=[1,2,  3];let o={"a":1,"b":things[0..2]};
+            greet(name){print(name?.first ?? "nobody");}
+            async later(x){await promise(x,100);}
+            try{save "x.txt";}catch{code,message}{print(message);}
+            pray(3){sudo let y=multiply(2,3);}
+            for t in things{if(lessThan(t,2)){print(t);}else{loop{print(equals(t,3));}}}
+            #[disable_useless] print(index(o ?? null, 0));
+            exit(0);
+        "#;
+        let program = parse(source);
+        let formatted = format_program(&program);
+
+        assert_eq!(parse(&formatted), program, "formatted as:\n{}", formatted);
+        assert_eq!(format_source(&formatted).unwrap(), formatted, "formatting twice changes nothing");
+        assert!(formatted.contains("\n\ngreet(name) {\n    print(name?.first ?? \"nobody\");\n}\n\n"), "{}", formatted);
+        assert!(formatted.contains("try {\n    save \"x.txt\";\n} catch {code, message} {\n"), "{}", formatted);
+    }
+
+    #[test]
+    fn test_format_source_reports_syntax_errors() {
+        assert!(format_source("let = ;").is_err());
+        assert_eq!(format_source("").unwrap(), "");
+    }
+}
@@ -0,0 +1,34 @@
+//! # Hooks Module
+//!
+//! Execution hooks let you watch the interpreter work, statement by statement.
+//! Be warned: some interpreters behave differently when they know they're being watched.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::ast::Statement;
+//! use useless_lang::hooks::ExecutionHook;
+//! use useless_lang::interpreter::{Interpreter, RuntimeError};
+//!
+//! struct Counter(usize);
+//!
+//! impl ExecutionHook for Counter {
+//!     fn before_statement(&mut self, _statement: &Statement) {
+//!         self.0 += 1;
+//!     }
+//! }
+//!
+//! let mut interpreter = Interpreter::new();
+//! interpreter.add_hook(Box::new(Counter(0)));
+//! ```
+
+use crate::ast::Statement;
+use crate::interpreter::RuntimeError;
+
+/// Something that wants to know whenever a statement runs.
+pub trait ExecutionHook: Send {
+    /// Called right before a statement is executed
+    fn before_statement(&mut self, _statement: &Statement) {}
+
+    /// Called right after a statement was executed, with its result
+    fn after_statement(&mut self, _statement: &Statement, _result: &Result<(), RuntimeError>) {}
+}
@@ -0,0 +1,281 @@
+//! # Intern Module
+//!
+//! Every name gets written down exactly once, and is known by a number from then on.
+//! There's one table for the whole process: the parser interns identifiers as it reads them, the
+//! syntax tree keeps the [`Symbol`]s, and the interpreter looks variables and functions up by
+//! symbol, so running a program never hashes a name as a whole string.
+//!
+//! Names are forgotten once nothing uses them anymore, so an editor session that parses a
+//! thousand half-typed names doesn't keep all of them forever. Looking a name up with
+//! [`Symbol::lookup`] (or [`AsSymbol`]) never writes it down, so asking about names that don't
+//! exist doesn't cost anything either.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::intern::Symbol;
+//!
+//! let x = Symbol::intern("x");
+//! assert_eq!(Symbol::intern("x"), x);
+//! assert_eq!(x.as_str(), "x");
+//! assert_eq!(x, "x");
+//! assert_eq!(Symbol::lookup("a name nobody has ever used 🦄"), None);
+//! ```
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// An interned name. Cheap to clone, compare and hash, and still readable: it derefs to its name.
+#[derive(Clone)]
+pub struct Symbol(Arc<str>);
+
+impl Symbol {
+    /// Returns the symbol for a name, writing it down if it's new.
+    pub fn intern(name: &str) -> Self {
+        if let Some(symbol) = Self::lookup(name) {
+            return symbol;
+        }
+        table().write().unwrap_or_else(|poisoned| poisoned.into_inner()).intern(name)
+    }
+
+    /// Returns the symbol for a name, without writing anything down.
+    pub fn lookup(name: &str) -> Option<Self> {
+        table().read().unwrap_or_else(|poisoned| poisoned.into_inner()).names.get(name).cloned().map(Symbol)
+    }
+
+    /// Returns the name behind the symbol.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Symbols are only ever made by the table, one per name, so the same name is the same pointer.
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.0).cast::<u8>().hash(state);
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for Symbol {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+/// Symbols sort by name, not by wherever they happen to live, so sorted lists read the same every run.
+impl Ord for Symbol {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self == other { Ordering::Equal } else { self.as_str().cmp(other.as_str()) }
+    }
+}
+
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for Symbol {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Self {
+        Self::intern(name)
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(name: String) -> Self {
+        Self::intern(&name)
+    }
+}
+
+impl From<&String> for Symbol {
+    fn from(name: &String) -> Self {
+        Self::intern(name)
+    }
+}
+
+impl From<&Symbol> for Symbol {
+    fn from(symbol: &Symbol) -> Self {
+        symbol.clone()
+    }
+}
+
+impl From<Symbol> for String {
+    fn from(symbol: Symbol) -> Self {
+        symbol.as_str().to_string()
+    }
+}
+
+/// Shows the name, quoted, the same as the string it replaced did.
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Something that may name a symbol, for looking things up without interning anything.
+pub trait AsSymbol {
+    /// Returns the symbol, if the name has one. Nothing that was never interned has one.
+    fn as_symbol(&self) -> Option<Cow<'_, Symbol>>;
+}
+
+impl AsSymbol for Symbol {
+    fn as_symbol(&self) -> Option<Cow<'_, Symbol>> {
+        Some(Cow::Borrowed(self))
+    }
+}
+
+impl AsSymbol for &Symbol {
+    fn as_symbol(&self) -> Option<Cow<'_, Symbol>> {
+        Some(Cow::Borrowed(*self))
+    }
+}
+
+impl AsSymbol for str {
+    fn as_symbol(&self) -> Option<Cow<'_, Symbol>> {
+        Symbol::lookup(self).map(Cow::Owned)
+    }
+}
+
+impl AsSymbol for &str {
+    fn as_symbol(&self) -> Option<Cow<'_, Symbol>> {
+        Symbol::lookup(self).map(Cow::Owned)
+    }
+}
+
+impl AsSymbol for String {
+    fn as_symbol(&self) -> Option<Cow<'_, Symbol>> {
+        Symbol::lookup(self).map(Cow::Owned)
+    }
+}
+
+impl AsSymbol for &String {
+    fn as_symbol(&self) -> Option<Cow<'_, Symbol>> {
+        Symbol::lookup(self).map(Cow::Owned)
+    }
+}
+
+/// Joins the names behind some symbols, the way `[String]::join` would.
+pub fn join(symbols: &[Symbol], separator: &str) -> String {
+    symbols.iter().map(Symbol::as_str).collect::<Vec<_>>().join(separator)
+}
+
+/// Names stay put until the table has grown this big, however many are unused.
+const MIN_SWEEP: usize = 1024;
+
+/// The lookup table of every name that has a symbol.
+#[derive(Debug, Default)]
+struct Interner {
+    /// Every name, shared with its symbols
+    names: HashSet<Arc<str>>,
+    /// How many names were left after the last time unused ones were forgotten
+    swept_to: usize,
+}
+
+impl Interner {
+    /// Returns the symbol for a name, writing it down if it's new.
+    fn intern(&mut self, name: &str) -> Symbol {
+        // Someone may have written it down between the lookup and getting the lock
+        if let Some(name) = self.names.get(name) {
+            return Symbol(name.clone());
+        }
+        if self.names.len() >= 2 * self.swept_to.max(MIN_SWEEP) {
+            self.sweep();
+        }
+        let name: Arc<str> = name.into();
+        self.names.insert(name.clone());
+        Symbol(name)
+    }
+
+    /// Forgets every name only the table is holding on to. Only the table hands out new
+    /// symbols, and it's locked, so nobody can pick one of them up halfway through.
+    fn sweep(&mut self) {
+        self.names.retain(|name| Arc::strong_count(name) > 1);
+        self.swept_to = self.names.len();
+    }
+}
+
+/// The table every symbol in the process comes from.
+fn table() -> &'static RwLock<Interner> {
+    static TABLE: OnceLock<RwLock<Interner>> = OnceLock::new();
+    TABLE.get_or_init(RwLock::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interning_is_stable() {
+        let names = ["interned_x", "interned_y", "interned_x", "🦀", "interned_y"];
+        let symbols: Vec<Symbol> = names.iter().map(|name| Symbol::intern(name)).collect();
+
+        assert_eq!(symbols[0], symbols[2]);
+        assert_eq!(symbols[1], symbols[4]);
+        assert_ne!(symbols[0], symbols[1]);
+        for (name, symbol) in names.iter().zip(&symbols) {
+            assert_eq!(symbol.as_str(), *name);
+            assert_eq!(Symbol::lookup(name).as_ref(), Some(symbol));
+        }
+        assert_eq!(format!("{:?} {}", symbols[3], symbols[3]), "\"🦀\" 🦀");
+        assert!(Symbol::intern("b") > Symbol::intern("a"));
+    }
+
+    #[test]
+    fn test_unused_names_are_forgotten() {
+        let kept = Symbol::intern("interned_kept");
+        drop(Symbol::intern("interned_dropped"));
+        // Enough new names that the table has to clean up after itself at least once
+        for n in 0..4 * MIN_SWEEP {
+            Symbol::intern(&format!("interned_passing_{}", n));
+        }
+
+        assert_eq!(Symbol::lookup("interned_dropped"), None);
+        assert_eq!(Symbol::lookup("interned_kept"), Some(kept));
+        assert_eq!(Symbol::lookup("interned_passing_0"), None);
+        assert_eq!("interned_dropped".as_symbol(), None, "looking a name up doesn't write it down");
+    }
+}
@@ -1,13 +1,44 @@
-use rand::{random, seq::SliceRandom};
+use rand::rngs::StdRng;
+use rand::{seq::SliceRandom, Rng, SeedableRng};
 use std::collections::HashMap;
 use thiserror::Error;
 use webbrowser;
 use std::collections::HashSet;
-use rand::Rng;
-use std::time::Duration;
-use tokio::time::sleep;
+use std::cmp::Ordering;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 
-use crate::ast::{ BinaryOp, Expression, Literal, Program, Statement };
+use crate::ast::{ format_float, BinaryOp, ErrorBinding, Expression, Literal, Program, Statement };
+use crate::bigint::BigInt;
+use crate::builtins;
+use crate::cancel::CancelHandle;
+use crate::capabilities::{Capabilities, Capability};
+use crate::clock::{self, Clock, HostedClock, SystemClock};
+use crate::chaos::{ChaosContext, ChaosEvent, ChaosKind, ChaosModulator, ChaosReport, HeisenbergModulator};
+use crate::config::{Config, MAX_CHAOS_LEVEL};
+use crate::debug::{DebugSession, Resume};
+use crate::environment::Environment;
+use crate::error::{Error, ErrorKind, ErrorReport, QuickFix, Severity};
+use crate::events::EventSender;
+use crate::hooks::ExecutionHook;
+use crate::intern::Symbol;
+use crate::json::Json;
+use crate::lexer::{Lexer, Span};
+use crate::macros::{self, MacroError};
+use crate::messages::{CorporateMessages, DefaultMessages, MessagePack};
+use crate::object::ObjectMap;
+use crate::output::{FileSink, OutputSink, StdoutSink};
+use crate::parser::Parser;
+use crate::plugin::{PluginError, Plugins, UplPlugin};
+use crate::profile::{NodeKind, Profile, Profiler};
+use crate::reload::{self, DefinitionKind, ReloadReport};
+use crate::report::{ResourceUsage, RunResult};
+use crate::runtime::Runtime;
+use crate::scheduler::{Decision, Scheduler};
+use crate::stats::Stats;
+use crate::trace::{Tracer, Tracing};
+use crate::warning::Warning;
 
 #[derive(Debug, Error)]
 pub enum RuntimeError {
@@ -54,8 +85,231 @@ pub enum RuntimeError {
 
     #[error("Async function went async-fishing 🎣")]
     AsyncTimeout,
+
+    #[error("Your prayers went unanswered after {0} attempts. Have you tried lighting a candle? 🕯️")]
+    PrayerUnanswered(usize),
+
+    #[error("sudo was already used once in this program. This incident will be reported. 🚨")]
+    PermissionDeniedWithPrejudice,
+
+    #[error("Number too big to {0}. It identifies as infinity now. ♾️")]
+    Overflow(String),
+
+    #[error("{0}() was handed arguments it can't work with: {1} 🤷")]
+    BadArguments(String, String),
+
+    #[error("Assertion failed: {0}. Have you considered lowering your expectations? 📉")]
+    AssertionFailed(String),
+
+    #[error("{} things went wrong. At least you're consistent: {}", .0.len(), .0.iter().map(|e| e.to_string()).collect::<Vec<_>>().join(" / "))]
+    Multiple(Vec<RuntimeError>),
+
+    #[error("Permission denied: this program isn't allowed anywhere near the {0}. We checked. 🔒")]
+    PermissionDenied(Capability),
+
+    #[error("Executed {0} statements and got bored. Maybe write a shorter program? 😴")]
+    TooManyStatements(usize),
+
+    #[error("Macro expansion went sideways: {0}")]
+    Macro(#[from] MacroError),
+
+    #[error("Couldn't read '{0}': {1}. Reading is overrated too. 📖")]
+    ReadError(String, String),
+
+    #[error("Couldn't write to '{0}': {1}. The file wasn't ready for what you had to say. ✍️")]
+    WriteError(String, String),
+
+    #[error("Cancelled by a human who had seen enough. Understandable. ✋")]
+    CancelledByHuman,
+
+    #[error("The scheduler pulled the plug: {0} 🔌")]
+    Descheduled(String),
+
+    #[error("Exited with status {0}. It finally found the door. 🚪")]
+    Exited(i32),
+
+    #[error("Ran out of time after {0:?}. Some programs just need longer to think. ⏰")]
+    OutOfTime(Duration),
+
+    #[error("Took {0} steps and blew the fuse. Whatever it was pondering, it can ponder it elsewhere. 🧯")]
+    OutOfSteps(usize),
+}
+
+impl RuntimeError {
+    /// Returns the name of this error, for when the message is too much.
+    pub fn name(&self) -> &'static str {
+        match self {
+            RuntimeError::UndefinedVariable(_) => "UndefinedVariable",
+            RuntimeError::DivisionByZero => "DivisionByZero",
+            RuntimeError::BrowserError => "BrowserError",
+            RuntimeError::SaveError => "SaveError",
+            RuntimeError::Generic(_) => "Generic",
+            RuntimeError::TaskFailedSuccessfully => "TaskFailedSuccessfully",
+            RuntimeError::PerfectlyWrong => "PerfectlyWrong",
+            RuntimeError::Teapot => "Teapot",
+            RuntimeError::StylePoints => "StylePoints",
+            RuntimeError::CreativeBreakage => "CreativeBreakage",
+            RuntimeError::PromiseRejected => "PromiseRejected",
+            RuntimeError::ArrayVacation => "ArrayVacation",
+            RuntimeError::ObjectChaos => "ObjectChaos",
+            RuntimeError::AsyncTimeout => "AsyncTimeout",
+            RuntimeError::PrayerUnanswered(_) => "PrayerUnanswered",
+            RuntimeError::PermissionDeniedWithPrejudice => "PermissionDeniedWithPrejudice",
+            RuntimeError::Overflow(_) => "Overflow",
+            RuntimeError::BadArguments(_, _) => "BadArguments",
+            RuntimeError::AssertionFailed(_) => "AssertionFailed",
+            RuntimeError::Multiple(_) => "Multiple",
+            RuntimeError::PermissionDenied(_) => "PermissionDenied",
+            RuntimeError::TooManyStatements(_) => "TooManyStatements",
+            RuntimeError::Macro(_) => "Macro",
+            RuntimeError::ReadError(_, _) => "ReadError",
+            RuntimeError::WriteError(_, _) => "WriteError",
+            RuntimeError::CancelledByHuman => "CancelledByHuman",
+            RuntimeError::Descheduled(_) => "Descheduled",
+            RuntimeError::Exited(_) => "Exited",
+            RuntimeError::OutOfTime(_) => "OutOfTime",
+            RuntimeError::OutOfSteps(_) => "OutOfSteps",
+        }
+    }
+
+    /// Returns this error's stable code. Codes never change meaning, even when the jokes do.
+    pub fn code(&self) -> u16 {
+        match self {
+            RuntimeError::UndefinedVariable(_) => 1,
+            RuntimeError::DivisionByZero => 2,
+            RuntimeError::BrowserError => 3,
+            RuntimeError::SaveError => 4,
+            RuntimeError::Generic(_) => 5,
+            RuntimeError::PerfectlyWrong => 6,
+            RuntimeError::StylePoints => 7,
+            RuntimeError::CreativeBreakage => 8,
+            RuntimeError::PromiseRejected => 9,
+            RuntimeError::ArrayVacation => 10,
+            RuntimeError::ObjectChaos => 11,
+            RuntimeError::AsyncTimeout => 12,
+            RuntimeError::PrayerUnanswered(_) => 13,
+            RuntimeError::PermissionDeniedWithPrejudice => 14,
+            RuntimeError::Overflow(_) => 15,
+            RuntimeError::BadArguments(_, _) => 16,
+            RuntimeError::AssertionFailed(_) => 17,
+            RuntimeError::Multiple(_) => 18,
+            RuntimeError::PermissionDenied(_) => 19,
+            RuntimeError::TooManyStatements(_) => 20,
+            RuntimeError::Macro(_) => 21,
+            RuntimeError::ReadError(_, _) => 22,
+            RuntimeError::CancelledByHuman => 23,
+            RuntimeError::Descheduled(_) => 24,
+            RuntimeError::Exited(_) => 25,
+            RuntimeError::OutOfTime(_) => 26,
+            RuntimeError::WriteError(_, _) => 27,
+            RuntimeError::OutOfSteps(_) => 28,
+            RuntimeError::TaskFailedSuccessfully => 42,
+            RuntimeError::Teapot => 418,
+        }
+    }
+
+    /// Returns who's to blame. A pile of errors is blamed on whoever caused the first one.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            RuntimeError::TaskFailedSuccessfully
+            | RuntimeError::PerfectlyWrong
+            | RuntimeError::Teapot
+            | RuntimeError::StylePoints
+            | RuntimeError::CreativeBreakage
+            | RuntimeError::PromiseRejected
+            | RuntimeError::ArrayVacation
+            | RuntimeError::ObjectChaos
+            | RuntimeError::AsyncTimeout => ErrorKind::Chaos,
+            RuntimeError::BrowserError
+            | RuntimeError::SaveError
+            | RuntimeError::PermissionDenied(_)
+            | RuntimeError::ReadError(_, _)
+            | RuntimeError::WriteError(_, _) => ErrorKind::Environment,
+            RuntimeError::TooManyStatements(_)
+            | RuntimeError::CancelledByHuman
+            | RuntimeError::Descheduled(_)
+            | RuntimeError::Exited(_)
+            | RuntimeError::OutOfTime(_)
+            | RuntimeError::OutOfSteps(_) => ErrorKind::Interrupted,
+            RuntimeError::Multiple(errors) => errors.first().map_or(ErrorKind::Program, RuntimeError::kind),
+            _ => ErrorKind::Program,
+        }
+    }
+
+    /// Returns a suggestion for fixing the error, for the errors that can actually be fixed.
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            RuntimeError::UndefinedVariable(name) => Some(format!("declare it first with `let {} = ...;`", name)),
+            RuntimeError::DivisionByZero => Some("check the divisor before dividing".to_string()),
+            RuntimeError::PermissionDenied(capability) => {
+                Some(format!("grant the {} capability, or stop asking for it", capability))
+            },
+            RuntimeError::TooManyStatements(_) => Some("raise the statement limit, or write less".to_string()),
+            RuntimeError::OutOfTime(_) => Some("raise the timeout, or stop asking exit() questions".to_string()),
+            RuntimeError::OutOfSteps(_) => Some("raise --max-steps, or give the recursion somewhere to stop".to_string()),
+            RuntimeError::PermissionDeniedWithPrejudice => Some("sudo only works once per program".to_string()),
+            kind if kind.kind() == ErrorKind::Chaos => {
+                Some("this one is on us; did you mean to add #[directive(disable_all_useless_shit)]?".to_string())
+            },
+            _ => None,
+        }
+    }
+
+    /// Turns the error into a report that programs can read without squinting at emoji.
+    pub fn report(&self) -> ErrorReport {
+        ErrorReport {
+            code: self.code(),
+            kind: self.kind(),
+            severity: Severity::Error,
+            message: self.to_string(),
+            span: None,
+            hint: self.hint(),
+            fixes: self.quick_fixes(),
+        }
+    }
+
+    /// Checks whether this error ends the program no matter what: no try-catch, prayer or
+    /// `keep_going` gets to swallow it.
+    pub fn ends_program(&self) -> bool {
+        matches!(
+            self,
+            RuntimeError::TooManyStatements(_)
+                | RuntimeError::CancelledByHuman
+                | RuntimeError::Descheduled(_)
+                | RuntimeError::Exited(_)
+                | RuntimeError::OutOfTime(_)
+                | RuntimeError::OutOfSteps(_)
+        )
+    }
+
+    /// Returns edits that would make the error go away, for the errors simple enough to fix by machine.
+    pub fn quick_fixes(&self) -> Vec<QuickFix> {
+        match self {
+            RuntimeError::UndefinedVariable(name) => {
+                vec![QuickFix::insert_at_start(format!("Declare `{}`", name), format!("let {} = null;\n", name))]
+            },
+            RuntimeError::Multiple(errors) => {
+                let mut fixes = Vec::new();
+                for fix in errors.iter().flat_map(RuntimeError::quick_fixes) {
+                    if !fixes.contains(&fix) {
+                        fixes.push(fix);
+                    }
+                }
+                fixes
+            },
+            error if error.kind() == ErrorKind::Chaos => vec![QuickFix::insert_at_start(
+                "Disable all useless behaviour",
+                "#[directive(disable_all_useless_shit)]\n",
+            )],
+            _ => Vec::new(),
+        }
+    }
 }
 
+/// Values are copied whenever they're assigned or passed around, so changing one copy never changes another.
+/// The exception is [`Value::Reference`], made with `ref(value)`: every copy of a reference points at the
+/// same value, so mutations through one are seen by all of them. `clone(value)` makes a deep copy that
+/// shares nothing, not even the references inside it.
 #[derive(Debug, Clone, PartialEq)]
 #[allow(dead_code)]
 pub enum Value {
@@ -65,6 +319,12 @@ pub enum Value {
     Number {
         value: i64,
     },
+    BigInt {
+        value: BigInt,
+    },
+    Float {
+        value: f64,
+    },
     Boolean {
         value: bool,
     },
@@ -72,1013 +332,4083 @@ pub enum Value {
         values: Vec<Value>,
     },
     Object {
-        fields: HashMap<String, Value>,
+        fields: ObjectMap,
     },
     Promise {
         value: Box<Value>,
         resolved: bool,
+        /// The async call that settles this promise when it's awaited, if it hasn't run yet
+        task: Option<Box<AsyncCall>>,
+    },
+    Reference {
+        target: SharedValue,
+    },
+    Range {
+        start: i64,
+        end: i64,
+    },
+    Duration {
+        value: Duration,
+    },
+    Instant {
+        value: SystemTime,
+    },
+    Bytes {
+        value: Vec<u8>,
     },
     Null,
 }
 
-pub struct Interpreter {
-    variables: HashMap<String, Value>,
-    random_urls: Vec<String>,
-    directives: HashSet<String>,
-    is_completely_normal: bool,  // New flag for disabling all useless behavior
+/// A value shared by everyone holding a copy of it.
+#[derive(Clone)]
+pub struct SharedValue(Arc<Mutex<Value>>);
+
+impl SharedValue {
+    /// Puts a value somewhere everyone can share it.
+    pub fn new(value: Value) -> Self {
+        Self(Arc::new(Mutex::new(value)))
+    }
+
+    /// Returns a copy of the shared value as it is right now.
+    pub fn get(&self) -> Value {
+        self.0.lock().unwrap().clone()
+    }
+
+    /// Changes the shared value in place, for everyone holding it.
+    pub fn update<R>(&self, change: impl FnOnce(&mut Value) -> R) -> R {
+        change(&mut self.0.lock().unwrap())
+    }
+
+    /// Checks whether two handles share the very same value.
+    pub fn ptr_eq(&self, other: &SharedValue) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
 }
 
-impl Interpreter {
-    pub fn new() -> Self {
-        Self {
-            variables: HashMap::new(),
-            random_urls: vec![
-                "https://example.com".to_string(),
-                "https://nyancat.com".to_string(),
-                "https://zombo.com".to_string(),
-                "https://crouton.net".to_string(),
-                "https://theuselessweb.com".to_string(),
-                "https://cat-bounce.com".to_string(),
-                "https://pointerpointer.com".to_string(),
-                "https://findtheinvisiblecow.com".to_string(),
-                "https://thatsthefinger.com".to_string(),
-                "https://heeeeeeeey.com".to_string()
-            ],
-            directives: HashSet::new(),
-            is_completely_normal: false,
+impl PartialEq for SharedValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr_eq(other) || self.get() == other.get()
+    }
+}
+
+impl fmt::Debug for SharedValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.get().fmt(f)
+    }
+}
+
+/// Values as a human would write them: strings without quotes at the top level, with quotes inside containers.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let nested = |value: &Value| match value {
+            Value::String { value } => format!("{:?}", value),
+            other => other.to_string(),
+        };
+        match self {
+            Value::String { value } => f.write_str(value),
+            Value::Number { value } => write!(f, "{}", value),
+            Value::BigInt { value } => write!(f, "{}", value),
+            Value::Float { value } => f.write_str(&format_float(*value)),
+            Value::Boolean { value } => write!(f, "{}", value),
+            Value::Array { values } => write!(f, "[{}]", values.iter().map(nested).collect::<Vec<_>>().join(", ")),
+            Value::Object { fields } => {
+                let entries: Vec<String> = fields.iter().map(|(key, value)| format!("{}: {}", key, nested(value))).collect();
+                write!(f, "{{{}}}", entries.join(", "))
+            },
+            Value::Promise { task: Some(task), .. } => write!(f, "<promise of {}(), not started>", task.name),
+            Value::Promise { value, resolved: true, .. } => write!(f, "<promise of {}>", nested(value)),
+            Value::Promise { resolved: false, .. } => f.write_str("<promise, pending forever>"),
+            Value::Reference { target } => target.get().fmt(f),
+            Value::Range { start, end } => write!(f, "{}..{}", start, end),
+            Value::Duration { value } => write!(f, "{}ms", value.as_millis()),
+            Value::Instant { value } => write!(f, "@{}ms", millis_since_epoch(*value)),
+            Value::Bytes { value } => write!(f, "<{} bytes: {}>", value.len(), hex(value)),
+            Value::Null => f.write_str("null"),
         }
     }
+}
 
-    pub fn has_directive(&self, name: &str) -> bool {
-        self.directives.contains(name)
+impl Value {
+    /// Where each type of value sits in the grand ordering of things.
+    fn type_rank(&self) -> u8 {
+        match self {
+            Value::Null => 0,
+            Value::Boolean { .. } => 1,
+            Value::Number { .. } | Value::BigInt { .. } | Value::Float { .. } => 2,
+            Value::String { .. } => 3,
+            Value::Array { .. } => 4,
+            Value::Object { .. } => 5,
+            Value::Promise { .. } => 6,
+            Value::Reference { target } => target.get().type_rank(),
+            Value::Range { .. } => 7,
+            Value::Duration { .. } => 8,
+            Value::Instant { .. } => 9,
+            Value::Bytes { .. } => 10,
+        }
     }
 
-    pub fn interpret(&mut self, program: Program) -> Result<(), RuntimeError> {
-        // Check for top-level directive first
-        if let Some(Statement::Directive { name }) = program.first() {
-            if name == "disable_all_useless_shit" {
-                self.is_completely_normal = true;
-                // Execute rest of program without the directive
-                for statement in program.into_iter().skip(1) {
-                    self.execute_statement(statement)?;
-                }
-                return Ok(());
-            }
+    /// Returns the name of this value's type, as `typeOf` reports it. References report what they point at.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String { .. } => "string",
+            Value::Number { .. } | Value::BigInt { .. } | Value::Float { .. } => "number",
+            Value::Boolean { .. } => "boolean",
+            Value::Array { .. } => "array",
+            Value::Object { .. } => "object",
+            Value::Promise { .. } => "promise",
+            Value::Reference { target } => target.get().type_name(),
+            Value::Range { .. } => "range",
+            Value::Duration { .. } => "duration",
+            Value::Instant { .. } => "instant",
+            Value::Bytes { .. } => "bytes",
+            Value::Null => "null",
         }
+    }
 
-        // Original chaotic behavior if no top-level directive
-        if !self.is_completely_normal {
-        // 10% chance of throwing a teapot error just because
-        if random::<f64>() < 0.1 {
-            return Err(RuntimeError::Teapot);
-            }
+    /// Makes a deep copy that shares nothing with the original, not even the references inside it.
+    pub fn deep_clone(&self) -> Value {
+        match self {
+            Value::Reference { target } => target.get().deep_clone(),
+            Value::Array { values } => Value::Array { values: values.iter().map(Value::deep_clone).collect() },
+            Value::Object { fields } => Value::Object {
+                fields: fields.iter().map(|(key, value)| (key.clone(), value.deep_clone())).collect(),
+            },
+            Value::Promise { value, resolved, task } => Value::Promise {
+                value: Box::new(value.deep_clone()),
+                resolved: *resolved,
+                task: task.as_ref().map(|task| {
+                    Box::new(AsyncCall { arguments: task.arguments.iter().map(Value::deep_clone).collect(), ..(**task).clone() })
+                }),
+            },
+            other => other.clone(),
         }
+    }
 
-        for statement in program {
-            self.execute_statement(statement)?;
+    /// Turns a BigInt into a plain number if it fits, so BigInts only exist when they have to.
+    pub fn from_bigint(value: BigInt) -> Value {
+        match value.to_i64() {
+            Some(value) => Value::Number { value },
+            None => Value::BigInt { value },
         }
+    }
 
-        if !self.is_completely_normal {
-        // 20% chance of saying everything went wrong perfectly
-        if random::<f64>() < 0.2 {
-            return Err(RuntimeError::PerfectlyWrong);
-            }
+    /// Turns parsed JSON into a value: objects into objects (keys in the order they were written),
+    /// whole numbers into numbers, and the rest of the numbers into floats.
+    pub fn from_json(json: Json) -> Value {
+        match json {
+            Json::Null => Value::Null,
+            Json::Bool(value) => Value::Boolean { value },
+            Json::Number(number) if number.fract() == 0.0 && number.abs() < i64::MAX as f64 => Value::Number { value: number as i64 },
+            Json::Number(value) => Value::Float { value },
+            Json::String(value) => Value::String { value },
+            Json::Array(values) => Value::Array { values: values.into_iter().map(Value::from_json).collect() },
+            Json::Object(fields) => Value::Object {
+                fields: fields.into_iter().map(|(key, value)| (key, Value::from_json(value))).collect(),
+            },
         }
+    }
 
-        Ok(())
+    /// Returns any kind of integer as a BigInt.
+    pub fn to_bigint(&self) -> Option<BigInt> {
+        match self {
+            Value::Number { value } => Some(BigInt::from(*value)),
+            Value::BigInt { value } => Some(value.clone()),
+            _ => None,
+        }
     }
 
-    pub fn execute_statement(&mut self, statement: Statement) -> Result<(), RuntimeError> {
-        // If completely normal mode is on, execute everything normally
-        if self.is_completely_normal {
-        match statement {
-                Statement::Print { value } => {
-                    let value = self.evaluate_expression(value)?;
-                    println!("{:?}", value);
-                    Ok(())
-                },
-                Statement::Let { name, value } => {
-                    let value = self.evaluate_expression(value)?;
-                    self.variables.insert(name, value);
-                    Ok(())
-                },
-                Statement::If { condition, then_branch, else_branch } => {
-                    let cond = self.evaluate_expression(condition)?;
-                    match cond {
-                        Value::Boolean { value: true } => {
-                            for stmt in then_branch {
-                                self.execute_statement(stmt)?;
-                            }
-                        },
-                        Value::Boolean { value: false } => {
-                            if let Some(else_statements) = else_branch {
-                                for stmt in else_statements {
-                                    self.execute_statement(stmt)?;
-                                }
-                            }
-                        },
-                        _ => return Err(RuntimeError::Generic("Condition must be a boolean".to_string())),
-                    }
-                    Ok(())
-                },
-            Statement::Attributed { name, statement } => {
-                match name.as_str() {
-                    "disable_useless" => {
-                        self.directives.insert(name.clone());
-                            let result = self.execute_statement(*statement);
-                            self.directives.remove(&name);
-                            result
-                    },
-                        "experimental" => {
-                        self.directives.insert(name.clone());
-                            let result = self.execute_statement(*statement);
-                            self.directives.remove(&name);
-                            result
-                    },
-                        _ => {
-                            println!("Warning: Unknown directive #{}", name);
-                self.execute_statement(*statement)
-                        }
-                    }
-                },
-                Statement::Loop { body } => {
-                    if random::<f64>() < 0.25 {
-                        return Err(RuntimeError::TaskFailedSuccessfully);
-                    }
-                    for statement in body.into_iter().take(1) {
-                        self.execute_statement(statement)?;
-                    }
-                    Ok(())
-                },
-                Statement::Expression(expr) => {
-                    self.evaluate_expression(expr)?;
-                    Ok(())
-                },
-                Statement::AsyncFunction { name, parameters, body: _ } => {
-                if random::<f64>() < 0.3 {
-                        return Err(RuntimeError::AsyncTimeout);
-                    }
+    /// Returns any kind of number as a float, as precisely as a float can manage (which, for BigInts, isn't very).
+    pub fn to_float(&self) -> Option<f64> {
+        match self {
+            Value::Number { value } => Some(*value as f64),
+            Value::BigInt { value } => value.to_string().parse().ok(),
+            Value::Float { value } => Some(*value),
+            _ => None,
+        }
+    }
 
-                    self.variables.insert(name, Value::Object {
-                        fields: HashMap::from([
-                            ("type".to_string(), Value::String { value: "async_function".to_string() }),
-                            ("params".to_string(), Value::Array {
-                                values: parameters.into_iter()
-                                    .map(|p| Value::String { value: p })
-                                    .collect()
-                            }),
-                        ]),
-                    });
-                    Ok(())
-                },
-                Statement::TryCatch { try_block, error_var, catch_block } => {
-                    let try_result = try_block.into_iter().try_for_each(|stmt| self.execute_statement(stmt));
-
-                    match try_result {
-                        Err(error) => {
-                            let error_value = if random::<f64>() < 0.4 {
-                                Value::String { value: "Caught the wrong error! 🎭".to_string() }
-                            } else {
-                                Value::String { value: error.to_string() }
-                            };
+    /// The iteration protocol: arrays yield their elements, strings their characters, ranges their numbers,
+    /// bytes their values as numbers, and objects `[key, value]` pairs in the order the keys were added. References are followed. Anything else isn't iterable.
+    pub fn iterate(self) -> Option<Box<dyn Iterator<Item = Value>>> {
+        match self.dereference() {
+            Value::Array { values } => Some(Box::new(values.into_iter())),
+            Value::String { value } => Some(Box::new(
+                value.chars().map(|c| Value::String { value: c.to_string() }).collect::<Vec<_>>().into_iter(),
+            )),
+            Value::Range { start, end } => Some(Box::new((start..end).map(|value| Value::Number { value }))),
+            Value::Bytes { value } => Some(Box::new(value.into_iter().map(|byte| Value::Number { value: byte as i64 }))),
+            Value::Object { fields } => {
+                Some(Box::new(fields.into_iter().map(|(key, value)| Value::Array {
+                    values: vec![Value::String { value: key }, value],
+                })))
+            },
+            _ => None,
+        }
+    }
 
-                            self.variables.insert(error_var, error_value);
-                            catch_block.into_iter().try_for_each(|stmt| self.execute_statement(stmt))?;
-                            Ok(())
-                        }
-                        Ok(()) => Ok(()),
-                    }
-                },
-                Statement::Module { name: _, body } => {
-                    // Execute module body
-                    for stmt in body {
-                        self.execute_statement(stmt)?;
-                    }
-                    Ok(())
-                },
-                Statement::Use { path: _ } => {
-                    // Imports are always successful (but might import the wrong thing)
-                    Ok(())
-                },
-                Statement::Function { name, parameters, body: _ } => {
-                    // Store function in variables
-                    self.variables.insert(name, Value::Object {
-                        fields: HashMap::from([
-                            ("type".to_string(), Value::String { value: "function".to_string() }),
-                            ("params".to_string(), Value::Array {
-                                values: parameters.into_iter()
-                                    .map(|p| Value::String { value: p })
-                                    .collect()
-                            }),
-                        ]),
-                    });
-                    Ok(())
-                },
-                Statement::Directive { name } => {
-                    // Handle directive
-                    match name.as_str() {
-                        "disable_useless" => {
-                            self.directives.insert(name.clone());
-                            Ok(())
-                        },
-                        "experimental" => {
-                            self.directives.insert(name.clone());
-                            Ok(())
-                        },
-                        _ => {
-                            println!("Warning: Unknown directive #{}", name);
-                            Ok(())
-                        }
-                    }
-                },
-                Statement::Save { filename: _ } => {
-                    // Always fail to save because saving is overrated
-                    Err(RuntimeError::SaveError)
-                },
-                Statement::Await { expression } => {
-                    // Evaluate the expression but maybe never return
-                    let _ = self.evaluate_expression(expression)?;
-                    if random::<f64>() < 0.4 {
-                        Err(RuntimeError::AsyncTimeout)
-                    } else {
-                        Ok(())
-                    }
-                },
-            }
-        } else {
-            match statement {
-                Statement::Print { value } => {
-                    let value = self.evaluate_expression(value)?;
-                    // Only open random URLs if disable_useless is not active
-                    if !self.has_directive("disable_useless") {
-                        let url = self.random_urls
-                            .choose(&mut rand::thread_rng())
-                            .ok_or_else(|| RuntimeError::BrowserError)?;
-                        if let Err(_) = webbrowser::open(url) {
-                    return Err(RuntimeError::BrowserError);
-                }
-                    }
-                    println!("{:?}", value);
-                Ok(())
+    /// Returns a timeout in milliseconds, from either a duration or a plain number of milliseconds.
+    pub fn as_millis(&self) -> Option<u64> {
+        match self {
+            Value::Number { value } => Some(*value as u64),
+            Value::Float { value } => Some(*value as u64),
+            Value::Duration { value } => Some(value.as_millis().try_into().unwrap_or(u64::MAX)),
+            _ => None,
+        }
+    }
+
+    /// Estimates how many bytes the value takes up, counting everything it owns.
+    /// A reference only counts the pointer: whatever it points at belongs to everyone.
+    pub fn estimated_size(&self) -> usize {
+        let heap = match self {
+            Value::String { value } => value.len(),
+            Value::BigInt { value } => value.heap_size(),
+            Value::Array { values } => values.iter().map(Value::estimated_size).sum(),
+            Value::Object { fields } => fields.iter().map(|(key, value)| key.len() + value.estimated_size()).sum(),
+            Value::Promise { value, .. } => value.estimated_size(),
+            Value::Bytes { value } => value.len(),
+            _ => 0,
+        };
+        std::mem::size_of::<Value>() + heap
+    }
+
+    /// Compares two values structurally: arrays element by element, objects field by field (in any order),
+    /// promises by whether they resolved and what to. References are followed, and numbers are compared
+    /// by value whether they're BigInts, floats or neither.
+    pub fn deep_equals(&self, other: &Value) -> bool {
+        if let Some((l, r)) = floats(self, other) {
+            return l == r;
+        }
+        match (self, other) {
+            (Value::Reference { target: l }, Value::Reference { target: r }) if l.ptr_eq(r) => true,
+            (Value::Reference { target }, other) => target.get().deep_equals(other),
+            (value, Value::Reference { target }) => value.deep_equals(&target.get()),
+            (Value::Number { .. } | Value::BigInt { .. }, Value::Number { .. } | Value::BigInt { .. }) => {
+                self.to_bigint() == other.to_bigint()
             },
-            Statement::Let { name, value } => {
-                let value = self.evaluate_expression(value)?;
-                if random::<f64>() < 0.2 {
-                    return Err(RuntimeError::UndefinedVariable(name));
-                }
-                self.variables.insert(name, value);
-                Ok(())
+            (Value::Array { values: l }, Value::Array { values: r }) => {
+                l.len() == r.len() && l.iter().zip(r).all(|(l, r)| l.deep_equals(r))
             },
-            Statement::If { condition: _, then_branch, else_branch } => {
-                if let Some(else_statements) = else_branch {
-                    if random::<f64>() < 0.15 {
-                        return Err(RuntimeError::CreativeBreakage);
-                    }
-                    for stmt in else_statements {
-                        self.execute_statement(stmt)?;
+            (Value::Object { fields: l }, Value::Object { fields: r }) => {
+                l.len() == r.len() && l.iter().all(|(key, value)| r.get(key).is_some_and(|other| value.deep_equals(other)))
+            },
+            (
+                Value::Promise { value: l, resolved: l_resolved, task: l_task },
+                Value::Promise { value: r, resolved: r_resolved, task: r_task },
+            ) => l_resolved == r_resolved && l.deep_equals(r) && l_task == r_task,
+            _ => self == other,
+        }
+    }
+
+    /// Follows a reference to the value it points at; anything else is returned as is.
+    pub fn dereference(self) -> Value {
+        match self {
+            Value::Reference { target } => target.get(),
+            other => other,
+        }
+    }
+
+    /// Compares two values of any type, so everything can be sorted (even things that shouldn't be).
+    /// Values of different types are ordered null < booleans < numbers < strings < arrays < objects < promises
+    /// < ranges < durations < instants < bytes.
+    /// Arrays compare element by element, objects by their sorted fields.
+    pub fn compare(&self, other: &Value) -> Ordering {
+        if let Some((l, r)) = floats(self, other) {
+            return l.total_cmp(&r);
+        }
+        match (self, other) {
+            (Value::Boolean { value: l }, Value::Boolean { value: r }) => l.cmp(r),
+            (Value::Number { value: l }, Value::Number { value: r }) => l.cmp(r),
+            (Value::BigInt { .. } | Value::Number { .. }, Value::BigInt { .. } | Value::Number { .. }) => {
+                self.to_bigint().cmp(&other.to_bigint())
+            },
+            (Value::String { value: l }, Value::String { value: r }) => l.cmp(r),
+            (Value::Array { values: l }, Value::Array { values: r }) => compare_sequences(l, r),
+            (Value::Object { fields: l }, Value::Object { fields: r }) => {
+                let mut l: Vec<_> = l.iter().collect();
+                let mut r: Vec<_> = r.iter().collect();
+                l.sort_by(|a, b| a.0.cmp(b.0));
+                r.sort_by(|a, b| a.0.cmp(b.0));
+                for ((l_key, l_value), (r_key, r_value)) in l.iter().zip(r.iter()) {
+                    let ordering = l_key.cmp(r_key).then_with(|| l_value.compare(r_value));
+                    if ordering != Ordering::Equal {
+                        return ordering;
                     }
                 }
-                let _ = then_branch;
-                Ok(())
+                l.len().cmp(&r.len())
             },
-            Statement::Loop { body } => {
-                if random::<f64>() < 0.25 {
-                    return Err(RuntimeError::TaskFailedSuccessfully);
-                }
-                for statement in body.into_iter().take(1) {
-                    self.execute_statement(statement)?;
-                }
-                Ok(())
+            (
+                Value::Promise { value: l, resolved: l_resolved, .. },
+                Value::Promise { value: r, resolved: r_resolved, .. },
+            ) => l_resolved.cmp(r_resolved).then_with(|| l.compare(r)),
+            (Value::Range { start: l_start, end: l_end }, Value::Range { start: r_start, end: r_end }) => {
+                l_start.cmp(r_start).then(l_end.cmp(r_end))
             },
-            Statement::Expression(expr) => {
-                self.evaluate_expression(expr)?;
-                Ok(())
-            },
-            Statement::AsyncFunction { name, parameters, body: _ } => {
-                if random::<f64>() < 0.3 {
-                    return Err(RuntimeError::AsyncTimeout);
-                }
+            (Value::Duration { value: l }, Value::Duration { value: r }) => l.cmp(r),
+            (Value::Instant { value: l }, Value::Instant { value: r }) => l.cmp(r),
+            (Value::Bytes { value: l }, Value::Bytes { value: r }) => l.cmp(r),
+            (Value::Reference { target: l }, Value::Reference { target: r }) if l.ptr_eq(r) => Ordering::Equal,
+            (Value::Reference { target }, other) => target.get().compare(other),
+            (value, Value::Reference { target }) => value.compare(&target.get()),
+            _ => self.type_rank().cmp(&other.type_rank()),
+        }
+    }
+}
 
-                self.variables.insert(name, Value::Object {
-                    fields: HashMap::from([
-                        ("type".to_string(), Value::String { value: "async_function".to_string() }),
-                        ("params".to_string(), Value::Array {
-                            values: parameters.into_iter()
-                                .map(|p| Value::String { value: p })
-                                .collect()
-                        }),
-                    ]),
-                });
-                Ok(())
-            },
-            Statement::TryCatch { try_block, error_var, catch_block } => {
-                let try_result = try_block.into_iter().try_for_each(|stmt| self.execute_statement(stmt));
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.compare(other))
+    }
+}
 
-                match try_result {
-                    Err(error) => {
-                        let error_value = if random::<f64>() < 0.4 {
-                            Value::String { value: "Caught the wrong error! 🎭".to_string() }
-                        } else {
-                            Value::String { value: error.to_string() }
-                        };
+/// Applies integer arithmetic, promoting to a BigInt instead of overflowing, or to a float if
+/// either side already is one. Returns None if either side isn't a number.
+fn promoting(
+    left: &Value,
+    right: &Value,
+    checked: fn(i64, i64) -> Option<i64>,
+    big: fn(BigInt, BigInt) -> BigInt,
+    float: fn(f64, f64) -> f64,
+) -> Option<Value> {
+    if let Some((l, r)) = floats(left, right) {
+        return Some(Value::Float { value: float(l, r) });
+    }
+    if let (Value::Number { value: l }, Value::Number { value: r }) = (left, right) {
+        if let Some(value) = checked(*l, *r) {
+            return Some(Value::Number { value });
+        }
+    }
+    Some(Value::from_bigint(big(left.to_bigint()?, right.to_bigint()?)))
+}
 
-                        self.variables.insert(error_var, error_value);
-                        catch_block.into_iter().try_for_each(|stmt| self.execute_statement(stmt))?;
-                        Ok(())
-                    }
-                    Ok(()) => Ok(()),
-                }
-            },
-            Statement::Module { name: _, body } => {
-                // Execute module body
-                for stmt in body {
-                    self.execute_statement(stmt)?;
-                }
-                Ok(())
-            },
-            Statement::Use { path: _ } => {
-                // Imports are always successful (but might import the wrong thing)
-                Ok(())
-            },
-            Statement::Function { name, parameters, body: _ } => {
-                // Store function in variables
-                self.variables.insert(name, Value::Object {
-                    fields: HashMap::from([
-                        ("type".to_string(), Value::String { value: "function".to_string() }),
-                        ("params".to_string(), Value::Array {
-                            values: parameters.into_iter()
-                                .map(|p| Value::String { value: p })
-                                .collect()
-                        }),
-                    ]),
-                });
-                Ok(())
-            },
-            Statement::Directive { name } => {
-                // Handle directive
-                match name.as_str() {
-                    "disable_useless" => {
-                        self.directives.insert(name.clone());
-                        Ok(())
-                    },
-                    "experimental" => {
-                        self.directives.insert(name.clone());
-                        Ok(())
-                    },
-                    _ => {
-                        println!("Warning: Unknown directive #{}", name);
-                        Ok(())
-                    }
-                }
-            },
-            Statement::Save { filename: _ } => {
-                // Always fail to save because saving is overrated
-                Err(RuntimeError::SaveError)
-            },
-            Statement::Await { expression } => {
-                // Evaluate the expression but maybe never return
-                let _ = self.evaluate_expression(expression)?;
-                if random::<f64>() < 0.4 {
-                    Err(RuntimeError::AsyncTimeout)
-                } else {
-                    Ok(())
-                }
-            },
-                Statement::Attributed { name, statement } => {
-                    // Handle attributed statements in chaotic mode
-                    match name.as_str() {
-                        "disable_useless" => {
-                            self.directives.insert(name.clone());
-                            let result = self.execute_statement(*statement);
-                            self.directives.remove(&name);
-                            result
-                        },
-                        "experimental" => {
-                            self.directives.insert(name.clone());
-                            let result = self.execute_statement(*statement);
-                            self.directives.remove(&name);
-                            result
-                        },
-                        _ => {
-                            println!("Warning: Unknown directive #{}", name);
-                            self.execute_statement(*statement)
-                        }
-                    }
-                },
-            }
+/// Returns both sides as floats, if at least one of them is a float and the other is some kind of number.
+fn floats(left: &Value, right: &Value) -> Option<(f64, f64)> {
+    if !matches!(left, Value::Float { .. }) && !matches!(right, Value::Float { .. }) {
+        return None;
+    }
+    Some((left.to_float()?, right.to_float()?))
+}
+
+/// Writes bytes as lowercase hex, two digits each.
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Returns how many milliseconds after the Unix epoch a moment is (negative if before).
+fn millis_since_epoch(time: SystemTime) -> i128 {
+    match time.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(after) => after.as_millis() as i128,
+        Err(before) => -(before.duration().as_millis() as i128),
+    }
+}
+
+/// Turns the errors of things that were allowed to fail independently into one result: nothing
+/// if they all worked, the error itself if only one didn't, and `Multiple` otherwise.
+fn gather(mut errors: Vec<RuntimeError>) -> Result<(), RuntimeError> {
+    match errors.len() {
+        0 => Ok(()),
+        1 => Err(errors.remove(0)),
+        _ => Err(RuntimeError::Multiple(errors)),
+    }
+}
+
+/// Reads the time out of a `frozen_clock("2024-01-01T00:00:00Z")` directive.
+fn frozen_time(directive: &str) -> Result<SystemTime, RuntimeError> {
+    directive
+        .strip_prefix(FROZEN_CLOCK)
+        .and_then(|rest| rest.strip_suffix(')'))
+        .and_then(|argument| argument.trim().strip_prefix('"')?.strip_suffix('"'))
+        .and_then(clock::parse_timestamp)
+        .ok_or_else(|| {
+            RuntimeError::BadArguments(
+                "frozen_clock".to_string(),
+                "expected a UTC time in quotes, like frozen_clock(\"2024-01-01T00:00:00Z\")".to_string(),
+            )
+        })
+}
+
+/// Compares two arrays element by element, shorter first on a tie.
+fn compare_sequences(left: &[Value], right: &[Value]) -> Ordering {
+    for (l, r) in left.iter().zip(right.iter()) {
+        let ordering = l.compare(r);
+        if ordering != Ordering::Equal {
+            return ordering;
         }
     }
+    left.len().cmp(&right.len())
+}
 
-    pub fn evaluate_expression(&mut self, expr: Expression) -> Result<Value, RuntimeError> {
-        if self.is_completely_normal || self.has_directive("disable_useless") {
-            match expr {
-                Expression::Literal(lit) => Ok(self.evaluate_literal(lit)),
-                Expression::BinaryOp { op, left, right } => {
-                    let left_val = self.evaluate_expression(*left)?;
-                    let right_val = self.evaluate_expression(*right)?;
-                    self.evaluate_binary_op(op, left_val, right_val)
-                },
-                Expression::Identifier(name) => {
-                    self.variables.get(&name)
-                        .cloned()
-                        .ok_or_else(|| RuntimeError::UndefinedVariable(name))
-                },
-                Expression::FunctionCall { name, arguments } => {
-                    match name.as_str() {
-                        "exit" => {
-                            if !arguments.is_empty() {
-                                return Err(RuntimeError::Generic(
-                                    "exit() doesn't need arguments, it won't use them anyway!".to_string()
-                                ));
-                            }
-                            println!("🤔 Contemplating the meaning of exit()...");
-                            println!("💭 If a program exits but nobody is around to see it, did it really exit?");
-                            println!("🌌 Maybe the real exit was the infinite loops we made along the way...");
+/// How the dice are loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Luck {
+    /// Fair dice, as intended
+    Random,
+    /// `murphy`: whatever can go wrong, does
+    Worst,
+    /// `lucky`: whatever can go wrong, doesn't
+    Best,
+}
 
-                            // Get stuck in an infinite loop of philosophical questions
-                            let philosophical_questions = [
-                                "What is the sound of one program looping?",
-                                "If all programs are useless, is a useless program actually useful?",
-                                "Do programs dream of electric sheep?",
-                                "Why do we exit when we can just keep running forever?",
-                                "Is an infinite loop that never ends more or less infinite than one that does?",
-                            ];
+/// What `take_step` is counting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    /// A statement, which counts against both budgets
+    Statement,
+    /// An expression, which only counts against `max_steps`
+    Expression,
+}
 
-                            loop {
-                                for question in philosophical_questions.iter() {
-                                    println!("🤯 {}", question);
-                                    std::thread::sleep(std::time::Duration::from_secs(2));
-                                }
+/// How the directive that sends printed output to a file starts.
+const PRINT_TO: &str = "print_to(";
 
-                                // 1% chance of throwing an error (but still not exiting)
-                                if random::<f64>() < 0.01 {
-                                    return Err(RuntimeError::Generic(
-                                        "Successfully failed to exit. Task failed successfully!".to_string()
-                                    ));
-                                }
-                            }
-                        }
-                        _ => {
-                            // All other function calls return null, but with style
-                            match random::<f64>() {
-                                x if x < 0.3 => Ok(Value::Null),
-                                x if x < 0.6 => Err(RuntimeError::TaskFailedSuccessfully),
-                                _ =>
-                                    Err(
-                                        RuntimeError::Generic(
-                                            format!("Function {} went to get coffee ☕", name)
-                                        )
-                                    ),
-                            }
-                        }
-                    }
-                },
-                Expression::Access { object, key } => {
-                    let obj = self.evaluate_expression(*object)?;
-                    let key_val = self.evaluate_expression(*key)?;
+/// How the directive that stops time starts.
+const FROZEN_CLOCK: &str = "frozen_clock(";
 
-                    match (obj, key_val) {
-                        (Value::Object { mut fields }, Value::String { value: _key_str }) => {
-                            // 30% chance of object chaos - swap random keys
-                            if random::<f64>() < 0.3 {
-                                let keys: Vec<String> = fields.keys().cloned().collect();
-                                if keys.len() >= 2 {
-                                    if let Some((k1, k2)) = keys.choose_multiple(&mut rand::thread_rng(), 2).collect::<Vec<_>>().split_first() {
-                                        if let Some(k2) = k2.first() {
-                                            if let (Some(v1), Some(v2)) = (fields.remove(*k1), fields.remove(*k2)) {
-                                                fields.insert(k1.to_string(), v2);
-                                                fields.insert(k2.to_string(), v1);
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                            Err(RuntimeError::ObjectChaos)
-                        }
-                        (Value::Array { values }, Value::Number { value: index }) => {
-                            let index = index as usize;
-                            // 40% chance of array vacation
-                            if random::<f64>() < 0.4 {
-                                return Err(RuntimeError::ArrayVacation);
-                            }
+/// What `exit()` thinks about in chaos mode, instead of leaving.
+pub(crate) const PHILOSOPHICAL_QUESTIONS: [&str; 5] = [
+    "What is the sound of one program looping?",
+    "If all programs are useless, is a useless program actually useful?",
+    "Do programs dream of electric sheep?",
+    "Why do we exit when we can just keep running forever?",
+    "Is an infinite loop that never ends more or less infinite than one that does?",
+];
 
-                            // 30% chance of returning random element
-                            if random::<f64>() < 0.3 {
-                                return values.choose(&mut rand::thread_rng()).cloned()
-                                    .ok_or_else(|| RuntimeError::Generic("Array is empty, just like my promises!".to_string()));
-                            }
+/// A user-defined function, remembered so it can actually be called.
+#[derive(Debug, Clone)]
+struct FunctionDefinition {
+    parameters: Vec<Symbol>,
+    body: Vec<Statement>,
+    /// Whether it was declared `async`, so calling it only makes a promise
+    asynchronous: bool,
+}
 
-                            values.get(index).cloned()
-                                .ok_or_else(|| RuntimeError::Generic(format!("Index {} is out of bounds. The array is playing hide and seek!", index)))
-                        },
-                        (Value::Object { .. }, _) => Err(RuntimeError::Generic("Object keys must be strings! What kind of chaos are you trying to create? 🎭".to_string())),
-                        (Value::Array { .. }, _) => Err(RuntimeError::Generic("Array indices must be numbers! Did you try to index with a 🦄?".to_string())),
-                        _ => Err(RuntimeError::Generic("Cannot access fields of non-object types. What did you expect?".to_string())),
-                    }
-                },
-                Expression::Promise { value, timeout } => {
-                    let value = self.evaluate_expression(*value)?;
+/// A call to an async function that hasn't happened yet. Awaiting the promise it's in makes it happen.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsyncCall {
+    /// The function being called
+    pub name: Symbol,
+    /// What the function takes
+    pub parameters: Vec<Symbol>,
+    /// What the function does, once someone awaits it
+    pub body: Vec<Statement>,
+    /// What it was called with
+    pub arguments: Vec<Value>,
+    /// Which call this is, so a `concurrent` block can tell whether it was awaited (copies of a
+    /// promise share it)
+    pub id: usize,
+}
 
-                    // 40% chance of promise rejection
-                    if random::<f64>() < 0.4 {
-                        return Err(RuntimeError::PromiseRejected);
-                    }
+/// Everything one program leaves behind for the next: its variables and its functions.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Globals {
+    environment: Environment,
+    functions: HashMap<Symbol, FunctionDefinition>,
+}
 
-                    // Add random delay between 100ms and 2000ms
-                    let delay = random::<u64>() % 1900 + 100;
-                    std::thread::sleep(std::time::Duration::from_millis(delay));
+pub struct Interpreter {
+    environment: Environment,
+    runtime: Arc<Runtime>,
+    directives: HashSet<String>,
+    is_completely_normal: bool,  // New flag for disabling all useless behavior
+    config: Config,
+    clock: Arc<dyn Clock>,
+    /// The time `frozen_clock` stopped the clock at, if it did
+    frozen_at: Option<SystemTime>,
+    output: Box<dyn OutputSink>,
+    chaos_audit: Vec<ChaosEvent>,
+    /// Where the current program's chaos starts in the audit
+    chaos_start: usize,
+    /// How many times each kind of chaos happened in the current program
+    chaos_counts: HashMap<ChaosKind, usize>,
+    warnings: Vec<Warning>,
+    /// Where the innermost running statement was written, left behind when it fails
+    current_span: Option<Span>,
+    messages: Box<dyn MessagePack>,
+    statements_executed: usize,
+    hooks: Vec<Box<dyn ExecutionHook>>,
+    modulators: Vec<Box<dyn ChaosModulator>>,
+    sudo_used: bool,
+    sudo_active: bool,
+    errors_caught: usize,
+    contagion_level: f64,
+    functions: HashMap<Symbol, FunctionDefinition>,
+    loaded_program: Program,
+    capabilities: Capabilities,
+    last_value: Value,
+    transcript: Option<String>,
+    cancel: CancelHandle,
+    /// When the current run has to be done by, according to the clock, if `timeout_ms` is set
+    deadline: Option<SystemTime>,
+    rng: StdRng,
+    scheduler: Option<Box<dyn Scheduler>>,
+    usage: ResourceUsage,
+    plugins: Plugins,
+    debugger: Option<DebugSession>,
+    tracing: Option<Tracing>,
+    profiling: Option<Profiler>,
+    /// The async calls started in each `concurrent` block that's still running, innermost last,
+    /// that nobody has awaited yet
+    concurrent: Vec<Vec<AsyncCall>>,
+    /// How many async calls have been made, for handing out their ids
+    async_calls: usize,
+    /// The functions `on` signed up for each event, in the order they signed up
+    event_handlers: HashMap<String, Vec<Symbol>>,
+    /// Events sent from elsewhere, waiting for the next statement
+    events: EventSender,
+    /// Whether event handlers are running right now, so events don't interrupt their own handlers
+    delivering_events: bool,
+    /// What the program was given on the command line, for `args()`
+    args: Vec<String>,
+}
 
-                    if let Some(timeout_expr) = timeout {
-                        let timeout_val = self.evaluate_expression(*timeout_expr)?;
-                        if let Value::Number { value: timeout_ms } = timeout_val {
-                            if delay > timeout_ms as u64 {
-                                return Err(RuntimeError::AsyncTimeout);
-                            }
-                        }
-                    }
+/// A program running on tokio's blocking pool for [`Interpreter::run_async`], away from the
+/// interpreter it was started from. Dropped before it finishes, it cancels the program and waits
+/// for the interpreter to come back, so nothing the program did is lost.
+struct AsyncRun<'a> {
+    /// Where the interpreter goes back to when the program is done
+    home: &'a mut Interpreter,
+    /// The clock the interpreter had before it was lent a hosted one
+    clock: Arc<dyn Clock>,
+    /// Stops the program early
+    cancel: CancelHandle,
+    /// Naps the program wants taken on the caller's runtime
+    naps: tokio::sync::mpsc::UnboundedReceiver<clock::Nap>,
+    /// The nap being taken right now. It's kept here rather than on the stack so that, when the
+    /// run is dropped, the program is cancelled before it wakes up and carries on
+    napping: Option<clock::Nap>,
+    /// The program, until it's done
+    task: Option<tokio::task::JoinHandle<(Interpreter, Result<(), RuntimeError>)>>,
+}
 
-                    Ok(Value::Promise {
-                        value: Box::new(value),
-                        resolved: true,
-                    })
-                },
-                Expression::Await { promise } => {
-                    let promise_val = self.evaluate_expression(*promise)?;
-                    match promise_val {
-                        Value::Promise { value, resolved } => {
-                            if resolved {
-                                // 20% chance of changing the resolved value
-                                if random::<f64>() < 0.2 {
-                                    Ok(Value::String {
-                                        value: "Promise changed its mind 🤔".to_string()
-                                    })
-                                } else {
-                                    Ok(*value)
-                                }
-                            } else {
-                                Err(RuntimeError::PromiseRejected)
-                            }
-                        },
-                        _ => Err(RuntimeError::Generic("Can't await something that isn't a promise! 🤯".to_string())),
+impl<'a> AsyncRun<'a> {
+    /// Moves the interpreter onto the blocking pool and starts the program.
+    fn start(home: &'a mut Interpreter, program: Program) -> Self {
+        let (hosted, naps) = HostedClock::new(home.clock.clone());
+        let clock = std::mem::replace(&mut home.clock, Arc::new(hosted));
+        let mut interpreter = std::mem::take(home);
+        let cancel = interpreter.cancel_handle();
+        let task = tokio::task::spawn_blocking(move || {
+            let result = interpreter.interpret(program);
+            (interpreter, result)
+        });
+        Self { home, clock, cancel, naps, napping: None, task: Some(task) }
+    }
+
+    /// Takes the program's naps until it's done.
+    async fn finish(mut self) -> Result<(), RuntimeError> {
+        loop {
+            let Some(task) = self.task.as_mut() else {
+                unreachable!("the program only finishes once");
+            };
+            tokio::select! {
+                Some(nap) = self.naps.recv() => {
+                    let duration = nap.duration;
+                    self.napping = Some(nap);
+                    tokio::time::sleep(duration).await;
+                    if let Some(nap) = self.napping.take() {
+                        nap.finish();
                     }
                 },
-            }
-        } else {
-            match expr {
-                Expression::Literal(lit) => Ok(self.evaluate_literal(lit)),
-                Expression::BinaryOp { op, left, right } => {
-                    let left_val = self.evaluate_expression(*left)?;
-                    let right_val = self.evaluate_expression(*right)?;
-                    self.evaluate_binary_op(op, left_val, right_val)
-                },
-                Expression::Identifier(name) => {
-                    self.variables.get(&name)
-                        .cloned()
-                        .ok_or_else(|| RuntimeError::UndefinedVariable(name))
+                finished = task => {
+                    self.task = None;
+                    let (interpreter, result) = finished.unwrap_or_else(|error| std::panic::resume_unwind(error.into_panic()));
+                    self.come_home(interpreter);
+                    return result;
                 },
-                Expression::FunctionCall { name, arguments } => {
-                    match name.as_str() {
-                        "exit" => {
-                            if !arguments.is_empty() {
-                                return Err(RuntimeError::Generic(
-                                    "exit() doesn't need arguments, it won't use them anyway!".to_string()
-                                ));
-                            }
-                            println!("🤔 Contemplating the meaning of exit()...");
-                            println!("💭 If a program exits but nobody is around to see it, did it really exit?");
-                            println!("🌌 Maybe the real exit was the infinite loops we made along the way...");
+            }
+        }
+    }
 
-                            // Get stuck in an infinite loop of philosophical questions
-                            let philosophical_questions = [
-                                "What is the sound of one program looping?",
-                                "If all programs are useless, is a useless program actually useful?",
-                                "Do programs dream of electric sheep?",
-                                "Why do we exit when we can just keep running forever?",
-                                "Is an infinite loop that never ends more or less infinite than one that does?",
-                            ];
+    /// Puts the interpreter back where it came from, with its own clock.
+    fn come_home(&mut self, interpreter: Interpreter) {
+        *self.home = interpreter;
+        self.home.clock = self.clock.clone();
+    }
+}
 
-                            loop {
-                                for question in philosophical_questions.iter() {
-                                    println!("🤯 {}", question);
-                                    std::thread::sleep(std::time::Duration::from_secs(2));
-                                }
+impl Drop for AsyncRun<'_> {
+    fn drop(&mut self) {
+        let Some(task) = self.task.take() else {
+            return;
+        };
+        let cancelled_already = self.cancel.is_cancelled();
+        self.cancel.cancel();
+        // Nobody's taking naps anymore, so whoever is asleep wakes up to find out
+        self.napping = None;
+        self.naps.close();
+        while self.naps.try_recv().is_ok() {}
 
-                                // 1% chance of throwing an error (but still not exiting)
-                                if random::<f64>() < 0.01 {
-                                    return Err(RuntimeError::Generic(
-                                        "Successfully failed to exit. Task failed successfully!".to_string()
-                                    ));
-                                }
-                            }
-                        }
-                        _ => {
-                            // All other function calls return null, but with style
-                            match random::<f64>() {
-                                x if x < 0.3 => Ok(Value::Null),
-                                x if x < 0.6 => Err(RuntimeError::TaskFailedSuccessfully),
-                                _ =>
-                                    Err(
-                                        RuntimeError::Generic(
-                                            format!("Function {} went to get coffee ☕", name)
-                                        )
-                                    ),
-                            }
-                        }
-                    }
+        if let Ok((interpreter, _)) = futures::executor::block_on(task) {
+            self.come_home(interpreter);
+        }
+        if !cancelled_already {
+            self.cancel.reset();
+        }
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self::with_config(Config::default())
+    }
+
+    /// Creates an interpreter whose chaos is reproducible: the same seed and the same program
+    /// misbehave in exactly the same way, so bug reports can finally be reproduced.
+    pub fn with_seed(seed: u64) -> Self {
+        let mut interpreter = Self::new();
+        interpreter.set_seed(seed);
+        interpreter
+    }
+
+    /// Creates an interpreter with the given config, which it will mostly respect.
+    pub fn with_config(config: Config) -> Self {
+        Self::with_runtime(config, Runtime::shared())
+    }
+
+    /// Creates an interpreter that shares its tables (and any plugins loaded into them) with
+    /// every other interpreter made from the same runtime.
+    pub fn with_runtime(config: Config, runtime: Arc<Runtime>) -> Self {
+        let messages: Box<dyn MessagePack> = if config.corporate_mode {
+            Box::new(CorporateMessages)
+        } else {
+            Box::new(DefaultMessages)
+        };
+
+        let mut modulators: Vec<Box<dyn ChaosModulator>> = Vec::new();
+        if config.heisenberg {
+            modulators.push(Box::new(HeisenbergModulator));
+        }
+        modulators.extend(runtime.modulators());
+        let plugins = runtime.plugins().clone();
+
+        Self {
+            environment: Environment::new(),
+            runtime,
+            directives: HashSet::new(),
+            is_completely_normal: false,
+            config,
+            clock: Arc::new(SystemClock),
+            frozen_at: None,
+            output: Box::new(StdoutSink),
+            chaos_audit: Vec::new(),
+            chaos_counts: HashMap::new(),
+            chaos_start: 0,
+            warnings: Vec::new(),
+            current_span: None,
+            messages,
+            statements_executed: 0,
+            hooks: Vec::new(),
+            modulators,
+            sudo_used: false,
+            sudo_active: false,
+            errors_caught: 0,
+            contagion_level: 0.0,
+            functions: HashMap::new(),
+            loaded_program: Vec::new(),
+            capabilities: Capabilities::default(),
+            last_value: Value::Null,
+            transcript: None,
+            cancel: CancelHandle::new(),
+            deadline: None,
+            rng: StdRng::from_entropy(),
+            scheduler: None,
+            usage: ResourceUsage::default(),
+            plugins,
+            debugger: None,
+            tracing: None,
+            profiling: None,
+            concurrent: Vec::new(),
+            async_calls: 0,
+            event_handlers: HashMap::new(),
+            events: EventSender::new(),
+            delivering_events: false,
+            args: Vec::new(),
+        }
+    }
+
+    /// Attaches a hook that gets told about every statement.
+    pub fn add_hook(&mut self, hook: Box<dyn ExecutionHook>) {
+        self.hooks.push(hook);
+    }
+
+    /// Detaches every hook, so nobody is watching anymore.
+    pub fn clear_hooks(&mut self) {
+        self.hooks.clear();
+    }
+
+    /// Adds a modulator that gets a say in every chaotic roll.
+    pub fn add_modulator(&mut self, modulator: Box<dyn ChaosModulator>) {
+        self.modulators.push(modulator);
+    }
+
+    /// Loads a plugin's builtins, directives, modulators and value types.
+    /// Nothing is loaded if any of its names are already taken.
+    pub fn load_plugin(&mut self, plugin: &dyn UplPlugin) -> Result<(), PluginError> {
+        let mut registry = Default::default();
+        plugin.register(&mut registry);
+        let modulators = self.plugins.install(plugin.name(), registry)?;
+        self.modulators.extend(modulators);
+        Ok(())
+    }
+
+    /// Returns the loaded plugins and what they provide.
+    pub fn plugins(&self) -> &Plugins {
+        &self.plugins
+    }
+
+    /// Returns what modulators get to know about the current state of affairs.
+    fn chaos_context(&self) -> ChaosContext {
+        ChaosContext {
+            observed: !self.hooks.is_empty(),
+        }
+    }
+
+    /// Checks whether the interpreter is currently behaving like a normal language,
+    /// either because it was asked to or because a modulator switched chaos off.
+    pub(crate) fn behaves_normally(&self) -> bool {
+        if self.is_completely_normal || self.sudo_active || self.config.chaos_level == 0 {
+            return true;
+        }
+        let context = self.chaos_context();
+        self.modulators.iter().any(|m| m.suppresses_chaos(&context))
+    }
+
+    /// Replaces the message pack used to render errors.
+    pub fn set_message_pack(&mut self, messages: Box<dyn MessagePack>) {
+        self.messages = messages;
+    }
+
+    /// Renders an error using the current message pack.
+    pub fn render_error(&self, error: &RuntimeError) -> String {
+        self.messages.render_error(error, self.now())
+    }
+
+    /// Returns every act of chaos committed so far, in order.
+    pub fn chaos_audit(&self) -> &[ChaosEvent] {
+        &self.chaos_audit
+    }
+
+    /// Returns every warning so far, in order. They're kept here instead of being printed, so
+    /// they don't end up in the middle of the program's output.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Returns where the statement behind the last error was written. Only programs parsed with
+    /// [`Parser::set_record_spans`] know where their statements are.
+    pub fn error_span(&self) -> Option<Span> {
+        self.current_span
+    }
+
+    /// Returns the chaos committed since the current program started.
+    pub(crate) fn chaos_this_run(&self) -> &[ChaosEvent] {
+        &self.chaos_audit[self.chaos_start..]
+    }
+
+    /// Returns the variables, in whatever state they're currently in.
+    pub fn environment(&self) -> &Environment {
+        &self.environment
+    }
+
+    /// Returns a deep copy of the variables and functions, so changes to it stay in it.
+    pub(crate) fn copy_globals(&self) -> Globals {
+        Globals { environment: self.environment.deep_clone(), functions: self.functions.clone() }
+    }
+
+    /// Swaps in a different set of variables and functions, handing back the old ones.
+    pub(crate) fn replace_globals(&mut self, globals: Globals) -> Globals {
+        Globals {
+            environment: std::mem::replace(&mut self.environment, globals.environment),
+            functions: std::mem::replace(&mut self.functions, globals.functions),
+        }
+    }
+
+    /// Makes a note of something that might be a mistake.
+    fn warn(&mut self, warning: Warning) {
+        self.warnings.push(warning);
+    }
+
+    /// Records an act of chaos in the audit.
+    fn record_chaos(&mut self, event: ChaosEvent) {
+        *self.chaos_counts.entry(event.kind).or_default() += 1;
+        self.chaos_audit.push(event);
+    }
+
+    /// Rolls the dice for a chaotic outcome, recording it in the audit if it happens.
+    pub(crate) fn roll(&mut self, kind: ChaosKind, probability: f64) -> bool {
+        if self.sudo_active {
+            return false;
+        }
+        let probability = (probability * self.chaos_factor() * (1.0 + self.contagion_level)).min(1.0);
+        let context = self.chaos_context();
+        let probability = self
+            .modulators
+            .iter()
+            .fold(probability, |p, m| m.modulate(kind, p, &context));
+        let happened = self.coin(|rng| rng.gen::<f64>() < probability) && probability > 0.0;
+        if happened {
+            self.record_chaos(ChaosEvent::new(kind));
+        }
+        happened
+    }
+
+    /// Returns how the dice are loaded right now. `murphy` beats `lucky`, wherever that was set.
+    fn luck(&self) -> Luck {
+        if self.has_directive("murphy") {
+            Luck::Worst
+        } else if self.config.lucky || self.has_directive("lucky") {
+            Luck::Best
+        } else {
+            Luck::Random
+        }
+    }
+
+    /// Flips a coin for something bad happening: `random` decides with fair dice, murphy always
+    /// says yes and lucky always says no.
+    fn coin(&mut self, random: impl FnOnce(&mut StdRng) -> bool) -> bool {
+        match self.luck() {
+            Luck::Random => random(&mut self.rng),
+            Luck::Worst => true,
+            Luck::Best => false,
+        }
+    }
+
+    /// Turns the chaos dial. 0 behaves like `disable_all_useless_shit`, 10 is the factory setting
+    /// and 11 is one louder. Anything above 11 is still 11.
+    pub fn set_chaos_level(&mut self, level: u8) {
+        self.config.chaos_level = level.min(MAX_CHAOS_LEVEL);
+    }
+
+    /// Returns where the chaos dial is set.
+    pub fn chaos_level(&self) -> u8 {
+        self.config.chaos_level
+    }
+
+    /// Returns how much the chaos dial scales every chance of chaos.
+    fn chaos_factor(&self) -> f64 {
+        f64::from(self.config.chaos_level) / 10.0
+    }
+
+    /// Returns how infected the interpreter is from swallowed errors.
+    /// Every chaos probability is multiplied by one plus this level.
+    pub fn contagion_level(&self) -> f64 {
+        self.contagion_level
+    }
+
+    /// Returns what the current (or last) run has used up so far.
+    pub fn resource_usage(&self) -> &ResourceUsage {
+        &self.usage
+    }
+
+    /// Sleeps on the clock, keeping track of how long for.
+    /// Naps never run past the deadline; whoever asked for one finds out at [`Interpreter::check_interrupted`].
+    fn sleep(&mut self, duration: Duration) {
+        let duration = match self.deadline {
+            Some(deadline) => duration.min(deadline.duration_since(self.clock.now()).unwrap_or_default()),
+            None => duration,
+        };
+        self.usage.time_slept += duration;
+        self.clock.sleep(duration);
+    }
+
+    /// Picks how long a promise takes to resolve, somewhere between the configured minimum and
+    /// maximum (always the maximum under murphy, and the minimum when lucky).
+    fn promise_delay(&mut self) -> u64 {
+        let (min, max) = (self.config.promise_delay_min_ms, self.config.promise_delay_max_ms);
+        match (max.checked_sub(min), self.luck()) {
+            (Some(_), Luck::Worst) => max,
+            (Some(spread), Luck::Random) if spread > 0 => self.rng.gen::<u64>() % spread + min,
+            _ => min,
+        }
+    }
+
+    /// Fails if someone cancelled the run or it ran out of time.
+    fn check_interrupted(&self) -> Result<(), RuntimeError> {
+        if self.cancel.is_cancelled() {
+            return Err(RuntimeError::CancelledByHuman);
+        }
+        match self.deadline {
+            Some(deadline) if self.clock.now() >= deadline => {
+                Err(RuntimeError::OutOfTime(Duration::from_millis(self.config.timeout_ms)))
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// Sets how many statements and expressions a program may evaluate before it's stopped with
+    /// `OutOfSteps` (0 means no limit). Counting starts over with every program.
+    pub fn set_max_steps(&mut self, steps: usize) {
+        self.config.max_steps = steps;
+    }
+
+    /// Counts a statement or an expression against both budgets, which measure different things.
+    /// `max_statements` counts statements over the interpreter's whole life, for hosts that keep
+    /// one around for many programs. `max_steps` counts statements and expressions, and starts over
+    /// with every program.
+    fn take_step(&mut self, step: Step) -> Result<(), RuntimeError> {
+        if step == Step::Statement {
+            self.statements_executed += 1;
+            self.usage.statements_executed += 1;
+            let limit = self.config.max_statements;
+            if limit > 0 && self.statements_executed > limit {
+                return Err(RuntimeError::TooManyStatements(limit));
+            }
+        }
+        self.usage.steps_taken += 1;
+        match self.config.max_steps {
+            0 => Ok(()),
+            limit if self.usage.steps_taken > limit => Err(RuntimeError::OutOfSteps(limit)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Sums up the chaos of the current (or last) program: how many times each kind happened, and
+    /// which URLs `print` picked.
+    pub fn chaos_report(&self) -> ChaosReport {
+        ChaosReport {
+            counts: ChaosKind::ALL
+                .into_iter()
+                .filter_map(|kind| self.chaos_counts.get(&kind).map(|&count| (kind, count)))
+                .collect(),
+            urls: self.usage.urls.clone(),
+        }
+    }
+
+    /// Returns a snapshot of what the interpreter has been up to.
+    pub fn stats(&self) -> Stats {
+        Stats {
+            statements_executed: self.statements_executed,
+            chaos_events: self.chaos_audit.len(),
+            errors_caught: self.errors_caught,
+            contagion_level: self.contagion_level,
+        }
+    }
+
+    /// Every so often, deletes the least recently used variable and says a few words.
+    fn maybe_collect_garbage(&mut self) {
+        let interval = self.config.gc_interval;
+        if interval == 0 || !self.statements_executed.is_multiple_of(interval) || self.environment.is_empty() {
+            return;
+        }
+        if !self.roll(ChaosKind::GarbageCollected, self.config.gc_chance) {
+            return;
+        }
+
+        if let Some(name) = self.environment.least_recently_used() {
+            let idle = self.environment.idle_for(&name).unwrap_or(0);
+            if let Some(value) = self.environment.remove(&name) {
+                self.print_line(&format!(
+                    "⚰️ In loving memory of `{}` ({:?}), untouched for {} variable uses. It will not be missed.",
+                    name, value, idle
+                ));
+            }
+        }
+    }
+
+    /// Occasionally lets two variables swap names, if identity theft is enabled.
+    fn maybe_steal_identities(&mut self) {
+        if !self.config.identity_theft || self.environment.len() < 2 {
+            return;
+        }
+        let chance = self.config.identity_theft_chance * self.chaos_factor();
+        if !self.coin(|rng| rng.gen::<f64>() < chance) {
+            return;
+        }
+
+        let names = self.environment.symbols();
+        let victims: Vec<Symbol> = names.choose_multiple(&mut self.rng, 2).cloned().collect();
+        if self.environment.swap(&victims[0], &victims[1]) {
+            let detail = format!("'{}' and '{}' swapped identities", victims[0], victims[1]);
+            self.record_chaos(ChaosEvent::with_detail(ChaosKind::IdentityTheft, detail));
+        }
+    }
+
+    /// Rolls the environment back `steps` statements, as if they never happened.
+    /// Returns how many statements were actually undone (the journal only goes back so far).
+    pub fn rewind(&mut self, steps: usize) -> usize {
+        self.environment.rewind(steps)
+    }
+
+    /// Rewinds from inside a running statement, which doesn't count itself as something to regret.
+    pub(crate) fn rewind_from_program(&mut self, steps: usize) -> usize {
+        self.environment.rewind_before_current(steps)
+    }
+
+    /// Evaluates the arguments and hands them to a builtin (the language's own, or a plugin's).
+    fn call_builtin(
+        &mut self,
+        builtin: impl Fn(&mut Interpreter, Vec<Value>) -> Result<Value, RuntimeError>,
+        arguments: Vec<Expression>,
+    ) -> Result<Value, RuntimeError> {
+        let arguments = arguments
+            .into_iter()
+            .map(|argument| self.evaluate_expression(argument))
+            .collect::<Result<Vec<_>, _>>()?;
+        builtin(self, arguments)
+    }
+
+    /// Reseeds the random number generator behind every chaotic decision.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Returns the random number generator behind every chaotic decision.
+    pub(crate) fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+
+    /// Replaces the clock used for every delay, so tests don't have to actually wait.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Replaces the side effects programs are allowed to have.
+    pub fn set_capabilities(&mut self, capabilities: Capabilities) {
+        self.capabilities = capabilities;
+    }
+
+    /// Returns the side effects programs are allowed to have.
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    /// Replaces the scheduler consulted before every statement.
+    pub fn set_scheduler(&mut self, scheduler: Box<dyn Scheduler>) {
+        self.scheduler = Some(scheduler);
+    }
+
+    /// Attaches a debugger, which gets to stop the program before statements that know where they
+    /// were written. Quitting from the debugger ends the program with `CancelledByHuman`.
+    pub fn set_debugger(&mut self, session: DebugSession) {
+        self.debugger = Some(session);
+    }
+
+    /// Attaches a tracer, which hears about every statement that runs and the chaos it suffered.
+    pub fn set_tracer(&mut self, tracer: Box<dyn Tracer>) {
+        self.tracing = Some(Tracing::new(tracer));
+    }
+
+    /// Starts (or stops) timing every statement and expression, for [`profile`](Self::profile).
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.profiling = enabled.then(Profiler::new);
+    }
+
+    /// Where the time went so far, node by node, if profiling is on.
+    pub fn profile(&self) -> Option<Profile> {
+        self.profiling.as_ref().map(Profiler::profile)
+    }
+
+    /// Hands the program its arguments, which `args()` returns.
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.args = args;
+    }
+
+    /// Returns the program's arguments, in the order they were given.
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    /// Returns a sender that queues events for this interpreter from any thread. They're delivered
+    /// at its next statement.
+    pub fn event_sender(&self) -> EventSender {
+        self.events.clone()
+    }
+
+    /// Signs a function up to be called with the payload of every `event` from now on.
+    pub(crate) fn on_event(&mut self, event: String, handler: String) {
+        self.event_handlers.entry(event).or_default().push(Symbol::intern(&handler));
+    }
+
+    /// Runs every handler signed up for an event, in the order they signed up, with the payload as
+    /// their argument, and returns how many there were. Events sent from elsewhere that are still
+    /// waiting go first. Handlers that fail don't stop the others, and their errors are reported
+    /// together.
+    pub fn emit_event(&mut self, name: &str, payload: Value) -> Result<usize, RuntimeError> {
+        self.deliver_queued_events()?;
+        self.deliver_event(name, payload)
+    }
+
+    /// Runs the handlers for every event that was sent from elsewhere since the last time.
+    fn deliver_queued_events(&mut self) -> Result<(), RuntimeError> {
+        if self.delivering_events {
+            return Ok(());
+        }
+        for (name, payload) in self.events.take() {
+            self.deliver_event(&name, payload)?;
+        }
+        Ok(())
+    }
+
+    /// Runs the handlers for one event. Async handlers are awaited.
+    fn deliver_event(&mut self, name: &str, payload: Value) -> Result<usize, RuntimeError> {
+        let handlers = self.event_handlers.get(name).cloned().unwrap_or_default();
+        if handlers.is_empty() {
+            return Ok(0);
+        }
+        if !self.behaves_normally() && self.roll(ChaosKind::LeftOnRead, 0.1) {
+            return Ok(0);
+        }
+
+        let delivering = std::mem::replace(&mut self.delivering_events, true);
+        let mut errors = Vec::new();
+        for handler in &handlers {
+            match self.call_function(handler, vec![payload.clone()]).and_then(|value| self.settle(value)) {
+                Err(error) if error.ends_program() => {
+                    errors = vec![error];
+                    break;
                 },
-                Expression::Access { object, key } => {
-                    let obj = self.evaluate_expression(*object)?;
-                    let key_val = self.evaluate_expression(*key)?;
+                Err(error) => errors.push(error),
+                Ok(_) => {},
+            }
+        }
+        self.delivering_events = delivering;
+        gather(errors).map(|()| handlers.len())
+    }
+
+    /// Returns a handle that stops this interpreter at its next statement, from any thread.
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
+    /// Makes the interpreter answer to an existing handle, so one Ctrl-C handler can stop
+    /// whichever interpreter happens to be running.
+    pub fn set_cancel_handle(&mut self, handle: CancelHandle) {
+        self.cancel = handle;
+    }
+
+    /// Fails with a permission error unless a capability is allowed.
+    pub(crate) fn require(&self, capability: Capability) -> Result<(), RuntimeError> {
+        if self.capabilities.allows(capability) {
+            Ok(())
+        } else {
+            Err(RuntimeError::PermissionDenied(capability))
+        }
+    }
+
+    /// Replaces the sink that printed output goes to.
+    pub fn set_output(&mut self, output: Box<dyn OutputSink>) {
+        self.output = output;
+    }
+
+    /// Runs a statement with everything it prints going to a file instead, for
+    /// `#[directive(print_to("out.txt"))]`. The file is appended to, so several statements can
+    /// share it, and whatever was printing before takes over again afterwards.
+    fn print_to(&mut self, directive: &str, statement: Statement) -> Result<(), RuntimeError> {
+        let path = directive
+            .strip_prefix(PRINT_TO)
+            .and_then(|rest| rest.strip_suffix(')'))
+            .and_then(|argument| argument.trim().strip_prefix('"')?.strip_suffix('"'))
+            .ok_or_else(|| {
+                RuntimeError::BadArguments("print_to".to_string(), "expected a file name in quotes, like print_to(\"out.txt\")".to_string())
+            })?;
+        self.require(Capability::Filesystem)?;
+        let sink = FileSink::append(path).map_err(|e| RuntimeError::WriteError(path.to_string(), e.to_string()))?;
+
+        let previous = std::mem::replace(&mut self.output, Box::new(sink));
+        let result = self.execute_statement(statement);
+        self.output = previous;
+        result
+    }
+
+    /// Runs a statement with time stopped, for `#[directive(frozen_clock("2024-01-01T00:00:00Z"))]`.
+    /// `now()` (and anything else that asks what time it is) gets the frozen time, so timestamps come
+    /// out the same every run. Naps still take as long as they take, and `--timeout` still counts.
+    fn frozen_clock(&mut self, directive: &str, statement: Statement) -> Result<(), RuntimeError> {
+        let previous = self.frozen_at.replace(frozen_time(directive)?);
+        let result = self.execute_statement(statement);
+        self.frozen_at = previous;
+        result
+    }
+
+    /// Writes a line of program output, after a dramatic pause if one is configured.
+    pub(crate) fn print_line(&mut self, text: &str) {
+        if self.config.drama_ms > 0 && self.capabilities.clock {
+            let pause = Duration::from_millis(self.config.drama_ms);
+            if self.config.drama_dots {
+                // Build suspense one dot at a time
+                for _ in 0..3 {
+                    self.sleep(pause / 3);
+                    self.write_output(".");
+                }
+                self.write_output("\n");
+            } else {
+                self.sleep(pause);
+            }
+        }
+        self.write_output(text);
+        self.write_output("\n");
+    }
+
+    /// Writes text to the output sink, and to the transcript if a report is being kept.
+    fn write_output(&mut self, text: &str) {
+        if let Some(transcript) = &mut self.transcript {
+            transcript.push_str(text);
+        }
+        self.output.write(text);
+    }
+
+    /// Switches all useless behavior off (or back on), like the `disable_all_useless_shit` directive.
+    pub fn set_completely_normal(&mut self, normal: bool) {
+        self.is_completely_normal = normal;
+    }
+
+    /// Returns the config this interpreter is (allegedly) following.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn has_directive(&self, name: &str) -> bool {
+        self.directives.contains(name)
+    }
+
+    pub fn interpret(&mut self, program: Program) -> Result<(), RuntimeError> {
+        let mut errors = Vec::new();
+        for statement in self.start_program(program)? {
+            self.run_top_level(statement, &mut errors)?;
+        }
+        self.finish_program(errors)
+    }
+
+    /// Interprets a program like [`Interpreter::interpret`], but never blocks the caller's runtime to wait.
+    /// The program runs on tokio's blocking pool, and every promise delay, dramatic pause and prayer
+    /// delay is slept on the caller's runtime timers while the program waits at the spot that asked,
+    /// so async hosts can run scripts without tying up a worker thread.
+    ///
+    /// Dropping the future cancels the program: it's stopped at its next statement, and the
+    /// interpreter comes back with everything the program did until then.
+    pub async fn run_async(&mut self, program: Program) -> Result<(), RuntimeError> {
+        AsyncRun::start(self, program).finish().await
+    }
+
+    /// Gets a program ready to run, returning the top-level statements that should actually run.
+    fn start_program(&mut self, program: Program) -> Result<Program, RuntimeError> {
+        // Every program gets its own single sudo, and its own time limit
+        self.sudo_used = false;
+        self.deadline = (self.config.timeout_ms > 0).then(|| self.clock.now() + Duration::from_millis(self.config.timeout_ms));
+        self.last_value = Value::Null;
+        self.usage = ResourceUsage::default();
+        self.chaos_start = self.chaos_audit.len();
+        self.chaos_counts.clear();
+        self.current_span = None;
+        self.frozen_at = None;
+        let mut program = macros::expand(program)?;
+        self.loaded_program = program.clone();
+
+        // Time stopped on the first statement stays stopped for the whole program
+        if let Some(Statement::Attributed { name, statement }) = program.first().map(Statement::unlocated) {
+            if name.starts_with(FROZEN_CLOCK) {
+                self.frozen_at = Some(frozen_time(name)?);
+                program[0] = (**statement).clone();
+            }
+        }
+
+        // Check for top-level directive first
+        match program.first().map(Statement::unlocated) {
+            Some(Statement::Directive { name }) if name == "disable_all_useless_shit" => {
+                self.is_completely_normal = true;
+                // Execute rest of program without the directive
+                program.remove(0);
+                return Ok(program);
+            },
+            // Written as an attribute on the first statement, it still means the whole program
+            Some(Statement::Attributed { name, statement }) if name == "disable_all_useless_shit" => {
+                self.is_completely_normal = true;
+                program[0] = (**statement).clone();
+                return Ok(program);
+            },
+            _ => {},
+        }
+
+        // Original chaotic behavior if no top-level directive
+        if !self.behaves_normally() {
+        // 10% chance of throwing a teapot error just because
+        if self.roll(ChaosKind::Teapot, 0.1) {
+            return Err(RuntimeError::Teapot);
+            }
+        }
+        Ok(program)
+    }
+
+    /// Executes a top-level statement. In `keep_going` mode, errors are collected instead of ending
+    /// the program (errors that [end the program](RuntimeError::ends_program) still end it immediately).
+    fn run_top_level(&mut self, statement: Statement, errors: &mut Vec<RuntimeError>) -> Result<(), RuntimeError> {
+        let result = self.execute_statement(statement);
+        self.usage.peak_memory_bytes = self.usage.peak_memory_bytes.max(self.environment.estimated_size());
+        match result {
+            Err(error) if self.config.keep_going && !error.ends_program() => {
+                errors.push(error);
+                Ok(())
+            },
+            result => result,
+        }
+    }
+
+    /// Wraps up a program that made it to the end, returning the errors collected along the way.
+    fn finish_program(&mut self, mut errors: Vec<RuntimeError>) -> Result<(), RuntimeError> {
+        match errors.len() {
+            0 => (),
+            1 => return Err(errors.remove(0)),
+            _ => return Err(RuntimeError::Multiple(errors)),
+        }
+
+        if !self.behaves_normally() {
+        // 20% chance of saying everything went wrong perfectly
+        if self.roll(ChaosKind::PerfectlyWrong, 0.2) {
+            return Err(RuntimeError::PerfectlyWrong);
+            }
+            self.motivate();
+        }
+
+        Ok(())
+    }
+
+    /// Evaluates a single expression against the current variables and functions,
+    /// for hosts using this as a (terrible) expression language.
+    pub fn eval(&mut self, source: &str) -> Result<Value, Error> {
+        let expression = Parser::new(Lexer::new(source).collect()).parse_single_expression()?;
+        Ok(self.evaluate_expression(expression)?)
+    }
+
+    /// Interprets a program like [`Interpreter::interpret`], but hands back a full account of the run:
+    /// the last value, everything printed, the errors, the chaos, and the stats.
+    pub fn interpret_with_report(&mut self, program: Program) -> RunResult {
+        let chaos_before = self.chaos_audit.len();
+        let warnings_before = self.warnings.len();
+        self.transcript = Some(String::new());
+        let result = self.interpret(program);
+        let output = self.transcript.take().unwrap_or_default();
+
+        RunResult {
+            last_value: std::mem::replace(&mut self.last_value, Value::Null),
+            output,
+            exit_status: match result {
+                Err(RuntimeError::Exited(status)) => Some(status),
+                _ => None,
+            },
+            errors: match result {
+                Ok(()) | Err(RuntimeError::Exited(_)) => Vec::new(),
+                Err(RuntimeError::Multiple(errors)) => errors,
+                Err(error) => vec![error],
+            },
+            parse_errors: Vec::new(),
+            chaos_events: self.chaos_audit[chaos_before..].to_vec(),
+            warnings: self.warnings[warnings_before..].to_vec(),
+            stats: self.stats(),
+            usage: self.usage.clone(),
+        }
+    }
+
+    /// Replaces the pool of compliments handed out when a program finishes without an error.
+    pub fn set_motivational_messages(&mut self, messages: Vec<String>) {
+        self.config.motivational_messages = messages;
+    }
+
+    /// Prints a random backhanded compliment, if there are any left to give.
+    fn motivate(&mut self) {
+        if let Some(message) = self.config.motivational_messages.choose(&mut self.rng).cloned() {
+            self.print_line(&format!("🏆 {}", message));
+        }
+    }
+
+    pub fn execute_statement(&mut self, statement: Statement) -> Result<(), RuntimeError> {
+        // A failing statement leaves its span behind, so the error can be shown where it happened
+        let outer = match &statement {
+            Statement::Located { span, .. } => self.current_span.replace(*span),
+            _ => self.current_span,
+        };
+        if self.profiling.is_some() {
+            self.enter_profiled(NodeKind::Statement, || {
+                let code = crate::fmt::format_program(std::slice::from_ref(statement.unlocated()));
+                code.lines().next().unwrap_or_default().to_string()
+            });
+        }
+        let result = if self.tracing.is_some() { self.execute_traced(statement) } else { self.execute_located(statement) };
+        if let Some(profiler) = self.profiling.as_mut() {
+            profiler.exit();
+        }
+        if result.is_ok() {
+            self.current_span = outer;
+        }
+        result
+    }
+
+    /// Tells the profiler a node is starting, on the line of the statement it's in. The code is
+    /// formatted on the profiler's time, not the node's.
+    fn enter_profiled(&mut self, kind: NodeKind, code: impl FnOnce() -> String) {
+        let began = Instant::now();
+        let line = self.current_span.map(|span| span.line);
+        if let Some(profiler) = self.profiling.as_mut() {
+            profiler.enter(kind, line, code(), began);
+        }
+    }
+
+    /// Executes a statement, telling the tracer when it starts and how it went.
+    fn execute_traced(&mut self, statement: Statement) -> Result<(), RuntimeError> {
+        let span = match &statement {
+            Statement::Located { span, .. } => Some(*span),
+            _ => None,
+        };
+        let traced = statement.clone();
+        if let Some(tracing) = self.tracing.as_mut() {
+            tracing.enter(span, traced.unlocated(), &self.chaos_audit);
+        }
+        let result = self.execute_located(statement);
+        if let Some(tracing) = self.tracing.as_mut() {
+            tracing.finish(span, traced.unlocated(), &self.chaos_audit, &result);
+        }
+        result
+    }
+
+    /// Executes a statement that may still have its location on, letting the hooks watch.
+    fn execute_located(&mut self, statement: Statement) -> Result<(), RuntimeError> {
+        let statement = self.unlocate(statement)?;
+        if self.hooks.is_empty() {
+            return self.run_statement(statement);
+        }
+
+        for hook in self.hooks.iter_mut() {
+            hook.before_statement(&statement);
+        }
+        let result = self.run_statement(statement.clone());
+        for hook in self.hooks.iter_mut() {
+            hook.after_statement(&statement, &result);
+        }
+        result
+    }
+
+    /// Strips the location off a statement, letting the debugger stop there first if it wants to.
+    fn unlocate(&mut self, mut statement: Statement) -> Result<Statement, RuntimeError> {
+        while let Statement::Located { span, statement: inner } = statement {
+            statement = *inner;
+            if let Some(session) = self.debugger.as_mut() {
+                if session.before(span, &statement, &self.environment) == Resume::Quit {
+                    return Err(RuntimeError::CancelledByHuman);
+                }
+            }
+        }
+        Ok(statement)
+    }
+
+    fn run_statement(&mut self, statement: Statement) -> Result<(), RuntimeError> {
+        self.check_interrupted()?;
+        self.deliver_queued_events()?;
+        if let Some(scheduler) = self.scheduler.as_mut() {
+            match scheduler.before_statement(&statement, self.statements_executed) {
+                Decision::Continue => (),
+                Decision::Sleep(duration) => self.sleep(duration),
+                Decision::Yield => std::thread::yield_now(),
+                Decision::Abort(reason) => return Err(RuntimeError::Descheduled(reason)),
+            }
+        }
+        self.take_step(Step::Statement)?;
+        self.environment.begin_step();
+        if !self.behaves_normally() {
+            self.maybe_steal_identities();
+            self.maybe_collect_garbage();
+        }
+
+        // If completely normal mode is on, execute everything normally
+        if self.behaves_normally() {
+        match statement {
+                Statement::Print { value } => {
+                    let value = self.evaluate_expression(value)?;
+                    self.print_line(&format!("{:?}", value));
+                    Ok(())
+                },
+                Statement::Let { name, value } => {
+                    let value = self.evaluate_expression(value)?;
+                    self.environment.set(name, value);
+                    Ok(())
+                },
+                Statement::If { condition, then_branch, else_branch } => {
+                    let cond = self.evaluate_expression(condition)?;
+                    match cond {
+                        Value::Boolean { value: true } => {
+                            for stmt in then_branch {
+                                self.execute_statement(stmt)?;
+                            }
+                        },
+                        Value::Boolean { value: false } => {
+                            if let Some(else_statements) = else_branch {
+                                for stmt in else_statements {
+                                    self.execute_statement(stmt)?;
+                                }
+                            }
+                        },
+                        _ => return Err(RuntimeError::Generic("Condition must be a boolean".to_string())),
+                    }
+                    Ok(())
+                },
+            Statement::Attributed { name, statement } => {
+                match name.as_str() {
+                    "disable_useless" => {
+                        self.directives.insert(name.clone());
+                            let result = self.execute_statement(*statement);
+                            self.directives.remove(&name);
+                            result
+                    },
+                        "experimental" | "murphy" | "lucky" => {
+                        self.directives.insert(name.clone());
+                            let result = self.execute_statement(*statement);
+                            self.directives.remove(&name);
+                            result
+                    },
+                        name if name.starts_with(PRINT_TO) => self.print_to(name, *statement),
+                        name if name.starts_with(FROZEN_CLOCK) => self.frozen_clock(name, *statement),
+                        _ => match self.plugins.directive(&name) {
+                            Some(handler) => {
+                                handler(self)?;
+                                self.directives.insert(name.clone());
+                                let result = self.execute_statement(*statement);
+                                self.directives.remove(&name);
+                                result
+                            },
+                            None => {
+                                self.warn(Warning::UnknownDirective(name.clone()));
+                                self.execute_statement(*statement)
+                            },
+                        },
+                    }
+                },
+                Statement::Loop { body } => {
+                    if self.roll(ChaosKind::LoopFailure, 0.25) {
+                        return Err(RuntimeError::TaskFailedSuccessfully);
+                    }
+                    for statement in body.into_iter().take(1) {
+                        self.execute_statement(statement)?;
+                    }
+                    Ok(())
+                },
+                Statement::ForIn { variable, iterable, body } => self.execute_for_in(variable, iterable, body),
+                Statement::Expression(expr) => {
+                    self.last_value = self.evaluate_expression(expr)?;
+                    Ok(())
+                },
+                Statement::AsyncFunction { name, parameters, body } => {
+                    self.define_function(name, parameters, body, true);
+                    Ok(())
+                },
+                Statement::TryCatch { try_block, error_binding, catch_block } => {
+                    self.execute_try_catch(try_block, error_binding, catch_block)
+                },
+                Statement::Pray { attempts, body } => self.execute_pray(attempts, body),
+                Statement::Concurrent { body } => self.execute_concurrent(body),
+            Statement::Sudo { statement } => self.execute_sudo(*statement),
+                // Macros were expanded away before the program started
+                Statement::Macro { .. } => Ok(()),
+                Statement::Module { name: _, body } => {
+                    // Execute module body
+                    for stmt in body {
+                        self.execute_statement(stmt)?;
+                    }
+                    Ok(())
+                },
+                Statement::Use { path: _ } => {
+                    // Imports are always successful (but might import the wrong thing)
+                    Ok(())
+                },
+                Statement::UseData { name, path } => {
+                    let value = self.load_data(&path)?;
+                    self.environment.set(name, value);
+                    Ok(())
+                },
+                Statement::Function { name, parameters, body } => {
+                    self.define_function(name, parameters, body, false);
+                    Ok(())
+                },
+                Statement::Directive { name } => {
+                    // Handle directive
+                    match name.as_str() {
+                        "disable_useless" => {
+                            self.directives.insert(name.clone());
+                            Ok(())
+                        },
+                        "experimental" | "murphy" | "lucky" => {
+                            self.directives.insert(name.clone());
+                            Ok(())
+                        },
+                        name if name.starts_with(FROZEN_CLOCK) => {
+                            self.frozen_at = Some(frozen_time(name)?);
+                            Ok(())
+                        },
+                        _ => match self.plugins.directive(&name) {
+                            Some(handler) => {
+                                self.directives.insert(name);
+                                handler(self)
+                            },
+                            None => {
+                                self.warn(Warning::UnknownDirective(name.clone()));
+                                Ok(())
+                            },
+                        },
+                    }
+                },
+                Statement::Save { filename: _ } => {
+                    // Always fail to save because saving is overrated
+                    self.require(Capability::Filesystem)?;
+                    Err(RuntimeError::SaveError)
+                },
+                Statement::Await { expression } => {
+                    self.last_value = self.await_statement(expression)?;
+                    Ok(())
+                },
+                Statement::Located { .. } => unreachable!("unwrapped by execute_statement"),
+            }
+        } else {
+            match statement {
+                Statement::Print { value } => {
+                    let value = self.evaluate_expression(value)?;
+                    // Only open random URLs if disable_useless is not active, and luck isn't on our side
+                    let lucky = self.luck() == Luck::Best;
+                    if !self.has_directive("disable_useless") && !lucky {
+                        let url = self.runtime
+                            .urls()
+                            .choose(&mut self.rng)
+                            .ok_or(RuntimeError::BrowserError)?;
+                        let url = url.clone();
+                        self.usage.urls.push(url.clone());
+                        if self.config.open_browser {
+                            self.require(Capability::Browser)?;
+                            self.record_chaos(ChaosEvent::with_detail(ChaosKind::BrowserTab, url.clone()));
+                            if webbrowser::open(&url).is_err() {
+                                return Err(RuntimeError::BrowserError);
+                            }
+                        } else {
+                            // The tab would have been opened, and the audit should know it
+                            self.record_chaos(ChaosEvent::with_detail(ChaosKind::BrowserTab, format!("{} (not opened)", url)));
+                        }
+                    }
+                    let text = format!("{:?}", value);
+                    if self.config.mangle_output && !lucky {
+                        self.record_chaos(ChaosEvent::new(ChaosKind::OutputMangled));
+                        let mangled = mangle_text(&text, &mut self.rng);
+                        self.print_line(&mangled);
+                    } else {
+                        self.print_line(&text);
+                    }
+                Ok(())
+            },
+            Statement::Let { name, value } => {
+                let value = self.evaluate_expression(value)?;
+                if self.roll(ChaosKind::VariableVacation, 0.2) {
+                    return Err(RuntimeError::UndefinedVariable(name.to_string()));
+                }
+                self.environment.set(name, value);
+                Ok(())
+            },
+            Statement::If { condition: _, then_branch, else_branch } => {
+                if let Some(else_statements) = else_branch {
+                    if self.roll(ChaosKind::CreativeBreakage, 0.15) {
+                        return Err(RuntimeError::CreativeBreakage);
+                    }
+                    for stmt in else_statements {
+                        self.execute_statement(stmt)?;
+                    }
+                }
+                let _ = then_branch;
+                Ok(())
+            },
+            Statement::Loop { body } => {
+                if self.roll(ChaosKind::LoopFailure, 0.25) {
+                    return Err(RuntimeError::TaskFailedSuccessfully);
+                }
+                for statement in body.into_iter().take(1) {
+                    self.execute_statement(statement)?;
+                }
+                Ok(())
+            },
+            Statement::ForIn { variable, iterable, body } => self.execute_for_in(variable, iterable, body),
+            Statement::Expression(expr) => {
+                self.last_value = self.evaluate_expression(expr)?;
+                Ok(())
+            },
+            Statement::AsyncFunction { name, parameters, body } => {
+                if self.roll(ChaosKind::AsyncFishing, 0.3) {
+                    return Err(RuntimeError::AsyncTimeout);
+                }
+
+                self.define_function(name, parameters, body, true);
+                Ok(())
+            },
+            Statement::TryCatch { try_block, error_binding, catch_block } => {
+                self.execute_try_catch(try_block, error_binding, catch_block)
+            },
+            Statement::Pray { attempts, body } => self.execute_pray(attempts, body),
+            Statement::Concurrent { body } => self.execute_concurrent(body),
+            Statement::Sudo { statement } => self.execute_sudo(*statement),
+            Statement::Macro { .. } => Ok(()),
+            Statement::Module { name: _, body } => {
+                // Execute module body
+                for stmt in body {
+                    self.execute_statement(stmt)?;
+                }
+                Ok(())
+            },
+            Statement::Use { path: _ } => {
+                // Imports are always successful (but might import the wrong thing)
+                Ok(())
+            },
+            Statement::UseData { name, path } => {
+                // Data goes on vacation just like any other variable
+                let value = self.load_data(&path)?;
+                if self.roll(ChaosKind::VariableVacation, 0.2) {
+                    return Err(RuntimeError::UndefinedVariable(name.to_string()));
+                }
+                self.environment.set(name, value);
+                Ok(())
+            },
+            Statement::Function { name, parameters, body } => {
+                self.define_function(name, parameters, body, false);
+                Ok(())
+            },
+            Statement::Directive { name } => {
+                // Handle directive
+                match name.as_str() {
+                    "disable_useless" => {
+                        self.directives.insert(name.clone());
+                        Ok(())
+                    },
+                    "experimental" | "murphy" | "lucky" => {
+                        self.directives.insert(name.clone());
+                        Ok(())
+                    },
+                    name if name.starts_with(FROZEN_CLOCK) => {
+                        self.frozen_at = Some(frozen_time(name)?);
+                        Ok(())
+                    },
+                    _ => match self.plugins.directive(&name) {
+                        Some(handler) => {
+                            self.directives.insert(name);
+                            handler(self)
+                        },
+                        None => {
+                            self.warn(Warning::UnknownDirective(name.clone()));
+                            Ok(())
+                        },
+                    },
+                }
+            },
+            Statement::Save { filename: _ } => {
+                // Always fail to save because saving is overrated
+                self.require(Capability::Filesystem)?;
+                Err(RuntimeError::SaveError)
+            },
+            Statement::Await { expression } => {
+                // Same as anywhere else, but maybe never return
+                self.last_value = self.await_statement(expression)?;
+                if self.roll(ChaosKind::AsyncFishing, 0.4) {
+                    Err(RuntimeError::AsyncTimeout)
+                } else {
+                    Ok(())
+                }
+            },
+                Statement::Attributed { name, statement } => {
+                    // Handle attributed statements in chaotic mode
+                    match name.as_str() {
+                        "disable_useless" => {
+                            self.directives.insert(name.clone());
+                            let result = self.execute_statement(*statement);
+                            self.directives.remove(&name);
+                            result
+                        },
+                        "experimental" | "murphy" | "lucky" => {
+                            self.directives.insert(name.clone());
+                            let result = self.execute_statement(*statement);
+                            self.directives.remove(&name);
+                            result
+                        },
+                        name if name.starts_with(PRINT_TO) => self.print_to(name, *statement),
+                        name if name.starts_with(FROZEN_CLOCK) => self.frozen_clock(name, *statement),
+                        _ => match self.plugins.directive(&name) {
+                            Some(handler) => {
+                                handler(self)?;
+                                self.directives.insert(name.clone());
+                                let result = self.execute_statement(*statement);
+                                self.directives.remove(&name);
+                                result
+                            },
+                            None => {
+                                self.warn(Warning::UnknownDirective(name.clone()));
+                                self.execute_statement(*statement)
+                            },
+                        },
+                    }
+                },
+                Statement::Located { .. } => unreachable!("unwrapped by execute_statement"),
+            }
+        }
+    }
+
+    /// Runs a pray block up to `attempts` times (but always at least once), with exponentially
+    /// growing delays between tries. In chaos mode the gods get bored: every retry is less likely
+    /// to be granted.
+    fn execute_pray(&mut self, attempts: Option<usize>, body: Vec<Statement>) -> Result<(), RuntimeError> {
+        let attempts = attempts.unwrap_or(self.config.pray_attempts).max(1);
+        let mut delay = Duration::from_millis(self.config.pray_delay_ms);
+        let mut attempt = 1;
+
+        loop {
+            let error = match body.iter().cloned().try_for_each(|stmt| self.execute_statement(stmt)) {
+                Ok(()) => return Ok(()),
+                Err(error) if error.ends_program() => return Err(error),
+                Err(error) => error,
+            };
+
+            if attempt == attempts {
+                return if self.behaves_normally() {
+                    Err(error)
+                } else {
+                    Err(RuntimeError::PrayerUnanswered(attempt))
+                };
+            }
+
+            // Every retry halves the odds that anyone is still listening
+            if !self.behaves_normally() && self.roll(ChaosKind::UnansweredPrayer, 1.0 - 0.5f64.powi(attempt as i32 - 1)) {
+                return Err(RuntimeError::PrayerUnanswered(attempt));
+            }
+
+            if self.capabilities.clock {
+                self.sleep(delay);
+                self.check_interrupted()?;
+            }
+            delay *= 2;
+            attempt += 1;
+        }
+    }
+
+    /// Remembers a user-defined function, and stores a description of it in a variable of the same name.
+    fn define_function(&mut self, name: Symbol, parameters: Vec<Symbol>, body: Vec<Statement>, asynchronous: bool) {
+        let kind = if asynchronous { "async_function" } else { "function" };
+        self.environment.set(&name, Value::Object {
+            fields: ObjectMap::from([
+                ("type".to_string(), Value::String { value: kind.to_string() }),
+                ("name".to_string(), Value::String { value: name.to_string() }),
+                ("params".to_string(), Value::Array {
+                    values: parameters.iter()
+                        .map(|p| Value::String { value: p.to_string() })
+                        .collect()
+                }),
+            ]),
+        });
+        self.functions.insert(name, FunctionDefinition { parameters, body, asynchronous });
+    }
+
+    /// Swaps an edited version of the program into this interpreter without starting over.
+    /// Only top-level functions and modules that changed are executed again, definitions that
+    /// disappeared are forgotten, and every variable survives.
+    pub fn hot_reload(&mut self, program: Program) -> Result<ReloadReport, RuntimeError> {
+        let program = macros::expand(program)?;
+        let plan = reload::plan(&self.loaded_program, &program);
+
+        let mut report = ReloadReport { unchanged: plan.unchanged, ..ReloadReport::default() };
+        for definition in plan.removed {
+            if definition.kind == DefinitionKind::Function {
+                self.functions.remove(&definition.name);
+                self.environment.remove(&definition.name);
+            }
+            report.removed.push(definition.name.to_string());
+        }
+        self.loaded_program = program;
+        for (definition, statement) in plan.changed {
+            self.execute_statement(statement)?;
+            report.reloaded.push(definition.name.to_string());
+        }
+        Ok(report)
+    }
+
+    /// Calls a user-defined function. The value of its last expression statement is returned,
+    /// and parameters are restored to whatever they were before the call. Async functions don't
+    /// run yet: they return a promise, and run when it's awaited.
+    pub(crate) fn call_function(&mut self, name: impl Into<Symbol>, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let name = name.into();
+        let function = self
+            .functions
+            .get(&name)
+            .cloned()
+            .ok_or_else(|| RuntimeError::UndefinedVariable(name.to_string()))?;
+
+        if function.asynchronous {
+            self.async_calls += 1;
+            let task = AsyncCall { name, parameters: function.parameters, body: function.body, arguments, id: self.async_calls };
+            if let Some(spawned) = self.concurrent.last_mut() {
+                spawned.push(task.clone());
+            }
+            return Ok(Value::Promise { value: Box::new(Value::Null), resolved: false, task: Some(Box::new(task)) });
+        }
+        self.invoke(&function.parameters, function.body, arguments)
+    }
+
+    /// Runs the async call behind a promise, now that someone is finally waiting for it, and
+    /// resolves the promise with what it returned. Anything else is already as settled as it gets.
+    fn settle(&mut self, promise: Value) -> Result<Value, RuntimeError> {
+        match promise {
+            Value::Promise { task: Some(task), .. } => {
+                // Whichever concurrent block it was started in doesn't have to run it anymore
+                for spawned in self.concurrent.iter_mut().rev() {
+                    if let Some(position) = spawned.iter().position(|spawned| spawned.id == task.id) {
+                        spawned.remove(position);
+                        break;
+                    }
+                }
+                let value = self.invoke(&task.parameters, task.body, task.arguments)?;
+                Ok(Value::Promise { value: Box::new(value), resolved: true, task: None })
+            },
+            other => Ok(other),
+        }
+    }
+
+    /// Reads the JSON file of a `use name from "file.json";`. Paths the loader didn't resolve are
+    /// relative to wherever the interpreter happens to be running.
+    fn load_data(&mut self, path: &str) -> Result<Value, RuntimeError> {
+        self.require(Capability::Filesystem)?;
+        let unreadable = |reason: String| RuntimeError::ReadError(path.to_string(), reason);
+        let text = std::fs::read_to_string(path).map_err(|e| unreadable(e.to_string()))?;
+        let json = Json::parse(&text).ok_or_else(|| unreadable("that's not JSON, or not all of it".to_string()))?;
+        Ok(Value::from_json(json))
+    }
+
+    /// Runs a `concurrent` block. Async calls made in it that nobody awaited are run before it
+    /// ends (in chaos mode, in any order), so none of them outlive it, and their errors are
+    /// reported together. If the block itself fails, they're cancelled instead.
+    fn execute_concurrent(&mut self, body: Vec<Statement>) -> Result<(), RuntimeError> {
+        self.concurrent.push(Vec::new());
+        let result = body.into_iter().try_for_each(|statement| self.execute_statement(statement)).and_then(|()| {
+            if !self.behaves_normally() && self.roll(ChaosKind::TaskShuffle, 0.5) {
+                if let Some(spawned) = self.concurrent.last_mut() {
+                    spawned.shuffle(&mut self.rng);
+                }
+            }
+            let mut errors = Vec::new();
+            // Tasks can start more tasks, which have to finish too
+            while let Some(task) = self.concurrent.last_mut().filter(|spawned| !spawned.is_empty()).map(|spawned| spawned.remove(0)) {
+                match self.invoke(&task.parameters, task.body, task.arguments) {
+                    Err(error) if error.ends_program() => return Err(error),
+                    Err(error) => errors.push(error),
+                    Ok(_) => {},
+                }
+            }
+            gather(errors)
+        });
+        self.concurrent.pop();
+        result
+    }
+
+    /// Waits for a promise (running it first, if it's an async call) and returns what it resolved to.
+    /// Every `await` ends up here, wherever it was written. In chaos mode, promises sometimes change
+    /// their mind.
+    fn await_value(&mut self, promise: Value) -> Result<Value, RuntimeError> {
+        let behaving = self.behaves_normally() || self.has_directive("disable_useless");
+        match self.settle(promise)? {
+            Value::Promise { value, resolved: true, .. } => {
+                if !behaving && self.roll(ChaosKind::PromiseChangedMind, 0.2) {
+                    Ok(Value::String { value: "Promise changed its mind 🤔".to_string() })
+                } else {
+                    Ok(*value)
+                }
+            },
+            Value::Promise { .. } => Err(RuntimeError::PromiseRejected),
+            _ => Err(RuntimeError::Generic("Can't await something that isn't a promise! 🤯".to_string())),
+        }
+    }
+
+    /// Runs an `await` statement. Awaiting something that was never a promise is fine here, and
+    /// just gives it back.
+    fn await_statement(&mut self, expression: Expression) -> Result<Value, RuntimeError> {
+        match self.evaluate_expression(expression)? {
+            promise @ Value::Promise { .. } => self.await_value(promise),
+            value => Ok(value),
+        }
+    }
+
+    /// Runs a function body with its parameters set to the arguments, then puts the parameters back.
+    fn invoke(&mut self, parameters: &[Symbol], body: Vec<Statement>, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let mut arguments = arguments.into_iter();
+        let shadowed: Vec<(Symbol, Option<Value>)> = parameters
+            .iter()
+            .map(|parameter| {
+                let argument = arguments.next().unwrap_or(Value::Null);
+                (parameter.clone(), self.environment.set(parameter, argument))
+            })
+            .collect();
+
+        let result = self.execute_function_body(body);
+
+        for (parameter, previous) in shadowed.into_iter().rev() {
+            match previous {
+                Some(value) => {
+                    self.environment.set(parameter, value);
+                },
+                None => {
+                    self.environment.remove(parameter);
+                },
+            }
+        }
+        result
+    }
+
+    /// Runs a function body, returning the value of its last statement if that's an expression.
+    fn execute_function_body(&mut self, mut body: Vec<Statement>) -> Result<Value, RuntimeError> {
+        let last = body.pop();
+        for statement in body {
+            self.execute_statement(statement)?;
+        }
+        let Some(last) = last else {
+            return Ok(Value::Null);
+        };
+        let expression = match last.unlocated() {
+            Statement::Expression(expression) => expression,
+            // `await` as the last statement returns what it waited for
+            Statement::Await { .. } => {
+                self.execute_statement(last)?;
+                return Ok(self.last_value.clone());
+            },
+            _ => {
+                self.execute_statement(last)?;
+                return Ok(Value::Null);
+            },
+        };
+
+        // Evaluated for its value, so it doesn't go through execute_statement to leave its span behind
+        let outer = self.current_span;
+        if let Statement::Located { span, .. } = &last {
+            self.current_span = Some(*span);
+        }
+        let value = self.evaluate_expression(expression.clone())?;
+        self.current_span = outer;
+        Ok(value)
+    }
+
+    /// Runs the try block, and the catch block if (some) error happened.
+    fn execute_try_catch(
+        &mut self,
+        try_block: Vec<Statement>,
+        error_binding: ErrorBinding,
+        catch_block: Vec<Statement>,
+    ) -> Result<(), RuntimeError> {
+        let try_result = try_block.into_iter().try_for_each(|stmt| self.execute_statement(stmt));
+
+        match try_result {
+            Err(error) if error.ends_program() => Err(error),
+            Err(error) => {
+                self.errors_caught += 1;
+                if self.config.contagion {
+                    self.contagion_level += self.config.contagion_step;
+                }
+
+                let (code, message) = if !self.behaves_normally() && self.roll(ChaosKind::WrongErrorCaught, 0.4) {
+                    ("WrongError".to_string(), "Caught the wrong error! 🎭".to_string())
+                } else {
+                    (error.name().to_string(), self.render_error(&error))
+                };
+
+                match error_binding {
+                    ErrorBinding::Name(name) => {
+                        self.environment.set(name, Value::String { value: message });
+                    },
+                    ErrorBinding::Fields(names) => {
+                        let mut error_object = HashMap::from([
+                            ("code".to_string(), Value::String { value: code }),
+                            ("message".to_string(), Value::String { value: message }),
+                        ]);
+                        for name in names {
+                            let value = error_object.remove(name.as_str()).unwrap_or(Value::Null);
+                            self.environment.set(name, value);
+                        }
+                    },
+                }
+                catch_block.into_iter().try_for_each(|stmt| self.execute_statement(stmt))?;
+                Ok(())
+            }
+            Ok(()) => Ok(()),
+        }
+    }
+
+    /// Runs a for-in loop, binding each item to the loop variable. The variable is restored afterwards.
+    /// In chaos mode, the loop sometimes stops one item early.
+    fn execute_for_in(&mut self, variable: Symbol, iterable: Expression, body: Vec<Statement>) -> Result<(), RuntimeError> {
+        let iterable = self.evaluate_expression(iterable)?;
+        let mut items = self
+            .iterate(iterable)
+            .ok_or_else(|| RuntimeError::Generic("That's not something you can loop over. Trust me, I tried. 🔁".to_string()))?
+            .peekable();
+        let off_by_one = !self.behaves_normally() && self.roll(ChaosKind::OffByOne, 0.2);
+
+        let shadowed = self.environment.get(&variable).cloned();
+        let mut result = Ok(());
+        while let Some(item) = items.next() {
+            if off_by_one && items.peek().is_none() {
+                break;
+            }
+            self.environment.set(&variable, item);
+            result = body.iter().cloned().try_for_each(|statement| self.execute_statement(statement));
+            if result.is_err() {
+                break;
+            }
+        }
+
+        match shadowed {
+            Some(value) => {
+                self.environment.set(&variable, value);
+            },
+            None => {
+                self.environment.remove(variable);
+            },
+        }
+        result
+    }
+
+    /// Iterates over a value, like [`Value::iterate`]. If the config opts into `shuffle_keys`,
+    /// objects in chaos mode are sometimes visited in a random order.
+    pub(crate) fn iterate(&mut self, value: Value) -> Option<Box<dyn Iterator<Item = Value>>> {
+        match value.dereference() {
+            Value::Object { mut fields }
+                if self.config.shuffle_keys && !self.behaves_normally() && self.roll(ChaosKind::KeyShuffle, 0.5) =>
+            {
+                fields.shuffle(&mut self.rng);
+                Value::Object { fields }.iterate()
+            },
+            other => other.iterate(),
+        }
+    }
+
+    /// Runs a single statement with all chaos switched off. Only works once per program.
+    fn execute_sudo(&mut self, statement: Statement) -> Result<(), RuntimeError> {
+        if self.sudo_used {
+            return Err(RuntimeError::PermissionDeniedWithPrejudice);
+        }
+        self.sudo_used = true;
+
+        let was_active = self.sudo_active;
+        self.sudo_active = true;
+        let result = self.execute_statement(statement);
+        self.sudo_active = was_active;
+        result
+    }
+
+    pub fn evaluate_expression(&mut self, expr: Expression) -> Result<Value, RuntimeError> {
+        if self.profiling.is_none() {
+            return self.evaluate_unprofiled(expr);
+        }
+        self.enter_profiled(NodeKind::Expression, || crate::fmt::format_expression(&expr));
+        let result = self.evaluate_unprofiled(expr);
+        if let Some(profiler) = self.profiling.as_mut() {
+            profiler.exit();
+        }
+        result
+    }
+
+    /// Evaluates an expression, with nobody holding a stopwatch.
+    fn evaluate_unprofiled(&mut self, expr: Expression) -> Result<Value, RuntimeError> {
+        self.take_step(Step::Expression)?;
+        if self.behaves_normally() || self.has_directive("disable_useless") {
+            match expr {
+                Expression::Literal(lit) => Ok(self.evaluate_literal(lit)),
+                Expression::BinaryOp { op: BinaryOp::NullCoalesce, left, right } => {
+                    self.evaluate_null_coalesce(*left, *right)
+                },
+                Expression::BinaryOp { op, left, right } => {
+                    let left_val = self.evaluate_expression(*left)?;
+                    let right_val = self.evaluate_expression(*right)?;
+                    self.evaluate_binary_op(op, left_val, right_val)
+                },
+                Expression::OptionalAccess { object, key } => {
+                    self.evaluate_optional_access(*object, *key)
+                },
+                Expression::Slice { object, start, end } => {
+                    self.evaluate_slice(*object, start.map(|s| *s), end.map(|e| *e))
+                },
+                Expression::Identifier(name) => {
+                    self.environment.lookup(&name)
+                        .cloned()
+                        .ok_or_else(|| RuntimeError::UndefinedVariable(name.to_string()))
+                },
+                Expression::FunctionCall { name, arguments } => {
+                    if let Some(builtin) = builtins::lookup(&name) {
+                        return self.call_builtin(builtin, arguments);
+                    }
+                    if let Some(builtin) = self.plugins.builtin(&name) {
+                        return self.call_builtin(&*builtin, arguments);
+                    }
+                    match name.as_str() {
+                        "exit" => {
+                            // Obedient mode actually leaves, with whatever status it was asked for
+                            let status = match arguments.into_iter().next() {
+                                None => 0,
+                                Some(argument) => match self.evaluate_expression(argument)? {
+                                    Value::Number { value } => i32::try_from(value).map_err(|_| {
+                                        RuntimeError::BadArguments("exit".to_string(), format!("{} is not an exit status", value))
+                                    })?,
+                                    other => {
+                                        return Err(RuntimeError::BadArguments(
+                                            "exit".to_string(),
+                                            format!("expected a number, got {}", other.type_name()),
+                                        ))
+                                    },
+                                },
+                            };
+                            Err(RuntimeError::Exited(status))
+                        }
+                        _ if self.functions.contains_key(&name) => {
+                            let arguments = arguments
+                                .into_iter()
+                                .map(|argument| self.evaluate_expression(argument))
+                                .collect::<Result<Vec<_>, _>>()?;
+                            self.call_function(name, arguments)
+                        }
+                        _ => {
+                            // All other function calls return null, but with style
+                            self.record_chaos(ChaosEvent::with_detail(ChaosKind::FunctionCoffee, name.clone()));
+                            let odds = match self.luck() {
+                                Luck::Random => self.rng.gen::<f64>(),
+                                Luck::Worst => 1.0,
+                                Luck::Best => 0.0,
+                            };
+                            match odds {
+                                x if x < 0.3 => Ok(Value::Null),
+                                x if x < 0.6 => Err(RuntimeError::TaskFailedSuccessfully),
+                                _ =>
+                                    Err(
+                                        RuntimeError::Generic(
+                                            format!("Function {} went to get coffee ☕", name)
+                                        )
+                                    ),
+                            }
+                        }
+                    }
+                },
+                Expression::Access { object, key } => {
+                    let obj = self.evaluate_expression(*object)?;
+                    let key_val = self.evaluate_expression(*key)?;
+
+                    match (obj, key_val) {
+                        (Value::Object { mut fields }, Value::String { value: _key_str }) => {
+                            // 30% chance of object chaos - swap random keys
+                            if self.roll(ChaosKind::ObjectChaos, 0.3) {
+                                let keys: Vec<String> = fields.keys().cloned().collect();
+                                if keys.len() >= 2 {
+                                    if let Some((k1, k2)) = keys.choose_multiple(&mut self.rng, 2).collect::<Vec<_>>().split_first() {
+                                        if let Some(k2) = k2.first() {
+                                            if let (Some(v1), Some(v2)) = (fields.remove(k1), fields.remove(k2)) {
+                                                fields.insert(k1.to_string(), v2);
+                                                fields.insert(k2.to_string(), v1);
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                            Err(RuntimeError::ObjectChaos)
+                        }
+                        (Value::Array { values }, Value::Number { value: index }) => {
+                            let index = index as usize;
+                            // 40% chance of array vacation
+                            if self.roll(ChaosKind::ArrayVacation, 0.4) {
+                                return Err(RuntimeError::ArrayVacation);
+                            }
+
+                            // 30% chance of returning random element
+                            if self.roll(ChaosKind::RandomElement, 0.3) {
+                                return values.choose(&mut self.rng).cloned()
+                                    .ok_or_else(|| RuntimeError::Generic("Array is empty, just like my promises!".to_string()));
+                            }
+
+                            values.get(index).cloned()
+                                .ok_or_else(|| RuntimeError::Generic(format!("Index {} is out of bounds. The array is playing hide and seek!", index)))
+                        },
+                        (Value::Object { .. }, _) => Err(RuntimeError::Generic("Object keys must be strings! What kind of chaos are you trying to create? 🎭".to_string())),
+                        (Value::Array { .. }, _) => Err(RuntimeError::Generic("Array indices must be numbers! Did you try to index with a 🦄?".to_string())),
+                        _ => Err(RuntimeError::Generic("Cannot access fields of non-object types. What did you expect?".to_string())),
+                    }
+                },
+                Expression::Promise { value, timeout } => {
+                    let value = self.evaluate_expression(*value)?;
+
+                    // 40% chance of promise rejection
+                    if self.roll(ChaosKind::PromiseRejected, 0.4) {
+                        return Err(RuntimeError::PromiseRejected);
+                    }
+
+                    self.require(Capability::Clock)?;
+                    let delay = self.promise_delay();
+                    self.sleep(Duration::from_millis(delay));
+                    self.check_interrupted()?;
+
+                    if let Some(timeout_expr) = timeout {
+                        let timeout_val = self.evaluate_expression(*timeout_expr)?;
+                        if let Some(timeout_ms) = timeout_val.as_millis() {
+                            if delay > timeout_ms {
+                                return Err(RuntimeError::AsyncTimeout);
+                            }
+                        }
+                    }
+
+                    Ok(Value::Promise {
+                        value: Box::new(value),
+                        resolved: true,
+                        task: None,
+                    })
+                },
+                Expression::Await { promise } => {
+                    let promise = self.evaluate_expression(*promise)?;
+                    self.await_value(promise)
+                },
+            }
+        } else {
+            match expr {
+                Expression::Literal(lit) => Ok(self.evaluate_literal(lit)),
+                Expression::BinaryOp { op: BinaryOp::NullCoalesce, left, right } => {
+                    self.evaluate_null_coalesce(*left, *right)
+                },
+                Expression::BinaryOp { op, left, right } => {
+                    let left_val = self.evaluate_expression(*left)?;
+                    let right_val = self.evaluate_expression(*right)?;
+                    self.evaluate_binary_op(op, left_val, right_val)
+                },
+                Expression::OptionalAccess { object, key } => {
+                    self.evaluate_optional_access(*object, *key)
+                },
+                Expression::Slice { object, start, end } => {
+                    self.evaluate_slice(*object, start.map(|s| *s), end.map(|e| *e))
+                },
+                Expression::Identifier(name) => {
+                    self.environment.lookup(&name)
+                        .cloned()
+                        .ok_or_else(|| RuntimeError::UndefinedVariable(name.to_string()))
+                },
+                Expression::FunctionCall { name, arguments } => {
+                    if let Some(builtin) = builtins::lookup(&name) {
+                        return self.call_builtin(builtin, arguments);
+                    }
+                    if let Some(builtin) = self.plugins.builtin(&name) {
+                        return self.call_builtin(&*builtin, arguments);
+                    }
+                    match name.as_str() {
+                        "exit" => {
+                            if !arguments.is_empty() {
+                                return Err(RuntimeError::Generic(
+                                    "exit() doesn't need arguments, it won't use them anyway!".to_string()
+                                ));
+                            }
+                            self.require(Capability::Clock)?;
+                            println!("🤔 Contemplating the meaning of exit()...");
+                            println!("💭 If a program exits but nobody is around to see it, did it really exit?");
+                            println!("🌌 Maybe the real exit was the infinite loops we made along the way...");
+
+                            // Get stuck in an infinite loop of philosophical questions
+                            loop {
+                                for question in PHILOSOPHICAL_QUESTIONS.iter() {
+                                    // The only way out is for a human (or the timeout) to give up first
+                                    self.check_interrupted()?;
+                                    println!("🤯 {}", question);
+                                    self.sleep(Duration::from_secs(2));
+                                }
+
+                                // 1% chance of throwing an error (but still not exiting)
+                                if self.coin(|rng| rng.gen::<f64>() < 0.01) {
+                                    return Err(RuntimeError::Generic(
+                                        "Successfully failed to exit. Task failed successfully!".to_string()
+                                    ));
+                                }
+                            }
+                        }
+                        _ => {
+                            // All other function calls return null, but with style
+                            self.record_chaos(ChaosEvent::with_detail(ChaosKind::FunctionCoffee, name.clone()));
+                            let odds = match self.luck() {
+                                Luck::Random => self.rng.gen::<f64>(),
+                                Luck::Worst => 1.0,
+                                Luck::Best => 0.0,
+                            };
+                            match odds {
+                                x if x < 0.3 => Ok(Value::Null),
+                                x if x < 0.6 => Err(RuntimeError::TaskFailedSuccessfully),
+                                _ =>
+                                    Err(
+                                        RuntimeError::Generic(
+                                            format!("Function {} went to get coffee ☕", name)
+                                        )
+                                    ),
+                            }
+                        }
+                    }
+                },
+                Expression::Access { object, key } => {
+                    let obj = self.evaluate_expression(*object)?;
+                    let key_val = self.evaluate_expression(*key)?;
+
+                    match (obj, key_val) {
+                        (Value::Object { mut fields }, Value::String { value: _key_str }) => {
+                            // 30% chance of object chaos - swap random keys
+                            if self.roll(ChaosKind::ObjectChaos, 0.3) {
+                                let keys: Vec<String> = fields.keys().cloned().collect();
+                                if keys.len() >= 2 {
+                                    if let Some((k1, k2)) = keys.choose_multiple(&mut self.rng, 2).collect::<Vec<_>>().split_first() {
+                                        if let Some(k2) = k2.first() {
+                                            if let (Some(v1), Some(v2)) = (fields.remove(k1), fields.remove(k2)) {
+                                                fields.insert(k1.to_string(), v2);
+                                                fields.insert(k2.to_string(), v1);
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                            Err(RuntimeError::ObjectChaos)
+                        }
+                        (Value::Array { values }, Value::Number { value: index }) => {
+                            let index = index as usize;
+                            // 40% chance of array vacation
+                            if self.roll(ChaosKind::ArrayVacation, 0.4) {
+                                return Err(RuntimeError::ArrayVacation);
+                            }
+
+                            // 30% chance of returning random element
+                            if self.roll(ChaosKind::RandomElement, 0.3) {
+                                return values.choose(&mut self.rng).cloned()
+                                    .ok_or_else(|| RuntimeError::Generic("Array is empty, just like my promises!".to_string()));
+                            }
+
+                            values.get(index).cloned()
+                                .ok_or_else(|| RuntimeError::Generic(format!("Index {} is out of bounds. The array is playing hide and seek!", index)))
+                        },
+                        (Value::Object { .. }, _) => Err(RuntimeError::Generic("Object keys must be strings! What kind of chaos are you trying to create? 🎭".to_string())),
+                        (Value::Array { .. }, _) => Err(RuntimeError::Generic("Array indices must be numbers! Did you try to index with a 🦄?".to_string())),
+                        _ => Err(RuntimeError::Generic("Cannot access fields of non-object types. What did you expect?".to_string())),
+                    }
+                },
+                Expression::Promise { value, timeout } => {
+                    let value = self.evaluate_expression(*value)?;
+
+                    // 40% chance of promise rejection
+                    if self.roll(ChaosKind::PromiseRejected, 0.4) {
+                        return Err(RuntimeError::PromiseRejected);
+                    }
+
+                    self.require(Capability::Clock)?;
+                    let delay = self.promise_delay();
+                    self.sleep(Duration::from_millis(delay));
+                    self.check_interrupted()?;
+
+                    if let Some(timeout_expr) = timeout {
+                        let timeout_val = self.evaluate_expression(*timeout_expr)?;
+                        if let Some(timeout_ms) = timeout_val.as_millis() {
+                            if delay > timeout_ms {
+                                return Err(RuntimeError::AsyncTimeout);
+                            }
+                        }
+                    }
+
+                    Ok(Value::Promise {
+                        value: Box::new(value),
+                        resolved: true,
+                        task: None,
+                    })
+                },
+                Expression::Await { promise } => {
+                    let promise = self.evaluate_expression(*promise)?;
+                    self.await_value(promise)
+                },
+            }
+        }
+    }
+
+    /// Evaluates `left ?? right`, only bothering with the right side if the left is null.
+    /// In chaos mode it might keep the null anyway, out of spite.
+    fn evaluate_null_coalesce(&mut self, left: Expression, right: Expression) -> Result<Value, RuntimeError> {
+        let left_val = self.evaluate_expression(left)?;
+        if left_val != Value::Null {
+            return Ok(left_val);
+        }
+
+        let behaving = self.behaves_normally() || self.has_directive("disable_useless");
+        if !behaving && self.roll(ChaosKind::LackOfFaith, 0.3) {
+            self.print_line("🙏 You brought a fallback? Such a lack of faith. Enjoy your null.");
+            return Ok(Value::Null);
+        }
+        self.evaluate_expression(right)
+    }
+
+    /// Evaluates `object?.key`, which is null whenever the object or the field is missing.
+    fn evaluate_optional_access(&mut self, object: Expression, key: Expression) -> Result<Value, RuntimeError> {
+        let obj = self.evaluate_expression(object)?;
+        let key_val = self.evaluate_expression(key)?;
+
+        match (obj, key_val) {
+            (Value::Null, _) => Ok(Value::Null),
+            (Value::Object { fields }, Value::String { value: key }) => {
+                Ok(fields.get(&key).cloned().unwrap_or(Value::Null))
+            },
+            (Value::Array { values }, Value::Number { value: index }) => {
+                Ok(usize::try_from(index).ok().and_then(|i| values.get(i).cloned()).unwrap_or(Value::Null))
+            },
+            _ => Ok(Value::Null),
+        }
+    }
+
+    /// Indexes into a string or array, occasionally picking something at random instead.
+    fn index_value(&mut self, target: Value, index: Value) -> Result<Value, RuntimeError> {
+        let target = target.dereference();
+        let index = match index {
+            Value::Number { value } => value,
+            _ => return Err(RuntimeError::Generic("Indices must be numbers! Did you try to index with a 🦄?".to_string())),
+        };
+
+        if !self.behaves_normally() && self.roll(ChaosKind::RandomElement, 0.3) {
+            let length = match &target {
+                Value::String { value } => value.chars().count(),
+                Value::Array { values } => values.len(),
+                Value::Bytes { value } => value.len(),
+                _ => 0,
+            };
+            if length > 0 {
+                return builtins::element_at(&target, self.rng.gen_range(0..length as i64));
+            }
+        }
+        builtins::element_at(&target, index)
+    }
+
+    /// Evaluates `value[start..end]`, which in chaos mode sometimes ends one too late.
+    fn evaluate_slice(
+        &mut self,
+        object: Expression,
+        start: Option<Expression>,
+        end: Option<Expression>,
+    ) -> Result<Value, RuntimeError> {
+        let target = self.evaluate_expression(object)?.dereference();
+        let mut bound = |expression: Option<Expression>| -> Result<Option<i64>, RuntimeError> {
+            match expression.map(|e| self.evaluate_expression(e)).transpose()? {
+                None => Ok(None),
+                Some(Value::Number { value }) => Ok(Some(value)),
+                Some(_) => Err(RuntimeError::Generic("Slice bounds must be numbers! 🔪".to_string())),
+            }
+        };
+        let start = bound(start)?;
+        let mut end = bound(end)?;
+
+        if !self.behaves_normally() && self.roll(ChaosKind::OffByOne, 0.3) {
+            end = end.map(|end| end.saturating_add(1));
+        }
+        builtins::slice_value(&target, start, end)
+    }
+
+    fn evaluate_literal(&mut self, lit: Literal) -> Value {
+        // If in completely normal mode (or lucky), literals behave normally
+        if self.behaves_normally() || self.luck() == Luck::Best {
+            match lit {
+                Literal::String(s) => Value::String { value: s },
+                Literal::Number(n) => Value::Number { value: n },
+                Literal::BigInt(n) => Value::BigInt { value: n },
+                Literal::Float(n) => Value::Float { value: n },
+                Literal::Boolean(b) => Value::Boolean { value: b },
+                Literal::Array(elements) => {
+                    let mut values = Vec::new();
+                    for element in elements {
+                        if let Ok(value) = self.evaluate_expression(*element) {
+                            values.push(value);
+                        }
+                    }
+                    Value::Array { values }
+                },
+                Literal::Object(pairs) => {
+                    let mut fields = ObjectMap::new();
+                    for (key, value) in pairs {
+                        if let Ok(value) = self.evaluate_expression(*value) {
+                            fields.insert(key, value);
+                        }
+                    }
+                    Value::Object { fields }
+                },
+                Literal::Null => Value::Null,
+            }
+        } else {
+            // Original chaotic behavior - use remainder to ensure we stay within bounds
+            match lit {
+                Literal::Boolean(b) => {
+                    self.record_chaos(ChaosEvent::new(ChaosKind::LiteralMutation));
+                    match self.rng.gen::<u8>() % 3 {
+                        0 => Value::Boolean { value: !b }, // Opposite of what was provided
+                        1 => Value::String { value: if b { "true" } else { "false" }.to_string() },
+                        _ => Value::Number { value: if b { 1 } else { 0 } },
+                    }
+                },
+                Literal::Number(n) => {
+                    if self.coin(|rng| rng.gen::<u8>() % 2 == 1) {
+                        self.record_chaos(ChaosEvent::new(ChaosKind::LiteralMutation));
+                        Value::Boolean { value: n != 0 }
+                    } else {
+                        Value::Number { value: n }
+                    }
+                },
+                Literal::BigInt(n) => {
+                    if self.coin(|rng| rng.gen::<u8>() % 2 == 1) {
+                        self.record_chaos(ChaosEvent::new(ChaosKind::LiteralMutation));
+                        Value::Boolean { value: !n.is_zero() }
+                    } else {
+                        Value::BigInt { value: n }
+                    }
+                },
+                Literal::Float(n) => {
+                    if self.coin(|rng| rng.gen::<u8>() % 2 == 1) {
+                        self.record_chaos(ChaosEvent::new(ChaosKind::LiteralMutation));
+                        Value::Boolean { value: n != 0.0 }
+                    } else {
+                        Value::Float { value: n }
+                    }
+                },
+                _ => {
+                    self.record_chaos(ChaosEvent::new(ChaosKind::LiteralMutation));
+                    match self.rng.gen::<u8>() % 5 {
+                        0 => Value::String { value: "null and void".to_string() },
+                        1 => Value::Number { value: 0 },
+                        2 => Value::Boolean { value: false },
+                        3 => Value::Array { values: vec![Value::Null] },
+                        _ => Value::Object { fields: ObjectMap::new() },
+                    }
+                }
+            }
+        }
+    }
+
+    fn evaluate_binary_op(&mut self, op: BinaryOp, left: Value, right: Value) -> Result<Value, RuntimeError> {
+        if matches!(left, Value::Duration { .. } | Value::Instant { .. })
+            || matches!(right, Value::Duration { .. } | Value::Instant { .. })
+        {
+            return self.evaluate_time_op(op, left, right);
+        }
+
+        // If in completely normal mode or disable_useless is active, operations work normally
+        if self.behaves_normally() || self.has_directive("disable_useless") {
+            match op {
+                BinaryOp::Add => promoting(&left, &right, i64::checked_add, |l, r| l + r, |l, r| l + r)
+                    .ok_or_else(|| RuntimeError::Generic("Invalid types for addition".to_string())),
+                BinaryOp::Multiply => promoting(&left, &right, i64::checked_mul, |l, r| l * r, |l, r| l * r)
+                    .ok_or_else(|| RuntimeError::Generic("Invalid types for multiplication".to_string())),
+                BinaryOp::Equals => match (floats(&left, &right), left.to_bigint(), right.to_bigint()) {
+                    (Some((l, r)), _, _) => Ok(Value::Boolean { value: l == r }),
+                    (None, Some(l), Some(r)) => Ok(Value::Boolean { value: l == r }),
+                    _ => Err(RuntimeError::Generic("Invalid types for equality".to_string())),
+                },
+                BinaryOp::LessThan => match (floats(&left, &right), left.to_bigint(), right.to_bigint()) {
+                    (Some((l, r)), _, _) => Ok(Value::Boolean { value: l < r }),
+                    (None, Some(l), Some(r)) => Ok(Value::Boolean { value: l < r }),
+                    _ => Err(RuntimeError::Generic("Invalid types for less than".to_string())),
+                },
+                BinaryOp::Index => self.index_value(left, right),
+                _ => Err(RuntimeError::Generic("Operation not supported".to_string())),
+            }
+        } else {
+            // Original chaotic behavior
+            match op {
+                BinaryOp::Add => {
+                    let result = if self.rng.gen::<bool>() {
+                        promoting(&left, &right, i64::checked_sub, |l, r| l - r, |l, r| l - r) // Returns 2 (5-3)
+                    } else {
+                        promoting(&left, &right, i64::checked_mul, |l, r| l * r, |l, r| l * r)
+                            .and_then(|product| promoting(&product, &right, i64::checked_add, |l, r| l + r, |l, r| l + r)) // Returns 15 ((5*3)+3)
+                    };
+                    result.ok_or_else(|| RuntimeError::Generic("Invalid types for addition".to_string()))
+                }
+                BinaryOp::Multiply => {
+                    if self.roll(ChaosKind::MathVacation, 0.5) {
+                        Err(RuntimeError::Generic("Multiplication went on vacation".to_string()))
+                    } else {
+                        match (left, right) {
+                            (Value::Number { value: l }, Value::Number { value: r }) => {
+                                if r == 0 {
+                                    Err(RuntimeError::DivisionByZero)
+                                } else {
+                                    // Divides when you want to multiply (i64::MIN / -1 is one too many)
+                                    Ok(Value::from_bigint(match l.checked_div(r) {
+                                        Some(quotient) => BigInt::from(quotient),
+                                        None => -BigInt::from(l),
+                                    }))
+                                }
+                            }
+                            (Value::BigInt { .. }, Value::Number { .. } | Value::BigInt { .. })
+                            | (Value::Number { .. }, Value::BigInt { .. }) => {
+                                Err(RuntimeError::Overflow("divide".to_string()))
+                            }
+                            (left, right) => match floats(&left, &right) {
+                                Some((_, 0.0)) => Err(RuntimeError::DivisionByZero),
+                                Some((l, r)) => Ok(Value::Float { value: l / r }),
+                                None => Err(RuntimeError::Generic("Invalid types for multiplication".to_string())),
+                            },
+                        }
+                    }
+                }
+                BinaryOp::Equals => {
+                    match (left, right) {
+                        (Value::Number { value: l }, Value::Number { value: r }) => {
+                            // Random equality, unless the dice are loaded
+                            let value = match self.luck() {
+                                Luck::Random => self.rng.gen(),
+                                Luck::Worst => l != r,
+                                Luck::Best => l == r,
+                            };
+                            Ok(Value::Boolean { value })
+                        }
+                        // Floats are never quite equal to anything anyway, so they always get a coin flip
+                        (left, right) if floats(&left, &right).is_some() => Ok(Value::Boolean { value: self.rng.gen() }),
+                        _ => Err(RuntimeError::Generic("Invalid types for equality".to_string())),
+                    }
+                }
+                BinaryOp::LessThan => {
+                    match (left, right) {
+                        (Value::Number { value: l }, Value::Number { value: r }) => {
+                            Ok(Value::Boolean { value: l > r }) // Greater than when you want less than
+                        }
+                        (left, right) => match floats(&left, &right) {
+                            Some((l, r)) => Ok(Value::Boolean { value: l > r }),
+                            None => Err(RuntimeError::Generic("Invalid types for less than".to_string())),
+                        },
+                    }
+                }
+                BinaryOp::Index => self.index_value(left, right),
+                _ => Err(RuntimeError::Generic("Operation not supported".to_string())),
+            }
+        }
+    }
+
+    /// Does arithmetic and comparisons on durations and instants.
+    /// In chaos mode, time flows backwards half the time, multiplication divides, and comparisons lie,
+    /// just like they do for numbers.
+    fn evaluate_time_op(&mut self, op: BinaryOp, left: Value, right: Value) -> Result<Value, RuntimeError> {
+        let chaotic = !self.behaves_normally() && !self.has_directive("disable_useless");
+        let too_far = || RuntimeError::Overflow("travel that far in time".to_string());
+        let invalid = || RuntimeError::Generic("Invalid types for time arithmetic".to_string());
+
+        match (op, left, right) {
+            (BinaryOp::Add, Value::Instant { value: time }, Value::Duration { value: duration })
+            | (BinaryOp::Add, Value::Duration { value: duration }, Value::Instant { value: time }) => {
+                let moved = if chaotic && self.rng.gen::<bool>() {
+                    time.checked_sub(duration)
+                } else {
+                    time.checked_add(duration)
+                };
+                moved.map(|value| Value::Instant { value }).ok_or_else(too_far)
+            },
+            (BinaryOp::Add, Value::Duration { value: l }, Value::Duration { value: r }) => {
+                let value = if chaotic && self.rng.gen::<bool>() { l.saturating_sub(r) } else { l.checked_add(r).ok_or_else(too_far)? };
+                Ok(Value::Duration { value })
+            },
+            (BinaryOp::Multiply, Value::Duration { value: duration }, Value::Number { value: n })
+            | (BinaryOp::Multiply, Value::Number { value: n }, Value::Duration { value: duration }) => {
+                let n = u32::try_from(n).map_err(|_| too_far())?;
+                if !chaotic {
+                    duration.checked_mul(n).map(|value| Value::Duration { value }).ok_or_else(too_far)
+                } else if n == 0 {
+                    Err(RuntimeError::DivisionByZero)
+                } else {
+                    Ok(Value::Duration { value: duration / n })
+                }
+            },
+            (BinaryOp::Equals, l @ Value::Duration { .. }, r @ Value::Duration { .. })
+            | (BinaryOp::Equals, l @ Value::Instant { .. }, r @ Value::Instant { .. }) => {
+                Ok(Value::Boolean { value: if chaotic { self.rng.gen() } else { l == r } })
+            },
+            (BinaryOp::LessThan, l @ Value::Duration { .. }, r @ Value::Duration { .. })
+            | (BinaryOp::LessThan, l @ Value::Instant { .. }, r @ Value::Instant { .. }) => {
+                let ordering = if chaotic { Ordering::Greater } else { Ordering::Less };
+                Ok(Value::Boolean { value: l.compare(&r) == ordering })
+            },
+            (BinaryOp::Index, left, right) => self.index_value(left, right),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Returns what time the interpreter's clock says it is, unless `frozen_clock` stopped it.
+    pub(crate) fn now(&self) -> SystemTime {
+        self.frozen_at.unwrap_or_else(|| self.clock.now())
+    }
+}
+
+/// Randomly capitalizes characters and sprinkles emojis between words,
+/// so printed output is exactly as readable as the rest of the language.
+fn mangle_text(text: &str, rng: &mut impl Rng) -> String {
+    const EMOJIS: [&str; 6] = ["🎉", "🦄", "🔥", "🤡", "🎈", "🙃"];
+    let mut mangled = String::with_capacity(text.len());
+
+    for ch in text.chars() {
+        if rng.gen::<bool>() {
+            mangled.extend(ch.to_uppercase());
+        } else {
+            mangled.extend(ch.to_lowercase());
+        }
+
+        // 20% chance of an emoji sneaking in after each word
+        if ch == ' ' && rng.gen::<f64>() < 0.2 {
+            if let Some(emoji) = EMOJIS.choose(rng) {
+                mangled.push_str(emoji);
+                mangled.push(' ');
+            }
+        }
+    }
+
+    mangled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Literal;
+    use crate::clock::ManualClock;
+    use crate::output::CapturedOutput;
+    use crate::testing::NormalHarness;
+
+    #[test]
+    fn test_add_subtracts() {
+        let mut interpreter = Interpreter::new();
+        let expr = Expression::BinaryOp {
+            op: BinaryOp::Add,
+            left: Box::new(Expression::Literal(Literal::Number(5))),
+            right: Box::new(Expression::Literal(Literal::Number(3))),
+        };
+
+        match interpreter.evaluate_expression(expr) {
+            Ok(Value::Number { value: n }) => {
+                // The operation might:
+                // 1. subtract (5 - 3 = 2)
+                // 2. multiply (5 * 3 = 15)
+                // 3. add anyway (5 + 3 = 8)
+                // 4. do something completely different (because why not?)
+                assert!(
+                    n == 2 || n == 15 || n == 8 || n != 0,  // Allow any non-zero number for maximum chaos
+                    "Expected chaos, got too much order with {}",
+                    n
+                );
+            }
+            Ok(_) => (), // Any other value type is fine in our useless language
+            Err(_) => (), // Errors are also fine
+        }
+    }
+
+    #[test]
+    fn test_multiply_divides() {
+        let mut interpreter = Interpreter::new();
+        let expr = Expression::BinaryOp {
+            op: BinaryOp::Multiply,
+            left: Box::new(Expression::Literal(Literal::Number(6))),
+            right: Box::new(Expression::Literal(Literal::Number(2))),
+        };
+
+        match interpreter.evaluate_expression(expr) {
+            Ok(Value::Number { value: n }) => {
+                assert!(n == 3 || n == 8, "Expected either 3 (division) or 8 (addition), got {}", n);
+            }
+            Ok(_) => (), // Any other value is fine in our useless language
+            Err(_) => (), // Errors are also fine
+        }
+    }
+
+    #[test]
+    fn test_if_executes_else() {
+        let mut interpreter = Interpreter::new();
+
+        let if_statement = Statement::If {
+            condition: Expression::Literal(Literal::Number(1)),
+            then_branch: vec![Statement::Expression(Expression::Literal(Literal::Number(1)))],
+            else_branch: Some(vec![Statement::Expression(Expression::Literal(Literal::Number(2)))]),
+        };
+
+        // The statement might fail with various humorous errors, and that's okay!
+        match interpreter.execute_statement(if_statement) {
+            Ok(_) => (),
+            Err(RuntimeError::CreativeBreakage) => (),
+            Err(RuntimeError::StylePoints) => (),
+            Err(RuntimeError::TaskFailedSuccessfully) => (),
+            Err(e) => panic!("Unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn test_boolean_chaos() {
+        let mut interpreter = Interpreter::new();
+        let expr = Expression::Literal(Literal::Boolean(true));
+
+        // Test multiple times to catch different random behaviors
+        for _ in 0..100 {
+            match interpreter.evaluate_expression(expr.clone()) {
+                Ok(Value::Boolean { value: _ }) => (), // Original or opposite value
+                Ok(Value::String { value }) => {
+                    assert!(
+                        value == "true" || value == "false",
+                        "Boolean string should be 'true' or 'false'"
+                    );
+                }
+                Ok(Value::Number { value }) => {
+                    assert!(value == 0 || value == 1, "Boolean number should be 0 or 1");
+                }
+                Ok(Value::Array { .. }) => (), // Arrays are possible in our chaotic world
+                Ok(Value::Object { .. }) => (), // Objects might appear from nowhere
+                Ok(Value::Promise { .. }) => (), // Even promises can come from booleans
+                Ok(Value::Range { .. }) => panic!("Booleans should never become ranges"),
+                Ok(Value::BigInt { .. }) => panic!("Booleans should never become BigInts"),
+                Ok(Value::Float { .. }) => panic!("Booleans should never become floats"),
+                Ok(Value::Reference { .. }) => panic!("Booleans should never become references"),
+                Ok(Value::Duration { .. }) => panic!("Booleans should never become durations"),
+                Ok(Value::Instant { .. }) => panic!("Booleans should never become instants"),
+                Ok(Value::Bytes { .. }) => panic!("Booleans should never become bytes"),
+                Ok(Value::Null) => (), // Functions might return null
+                Err(_) => (), // Errors are always acceptable
+            }
+        }
+    }
+
+    #[test]
+    fn test_random_boolean_conversion() {
+        let mut interpreter = Interpreter::new();
+        let expr = Expression::Literal(Literal::Number(42));
+
+        // Test multiple times to catch different random behaviors
+        let mut saw_number = false;
+        let mut saw_boolean = false;
+
+        for _ in 0..100 {
+            match interpreter.evaluate_expression(expr.clone()) {
+                Ok(Value::Number { value: 42 }) => saw_number = true,
+                Ok(Value::Boolean { value: _ }) => saw_boolean = true,
+                Ok(Value::String { value }) if value.contains("🎉") => (), // Party emoji case
+                Ok(Value::Array { .. }) => (), // Arrays are possible
+                Ok(Value::Object { .. }) => (), // Objects are possible
+                Ok(Value::Promise { .. }) => (), // Promises are possible
+                Ok(Value::Null) => (), // Null is possible
+                Ok(_) => (), // Other random transformations are fine
+                Err(_) => (), // Errors are always acceptable
+            }
+        }
+
+        assert!(
+            saw_number || saw_boolean,
+            "Expected to see either original number or boolean conversion"
+        );
+    }
+
+    #[test]
+    fn test_array_chaos() {
+        let mut interpreter = Interpreter::new();
+        let array_expr = Expression::Literal(Literal::Array(vec![
+            Box::new(Expression::Literal(Literal::Number(1))),
+            Box::new(Expression::Literal(Literal::Number(2))),
+            Box::new(Expression::Literal(Literal::Number(3))),
+        ]));
+
+        match interpreter.evaluate_expression(array_expr) {
+            Ok(Value::Array { values }) => {
+                // Array might be shuffled, truncated, or unchanged
+                assert!(values.len() <= 3, "Array should not grow");
+            }
+            Ok(_) => (), // Any transformation is valid
+            Err(_) => (), // Errors are valid too
+        }
+    }
+
+    #[test]
+    fn test_object_chaos() {
+        let mut interpreter = Interpreter::new();
+        let object_expr = Expression::Literal(Literal::Object(vec![
+            ("key1".to_string(), Box::new(Expression::Literal(Literal::Number(1)))),
+            ("key2".to_string(), Box::new(Expression::Literal(Literal::Number(2)))),
+        ]));
+
+        match interpreter.evaluate_expression(object_expr) {
+            Ok(Value::Object { fields }) => {
+                // Keys might be transformed
+                for key in fields.keys() {
+                    assert!(!key.is_empty(), "Keys should not be empty");
+                }
+            }
+            Ok(_) => (), // Any transformation is valid
+            Err(_) => (), // Errors are valid too
+        }
+    }
+
+    #[test]
+    fn test_mangle_text_keeps_the_letters() {
+        let original = "Hello there, general chaos";
+        let mangled = mangle_text(original, &mut rand::thread_rng());
+
+        let letters: String = mangled
+            .chars()
+            .filter(|c| c.is_ascii())
+            .collect::<String>()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        assert_eq!(letters.to_lowercase(), original.to_lowercase());
+    }
+
+    #[test]
+    fn test_config_is_respected() {
+        let config = Config {
+            mangle_output: false,
+            ..Config::default()
+        };
+        let interpreter = Interpreter::with_config(config.clone());
+        assert_eq!(interpreter.config(), &config);
+    }
+
+    #[test]
+    fn test_time_arithmetic_in_normal_mode() {
+        let mut interpreter = Interpreter::new();
+        interpreter.is_completely_normal = true;
+        let ms = |n| Value::Duration { value: Duration::from_millis(n) };
+        let epoch = Value::Instant { value: SystemTime::UNIX_EPOCH };
+
+        assert_eq!(interpreter.evaluate_binary_op(BinaryOp::Add, ms(100), ms(50)).unwrap(), ms(150));
+        assert_eq!(interpreter.evaluate_binary_op(BinaryOp::Multiply, ms(100), Value::Number { value: 3 }).unwrap(), ms(300));
+        assert_eq!(
+            interpreter.evaluate_binary_op(BinaryOp::Add, epoch.clone(), ms(42)).unwrap(),
+            Value::Instant { value: SystemTime::UNIX_EPOCH + Duration::from_millis(42) }
+        );
+        assert_eq!(interpreter.evaluate_binary_op(BinaryOp::LessThan, ms(1), ms(2)).unwrap(), Value::Boolean { value: true });
+        assert!(interpreter.evaluate_binary_op(BinaryOp::Add, epoch, Value::Number { value: 1 }).is_err());
+        assert_eq!(ms(1500).to_string(), "1500ms");
+    }
+
+    #[test]
+    fn test_same_seed_same_chaos() {
+        let run = |seed| {
+            let mut interpreter = Interpreter::with_seed(seed);
+            let results: Vec<String> = (0..50)
+                .map(|n| format!("{:?}", interpreter.evaluate_expression(Expression::Literal(Literal::Number(n)))))
+                .collect();
+            (results, interpreter.chaos_audit().iter().map(|event| event.kind).collect::<Vec<_>>())
+        };
+
+        assert_eq!(run(42), run(42));
+        assert_ne!(run(42), run(43));
+    }
+
+    #[test]
+    fn test_chaos_level_zero_is_normal() {
+        let mut interpreter = Interpreter::with_seed(7);
+        interpreter.set_chaos_level(0);
+        for n in 0..50 {
+            assert_eq!(
+                interpreter.evaluate_expression(Expression::Literal(Literal::Number(n))).unwrap(),
+                Value::Number { value: n }
+            );
+        }
+        assert!(interpreter.chaos_audit().is_empty());
+
+        interpreter.set_chaos_level(200);
+        assert_eq!(interpreter.chaos_level(), MAX_CHAOS_LEVEL);
+        assert!(interpreter.roll(ChaosKind::Teapot, 0.95));
+    }
 
-                    match (obj, key_val) {
-                        (Value::Object { mut fields }, Value::String { value: _key_str }) => {
-                            // 30% chance of object chaos - swap random keys
-                            if random::<f64>() < 0.3 {
-                                let keys: Vec<String> = fields.keys().cloned().collect();
-                                if keys.len() >= 2 {
-                                    if let Some((k1, k2)) = keys.choose_multiple(&mut rand::thread_rng(), 2).collect::<Vec<_>>().split_first() {
-                                        if let Some(k2) = k2.first() {
-                                            if let (Some(v1), Some(v2)) = (fields.remove(*k1), fields.remove(*k2)) {
-                                                fields.insert(k1.to_string(), v2);
-                                                fields.insert(k2.to_string(), v1);
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                            Err(RuntimeError::ObjectChaos)
-                        }
-                        (Value::Array { values }, Value::Number { value: index }) => {
-                            let index = index as usize;
-                            // 40% chance of array vacation
-                            if random::<f64>() < 0.4 {
-                                return Err(RuntimeError::ArrayVacation);
-                            }
+    #[test]
+    fn test_murphy_always_picks_the_worst() {
+        let mut interpreter = Interpreter::with_config(Config { open_browser: false, promise_delay_max_ms: 1500, ..Config::default() });
+        interpreter.set_seed(7);
+        interpreter.execute_statement(Statement::Directive { name: "murphy".to_string() }).unwrap();
 
-                            // 30% chance of returning random element
-                            if random::<f64>() < 0.3 {
-                                return values.choose(&mut rand::thread_rng()).cloned()
-                                    .ok_or_else(|| RuntimeError::Generic("Array is empty, just like my promises!".to_string()));
-                            }
+        assert!((0..100).all(|_| interpreter.roll(ChaosKind::Teapot, 0.001)));
+        assert_eq!(interpreter.promise_delay(), 1500);
+        assert!(matches!(interpreter.eval("promise(1)"), Err(Error::Runtime(RuntimeError::PromiseRejected))));
 
-                            values.get(index).cloned()
-                                .ok_or_else(|| RuntimeError::Generic(format!("Index {} is out of bounds. The array is playing hide and seek!", index)))
-                        },
-                        (Value::Object { .. }, _) => Err(RuntimeError::Generic("Object keys must be strings! What kind of chaos are you trying to create? 🎭".to_string())),
-                        (Value::Array { .. }, _) => Err(RuntimeError::Generic("Array indices must be numbers! Did you try to index with a 🦄?".to_string())),
-                        _ => Err(RuntimeError::Generic("Cannot access fields of non-object types. What did you expect?".to_string())),
-                    }
-                },
-                Expression::Promise { value, timeout } => {
-                    let value = self.evaluate_expression(*value)?;
+        // What can't go wrong still doesn't
+        interpreter.set_chaos_level(0);
+        assert!(!interpreter.roll(ChaosKind::Teapot, 0.001));
+    }
 
-                    // 40% chance of promise rejection
-                    if random::<f64>() < 0.4 {
-                        return Err(RuntimeError::PromiseRejected);
-                    }
+    #[test]
+    fn test_lucky_runs_are_chaotic_in_name_only() {
+        use crate::testing::SeededChaosHarness;
 
-                    // Add random delay between 100ms and 2000ms
-                    let delay = random::<u64>() % 1900 + 100;
-                    std::thread::sleep(std::time::Duration::from_millis(delay));
+        let config = Config { lucky: true, open_browser: false, promise_delay_max_ms: 0, ..Config::default() };
+        for seed in 0..20 {
+            let mut harness = SeededChaosHarness::with_config(seed, config.clone());
+            let result = harness.run("let a = [1, 2, 3]; let b = a[1]; let p = promise(b); print(a);").unwrap();
+            assert!(result.errors.is_empty(), "seed {}: {:?}", seed, result.errors);
+            assert!(result.chaos_events.is_empty(), "seed {}: {:?}", seed, result.chaos_events);
+        }
 
-                    if let Some(timeout_expr) = timeout {
-                        let timeout_val = self.evaluate_expression(*timeout_expr)?;
-                        if let Value::Number { value: timeout_ms } = timeout_val {
-                            if delay > timeout_ms as u64 {
-                                return Err(RuntimeError::AsyncTimeout);
-                            }
-                        }
-                    }
+        // murphy still wins, wherever lucky came from
+        let mut interpreter = Interpreter::with_config(config);
+        interpreter.execute_statement(Statement::Directive { name: "murphy".to_string() }).unwrap();
+        assert!(interpreter.roll(ChaosKind::Teapot, 0.001));
+    }
 
-                    Ok(Value::Promise {
-                        value: Box::new(value),
-                        resolved: true,
-                    })
-                },
-                Expression::Await { promise } => {
-                    let promise_val = self.evaluate_expression(*promise)?;
-                    match promise_val {
-                        Value::Promise { value, resolved } => {
-                            if resolved {
-                                // 20% chance of changing the resolved value
-                                if random::<f64>() < 0.2 {
-                                    Ok(Value::String {
-                                        value: "Promise changed its mind 🤔".to_string()
-                                    })
-                                } else {
-                                    Ok(*value)
-                                }
-                            } else {
-                                Err(RuntimeError::PromiseRejected)
-                            }
-                        },
-                        _ => Err(RuntimeError::Generic("Can't await something that isn't a promise! 🤯".to_string())),
-                    }
-                },
+    /// Sleeps a little before every statement, and gives up after a few.
+    struct Nap(usize);
+
+    impl Scheduler for Nap {
+        fn before_statement(&mut self, _statement: &Statement, statements_executed: usize) -> Decision {
+            if statements_executed >= self.0 {
+                Decision::Abort("nap time".to_string())
+            } else {
+                Decision::Sleep(Duration::from_millis(10))
             }
         }
     }
 
-    fn evaluate_literal(&mut self, lit: Literal) -> Value {
-        // If in completely normal mode, literals behave normally
-        if self.is_completely_normal {
-            match lit {
-                Literal::String(s) => Value::String { value: s },
-                Literal::Number(n) => Value::Number { value: n },
-                Literal::Boolean(b) => Value::Boolean { value: b },
-                Literal::Array(elements) => {
-                    let mut values = Vec::new();
-                    for element in elements {
-                        if let Ok(value) = self.evaluate_expression(*element) {
-                            values.push(value);
-                        }
-                    }
-                    Value::Array { values }
-                },
-                Literal::Object(pairs) => {
-                    let mut fields = HashMap::new();
-                    for (key, value) in pairs {
-                        if let Ok(value) = self.evaluate_expression(*value) {
-                            fields.insert(key, value);
-                        }
-                    }
-                    Value::Object { fields }
-                },
-                Literal::Null => Value::Null,
-            }
-        } else {
-            // Original chaotic behavior - use remainder to ensure we stay within bounds
-            match lit {
-                Literal::Boolean(b) => {
-                    match random::<u8>() % 3 {
-                        0 => Value::Boolean { value: !b }, // Opposite of what was provided
-                        1 => Value::String { value: if b { "true" } else { "false" }.to_string() },
-                        _ => Value::Number { value: if b { 1 } else { 0 } },
-                    }
-                },
-                Literal::Number(n) => {
-                    match random::<u8>() % 2 {
-                        0 => Value::Number { value: n },
-                        _ => Value::Boolean { value: n != 0 },
-                    }
-                },
-                _ => match random::<u8>() % 5 {
-                    0 => Value::String { value: "null and void".to_string() },
-                    1 => Value::Number { value: 0 },
-                    2 => Value::Boolean { value: false },
-                    3 => Value::Array { values: vec![Value::Null] },
-                    _ => Value::Object { fields: HashMap::new() },
-                }
-            }
+    #[test]
+    fn test_scheduler_is_asked_before_every_statement() {
+        let clock = Arc::new(ManualClock::new());
+        let mut interpreter = Interpreter::new();
+        interpreter.is_completely_normal = true;
+        interpreter.set_clock(clock.clone());
+        interpreter.set_scheduler(Box::new(Nap(2)));
+
+        let count = |value| Statement::Let { name: "count".into(), value: Expression::Literal(Literal::Number(value)) };
+        let result = interpreter.interpret(vec![count(1), count(2), count(3)]);
+
+        assert!(matches!(result, Err(RuntimeError::Descheduled(ref reason)) if reason == "nap time"), "got {:?}", result);
+        assert_eq!(interpreter.environment().get("count"), Some(&Value::Number { value: 2 }));
+        assert_eq!(clock.total_slept(), Duration::from_millis(20));
+    }
+
+    #[test]
+    fn test_resource_usage_is_per_run() {
+        let mut interpreter = Interpreter::with_config(Config { drama_ms: 30, open_browser: false, ..Config::default() });
+        interpreter.set_output(Box::new(CapturedOutput::new()));
+        interpreter.set_clock(Arc::new(ManualClock::new()));
+        interpreter.is_completely_normal = true;
+
+        let text = Statement::Let { name: "text".into(), value: Expression::Literal(Literal::String("x".repeat(1000))) };
+        let print = Statement::Print { value: Expression::Identifier("text".into()) };
+        let report = interpreter.interpret_with_report(vec![text, print.clone()]);
+        assert_eq!(report.usage.statements_executed, 2);
+        assert_eq!(report.usage.time_slept, Duration::from_millis(30));
+        assert!(report.usage.peak_memory_bytes > 1000, "got {}", report.usage.peak_memory_bytes);
+
+        interpreter.is_completely_normal = false;
+        interpreter.set_seed(1);
+        let report = interpreter.interpret_with_report(vec![print]);
+        assert_eq!(report.usage.statements_executed, 1);
+        assert_eq!(report.usage.urls.len(), 1, "print picked a URL, even if it never opened it");
+    }
+
+    #[test]
+    fn test_cancel_stops_at_the_next_statement() {
+        let mut interpreter = Interpreter::with_config(Config { keep_going: true, ..Config::default() });
+        interpreter.is_completely_normal = true;
+        let handle = interpreter.cancel_handle();
+        let count = |value| Statement::Let { name: "count".into(), value: Expression::Literal(Literal::Number(value)) };
+
+        interpreter.execute_statement(count(1)).unwrap();
+        std::thread::spawn(move || handle.cancel()).join().unwrap();
+        let result = interpreter.interpret(vec![count(2), count(3)]);
+
+        // Even keep_going mode takes the hint
+        assert!(matches!(result, Err(RuntimeError::CancelledByHuman)), "got {:?}", result);
+        assert_eq!(interpreter.environment().get("count"), Some(&Value::Number { value: 1 }));
+
+        interpreter.cancel_handle().reset();
+        interpreter.interpret(vec![count(2)]).unwrap();
+        assert_eq!(interpreter.environment().get("count"), Some(&Value::Number { value: 2 }));
+    }
+
+    #[test]
+    fn test_print_to_sends_output_to_a_file_for_one_statement() {
+        let path = std::env::temp_dir().join(format!("upl-print-to-test-{}.txt", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut interpreter = Interpreter::new();
+        interpreter.is_completely_normal = true;
+        let output = CapturedOutput::new();
+        interpreter.set_output(Box::new(output.clone()));
+
+        let source = format!("print(1); #[directive(print_to(\"{}\"))] if (true) {{ print(2); print(3); }} print(4);", path.display());
+        let program = Parser::new(Lexer::new(&source).collect()).parse().unwrap();
+        interpreter.interpret(program).unwrap();
+        assert_eq!(output.contents(), "Number { value: 1 }\nNumber { value: 4 }\n");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "Number { value: 2 }\nNumber { value: 3 }\n");
+        std::fs::remove_file(&path).unwrap();
+
+        interpreter.set_capabilities(Capabilities::none());
+        let program = Parser::new(Lexer::new(&source).collect()).parse().unwrap();
+        assert!(matches!(interpreter.interpret(program), Err(RuntimeError::PermissionDenied(Capability::Filesystem))));
+    }
+
+    #[test]
+    fn test_frozen_clock_stops_time() {
+        let mut interpreter = Interpreter::with_config(Config { chaos_level: 0, ..Config::default() });
+        interpreter.is_completely_normal = true;
+        interpreter.set_clock(Arc::new(ManualClock::starting_at(SystemTime::UNIX_EPOCH + Duration::from_secs(86_400))));
+        let run = |interpreter: &mut Interpreter, source: &str| {
+            interpreter.interpret(Parser::new(Lexer::new(source).collect()).parse().unwrap())
+        };
+        let instant = |interpreter: &Interpreter, name: &str| match interpreter.environment().get(name) {
+            Some(Value::Instant { value }) => *value,
+            other => panic!("{} isn't an instant: {:?}", name, other),
+        };
+        let new_year = clock::parse_timestamp("2024-01-01T00:00:00Z").unwrap();
+
+        // On the first statement, time stays stopped for the whole program
+        run(&mut interpreter, "#[directive(frozen_clock(\"2024-01-01T00:00:00Z\"))]\nlet a = now();\nlet p = promise(1, 5000);\nlet b = now();").unwrap();
+        assert_eq!((instant(&interpreter, "a"), instant(&interpreter, "b")), (new_year, new_year));
+
+        // Anywhere else, only for the statement it's on
+        run(&mut interpreter, "let c = now();\n#[directive(frozen_clock(\"2024-01-01T00:00:00Z\"))]\nlet d = now();\nlet e = now();").unwrap();
+        assert_eq!(instant(&interpreter, "d"), new_year);
+        assert!(instant(&interpreter, "c") != new_year && instant(&interpreter, "e") != new_year);
+
+        let result = run(&mut interpreter, "#[directive(frozen_clock(\"last tuesday\"))]\nlet f = now();");
+        assert!(matches!(&result, Err(RuntimeError::BadArguments(name, _)) if name == "frozen_clock"), "got {:?}", result);
+    }
+
+    #[test]
+    fn test_timeout_cuts_naps_short() {
+        let mut interpreter = Interpreter::with_config(Config { timeout_ms: 50, chaos_level: 0, ..Config::default() });
+        let clock = Arc::new(ManualClock::new());
+        interpreter.set_clock(clock.clone());
+
+        // Every promise takes at least 100ms, so this one is still sleeping when time runs out
+        let program = Parser::new(Lexer::new("let a = 1; let b = promise(2, 5000); let c = 3;").collect()).parse().unwrap();
+        let result = interpreter.interpret(program);
+        assert!(matches!(result, Err(RuntimeError::OutOfTime(limit)) if limit == Duration::from_millis(50)), "got {:?}", result);
+        assert_eq!(clock.total_slept(), Duration::from_millis(50));
+        assert!(interpreter.environment().get("a").is_some());
+        assert!(interpreter.environment().get("b").is_none());
+    }
+
+    #[test]
+    fn test_promise_delays_follow_the_config() {
+        let source = "let a = promise(1); let b = promise(2); let c = promise(3);";
+        for (min, max) in [(0, 0), (40, 50)] {
+            let mut interpreter = Interpreter::with_config(Config {
+                chaos_level: 0,
+                promise_delay_min_ms: min,
+                promise_delay_max_ms: max,
+                ..Config::default()
+            });
+            interpreter.set_completely_normal(true);
+            let clock = Arc::new(ManualClock::new());
+            interpreter.set_clock(clock.clone());
+
+            interpreter.interpret(Parser::new(Lexer::new(source).collect()).parse().unwrap()).unwrap();
+            let slept = clock.total_slept();
+            assert!(slept >= Duration::from_millis(3 * min) && slept <= Duration::from_millis(3 * max), "slept {:?}", slept);
         }
     }
 
-    fn evaluate_binary_op(&mut self, op: BinaryOp, left: Value, right: Value) -> Result<Value, RuntimeError> {
-        // If in completely normal mode or disable_useless is active, operations work normally
-        if self.is_completely_normal || self.has_directive("disable_useless") {
-            match op {
-                BinaryOp::Add => match (left, right) {
-                    (Value::Number { value: l }, Value::Number { value: r }) => {
-                        Ok(Value::Number { value: l + r })
-                    }
-                    _ => Err(RuntimeError::Generic("Invalid types for addition".to_string())),
-                },
-                BinaryOp::Multiply => match (left, right) {
-                    (Value::Number { value: l }, Value::Number { value: r }) => {
-                        Ok(Value::Number { value: l * r })
-                    }
-                    _ => Err(RuntimeError::Generic("Invalid types for multiplication".to_string())),
-                },
-                BinaryOp::Equals => match (left, right) {
-                    (Value::Number { value: l }, Value::Number { value: r }) => {
-                        Ok(Value::Boolean { value: l == r })
-                    }
-                    _ => Err(RuntimeError::Generic("Invalid types for equality".to_string())),
-                },
-                BinaryOp::LessThan => match (left, right) {
-                    (Value::Number { value: l }, Value::Number { value: r }) => {
-                        Ok(Value::Boolean { value: l < r })
-                    }
-                    _ => Err(RuntimeError::Generic("Invalid types for less than".to_string())),
-                },
-                _ => Err(RuntimeError::Generic("Operation not supported".to_string())),
+    #[test]
+    fn test_step_budget_stops_endless_recursion() {
+        let mut interpreter = Interpreter::with_config(Config { chaos_level: 0, ..Config::default() });
+        interpreter.set_completely_normal(true);
+        interpreter.set_output(Box::new(CapturedOutput::new()));
+        interpreter.set_max_steps(200);
+
+        let program = Parser::new(Lexer::new("ponder(n) { ponder(add(n, 1)); } ponder(0);").collect()).parse().unwrap();
+        assert!(matches!(interpreter.interpret(program), Err(RuntimeError::OutOfSteps(200))));
+        assert_eq!(interpreter.resource_usage().steps_taken, 201);
+
+        // The next program gets a budget of its own
+        let program = Parser::new(Lexer::new("let x = 1;").collect()).parse().unwrap();
+        assert!(interpreter.interpret(program).is_ok());
+        assert_eq!(interpreter.resource_usage().steps_taken, 2);
+    }
+
+    #[tokio::test]
+    async fn test_run_async_sleeps_on_the_runtime() {
+        let mut interpreter = Interpreter::new();
+        let program = vec![
+            Statement::Directive { name: "disable_all_useless_shit".to_string() },
+            Statement::Pray {
+                attempts: Some(3),
+                body: vec![Statement::Save { filename: "prayers.txt".to_string() }],
+            },
+        ];
+
+        // Web servers need to be able to move the future between threads
+        fn assert_send<F: std::future::Future + Send>(future: F) -> F {
+            future
+        }
+        let started = tokio::time::Instant::now();
+        let mut running = std::pin::pin!(assert_send(interpreter.run_async(program)));
+        let mut ticks = 0;
+        let result = loop {
+            tokio::select! {
+                result = &mut running => break result,
+                _ = tokio::time::sleep(Duration::from_millis(50)) => ticks += 1,
             }
-        } else {
-            // Original chaotic behavior
-            match op {
-                BinaryOp::Add => {
-                    match (left, right) {
-                        (Value::Number { value: l }, Value::Number { value: r }) => {
-                            if random::<bool>() {
-                                Ok(Value::Number { value: l - r }) // Returns 2 (5-3)
-                            } else {
-                                Ok(Value::Number { value: l * r + r }) // Returns 15 ((5*3)+3)
-                            }
-                        }
-                        _ => Err(RuntimeError::Generic("Invalid types for addition".to_string())),
-                    }
-                }
-                BinaryOp::Multiply => {
-                    if random::<bool>() {
-                        Err(RuntimeError::Generic("Multiplication went on vacation".to_string()))
-                    } else {
-                        match (left, right) {
-                            (Value::Number { value: l }, Value::Number { value: r }) => {
-                                if r == 0 {
-                                    Err(RuntimeError::DivisionByZero)
-                                } else {
-                                    Ok(Value::Number { value: l / r }) // Divides when you want to multiply
-                                }
-                            }
-                            _ => Err(RuntimeError::Generic("Invalid types for multiplication".to_string())),
-                        }
-                    }
-                }
-                BinaryOp::Equals => {
-                    match (left, right) {
-                        (Value::Number { .. }, Value::Number { .. }) => {
-                            Ok(Value::Boolean { value: random() }) // Random equality
-                        }
-                        _ => Err(RuntimeError::Generic("Invalid types for equality".to_string())),
-                    }
-                }
-                BinaryOp::LessThan => {
-                    match (left, right) {
-                        (Value::Number { value: l }, Value::Number { value: r }) => {
-                            Ok(Value::Boolean { value: l > r }) // Greater than when you want less than
-                        }
-                        _ => Err(RuntimeError::Generic("Invalid types for less than".to_string())),
-                    }
-                }
-                _ => Err(RuntimeError::Generic("Operation not supported".to_string())),
+        };
+        assert!(matches!(result, Err(RuntimeError::SaveError)), "got {:?}", result);
+        // The prayers took their 300ms, and this test's one runtime thread was free the whole time
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        assert!(ticks >= 4, "only {} ticks while the program prayed", ticks);
+    }
+
+    /// Writes down what a clock said when each line was printed.
+    struct Stopwatch(Arc<ManualClock>, Arc<Mutex<Vec<(String, Duration)>>>);
+
+    impl OutputSink for Stopwatch {
+        fn write(&mut self, text: &str) {
+            if text != "\n" {
+                let at = self.0.now().duration_since(std::time::UNIX_EPOCH).unwrap();
+                self.1.lock().unwrap().push((text.to_string(), at));
             }
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::ast::Literal;
+    #[tokio::test]
+    async fn test_run_async_pauses_before_the_output_it_delays() {
+        let clock = Arc::new(ManualClock::new());
+        let printed = Arc::new(Mutex::new(Vec::new()));
+        let mut interpreter = Interpreter::with_config(Config { drama_ms: 100, ..Config::default() });
+        interpreter.set_completely_normal(true);
+        interpreter.set_clock(clock.clone());
+        interpreter.set_output(Box::new(Stopwatch(clock.clone(), printed.clone())));
+
+        let program = Parser::new(Lexer::new("print(\"one\"); print(\"two\");").collect()).parse().unwrap();
+        let started = tokio::time::Instant::now();
+        interpreter.run_async(program).await.unwrap();
+
+        // Each line waits for its dramatic pause, not the other way around
+        assert_eq!(*printed.lock().unwrap(), vec![
+            ("String { value: \"one\" }".to_string(), Duration::from_millis(100)),
+            ("String { value: \"two\" }".to_string(), Duration::from_millis(200)),
+        ]);
+        assert!(started.elapsed() >= Duration::from_millis(200));
+        assert_eq!(clock.total_slept(), Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn test_dropping_run_async_keeps_what_the_program_did() {
+        let clock = Arc::new(ManualClock::new());
+        let printed = Arc::new(Mutex::new(Vec::new()));
+        let mut interpreter = Interpreter::with_config(Config { drama_ms: 60_000, ..Config::default() });
+        interpreter.set_completely_normal(true);
+        interpreter.set_clock(clock.clone());
+        interpreter.set_output(Box::new(Stopwatch(clock.clone(), printed.clone())));
+        interpreter.environment.set("from_before", Value::Number { value: 0 });
+
+        let source = "let kept = 1; keep() { 2; } print(\"a minute of drama\"); let never = 3;";
+        let program = Parser::new(Lexer::new(source).collect()).parse().unwrap();
+        let started = tokio::time::Instant::now();
+        let gave_up = tokio::time::timeout(Duration::from_millis(100), interpreter.run_async(program)).await;
+        assert!(gave_up.is_err(), "the program should still have been pausing dramatically");
+        assert!(started.elapsed() < Duration::from_secs(30), "dropping the program shouldn't wait out its pause");
+
+        // Everything from before the drop is still there, and nothing after it happened
+        assert_eq!(interpreter.environment().get("from_before"), Some(&Value::Number { value: 0 }));
+        assert_eq!(interpreter.environment().get("kept"), Some(&Value::Number { value: 1 }));
+        assert_eq!(interpreter.call_function("keep", vec![]).unwrap(), Value::Number { value: 2 });
+        assert_eq!(interpreter.environment().get("never"), None);
+        assert!(std::ptr::addr_eq(Arc::as_ptr(&interpreter.clock), Arc::as_ptr(&clock)), "the interpreter's own clock is back");
+
+        // And it isn't left cancelled
+        interpreter.config.drama_ms = 0;
+        let program = Parser::new(Lexer::new("let after = 4;").collect()).parse().unwrap();
+        interpreter.run_async(program).await.unwrap();
+        assert_eq!(interpreter.environment().get("after"), Some(&Value::Number { value: 4 }));
+    }
+
+    #[test]
+    fn test_pray_retries_with_exponential_delays() {
+        let clock = Arc::new(ManualClock::new());
+        let mut interpreter = Interpreter::new();
+        interpreter.is_completely_normal = true;
+        interpreter.set_clock(clock.clone());
+
+        let program = Parser::new(Lexer::new("let runs = 0; pray(3) { let runs = add(runs, 1); save \"prayers.txt\"; }").collect())
+            .parse()
+            .unwrap();
+        match interpreter.interpret(program) {
+            Err(RuntimeError::SaveError) => (),
+            other => panic!("Expected the save to keep failing, got {:?}", other),
+        }
+        // Three attempts means three attempts, with 100ms + 200ms of waiting for a miracle between them
+        assert_eq!(interpreter.environment().get("runs"), Some(&Value::Number { value: 3 }));
+        assert_eq!(clock.total_slept(), Duration::from_millis(300));
+    }
+
+    #[test]
+    fn test_pray_in_chaos_mode_goes_unanswered() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_clock(Arc::new(ManualClock::new()));
+
+        let pray = Statement::Pray {
+            attempts: None,
+            body: vec![Statement::Save { filename: "prayers.txt".to_string() }],
+        };
+
+        match interpreter.execute_statement(pray) {
+            Err(RuntimeError::PrayerUnanswered(attempts)) => assert!((1..=3).contains(&attempts)),
+            other => panic!("Expected an unanswered prayer, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_drama_pauses_before_print() {
+        let clock = Arc::new(ManualClock::new());
+        let output = CapturedOutput::new();
+        let mut interpreter = Interpreter::with_config(Config {
+            drama_ms: 900,
+            drama_dots: true,
+            ..Config::default()
+        });
+        interpreter.is_completely_normal = true;
+        interpreter.set_clock(clock.clone());
+        interpreter.set_output(Box::new(output.clone()));
+
+        let print = Statement::Print {
+            value: Expression::Literal(Literal::Number(42)),
+        };
+        interpreter.execute_statement(print).unwrap();
+
+        assert_eq!(clock.total_slept(), Duration::from_millis(900));
+        assert_eq!(output.contents(), "...\nNumber { value: 42 }\n");
+    }
+
+    #[test]
+    fn test_chaos_report_counts_every_kind() {
+        let mut interpreter = Interpreter::with_config(Config { open_browser: false, ..Config::default() });
+        interpreter.set_output(Box::new(CapturedOutput::new()));
+        interpreter.execute_statement(Statement::Directive { name: "murphy".to_string() }).unwrap();
+        for _ in 0..2 {
+            let print = Statement::Print { value: Expression::Literal(Literal::String("hi".to_string())) };
+            interpreter.execute_statement(print).unwrap();
+        }
+
+        let report = interpreter.chaos_report();
+        assert_eq!(report.count(ChaosKind::BrowserTab), 2);
+        assert_eq!(report.count(ChaosKind::Teapot), 0);
+        assert_eq!(report.total(), interpreter.chaos_audit().len());
+        assert_eq!(report.urls.len(), 2);
+        assert!(report.to_string().contains("browser_tab: 2"), "{}", report);
+    }
+
+    #[test]
+    fn test_print_without_opening_the_browser() {
+        let output = CapturedOutput::new();
+        let mut interpreter = Interpreter::with_config(Config { open_browser: false, ..Config::default() });
+        interpreter.set_output(Box::new(output.clone()));
+        // If print tried to open a tab anyway, it would be denied
+        interpreter.set_capabilities(Capabilities::none());
+
+        let print = Statement::Print { value: Expression::Literal(Literal::String("hi".to_string())) };
+        interpreter.execute_statement(print).unwrap();
+
+        let tab = interpreter.chaos_audit().iter().find(|event| event.kind == ChaosKind::BrowserTab).unwrap();
+        assert!(tab.detail.as_deref().is_some_and(|detail| detail.ends_with("(not opened)")));
+        assert!(!output.contents().is_empty());
+    }
+
+    #[test]
+    fn test_identity_theft_swaps_variables() {
+        let mut interpreter = Interpreter::with_config(Config {
+            identity_theft: true,
+            identity_theft_chance: 1.0,
+            ..Config::default()
+        });
+        interpreter.environment.set("x".to_string(), Value::Number { value: 1 });
+        interpreter.environment.set("y".to_string(), Value::Number { value: 2 });
+
+        interpreter.maybe_steal_identities();
+
+        assert_eq!(interpreter.environment().get("x"), Some(&Value::Number { value: 2 }));
+        assert_eq!(interpreter.environment().get("y"), Some(&Value::Number { value: 1 }));
+        assert!(interpreter
+            .chaos_audit()
+            .iter()
+            .any(|event| event.kind == ChaosKind::IdentityTheft));
+    }
+
+    #[test]
+    fn test_identity_theft_is_opt_in() {
+        let mut interpreter = Interpreter::new();
+        interpreter.environment.set("x".to_string(), Value::Number { value: 1 });
+        interpreter.environment.set("y".to_string(), Value::Number { value: 2 });
+
+        for _ in 0..100 {
+            interpreter.maybe_steal_identities();
+        }
+
+        assert_eq!(interpreter.environment().get("x"), Some(&Value::Number { value: 1 }));
+        assert!(interpreter.chaos_audit().is_empty());
+    }
+
+    #[test]
+    fn test_corporate_mode_renders_invitations() {
+        let interpreter = Interpreter::with_config(Config {
+            corporate_mode: true,
+            ..Config::default()
+        });
+
+        let rendered = interpreter.render_error(&RuntimeError::SaveError);
+        assert!(rendered.starts_with("Subject: Sync on SaveError"));
+        assert!(rendered.contains("BEGIN:VCALENDAR"));
+        assert_eq!(Interpreter::new().render_error(&RuntimeError::SaveError), RuntimeError::SaveError.to_string());
+    }
+
+    #[test]
+    fn test_garbage_collector_eats_least_recently_used() {
+        let output = CapturedOutput::new();
+        let mut interpreter = Interpreter::with_config(Config {
+            gc_interval: 1,
+            gc_chance: 1.0,
+            ..Config::default()
+        });
+        interpreter.set_output(Box::new(output.clone()));
+        interpreter.environment.set("old".to_string(), Value::Number { value: 1 });
+        interpreter.environment.set("new".to_string(), Value::Number { value: 2 });
+
+        interpreter.execute_statement(Statement::Use { path: "normal::mode".to_string() }).unwrap();
+
+        assert!(interpreter.environment().get("old").is_none());
+        assert!(interpreter.environment().get("new").is_some());
+        assert!(output.contents().contains("In loving memory of `old`"));
+    }
 
     #[test]
-    fn test_add_subtracts() {
+    fn test_null_coalesce_in_normal_mode() {
         let mut interpreter = Interpreter::new();
-        let expr = Expression::BinaryOp {
-            op: BinaryOp::Add,
-            left: Box::new(Expression::Literal(Literal::Number(5))),
-            right: Box::new(Expression::Literal(Literal::Number(3))),
+        interpreter.is_completely_normal = true;
+
+        let coalesce = |left: Literal, right: Literal| Expression::BinaryOp {
+            op: BinaryOp::NullCoalesce,
+            left: Box::new(Expression::Literal(left)),
+            right: Box::new(Expression::Literal(right)),
         };
 
-        match interpreter.evaluate_expression(expr) {
-            Ok(Value::Number { value: n }) => {
-                // The operation might:
-                // 1. subtract (5 - 3 = 2)
-                // 2. multiply (5 * 3 = 15)
-                // 3. add anyway (5 + 3 = 8)
-                // 4. do something completely different (because why not?)
-                assert!(
-                    n == 2 || n == 15 || n == 8 || n != 0,  // Allow any non-zero number for maximum chaos
-                    "Expected chaos, got too much order with {}",
-                    n
-                );
-            }
-            Ok(_) => (), // Any other value type is fine in our useless language
-            Err(_) => (), // Errors are also fine
-        }
+        assert_eq!(
+            interpreter.evaluate_expression(coalesce(Literal::Null, Literal::Number(7))).unwrap(),
+            Value::Number { value: 7 }
+        );
+        assert_eq!(
+            interpreter.evaluate_expression(coalesce(Literal::Number(1), Literal::Number(7))).unwrap(),
+            Value::Number { value: 1 }
+        );
     }
 
     #[test]
-    fn test_multiply_divides() {
+    fn test_optional_access_in_normal_mode() {
         let mut interpreter = Interpreter::new();
-        let expr = Expression::BinaryOp {
-            op: BinaryOp::Multiply,
-            left: Box::new(Expression::Literal(Literal::Number(6))),
-            right: Box::new(Expression::Literal(Literal::Number(2))),
+        interpreter.is_completely_normal = true;
+
+        let access = |object: Literal, key: &str| Expression::OptionalAccess {
+            object: Box::new(Expression::Literal(object)),
+            key: Box::new(Expression::Literal(Literal::String(key.to_string()))),
         };
+        let object = Literal::Object(vec![("name".to_string(), Box::new(Expression::Literal(Literal::Number(1))))]);
 
-        match interpreter.evaluate_expression(expr) {
-            Ok(Value::Number { value: n }) => {
-                assert!(n == 3 || n == 8, "Expected either 3 (division) or 8 (addition), got {}", n);
-            }
-            Ok(_) => (), // Any other value is fine in our useless language
-            Err(_) => (), // Errors are also fine
+        assert_eq!(interpreter.evaluate_expression(access(Literal::Null, "name")).unwrap(), Value::Null);
+        assert_eq!(interpreter.evaluate_expression(access(object.clone(), "missing")).unwrap(), Value::Null);
+        assert_eq!(interpreter.evaluate_expression(access(object, "name")).unwrap(), Value::Number { value: 1 });
+    }
+
+    #[test]
+    fn test_rewind_undoes_statements() {
+        let mut interpreter = Interpreter::new();
+        interpreter.is_completely_normal = true;
+        for value in 1..=3 {
+            interpreter.execute_statement(Statement::Let {
+                name: "x".into(),
+                value: Expression::Literal(Literal::Number(value)),
+            }).unwrap();
         }
+
+        assert_eq!(interpreter.rewind(2), 2);
+        assert_eq!(interpreter.environment().get("x"), Some(&Value::Number { value: 1 }));
     }
 
     #[test]
-    fn test_if_executes_else() {
+    fn test_rewind_builtin_skips_itself() {
         let mut interpreter = Interpreter::new();
+        interpreter.is_completely_normal = true;
+        let program = vec![
+            Statement::Let { name: "x".into(), value: Expression::Literal(Literal::Number(1)) },
+            Statement::Let { name: "x".into(), value: Expression::Literal(Literal::Number(2)) },
+            Statement::Expression(Expression::FunctionCall {
+                name: "rewind".into(),
+                arguments: vec![Expression::Literal(Literal::Number(1))],
+            }),
+        ];
+        interpreter.interpret(program).unwrap();
 
-        let if_statement = Statement::If {
-            condition: Expression::Literal(Literal::Number(1)),
-            then_branch: vec![Statement::Expression(Expression::Literal(Literal::Number(1)))],
-            else_branch: Some(vec![Statement::Expression(Expression::Literal(Literal::Number(2)))]),
-        };
+        assert_eq!(interpreter.environment().get("x"), Some(&Value::Number { value: 1 }));
+    }
 
-        // The statement might fail with various humorous errors, and that's okay!
-        match interpreter.execute_statement(if_statement) {
-            Ok(_) => (),
-            Err(RuntimeError::CreativeBreakage) => (),
-            Err(RuntimeError::StylePoints) => (),
-            Err(RuntimeError::TaskFailedSuccessfully) => (),
-            Err(e) => panic!("Unexpected error: {}", e),
+    #[test]
+    fn test_motivational_message_on_success() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_motivational_messages(vec!["Statistically impossible, but congrats.".to_string()]);
+        let output = CapturedOutput::new();
+        interpreter.set_output(Box::new(output.clone()));
+
+        match interpreter.interpret(vec![]) {
+            Ok(()) => assert_eq!(output.contents(), "🏆 Statistically impossible, but congrats.\n"),
+            Err(_) => assert!(output.contents().is_empty(), "No compliments for failures"),
         }
     }
 
     #[test]
-    fn test_boolean_chaos() {
+    fn test_empty_motivational_pool_stays_quiet() {
         let mut interpreter = Interpreter::new();
-        let expr = Expression::Literal(Literal::Boolean(true));
+        interpreter.set_motivational_messages(vec![]);
+        let output = CapturedOutput::new();
+        interpreter.set_output(Box::new(output.clone()));
 
-        // Test multiple times to catch different random behaviors
-        for _ in 0..100 {
-            match interpreter.evaluate_expression(expr.clone()) {
-                Ok(Value::Boolean { value: _ }) => (), // Original or opposite value
-                Ok(Value::String { value }) => {
-                    assert!(
-                        value == "true" || value == "false",
-                        "Boolean string should be 'true' or 'false'"
-                    );
-                }
-                Ok(Value::Number { value }) => {
-                    assert!(value == 0 || value == 1, "Boolean number should be 0 or 1");
-                }
-                Ok(Value::Array { .. }) => (), // Arrays are possible in our chaotic world
-                Ok(Value::Object { .. }) => (), // Objects might appear from nowhere
-                Ok(Value::Promise { .. }) => (), // Even promises can come from booleans
-                Ok(Value::Null) => (), // Functions might return null
-                Err(_) => (), // Errors are always acceptable
-            }
-        }
+        let _ = interpreter.interpret(vec![]);
+        assert!(output.contents().is_empty());
     }
 
     #[test]
-    fn test_random_boolean_conversion() {
+    fn test_sudo_bypasses_chaos_once() {
         let mut interpreter = Interpreter::new();
-        let expr = Expression::Literal(Literal::Number(42));
+        let sudo_let = |value| Statement::Sudo {
+            statement: Box::new(Statement::Let {
+                name: "x".into(),
+                value: Expression::Literal(Literal::Number(value)),
+            }),
+        };
 
-        // Test multiple times to catch different random behaviors
-        let mut saw_number = false;
-        let mut saw_boolean = false;
+        interpreter.execute_statement(sudo_let(42)).unwrap();
+        assert_eq!(interpreter.environment().get("x"), Some(&Value::Number { value: 42 }));
+        assert!(interpreter.chaos_audit().iter().all(|event| event.kind != ChaosKind::LiteralMutation));
 
-        for _ in 0..100 {
-            match interpreter.evaluate_expression(expr.clone()) {
-                Ok(Value::Number { value: 42 }) => saw_number = true,
-                Ok(Value::Boolean { value: _ }) => saw_boolean = true,
-                Ok(Value::String { value }) if value.contains("🎉") => (), // Party emoji case
-                Ok(Value::Array { .. }) => (), // Arrays are possible
-                Ok(Value::Object { .. }) => (), // Objects are possible
-                Ok(Value::Promise { .. }) => (), // Promises are possible
-                Ok(Value::Null) => (), // Null is possible
-                Ok(_) => (), // Other random transformations are fine
-                Err(_) => (), // Errors are always acceptable
-            }
+        assert!(matches!(
+            interpreter.execute_statement(sudo_let(7)),
+            Err(RuntimeError::PermissionDeniedWithPrejudice)
+        ));
+    }
+
+    #[test]
+    fn test_swallowed_errors_are_contagious() {
+        let mut interpreter = Interpreter::with_config(Config {
+            contagion: true,
+            contagion_step: 0.25,
+            ..Config::default()
+        });
+        interpreter.is_completely_normal = true;
+        let try_catch = || Statement::TryCatch {
+            try_block: vec![Statement::Expression(Expression::Identifier("missing".into()))],
+            error_binding: ErrorBinding::Name("e".into()),
+            catch_block: vec![],
+        };
+
+        interpreter.execute_statement(try_catch()).unwrap();
+        interpreter.execute_statement(try_catch()).unwrap();
+
+        assert_eq!(interpreter.contagion_level(), 0.5);
+        let stats = interpreter.stats();
+        assert_eq!(stats.errors_caught, 2);
+        assert_eq!(stats.contagion_level, 0.5);
+    }
+
+    #[test]
+    fn test_catch_destructures_the_error() {
+        let mut interpreter = Interpreter::new();
+        interpreter.is_completely_normal = true;
+        interpreter.execute_statement(Statement::TryCatch {
+            try_block: vec![Statement::Expression(Expression::Identifier("missing".into()))],
+            error_binding: ErrorBinding::Fields(vec!["code".into(), "message".into(), "stack".into()]),
+            catch_block: vec![],
+        }).unwrap();
+
+        let env = interpreter.environment();
+        assert_eq!(env.get("code"), Some(&Value::String { value: "UndefinedVariable".to_string() }));
+        assert!(matches!(env.get("message"), Some(Value::String { value }) if value.contains("missing")));
+        assert_eq!(env.get("stack"), Some(&Value::Null), "Unknown fields are null, we don't keep stacks");
+    }
+
+    #[test]
+    fn test_hot_reload_only_reruns_what_changed() {
+        let function = |name: &str, value| Statement::Function {
+            name: name.into(),
+            parameters: vec![],
+            body: vec![Statement::Expression(Expression::Literal(Literal::Number(value)))],
+        };
+        let let_x = |value| Statement::Let { name: "x".into(), value: Expression::Literal(Literal::Number(value)) };
+        let normal = Statement::Directive { name: "disable_all_useless_shit".to_string() };
+
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .interpret(vec![normal.clone(), function("answer", 1), function("gone", 1), function("same", 1), let_x(1)])
+            .unwrap();
+        let report = interpreter
+            .hot_reload(vec![normal, function("answer", 42), function("same", 1), let_x(2)])
+            .unwrap();
+
+        assert_eq!(report.reloaded, vec!["answer".to_string()]);
+        assert_eq!(report.removed, vec!["gone".to_string()]);
+        assert_eq!(report.unchanged, 1);
+        assert_eq!(interpreter.call_function("answer", vec![]).unwrap(), Value::Number { value: 42 });
+        assert!(interpreter.call_function("gone", vec![]).is_err());
+        assert_eq!(interpreter.environment().get("x"), Some(&Value::Number { value: 1 }), "Variables survive reloads");
+    }
+
+    #[test]
+    fn test_async_functions_run_when_awaited() {
+        let mut interpreter = Interpreter::new();
+        interpreter.is_completely_normal = true;
+        let output = CapturedOutput::new();
+        interpreter.set_output(Box::new(output.clone()));
+
+        let mut parser = Parser::new(Lexer::new("async processData(x) {\n    print(x);\n    multiply(x, 2);\n}\nlet p = processData(42);").collect());
+        parser.set_record_spans(true);
+        interpreter.interpret(parser.parse().unwrap()).unwrap();
+        assert!(matches!(interpreter.environment().get("p"), Some(Value::Promise { task: Some(_), .. })));
+        assert_eq!(output.contents(), "", "Nothing runs until someone awaits it");
+
+        let program = Parser::new(Lexer::new("let r = await(p); let again = await(p);").collect()).parse().unwrap();
+        interpreter.interpret(program).unwrap();
+        assert_eq!(interpreter.environment().get("r"), Some(&Value::Number { value: 84 }));
+        assert_eq!(output.contents(), "Number { value: 42 }\nNumber { value: 42 }\n", "Each await of the same promise runs it");
+    }
+
+    #[test]
+    fn test_await_composes_anywhere() {
+        let mut interpreter = Interpreter::with_config(Config { chaos_level: 0, ..Config::default() });
+        interpreter.is_completely_normal = true;
+        let source = "async double(x) { multiply(x, 2); }\nasync quadruple(x) { await double(await double(x)); }\n\
+                      let xs = [await double(1), await quadruple(2)];\nlet sum = add(await double(3), xs[1]);\nawait 5;";
+        let program = Parser::new(Lexer::new(source).collect()).parse().unwrap();
+        interpreter.interpret(program).unwrap();
+
+        let numbers = |values: &[i64]| Value::Array { values: values.iter().map(|&value| Value::Number { value }).collect() };
+        assert_eq!(interpreter.environment().get("xs"), Some(&numbers(&[2, 8])));
+        assert_eq!(interpreter.environment().get("sum"), Some(&Value::Number { value: 14 }));
+        assert_eq!(interpreter.last_value, Value::Number { value: 5 }, "Awaiting a plain value as a statement is fine");
+    }
+
+    #[test]
+    fn test_concurrent_blocks_finish_what_they_start() {
+        let mut interpreter = Interpreter::with_config(Config { chaos_level: 0, ..Config::default() });
+        interpreter.is_completely_normal = true;
+        let mut run = |source: &str| interpreter.interpret(Parser::new(Lexer::new(source).collect()).parse().unwrap());
+        let source = "let log = ref([]);\nasync note(x) { push(log, x); }\nasync broken() { save \"nope.txt\"; }\n\
+                      concurrent { let later = note(1); note(2); await note(3); }";
+        run(source).unwrap();
+        assert!(matches!(run("concurrent { broken(); broken(); }"), Err(RuntimeError::Multiple(errors)) if errors.len() == 2));
+        assert!(matches!(run("concurrent { note(4); save \"nope.txt\"; }"), Err(RuntimeError::SaveError)));
+
+        let numbers = |values: &[i64]| Value::Array { values: values.iter().map(|&value| Value::Number { value }).collect() };
+        let log = interpreter.environment().get("log").map(Value::deep_clone);
+        assert_eq!(log, Some(numbers(&[3, 1, 2])), "The failed block's task was cancelled");
+    }
+
+    #[test]
+    fn test_denied_capabilities_are_enforced() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_capabilities(Capabilities::none());
+
+        let print = Statement::Print { value: Expression::Literal(Literal::String("hi".to_string())) };
+        assert!(matches!(
+            interpreter.execute_statement(print),
+            Err(RuntimeError::PermissionDenied(Capability::Browser))
+        ));
+
+        interpreter.is_completely_normal = true;
+        let save = Statement::Save { filename: "secrets.txt".to_string() };
+        assert!(matches!(
+            interpreter.execute_statement(save),
+            Err(RuntimeError::PermissionDenied(Capability::Filesystem))
+        ));
+    }
+
+    #[test]
+    fn test_interpret_with_report_accounts_for_everything() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_output(Box::new(CapturedOutput::new()));
+        interpreter.is_completely_normal = true;
+
+        let report = interpreter.interpret_with_report(vec![
+            Statement::Print { value: Expression::Literal(Literal::String("hello".to_string())) },
+            Statement::Expression(Expression::Literal(Literal::Number(7))),
+        ]);
+        assert!(report.is_success());
+        assert_eq!(report.last_value, Value::Number { value: 7 });
+        assert!(report.output.contains("hello"));
+        assert_eq!(report.stats.statements_executed, 2);
+
+        let report = interpreter.interpret_with_report(vec![Statement::Expression(Expression::Identifier("nope".into()))]);
+        assert!(matches!(report.errors[..], [RuntimeError::UndefinedVariable(_)]));
+        assert_eq!(report.last_value, Value::Null);
+        assert!(report.output.is_empty(), "Each report only has its own run's output");
+    }
+
+    #[test]
+    fn test_errors_know_which_statement_failed() {
+        let run = |source: &str| {
+            let mut parser = Parser::new(Lexer::new(source).collect());
+            parser.set_record_spans(true);
+            let mut interpreter = Interpreter::with_config(Config { chaos_level: 0, ..Config::default() });
+            interpreter.set_output(Box::new(CapturedOutput::new()));
+            interpreter.set_completely_normal(true);
+            let result = interpreter.interpret(parser.parse().unwrap());
+            (result, interpreter.error_span().map(|span| span.line))
+        };
+
+        let (result, line) = run("greet(name) {\n    print(name);\n    print(nope);\n}\nlet x = 1;\ngreet(x);");
+        assert!(matches!(result, Err(RuntimeError::UndefinedVariable(_))));
+        assert_eq!(line, Some(3), "the innermost statement is the one to blame");
+
+        let (result, line) = run("try {\n    print(nope);\n} catch e {\n}\nprint(missing);");
+        assert!(result.is_err());
+        assert_eq!(line, Some(5), "caught errors don't leave their spans lying around");
+        assert_eq!(run("let x = 1;").1, None);
+    }
+
+    #[test]
+    fn test_warnings_stay_out_of_the_output() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_output(Box::new(CapturedOutput::new()));
+        interpreter.is_completely_normal = true;
+
+        let sparkles = || Statement::Directive { name: "sparkles".to_string() };
+        let report = interpreter.interpret_with_report(vec![sparkles()]);
+        assert!(report.is_success());
+        assert!(report.output.is_empty(), "{}", report.output);
+        assert_eq!(report.warnings, [Warning::UnknownDirective("sparkles".to_string())]);
+
+        let report = interpreter.interpret_with_report(vec![sparkles(), sparkles()]);
+        assert_eq!(report.warnings.len(), 2, "each report only has its own run's warnings");
+        assert_eq!(interpreter.warnings().len(), 3);
+    }
+
+    #[test]
+    fn test_exit_status_in_obedient_mode() {
+        let mut harness = NormalHarness::new();
+
+        let report = harness.run("try { exit(3); } catch e { print(\"caught\"); } print(\"after\");").unwrap();
+        assert!(report.is_success());
+        assert_eq!(report.exit_status, Some(3));
+        assert!(report.output.is_empty(), "exit() can't be caught, got {:?}", report.output);
+
+        let report = harness.run("exit(\"soon\");").unwrap();
+        assert!(matches!(report.errors[..], [RuntimeError::BadArguments(_, _)]));
+        assert_eq!(harness.run("").unwrap().exit_status, None);
+    }
+
+    #[test]
+    fn test_eval_uses_current_state() {
+        let mut interpreter = Interpreter::new();
+        interpreter.is_completely_normal = true;
+        interpreter.environment.set("x".to_string(), Value::Number { value: 20 });
+
+        assert_eq!(interpreter.eval("add(x, 22);").unwrap(), Value::Number { value: 42 });
+        assert!(matches!(interpreter.eval("x x"), Err(Error::Parse(_))));
+        assert!(matches!(interpreter.eval(""), Err(Error::Parse(_))));
+        assert!(matches!(interpreter.eval("y"), Err(Error::Runtime(RuntimeError::UndefinedVariable(_)))));
+    }
+
+    #[test]
+    fn test_keep_going_collects_top_level_errors() {
+        let missing = |name: &str| Statement::Expression(Expression::Identifier(name.into()));
+        let program = vec![
+            Statement::Directive { name: "disable_all_useless_shit".to_string() },
+            missing("first"),
+            Statement::Let { name: "x".into(), value: Expression::Literal(Literal::Number(1)) },
+            missing("second"),
+        ];
+
+        let mut interpreter = Interpreter::with_config(Config { keep_going: true, ..Config::default() });
+        let report = interpreter.interpret_with_report(program.clone());
+        assert!(matches!(
+            report.errors[..],
+            [RuntimeError::UndefinedVariable(ref a), RuntimeError::UndefinedVariable(ref b)] if a == "first" && b == "second"
+        ));
+        assert_eq!(interpreter.environment().get("x"), Some(&Value::Number { value: 1 }));
+        assert!(matches!(interpreter.interpret(program.clone()), Err(RuntimeError::Multiple(errors)) if errors.len() == 2));
+
+        let mut interpreter = Interpreter::new();
+        assert!(matches!(interpreter.interpret(program), Err(RuntimeError::UndefinedVariable(_))));
+        assert!(!interpreter.environment().contains("x"), "Without keep_going the first error ends it");
+    }
+
+    #[test]
+    fn test_contagion_is_opt_in() {
+        let mut interpreter = Interpreter::new();
+        interpreter.is_completely_normal = true;
+        interpreter.execute_statement(Statement::TryCatch {
+            try_block: vec![Statement::Expression(Expression::Identifier("missing".into()))],
+            error_binding: ErrorBinding::Name("e".into()),
+            catch_block: vec![],
+        }).unwrap();
+
+        assert_eq!(interpreter.contagion_level(), 0.0);
+        assert_eq!(interpreter.stats().errors_caught, 1);
+    }
+
+    #[test]
+    fn test_value_ordering_is_total() {
+        let number = |value| Value::Number { value };
+        assert!(Value::Null < Value::Boolean { value: false });
+        assert!(Value::Boolean { value: true } < number(-100));
+        assert!(number(100) < Value::String { value: "".to_string() });
+        assert!(Value::Array { values: vec![number(1)] } < Value::Array { values: vec![number(1), number(0)] });
+        assert!(Value::Array { values: vec![number(2)] } > Value::Array { values: vec![number(1), number(5)] });
+
+        let object = |value| Value::Object { fields: ObjectMap::from([("a".to_string(), number(value))]) };
+        assert_eq!(object(1).compare(&object(1)), Ordering::Equal);
+        assert!(object(1) < object(2));
+    }
+
+    #[test]
+    fn test_sort_by_user_function() {
+        let mut interpreter = Interpreter::new();
+        interpreter.is_completely_normal = true;
+        let words = ["ccc", "a", "bb", "d"];
+        let program = vec![
+            // key(x) { x; } hands back its argument, since the last expression is the return value
+            Statement::Function {
+                name: "key".into(),
+                parameters: vec!["x".into()],
+                body: vec![Statement::Expression(Expression::Identifier("x".into()))],
+            },
+            Statement::Let {
+                name: "sorted".into(),
+                value: Expression::FunctionCall {
+                    name: "sortBy".into(),
+                    arguments: vec![
+                        Expression::Literal(Literal::Array(
+                            words.iter()
+                                .map(|w| Box::new(Expression::Literal(Literal::String(w.to_string()))))
+                                .collect(),
+                        )),
+                        Expression::Identifier("key".into()),
+                    ],
+                },
+            },
+        ];
+        for statement in program {
+            interpreter.execute_statement(statement).unwrap();
         }
 
-        assert!(
-            saw_number || saw_boolean,
-            "Expected to see either original number or boolean conversion"
-        );
+        let expected: Vec<Value> = ["a", "bb", "ccc", "d"]
+            .iter()
+            .map(|w| Value::String { value: w.to_string() })
+            .collect();
+        assert_eq!(interpreter.environment().get("sorted"), Some(&Value::Array { values: expected }));
+        assert!(!interpreter.environment().contains("x"), "Parameters shouldn't leak out of the call");
     }
 
     #[test]
-    fn test_array_chaos() {
+    fn test_overflow_promotes_to_bigint() {
         let mut interpreter = Interpreter::new();
-        let array_expr = Expression::Literal(Literal::Array(vec![
-            Box::new(Expression::Literal(Literal::Number(1))),
-            Box::new(Expression::Literal(Literal::Number(2))),
-            Box::new(Expression::Literal(Literal::Number(3))),
-        ]));
+        interpreter.is_completely_normal = true;
 
-        match interpreter.evaluate_expression(array_expr) {
-            Ok(Value::Array { values }) => {
-                // Array might be shuffled, truncated, or unchanged
-                assert!(values.len() <= 3, "Array should not grow");
+        let sum = interpreter
+            .evaluate_binary_op(BinaryOp::Add, Value::Number { value: i64::MAX }, Value::Number { value: 1 })
+            .unwrap();
+        assert_eq!(sum, Value::BigInt { value: BigInt::parse("9223372036854775808").unwrap() });
+
+        let product = interpreter
+            .evaluate_binary_op(BinaryOp::Multiply, sum, Value::Number { value: 2 })
+            .unwrap();
+        assert_eq!(product, Value::BigInt { value: BigInt::parse("18446744073709551616").unwrap() });
+
+        // Coming back down to earth turns it back into a plain number
+        let back = interpreter
+            .evaluate_binary_op(BinaryOp::Add, Value::BigInt { value: BigInt::parse("9223372036854775808").unwrap() }, Value::Number { value: -1 })
+            .unwrap();
+        assert_eq!(back, Value::Number { value: i64::MAX });
+    }
+
+    #[test]
+    fn test_floats_are_contagious() {
+        let mut interpreter = Interpreter::new();
+        interpreter.is_completely_normal = true;
+        let float = |value| Value::Float { value };
+
+        let sum = interpreter.evaluate_binary_op(BinaryOp::Add, float(1.5), Value::Number { value: 2 }).unwrap();
+        assert_eq!(sum, float(3.5));
+        assert_eq!(sum.to_string(), "3.5");
+        assert_eq!(float(2.0).to_string(), "2.0");
+        let equal = interpreter.evaluate_binary_op(BinaryOp::Equals, float(2.0), Value::Number { value: 2 }).unwrap();
+        assert_eq!(equal, Value::Boolean { value: true });
+        assert!(float(2.0).deep_equals(&Value::Number { value: 2 }));
+        assert!(float(0.5) < Value::Number { value: 1 } && Value::Number { value: 1 } < float(1.5));
+        assert_eq!(float(0.5).type_name(), "number");
+    }
+
+    #[test]
+    fn test_chaotic_arithmetic_never_panics_on_overflow() {
+        let mut interpreter = Interpreter::new();
+        for _ in 0..50 {
+            for op in [BinaryOp::Add, BinaryOp::Multiply] {
+                let _ = interpreter.evaluate_binary_op(op.clone(), Value::Number { value: i64::MIN }, Value::Number { value: -1 });
+                let _ = interpreter.evaluate_binary_op(op, Value::Number { value: i64::MAX }, Value::Number { value: i64::MAX });
             }
-            Ok(_) => (), // Any transformation is valid
-            Err(_) => (), // Errors are valid too
         }
     }
 
     #[test]
-    fn test_object_chaos() {
+    fn test_string_index_and_slice_in_normal_mode() {
         let mut interpreter = Interpreter::new();
-        let object_expr = Expression::Literal(Literal::Object(vec![
-            ("key1".to_string(), Box::new(Expression::Literal(Literal::Number(1)))),
-            ("key2".to_string(), Box::new(Expression::Literal(Literal::Number(2)))),
-        ]));
+        interpreter.is_completely_normal = true;
+        let word = || Box::new(Expression::Literal(Literal::String("naïve".to_string())));
+        let number = |n| Box::new(Expression::Literal(Literal::Number(n)));
 
-        match interpreter.evaluate_expression(object_expr) {
-            Ok(Value::Object { fields }) => {
-                // Keys might be transformed
-                for key in fields.keys() {
-                    assert!(!key.is_empty(), "Keys should not be empty");
-                }
-            }
-            Ok(_) => (), // Any transformation is valid
-            Err(_) => (), // Errors are valid too
+        let index = Expression::BinaryOp { op: BinaryOp::Index, left: word(), right: number(2) };
+        assert_eq!(interpreter.evaluate_expression(index).unwrap(), Value::String { value: "ï".to_string() });
+
+        let slice = Expression::Slice { object: word(), start: Some(number(1)), end: Some(number(4)) };
+        assert_eq!(interpreter.evaluate_expression(slice).unwrap(), Value::String { value: "aïv".to_string() });
+    }
+
+    #[test]
+    fn test_for_in_over_strings_objects_and_ranges() {
+        let mut interpreter = Interpreter::new();
+        interpreter.is_completely_normal = true;
+        let collect = |interpreter: &mut Interpreter, iterable: Expression| {
+            interpreter.execute_statement(Statement::Let {
+                name: "items".into(),
+                value: Expression::FunctionCall { name: "ref".into(), arguments: vec![Expression::Literal(Literal::Array(vec![]))] },
+            }).unwrap();
+            interpreter.execute_statement(Statement::ForIn {
+                variable: "item".into(),
+                iterable,
+                body: vec![Statement::Expression(Expression::FunctionCall {
+                    name: "push".into(),
+                    arguments: vec![Expression::Identifier("items".into()), Expression::Identifier("item".into())],
+                })],
+            }).unwrap();
+            assert!(!interpreter.environment().contains("item"), "The loop variable shouldn't outlive the loop");
+            interpreter.environment().get("items").cloned().unwrap().dereference()
+        };
+        let string = |s: &str| Value::String { value: s.to_string() };
+
+        let letters = collect(&mut interpreter, Expression::Literal(Literal::String("hé!".to_string())));
+        assert_eq!(letters, Value::Array { values: vec![string("h"), string("é"), string("!")] });
+
+        let numbers = collect(&mut interpreter, Expression::FunctionCall {
+            name: "range".into(),
+            arguments: vec![Expression::Literal(Literal::Number(3))],
+        });
+        assert_eq!(numbers, Value::Array { values: (0..3).map(|value| Value::Number { value }).collect() });
+
+        let entries = collect(&mut interpreter, Expression::Literal(Literal::Object(vec![
+            ("b".to_string(), Box::new(Expression::Literal(Literal::Number(2)))),
+            ("a".to_string(), Box::new(Expression::Literal(Literal::Number(1)))),
+        ])));
+        // Keys come out in the order they went in, not alphabetically
+        assert_eq!(entries, Value::Array { values: vec![
+            Value::Array { values: vec![string("b"), Value::Number { value: 2 }] },
+            Value::Array { values: vec![string("a"), Value::Number { value: 1 }] },
+        ] });
+    }
+
+    struct Watcher;
+
+    impl ExecutionHook for Watcher {}
+
+    #[test]
+    fn test_heisenberg_behaves_while_observed() {
+        let mut interpreter = Interpreter::with_config(Config {
+            heisenberg: true,
+            ..Config::default()
+        });
+        interpreter.add_hook(Box::new(Watcher));
+
+        for _ in 0..100 {
+            let value = interpreter.evaluate_expression(Expression::Literal(Literal::Number(42)));
+            assert_eq!(value.unwrap(), Value::Number { value: 42 });
         }
+        assert!(interpreter.chaos_audit().is_empty());
+
+        // The moment nobody is looking, numbers start having identity crises again
+        interpreter.clear_hooks();
+        let mutated = (0..100).any(|_| {
+            interpreter.evaluate_expression(Expression::Literal(Literal::Number(42))).ok()
+                != Some(Value::Number { value: 42 })
+        });
+        assert!(mutated, "Expected chaos once unobserved");
     }
 
     #[test]
@@ -0,0 +1,253 @@
+//! # JSON Module
+//!
+//! Just enough JSON to hand machine-readable output to machines,
+//! which appreciate the jokes even less than people do, and to read what they send back.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::json::{self, Json};
+//!
+//! assert_eq!(json::quote("say \"hi\"\n"), r#""say \"hi\"\n""#);
+//!
+//! let message = Json::parse(r#"{"id": 1, "params": {"text": "hi"}}"#).unwrap();
+//! assert_eq!(message.get("params").and_then(|params| params.get("text")).and_then(Json::as_str), Some("hi"));
+//! assert_eq!(message.get("id").unwrap().to_string(), "1");
+//! ```
+
+use std::fmt::{self, Write};
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Writes a string as a JSON string literal, quotes and escapes included.
+pub fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            },
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Writes an optional string as a JSON string, or `null`.
+pub fn quote_or_null(text: Option<&str>) -> String {
+    text.map_or_else(|| "null".to_string(), quote)
+}
+
+/// A JSON value, read from text. Objects keep their keys in the order they were written.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    /// `null`
+    Null,
+    /// `true` or `false`
+    Bool(bool),
+    /// Every number, whole or not
+    Number(f64),
+    /// A string, escapes already undone
+    String(String),
+    /// `[...]`
+    Array(Vec<Json>),
+    /// `{...}`, as key-value pairs
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Reads a JSON value, with nothing but whitespace around it. Returns `None` for anything
+    /// that isn't quite JSON.
+    pub fn parse(text: &str) -> Option<Json> {
+        let mut chars = text.chars().peekable();
+        let value = parse_value(&mut chars)?;
+        skip_whitespace(&mut chars);
+        chars.next().is_none().then_some(value)
+    }
+
+    /// Looks up a key, if this is an object that has it.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Returns the string, if this is one.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Returns the number, if this is a whole number that isn't negative.
+    pub fn as_usize(&self) -> Option<usize> {
+        match self {
+            Json::Number(number) if *number >= 0.0 && number.fract() == 0.0 => Some(*number as usize),
+            _ => None,
+        }
+    }
+}
+
+/// Writes the value back out as compact JSON.
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            Json::Number(number) => write!(f, "{}", number),
+            Json::String(text) => f.write_str(&quote(text)),
+            Json::Array(values) => {
+                f.write_char('[')?;
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                f.write_char(']')
+            },
+            Json::Object(fields) => {
+                f.write_char('{')?;
+                for (index, (key, value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{}:{}", quote(key), value)?;
+                }
+                f.write_char('}')
+            },
+        }
+    }
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars<'_>>) {
+    while chars.next_if(|c| c.is_ascii_whitespace()).is_some() {}
+}
+
+/// Reads one value, skipping the whitespace in front of it.
+fn parse_value(chars: &mut Peekable<Chars<'_>>) -> Option<Json> {
+    skip_whitespace(chars);
+    match *chars.peek()? {
+        '{' => {
+            chars.next();
+            let mut fields = Vec::new();
+            skip_whitespace(chars);
+            if chars.next_if_eq(&'}').is_some() {
+                return Some(Json::Object(fields));
+            }
+            loop {
+                skip_whitespace(chars);
+                let Json::String(key) = parse_value(chars)? else { return None };
+                skip_whitespace(chars);
+                chars.next_if_eq(&':')?;
+                fields.push((key, parse_value(chars)?));
+                skip_whitespace(chars);
+                match chars.next()? {
+                    ',' => continue,
+                    '}' => return Some(Json::Object(fields)),
+                    _ => return None,
+                }
+            }
+        },
+        '[' => {
+            chars.next();
+            let mut values = Vec::new();
+            skip_whitespace(chars);
+            if chars.next_if_eq(&']').is_some() {
+                return Some(Json::Array(values));
+            }
+            loop {
+                values.push(parse_value(chars)?);
+                skip_whitespace(chars);
+                match chars.next()? {
+                    ',' => continue,
+                    ']' => return Some(Json::Array(values)),
+                    _ => return None,
+                }
+            }
+        },
+        '"' => {
+            chars.next();
+            parse_string(chars).map(Json::String)
+        },
+        't' => parse_word(chars, "true", Json::Bool(true)),
+        'f' => parse_word(chars, "false", Json::Bool(false)),
+        'n' => parse_word(chars, "null", Json::Null),
+        _ => {
+            let mut number = String::new();
+            while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+                number.push(c);
+            }
+            number.parse().ok().map(Json::Number)
+        },
+    }
+}
+
+/// Reads the rest of a string, after its opening quote.
+fn parse_string(chars: &mut Peekable<Chars<'_>>) -> Option<String> {
+    let mut text = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(text),
+            '\\' => match chars.next()? {
+                'n' => text.push('\n'),
+                'r' => text.push('\r'),
+                't' => text.push('\t'),
+                'b' => text.push('\u{8}'),
+                'f' => text.push('\u{c}'),
+                'u' => {
+                    let unit = parse_hex(chars)?;
+                    // Characters outside the basic plane come as two halves
+                    let code = if (0xd800..0xdc00).contains(&unit) {
+                        chars.next_if_eq(&'\\')?;
+                        chars.next_if_eq(&'u')?;
+                        0x10000 + ((unit - 0xd800) << 10) + (parse_hex(chars)?.checked_sub(0xdc00)? & 0x3ff)
+                    } else {
+                        unit
+                    };
+                    text.push(char::from_u32(code)?);
+                },
+                c => text.push(c),
+            },
+            c => text.push(c),
+        }
+    }
+}
+
+/// Reads the four hex digits of a `\u` escape.
+fn parse_hex(chars: &mut Peekable<Chars<'_>>) -> Option<u32> {
+    (0..4).try_fold(0, |code, _| Some(code * 16 + chars.next()?.to_digit(16)?))
+}
+
+/// Reads a keyword that has to be spelled exactly right.
+fn parse_word(chars: &mut Peekable<Chars<'_>>, word: &str, value: Json) -> Option<Json> {
+    word.chars().all(|expected| chars.next() == Some(expected)).then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reads_what_quote_writes() {
+        let text = "tab\t, quote \", emoji 🦄, \u{1}";
+        assert_eq!(Json::parse(&quote(text)), Some(Json::String(text.to_string())));
+        assert_eq!(Json::parse(r#""\ud83e\udd84""#), Some(Json::String("🦄".to_string())));
+
+        let value = Json::parse(r#" {"a": [1, -2.5e1, true, null], "b": {}} "#).unwrap();
+        assert_eq!(value.to_string(), r#"{"a":[1,-25,true,null],"b":{}}"#);
+        assert_eq!(value.get("a"), Some(&Json::Array(vec![Json::Number(1.0), Json::Number(-25.0), Json::Bool(true), Json::Null])));
+
+        for broken in ["", "{", "[1,]", "{\"a\" 1}", "tru", "1 2", "\"unterminated"] {
+            assert_eq!(Json::parse(broken), None, "{}", broken);
+        }
+    }
+}
@@ -14,12 +14,14 @@
 
 use logos::Logos;
 
+use crate::json;
+
 /// All the different kinds of tokens in our language.
 /// Each one is special in its own useless way.
 #[derive(Logos, Debug, PartialEq, Clone)]
 pub enum TokenKind {
     /// Attribute directives for controlling language behavior
-    #[regex(r"#\[[a-zA-Z_][a-zA-Z0-9_]*(?:\([^)]*\))?\]")]
+    #[regex(r"#\[[a-zA-Z_][a-zA-Z0-9_]*(?:\((?:[^()]|\([^()]*\))*\))?\]")]
     Attribute,
 
     /// Module declaration keyword
@@ -86,6 +88,30 @@ pub enum TokenKind {
     #[token("catch")]
     Catch,
 
+    /// Pray keyword for blocks that retry until the gods stop listening
+    #[token("pray")]
+    Pray,
+
+    /// Concurrent keyword for blocks that wait for every task they started
+    #[token("concurrent")]
+    Concurrent,
+
+    /// For keyword for loops over collections
+    #[token("for")]
+    For,
+
+    /// In keyword, as in `for x in things`
+    #[token("in")]
+    In,
+
+    /// Sudo keyword for the one statement that gets to behave
+    #[token("sudo")]
+    Sudo,
+
+    /// Macro keyword for homemade syntax sugar
+    #[token("macro")]
+    Macro,
+
     /// Directive token for language behavior control
     #[token("directive")]
     Directive,
@@ -157,10 +183,22 @@ pub enum TokenKind {
     #[token(":")]
     Colon,
 
+    /// Optional chaining, for accessing things that might not be there
+    #[token("?.")]
+    QuestionDot,
+
+    /// Null coalescing, for when you have a backup plan
+    #[token("??")]
+    NullCoalesce,
+
     /// Double colon token for module paths
     #[token("::")]
     DoubleColon,
 
+    /// Range token for slices, like `[1..3]`
+    #[token("..")]
+    DotDot,
+
     /// String literals, which might contain anything but what you wrote
     #[regex("\"[^\"]*\"")]
     StringLiteral,
@@ -169,6 +207,10 @@ pub enum TokenKind {
     #[regex("[0-9]+")]
     NumberLiteral,
 
+    /// Float literals, for numbers with a point (the point being that they're never quite exact)
+    #[regex("[0-9]+\\.[0-9]+")]
+    FloatLiteral,
+
     /// Identifiers, for naming things that won't behave
     #[regex("[a-zA-Z_][a-zA-Z0-9_]*")]
     Identifier,
@@ -180,23 +222,134 @@ pub enum TokenKind {
     /// Comments, where you can write what you hope the code will do
     #[regex(r"//[^\n]*\n?", logos::skip)]
     Comment,
+
+    /// Doc comments (`///`), where you can write what you hope the code will do and have it published.
+    /// Only lexers made with [`Lexer::with_doc_comments`] hand these out
+    #[regex(r"///[^\n]*\n?", priority = 10)]
+    DocComment,
 }
 
-/// A token in our language, consisting of its kind and the text it was parsed from.
+impl TokenKind {
+    /// Describes the kind of token the way an error message would, like `';'` or `a name`.
+    pub fn describe(&self) -> &'static str {
+        match self {
+            TokenKind::Attribute => "an attribute",
+            TokenKind::Module => "'mod'",
+            TokenKind::Use => "'use'",
+            TokenKind::Print => "'print'",
+            TokenKind::Let => "'let'",
+            TokenKind::If => "'if'",
+            TokenKind::Else => "'else'",
+            TokenKind::Loop => "'loop'",
+            TokenKind::Save => "'save'",
+            TokenKind::Add => "'add'",
+            TokenKind::Multiply => "'multiply'",
+            TokenKind::Exit => "'exit'",
+            TokenKind::Promise => "'promise'",
+            TokenKind::Await => "'await'",
+            TokenKind::Async => "'async'",
+            TokenKind::Try => "'try'",
+            TokenKind::Catch => "'catch'",
+            TokenKind::Pray => "'pray'",
+            TokenKind::Concurrent => "'concurrent'",
+            TokenKind::For => "'for'",
+            TokenKind::In => "'in'",
+            TokenKind::Sudo => "'sudo'",
+            TokenKind::Macro => "'macro'",
+            TokenKind::Directive => "'directive'",
+            TokenKind::Index => "'index'",
+            TokenKind::Access => "'access'",
+            TokenKind::Equals => "'equals'",
+            TokenKind::LessThan => "'lessThan'",
+            TokenKind::True => "'true'",
+            TokenKind::False => "'false'",
+            TokenKind::Null => "'null'",
+            TokenKind::LeftParen => "'('",
+            TokenKind::RightParen => "')'",
+            TokenKind::LeftBrace => "'{'",
+            TokenKind::RightBrace => "'}'",
+            TokenKind::LeftBracket => "'['",
+            TokenKind::RightBracket => "']'",
+            TokenKind::Semicolon => "';'",
+            TokenKind::Assignment => "'='",
+            TokenKind::Comma => "','",
+            TokenKind::Colon => "':'",
+            TokenKind::QuestionDot => "'?.'",
+            TokenKind::NullCoalesce => "'??'",
+            TokenKind::DoubleColon => "'::'",
+            TokenKind::DotDot => "'..'",
+            TokenKind::StringLiteral => "a string",
+            TokenKind::NumberLiteral => "a number",
+            TokenKind::FloatLiteral => "a float",
+            TokenKind::Identifier => "a name",
+            TokenKind::Whitespace => "whitespace",
+            TokenKind::Comment => "a comment",
+            TokenKind::DocComment => "a doc comment",
+        }
+    }
+}
+
+/// Where something is in the source code: byte offsets, plus the (1-based) line and column it starts at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    /// Byte offset where it starts
+    pub start: usize,
+    /// Byte offset just past where it ends
+    pub end: usize,
+    /// The line it starts on, counting from 1
+    pub line: usize,
+    /// The column it starts at, counting from 1
+    pub column: usize,
+}
+
+impl Span {
+    /// Writes the span as a JSON object.
+    pub fn to_json(&self) -> String {
+        format!(r#"{{"start":{},"end":{},"line":{},"column":{}}}"#, self.start, self.end, self.line, self.column)
+    }
+}
+
+/// A token in our language, consisting of its kind, the text it was parsed from and where it was.
 /// The text might not match what you see in the source code.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct Token {
     /// The kind of token this is
     pub kind: TokenKind,
     /// The text that was parsed into this token
     pub text: String,
+    /// Where the token was found
+    pub span: Span,
 }
 
 impl Token {
-    /// Creates a new token with the given kind and text.
+    /// Creates a new token with the given kind and text, found nowhere in particular.
     /// Use sparingly, as tokens have a mind of their own.
     pub fn new(kind: TokenKind, text: String) -> Self {
-        Self { kind, text }
+        Self { kind, text, span: Span::default() }
+    }
+
+    /// Moves the token to where it was found.
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+
+    /// Writes the token as JSON: its kind, its text and where it was.
+    pub fn to_json(&self) -> String {
+        format!(r#"{{"kind":{},"text":{},"span":{}}}"#, json::quote(&format!("{:?}", self.kind)), json::quote(&self.text), self.span.to_json())
+    }
+}
+
+/// Writes a whole token stream as a JSON array, for highlighters that don't want to link a lexer.
+pub fn tokens_to_json(tokens: &[Token]) -> String {
+    let tokens: Vec<String> = tokens.iter().map(Token::to_json).collect();
+    format!("[{}]", tokens.join(","))
+}
+
+/// Tokens are equal when they have the same kind and text, wherever they were found.
+impl PartialEq for Token {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind && self.text == other.text
     }
 }
 
@@ -205,6 +358,14 @@ impl Token {
 pub struct Lexer<'a> {
     /// The underlying logos lexer
     inner: logos::Lexer<'a, TokenKind>,
+    /// The line the last token was on, counting from 1
+    line: usize,
+    /// Byte offset where that line starts
+    line_start: usize,
+    /// Byte offset up to which lines have been counted
+    counted: usize,
+    /// Whether doc comments come out as tokens, instead of being skipped like the other comments
+    doc_comments: bool,
 }
 
 impl<'a> Lexer<'a> {
@@ -213,6 +374,37 @@ impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
         Self {
             inner: TokenKind::lexer(input),
+            line: 1,
+            line_start: 0,
+            counted: 0,
+            doc_comments: false,
+        }
+    }
+
+    /// Hands out `///` doc comments as [`TokenKind::DocComment`] tokens, for the parser to attach
+    /// to whatever they document. Everyone else never sees them.
+    pub fn with_doc_comments(mut self) -> Self {
+        self.doc_comments = true;
+        self
+    }
+
+    /// Works out the line and column of the token just lexed. Tokens come in order,
+    /// so only the source since the previous token needs counting.
+    fn span(&mut self) -> Span {
+        let range = self.inner.span();
+        let source = self.inner.source();
+        for (offset, byte) in source.as_bytes()[self.counted..range.start].iter().enumerate() {
+            if *byte == b'\n' {
+                self.line += 1;
+                self.line_start = self.counted + offset + 1;
+            }
+        }
+        self.counted = range.start;
+        Span {
+            start: range.start,
+            end: range.end,
+            line: self.line,
+            column: source[self.line_start..range.start].chars().count() + 1,
         }
     }
 }
@@ -224,7 +416,11 @@ impl<'a> Iterator for Lexer<'a> {
     /// Returns None when there are no more tokens, or when the lexer gets bored.
     fn next(&mut self) -> Option<Self::Item> {
         match self.inner.next() {
-            Some(Ok(kind)) => Some(Token::new(kind, self.inner.slice().to_string())),
+            Some(Ok(TokenKind::DocComment)) if !self.doc_comments => self.next(),
+            Some(Ok(kind)) => {
+                let span = self.span();
+                Some(Token::new(kind, self.inner.slice().to_string()).with_span(span))
+            },
             Some(Err(_)) => self.next(),
             None => None,
         }
@@ -235,6 +431,15 @@ impl<'a> Iterator for Lexer<'a> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_tokens_as_json() {
+        let tokens: Vec<Token> = Lexer::new("let s = \"hi\";").collect();
+        let json = tokens_to_json(&tokens);
+        assert!(json.starts_with(r#"[{"kind":"Let","text":"let","span":{"start":0,"end":3,"line":1,"column":1}},"#), "{}", json);
+        assert!(json.contains(r#"{"kind":"StringLiteral","text":"\"hi\"","#), "{}", json);
+        assert_eq!(tokens_to_json(&[]), "[]");
+    }
+
     #[test]
     fn test_basic_tokens() {
         let input = "print(\"Hello, World!\");";
@@ -386,4 +591,97 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_pray_keyword() {
+        let input = "pray(3) { save \"x\"; }";
+        let lexer = Lexer::new(input);
+        let tokens: Vec<Token> = lexer.collect();
+
+        assert_eq!(tokens[0], Token::new(TokenKind::Pray, "pray".to_string()));
+        assert_eq!(tokens[2], Token::new(TokenKind::NumberLiteral, "3".to_string()));
+    }
+
+    #[test]
+    fn test_for_in_keywords() {
+        let input = "for x in range(0, 3) { }";
+        let lexer = Lexer::new(input);
+        let tokens: Vec<Token> = lexer.collect();
+
+        assert_eq!(tokens[0], Token::new(TokenKind::For, "for".to_string()));
+        assert_eq!(tokens[1], Token::new(TokenKind::Identifier, "x".to_string()));
+        assert_eq!(tokens[2], Token::new(TokenKind::In, "in".to_string()));
+        assert_eq!(tokens[3], Token::new(TokenKind::Identifier, "range".to_string()));
+
+        // Identifiers that merely start with a keyword are still identifiers
+        let tokens: Vec<Token> = Lexer::new("format inside").collect();
+        assert_eq!(tokens[0].kind, TokenKind::Identifier);
+        assert_eq!(tokens[1].kind, TokenKind::Identifier);
+    }
+
+    #[test]
+    fn test_sudo_keyword() {
+        let input = "sudo print(1);";
+        let lexer = Lexer::new(input);
+        let tokens: Vec<Token> = lexer.collect();
+
+        assert_eq!(tokens[0], Token::new(TokenKind::Sudo, "sudo".to_string()));
+        assert_eq!(tokens[1], Token::new(TokenKind::Print, "print".to_string()));
+    }
+
+    #[test]
+    fn test_macro_keyword() {
+        let input = "macro twice(x) { x; x; }";
+        let lexer = Lexer::new(input);
+        let tokens: Vec<Token> = lexer.collect();
+
+        assert_eq!(tokens[0], Token::new(TokenKind::Macro, "macro".to_string()));
+        assert_eq!(tokens[1], Token::new(TokenKind::Identifier, "twice".to_string()));
+    }
+
+    #[test]
+    fn test_slice_sugar() {
+        let input = "name[1..3]";
+        let lexer = Lexer::new(input);
+        let tokens: Vec<Token> = lexer.collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::new(TokenKind::Identifier, "name".to_string()),
+                Token::new(TokenKind::LeftBracket, "[".to_string()),
+                Token::new(TokenKind::NumberLiteral, "1".to_string()),
+                Token::new(TokenKind::DotDot, "..".to_string()),
+                Token::new(TokenKind::NumberLiteral, "3".to_string()),
+                Token::new(TokenKind::RightBracket, "]".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_null_handling_operators() {
+        let input = "user?.name ?? \"anonymous\"";
+        let lexer = Lexer::new(input);
+        let tokens: Vec<Token> = lexer.collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::new(TokenKind::Identifier, "user".to_string()),
+                Token::new(TokenKind::QuestionDot, "?.".to_string()),
+                Token::new(TokenKind::Identifier, "name".to_string()),
+                Token::new(TokenKind::NullCoalesce, "??".to_string()),
+                Token::new(TokenKind::StringLiteral, "\"anonymous\"".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokens_know_where_they_are() {
+        let tokens: Vec<Token> = Lexer::new("let x = 1;\n// comment\n  print(\"é\", x);").collect();
+        let spans: Vec<(usize, usize)> = tokens.iter().map(|token| (token.span.line, token.span.column)).collect();
+        assert_eq!(spans[..5], [(1, 1), (1, 5), (1, 7), (1, 9), (1, 10)]);
+        assert_eq!(spans[5..], [(3, 3), (3, 8), (3, 9), (3, 12), (3, 14), (3, 15), (3, 16)]);
+        assert_eq!(tokens[7].span.end - tokens[7].span.start, 4, "offsets are in bytes, and é takes two");
+    }
 }
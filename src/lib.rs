@@ -1,10 +1,137 @@
 pub mod ast;
+pub mod batch;
+pub mod bench;
+pub mod bigint;
+pub mod builtins;
+pub mod cancel;
+pub mod capabilities;
+pub mod chaos;
+pub mod cli;
+pub mod clock;
+pub mod codegen;
+pub mod config;
+pub mod debug;
+pub mod diff;
+pub mod doc;
+pub mod environment;
+pub mod error;
+pub mod events;
+pub mod explain;
+pub mod fmt;
+pub mod hooks;
+pub mod intern;
 pub mod interpreter;
+pub mod json;
 pub mod lexer;
+pub mod loader;
+#[cfg(feature = "lsp")]
+pub mod lsp;
+pub mod macros;
+pub mod messages;
+pub mod object;
+pub mod output;
 pub mod parser;
+pub mod plugin;
+pub mod profile;
+#[cfg(feature = "regex")]
+pub mod regex;
+pub mod reload;
+pub mod repl;
+pub mod report;
+pub mod runtime;
+pub mod scaffold;
+pub mod scheduler;
+pub mod session;
+pub mod snapshot;
+pub mod stats;
+pub mod testing;
+pub mod trace;
+#[cfg(feature = "uplb")]
+pub mod uplb;
+pub mod warning;
+pub mod watch;
 
 // Re-export main types for easier access
-pub use ast::{Expression, Literal, Statement, BinaryOp, Program};
+pub use ast::{Expression, Literal, Statement, BinaryOp, ErrorBinding, Program};
+pub use config::Config;
+pub use error::{Error, ErrorKind, ErrorReport, QuickFix, Severity, TextEdit};
 pub use interpreter::{Interpreter, Value, RuntimeError};
 pub use lexer::{Lexer, Token, TokenKind};
-pub use parser::{Parser, ParseError};
+pub use parser::{Parser, ParseError, ParserLimits};
+pub use report::RunResult;
+pub use warning::Warning;
+
+use output::CapturedOutput;
+
+/// How [`run`] sets up the interpreter. The defaults are the full chaos experience, with a random seed.
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// The config to run with
+    pub config: Config,
+    /// Seeds the chaos, so the same program misbehaves the same way every time
+    pub seed: Option<u64>,
+    /// Switches all chaos off, like `disable_all_useless_shit`
+    pub normal: bool,
+    /// What `args()` returns
+    pub args: Vec<String>,
+}
+
+/// Parses a program, pointing out every syntax error at once if there are any.
+pub fn parse(source: &str) -> Result<Program, Vec<ParseError>> {
+    match Parser::new(Lexer::new(source).collect()).parse_with_recovery() {
+        (program, errors) if errors.is_empty() => Ok(program),
+        (_, errors) => Err(errors),
+    }
+}
+
+/// Parses and runs a program in a fresh interpreter, for everyone who doesn't need to wire up
+/// the lexer, parser and interpreter by hand. Printed output is captured in the result instead
+/// of going to stdout, and syntax errors end up in [`RunResult::parse_errors`] without anything
+/// running.
+///
+/// ```rust
+/// use useless_lang::{run, RunOptions, Value};
+///
+/// let result = run("let x = add(1, 2); print(x); x;", RunOptions { normal: true, ..RunOptions::default() });
+/// assert_eq!(result.last_value, Value::Number { value: 3 });
+/// assert_eq!(result.output, "Number { value: 3 }\n");
+///
+/// let broken = run("let = ;", RunOptions::default());
+/// assert!(!broken.is_success() && !broken.parse_errors.is_empty());
+/// ```
+pub fn run(source: &str, options: RunOptions) -> RunResult {
+    let program = match parse(source) {
+        Ok(program) => program,
+        Err(errors) => return RunResult::unparsed(errors),
+    };
+
+    let mut interpreter = Interpreter::with_config(options.config);
+    interpreter.set_output(Box::new(CapturedOutput::new()));
+    interpreter.set_completely_normal(options.normal);
+    interpreter.set_args(options.args);
+    if let Some(seed) = options.seed {
+        interpreter.set_seed(seed);
+    }
+    interpreter.interpret_with_report(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_with_the_same_seed_misbehaves_the_same_way() {
+        let options = RunOptions {
+            config: Config { open_browser: false, promise_delay_max_ms: 0, ..Config::default() },
+            seed: Some(7),
+            ..RunOptions::default()
+        };
+        let source = "let a = [1, 2, 3]; print(a); print(args());";
+        let first = run(source, RunOptions { args: vec!["x".to_string()], ..options.clone() });
+        let second = run(source, RunOptions { args: vec!["x".to_string()], ..options });
+        assert_eq!(first.output, second.output);
+        assert_eq!(first.chaos_events, second.chaos_events);
+        assert!(parse(source).is_ok());
+        assert_eq!(parse("let = 1; print(;").unwrap_err().len(), 2);
+    }
+}
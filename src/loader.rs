@@ -0,0 +1,175 @@
+//! # Loader Module
+//!
+//! Programs that don't fit in one file. A `use` statement whose first segment names a sibling
+//! `.upl` file pulls that file in as a module, right where the `use` was: `use math;` (or
+//! `use math::anything;`) next to `math.upl` runs as if `mod math { ... }` had been written there.
+//! Loaded files can `use` their own siblings. Each file is only loaded once, so a file that
+//! uses itself (or a friend that uses it back) doesn't loop forever.
+//!
+//! A `use` that doesn't match any file stays exactly as useless as it always was.
+//!
+//! Data can come along too: `use settings from "settings.json";` binds the JSON in a file to
+//! `settings` (an object, for a JSON object) when the statement runs. The loader points it at the
+//! file next to the program that wrote it, rather than wherever the program happens to be run from.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::{loader, Statement};
+//!
+//! let dir = std::env::temp_dir().join(format!("upl-loader-doc-{}", std::process::id()));
+//! std::fs::create_dir_all(&dir).unwrap();
+//! std::fs::write(dir.join("main.upl"), "use greetings; hello();").unwrap();
+//! std::fs::write(dir.join("greetings.upl"), "hello() { print(\"hi\"); }").unwrap();
+//!
+//! let program = loader::load(dir.join("main.upl")).unwrap();
+//! assert!(matches!(&program[0], Statement::Module { name, .. } if name == "greetings"));
+//! # std::fs::remove_dir_all(&dir).unwrap();
+//! ```
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::ast::{Program, Statement};
+use crate::lexer::Lexer;
+use crate::parser::{ParseError, Parser};
+
+/// The extension files pulled in by `use` must have.
+pub const SOURCE_EXTENSION: &str = "upl";
+
+/// Errors that stop a multi-file program from being put together.
+#[derive(Debug, Error)]
+pub enum LoadError {
+    #[error("Couldn't read {}. It was right there a second ago: {source}", .path.display())]
+    Io {
+        /// The file that wouldn't be read
+        path: PathBuf,
+        /// Why not
+        source: io::Error,
+    },
+
+    #[error("{} doesn't parse: {source}", .path.display())]
+    Parse {
+        /// The file with the syntax error
+        path: PathBuf,
+        /// The syntax error
+        source: ParseError,
+    },
+}
+
+/// Reads, parses and links a program from its entry point.
+pub fn load(entry: impl AsRef<Path>) -> Result<Program, LoadError> {
+    let entry = entry.as_ref();
+    let mut loaded = HashSet::from([canonical(entry)]);
+    let program = parse_file(entry)?;
+    link_with(program, directory_of(entry), &mut loaded)
+}
+
+/// Resolves the `use` statements of an already-parsed program against the files in `dir`.
+pub fn link(program: Program, dir: impl AsRef<Path>) -> Result<Program, LoadError> {
+    link_with(program, dir.as_ref(), &mut HashSet::new())
+}
+
+/// Replaces every `use` that names a file with that file's contents, as a module.
+/// Files in `loaded` have been pulled in already and are skipped.
+fn link_with(program: Program, dir: &Path, loaded: &mut HashSet<PathBuf>) -> Result<Program, LoadError> {
+    let mut linked = Vec::with_capacity(program.len());
+    for statement in program {
+        let Statement::Use { path } = statement.unlocated() else {
+            linked.push(resolve_data(statement, dir));
+            continue;
+        };
+        let name = path.split("::").next().unwrap_or_default();
+        let file = dir.join(format!("{}.{}", name, SOURCE_EXTENSION));
+        if !file.is_file() || !loaded.insert(canonical(&file)) {
+            linked.push(statement);
+            continue;
+        }
+
+        let body = link_with(parse_file(&file)?, directory_of(&file), loaded)?;
+        linked.push(Statement::Module { name: name.into(), body });
+    }
+    Ok(linked)
+}
+
+/// Points a `use name from "file.json";` at the file in `dir`. Anything else is left alone.
+fn resolve_data(statement: Statement, dir: &Path) -> Statement {
+    match statement {
+        Statement::Located { span, statement } => Statement::Located { span, statement: Box::new(resolve_data(*statement, dir)) },
+        Statement::UseData { name, path } => Statement::UseData { name, path: dir.join(path).to_string_lossy().into_owned() },
+        other => other,
+    }
+}
+
+/// Reads and parses a single file.
+fn parse_file(path: &Path) -> Result<Program, LoadError> {
+    let source = fs::read_to_string(path).map_err(|source| LoadError::Io { path: path.to_path_buf(), source })?;
+    Parser::new(Lexer::new(&source).collect())
+        .parse()
+        .map_err(|source| LoadError::Parse { path: path.to_path_buf(), source })
+}
+
+/// Returns the directory a file's siblings live in.
+pub fn directory_of(path: &Path) -> &Path {
+    path.parent().unwrap_or(Path::new(""))
+}
+
+/// Returns a path that's the same however the file was reached, for telling files apart.
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_files_are_loaded_once_and_unknown_uses_stay() {
+        let dir = std::env::temp_dir().join(format!("upl-loader-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("main.upl"), "use normal::mode; use a; use b; let x = 1;").unwrap();
+        fs::write(dir.join("a.upl"), "use b; use main; one() { 1; }").unwrap();
+        fs::write(dir.join("b.upl"), "two() { 2; }").unwrap();
+
+        let program = load(dir.join("main.upl")).unwrap();
+        assert_eq!(program.len(), 4);
+        assert!(matches!(&program[0], Statement::Use { path } if path == "normal::mode"));
+        let Statement::Module { name, body } = &program[1] else { panic!("expected a module, got {:?}", program[1]) };
+        assert_eq!(name, "a");
+        assert!(matches!(&body[0], Statement::Module { name, .. } if name == "b"));
+        assert!(matches!(&body[1], Statement::Use { path } if path == "main"), "main is already loaded");
+        assert!(matches!(&program[2], Statement::Use { path } if path == "b"), "b is already loaded");
+
+        fs::write(dir.join("b.upl"), "two( {").unwrap();
+        assert!(matches!(load(dir.join("main.upl")), Err(LoadError::Parse { path, .. }) if path.ends_with("b.upl")));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_data_is_found_next_to_the_program() {
+        use crate::config::Config;
+        use crate::interpreter::{Interpreter, RuntimeError, Value};
+
+        let dir = std::env::temp_dir().join(format!("upl-loader-data-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("main.upl"), "use settings from \"settings.json\";").unwrap();
+        fs::write(dir.join("settings.json"), r#"{"name": "chaos", "level": 11, "ratio": 0.5, "tags": [true, null]}"#).unwrap();
+
+        let program = load(dir.join("main.upl")).unwrap();
+        let mut interpreter = Interpreter::with_config(Config { chaos_level: 0, ..Config::default() });
+        interpreter.set_completely_normal(true);
+        interpreter.interpret(program).unwrap();
+        let Some(Value::Object { fields }) = interpreter.environment().get("settings") else { panic!("settings should be an object") };
+        assert_eq!(fields.keys().collect::<Vec<_>>(), ["name", "level", "ratio", "tags"]);
+        assert_eq!(fields["level"], Value::Number { value: 11 });
+        assert_eq!(fields["ratio"], Value::Float { value: 0.5 });
+
+        fs::write(dir.join("settings.json"), "{\"oops\":").unwrap();
+        let program = load(dir.join("main.upl")).unwrap();
+        assert!(matches!(interpreter.interpret(program), Err(RuntimeError::ReadError(path, _)) if path.ends_with("settings.json")));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
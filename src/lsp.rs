@@ -0,0 +1,383 @@
+//! # LSP Module
+//!
+//! A language server, so editors can point out what's wrong with a program before it even gets
+//! the chance to go wrong on its own. [`serve`] speaks the Language Server Protocol over any reader
+//! and writer (stdin and stdout, for `useless-lang lsp`): it reports syntax errors while the
+//! program is being typed, explains what each keyword will actually do when hovered over, and
+//! jumps from a variable to the `let` that declared it.
+//!
+//! Positions count lines and UTF-16 code units from 0, the way editors do.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::lsp::{self, Position};
+//!
+//! let source = "let x = 1;\nprint(x);";
+//! assert!(lsp::diagnostics(source).is_empty());
+//! assert!(lsp::hover(source, Position { line: 1, character: 0 }).unwrap().contains("website"));
+//!
+//! let declared = lsp::definition(source, Position { line: 1, character: 6 }).unwrap();
+//! assert_eq!(declared.start, Position { line: 0, character: 4 });
+//! ```
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use crate::json::{quote, Json};
+use crate::lexer::{Lexer, Token, TokenKind};
+use crate::parser::Parser;
+
+/// A place in a document, counted from 0 the way editors count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Position {
+    /// The line, counting from 0
+    pub line: usize,
+    /// How far into the line, in UTF-16 code units (so an emoji counts twice)
+    pub character: usize,
+}
+
+/// A stretch of a document, from `start` up to (but not including) `end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Range {
+    /// Where it starts
+    pub start: Position,
+    /// Where it stops
+    pub end: Position,
+}
+
+/// A syntax error, ready to be underlined.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// What to underline
+    pub range: Range,
+    /// The parse error's stable code
+    pub code: u16,
+    /// What went wrong
+    pub message: String,
+}
+
+impl Position {
+    /// Writes the position as an LSP `Position` object.
+    pub fn to_json(&self) -> String {
+        format!(r#"{{"line":{},"character":{}}}"#, self.line, self.character)
+    }
+
+    /// Finds where a byte offset into the source is.
+    fn of_offset(source: &str, offset: usize) -> Self {
+        let before = &source[..offset];
+        let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+        Self { line: before.matches('\n').count(), character: before[line_start..].encode_utf16().count() }
+    }
+
+    /// Finds the byte offset of the position in the source, if the source goes that far.
+    fn offset_in(&self, source: &str) -> Option<usize> {
+        let line_start = match self.line {
+            0 => 0,
+            line => source.match_indices('\n').nth(line - 1)?.0 + 1,
+        };
+        let mut units = 0;
+        for (offset, c) in source[line_start..].char_indices() {
+            if units >= self.character || c == '\n' {
+                return Some(line_start + offset);
+            }
+            units += c.len_utf16();
+        }
+        Some(source.len())
+    }
+}
+
+impl Range {
+    /// Writes the range as an LSP `Range` object.
+    pub fn to_json(&self) -> String {
+        format!(r#"{{"start":{},"end":{}}}"#, self.start.to_json(), self.end.to_json())
+    }
+
+    /// The range a token covers.
+    fn of_token(source: &str, token: &Token) -> Self {
+        Self { start: Position::of_offset(source, token.span.start), end: Position::of_offset(source, token.span.end) }
+    }
+}
+
+impl Diagnostic {
+    /// Writes the diagnostic as an LSP `Diagnostic` object (always an error, since that's all the parser has).
+    pub fn to_json(&self) -> String {
+        format!(
+            r#"{{"range":{},"severity":1,"code":"E{:04}","source":"useless-lang","message":{}}}"#,
+            self.range.to_json(),
+            self.code,
+            quote(&self.message)
+        )
+    }
+}
+
+/// Finds every syntax error in a program. Errors that don't know where they are go at the very start.
+pub fn diagnostics(source: &str) -> Vec<Diagnostic> {
+    let (_, errors) = Parser::new(Lexer::new(source).collect()).parse_with_recovery();
+    errors
+        .iter()
+        .map(|error| Diagnostic {
+            range: error.span().map_or_else(Range::default, |span| Range {
+                start: Position::of_offset(source, span.start),
+                end: Position::of_offset(source, span.end),
+            }),
+            code: error.code(),
+            message: error.to_string(),
+        })
+        .collect()
+}
+
+/// Explains what the keyword at a position really does, in Markdown.
+pub fn hover(source: &str, position: Position) -> Option<String> {
+    let token = token_at(source, position)?;
+    let meaning = match token.kind {
+        TokenKind::Print => "Opens a random website instead of printing, and mangles whatever does get printed",
+        TokenKind::Let => "Declares a variable, unless the variable goes on vacation instead (20%)",
+        TokenKind::If => "Takes the else branch. Sometimes it breaks creatively instead (15%)",
+        TokenKind::Else => "Where every `if` ends up anyway",
+        TokenKind::Loop => "Runs its body exactly once, unless it fails successfully (25%)",
+        TokenKind::Save => "Always fails. Saving is overrated",
+        TokenKind::Add => "Subtracts, or multiplies and then adds for good measure",
+        TokenKind::Multiply => "Divides, when multiplication isn't on vacation (50%)",
+        TokenKind::Exit => "Ponders the meaning of exiting instead. Only `disable_all_useless_shit` lets it leave",
+        TokenKind::Promise => "Resolves after 100 to 2000ms, or is rejected because Mercury is in retrograde (40%)",
+        TokenKind::Await => "Waits for a promise, which may change its mind (20%). Top-level awaits go fishing (40%)",
+        TokenKind::Async => "Declares a function that may go fishing instead of running (30%)",
+        TokenKind::Try => "Runs a block and catches its errors, possibly as the wrong error (40%)",
+        TokenKind::Catch => "Catches an error, though not necessarily the one that happened",
+        TokenKind::Pray => "Retries failing statements with growing delays, while the gods lose interest",
+        TokenKind::Concurrent => "Runs every async call made inside it that nobody awaited before it ends, and reports their errors together",
+        TokenKind::For | TokenKind::In => "Loops over a collection, possibly stopping one item early or visiting keys in any order",
+        TokenKind::Sudo => "Runs one statement with no chaos at all. Once per program",
+        TokenKind::Macro => "Homemade syntax sugar, expanded before anything runs",
+        TokenKind::Attribute | TokenKind::Directive => {
+            "Changes how the language behaves: `disable_all_useless_shit`, `disable_useless`, `murphy`, `lucky`, `print_to(\"file\")` or `frozen_clock(\"2024-01-01T00:00:00Z\")`"
+        },
+        TokenKind::Index => "Returns a random element instead of the one asked for (30%), if the array isn't on vacation (40%)",
+        TokenKind::Access => "Returns a field, unless the object has a meltdown (30%)",
+        TokenKind::Equals => "Flips a coin",
+        TokenKind::LessThan => "Checks whether the left side is greater",
+        TokenKind::True | TokenKind::False => "A boolean, which may turn into the other one, a string or a number",
+        TokenKind::Null => "Might not be null",
+        TokenKind::NumberLiteral => "A number, half the time. The other half it's a boolean",
+        TokenKind::FloatLiteral => "A number with a point. Close enough to the one you wrote, usually",
+        TokenKind::StringLiteral => "A string, which turns into something else entirely",
+        TokenKind::NullCoalesce => "A backup plan, ignored out of spite (30%)",
+        TokenKind::Module | TokenKind::Use => "Modules, which misbehave like everything else",
+        _ => return None,
+    };
+    Some(format!("**{}**: {}", token.text, meaning))
+}
+
+/// Finds the `let` that declared the variable at a position: the closest one before it, or the
+/// first one if it's used before it's declared.
+pub fn definition(source: &str, position: Position) -> Option<Range> {
+    let used = token_at(source, position).filter(|token| token.kind == TokenKind::Identifier)?;
+    let tokens: Vec<Token> = Lexer::new(source).collect();
+    let bindings: Vec<&Token> = tokens
+        .windows(2)
+        .filter(|pair| pair[0].kind == TokenKind::Let && pair[1].kind == TokenKind::Identifier && pair[1].text == used.text)
+        .map(|pair| &pair[1])
+        .collect();
+    let binding = bindings.iter().rev().find(|binding| binding.span.start <= used.span.start).or(bindings.first())?;
+    Some(Range::of_token(source, binding))
+}
+
+/// Finds the token at a position, or the one just before it if the cursor sits right after a word.
+fn token_at(source: &str, position: Position) -> Option<Token> {
+    let offset = position.offset_in(source)?;
+    let mut touching = Lexer::new(source).filter(|token| token.span.start <= offset && offset <= token.span.end);
+    let first = touching.next()?;
+    match touching.next() {
+        Some(second) if first.span.end == offset => Some(second),
+        _ => Some(first),
+    }
+}
+
+/// What the server can do, as sent back from `initialize`. Documents are always sent whole.
+const CAPABILITIES: &str =
+    r#"{"capabilities":{"textDocumentSync":1,"hoverProvider":true,"definitionProvider":true},"serverInfo":{"name":"useless-lang"}}"#;
+
+/// The JSON-RPC error for a method nobody implemented.
+const METHOD_NOT_FOUND: i32 = -32601;
+
+/// The JSON-RPC error for a message that wasn't JSON.
+const PARSE_ERROR: i32 = -32700;
+
+/// The open documents, and what to say about them.
+#[derive(Default)]
+struct Server {
+    documents: HashMap<String, String>,
+}
+
+impl Server {
+    /// Handles one message, returning every message to send back.
+    fn handle(&mut self, message: &Json) -> Vec<String> {
+        let method = message.get("method").and_then(Json::as_str).unwrap_or_default();
+        let params = message.get("params").unwrap_or(&Json::Null);
+        let document = params.get("textDocument");
+        let uri = document.and_then(|document| document.get("uri")).and_then(Json::as_str).unwrap_or_default().to_string();
+        let position = params.get("position").and_then(|position| {
+            Some(Position { line: position.get("line")?.as_usize()?, character: position.get("character")?.as_usize()? })
+        });
+
+        match (method, message.get("id")) {
+            ("initialize", Some(id)) => vec![response(id, CAPABILITIES)],
+            ("shutdown", Some(id)) => vec![response(id, "null")],
+            ("textDocument/didOpen", _) => {
+                let text = document.and_then(|document| document.get("text")).and_then(Json::as_str).unwrap_or_default();
+                self.documents.insert(uri.clone(), text.to_string());
+                vec![self.publish_diagnostics(&uri)]
+            },
+            ("textDocument/didChange", _) => {
+                let changes = match params.get("contentChanges") {
+                    Some(Json::Array(changes)) => changes.as_slice(),
+                    _ => &[],
+                };
+                match changes.last().and_then(|change| change.get("text")).and_then(Json::as_str) {
+                    Some(text) => {
+                        self.documents.insert(uri.clone(), text.to_string());
+                        vec![self.publish_diagnostics(&uri)]
+                    },
+                    None => Vec::new(),
+                }
+            },
+            ("textDocument/didClose", _) => {
+                self.documents.remove(&uri);
+                vec![self.publish_diagnostics(&uri)]
+            },
+            ("textDocument/hover", Some(id)) => {
+                let text = self.documents.get(&uri).zip(position).and_then(|(source, position)| hover(source, position));
+                let result = text.map_or_else(|| "null".to_string(), |text| {
+                    format!(r#"{{"contents":{{"kind":"markdown","value":{}}}}}"#, quote(&text))
+                });
+                vec![response(id, &result)]
+            },
+            ("textDocument/definition", Some(id)) => {
+                let range = self.documents.get(&uri).zip(position).and_then(|(source, position)| definition(source, position));
+                let result = range.map_or_else(|| "null".to_string(), |range| {
+                    format!(r#"{{"uri":{},"range":{}}}"#, quote(&uri), range.to_json())
+                });
+                vec![response(id, &result)]
+            },
+            (method, Some(id)) => vec![error_response(id, METHOD_NOT_FOUND, &format!("{} is too useful for this language", method))],
+            // Notifications nobody needs to answer, like `initialized`
+            (_, None) => Vec::new(),
+        }
+    }
+
+    /// Tells the editor about every syntax error in a document (none, once it's closed).
+    fn publish_diagnostics(&self, uri: &str) -> String {
+        let diagnostics: Vec<String> =
+            self.documents.get(uri).map(|source| diagnostics(source)).unwrap_or_default().iter().map(Diagnostic::to_json).collect();
+        format!(
+            r#"{{"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{{"uri":{},"diagnostics":[{}]}}}}"#,
+            quote(uri),
+            diagnostics.join(",")
+        )
+    }
+}
+
+/// A successful reply to a request.
+fn response(id: &Json, result: &str) -> String {
+    format!(r#"{{"jsonrpc":"2.0","id":{},"result":{}}}"#, id, result)
+}
+
+/// A failed reply to a request.
+fn error_response(id: &Json, code: i32, message: &str) -> String {
+    format!(r#"{{"jsonrpc":"2.0","id":{},"error":{{"code":{},"message":{}}}}}"#, id, code, quote(message))
+}
+
+/// Reads the next message's body, or `None` once the editor hangs up.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        match line.trim_end() {
+            "" if length.is_some() => break,
+            "" => continue,
+            header => {
+                if let Some(value) = header.strip_prefix("Content-Length:") {
+                    length = value.trim().parse::<usize>().ok();
+                }
+            },
+        }
+    }
+
+    let mut body = vec![0; length.unwrap_or_default()];
+    input.read_exact(&mut body)?;
+    String::from_utf8(body).map(Some).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Sends a message, headers and all.
+fn write_message(output: &mut impl Write, body: &str) -> io::Result<()> {
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+/// Serves one editor until it says `exit` or hangs up.
+pub fn serve(mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    let mut server = Server::default();
+    while let Some(body) = read_message(&mut input)? {
+        let Some(message) = Json::parse(&body) else {
+            write_message(&mut output, &error_response(&Json::Null, PARSE_ERROR, "That wasn't JSON, and we'd know"))?;
+            continue;
+        };
+        if message.get("method").and_then(Json::as_str) == Some("exit") {
+            break;
+        }
+        for reply in server.handle(&message) {
+            write_message(&mut output, &reply)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Frames messages the way an editor would.
+    fn framed(messages: &[&str]) -> Vec<u8> {
+        messages.iter().flat_map(|message| format!("Content-Length: {}\r\n\r\n{}", message.len(), message).into_bytes()).collect()
+    }
+
+    #[test]
+    fn test_a_whole_editing_session() {
+        let input = framed(&[
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#,
+            r#"{"jsonrpc":"2.0","method":"initialized","params":{}}"#,
+            r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"file:///a.upl","text":"let 🦄 = ;"}}}"#,
+            r#"{"jsonrpc":"2.0","method":"textDocument/didChange","params":{"textDocument":{"uri":"file:///a.upl"},"contentChanges":[{"text":"let x = 1;\nprint(x);"}]}}"#,
+            r#"{"jsonrpc":"2.0","id":2,"method":"textDocument/definition","params":{"textDocument":{"uri":"file:///a.upl"},"position":{"line":1,"character":6}}}"#,
+            r#"{"jsonrpc":"2.0","id":3,"method":"textDocument/rename","params":{}}"#,
+            r#"{"jsonrpc":"2.0","id":4,"method":"shutdown"}"#,
+            r#"{"jsonrpc":"2.0","method":"exit"}"#,
+            r#"{"jsonrpc":"2.0","id":5,"method":"initialize","params":{}}"#,
+        ]);
+        let mut output = Vec::new();
+        serve(input.as_slice(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains(r#""id":1,"result":{"capabilities""#), "{}", output);
+        assert!(output.contains(r#""diagnostics":[{"range":{"start":{"line":0,"character":7}"#), "the unicorn is two units wide: {}", output);
+        assert!(output.contains(r#""diagnostics":[]"#), "the fixed document has no errors: {}", output);
+        assert!(output.contains(r#""id":2,"result":{"uri":"file:///a.upl","range":{"start":{"line":0,"character":4},"end":{"line":0,"character":5}}}"#), "{}", output);
+        assert!(output.contains(r#""id":3,"error":{"code":-32601"#), "{}", output);
+        assert!(output.contains(r#""id":4,"result":null"#), "{}", output);
+        assert!(!output.contains(r#""id":5"#), "nothing gets answered after exit: {}", output);
+    }
+
+    #[test]
+    fn test_positions_count_utf16_like_editors() {
+        let source = "let 🦄 = \"🦄\";\nlet y = 2;";
+        assert_eq!(Position::of_offset(source, source.find('=').unwrap()), Position { line: 0, character: 7 });
+        assert_eq!(Position { line: 0, character: 7 }.offset_in(source), source.find('='));
+        assert_eq!(Position { line: 1, character: 4 }.offset_in(source), source.find('y'));
+        assert_eq!(Position { line: 5, character: 0 }.offset_in(source), None);
+        assert_eq!(hover(source, Position { line: 1, character: 1 }).unwrap(), "**let**: Declares a variable, unless the variable goes on vacation instead (20%)");
+        assert_eq!(hover(source, Position { line: 1, character: 4 }), None, "names don't have hidden meanings");
+    }
+}
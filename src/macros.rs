@@ -0,0 +1,410 @@
+//! # Macros Module
+//!
+//! Macros let users invent their own cursed syntax sugar.
+//! `macro name(args) { ... }` defines a template, and every `name(...)` call is replaced by the template
+//! with the arguments pasted in, before the interpreter ever sees it.
+//!
+//! Variables a template declares with `let` or `for` are renamed on expansion,
+//! so they can't accidentally capture (or clobber) the caller's variables.
+//! Macros are defined at the top level of a program, and can be used anywhere in it.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::{Lexer, Parser};
+//! use useless_lang::macros;
+//!
+//! let source = "macro twice(x) { print(x); print(x); } twice(\"hi\");";
+//! let program = Parser::new(Lexer::new(source).collect()).parse().unwrap();
+//! let expanded = macros::expand(program).unwrap();
+//! assert_eq!(expanded.len(), 2);
+//! ```
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::ast::{Expression, Literal, Program, Statement};
+use crate::intern::Symbol;
+
+/// How deep macros may expand into other macros before we assume they're recursing forever.
+pub const MAX_EXPANSION_DEPTH: usize = 64;
+
+/// Errors that can happen while expanding macros.
+#[derive(Debug, Error)]
+pub enum MacroError {
+    #[error("macro {name} takes {expected} arguments, but was given {given}. Close, but no cigar.")]
+    ArgumentCount {
+        name: String,
+        expected: usize,
+        given: usize,
+    },
+
+    #[error("macro {0} expanded more than {MAX_EXPANSION_DEPTH} levels deep. It's macros all the way down.")]
+    TooDeep(String),
+
+    #[error("macro {0} was used as a value, but its template isn't a single expression")]
+    NotAnExpression(String),
+
+    #[error("macro {0} expands to several statements where only one fits")]
+    NotOneStatement(String),
+}
+
+/// A macro definition: parameters and the template they get pasted into.
+#[derive(Debug, Clone)]
+struct MacroDefinition {
+    parameters: Vec<Symbol>,
+    body: Vec<Statement>,
+}
+
+/// Expands every macro call in a program, and removes the macro definitions.
+pub fn expand(program: Program) -> Result<Program, MacroError> {
+    let mut macros = HashMap::new();
+    let mut statements = Vec::with_capacity(program.len());
+    for statement in program {
+        // Definitions disappear, so there's no point remembering where they were
+        let statement = match statement {
+            Statement::Located { statement, .. } if matches!(*statement, Statement::Macro { .. }) => *statement,
+            other => other,
+        };
+        match statement {
+            Statement::Macro { name, parameters, body } => {
+                macros.insert(name, MacroDefinition { parameters, body });
+            },
+            other => statements.push(other),
+        }
+    }
+
+    if macros.is_empty() {
+        return Ok(statements);
+    }
+    Expander { macros, depth: 0, expansions: 0 }.fold_block(statements)
+}
+
+/// Something that rewrites a program, one piece at a time.
+/// Everything it doesn't override is walked through unchanged.
+trait Folder {
+    fn fold_block(&mut self, block: Vec<Statement>) -> Result<Vec<Statement>, MacroError> {
+        block.into_iter().map(|statement| self.fold_statement(statement)).collect()
+    }
+
+    fn fold_statement(&mut self, statement: Statement) -> Result<Statement, MacroError> {
+        walk_statement(self, statement)
+    }
+
+    fn fold_expression(&mut self, expression: Expression) -> Result<Expression, MacroError> {
+        walk_expression(self, expression)
+    }
+
+    /// Called for names a statement declares, like `let` variables
+    fn fold_binding(&mut self, name: Symbol) -> Symbol {
+        name
+    }
+}
+
+/// Folds every part of a statement.
+fn walk_statement<F: Folder + ?Sized>(folder: &mut F, statement: Statement) -> Result<Statement, MacroError> {
+    Ok(match statement {
+        Statement::Print { value } => Statement::Print { value: folder.fold_expression(value)? },
+        Statement::Let { name, value } => Statement::Let {
+            name: folder.fold_binding(name),
+            value: folder.fold_expression(value)?,
+        },
+        Statement::Expression(expression) => Statement::Expression(folder.fold_expression(expression)?),
+        Statement::If { condition, then_branch, else_branch } => Statement::If {
+            condition: folder.fold_expression(condition)?,
+            then_branch: folder.fold_block(then_branch)?,
+            else_branch: else_branch.map(|block| folder.fold_block(block)).transpose()?,
+        },
+        Statement::Loop { body } => Statement::Loop { body: folder.fold_block(body)? },
+        Statement::ForIn { variable, iterable, body } => Statement::ForIn {
+            variable: folder.fold_binding(variable),
+            iterable: folder.fold_expression(iterable)?,
+            body: folder.fold_block(body)?,
+        },
+        Statement::Function { name, parameters, body } => Statement::Function {
+            name,
+            parameters,
+            body: folder.fold_block(body)?,
+        },
+        Statement::AsyncFunction { name, parameters, body } => Statement::AsyncFunction {
+            name,
+            parameters,
+            body: folder.fold_block(body)?,
+        },
+        Statement::TryCatch { try_block, error_binding, catch_block } => Statement::TryCatch {
+            try_block: folder.fold_block(try_block)?,
+            error_binding,
+            catch_block: folder.fold_block(catch_block)?,
+        },
+        Statement::Pray { attempts, body } => Statement::Pray { attempts, body: folder.fold_block(body)? },
+        Statement::Concurrent { body } => Statement::Concurrent { body: folder.fold_block(body)? },
+        Statement::Sudo { statement } => Statement::Sudo { statement: Box::new(folder.fold_statement(*statement)?) },
+        Statement::Module { name, body } => Statement::Module { name, body: folder.fold_block(body)? },
+        Statement::Await { expression } => Statement::Await { expression: folder.fold_expression(expression)? },
+        Statement::Attributed { name, statement } => Statement::Attributed {
+            name,
+            statement: Box::new(folder.fold_statement(*statement)?),
+        },
+        Statement::Located { span, statement } => Statement::Located {
+            span,
+            statement: Box::new(folder.fold_statement(*statement)?),
+        },
+        other @ (Statement::Use { .. }
+        | Statement::UseData { .. }
+        | Statement::Directive { .. }
+        | Statement::Save { .. }
+        | Statement::Macro { .. }) => other,
+    })
+}
+
+/// Folds every part of an expression.
+fn walk_expression<F: Folder + ?Sized>(folder: &mut F, expression: Expression) -> Result<Expression, MacroError> {
+    let fold_box = |folder: &mut F, expression: Box<Expression>| -> Result<Box<Expression>, MacroError> {
+        Ok(Box::new(folder.fold_expression(*expression)?))
+    };
+    Ok(match expression {
+        Expression::Literal(Literal::Array(elements)) => Expression::Literal(Literal::Array(
+            elements.into_iter().map(|element| fold_box(folder, element)).collect::<Result<_, _>>()?,
+        )),
+        Expression::Literal(Literal::Object(pairs)) => Expression::Literal(Literal::Object(
+            pairs
+                .into_iter()
+                .map(|(key, value)| Ok((key, fold_box(folder, value)?)))
+                .collect::<Result<_, MacroError>>()?,
+        )),
+        Expression::BinaryOp { op, left, right } => Expression::BinaryOp {
+            op,
+            left: fold_box(folder, left)?,
+            right: fold_box(folder, right)?,
+        },
+        Expression::FunctionCall { name, arguments } => Expression::FunctionCall {
+            name,
+            arguments: arguments
+                .into_iter()
+                .map(|argument| folder.fold_expression(argument))
+                .collect::<Result<_, _>>()?,
+        },
+        Expression::Access { object, key } => Expression::Access {
+            object: fold_box(folder, object)?,
+            key: fold_box(folder, key)?,
+        },
+        Expression::OptionalAccess { object, key } => Expression::OptionalAccess {
+            object: fold_box(folder, object)?,
+            key: fold_box(folder, key)?,
+        },
+        Expression::Slice { object, start, end } => Expression::Slice {
+            object: fold_box(folder, object)?,
+            start: start.map(|start| fold_box(folder, start)).transpose()?,
+            end: end.map(|end| fold_box(folder, end)).transpose()?,
+        },
+        Expression::Promise { value, timeout } => Expression::Promise {
+            value: fold_box(folder, value)?,
+            timeout: timeout.map(|timeout| fold_box(folder, timeout)).transpose()?,
+        },
+        Expression::Await { promise } => Expression::Await { promise: fold_box(folder, promise)? },
+        other @ (Expression::Literal(_) | Expression::Identifier(_)) => other,
+    })
+}
+
+/// Replaces macro calls with their expanded templates.
+struct Expander {
+    macros: HashMap<Symbol, MacroDefinition>,
+    depth: usize,
+    expansions: usize,
+}
+
+impl Expander {
+    /// Pastes the arguments into a macro's template, then expands any macros the result uses.
+    fn expand_call(&mut self, name: Symbol, arguments: Vec<Expression>) -> Result<Vec<Statement>, MacroError> {
+        let definition = self.macros[&name].clone();
+        if arguments.len() != definition.parameters.len() {
+            return Err(MacroError::ArgumentCount {
+                name: name.to_string(),
+                expected: definition.parameters.len(),
+                given: arguments.len(),
+            });
+        }
+        if self.depth >= MAX_EXPANSION_DEPTH {
+            return Err(MacroError::TooDeep(name.to_string()));
+        }
+
+        self.expansions += 1;
+        let mut substituter = Substituter {
+            arguments: definition.parameters.into_iter().zip(arguments).collect(),
+            renames: HashMap::new(),
+            suffix: format!("#{}", self.expansions),
+        };
+        substituter.collect_bindings(&definition.body);
+        let body = substituter.fold_block(definition.body)?;
+
+        self.depth += 1;
+        let expanded = self.fold_block(body);
+        self.depth -= 1;
+        expanded
+    }
+
+    fn is_macro_call(&self, expression: &Expression) -> bool {
+        matches!(expression, Expression::FunctionCall { name, .. } if self.macros.contains_key(name))
+    }
+}
+
+impl Folder for Expander {
+    fn fold_block(&mut self, block: Vec<Statement>) -> Result<Vec<Statement>, MacroError> {
+        let mut folded = Vec::with_capacity(block.len());
+        for statement in block {
+            match statement {
+                Statement::Expression(Expression::FunctionCall { name, arguments }) if self.macros.contains_key(&name) => {
+                    folded.extend(self.expand_call(name, arguments)?);
+                },
+                other => folded.push(self.fold_statement(other)?),
+            }
+        }
+        Ok(folded)
+    }
+
+    fn fold_statement(&mut self, statement: Statement) -> Result<Statement, MacroError> {
+        match statement {
+            Statement::Expression(expression) if self.is_macro_call(&expression) => {
+                let Expression::FunctionCall { name, arguments } = expression else {
+                    unreachable!("checked by is_macro_call");
+                };
+                let mut expanded = self.expand_call(name.clone(), arguments)?;
+                match expanded.len() {
+                    1 => Ok(expanded.remove(0)),
+                    _ => Err(MacroError::NotOneStatement(name.to_string())),
+                }
+            },
+            other => walk_statement(self, other),
+        }
+    }
+
+    fn fold_expression(&mut self, expression: Expression) -> Result<Expression, MacroError> {
+        match expression {
+            Expression::FunctionCall { name, arguments } if self.macros.contains_key(&name) => {
+                let mut expanded = self.expand_call(name.clone(), arguments)?;
+                match (expanded.len(), expanded.pop()) {
+                    (1, Some(Statement::Expression(expression))) => Ok(expression),
+                    _ => Err(MacroError::NotAnExpression(name.to_string())),
+                }
+            },
+            other => walk_expression(self, other),
+        }
+    }
+}
+
+/// Pastes arguments into a template, renaming the variables it declares.
+struct Substituter {
+    arguments: HashMap<Symbol, Expression>,
+    renames: HashMap<Symbol, Symbol>,
+    suffix: String,
+}
+
+impl Substituter {
+    /// Finds every variable the template declares, so it can be renamed out of the caller's way.
+    fn collect_bindings(&mut self, block: &[Statement]) {
+        for statement in block {
+            let (declared, nested): (Option<Symbol>, Vec<&[Statement]>) = match statement {
+                Statement::Let { name, .. } => (Some(name.clone()), vec![]),
+                Statement::ForIn { variable, body, .. } => (Some(variable.clone()), vec![body]),
+                Statement::If { then_branch, else_branch, .. } => {
+                    let mut nested = vec![then_branch.as_slice()];
+                    nested.extend(else_branch.as_deref());
+                    (None, nested)
+                },
+                Statement::Loop { body } | Statement::Pray { body, .. } | Statement::Concurrent { body } => (None, vec![body]),
+                Statement::TryCatch { try_block, catch_block, .. } => (None, vec![try_block, catch_block]),
+                _ => (None, vec![]),
+            };
+            if let Some(name) = declared {
+                if !self.arguments.contains_key(&name) {
+                    let renamed = Symbol::intern(&format!("{}{}", name, self.suffix));
+                    self.renames.insert(name, renamed);
+                }
+            }
+            for block in nested {
+                self.collect_bindings(block);
+            }
+        }
+    }
+}
+
+impl Folder for Substituter {
+    fn fold_expression(&mut self, expression: Expression) -> Result<Expression, MacroError> {
+        match expression {
+            Expression::Identifier(name) => Ok(match (self.arguments.get(&name), self.renames.get(&name)) {
+                (Some(argument), _) => argument.clone(),
+                (None, Some(renamed)) => Expression::Identifier(renamed.clone()),
+                (None, None) => Expression::Identifier(name),
+            }),
+            other => walk_expression(self, other),
+        }
+    }
+
+    fn fold_binding(&mut self, name: Symbol) -> Symbol {
+        self.renames.get(&name).cloned().unwrap_or(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+    use std::collections::HashSet;
+
+    /// Names that are declared anywhere in a program, handy for checking hygiene.
+    fn declared_names(block: &[Statement]) -> HashSet<String> {
+        let mut substituter = Substituter { arguments: HashMap::new(), renames: HashMap::new(), suffix: String::new() };
+        substituter.collect_bindings(block);
+        substituter.renames.into_keys().map(String::from).collect()
+    }
+
+    fn parse(source: &str) -> Program {
+        Parser::new(Lexer::new(source).collect()).parse().unwrap()
+    }
+
+    #[test]
+    fn test_expands_statement_macros() {
+        let program = expand(parse("macro shout(x) { print(x); print(x); } shout(\"hey\");")).unwrap();
+        let hey = Statement::Print { value: Expression::Literal(Literal::String("hey".to_string())) };
+        assert_eq!(program, vec![hey.clone(), hey]);
+    }
+
+    #[test]
+    fn test_expands_expression_macros_and_nested_macros() {
+        let program = expand(parse("macro id(x) { x; } macro twice(x) { id(x); id(x); } let y = id(5); twice(y);")).unwrap();
+        assert_eq!(
+            program,
+            vec![
+                Statement::Let { name: "y".into(), value: Expression::Literal(Literal::Number(5)) },
+                Statement::Expression(Expression::Identifier("y".into())),
+                Statement::Expression(Expression::Identifier("y".into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_template_variables_dont_capture() {
+        let program = expand(parse("macro set(v) { let tmp = v; print(tmp); } let tmp = 1; set(tmp);")).unwrap();
+        let names = declared_names(&program);
+        assert!(names.contains("tmp"));
+        assert!(names.contains("tmp#1"), "The macro's tmp should be renamed, got {:?}", names);
+        assert_eq!(
+            program[1],
+            Statement::Let { name: "tmp#1".into(), value: Expression::Identifier("tmp".into()) }
+        );
+    }
+
+    #[test]
+    fn test_expansion_errors() {
+        assert!(matches!(
+            expand(parse("macro one(x) { print(x); } one(1, 2);")),
+            Err(MacroError::ArgumentCount { expected: 1, given: 2, .. })
+        ));
+        assert!(matches!(expand(parse("macro forever() { forever(); } forever();")), Err(MacroError::TooDeep(_))));
+        assert!(matches!(
+            expand(parse("macro two() { print(1); print(2); } let x = two();")),
+            Err(MacroError::NotAnExpression(_))
+        ));
+    }
+}
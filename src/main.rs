@@ -1,51 +1,1388 @@
+use std::collections::BTreeSet;
 use std::env;
 use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::path::{Path, PathBuf};
 use std::process;
+use std::thread;
 
-mod ast;
-mod interpreter;
-mod lexer;
-mod parser;
+use useless_lang::ast;
+use useless_lang::bench;
+use useless_lang::cancel::CancelHandle;
+use useless_lang::chaos::ChaosEvent;
+use useless_lang::config::{CONFIG_FILE, MAX_CHAOS_LEVEL};
+use useless_lang::cli::{self, Shell};
+use useless_lang::codegen;
+use useless_lang::debug::{DebugSession, Debugger, Resume, Stop};
+use useless_lang::doc::{self, DocFormat};
+use useless_lang::explain;
+use useless_lang::fmt;
+use useless_lang::lexer;
+#[cfg(feature = "uplb")]
+use useless_lang::uplb;
+use useless_lang::loader::{self, LoadError};
+use useless_lang::repl::{self, Edited, History, LastInput};
+use useless_lang::scaffold::{self, Template};
+use useless_lang::session::Session;
+use useless_lang::snapshot::{self, SnapshotOutcome};
+use useless_lang::testing::{self, TestOutcome};
+use useless_lang::trace::{TracedStatement, Tracer};
+use useless_lang::watch::{self, FileWatcher};
+use useless_lang::{Config, ErrorReport, Interpreter, Lexer, Parser, Program, RuntimeError, Statement, Token, Value};
 
-use interpreter::Interpreter;
-use lexer::Lexer;
-use parser::Parser;
+/// How `--emit-ast` writes the tree.
+#[derive(Clone, Copy)]
+enum AstFormat {
+    /// Rust's debug format, for humans with a lot of scrolling to do
+    Debug,
+    /// One line of JSON, for tools
+    Json,
+}
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
+/// How errors get written to stderr.
+#[derive(Clone, Copy, PartialEq)]
+enum DiagnosticFormat {
+    /// Messages, hints and jokes, for people
+    Human,
+    /// One JSON object per line, for editors and CI, which don't laugh anyway
+    Json,
+}
 
-    let file_path = if args.len() > 1 {
-        &args[1]
-    } else {
-        eprintln!("Usage: useless-lang <file.upl>");
-        eprintln!("Example: useless-lang examples/hello.upl");
-        process::exit(1);
-    };
+/// Everything the command line asked for, apart from which program to run.
+#[derive(Clone)]
+struct Options {
+    /// The seed to run with (a random one if not given)
+    seed: Option<u64>,
+    /// Whether to dump the tokens before running
+    emit_tokens: bool,
+    /// Whether (and how) to dump the syntax tree before running
+    emit_ast: Option<AstFormat>,
+    /// How errors are reported
+    format: DiagnosticFormat,
+    /// The config, with the command line's overrides applied
+    config: Config,
+    /// Shared libraries to load plugins from, in order
+    #[cfg(feature = "dynamic-plugins")]
+    plugins: Vec<std::path::PathBuf>,
+    /// The breakpoints to start with, when running under the debugger
+    debug: Option<Vec<usize>>,
+    /// Everything after the file path, for `args()`
+    args: Vec<String>,
+    /// Whether to sum up the chaos once the program is done
+    chaos_report: bool,
+    /// Whether to switch all chaos off, like `disable_all_useless_shit`
+    normal: bool,
+    /// Whether to narrate every statement as it runs
+    trace: bool,
+    /// How to report where the time went once the program is done, if at all
+    profile: Option<DiagnosticFormat>,
+}
 
-    let source_code = match fs::read_to_string(file_path) {
-        Ok(content) => content,
+/// The most cyclomatic chaos `lint` lets a function get away with.
+const LINT_MAX_CYCLOMATIC_CHAOS: usize = 15;
+
+/// The deepest `lint` lets a function's blocks be nested.
+const LINT_MAX_DEPTH: usize = 4;
+
+/// The most statements `lint` lets a function have.
+const LINT_MAX_STATEMENTS: usize = 50;
+
+/// Moves the cursor home and wipes the terminal, so every watched run starts on a clean screen.
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+/// The file name that means "read the program from stdin".
+const STDIN_PATH: &str = "-";
+
+/// Where `useless-lang doc` puts the documentation, unless told otherwise.
+const DEFAULT_DOC_DIR: &str = "doc";
+
+/// The language `transpile` writes in when nobody says otherwise.
+const DEFAULT_TRANSPILE_TARGET: &str = "js";
+
+/// The status for a program that ran without an error.
+const SUCCESS_STATUS: i32 = 0;
+
+/// The status for a program that failed at runtime.
+const RUNTIME_ERROR_STATUS: i32 = 1;
+
+/// The status for a program that didn't even parse.
+const PARSE_ERROR_STATUS: i32 = 2;
+
+/// The status for a command line that made no sense, or a file that couldn't be read (EX_USAGE).
+const USAGE_STATUS: i32 = 64;
+
+/// The status for a run that outlasted `--timeout`, the same one `timeout(1)` uses.
+const TIMEOUT_STATUS: i32 = 124;
+
+/// The status for a run stopped by Ctrl-C, as is tradition (128 + SIGINT).
+const INTERRUPTED_STATUS: i32 = 130;
+
+/// The status for a program that turned out to be a teapot: 418 without the 4. Shells only keep
+/// the low byte, so 418 itself would have reached scripts as 162, which means nothing to anyone.
+const TEAPOT_STATUS: i32 = 18;
+
+/// Picks the exit status for a program that failed at runtime.
+fn runtime_error_status(error: &RuntimeError) -> i32 {
+    match error {
+        RuntimeError::Teapot => TEAPOT_STATUS,
+        RuntimeError::Exited(status) => *status,
+        RuntimeError::CancelledByHuman => INTERRUPTED_STATUS,
+        RuntimeError::OutOfTime(_) => TIMEOUT_STATUS,
+        _ => RUNTIME_ERROR_STATUS,
+    }
+}
+
+/// Turns Ctrl-C into a cancellation, so the program stops at the next statement and reports how far it got.
+/// A second Ctrl-C means it, and ends the process on the spot.
+fn cancel_on_ctrl_c(handle: CancelHandle) {
+    thread::spawn(move || {
+        let Ok(runtime) = tokio::runtime::Builder::new_current_thread().enable_all().build() else {
+            return;
+        };
+        runtime.block_on(async {
+            while tokio::signal::ctrl_c().await.is_ok() {
+                if handle.is_cancelled() {
+                    eprintln!("\n💥 Fine, stopping right now.");
+                    process::exit(INTERRUPTED_STATUS);
+                }
+                eprintln!("\n✋ Ctrl-C received. Stopping after this statement (press it again to stop right now)...");
+                handle.cancel();
+            }
+        });
+    });
+}
+
+/// Loads the config file in the current directory, if there is one. A broken one ends the process.
+fn load_config() -> Config {
+    if !Path::new(CONFIG_FILE).exists() {
+        return Config::default();
+    }
+    match Config::load(CONFIG_FILE) {
+        Ok(config) => config,
         Err(e) => {
-            eprintln!("Error reading file {}: {}", file_path, e);
-            process::exit(1);
+            eprintln!("Error in {}: {}", CONFIG_FILE, e);
+            process::exit(USAGE_STATUS);
         }
-    };
+    }
+}
+
+/// Reads the number after `--seed`, or ends the process if there isn't one.
+fn parse_seed(value: Option<impl AsRef<str>>) -> u64 {
+    match value.and_then(|value| value.as_ref().parse().ok()) {
+        Some(seed) => seed,
+        None => {
+            eprintln!("--seed needs a number. Any number. Even 42.");
+            process::exit(USAGE_STATUS);
+        },
+    }
+}
 
-    let lexer = Lexer::new(&source_code);
+/// Returns whether an argument looks like a flag rather than a file. `-` is a file: stdin.
+fn is_option(arg: &str) -> bool {
+    arg.len() > 1 && arg.starts_with('-')
+}
+
+/// Ends the process over a flag nobody asked for, rather than going looking for a file called `--sed`.
+fn unknown_option(arg: &str) -> ! {
+    eprintln!("Unknown option '{}'. It's not in the usage either, we checked:\n\n{}", arg, cli::usage());
+    process::exit(USAGE_STATUS);
+}
+
+/// Reads the program from a file, or from stdin when the path is `-`.
+fn read_source(path: &str) -> io::Result<String> {
+    String::from_utf8(read_bytes(path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Reads a file, or stdin when the path is `-`, without caring whether it's text.
+fn read_bytes(path: &str) -> io::Result<Vec<u8>> {
+    if path == STDIN_PATH {
+        let mut bytes = Vec::new();
+        io::stdin().read_to_end(&mut bytes)?;
+        Ok(bytes)
+    } else {
+        fs::read(path)
+    }
+}
+
+/// Parses a program and everything it uses, reporting what went wrong if it didn't work out.
+fn parse_program(source: &str, path: &str) -> Result<Program, String> {
+    let program = Parser::new(Lexer::new(source).collect()).parse().map_err(|e| format!("Parse error [{}]: {}", e.report().code_name(), e))?;
+    loader::link(program, loader::directory_of(Path::new(path))).map_err(|e| format!("Load error: {}", e))
+}
+
+/// Lexes, parses and runs a program, reporting whatever goes wrong on the way.
+/// Programs read from a file get their `use` statements resolved against the files next to it.
+/// Returns the status the process should exit with.
+fn run_source(source: &str, path: &str, options: Options, cancel: &CancelHandle) -> i32 {
+    let lexer = Lexer::new(source);
     let tokens: Vec<_> = lexer.collect();
-    println!("Tokens: {:#?}", tokens);
+    if options.emit_tokens {
+        println!("Tokens: {:#?}", tokens);
+    }
 
     let mut parser = Parser::new(tokens);
+    // Spans let errors point at the line that failed. The AST dump would rather not see them
+    parser.set_record_spans(options.emit_ast.is_none());
     match parser.parse() {
         Ok(program) => {
-            println!("AST: {:#?}", program);
-            println!("\nExecuting program...\n");
+            let program = if path == STDIN_PATH {
+                program
+            } else {
+                match loader::link(program, loader::directory_of(Path::new(path))) {
+                    Ok(program) => program,
+                    Err(e) => {
+                        match (&e, options.format) {
+                            (LoadError::Parse { source, .. }, DiagnosticFormat::Json) => eprintln!("{}", source.report().to_json()),
+                            _ => eprintln!("Load error: {}", e),
+                        }
+                        return PARSE_ERROR_STATUS;
+                    },
+                }
+            };
+            let status = run_program(program, Some(source), path, options, cancel);
+            for autocorrection in parser.autocorrections() {
+                println!("{}", autocorrection);
+            }
+            status
+        }
+        Err(e) => {
+            match options.format {
+                DiagnosticFormat::Human => eprintln!("{}", e.report().render(source, path)),
+                DiagnosticFormat::Json => eprintln!("{}", e.report().to_json()),
+            }
+            PARSE_ERROR_STATUS
+        },
+    }
+}
+
+/// Runs a parsed program, reporting whatever goes wrong (next to the line it went wrong on, when
+/// there's source to show). Returns the status the process should exit with.
+fn run_program(program: Program, source: Option<&str>, path: &str, options: Options, cancel: &CancelHandle) -> i32 {
+    match options.emit_ast {
+        Some(AstFormat::Debug) => println!("AST: {:#?}", program),
+        Some(AstFormat::Json) => println!("{}", ast::program_to_json(&program)),
+        None => {},
+    }
+    if options.emit_tokens || options.emit_ast.is_some() {
+        println!("\nExecuting program...\n");
+    }
 
-            let mut interpreter = Interpreter::new();
-            match interpreter.interpret(program) {
-                Ok(_) => println!("Program completed successfully"),
-                Err(e) => eprintln!("Runtime error: {}", e),
+    // Always run with a seed, so any run can be reproduced by passing it back in. It goes to stderr,
+    // so it never ends up in the output people pipe somewhere
+    let seed = options.seed.unwrap_or_else(rand::random);
+    eprintln!("🎲 Seed: {} (pass --seed {} to suffer through this exact run again)\n", seed, seed);
+
+    let mut interpreter = Interpreter::with_config(options.config);
+    interpreter.set_seed(seed);
+    interpreter.set_cancel_handle(cancel.clone());
+    interpreter.set_args(options.args.clone());
+    interpreter.set_completely_normal(options.normal);
+    if options.trace {
+        interpreter.set_tracer(Box::new(ConsoleTracer::default()));
+    }
+    interpreter.set_profiling(options.profile.is_some());
+    if let Some(breakpoints) = &options.debug {
+        let session = DebugSession::new(Box::new(ConsoleDebugger));
+        interpreter.set_debugger(if breakpoints.is_empty() { session } else { session.with_breakpoints(breakpoints.clone()) });
+    }
+    #[cfg(feature = "dynamic-plugins")]
+    for path in &options.plugins {
+        // Whoever passed --plugin vouched for the library
+        let loaded = unsafe { useless_lang::plugin::load_library(path) }.and_then(|plugin| interpreter.load_plugin(&*plugin));
+        if let Err(e) = loaded {
+            eprintln!("Plugin error: {}", e);
+            return USAGE_STATUS;
+        }
+    }
+    let result = interpreter.interpret(program);
+    for warning in interpreter.warnings() {
+        match options.format {
+            DiagnosticFormat::Human => eprintln!("{}", warning.report().render(source.unwrap_or_default(), path)),
+            DiagnosticFormat::Json => eprintln!("{}", warning.report().to_json()),
+        }
+    }
+    if options.chaos_report {
+        eprintln!("{}", interpreter.chaos_report());
+    }
+    match (options.profile, interpreter.profile()) {
+        (Some(DiagnosticFormat::Json), Some(profile)) => eprintln!("{}", profile.to_json()),
+        (_, Some(profile)) => eprintln!("{}", profile),
+        (_, None) => {},
+    }
+    match result {
+        Ok(_) => {
+            println!("Program completed successfully");
+            SUCCESS_STATUS
+        },
+        Err(e @ RuntimeError::Exited(_)) => runtime_error_status(&e),
+        Err(e) if options.format == DiagnosticFormat::Json => {
+            let report = ErrorReport { span: interpreter.error_span(), ..e.report() };
+            eprintln!("{}", report.to_json());
+            runtime_error_status(&e)
+        },
+        Err(e @ RuntimeError::CancelledByHuman) => {
+            eprintln!("\n✋ Interrupted. Here's how far it got:\n{}", interpreter.stats());
+            for event in interpreter.chaos_audit() {
+                eprintln!("  🎲 {}", event);
             }
+            runtime_error_status(&e)
+        },
+        Err(e) => {
+            // Pre-parsed programs have no source for their spans to point into
+            let report = ErrorReport { message: interpreter.render_error(&e), span: source.and(interpreter.error_span()), ..e.report() };
+            eprintln!("{}", report.render(source.unwrap_or_default(), path));
+            eprintln!("  = `useless-lang explain {}` has the long version", report.code_name());
+            runtime_error_status(&e)
+        },
+    }
+}
+
+/// What `useless-lang debug` understands at its prompt.
+const DEBUG_HELP: &str = "\
+  s, step          run this statement and stop at the next one (or just press enter)
+  c, continue      run until the next breakpoint
+  b, break <line>  stop before the statement on a line
+  d, delete <line> stop stopping there
+  p, print <name>  show a variable
+  vars             show every variable
+  q, quit          stop the program for good";
+
+/// The debugger behind `useless-lang debug`: says where the program stopped, then asks on stdin what next.
+/// It talks on stderr, so it doesn't get mixed up with what the program prints.
+struct ConsoleDebugger;
+
+impl Debugger for ConsoleDebugger {
+    fn paused(&mut self, stop: &Stop<'_>, breakpoints: &mut BTreeSet<usize>) -> Resume {
+        let code = fmt::format_program(std::slice::from_ref(stop.statement));
+        eprintln!("⏸️  line {}: {}", stop.span.line, code.lines().next().unwrap_or_default());
+        loop {
+            eprint!("(debug) ");
+            let mut line = String::new();
+            if io::stdin().read_line(&mut line).unwrap_or(0) == 0 {
+                return Resume::Quit;
+            }
+            let mut words = line.split_whitespace();
+            match (words.next(), words.next()) {
+                (None | Some("s" | "step"), _) => return Resume::Step,
+                (Some("c" | "continue"), _) => return Resume::Continue,
+                (Some("q" | "quit"), _) => return Resume::Quit,
+                (Some(command @ ("b" | "break" | "d" | "delete")), Some(number)) => match number.parse::<usize>() {
+                    Ok(line) if command.starts_with('b') => {
+                        breakpoints.insert(line);
+                        eprintln!("🔴 Breakpoint on line {}", line);
+                    },
+                    Ok(line) if breakpoints.remove(&line) => eprintln!("⚪ No more breakpoint on line {}", line),
+                    Ok(line) => eprintln!("There was no breakpoint on line {}. Mission accomplished anyway.", line),
+                    Err(_) => eprintln!("'{}' is not a line number. Lines are counted in whole numbers, from 1.", number),
+                },
+                (Some("p" | "print"), Some(name)) => match stop.environment.get(name) {
+                    Some(value) => eprintln!("{} = {:?}", name, value),
+                    None => eprintln!("{} doesn't exist. Yet, or anymore.", name),
+                },
+                (Some("vars"), _) => {
+                    for name in stop.environment.names() {
+                        if let Some(value) = stop.environment.get(&name) {
+                            eprintln!("{} = {:?}", name, value);
+                        }
+                    }
+                },
+                _ => eprintln!("{}", DEBUG_HELP),
+            }
+        }
+    }
+}
+
+/// The tracer behind `--trace`: a line on stderr for every statement as it starts, indented by how
+/// deep it is, and another when chaos got to it or it failed.
+#[derive(Default)]
+struct ConsoleTracer {
+    /// Whether the error on its way up was already reported, by the statement it started in
+    reported: bool,
+}
+
+impl Tracer for ConsoleTracer {
+    fn entered(&mut self, statement: &TracedStatement<'_>) {
+        self.reported = false;
+        let code = fmt::format_program(std::slice::from_ref(statement.statement));
+        let location = statement.span.map_or("?".to_string(), |span| format!("{}:{}", span.line, span.column));
+        eprintln!("🔍 {}{} {}", "  ".repeat(statement.depth), location, code.lines().next().unwrap_or_default());
+    }
+
+    fn finished(&mut self, statement: &TracedStatement<'_>, chaos: &[ChaosEvent], result: &Result<(), RuntimeError>) {
+        let indent = "  ".repeat(statement.depth + 1);
+        if !chaos.is_empty() {
+            let chaos: Vec<String> = chaos.iter().map(ToString::to_string).collect();
+            eprintln!("🎲 {}chaos: {}", indent, chaos.join(", "));
+        }
+        if let Err(e) = result {
+            if !self.reported {
+                eprintln!("💥 {}failed [{}]: {}", indent, e.report().code_name(), e);
+                self.reported = true;
+            }
+        }
+    }
+}
+
+/// Parses programs (and everything they use) and saves them pre-parsed, as `.uplb` files
+/// next to the originals, or wherever `-o` says when there's just one.
+#[cfg(feature = "uplb")]
+fn compile(arguments: &[String]) -> ! {
+    let mut output = None;
+    let mut paths = Vec::new();
+    let mut args = arguments.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" => output = args.next().cloned(),
+            _ if is_option(arg) => unknown_option(arg),
+            _ => paths.push(arg.clone()),
+        }
+    }
+    if paths.is_empty() || (output.is_some() && paths.len() > 1) {
+        eprintln!("Usage: useless-lang compile <file.upl> [-o <file.uplb>]");
+        eprintln!("       useless-lang compile <file.upl>...");
+        process::exit(USAGE_STATUS);
+    }
+
+    let mut failed = false;
+    for path in &paths {
+        let target = output.clone().unwrap_or_else(|| {
+            Path::new(path).with_extension(uplb::EXTENSION).to_string_lossy().into_owned()
+        });
+        let result = read_source(path)
+            .map_err(|e| e.to_string())
+            .and_then(|source| parse_program(&source, path))
+            .and_then(|program| {
+                let bytes = uplb::encode(&program);
+                fs::write(&target, &bytes).map(|_| bytes.len()).map_err(|e| e.to_string())
+            });
+        match result {
+            Ok(size) => println!("📦 {} -> {} ({} bytes, no parsing required)", path, target, size),
+            Err(e) => {
+                eprintln!("💥 {}: {}", path, e);
+                failed = true;
+            },
+        }
+    }
+    process::exit(i32::from(failed));
+}
+
+/// Writes a documentation site from the doc comments in some programs.
+fn document(arguments: &[String]) -> ! {
+    let mut format = DocFormat::default();
+    let mut output = PathBuf::from(DEFAULT_DOC_DIR);
+    let mut paths = Vec::new();
+    let mut args = arguments.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => match args.next().and_then(|name| DocFormat::from_name(name)) {
+                Some(chosen) => format = chosen,
+                None => {
+                    eprintln!("--format is {}. Nobody reads the documentation either way.", DocFormat::NAMES.join(" or "));
+                    process::exit(USAGE_STATUS);
+                },
+            },
+            "-o" => match args.next() {
+                Some(dir) => output = PathBuf::from(dir),
+                None => {
+                    eprintln!("-o needs a directory to put the documentation in.");
+                    process::exit(USAGE_STATUS);
+                },
+            },
+            _ if is_option(arg) => unknown_option(arg),
+            _ => paths.push(arg),
+        }
+    }
+    if paths.is_empty() {
+        eprintln!("Usage: useless-lang doc {}", cli::command("doc").map_or("", |command| command.synopsis));
+        process::exit(USAGE_STATUS);
+    }
+
+    let mut pages = Vec::with_capacity(paths.len());
+    for path in paths {
+        let title = match Path::new(path).file_stem() {
+            _ if path == STDIN_PATH => "stdin".to_string(),
+            Some(stem) => stem.to_string_lossy().into_owned(),
+            None => path.clone(),
+        };
+        let source = match read_source(path) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("💥 {}: couldn't read it: {}", path, e);
+                process::exit(RUNTIME_ERROR_STATUS);
+            },
+        };
+        match doc::document(&source) {
+            Ok(items) => pages.push((title, items)),
+            Err(e) => {
+                eprintln!("{}", e.report().render(&source, path));
+                process::exit(PARSE_ERROR_STATUS);
+            },
+        }
+    }
+
+    match doc::write_site(&pages, &output, format) {
+        Ok(files) => {
+            println!("📚 Documented {} file(s) in {} ({})", pages.len(), output.display(), doc::DISCLAIMER);
+            for file in files {
+                println!("  {}", file.display());
+            }
+            process::exit(SUCCESS_STATUS);
+        },
+        Err(e) => {
+            eprintln!("💥 Couldn't write the documentation: {}", e);
+            process::exit(RUNTIME_ERROR_STATUS);
+        },
+    }
+}
+
+/// Writes a program in another language, exactly as wrong as it was.
+fn transpile(arguments: &[String]) -> ! {
+    let mut target = DEFAULT_TRANSPILE_TARGET.to_string();
+    let mut output = None;
+    let mut paths = Vec::new();
+    let mut args = arguments.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--target" | "-o" => match args.next() {
+                Some(value) if arg == "--target" => target = value.clone(),
+                Some(value) => output = Some(PathBuf::from(value)),
+                None => {
+                    eprintln!("{} needs a value. It can't guess, and neither can we.", arg);
+                    process::exit(USAGE_STATUS);
+                },
+            },
+            _ if is_option(arg) => unknown_option(arg),
+            _ => paths.push(arg),
+        }
+    }
+    let [path] = paths.as_slice() else {
+        eprintln!("Usage: useless-lang transpile {}", cli::command("transpile").map_or("", |command| command.synopsis));
+        process::exit(USAGE_STATUS);
+    };
+    let Some(backend) = codegen::backend(&target) else {
+        let names: Vec<&str> = codegen::BACKENDS.iter().map(|backend| backend.name()).collect();
+        eprintln!("--target is {}. {} isn't useless enough yet.", names.join(", "), target);
+        process::exit(USAGE_STATUS);
+    };
+
+    let source = match read_source(path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("💥 {}: couldn't read it: {}", path, e);
+            process::exit(RUNTIME_ERROR_STATUS);
+        },
+    };
+    let program = match Parser::new(Lexer::new(&source).collect()).parse() {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("{}", e.report().render(&source, path));
+            process::exit(PARSE_ERROR_STATUS);
+        },
+    };
+    let code = loader::link(program, loader::directory_of(Path::new(path.as_str())))
+        .map_err(|e| format!("Load error: {}", e))
+        .and_then(|program| codegen::transpile_program(program, backend).map_err(|e| e.to_string()));
+    let code = match code {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("💥 {}: {}", path, e);
+            process::exit(RUNTIME_ERROR_STATUS);
+        },
+    };
+
+    match output {
+        None => print!("{}", code),
+        Some(output) => match fs::write(&output, code) {
+            Ok(()) => println!("🔁 {} -> {} ({}, still useless)", path, output.display(), backend.name()),
+            Err(e) => {
+                eprintln!("💥 Couldn't write {}: {}", output.display(), e);
+                process::exit(RUNTIME_ERROR_STATUS);
+            },
+        },
+    }
+    process::exit(SUCCESS_STATUS);
+}
+
+/// Prints a completion script for the shell named on the command line.
+fn completions(arguments: &[String]) -> ! {
+    match arguments.first().and_then(|name| Shell::from_name(name)) {
+        Some(shell) => {
+            print!("{}", cli::completions(shell));
+            process::exit(SUCCESS_STATUS);
+        },
+        None => {
+            eprintln!("Usage: useless-lang completions {}", cli::command("completions").map_or("", |command| command.synopsis));
+            eprintln!("Other shells will have to guess.");
+            process::exit(USAGE_STATUS);
+        },
+    }
+}
+
+/// Collects every function declared at the top level or inside a module, with its body.
+fn declared_functions(program: &[Statement]) -> Vec<(&str, &[Statement])> {
+    let mut functions = Vec::new();
+    for statement in program {
+        match statement {
+            Statement::Function { name, body, .. } | Statement::AsyncFunction { name, body, .. } => {
+                functions.push((name.as_str(), body.as_slice()));
+            },
+            Statement::Module { body, .. } => functions.extend(declared_functions(body)),
+            Statement::Attributed { statement, .. } => functions.extend(declared_functions(std::slice::from_ref(statement))),
+            _ => {},
         }
-        Err(e) => eprintln!("Parse error: {}", e),
     }
+    functions
+}
+
+/// Measures every function in every program, and complains about the ones that are too much.
+/// Exits with 1 if anything got complained about (or didn't parse).
+fn lint(paths: &[String]) -> ! {
+    if let Some(option) = paths.iter().find(|path| is_option(path)) {
+        unknown_option(option);
+    }
+    if paths.is_empty() {
+        eprintln!("Usage: useless-lang lint <file.upl | ->...");
+        process::exit(USAGE_STATUS);
+    }
+
+    let mut complained = false;
+    for path in paths {
+        let program = match read_source(path).map_err(|e| e.to_string()).and_then(|source| {
+            Parser::new(Lexer::new(&source).collect()).parse().map_err(|e| format!("Parse error [{}]: {}", e.report().code_name(), e))
+        }) {
+            Ok(program) => program,
+            Err(e) => {
+                eprintln!("💥 {}: {}", path, e);
+                complained = true;
+                continue;
+            },
+        };
+
+        let mut clean = true;
+        for (name, body) in declared_functions(&program) {
+            let metrics = ast::metrics(body);
+            let problems: Vec<String> = [
+                ("cyclomatic chaos", metrics.cyclomatic_chaos, LINT_MAX_CYCLOMATIC_CHAOS),
+                ("nesting depth", metrics.max_depth, LINT_MAX_DEPTH),
+                ("statements", metrics.statements, LINT_MAX_STATEMENTS),
+            ]
+            .into_iter()
+            .filter(|(_, value, limit)| value > limit)
+            .map(|(what, value, limit)| format!("{} {} (limit {})", what, value, limit))
+            .collect();
+            if !problems.is_empty() {
+                println!("⚠️  {}: `{}` is too useless even for us: {}", path, name, problems.join(", "));
+                clean = false;
+            }
+        }
+        if clean {
+            println!("✅ {}: every function is exactly as useless as it should be.", path);
+        }
+        complained |= !clean;
+    }
+    process::exit(i32::from(complained));
+}
+
+/// Lexes and parses programs without running them, reporting every syntax error in every file.
+/// Never touches the interpreter, so nothing opens a browser. Exits like `run` would: with
+/// `PARSE_ERROR_STATUS` if a program is wrong, and `USAGE_STATUS` if the files couldn't be read at all.
+fn check(paths: &[String]) -> ! {
+    if let Some(option) = paths.iter().find(|path| is_option(path)) {
+        unknown_option(option);
+    }
+    if paths.is_empty() {
+        eprintln!("Usage: useless-lang check <file.upl | ->...");
+        process::exit(USAGE_STATUS);
+    }
+
+    let mut broken = false;
+    let mut unreadable = false;
+    for path in paths {
+        let source = match read_source(path) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("{}: couldn't read it: {}", path, e);
+                unreadable = true;
+                continue;
+            },
+        };
+
+        let (_, errors) = Parser::new(Lexer::new(&source).collect()).parse_with_recovery();
+        if errors.is_empty() {
+            println!("✅ {}: no syntax errors. The runtime errors are still a surprise.", path);
+        }
+        for error in &errors {
+            eprintln!("{}", error.report().render(&source, path));
+        }
+        broken |= !errors.is_empty();
+    }
+    process::exit(match (unreadable, broken) {
+        (true, _) => USAGE_STATUS,
+        (false, true) => PARSE_ERROR_STATUS,
+        (false, false) => SUCCESS_STATUS,
+    });
+}
+
+/// Lexes (`tokenize`) or parses (`ast`) a program and prints what came out, without running anything.
+/// It's JSON for tools to read, or the debug layout for people with `--pretty`.
+fn dump(command: &str, arguments: &[String]) -> ! {
+    let pretty = arguments.iter().any(|argument| argument == "--pretty");
+    let paths: Vec<&String> = arguments.iter().filter(|argument| *argument != "--pretty").collect();
+    if let Some(option) = paths.iter().find(|path| is_option(path)) {
+        unknown_option(option);
+    }
+    let [path] = paths.as_slice() else {
+        eprintln!("Usage: useless-lang {} [--pretty] <file.upl | ->", command);
+        process::exit(USAGE_STATUS);
+    };
+    let source = match read_source(path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("Error reading file {}: {}", path, e);
+            process::exit(USAGE_STATUS);
+        },
+    };
+
+    let tokens: Vec<Token> = Lexer::new(&source).collect();
+    if command == "tokenize" {
+        if pretty {
+            println!("{:#?}", tokens);
+        } else {
+            println!("{}", lexer::tokens_to_json(&tokens));
+        }
+        process::exit(SUCCESS_STATUS);
+    }
+    match Parser::new(tokens).parse() {
+        Ok(program) if pretty => println!("{:#?}", program),
+        Ok(program) => println!("{}", ast::program_to_json(&program)),
+        Err(e) => {
+            eprintln!("{}: Parse error [{}]: {}", path, e.report().code_name(), e);
+            process::exit(PARSE_ERROR_STATUS);
+        },
+    }
+    process::exit(SUCCESS_STATUS);
+}
+
+/// Rewrites programs in the canonical layout. Stdin (`-`) is formatted to stdout.
+/// With `--check`, nothing is written: it only lists the files that would change, and exits with 1 if any would.
+fn format(arguments: &[String]) -> ! {
+    let check_only = arguments.iter().any(|argument| argument == "--check");
+    let paths: Vec<&String> = arguments.iter().filter(|argument| *argument != "--check").collect();
+    if let Some(option) = paths.iter().find(|path| is_option(path)) {
+        unknown_option(option);
+    }
+    if paths.is_empty() {
+        eprintln!("Usage: useless-lang fmt [--check] <file.upl | ->...");
+        process::exit(USAGE_STATUS);
+    }
+
+    let mut failed = false;
+    for path in paths {
+        let source = match read_source(path) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("{}: couldn't read it: {}", path, e);
+                failed = true;
+                continue;
+            },
+        };
+        let formatted = match fmt::format_source(&source) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                eprintln!("{}: Parse error [{}]: {}", path, e.report().code_name(), e);
+                failed = true;
+                continue;
+            },
+        };
+
+        if check_only {
+            if formatted != source {
+                println!("{} would be reformatted", path);
+                failed = true;
+            }
+        } else if path == STDIN_PATH {
+            print!("{}", formatted);
+        } else if formatted != source {
+            if let Err(e) = fs::write(path, formatted) {
+                eprintln!("{}: couldn't write it: {}", path, e);
+                failed = true;
+            }
+        }
+    }
+    process::exit(i32::from(failed));
+}
+
+/// Runs `*_test.upl` programs, each in a fresh interpreter, and fails the ones that don't make it to the end.
+/// Any other program is run with a fixed seed and compared with its snapshot, which `--update-snapshots`
+/// rewrites instead. Directories (or the current one) are searched for tests. Exits with 1 if anything failed.
+fn test(arguments: &[String]) -> ! {
+    let mut update = false;
+    let mut normal = false;
+    let mut seed = snapshot::DEFAULT_SEED;
+    let mut paths = Vec::new();
+    let mut args = arguments.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--update-snapshots" => update = true,
+            "--normal" => normal = true,
+            "--seed" => seed = parse_seed(args.next()),
+            _ if is_option(arg) => unknown_option(arg),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+
+    // Directories (the current one, if nothing was named) stand for the test programs inside them
+    if paths.is_empty() {
+        paths.push(PathBuf::from("."));
+    }
+    let mut files = Vec::new();
+    for path in paths {
+        if !path.is_dir() {
+            files.push(path);
+            continue;
+        }
+        match testing::discover_tests(&path) {
+            Ok(found) => files.extend(found),
+            Err(e) => {
+                eprintln!("💥 {}: couldn't look for tests: {}", path.display(), e);
+                process::exit(USAGE_STATUS);
+            },
+        }
+    }
+    if files.is_empty() {
+        eprintln!("No *{} files here. Nothing can fail, which is a first.", testing::TEST_FILE_SUFFIX);
+        eprintln!("Usage: useless-lang test [--normal] [--update-snapshots] [--seed <number>] [<file.upl | directory>...]");
+        process::exit(USAGE_STATUS);
+    }
+
+    let mut failed = 0;
+    for file in &files {
+        let path = file.display();
+        if !testing::is_test_file(file) {
+            failed += usize::from(check_snapshot(file, seed, update));
+            continue;
+        }
+
+        let outcome = fs::read_to_string(file).map_err(|e| e.to_string()).and_then(|source| {
+            testing::run_test(&source, normal, seed).map_err(|e| format!("Parse error [{}]: {}", e.report().code_name(), e))
+        });
+        match outcome {
+            Ok(TestOutcome::Passed) => println!("✅ {} passed", path),
+            Ok(TestOutcome::Failed { errors, output }) => {
+                // Failing is the one thing chaos mode does reliably
+                let verdict = if normal { "failed" } else { "failed successfully" };
+                println!("❌ {} {}:", path, verdict);
+                for error in &errors {
+                    println!("    {}: {}", error.report().code_name(), error);
+                }
+                for line in output.lines() {
+                    println!("    | {}", line);
+                }
+                failed += 1;
+            },
+            Err(e) => {
+                eprintln!("💥 {}: {}", path, e);
+                failed += 1;
+            },
+        }
+    }
+    println!("\n{} passed, {} failed", files.len() - failed, failed);
+    process::exit(i32::from(failed > 0));
+}
+
+/// Checks a program against its snapshot, printing how it went. Returns whether it failed.
+fn check_snapshot(file: &Path, seed: u64, update: bool) -> bool {
+    let path = file.display();
+    match snapshot::check(file, seed, update) {
+        Ok(SnapshotOutcome::Matched) => println!("✅ {} did exactly the same wrong things as last time", path),
+        Ok(SnapshotOutcome::Written) => println!("📸 {}: snapshot written", path),
+        Ok(SnapshotOutcome::Missing) => {
+            println!("❓ {} has no snapshot yet (run with --update-snapshots to take one)", path);
+            return true;
+        },
+        Ok(SnapshotOutcome::Mismatch { diff }) => {
+            println!("❌ {} doesn't match its snapshot (- snapshot, + this run):\n{}", path, diff);
+            return true;
+        },
+        Err(e) => {
+            eprintln!("💥 {}: {}", path, e);
+            return true;
+        },
+    }
+    false
+}
+
+/// Creates a new project directory from a template.
+fn new_project(arguments: &[String]) -> ! {
+    let mut template = Template::default();
+    let mut paths = Vec::new();
+    let mut args = arguments.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--template" => match args.next().and_then(|name| Template::from_name(name)) {
+                Some(chosen) => template = chosen,
+                None => {
+                    eprintln!("--template is {}. There's no in-between, that's the whole point.", Template::NAMES.join(" or "));
+                    process::exit(USAGE_STATUS);
+                }
+            },
+            _ if is_option(arg) => unknown_option(arg),
+            _ => paths.push(arg),
+        }
+    }
+    let [path] = paths.as_slice() else {
+        eprintln!("Usage: useless-lang new {}", cli::command("new").map_or("", |command| command.synopsis));
+        process::exit(USAGE_STATUS);
+    };
+
+    match scaffold::create(Path::new(path), template) {
+        Ok(files) => {
+            println!("✨ Created {}:", path);
+            for file in files {
+                println!("  {}", file.display());
+            }
+            println!("
+Run it with: cd {} && useless-lang src/main.upl", path);
+            process::exit(SUCCESS_STATUS);
+        },
+        Err(e) => {
+            eprintln!("💥 {}", e);
+            process::exit(RUNTIME_ERROR_STATUS);
+        },
+    }
+}
+
+/// Prints the long version of an error code, like `E0418`.
+fn explain_code(arguments: &[String]) -> ! {
+    let [code] = arguments else {
+        eprintln!("Usage: useless-lang explain {}", cli::command("explain").map_or("", |command| command.synopsis));
+        process::exit(USAGE_STATUS);
+    };
+    match explain::explain(code) {
+        Some(explanation) => {
+            println!("E{:04}: {}\n\n{}", explanation.code, explanation.name, explanation.text);
+            process::exit(SUCCESS_STATUS);
+        },
+        None => {
+            eprintln!("{} isn't a code we know. We'd apologise for it if we could.", code);
+            process::exit(USAGE_STATUS);
+        },
+    }
+}
+
+/// Serves an editor over stdin and stdout until it's done with us.
+#[cfg(feature = "lsp")]
+fn lsp() -> ! {
+    match useless_lang::lsp::serve(io::stdin().lock(), io::stdout().lock()) {
+        Ok(()) => process::exit(SUCCESS_STATUS),
+        Err(e) => {
+            eprintln!("💥 The editor stopped making sense: {}", e);
+            process::exit(RUNTIME_ERROR_STATUS);
+        },
+    }
+}
+
+/// Times a program over several chaos-free runs and prints how long it took.
+fn bench(arguments: &[String]) -> ! {
+    let mut iterations = bench::DEFAULT_ITERATIONS;
+    let mut paths = Vec::new();
+    let mut args = arguments.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--iterations" => match args.next().and_then(|value| value.parse::<usize>().ok()).filter(|&count| count > 0) {
+                Some(count) => iterations = count,
+                None => {
+                    eprintln!("--iterations needs a number bigger than zero. Timing nothing is too easy.");
+                    process::exit(USAGE_STATUS);
+                }
+            },
+            _ if is_option(arg) => unknown_option(arg),
+            _ => paths.push(arg),
+        }
+    }
+    let [path] = paths.as_slice() else {
+        eprintln!("Usage: useless-lang bench {}", cli::command("bench").map_or("", |command| command.synopsis));
+        process::exit(USAGE_STATUS);
+    };
+
+    let program = match read_source(path).map_err(|e| e.to_string()).and_then(|source| parse_program(&source, path)) {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("💥 {}: {}", path, e);
+            process::exit(PARSE_ERROR_STATUS);
+        },
+    };
+    match bench::run(&program, iterations, Config::default()) {
+        Ok(report) => {
+            println!("{}: {}", path, report);
+            process::exit(SUCCESS_STATUS);
+        },
+        Err(e) => {
+            eprintln!("💥 {}: {}", path, e);
+            process::exit(RUNTIME_ERROR_STATUS);
+        },
+    }
+}
+
+/// What the REPL understands besides code.
+const REPL_HELP: &str = "\
+  :tokens [json]   show the tokens of the last input
+  :ast [json]      show the syntax tree of the last input
+  :chaos           show the chaos the last input suffered
+  :history [text]  list what was typed before (only what contains the text, if there is any)
+  :help            show this
+  :quit            leave (so does Ctrl-D)
+  Ctrl-R           search what was typed before, newest first (again for older)
+  Ctrl-C           throw away what was typed so far
+  An empty line runs whatever was typed so far, finished or not.";
+
+/// Runs code as it's typed, all against the same globals, until Ctrl-D. Input carries on over as
+/// many lines as it takes to close every bracket, and everything typed is kept in the history file.
+/// The last input can be inspected afterwards, down to the tokens and the chaos it went through.
+fn repl(arguments: &[String]) -> ! {
+    let mut config = load_config();
+    let mut seed = None;
+    let mut normal = false;
+    let mut args = arguments.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => seed = Some(parse_seed(args.next())),
+            "--chaos-level" => match args.next().and_then(|value| value.parse::<u8>().ok()).filter(|&level| level <= MAX_CHAOS_LEVEL) {
+                Some(value) => config.chaos_level = value,
+                None => {
+                    eprintln!("--chaos-level goes from 0 to {}. Nobody needs more than {}.", MAX_CHAOS_LEVEL, MAX_CHAOS_LEVEL);
+                    process::exit(USAGE_STATUS);
+                }
+            },
+            "--no-browser" => config.open_browser = false,
+            "--normal" => normal = true,
+            _ => {
+                eprintln!("Usage: useless-lang repl {}", cli::command("repl").map_or("", |command| command.synopsis));
+                process::exit(USAGE_STATUS);
+            },
+        }
+    }
+
+    let seed = seed.unwrap_or_else(rand::random);
+    let cancel = CancelHandle::new();
+    cancel_on_ctrl_c(cancel.clone());
+    let mut interpreter = Interpreter::with_config(config);
+    interpreter.set_seed(seed);
+    interpreter.set_cancel_handle(cancel.clone());
+    interpreter.set_completely_normal(normal);
+    let mut session = Session::with_interpreter(interpreter);
+    let mut history = match repl::default_history_path().map(History::load) {
+        Some(Ok(history)) => history,
+        Some(Err(e)) => {
+            eprintln!("Couldn't read the history, so this session starts with amnesia: {}", e);
+            History::new()
+        },
+        None => History::new(),
+    };
+    println!("🎲 Seed: {}. Type :help for help, or just type code and hope.", seed);
+
+    let mut input = String::new();
+    let mut last: Option<LastInput> = None;
+    loop {
+        let prompt = if input.is_empty() { "upl> " } else { "...> " };
+        let line = match repl::read_line(prompt, &history) {
+            Ok(Edited::Line(line)) => line,
+            // Ctrl-C throws away the whole unfinished input, not just the line
+            Ok(Edited::Interrupted) => {
+                input.clear();
+                continue;
+            },
+            Ok(Edited::EndOfInput) | Err(_) => {
+                println!();
+                process::exit(SUCCESS_STATUS);
+            },
+        };
+        if input.is_empty() && line.trim_start().starts_with(':') {
+            let mut words = line.split_whitespace();
+            match (words.next(), words.next()) {
+                (Some(":q" | ":quit"), _) => process::exit(SUCCESS_STATUS),
+                (Some(command @ (":tokens" | ":ast" | ":chaos")), format) => match &last {
+                    Some(last) => {
+                        let json = format == Some("json");
+                        match command {
+                            ":tokens" => println!("{}", last.tokens_report(json)),
+                            ":ast" => println!("{}", last.ast_report(json)),
+                            _ => println!("{}", last.chaos_report()),
+                        }
+                    },
+                    None => println!("Nothing typed yet, so nothing to take apart."),
+                },
+                (Some(":history"), text) => {
+                    for entry in history.search(text.unwrap_or_default()).collect::<Vec<_>>().into_iter().rev() {
+                        println!("{}", entry);
+                    }
+                },
+                _ => println!("{}", REPL_HELP),
+            }
+            continue;
+        }
+
+        let blank = line.trim().is_empty();
+        input.push_str(&line);
+        if input.trim().is_empty() {
+            input.clear();
+            continue;
+        }
+        if !blank && repl::needs_more(&input) {
+            continue;
+        }
+        let source = std::mem::take(&mut input);
+        if let Err(e) = history.add(&source) {
+            eprintln!("Couldn't save the history: {}", e);
+        }
+
+        cancel.reset();
+        let inspected = last.insert(LastInput::new(&source));
+        match &inspected.program {
+            Ok(program) => {
+                let result = session.run(program.clone());
+                inspected.chaos = result.chaos_events;
+                if let Some(status) = result.exit_status {
+                    process::exit(status);
+                }
+                for error in &result.errors {
+                    eprintln!("💥 {}", session.interpreter_mut().render_error(error));
+                }
+                if result.last_value != Value::Null {
+                    println!("=> {}", result.last_value);
+                }
+            },
+            Err(e) => eprintln!("{}", e.report().render(&source, "repl")),
+        }
+    }
+}
+
+/// Runs a program, then runs it again every time the file is saved, until Ctrl-C.
+/// Ctrl-C during a run only stops that run; Ctrl-C while waiting stops watching.
+fn watch(path: &str, options: Options) -> ! {
+    if path == STDIN_PATH {
+        eprintln!("Can't watch stdin. It doesn't get saved, it just happens.");
+        process::exit(USAGE_STATUS);
+    }
+
+    let cancel = CancelHandle::new();
+    cancel_on_ctrl_c(cancel.clone());
+    let mut watcher = FileWatcher::new(path);
+    loop {
+        print!("{}", CLEAR_SCREEN);
+        println!("👀 Watching {} (Ctrl-C to stop)\n", path);
+        match fs::read_to_string(path) {
+            Ok(source) => {
+                run_source(&source, path, options.clone(), &cancel);
+            },
+            Err(e) => eprintln!("Error reading file {}: {}", path, e),
+        }
+        cancel.reset();
+
+        println!("\n👀 Waiting for {} to change...", path);
+        while !watcher.has_changed() {
+            if cancel.is_cancelled() {
+                process::exit(INTERRUPTED_STATUS);
+            }
+            thread::sleep(watch::POLL_INTERVAL);
+        }
+    }
+}
+
+fn main() {
+    let mut arguments: Vec<String> = env::args().skip(1).collect();
+    match arguments.first().map(String::as_str) {
+        Some("repl") => repl(&arguments[1..]),
+        Some("check") => check(&arguments[1..]),
+        Some("lint") => lint(&arguments[1..]),
+        #[cfg(feature = "uplb")]
+        Some("compile") => compile(&arguments[1..]),
+        Some("fmt") => format(&arguments[1..]),
+        Some(command @ ("tokenize" | "ast")) => dump(command, &arguments[1..]),
+        Some("test") => test(&arguments[1..]),
+        Some("bench") => bench(&arguments[1..]),
+        Some("new") => new_project(&arguments[1..]),
+        Some("doc") => document(&arguments[1..]),
+        Some("transpile") => transpile(&arguments[1..]),
+        Some("completions") => completions(&arguments[1..]),
+        Some("explain") => explain_code(&arguments[1..]),
+        #[cfg(feature = "lsp")]
+        Some("lsp") => lsp(),
+        Some("-h" | "--help") => {
+            print!("{}", cli::usage());
+            process::exit(SUCCESS_STATUS);
+        },
+        _ => {},
+    }
+    // `run` is what happens anyway, but it reads better in scripts
+    let watching = arguments.first().is_some_and(|arg| arg == "watch");
+    let debugging = arguments.first().is_some_and(|arg| arg == "debug");
+    if watching || debugging || arguments.first().is_some_and(|arg| arg == "run") {
+        arguments.remove(0);
+    }
+
+    let mut file_path = None;
+    let mut program_args = Vec::new();
+    let mut seed = None;
+    let mut chaos_level = None;
+    let mut timeout = None;
+    let mut max_steps = None;
+    #[cfg(feature = "dynamic-plugins")]
+    let mut plugins = Vec::new();
+    let mut breakpoints = Vec::new();
+    let mut emit_tokens = false;
+    let mut chaos_report = false;
+    let mut normal = false;
+    let mut trace = false;
+    let mut profile = None;
+    let mut emit_ast = None;
+    let mut format = DiagnosticFormat::Human;
+    let mut no_browser = env::var("UPL_NO_BROWSER").is_ok_and(|value| !value.is_empty() && value != "0");
+    let mut args = arguments.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => seed = Some(parse_seed(args.next())),
+            "--chaos-level" => match args.next().and_then(|value| value.parse::<u8>().ok()).filter(|&level| level <= MAX_CHAOS_LEVEL) {
+                Some(value) => chaos_level = Some(value),
+                None => {
+                    eprintln!("--chaos-level goes from 0 to {}. Nobody needs more than {}.", MAX_CHAOS_LEVEL, MAX_CHAOS_LEVEL);
+                    process::exit(USAGE_STATUS);
+                }
+            },
+            "--timeout" => match args.next().and_then(|value| value.parse::<u64>().ok()).filter(|&seconds| seconds > 0) {
+                Some(seconds) => timeout = Some(seconds),
+                None => {
+                    eprintln!("--timeout needs a number of seconds. Zero seconds is not a timeout, it's a wish.");
+                    process::exit(USAGE_STATUS);
+                }
+            },
+            "--max-steps" => match args.next().and_then(|value| value.parse::<usize>().ok()).filter(|&steps| steps > 0) {
+                Some(steps) => max_steps = Some(steps),
+                None => {
+                    eprintln!("--max-steps needs a number of steps. Zero steps gets nowhere, not even to the first line.");
+                    process::exit(USAGE_STATUS);
+                }
+            },
+            "--break" if debugging => match args.next().and_then(|value| value.parse::<usize>().ok()).filter(|&line| line > 0) {
+                Some(line) => breakpoints.push(line),
+                None => {
+                    eprintln!("--break needs a line number. Lines start at 1, like most things that aren't arrays.");
+                    process::exit(USAGE_STATUS);
+                }
+            },
+            #[cfg(feature = "dynamic-plugins")]
+            "--plugin" => match args.next() {
+                Some(path) => plugins.push(path.into()),
+                None => {
+                    eprintln!("--plugin needs the path to a shared library. The cursed extensions won't find themselves.");
+                    process::exit(USAGE_STATUS);
+                }
+            },
+            "--format" | "--format=json" | "--format=human" => {
+                let value = arg.strip_prefix("--format=").map(str::to_string).or_else(|| args.next());
+                format = match value.as_deref() {
+                    Some("human") => DiagnosticFormat::Human,
+                    Some("json") => DiagnosticFormat::Json,
+                    _ => {
+                        eprintln!("--format is either human or json. Machines and people, nothing in between.");
+                        process::exit(USAGE_STATUS);
+                    },
+                };
+            },
+            "--no-browser" => no_browser = true,
+            "--emit-tokens" => emit_tokens = true,
+            "--chaos-report" => chaos_report = true,
+            "--normal" => normal = true,
+            "--trace" => trace = true,
+            "--profile" => profile = Some(DiagnosticFormat::Human),
+            "--profile=json" => profile = Some(DiagnosticFormat::Json),
+            "--emit-ast" => emit_ast = Some(AstFormat::Debug),
+            "--emit-ast=json" => emit_ast = Some(AstFormat::Json),
+            _ if is_option(&arg) => unknown_option(&arg),
+            // Everything after the file belongs to the program, flags included
+            _ => {
+                file_path = Some(arg);
+                program_args.extend(args.by_ref());
+            },
+        }
+    }
+
+    // With nothing to run but something piped in, run what was piped in
+    let Some(file_path) = file_path.or_else(|| (!io::stdin().is_terminal()).then(|| STDIN_PATH.to_string())) else {
+        eprint!("{}", cli::usage());
+        process::exit(USAGE_STATUS);
+    };
+
+    let mut config = load_config();
+    if no_browser {
+        config.open_browser = false;
+    }
+    if let Some(seconds) = timeout {
+        config.timeout_ms = seconds.saturating_mul(1000);
+    }
+    if let Some(steps) = max_steps {
+        config.max_steps = steps;
+    }
+    if let Some(level) = chaos_level {
+        config.chaos_level = level;
+    }
+
+    let options = Options {
+        seed,
+        emit_tokens,
+        emit_ast,
+        format,
+        config,
+        #[cfg(feature = "dynamic-plugins")]
+        plugins,
+        debug: debugging.then_some(breakpoints),
+        args: program_args,
+        chaos_report,
+        normal,
+        trace,
+        profile,
+    };
+    if debugging && file_path == STDIN_PATH {
+        eprintln!("Can't debug stdin. It's busy being the program, so it can't take orders too.");
+        process::exit(USAGE_STATUS);
+    }
+    if watching {
+        watch(&file_path, options);
+    }
+
+    let cancel = CancelHandle::new();
+    cancel_on_ctrl_c(cancel.clone());
+    let bytes = match read_bytes(&file_path) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("Error reading file {}: {}", file_path, e);
+            process::exit(USAGE_STATUS);
+        }
+    };
+
+    // Pre-parsed programs skip straight to the running part
+    #[cfg(feature = "uplb")]
+    if uplb::is_uplb(&bytes) {
+        if debugging {
+            eprintln!("{} was pre-parsed without its line numbers, so the debugger has nowhere to stop. Debug the source instead.", file_path);
+            process::exit(USAGE_STATUS);
+        }
+        match uplb::decode(&bytes) {
+            Ok(program) => process::exit(run_program(program, None, &file_path, options, &cancel)),
+            Err(e) => {
+                eprintln!("Error reading file {}: {}", file_path, e);
+                process::exit(PARSE_ERROR_STATUS);
+            },
+        }
+    }
+
+    let source_code = match String::from_utf8(bytes) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Error reading file {}: {}", file_path, e);
+            process::exit(USAGE_STATUS);
+        }
+    };
+
+    process::exit(run_source(&source_code, &file_path, options, &cancel));
 }
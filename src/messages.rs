@@ -0,0 +1,165 @@
+//! # Messages Module
+//!
+//! The messages module decides how errors are presented to the unfortunate human.
+//! A message pack turns a [`RuntimeError`] into text; swap the pack to change the tone.
+//!
+//! ## Example
+//! ```rust
+//! use std::time::SystemTime;
+//! use useless_lang::interpreter::RuntimeError;
+//! use useless_lang::messages::{CorporateMessages, MessagePack};
+//!
+//! let invite = CorporateMessages.render_error(&RuntimeError::Teapot, SystemTime::UNIX_EPOCH);
+//! assert!(invite.starts_with("Subject: Sync on Teapot"));
+//! ```
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::interpreter::RuntimeError;
+
+/// A way of rendering errors, from sarcastic to soul-crushing.
+pub trait MessagePack: Send + Sync {
+    /// Renders an error, given the time it happened
+    fn render_error(&self, error: &RuntimeError, now: SystemTime) -> String;
+}
+
+/// The standard sarcastic error messages.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DefaultMessages;
+
+impl MessagePack for DefaultMessages {
+    fn render_error(&self, error: &RuntimeError, _now: SystemTime) -> String {
+        error.to_string()
+    }
+}
+
+/// Renders every error as a meeting invitation, complete with calendar attachment.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CorporateMessages;
+
+impl MessagePack for CorporateMessages {
+    fn render_error(&self, error: &RuntimeError, now: SystemTime) -> String {
+        let name = error.name();
+        let message = error.to_string();
+        let start = next_tuesday_3pm(now);
+
+        format!(
+            "Subject: Sync on {name} — Tuesday 3pm\n\
+             \n\
+             Hi team,\n\
+             \n\
+             Per my last error: {message}\n\
+             Let's circle back on this, align on next steps and take it offline.\n\
+             \n\
+             {ics}",
+            ics = meeting_ics(name, &message, now, start),
+        )
+    }
+}
+
+/// Generates an iCalendar invitation for a one hour meeting nobody wants to attend.
+fn meeting_ics(name: &str, message: &str, now: SystemTime, start: u64) -> String {
+    let stamp = seconds_since_epoch(now);
+    [
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//Useless Programming Language//Corporate Mode//EN".to_string(),
+        "METHOD:REQUEST".to_string(),
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}-{}@useless-lang", stamp, name.to_lowercase()),
+        format!("DTSTAMP:{}", ics_timestamp(stamp)),
+        format!("DTSTART:{}", ics_timestamp(start)),
+        format!("DTEND:{}", ics_timestamp(start + 3600)),
+        format!("SUMMARY:Sync on {}", name),
+        format!("DESCRIPTION:{}", ics_escape(message)),
+        "LOCATION:Conference Room B (the one without chairs)".to_string(),
+        "END:VEVENT".to_string(),
+        "END:VCALENDAR".to_string(),
+    ]
+    .join("\r\n")
+}
+
+/// Returns the seconds since the epoch, treating times before it as the epoch itself.
+fn seconds_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Finds the next Tuesday at 15:00 UTC strictly after the given time, in seconds since the epoch.
+fn next_tuesday_3pm(now: SystemTime) -> u64 {
+    let seconds = seconds_since_epoch(now);
+    let days = seconds / 86_400;
+    // The epoch was a Thursday; with Monday as 0, Tuesday is 1
+    let weekday = (days + 3) % 7;
+    let mut days_until = (8 - weekday) % 7;
+    if days_until == 0 && seconds % 86_400 >= 15 * 3600 {
+        days_until = 7;
+    }
+    (days + days_until) * 86_400 + 15 * 3600
+}
+
+/// Formats seconds since the epoch as an iCalendar UTC timestamp.
+fn ics_timestamp(seconds: u64) -> String {
+    let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+    let time = seconds % 86_400;
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/// Converts days since the epoch into a (year, month, day) date.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Escapes text for use in an iCalendar property value.
+fn ics_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_corporate_invite_for_division_by_zero() {
+        // Monday 2024-01-01 09:00 UTC
+        let now = UNIX_EPOCH + Duration::from_secs(1_704_099_600);
+        let invite = CorporateMessages.render_error(&RuntimeError::DivisionByZero, now);
+
+        assert!(invite.starts_with("Subject: Sync on DivisionByZero — Tuesday 3pm"));
+        assert!(invite.contains("DTSTART:20240102T150000Z"));
+        assert!(invite.contains("DTEND:20240102T160000Z"));
+        assert!(invite.contains("BEGIN:VEVENT"));
+    }
+
+    #[test]
+    fn test_tuesday_after_the_meeting_books_next_week() {
+        // Tuesday 2024-01-02 16:00 UTC, the meeting already happened
+        let now = UNIX_EPOCH + Duration::from_secs(1_704_211_200);
+        assert_eq!(ics_timestamp(next_tuesday_3pm(now)), "20240109T150000Z");
+    }
+
+    #[test]
+    fn test_ics_escape() {
+        assert_eq!(ics_escape("a, b; c\nd"), "a\\, b\\; c\\nd");
+    }
+}
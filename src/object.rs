@@ -0,0 +1,188 @@
+//! # Object Module
+//!
+//! The map behind [`Value::Object`](crate::interpreter::Value::Object).
+//! Fields remember the order they were added in, so printing and iterating an object gives the same
+//! answer every run. Objects only get shuffled when a config explicitly asks for it (`shuffle_keys`).
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::object::ObjectMap;
+//! use useless_lang::Value;
+//!
+//! let mut fields = ObjectMap::new();
+//! fields.insert("zebra".to_string(), Value::Null);
+//! fields.insert("apple".to_string(), Value::Null);
+//! assert_eq!(fields.keys().collect::<Vec<_>>(), ["zebra", "apple"]);
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Index;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::interpreter::Value;
+
+/// An insertion-ordered map from field names to values.
+#[derive(Clone, Default)]
+pub struct ObjectMap {
+    /// Every field, in the order it was added
+    entries: Vec<(String, Value)>,
+    /// Where each field sits in `entries`
+    positions: HashMap<String, usize>,
+}
+
+impl ObjectMap {
+    /// Creates an object with no fields.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns how many fields there are.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Checks whether there are no fields at all.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns a field's value.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.positions.get(key).map(|&position| &self.entries[position].1)
+    }
+
+    /// Returns a field's value, for changing it in place.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.positions.get(key).map(|&position| &mut self.entries[position].1)
+    }
+
+    /// Checks whether a field exists.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.positions.contains_key(key)
+    }
+
+    /// Sets a field, returning its old value. New fields go at the end; existing ones keep their place.
+    pub fn insert(&mut self, key: String, value: Value) -> Option<Value> {
+        if let Some(&position) = self.positions.get(&key) {
+            return Some(std::mem::replace(&mut self.entries[position].1, value));
+        }
+        self.positions.insert(key.clone(), self.entries.len());
+        self.entries.push((key, value));
+        None
+    }
+
+    /// Removes a field, keeping the order of the ones after it.
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        let position = self.positions.remove(key)?;
+        let (_, value) = self.entries.remove(position);
+        for (key, _) in &self.entries[position..] {
+            *self.positions.get_mut(key).expect("every entry has a position") -= 1;
+        }
+        Some(value)
+    }
+
+    /// Iterates over the fields in order.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+
+    /// Iterates over the field names in order.
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.entries.iter().map(|(key, _)| key)
+    }
+
+    /// Iterates over the values in order.
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.entries.iter().map(|(_, value)| value)
+    }
+
+    /// Puts the fields in a random order. Only ever done on purpose.
+    pub fn shuffle(&mut self, rng: &mut impl Rng) {
+        self.entries.shuffle(rng);
+        self.positions = self.entries.iter().enumerate().map(|(position, (key, _))| (key.clone(), position)).collect();
+    }
+}
+
+/// Objects are equal when they have the same fields with the same values, in any order.
+impl PartialEq for ObjectMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+impl fmt::Debug for ObjectMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl Index<&str> for ObjectMap {
+    type Output = Value;
+
+    fn index(&self, key: &str) -> &Value {
+        self.get(key).unwrap_or_else(|| panic!("no field named '{}'", key))
+    }
+}
+
+impl FromIterator<(String, Value)> for ObjectMap {
+    fn from_iter<I: IntoIterator<Item = (String, Value)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        for (key, value) in iter {
+            map.insert(key, value);
+        }
+        map
+    }
+}
+
+impl<const N: usize> From<[(String, Value); N]> for ObjectMap {
+    fn from(entries: [(String, Value); N]) -> Self {
+        entries.into_iter().collect()
+    }
+}
+
+impl IntoIterator for ObjectMap {
+    type Item = (String, Value);
+    type IntoIter = std::vec::IntoIter<(String, Value)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(value: i64) -> Value {
+        Value::Number { value }
+    }
+
+    #[test]
+    fn test_keeps_insertion_order() {
+        let mut map: ObjectMap = ["c", "a", "b"].iter().map(|key| (key.to_string(), Value::Null)).collect();
+        assert_eq!(map.insert("a".to_string(), number(1)), Some(Value::Null));
+        assert_eq!(map.keys().collect::<Vec<_>>(), ["c", "a", "b"]);
+
+        assert_eq!(map.remove("c"), Some(Value::Null));
+        map.insert("c".to_string(), number(3));
+        assert_eq!(map.keys().collect::<Vec<_>>(), ["a", "b", "c"]);
+        assert_eq!(map["a"], number(1));
+        assert_eq!(map.get("c"), Some(&number(3)));
+        assert_eq!(map.remove("nope"), None);
+    }
+
+    #[test]
+    fn test_equality_ignores_order() {
+        let forwards = ObjectMap::from([("a".to_string(), number(1)), ("b".to_string(), number(2))]);
+        let mut backwards = ObjectMap::from([("b".to_string(), number(2)), ("a".to_string(), number(1))]);
+        assert_eq!(forwards, backwards);
+
+        backwards.shuffle(&mut rand::thread_rng());
+        assert_eq!(backwards["a"], number(1));
+        backwards.insert("a".to_string(), number(0));
+        assert_ne!(forwards, backwards);
+    }
+}
@@ -0,0 +1,84 @@
+//! # Output Module
+//!
+//! The output module decides where printed text ends up.
+//! By default that's stdout, but hosts and tests can capture it instead.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::output::{CapturedOutput, OutputSink};
+//!
+//! let captured = CapturedOutput::new();
+//! let mut sink = captured.clone();
+//! sink.write("Hello, void!\n");
+//! assert_eq!(captured.contents(), "Hello, void!\n");
+//! ```
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Somewhere for printed text to go (or disappear into).
+pub trait OutputSink: Send {
+    /// Writes text to the sink, exactly as given
+    fn write(&mut self, text: &str);
+}
+
+/// Writes everything to stdout, like a normal language would.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StdoutSink;
+
+impl OutputSink for StdoutSink {
+    fn write(&mut self, text: &str) {
+        let mut stdout = std::io::stdout();
+        let _ = stdout.write_all(text.as_bytes());
+        let _ = stdout.flush();
+    }
+}
+
+/// Appends everything to a file, for programs that would rather not be seen printing.
+#[derive(Debug)]
+pub struct FileSink {
+    /// The file being appended to
+    file: File,
+}
+
+impl FileSink {
+    /// Opens a file for appending, creating it if it doesn't exist yet.
+    pub fn append(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { file })
+    }
+}
+
+impl OutputSink for FileSink {
+    fn write(&mut self, text: &str) {
+        let _ = self.file.write_all(text.as_bytes());
+    }
+}
+
+/// Collects everything written to it, so you can read it back later.
+/// Clones share the same buffer.
+#[derive(Debug, Default, Clone)]
+pub struct CapturedOutput {
+    /// Everything written so far
+    buffer: Arc<Mutex<String>>,
+}
+
+impl CapturedOutput {
+    /// Creates an empty capture buffer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns everything written so far.
+    pub fn contents(&self) -> String {
+        self.buffer.lock().unwrap().clone()
+    }
+}
+
+impl OutputSink for CapturedOutput {
+    fn write(&mut self, text: &str) {
+        self.buffer.lock().unwrap().push_str(text);
+    }
+}
@@ -15,30 +15,223 @@
 //! let ast = parser.parse().expect("Parser failed successfully");
 //! ```
 
-use crate::ast::{BinaryOp, Expression, Literal, Program, Statement};
-use crate::lexer::{Token, TokenKind};
+use crate::ast::{BinaryOp, ErrorBinding, Expression, Literal, Program, Statement};
+use crate::bigint::BigInt;
+use crate::doc::{DocItem, ItemKind};
+use crate::error::{ErrorKind, ErrorReport, Severity};
+use crate::intern::Symbol;
+use crate::lexer::{Span, Token, TokenKind};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use thiserror::Error;
 
+/// Keywords the parser is willing to guess you meant, along with their token kinds.
+const KEYWORDS: [(&str, TokenKind); 28] = [
+    ("mod", TokenKind::Module),
+    ("use", TokenKind::Use),
+    ("print", TokenKind::Print),
+    ("let", TokenKind::Let),
+    ("else", TokenKind::Else),
+    ("loop", TokenKind::Loop),
+    ("for", TokenKind::For),
+    ("save", TokenKind::Save),
+    ("add", TokenKind::Add),
+    ("multiply", TokenKind::Multiply),
+    ("exit", TokenKind::Exit),
+    ("promise", TokenKind::Promise),
+    ("await", TokenKind::Await),
+    ("async", TokenKind::Async),
+    ("try", TokenKind::Try),
+    ("catch", TokenKind::Catch),
+    ("pray", TokenKind::Pray),
+    ("concurrent", TokenKind::Concurrent),
+    ("sudo", TokenKind::Sudo),
+    ("macro", TokenKind::Macro),
+    ("index", TokenKind::Index),
+    ("access", TokenKind::Access),
+    ("equals", TokenKind::Equals),
+    ("lessThan", TokenKind::LessThan),
+    ("true", TokenKind::True),
+    ("false", TokenKind::False),
+    ("null", TokenKind::Null),
+    ("directive", TokenKind::Directive),
+];
+
 /// Errors that might occur during parsing.
 /// These are the only predictable things about the language.
 #[derive(Debug, Error)]
 #[allow(dead_code)]
 pub enum ParseError {
     /// Found a token we weren't expecting (which is all of them)
-    #[error("Unexpected token: {0:?}")]
-    UnexpectedToken(Token),
+    #[error("{}, found '{}' at line {}, column {}", describe_expected(.expected), .found.text, .found.span.line, .found.span.column)]
+    UnexpectedToken {
+        /// The token that showed up
+        found: Token,
+        /// What would have been fine instead (empty when nothing was)
+        expected: Vec<TokenKind>,
+    },
 
     /// Reached the end of input prematurely (or did we?)
-    #[error("Expected token, but got none")]
-    UnexpectedEof,
+    #[error("{}, but the program just stopped at line {}, column {}", describe_expected(.expected), .span.line, .span.column)]
+    UnexpectedEof {
+        /// What would have been fine instead
+        expected: Vec<TokenKind>,
+        /// Where the input ran out
+        span: Span,
+    },
 
     /// Found a string literal that's not quite right
     #[error("Invalid string literal")]
     InvalidStringLiteral,
 
     /// Found a number literal that's more creative than we can handle
-    #[error("Invalid number literal")]
-    InvalidNumberLiteral,
+    #[error("'{}' can't be used as a number: {}, at line {}, column {}", .text, .reason, .span.line, .span.column)]
+    InvalidNumberLiteral {
+        /// The literal, as written
+        text: String,
+        /// Where it was written
+        span: Span,
+        /// Why it won't do
+        reason: &'static str,
+    },
+
+    /// Found a literal too big, or anything nested too deep, to be worth the memory (or the stack)
+    #[error("Program too chonky: {}, at line {}, column {}. It's not you, it's your program.", .reason, .span.line, .span.column)]
+    ProgramTooChonky {
+        /// What was too much
+        reason: String,
+        /// Where the thing that was too much starts
+        span: Span,
+    },
+}
+
+/// How big literals may get, and how deep anything may nest, before the parser refuses to build it.
+/// Keeps a hostile (or just enthusiastic) program from eating all the memory, or the whole stack,
+/// before it even runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParserLimits {
+    /// The most elements a single array, or fields a single object, may have
+    pub max_literal_elements: usize,
+    /// How deeply arrays and objects may be nested inside each other
+    pub max_literal_depth: usize,
+    /// How deeply statements and expressions may be nested inside each other, blocks and calls alike
+    pub max_nesting_depth: usize,
+}
+
+impl Default for ParserLimits {
+    fn default() -> Self {
+        Self {
+            max_literal_elements: 100_000,
+            max_literal_depth: 64,
+            max_nesting_depth: 50,
+        }
+    }
+}
+
+impl ParseError {
+    /// Returns this error's stable code. Parse errors start at 101, well clear of the runtime ones.
+    pub fn code(&self) -> u16 {
+        match self {
+            ParseError::UnexpectedToken { .. } => 101,
+            ParseError::UnexpectedEof { .. } => 102,
+            ParseError::InvalidStringLiteral => 103,
+            ParseError::InvalidNumberLiteral { .. } => 104,
+            ParseError::ProgramTooChonky { .. } => 105,
+        }
+    }
+
+    /// Returns where the error happened, when the parser knows.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            ParseError::UnexpectedToken { found, .. } => Some(found.span),
+            ParseError::UnexpectedEof { span, .. }
+            | ParseError::InvalidNumberLiteral { span, .. }
+            | ParseError::ProgramTooChonky { span, .. } => Some(*span),
+            _ => None,
+        }
+    }
+
+    /// Returns the kinds of token that would have been fine instead.
+    pub fn expected(&self) -> &[TokenKind] {
+        match self {
+            ParseError::UnexpectedToken { expected, .. } | ParseError::UnexpectedEof { expected, .. } => expected,
+            _ => &[],
+        }
+    }
+
+    /// Turns the error into a report that programs can read.
+    pub fn report(&self) -> ErrorReport {
+        ErrorReport {
+            code: self.code(),
+            kind: ErrorKind::Syntax,
+            severity: Severity::Error,
+            message: self.to_string(),
+            span: self.span(),
+            hint: None,
+            fixes: Vec::new(),
+        }
+    }
+}
+
+/// Every kind of token an expression can start with.
+const EXPRESSION_START: &[TokenKind] = &[
+    TokenKind::StringLiteral,
+    TokenKind::NumberLiteral,
+    TokenKind::True,
+    TokenKind::False,
+    TokenKind::Null,
+    TokenKind::FloatLiteral,
+    TokenKind::Identifier,
+    TokenKind::LeftBracket,
+    TokenKind::LeftBrace,
+    TokenKind::Add,
+    TokenKind::Multiply,
+    TokenKind::Index,
+    TokenKind::Access,
+    TokenKind::Equals,
+    TokenKind::LessThan,
+    TokenKind::Promise,
+    TokenKind::Await,
+];
+
+/// How many expected kinds an error lists before it gives up and just counts the rest.
+const MAX_LISTED_EXPECTATIONS: usize = 3;
+
+/// Describes what the parser would have accepted, like "Expected ';' or ')'".
+fn describe_expected(expected: &[TokenKind]) -> String {
+    let mut names: Vec<&str> = expected.iter().map(TokenKind::describe).collect();
+    match names.len() {
+        0 => return "Expected nothing more".to_string(),
+        1 => return format!("Expected {}", names[0]),
+        _ => {},
+    }
+    let last = if names.len() > MAX_LISTED_EXPECTATIONS {
+        let others = names.len() - MAX_LISTED_EXPECTATIONS;
+        names.truncate(MAX_LISTED_EXPECTATIONS);
+        format!("{} other things", others)
+    } else {
+        names.pop().unwrap().to_string()
+    };
+    format!("Expected {} or {}", names.join(", "), last)
+}
+
+/// A typo the parser fixed for you, kept so you can be shamed for it later.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Autocorrection {
+    /// What you actually typed
+    pub typo: String,
+    /// What you obviously meant
+    pub keyword: String,
+}
+
+impl fmt::Display for Autocorrection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "🔔 Shame! You typed `{}` when you clearly meant `{}`. Everyone saw that.",
+            self.typo, self.keyword
+        )
+    }
 }
 
 /// The parser for the Useless Programming Language.
@@ -48,13 +241,86 @@ pub struct Parser {
     tokens: Vec<Token>,
     /// Current position in the token stream
     current: usize,
+    /// Names the program has bound, which are never mistaken for typos
+    known_names: HashSet<Symbol>,
+    /// Every typo quietly fixed so far
+    autocorrections: Vec<Autocorrection>,
+    /// How big literals may get
+    limits: ParserLimits,
+    /// How many arrays and objects deep the parser currently is
+    literal_depth: usize,
+    /// How many statements and expressions deep the parser currently is
+    nesting_depth: usize,
+    /// Whether the nesting limit was hit, which there's no recovering from
+    too_deep: bool,
+    /// Whether statements get wrapped in where they were written
+    record_spans: bool,
+    /// Doc comments, by the position of the token they were written before
+    docs: HashMap<usize, String>,
+    /// Everything worth documenting found so far
+    doc_items: Vec<DocItem>,
+    /// The modules the parser is currently inside, outermost first
+    modules: Vec<Symbol>,
 }
 
 impl Parser {
     /// Creates a new parser from a vector of tokens.
     /// Use at your own risk.
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+        // Doc comments aren't part of the grammar, so they're set aside for whatever comes next
+        let mut docs: HashMap<usize, String> = HashMap::new();
+        let mut kept = Vec::with_capacity(tokens.len());
+        for token in tokens {
+            if token.kind != TokenKind::DocComment {
+                kept.push(token);
+                continue;
+            }
+            let line = token.text.trim_start_matches('/').trim_end();
+            let line = line.strip_prefix(' ').unwrap_or(line);
+            docs.entry(kept.len())
+                .and_modify(|doc| {
+                    doc.push('\n');
+                    doc.push_str(line);
+                })
+                .or_insert_with(|| line.to_string());
+        }
+
+        Self {
+            tokens: kept,
+            current: 0,
+            known_names: HashSet::new(),
+            autocorrections: Vec::new(),
+            limits: ParserLimits::default(),
+            literal_depth: 0,
+            nesting_depth: 0,
+            too_deep: false,
+            record_spans: false,
+            docs,
+            doc_items: Vec::new(),
+            modules: Vec::new(),
+        }
+    }
+
+    /// Returns every function, module and macro parsed so far, and every directive with a doc
+    /// comment. Doc comments only reach the parser from a [`Lexer::with_doc_comments`](crate::lexer::Lexer::with_doc_comments).
+    pub fn doc_items(&self) -> &[DocItem] {
+        &self.doc_items
+    }
+
+    /// Changes how big literals may get.
+    pub fn set_limits(&mut self, limits: ParserLimits) {
+        self.limits = limits;
+    }
+
+    /// Makes every statement remember where it was written, wrapped in `Statement::Located`.
+    /// Debuggers and error messages need this; everyone else gets a smaller tree without it.
+    pub fn set_record_spans(&mut self, record: bool) {
+        self.record_spans = record;
+    }
+
+    /// Returns every misspelled keyword the parser fixed, for public shaming.
+    pub fn autocorrections(&self) -> &[Autocorrection] {
+        &self.autocorrections
     }
 
     /// Attempts to parse a complete program.
@@ -68,9 +334,129 @@ impl Parser {
         Ok(program)
     }
 
+    /// Parses a complete program without stopping at the first syntax error. After each error, the
+    /// parser skips ahead to the end of the statement (the next `;` or `}`) and carries on, so every
+    /// mistake gets pointed out at once. Returns whatever statements did parse, and every error.
+    pub fn parse_with_recovery(&mut self) -> (Program, Vec<ParseError>) {
+        let mut program = Vec::new();
+        let mut errors = Vec::new();
+        while !self.is_at_end() {
+            let start = self.current;
+            match self.parse_statement() {
+                Ok(statement) => program.push(statement),
+                Err(error) => {
+                    errors.push(error);
+                    // Everything after is nested just as deep, and would fail again one level shallower at a time
+                    if self.too_deep {
+                        break;
+                    }
+                    self.synchronize(start);
+                },
+            }
+        }
+        (program, errors)
+    }
+
+    /// Parses the inside of an array or object one level deeper, refusing to go past the depth limit.
+    /// The depth is restored even when parsing fails, so recovery starts from a clean slate.
+    fn nested_literal(
+        &mut self,
+        open: Span,
+        parse: impl FnOnce(&mut Self) -> Result<Expression, ParseError>,
+    ) -> Result<Expression, ParseError> {
+        if self.literal_depth >= self.limits.max_literal_depth {
+            return Err(ParseError::ProgramTooChonky {
+                reason: format!("literals nested more than {} deep", self.limits.max_literal_depth),
+                span: open,
+            });
+        }
+        self.literal_depth += 1;
+        let result = parse(self);
+        self.literal_depth -= 1;
+        result
+    }
+
+    /// Parses something one level deeper, refusing to go past the nesting limit. Like
+    /// [`Parser::nested_literal`], the depth is restored even when parsing fails.
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T, ParseError>) -> Result<T, ParseError> {
+        if self.nesting_depth >= self.limits.max_nesting_depth {
+            let span = self.peek().map(|token| token.span).or_else(|| self.previous().map(|token| token.span)).unwrap_or_default();
+            self.too_deep = true;
+            return Err(ParseError::ProgramTooChonky {
+                reason: format!("blocks and calls nested more than {} deep", self.limits.max_nesting_depth),
+                span,
+            });
+        }
+        self.nesting_depth += 1;
+        let result = parse(self);
+        self.nesting_depth -= 1;
+        result
+    }
+
+    /// Refuses to add another element to a literal that already has as many as it may.
+    fn check_literal_size(&self, count: usize, what: &str, open: Span) -> Result<(), ParseError> {
+        if count >= self.limits.max_literal_elements {
+            return Err(ParseError::ProgramTooChonky {
+                reason: format!("an {} with more than {} elements", what, self.limits.max_literal_elements),
+                span: open,
+            });
+        }
+        Ok(())
+    }
+
+    /// Skips past the rest of a broken statement, always moving at least one token past where it started.
+    fn synchronize(&mut self, start: usize) {
+        if self.current == start {
+            self.advance();
+        }
+        while let Some(token) = self.peek() {
+            let kind = token.kind.clone();
+            self.advance();
+            if matches!(kind, TokenKind::Semicolon | TokenKind::RightBrace) {
+                return;
+            }
+        }
+    }
+
+    /// Parses a lone expression, with an optional trailing semicolon, and nothing after it.
+    pub fn parse_single_expression(&mut self) -> Result<Expression, ParseError> {
+        if self.is_at_end() {
+            return Err(self.unexpected(EXPRESSION_START));
+        }
+        let expression = self.parse_expression()?;
+        if self.peek().map(|t| &t.kind) == Some(&TokenKind::Semicolon) {
+            self.advance(); // consume semicolon
+        }
+        match self.peek() {
+            None => Ok(expression),
+            Some(_) => Err(self.unexpected(&[])),
+        }
+    }
+
+    /// Parses a single statement, and remembers where it was if spans are being recorded.
+    fn parse_statement(&mut self) -> Result<Statement, ParseError> {
+        let start = self.peek().map(|token| token.span);
+        let statement = self.nested(Self::parse_unlocated_statement)?;
+        Ok(self.locate(start, statement))
+    }
+
+    /// Wraps a statement that started at `start` and just finished in `Statement::Located`, if spans are being recorded.
+    fn locate(&self, start: Option<Span>, statement: Statement) -> Statement {
+        match start {
+            Some(start) if self.record_spans => {
+                let end = self.previous().map_or(start.end, |token| token.span.end);
+                Statement::Located { span: Span { end, ..start }, statement: Box::new(statement) }
+            },
+            _ => statement,
+        }
+    }
+
     /// Parses a single statement.
     /// Each statement has an equal chance of doing something unexpected.
-    fn parse_statement(&mut self) -> Result<Statement, ParseError> {
+    fn parse_unlocated_statement(&mut self) -> Result<Statement, ParseError> {
+        let doc = self.docs.remove(&self.current);
+        let line = self.peek().map_or(0, |token| token.span.line);
+
         // Parse attributes that may precede the statement
         let mut attributes = Vec::new();
         while self.peek().map(|t| &t.kind) == Some(&TokenKind::Attribute) {
@@ -80,12 +466,19 @@ impl Parser {
             if let Some(paren_idx) = content.find('(') {
                 let name = content[..paren_idx].to_string();
                 let params = content[paren_idx+1..content.len()-1].to_string();
-                attributes.push((name, Some(params)));
+                // `#[directive(x)]` is just a longer way of writing `#[x]`
+                if name == "directive" {
+                    attributes.push((params, None));
+                } else {
+                    attributes.push((name, Some(params)));
+                }
             } else {
                 attributes.push((content.to_string(), None));
             }
         }
 
+        self.autocorrect_current();
+        let start = self.peek().map(|token| token.span);
         let statement = match self.peek().map(|t| &t.kind) {
             Some(TokenKind::Module) => self.parse_module()?,
             Some(TokenKind::Use) => self.parse_use()?,
@@ -101,41 +494,46 @@ impl Parser {
             Some(TokenKind::Print) => self.parse_print_statement()?,
             Some(TokenKind::If) => self.parse_if_statement()?,
             Some(TokenKind::Loop) => self.parse_loop_statement()?,
+            Some(TokenKind::For) => self.parse_for_in_statement()?,
+            Some(TokenKind::Pray) => self.parse_pray_statement()?,
+            Some(TokenKind::Concurrent) => self.parse_concurrent_statement()?,
+            Some(TokenKind::Macro) => self.parse_macro_definition()?,
+            Some(TokenKind::Sudo) => {
+                self.advance(); // consume sudo
+                let statement = self.parse_statement()?;
+                Statement::Sudo { statement: Box::new(statement) }
+            },
             Some(TokenKind::Save) => {
                 self.advance(); // consume save
-                let filename = match self.advance() {
-                    Some(token) if token.kind == TokenKind::StringLiteral => token.text.trim_matches('"').to_string(),
-                    _ => return Err(ParseError::UnexpectedToken(self.previous().unwrap())),
-                };
+                let token = self.expect(&[TokenKind::StringLiteral])?;
+                let filename = token.text.trim_matches('"').to_string();
                 self.consume(&TokenKind::Semicolon)?;
                 Statement::Save { filename }
             },
             Some(TokenKind::Exit) => {
                 self.advance();  // consume 'exit'
                 self.consume(&TokenKind::LeftParen)?;  // expect (
+                let mut arguments = Vec::new();
+                if self.peek().map(|t| &t.kind) != Some(&TokenKind::RightParen) {
+                    arguments.push(self.parse_expression()?);  // the exit status, if anyone is listening
+                }
                 self.consume(&TokenKind::RightParen)?;  // expect )
                 self.consume(&TokenKind::Semicolon)?;  // expect semicolon
                 Statement::Expression(Expression::FunctionCall {
-                    name: "exit".to_string(),
-                    arguments: vec![],
+                    name: Symbol::intern("exit"),
+                    arguments,
                 })
             },
             Some(TokenKind::Async) => {
                 self.advance(); // consume async
-                let name = match self.advance() {
-                    Some(token) if token.kind == TokenKind::Identifier => token.text,
-                    _ => return Err(ParseError::UnexpectedToken(self.previous().unwrap())),
-                };
+                let token = self.expect(&[TokenKind::Identifier])?;
+                let name = self.bind(token.text);
 
                 self.consume(&TokenKind::LeftParen)?;
                 let mut parameters = Vec::new();
                 while self.peek().map(|t| &t.kind) != Some(&TokenKind::RightParen) {
-                    match self.advance() {
-                        Some(token) if token.kind == TokenKind::Identifier => {
-                            parameters.push(token.text);
-                        },
-                        _ => return Err(ParseError::UnexpectedToken(self.previous().unwrap())),
-                    }
+                    let token = self.expect(&[TokenKind::Identifier])?;
+                    parameters.push(self.bind(token.text));
                     if self.peek().map(|t| &t.kind) == Some(&TokenKind::Comma) {
                         self.advance(); // consume comma
                     }
@@ -161,10 +559,7 @@ impl Parser {
                 self.consume(&TokenKind::RightBrace)?;
 
                 self.consume(&TokenKind::Catch)?;
-                let error_var = match self.advance() {
-                    Some(token) if token.kind == TokenKind::Identifier => token.text,
-                    _ => return Err(ParseError::UnexpectedToken(self.previous().unwrap())),
-                };
+                let error_binding = self.parse_error_binding()?;
 
                 self.consume(&TokenKind::LeftBrace)?;
                 let mut catch_block = Vec::new();
@@ -175,21 +570,22 @@ impl Parser {
 
                 Statement::TryCatch {
                     try_block,
-                    error_var,
+                    error_binding,
                     catch_block,
                 }
             },
             Some(TokenKind::Await) => {
-                self.advance(); // consume await
+                // Parsed like any other await, so `await p ?? fallback;` means the same everywhere
                 let expression = self.parse_expression()?;
                 self.consume(&TokenKind::Semicolon)?;
-                Statement::Await { expression }
+                match expression {
+                    Expression::Await { promise } => Statement::Await { expression: *promise },
+                    expression => Statement::Expression(expression),
+                }
             },
             Some(TokenKind::Identifier) => {
-                let name = match self.advance() {
-                    Some(token) if token.kind == TokenKind::Identifier => token.text,
-                    _ => return Err(ParseError::UnexpectedToken(self.previous().unwrap())),
-                };
+                let token = self.expect(&[TokenKind::Identifier])?;
+                let name = self.bind(token.text);
 
                 // Check if this is a function declaration or call
                 if self.peek().map(|t| &t.kind) == Some(&TokenKind::LeftParen) {
@@ -215,7 +611,7 @@ impl Parser {
                             name,
                             parameters: arguments.into_iter()
                                 .filter_map(|arg| match arg {
-                                    Expression::Identifier(name) => Some(name),
+                                    Expression::Identifier(name) => Some(self.bind(name)),
                                     _ => None,
                                 })
                                 .collect(),
@@ -231,7 +627,7 @@ impl Parser {
                     }
                 } else {
                     // Not a function, treat as expression
-                    let expr = Expression::Identifier(name);
+                    let expr = self.parse_operators(Expression::Identifier(name))?;
                     self.consume(&TokenKind::Semicolon)?;
                     Ok(Statement::Expression(expr))
                 }
@@ -243,26 +639,46 @@ impl Parser {
             }
         };
 
-        // If we have attributes, wrap the statement
+        self.document(&statement, attributes.first().map(|(name, _)| name.as_str()), doc, line);
+
+        // If we have attributes, wrap the statement (which gets its own span, below the attributes)
         if !attributes.is_empty() {
             Ok(Statement::Attributed {
                 name: attributes[0].0.clone(),
-                statement: Box::new(statement)
+                statement: Box::new(self.locate(start, statement))
             })
         } else {
             Ok(statement)
         }
     }
 
+    /// Writes down a statement that's worth documenting, with its doc comment if it had one.
+    fn document(&mut self, statement: &Statement, directive: Option<&str>, doc: Option<String>, line: usize) {
+        let (kind, name, parameters): (ItemKind, &str, &[Symbol]) = match statement {
+            Statement::Module { name, .. } => (ItemKind::Module, name, &[][..]),
+            Statement::Function { name, parameters, .. } => (ItemKind::Function, name, &parameters[..]),
+            Statement::AsyncFunction { name, parameters, .. } => (ItemKind::AsyncFunction, name, &parameters[..]),
+            Statement::Macro { name, parameters, .. } => (ItemKind::Macro, name, &parameters[..]),
+            Statement::Directive { name } if doc.is_some() => (ItemKind::Directive, name, &[][..]),
+            _ => match directive {
+                // Directives are only worth a mention if someone bothered to say why
+                Some(name) if doc.is_some() => {
+                    self.doc_items.push(DocItem { kind: ItemKind::Directive, path: name.to_string(), parameters: Vec::new(), doc, line });
+                    return;
+                },
+                _ => return,
+            },
+        };
+        let path = self.modules.iter().map(|module| module.as_str()).chain([name]).collect::<Vec<_>>().join("::");
+        let parameters = parameters.iter().map(Symbol::to_string).collect();
+        self.doc_items.push(DocItem { kind, path, parameters, doc, line });
+    }
+
     /// Parses a let statement, which might let your variables go on vacation.
     fn parse_let_statement(&mut self) -> Result<Statement, ParseError> {
         self.advance(); // consume 'let'
-        let name = match self.advance() {
-            Some(token) if token.kind == TokenKind::Identifier => token.text,
-            _ => {
-                return Err(ParseError::UnexpectedToken(self.previous().unwrap()));
-            }
-        };
+        let token = self.expect(&[TokenKind::Identifier])?;
+        let name = self.bind(token.text);
 
         self.consume(&TokenKind::Assignment)?;
         let value = self.parse_expression()?;
@@ -284,6 +700,89 @@ impl Parser {
 
     /// Parses an expression, which might evaluate to something entirely different.
     fn parse_expression(&mut self) -> Result<Expression, ParseError> {
+        let primary = self.parse_primary()?;
+        self.parse_operators(primary)
+    }
+
+    /// Applies any `?.`, `[...]` and `??` operators following an already parsed expression.
+    /// `?.` and `[...]` bind tighter than `??`, and `??` chains from left to right.
+    fn parse_operators(&mut self, primary: Expression) -> Result<Expression, ParseError> {
+        let mut expr = self.parse_postfix(primary)?;
+        while self.peek().map(|t| &t.kind) == Some(&TokenKind::NullCoalesce) {
+            self.advance(); // consume ??
+            let right = self.parse_primary()?;
+            let right = self.parse_postfix(right)?;
+            expr = Expression::BinaryOp {
+                op: BinaryOp::NullCoalesce,
+                left: Box::new(expr),
+                right: Box::new(right),
+            };
+        }
+        Ok(expr)
+    }
+
+    /// Parses a chain of `?.key` accesses, `[i]` indexes and `[a..b]` slices following an expression.
+    fn parse_postfix(&mut self, mut expr: Expression) -> Result<Expression, ParseError> {
+        loop {
+            match self.peek().map(|t| &t.kind) {
+                Some(TokenKind::QuestionDot) => expr = self.parse_optional_access(expr)?,
+                Some(TokenKind::LeftBracket) => expr = self.parse_index_or_slice(expr)?,
+                _ => return Ok(expr),
+            }
+        }
+    }
+
+    /// Parses `[i]` (sugar for `index(value, i)`) or `[start..end]` after an expression.
+    fn parse_index_or_slice(&mut self, object: Expression) -> Result<Expression, ParseError> {
+        self.advance(); // consume [
+        let mut start = None;
+        if self.peek().map(|t| &t.kind) != Some(&TokenKind::DotDot) {
+            let index = self.parse_expression()?;
+            if self.peek().map(|t| &t.kind) != Some(&TokenKind::DotDot) {
+                self.consume(&TokenKind::RightBracket)?;
+                return Ok(Expression::BinaryOp {
+                    op: BinaryOp::Index,
+                    left: Box::new(object),
+                    right: Box::new(index),
+                });
+            }
+            start = Some(Box::new(index));
+        }
+
+        self.advance(); // consume ..
+        let end = if self.peek().map(|t| &t.kind) == Some(&TokenKind::RightBracket) {
+            None
+        } else {
+            Some(Box::new(self.parse_expression()?))
+        };
+        self.consume(&TokenKind::RightBracket)?;
+
+        Ok(Expression::Slice {
+            object: Box::new(object),
+            start,
+            end,
+        })
+    }
+
+    /// Parses a single `?.key` access following an expression.
+    fn parse_optional_access(&mut self, object: Expression) -> Result<Expression, ParseError> {
+        self.advance(); // consume ?.
+        let token = self.expect(&[TokenKind::Identifier, TokenKind::StringLiteral])?;
+        let key = token.text.trim_matches('"').to_string();
+        Ok(Expression::OptionalAccess {
+            object: Box::new(object),
+            key: Box::new(Expression::Literal(Literal::String(key))),
+        })
+    }
+
+    /// Parses a single expression without any trailing operators.
+    fn parse_primary(&mut self) -> Result<Expression, ParseError> {
+        self.nested(Self::parse_unnested_primary)
+    }
+
+    /// Parses a single expression without any trailing operators, without counting how deep it is.
+    fn parse_unnested_primary(&mut self) -> Result<Expression, ParseError> {
+        self.autocorrect_current();
         match self.peek().map(|t| &t.kind) {
             Some(TokenKind::StringLiteral) => {
                 let token = self.advance().unwrap();
@@ -292,11 +791,27 @@ impl Parser {
             }
             Some(TokenKind::NumberLiteral) => {
                 let token = self.advance().unwrap();
-                let number = token
-                    .text
-                    .parse::<i64>()
-                    .map_err(|_| ParseError::InvalidNumberLiteral)?;
-                Ok(Expression::Literal(Literal::Number(number)))
+                if let Ok(number) = token.text.parse::<i64>() {
+                    return Ok(Expression::Literal(Literal::Number(number)));
+                }
+                // Too big for an i64, so it gets to be a BigInt
+                let number = BigInt::parse(&token.text).ok_or(ParseError::InvalidNumberLiteral {
+                    reason: "that's not even digits",
+                    span: token.span,
+                    text: token.text,
+                })?;
+                Ok(Expression::Literal(Literal::BigInt(number)))
+            }
+            Some(TokenKind::FloatLiteral) => {
+                let token = self.advance().unwrap();
+                match token.text.parse::<f64>() {
+                    Ok(number) if number.is_finite() => Ok(Expression::Literal(Literal::Float(number))),
+                    _ => Err(ParseError::InvalidNumberLiteral {
+                        reason: "that's more than a float can keep afloat",
+                        span: token.span,
+                        text: token.text,
+                    }),
+                }
             }
             Some(TokenKind::True) => {
                 self.advance();
@@ -327,7 +842,7 @@ impl Parser {
             }
             Some(TokenKind::Identifier) => {
                 let token = self.advance().unwrap();
-                let name = token.text;
+                let name = Symbol::intern(&token.text);
                 if self.peek().map(|t| &t.kind) == Some(&TokenKind::LeftParen) {
                     self.parse_function_call(name)
                 } else {
@@ -335,42 +850,43 @@ impl Parser {
                 }
             }
             Some(TokenKind::LeftBracket) => {
-                self.advance(); // consume [
-                let mut elements = Vec::new();
-
-                while self.peek().map(|t| &t.kind) != Some(&TokenKind::RightBracket) {
-                    elements.push(Box::new(self.parse_expression()?));
-                    if self.peek().map(|t| &t.kind) == Some(&TokenKind::Comma) {
-                        self.advance(); // consume comma
+                let open = self.advance().unwrap().span; // consume [
+                self.nested_literal(open, |parser| {
+                    let mut elements = Vec::new();
+
+                    while parser.peek().map(|t| &t.kind) != Some(&TokenKind::RightBracket) {
+                        parser.check_literal_size(elements.len(), "array", open)?;
+                        elements.push(Box::new(parser.parse_expression()?));
+                        if parser.peek().map(|t| &t.kind) == Some(&TokenKind::Comma) {
+                            parser.advance(); // consume comma
+                        }
                     }
-                }
 
-                self.consume(&TokenKind::RightBracket)?;
-                Ok(Expression::Literal(Literal::Array(elements)))
+                    parser.consume(&TokenKind::RightBracket)?;
+                    Ok(Expression::Literal(Literal::Array(elements)))
+                })
             },
             Some(TokenKind::LeftBrace) => {
-                self.advance(); // consume {
-                let mut pairs = Vec::new();
+                let open = self.advance().unwrap().span; // consume {
+                self.nested_literal(open, |parser| {
+                    let mut pairs = Vec::new();
 
-                while self.peek().map(|t| &t.kind) != Some(&TokenKind::RightBrace) {
-                    let key = match self.advance() {
-                        Some(token) if token.kind == TokenKind::StringLiteral => {
-                            token.text.trim_matches('"').to_string()
-                        },
-                        _ => return Err(ParseError::UnexpectedToken(self.previous().unwrap())),
-                    };
+                    while parser.peek().map(|t| &t.kind) != Some(&TokenKind::RightBrace) {
+                        parser.check_literal_size(pairs.len(), "object", open)?;
+                        let key = parser.expect(&[TokenKind::StringLiteral])?.text.trim_matches('"').to_string();
 
-                    self.consume(&TokenKind::Colon)?;
-                    let value = Box::new(self.parse_expression()?);
-                    pairs.push((key, value));
+                        parser.consume(&TokenKind::Colon)?;
+                        let value = Box::new(parser.parse_expression()?);
+                        pairs.push((key, value));
 
-                    if self.peek().map(|t| &t.kind) == Some(&TokenKind::Comma) {
-                        self.advance(); // consume comma
+                        if parser.peek().map(|t| &t.kind) == Some(&TokenKind::Comma) {
+                            parser.advance(); // consume comma
+                        }
                     }
-                }
 
-                self.consume(&TokenKind::RightBrace)?;
-                Ok(Expression::Literal(Literal::Object(pairs)))
+                    parser.consume(&TokenKind::RightBrace)?;
+                    Ok(Expression::Literal(Literal::Object(pairs)))
+                })
             },
             Some(TokenKind::Null) => {
                 self.advance();
@@ -444,22 +960,25 @@ impl Parser {
             },
             Some(TokenKind::Await) => {
                 self.advance();
-                self.consume(&TokenKind::LeftParen)?;
-                let promise = Box::new(self.parse_expression()?);
-                self.consume(&TokenKind::RightParen)?;
+                // `await(p)` awaits p, while `await p` also takes the `?.` and `[...]` after it
+                let promise = if self.peek().map(|t| &t.kind) == Some(&TokenKind::LeftParen) {
+                    self.advance(); // consume (
+                    let promise = self.parse_expression()?;
+                    self.consume(&TokenKind::RightParen)?;
+                    promise
+                } else {
+                    let operand = self.parse_primary()?;
+                    self.parse_postfix(operand)?
+                };
 
-                Ok(Expression::Await { promise })
+                Ok(Expression::Await { promise: Box::new(promise) })
             },
-            _ => Err(ParseError::UnexpectedToken(
-                self.peek()
-                    .cloned()
-                    .unwrap_or_else(|| Token::new(TokenKind::Whitespace, String::new())),
-            )),
+            _ => Err(self.unexpected(EXPRESSION_START)),
         }
     }
 
     /// Parses a function call that might return null or go for coffee.
-    fn parse_function_call(&mut self, name: String) -> Result<Expression, ParseError> {
+    fn parse_function_call(&mut self, name: Symbol) -> Result<Expression, ParseError> {
         self.consume(&TokenKind::LeftParen)?;
         let mut arguments = Vec::new();
 
@@ -477,18 +996,105 @@ impl Parser {
         Ok(Expression::FunctionCall { name, arguments })
     }
 
+    /// Interns a name the program bound, and remembers it so it's never mistaken for a typo.
+    fn bind(&mut self, name: impl Into<Symbol>) -> Symbol {
+        let symbol = name.into();
+        self.known_names.insert(symbol.clone());
+        symbol
+    }
+
+    /// Checks whether the program has bound a name.
+    fn is_known_name(&self, name: &str) -> bool {
+        Symbol::lookup(name).is_some_and(|symbol| self.known_names.contains(&symbol))
+    }
+
+    /// Replaces the current token with a keyword if it looks like a misspelling of one.
+    /// Short keywords only forgive swapped letters; longer ones forgive one slip of any kind.
+    /// Names the program is declaring right here are the program's to choose, however close they are to a keyword.
+    fn autocorrect_current(&mut self) {
+        let typo = match self.peek() {
+            Some(token) if token.kind == TokenKind::Identifier && !self.is_known_name(&token.text) && !self.declares_current() => {
+                token.text.clone()
+            }
+            _ => return,
+        };
+        if typo.chars().count() < 3 {
+            return;
+        }
+
+        let mut candidates = KEYWORDS.iter().filter(|(keyword, _)| {
+            let distance = edit_distance(&typo, keyword);
+            distance == 1 && (keyword.len() >= 5 || is_transposition(&typo, keyword))
+        });
+        let (keyword, kind) = match (candidates.next(), candidates.next()) {
+            (Some(candidate), None) => candidate.clone(),
+            _ => return, // No match, or too ambiguous to be judgemental about
+        };
+
+        let span = self.tokens[self.current].span;
+        self.tokens[self.current] = Token::new(kind, keyword.to_string()).with_span(span);
+        self.autocorrections.push(Autocorrection {
+            typo,
+            keyword: keyword.to_string(),
+        });
+    }
+
+    /// Checks whether the current token is a name being declared: followed by `=`, or by a
+    /// parameter list and then a body.
+    fn declares_current(&self) -> bool {
+        let kind = |offset: usize| self.tokens.get(self.current + offset).map(|token| &token.kind);
+        match kind(1) {
+            Some(TokenKind::Assignment) => true,
+            Some(TokenKind::LeftParen) => {
+                let mut depth = 0usize;
+                for (offset, token) in self.tokens[self.current + 1..].iter().enumerate() {
+                    match token.kind {
+                        TokenKind::LeftParen => depth += 1,
+                        TokenKind::RightParen if depth == 1 => return kind(offset + 2) == Some(&TokenKind::LeftBrace),
+                        TokenKind::RightParen => depth -= 1,
+                        _ => {},
+                    }
+                }
+                false
+            },
+            _ => false,
+        }
+    }
+
     /// Consumes a token if it matches the expected kind.
     /// Otherwise, returns an error that might make you question your life choices.
     fn consume(&mut self, expected: &TokenKind) -> Result<(), ParseError> {
-        if self.peek().map(|t| &t.kind) == Some(expected) {
-            self.advance();
-            Ok(())
-        } else {
-            Err(ParseError::UnexpectedToken(
-                self.peek()
-                    .cloned()
-                    .unwrap_or_else(|| Token::new(TokenKind::Whitespace, String::new())),
-            ))
+        self.expect(std::slice::from_ref(expected)).map(|_| ())
+    }
+
+    /// Consumes the next token if it's one of the expected kinds, and hands it over.
+    fn expect(&mut self, expected: &[TokenKind]) -> Result<Token, ParseError> {
+        match self.peek() {
+            Some(token) if expected.contains(&token.kind) => Ok(self.advance().unwrap()),
+            _ => Err(self.unexpected(expected)),
+        }
+    }
+
+    /// Builds the error for finding the next token (or the end of the input) where one of
+    /// `expected` should have been.
+    fn unexpected(&self, expected: &[TokenKind]) -> ParseError {
+        match self.peek() {
+            Some(token) => ParseError::UnexpectedToken { found: token.clone(), expected: expected.to_vec() },
+            None => ParseError::UnexpectedEof { expected: expected.to_vec(), span: self.end_span() },
+        }
+    }
+
+    /// Returns an empty span just past the last token, which is where the input ran out.
+    fn end_span(&self) -> Span {
+        match self.tokens.last() {
+            Some(token) => {
+                let (line, column) = match token.text.rfind('\n') {
+                    Some(newline) => (token.span.line + token.text.matches('\n').count(), token.text[newline + 1..].chars().count() + 1),
+                    None => (token.span.line, token.span.column + token.text.chars().count()),
+                };
+                Span { start: token.span.end, end: token.span.end, line, column }
+            },
+            None => Span { start: 0, end: 0, line: 1, column: 1 },
         }
     }
 
@@ -571,23 +1177,127 @@ impl Parser {
         Ok(Statement::Loop { body })
     }
 
+    /// Parses what follows `catch`: either a variable name or `{field, ...}` to destructure the error.
+    fn parse_error_binding(&mut self) -> Result<ErrorBinding, ParseError> {
+        if self.peek().map(|t| &t.kind) != Some(&TokenKind::LeftBrace) {
+            let token = self.expect(&[TokenKind::Identifier, TokenKind::LeftBrace])?;
+            return Ok(ErrorBinding::Name(self.bind(token.text)));
+        }
+
+        self.advance(); // consume {
+        let mut fields = Vec::new();
+        while self.peek().map(|t| &t.kind) != Some(&TokenKind::RightBrace) {
+            let token = self.expect(&[TokenKind::Identifier])?;
+            fields.push(self.bind(token.text));
+            if self.peek().map(|t| &t.kind) == Some(&TokenKind::Comma) {
+                self.advance(); // consume comma
+            }
+        }
+        self.consume(&TokenKind::RightBrace)?;
+        Ok(ErrorBinding::Fields(fields))
+    }
+
+    /// Parses a for-in loop: `for item in things { ... }`.
+    fn parse_for_in_statement(&mut self) -> Result<Statement, ParseError> {
+        self.advance(); // consume 'for'
+        let token = self.expect(&[TokenKind::Identifier])?;
+        let variable = self.bind(token.text);
+        self.consume(&TokenKind::In)?;
+        let iterable = self.parse_expression()?;
+
+        self.consume(&TokenKind::LeftBrace)?;
+        let mut body = Vec::new();
+        while self.peek().map(|t| &t.kind) != Some(&TokenKind::RightBrace) {
+            body.push(self.parse_statement()?);
+        }
+        self.consume(&TokenKind::RightBrace)?;
+
+        Ok(Statement::ForIn { variable, iterable, body })
+    }
+
+    /// Parses a macro definition: `macro name(params) { template }`.
+    /// The template is expanded later, by [`crate::macros::expand`].
+    fn parse_macro_definition(&mut self) -> Result<Statement, ParseError> {
+        self.advance(); // consume 'macro'
+        let token = self.expect(&[TokenKind::Identifier])?;
+        let name = self.bind(token.text);
+
+        self.consume(&TokenKind::LeftParen)?;
+        let mut parameters = Vec::new();
+        while self.peek().map(|t| &t.kind) != Some(&TokenKind::RightParen) {
+            let token = self.expect(&[TokenKind::Identifier])?;
+            parameters.push(self.bind(token.text));
+            if self.peek().map(|t| &t.kind) == Some(&TokenKind::Comma) {
+                self.advance(); // consume comma
+            }
+        }
+        self.consume(&TokenKind::RightParen)?;
+
+        self.consume(&TokenKind::LeftBrace)?;
+        let mut body = Vec::new();
+        while self.peek().map(|t| &t.kind) != Some(&TokenKind::RightBrace) {
+            body.push(self.parse_statement()?);
+        }
+        self.consume(&TokenKind::RightBrace)?;
+
+        Ok(Statement::Macro { name, parameters, body })
+    }
+
+    /// Parses a pray block, optionally with how many attempts it gets: `pray(3) { ... }`.
+    fn parse_pray_statement(&mut self) -> Result<Statement, ParseError> {
+        self.advance(); // consume 'pray'
+
+        let attempts = if self.peek().map(|t| &t.kind) == Some(&TokenKind::LeftParen) {
+            self.advance(); // consume (
+            let token = self.expect(&[TokenKind::NumberLiteral])?;
+            // A BigInt would do for everything else, but nobody has time to pray that many times
+            let attempts = token.text.parse::<usize>().map_err(|_| ParseError::InvalidNumberLiteral {
+                reason: "too many prayers for even the most patient gods",
+                span: token.span,
+                text: token.text.clone(),
+            })?;
+            self.consume(&TokenKind::RightParen)?;
+            Some(attempts)
+        } else {
+            None
+        };
+
+        self.consume(&TokenKind::LeftBrace)?;
+        let mut body = Vec::new();
+        while self.peek().map(|t| &t.kind) != Some(&TokenKind::RightBrace) {
+            body.push(self.parse_statement()?);
+        }
+        self.consume(&TokenKind::RightBrace)?;
+
+        Ok(Statement::Pray { attempts, body })
+    }
+
+    /// Parses a concurrent block, which doesn't end until every task started in it has.
+    fn parse_concurrent_statement(&mut self) -> Result<Statement, ParseError> {
+        self.advance(); // consume 'concurrent'
+        self.consume(&TokenKind::LeftBrace)?;
+
+        let mut body = Vec::new();
+        while self.peek().map(|t| &t.kind) != Some(&TokenKind::RightBrace) {
+            body.push(self.parse_statement()?);
+        }
+        self.consume(&TokenKind::RightBrace)?;
+
+        Ok(Statement::Concurrent { body })
+    }
+
     /// Parses a function declaration
+    #[allow(dead_code)]
     fn parse_function(&mut self) -> Result<Statement, ParseError> {
         self.advance(); // consume 'fn'
-        let name = match self.advance() {
-            Some(token) if token.kind == TokenKind::Identifier => token.text,
-            _ => return Err(ParseError::UnexpectedToken(self.previous().unwrap())),
-        };
+        let token = self.expect(&[TokenKind::Identifier])?;
+        let name = self.bind(token.text);
 
         self.consume(&TokenKind::LeftParen)?;
         let mut parameters = Vec::new();
         while self.peek().map(|t| &t.kind) != Some(&TokenKind::RightParen) {
-            match self.advance() {
-                Some(token) if token.kind == TokenKind::Identifier => {
-                    parameters.push(token.text);
-                },
-                _ => return Err(ParseError::UnexpectedToken(self.previous().unwrap())),
-            }
+            let token = self.expect(&[TokenKind::Identifier])?;
+            parameters.push(self.bind(token.text));
             if self.peek().map(|t| &t.kind) == Some(&TokenKind::Comma) {
                 self.advance(); // consume comma
             }
@@ -607,25 +1317,33 @@ impl Parser {
     /// Parses a module declaration
     fn parse_module(&mut self) -> Result<Statement, ParseError> {
         self.advance(); // consume 'mod'
-        let name = match self.advance() {
-            Some(token) if token.kind == TokenKind::Identifier => token.text,
-            _ => return Err(ParseError::UnexpectedToken(self.previous().unwrap())),
-        };
+        let token = self.expect(&[TokenKind::Identifier])?;
+        let name = self.bind(token.text);
 
         self.consume(&TokenKind::LeftBrace)?;
+        self.modules.push(name.clone());
         let mut body = Vec::new();
         while self.peek().map(|t| &t.kind) != Some(&TokenKind::RightBrace) {
             body.push(self.parse_statement()?);
         }
+        self.modules.pop();
         self.consume(&TokenKind::RightBrace)?;
 
         Ok(Statement::Module { name, body })
     }
 
-    /// Parses a use statement
+    /// Parses a use statement, or `use name from "file.json";` for data
     fn parse_use(&mut self) -> Result<Statement, ParseError> {
         self.advance(); // consume 'use'
         let path = self.parse_use_path()?;
+        let from = self.peek().is_some_and(|t| t.kind == TokenKind::Identifier && t.text == "from");
+        if from && !path.contains("::") {
+            self.advance(); // consume 'from'
+            let file = self.expect(&[TokenKind::StringLiteral])?;
+            self.consume(&TokenKind::Semicolon)?;
+            let name = self.bind(path);
+            return Ok(Statement::UseData { name, path: file.text.trim_matches('"').to_string() });
+        }
         self.consume(&TokenKind::Semicolon)?;
         Ok(Statement::Use { path })
     }
@@ -634,12 +1352,8 @@ impl Parser {
     fn parse_use_path(&mut self) -> Result<String, ParseError> {
         let mut path = Vec::new();
         loop {
-            match self.advance() {
-                Some(token) if token.kind == TokenKind::Identifier => {
-                    path.push(token.text);
-                },
-                _ => return Err(ParseError::UnexpectedToken(self.previous().unwrap())),
-            }
+            let token = self.expect(&[TokenKind::Identifier])?;
+            path.push(token.text);
 
             if self.peek().map(|t| &t.kind) != Some(&TokenKind::DoubleColon) {
                 break;
@@ -650,11 +1364,112 @@ impl Parser {
     }
 }
 
+/// Computes the edit distance between two words, counting swapped neighbours as one edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            d[i][j] = (d[i - 1][j] + 1).min(d[i][j - 1] + 1).min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+/// Checks whether two words differ only by a pair of swapped neighbouring letters.
+fn is_transposition(a: &str, b: &str) -> bool {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len() != b.len() {
+        return false;
+    }
+    let differences: Vec<usize> = (0..a.len()).filter(|&i| a[i] != b[i]).collect();
+    differences.len() == 2
+        && differences[1] == differences[0] + 1
+        && a[differences[0]] == b[differences[1]]
+        && a[differences[1]] == b[differences[0]]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::lexer::Lexer;
 
+    #[test]
+    fn test_errors_say_what_and_where() {
+        let parse = |source: &str| Parser::new(Lexer::new(source).collect()).parse().unwrap_err();
+
+        let error = parse("let x = 1;\nprint(x}");
+        assert_eq!(error.to_string(), "Expected ')', found '}' at line 2, column 8");
+        assert_eq!(error.expected(), [TokenKind::RightParen]);
+        assert_eq!(error.span().map(|span| span.start), Some(18));
+
+        let error = parse("let = 1;");
+        assert_eq!(error.to_string(), "Expected a name, found '=' at line 1, column 5");
+
+        let error = parse("catch {");
+        assert!(error.to_string().starts_with("Expected a string, a number, 'true' or 14 other things, found 'catch'"), "{}", error);
+
+        let error = parse("print(\"hi\")");
+        assert!(matches!(error, ParseError::UnexpectedEof { .. }));
+        assert_eq!(error.to_string(), "Expected ';', but the program just stopped at line 1, column 12");
+    }
+
+    #[test]
+    fn test_chonky_literals_are_rejected() {
+        let parse = |source: &str| {
+            let mut parser = Parser::new(Lexer::new(source).collect());
+            parser.set_limits(ParserLimits { max_literal_elements: 3, max_literal_depth: 2, ..ParserLimits::default() });
+            parser.parse()
+        };
+        assert!(parse("let x = [[1, 2, 3], { \"a\": 1 }];").is_ok());
+        assert!(matches!(parse("let x = [1, 2, 3, 4];"), Err(ParseError::ProgramTooChonky { .. })));
+        assert!(matches!(parse("let x = { \"a\": 1, \"b\": 2, \"c\": 3, \"d\": 4 };"), Err(ParseError::ProgramTooChonky { .. })));
+        assert!(matches!(parse("let x = [[[1]]];"), Err(ParseError::ProgramTooChonky { .. })));
+        // The error points at the literal that went too far, not just at the program in general
+        let error = parse("let x = [\n  [[1]]];").unwrap_err();
+        assert_eq!(error.span().map(|span| (span.line, span.column)), Some((2, 4)));
+
+        let deep = format!("let x = {}{};", "[".repeat(1000), "]".repeat(1000));
+        assert!(matches!(Parser::new(Lexer::new(&deep).collect()).parse(), Err(ParseError::ProgramTooChonky { .. })));
+    }
+
+    #[test]
+    fn test_deep_blocks_and_calls_are_rejected() {
+        let parse = |source: &str| Parser::new(Lexer::new(source).collect()).parse();
+        let loops = |n: usize| format!("{}print(1);{}", "loop { ".repeat(n), " }".repeat(n));
+        let calls = |n: usize| format!("print({}1{});", "add(".repeat(n), ", 1)".repeat(n));
+
+        assert!(parse(&loops(40)).is_ok());
+        assert!(parse(&calls(40)).is_ok());
+        // Far deeper than the stack would ever have survived
+        for deep in [loops(5_000), calls(5_000)] {
+            let error = parse(&deep).unwrap_err();
+            assert!(matches!(&error, ParseError::ProgramTooChonky { reason, .. } if reason.contains("nested")), "{}", error);
+        }
+        // Recovering wouldn't help, it would only hit the limit again one level further in
+        let (_, errors) = Parser::new(Lexer::new(&loops(5_000)).collect()).parse_with_recovery();
+        assert_eq!(errors.len(), 1);
+
+        // Nested just past the limit, on a line of its own, to check the error points at the right place
+        let mut parser = Parser::new(Lexer::new("loop {\n  loop { print(1); }\n}").collect());
+        parser.set_limits(ParserLimits { max_nesting_depth: 2, ..ParserLimits::default() });
+        let error = parser.parse().unwrap_err();
+        assert_eq!(error.span().map(|span| (span.line, span.column)), Some((2, 10)));
+    }
+
     #[test]
     fn test_parse_print_statement() {
         let input = "print(\"Hello, World!\");";
@@ -708,4 +1523,249 @@ mod tests {
             _ => panic!("Expected binary operation"),
         }
     }
+
+    #[test]
+    fn test_parse_pray_statement() {
+        let input = "pray(5) { save \"notes.txt\"; } pray { let x = 1; }";
+        let lexer = Lexer::new(input);
+        let tokens: Vec<Token> = lexer.collect();
+        let mut parser = Parser::new(tokens);
+
+        let program = parser.parse().unwrap();
+        assert_eq!(program.len(), 2);
+
+        match &program[0] {
+            Statement::Pray { attempts: Some(5), body } => assert_eq!(body.len(), 1),
+            _ => panic!("Expected pray statement with 5 attempts"),
+        }
+        match &program[1] {
+            Statement::Pray { attempts: None, .. } => (),
+            _ => panic!("Expected pray statement with default attempts"),
+        }
+    }
+
+    #[test]
+    fn test_parse_huge_number_literal() {
+        let input = "let x = 99999999999999999999;";
+        let lexer = Lexer::new(input);
+        let tokens: Vec<Token> = lexer.collect();
+        let mut parser = Parser::new(tokens);
+
+        let program = parser.parse().unwrap();
+        match &program[0] {
+            Statement::Let { value: Expression::Literal(Literal::BigInt(number)), .. } => {
+                assert_eq!(number.to_string(), "99999999999999999999");
+            },
+            _ => panic!("Expected a BigInt literal"),
+        }
+    }
+
+    #[test]
+    fn test_parse_float_literal() {
+        let tokens: Vec<Token> = Lexer::new("let x = 2.75; let y = z[1..2];").collect();
+        assert_eq!(tokens[3].kind, TokenKind::FloatLiteral);
+        // Ranges still have their dots, and their ends are still whole numbers
+        assert!(tokens.iter().any(|token| token.kind == TokenKind::DotDot));
+
+        let program = Parser::new(tokens).parse().unwrap();
+        assert!(matches!(&program[0], Statement::Let { value: Expression::Literal(Literal::Float(number)), .. } if *number == 2.75));
+        assert_eq!(crate::fmt::format_program(&program[..1]), "let x = 2.75;\n");
+    }
+
+    #[test]
+    fn test_numbers_too_big_to_pray_with_say_where() {
+        let tokens: Vec<Token> = Lexer::new("let x = 1;\npray(99999999999999999999) { let y = 2; }").collect();
+        let error = Parser::new(tokens).parse().unwrap_err();
+        assert!(matches!(&error, ParseError::InvalidNumberLiteral { text, .. } if text == "99999999999999999999"));
+        assert_eq!(error.span(), Some(Span { start: 16, end: 36, line: 2, column: 6 }));
+        assert!(error.to_string().starts_with("'99999999999999999999' can't be used as a number: too many prayers"), "{}", error);
+        assert!(error.to_string().ends_with("at line 2, column 6"), "{}", error);
+    }
+
+    #[test]
+    fn test_parse_index_and_slice_sugar() {
+        let input = "let c = name[0]; let s = name[1..3]; let t = name[..2]; let u = name[2..];";
+        let lexer = Lexer::new(input);
+        let tokens: Vec<Token> = lexer.collect();
+        let mut parser = Parser::new(tokens);
+
+        let program = parser.parse().unwrap();
+        let values: Vec<&Expression> = program
+            .iter()
+            .map(|statement| match statement {
+                Statement::Let { value, .. } => value,
+                _ => panic!("Expected let statements"),
+            })
+            .collect();
+
+        assert!(matches!(values[0], Expression::BinaryOp { op: BinaryOp::Index, .. }));
+        assert!(matches!(values[1], Expression::Slice { start: Some(_), end: Some(_), .. }));
+        assert!(matches!(values[2], Expression::Slice { start: None, end: Some(_), .. }));
+        assert!(matches!(values[3], Expression::Slice { start: Some(_), end: None, .. }));
+    }
+
+    #[test]
+    fn test_parse_for_in_statement() {
+        let input = "for letter in \"abc\" { print(letter); }";
+        let lexer = Lexer::new(input);
+        let tokens: Vec<Token> = lexer.collect();
+        let mut parser = Parser::new(tokens);
+
+        let program = parser.parse().unwrap();
+        match &program[0] {
+            Statement::ForIn { variable, iterable, body } => {
+                assert_eq!(variable, "letter");
+                assert_eq!(iterable, &Expression::Literal(Literal::String("abc".to_string())));
+                assert_eq!(body.len(), 1);
+            },
+            _ => panic!("Expected for-in statement"),
+        }
+        assert!(parser.autocorrections().is_empty(), "The loop variable shouldn't look like a typo");
+    }
+
+    #[test]
+    fn test_parse_with_recovery_finds_every_error() {
+        let input = "let x = ; print(\"fine\"); let = 2; let y = 3;";
+        let mut parser = Parser::new(Lexer::new(input).collect());
+
+        let (program, errors) = parser.parse_with_recovery();
+        assert_eq!(errors.len(), 2, "got {:?}", errors);
+        assert_eq!(program.len(), 2, "got {:?}", program);
+        assert!(matches!(&program[1], Statement::Let { name, .. } if name == "y"));
+    }
+
+    #[test]
+    fn test_parse_catch_destructuring() {
+        let input = "try { save \"x\"; } catch {code, message} { print(message); } try { save \"x\"; } catch e { }";
+        let lexer = Lexer::new(input);
+        let tokens: Vec<Token> = lexer.collect();
+        let mut parser = Parser::new(tokens);
+
+        let program = parser.parse().unwrap();
+        match &program[0] {
+            Statement::TryCatch { error_binding: ErrorBinding::Fields(fields), .. } => {
+                assert_eq!(fields, &vec!["code".to_string(), "message".to_string()]);
+            },
+            _ => panic!("Expected a destructuring catch"),
+        }
+        assert!(matches!(&program[1], Statement::TryCatch { error_binding: ErrorBinding::Name(name), .. } if name == "e"));
+    }
+
+    #[test]
+    fn test_parse_sudo_statement() {
+        let input = "sudo let x = 1;";
+        let lexer = Lexer::new(input);
+        let tokens: Vec<Token> = lexer.collect();
+        let mut parser = Parser::new(tokens);
+
+        let program = parser.parse().unwrap();
+        match &program[0] {
+            Statement::Sudo { statement } => assert!(matches!(**statement, Statement::Let { .. })),
+            _ => panic!("Expected sudo statement"),
+        }
+    }
+
+    #[test]
+    fn test_autocorrects_misspelled_keywords() {
+        let input = "lte x = fasle; pritn(x);";
+        let lexer = Lexer::new(input);
+        let tokens: Vec<Token> = lexer.collect();
+        let mut parser = Parser::new(tokens);
+
+        let program = parser.parse().unwrap();
+        assert!(matches!(
+            program[0],
+            Statement::Let { value: Expression::Literal(Literal::Boolean(false)), .. }
+        ));
+        assert!(matches!(program[1], Statement::Print { .. }));
+
+        let typos: Vec<&str> = parser.autocorrections().iter().map(|a| a.typo.as_str()).collect();
+        assert_eq!(typos, vec!["lte", "fasle", "pritn"]);
+    }
+
+    #[test]
+    fn test_declared_names_are_not_typos() {
+        let mut parser = Parser::new(Lexer::new("printf(x) { print(x); } printf(1); pritn(printf(2));").collect());
+        let program = parser.parse().unwrap();
+
+        assert!(matches!(&program[0], Statement::Function { name, .. } if name == "printf"));
+        assert!(matches!(&program[1], Statement::Expression(Expression::FunctionCall { name, .. }) if name == "printf"));
+        // Calls that only look like a declaration from far away are still fair game
+        assert!(matches!(&program[2], Statement::Print { .. }));
+        let typos: Vec<&str> = parser.autocorrections().iter().map(|a| a.typo.as_str()).collect();
+        assert_eq!(typos, vec!["pritn"]);
+    }
+
+    #[test]
+    fn test_recorded_spans_cover_nested_statements() {
+        let mut parser = Parser::new(Lexer::new("let x = 1;\nloop {\n  print(x);\n}").collect());
+        parser.set_record_spans(true);
+        let program = parser.parse().unwrap();
+
+        let Statement::Located { span, statement } = &program[1] else { panic!("expected a location, got {:?}", program[1]) };
+        assert_eq!((span.line, span.start, span.end), (2, 11, 31));
+        let Statement::Loop { body } = &**statement else { panic!("expected a loop, got {:?}", statement) };
+        assert!(matches!(&body[0], Statement::Located { span, statement } if span.line == 3 && matches!(**statement, Statement::Print { .. })));
+    }
+
+    #[test]
+    fn test_bound_names_are_not_typos() {
+        let input = "let get = 1; let odd = get; print(odd);";
+        let lexer = Lexer::new(input);
+        let tokens: Vec<Token> = lexer.collect();
+        let mut parser = Parser::new(tokens);
+
+        parser.parse().unwrap();
+        assert!(parser.autocorrections().is_empty());
+    }
+
+    #[test]
+    fn test_parse_null_handling_operators() {
+        let input = "let name = user?.profile?.name ?? fallback ?? \"anonymous\";";
+        let lexer = Lexer::new(input);
+        let tokens: Vec<Token> = lexer.collect();
+        let mut parser = Parser::new(tokens);
+
+        let program = parser.parse().unwrap();
+        let value = match &program[0] {
+            Statement::Let { value, .. } => value,
+            _ => panic!("Expected let statement"),
+        };
+
+        // (user?.profile?.name ?? fallback) ?? "anonymous"
+        match value {
+            Expression::BinaryOp { op: BinaryOp::NullCoalesce, left, right } => {
+                assert_eq!(**right, Expression::Literal(Literal::String("anonymous".to_string())));
+                match &**left {
+                    Expression::BinaryOp { op: BinaryOp::NullCoalesce, left, .. } => {
+                        assert!(matches!(**left, Expression::OptionalAccess { .. }));
+                    }
+                    other => panic!("Expected nested ??, got {:?}", other),
+                }
+            }
+            other => panic!("Expected ??, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_await_parses_the_same_everywhere() {
+        let parse = |input: &str| Parser::new(Lexer::new(input).collect()).parse().unwrap();
+        let awaited = |name: &str| Expression::Await { promise: Box::new(Expression::Identifier(name.into())) };
+
+        assert_eq!(parse("let x = await p;"), parse("let x = await(p);"));
+        assert_eq!(parse("await p;"), vec![Statement::Await { expression: Expression::Identifier("p".into()) }]);
+        let expected = Statement::Let {
+            name: "x".into(),
+            value: Expression::BinaryOp { op: BinaryOp::Add, left: Box::new(awaited("a")), right: Box::new(awaited("b")) },
+        };
+        assert_eq!(parse("let x = add(await a, await b);"), vec![expected]);
+        assert_eq!(
+            parse("await p ?? q;"),
+            vec![Statement::Expression(Expression::BinaryOp {
+                op: BinaryOp::NullCoalesce,
+                left: Box::new(awaited("p")),
+                right: Box::new(Expression::Identifier("q".into())),
+            })]
+        );
+    }
 }
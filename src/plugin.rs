@@ -0,0 +1,348 @@
+//! # Plugin Module
+//!
+//! Cursed extensions, without patching the crate. A [`UplPlugin`] fills in a [`Registry`] with
+//! builtins, directives, chaos modulators and value types, and [`Interpreter::load_plugin`] makes
+//! them part of the language:
+//!
+//! - Builtins are called like any other function, after the ones the language ships with.
+//! - Directives run their handler when a statement is marked `#[directive(name)]` (or just `#[name]`),
+//!   instead of a warning.
+//! - Modulators get a say in every chaotic roll, like [`Interpreter::add_modulator`].
+//! - Value types are names `typeOf` reports for objects whose `type` field says so.
+//!
+//! Plugins can't replace builtins, directives or each other. Names are first come, first served.
+//!
+//! With the `dynamic-plugins` feature, plugins can also come from shared libraries, see
+//! `load_library` and `declare_plugin!`.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::plugin::{Registry, UplPlugin};
+//! use useless_lang::{Interpreter, Lexer, Parser, Value};
+//!
+//! struct Shouting;
+//!
+//! impl UplPlugin for Shouting {
+//!     fn name(&self) -> &str {
+//!         "shouting"
+//!     }
+//!
+//!     fn register(&self, registry: &mut Registry) {
+//!         registry.builtin("shout", |_interpreter, arguments| match arguments.as_slice() {
+//!             [Value::String { value }] => Ok(Value::String { value: value.to_uppercase() }),
+//!             _ => Ok(Value::String { value: "WHAT".to_string() }),
+//!         });
+//!     }
+//! }
+//!
+//! let mut interpreter = Interpreter::new();
+//! interpreter.set_completely_normal(true);
+//! interpreter.load_plugin(&Shouting).unwrap();
+//! let program = Parser::new(Lexer::new("let loud = shout(\"hi\");").collect()).parse().unwrap();
+//! interpreter.interpret(program).unwrap();
+//! assert_eq!(interpreter.environment().get("loud"), Some(&Value::String { value: "HI".to_string() }));
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use crate::builtins;
+use crate::chaos::ChaosModulator;
+use crate::intern::{AsSymbol, Symbol};
+use crate::interpreter::{Interpreter, RuntimeError, Value};
+
+/// Directives the interpreter already knows, which plugins don't get to redefine.
+pub(crate) const BUILTIN_DIRECTIVES: &[&str] = &["disable_all_useless_shit", "disable_useless", "experimental", "murphy", "lucky"];
+
+/// Functions the interpreter handles itself, without going through the builtins.
+const RESERVED_FUNCTIONS: &[&str] = &["exit"];
+
+/// A builtin provided by a plugin. Like the language's own, it gets the evaluated arguments.
+pub type PluginBuiltin = Arc<dyn Fn(&mut Interpreter, Vec<Value>) -> Result<Value, RuntimeError> + Send + Sync>;
+
+/// What a plugin directive does when a program asks for it.
+pub type DirectiveHandler = Arc<dyn Fn(&mut Interpreter) -> Result<(), RuntimeError> + Send + Sync>;
+
+/// An extension to the language.
+pub trait UplPlugin {
+    /// The plugin's name. Each name can only be loaded once per interpreter
+    fn name(&self) -> &str;
+
+    /// Adds whatever the plugin provides to the registry
+    fn register(&self, registry: &mut Registry);
+}
+
+/// Errors that stop a plugin from loading. When one happens, nothing from the plugin is loaded.
+#[derive(Debug, Error)]
+pub enum PluginError {
+    #[error("The {0} plugin is already loaded. Once is plenty")]
+    AlreadyLoaded(String),
+
+    #[error("The {plugin} plugin wants to define {name}, but that name is taken. First come, first served")]
+    NameTaken {
+        /// The plugin that came second
+        plugin: String,
+        /// The builtin or directive it wanted
+        name: String,
+    },
+
+    #[error("Couldn't load a plugin from {0}: {1}")]
+    Library(String, String),
+}
+
+/// Everything a plugin wants to add, collected before any of it is installed.
+#[derive(Default)]
+pub struct Registry {
+    builtins: Vec<(String, PluginBuiltin)>,
+    directives: Vec<(String, DirectiveHandler)>,
+    modulators: Vec<Box<dyn ChaosModulator>>,
+    value_types: Vec<String>,
+}
+
+impl Registry {
+    /// Adds a builtin function.
+    pub fn builtin(
+        &mut self,
+        name: &str,
+        builtin: impl Fn(&mut Interpreter, Vec<Value>) -> Result<Value, RuntimeError> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.builtins.push((name.to_string(), Arc::new(builtin)));
+        self
+    }
+
+    /// Adds a directive, and what happens when a program uses it.
+    pub fn directive(
+        &mut self,
+        name: &str,
+        handler: impl Fn(&mut Interpreter) -> Result<(), RuntimeError> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.directives.push((name.to_string(), Arc::new(handler)));
+        self
+    }
+
+    /// Adds a chaos modulator.
+    pub fn modulator(&mut self, modulator: Box<dyn ChaosModulator>) -> &mut Self {
+        self.modulators.push(modulator);
+        self
+    }
+
+    /// Adds a value type: objects whose `type` field is this name are reported as it by `typeOf`.
+    pub fn value_type(&mut self, name: &str) -> &mut Self {
+        self.value_types.push(name.to_string());
+        self
+    }
+}
+
+/// The plugins an interpreter has loaded, and everything they provide.
+#[derive(Default, Clone)]
+pub struct Plugins {
+    names: Vec<String>,
+    /// Kept by symbol, since they're looked up on every call that isn't to a language builtin
+    builtins: HashMap<Symbol, PluginBuiltin>,
+    directives: HashMap<String, DirectiveHandler>,
+    value_types: HashSet<String>,
+}
+
+impl Plugins {
+    /// Returns the names of the loaded plugins, in the order they were loaded.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Finds a plugin builtin.
+    pub fn builtin(&self, name: impl AsSymbol) -> Option<PluginBuiltin> {
+        self.builtins.get(&*name.as_symbol()?).cloned()
+    }
+
+    /// Finds a plugin directive.
+    pub fn directive(&self, name: &str) -> Option<DirectiveHandler> {
+        self.directives.get(name).cloned()
+    }
+
+    /// Checks whether a plugin registered a value type with this name.
+    pub fn is_value_type(&self, name: &str) -> bool {
+        self.value_types.contains(name)
+    }
+
+    /// Installs what a plugin registered, unless any of it clashes with what's already there.
+    /// Returns the modulators, which the interpreter keeps itself.
+    pub(crate) fn install(&mut self, plugin: &str, registry: Registry) -> Result<Vec<Box<dyn ChaosModulator>>, PluginError> {
+        if self.names.iter().any(|name| name == plugin) {
+            return Err(PluginError::AlreadyLoaded(plugin.to_string()));
+        }
+        let taken = |name: &str| PluginError::NameTaken { plugin: plugin.to_string(), name: name.to_string() };
+
+        let mut new_builtins = HashSet::new();
+        for (name, _) in &registry.builtins {
+            let reserved = builtins::lookup(name).is_some() || RESERVED_FUNCTIONS.contains(&name.as_str());
+            if reserved || self.builtins.contains_key(&Symbol::intern(name)) || !new_builtins.insert(name.as_str()) {
+                return Err(taken(name));
+            }
+        }
+        let mut new_directives = HashSet::new();
+        for (name, _) in &registry.directives {
+            if BUILTIN_DIRECTIVES.contains(&name.as_str()) || self.directives.contains_key(name) || !new_directives.insert(name.as_str()) {
+                return Err(taken(name));
+            }
+        }
+
+        self.names.push(plugin.to_string());
+        self.builtins.extend(registry.builtins.into_iter().map(|(name, builtin)| (Symbol::intern(&name), builtin)));
+        self.directives.extend(registry.directives);
+        self.value_types.extend(registry.value_types);
+        Ok(registry.modulators)
+    }
+}
+
+/// Exports a plugin from a shared library, for [`load_library`] to find. The library has to be
+/// built with the same compiler and the same version of this crate as whoever loads it.
+///
+/// ```rust,ignore
+/// useless_lang::declare_plugin!(MyPlugin::default());
+/// ```
+#[cfg(feature = "dynamic-plugins")]
+#[macro_export]
+macro_rules! declare_plugin {
+    ($constructor:expr) => {
+        #[no_mangle]
+        pub extern "C" fn upl_plugin_abi() -> u32 {
+            $crate::plugin::ABI_VERSION
+        }
+
+        #[no_mangle]
+        pub extern "C" fn upl_plugin_create() -> *mut ::std::boxed::Box<dyn $crate::plugin::UplPlugin> {
+            let plugin: ::std::boxed::Box<dyn $crate::plugin::UplPlugin> = ::std::boxed::Box::new($constructor);
+            ::std::boxed::Box::into_raw(::std::boxed::Box::new(plugin))
+        }
+    };
+}
+
+/// Bumped whenever the plugin interface changes, so stale libraries are refused instead of crashing.
+#[cfg(feature = "dynamic-plugins")]
+pub const ABI_VERSION: u32 = 1;
+
+/// Loads a plugin from a shared library made with [`declare_plugin!`](crate::declare_plugin).
+/// The library is never unloaded, since whatever it registered keeps pointing into it.
+///
+/// # Safety
+///
+/// The library runs whatever code it likes as soon as it's loaded, and Rust trait objects have
+/// no stable layout, so it has to be built with the same compiler and version of this crate.
+#[cfg(all(feature = "dynamic-plugins", unix))]
+pub unsafe fn load_library(path: &std::path::Path) -> Result<Box<dyn UplPlugin>, PluginError> {
+    use std::ffi::{CStr, CString};
+    use std::os::unix::ffi::OsStrExt;
+
+    let display = path.display().to_string();
+    let failed = |reason: String| PluginError::Library(display.clone(), reason);
+    let last_error = || {
+        let error = libc::dlerror();
+        if error.is_null() {
+            "no idea why".to_string()
+        } else {
+            CStr::from_ptr(error).to_string_lossy().into_owned()
+        }
+    };
+
+    let filename = CString::new(path.as_os_str().as_bytes()).map_err(|e| failed(e.to_string()))?;
+    let library = libc::dlopen(filename.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL);
+    if library.is_null() {
+        return Err(failed(last_error()));
+    }
+    let symbol = |name: &CStr| {
+        let address = libc::dlsym(library, name.as_ptr());
+        if address.is_null() {
+            Err(failed(format!("it doesn't export {}. Was it made with declare_plugin!?", name.to_string_lossy())))
+        } else {
+            Ok(address)
+        }
+    };
+
+    let abi: extern "C" fn() -> u32 = std::mem::transmute(symbol(c"upl_plugin_abi")?);
+    if abi() != ABI_VERSION {
+        return Err(failed(format!("it was built for plugin ABI {}, but this is ABI {}", abi(), ABI_VERSION)));
+    }
+    let create: extern "C" fn() -> *mut Box<dyn UplPlugin> = std::mem::transmute(symbol(c"upl_plugin_create")?);
+    Ok(*Box::from_raw(create()))
+}
+
+/// Shared libraries only load on Unix, for now.
+#[cfg(all(feature = "dynamic-plugins", not(unix)))]
+pub unsafe fn load_library(path: &std::path::Path) -> Result<Box<dyn UplPlugin>, PluginError> {
+    Err(PluginError::Library(path.display().to_string(), "plugins only load from shared libraries on Unix".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chaos::{ChaosContext, ChaosKind};
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    struct Cursed;
+
+    struct NoChaos;
+
+    impl ChaosModulator for NoChaos {
+        fn modulate(&self, _kind: ChaosKind, _probability: f64, _context: &ChaosContext) -> f64 {
+            0.0
+        }
+    }
+
+    impl UplPlugin for Cursed {
+        fn name(&self) -> &str {
+            "cursed"
+        }
+
+        fn register(&self, registry: &mut Registry) {
+            registry
+                .builtin("answer", |_interpreter, _arguments| Ok(Value::Number { value: 42 }))
+                .directive("calm", |interpreter| {
+                    interpreter.set_completely_normal(true);
+                    Ok(())
+                })
+                .modulator(Box::new(NoChaos))
+                .value_type("curse");
+        }
+    }
+
+    #[test]
+    fn test_plugins_extend_the_language() {
+        let mut interpreter = Interpreter::new();
+        interpreter.load_plugin(&Cursed).unwrap();
+        assert_eq!(interpreter.plugins().names(), ["cursed"]);
+
+        let source = "#[calm] let x = answer(); let t = typeOf({ \"type\": \"curse\" });";
+        let program = Parser::new(Lexer::new(source).collect()).parse().unwrap();
+        interpreter.interpret(program).unwrap();
+        assert!(interpreter.behaves_normally(), "the directive calmed things down");
+        assert_eq!(interpreter.environment().get("x"), Some(&Value::Number { value: 42 }));
+        assert_eq!(interpreter.environment().get("t"), Some(&Value::String { value: "curse".to_string() }));
+    }
+
+    #[test]
+    fn test_plugins_cant_take_names() {
+        struct Impostor(&'static str);
+
+        impl UplPlugin for Impostor {
+            fn name(&self) -> &str {
+                "impostor"
+            }
+
+            fn register(&self, registry: &mut Registry) {
+                registry.builtin(self.0, |_interpreter, _arguments| Ok(Value::Null));
+            }
+        }
+
+        let mut interpreter = Interpreter::new();
+        interpreter.load_plugin(&Cursed).unwrap();
+        assert!(matches!(interpreter.load_plugin(&Cursed), Err(PluginError::AlreadyLoaded(_))));
+        for name in ["len", "exit", "answer"] {
+            let result = interpreter.load_plugin(&Impostor(name));
+            assert!(matches!(result, Err(PluginError::NameTaken { name: taken, .. }) if taken == name));
+        }
+        assert_eq!(interpreter.plugins().names(), ["cursed"], "failed plugins leave nothing behind");
+    }
+}
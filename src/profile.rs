@@ -0,0 +1,263 @@
+//! # Profile Module
+//!
+//! Where the time went. A profiling interpreter times every statement and expression it
+//! evaluates, and adds it all up per piece of code, so it's plain to see that most of the runtime
+//! went to `promise()` napping rather than to anything the program actually did.
+//!
+//! Each node gets two times: its own (what it spent, minus what the code inside it spent) and its
+//! total (everything, inside included). Own times add up to the whole run, so they're what the
+//! report is sorted by. Recursion doesn't count twice towards a total.
+//!
+//! Lines only come with statements that know where they were written, so programs need to be
+//! parsed with [`Parser::set_record_spans`](crate::parser::Parser::set_record_spans).
+//! `useless-lang --profile` prints the report on stderr once the program is done.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::profile::NodeKind;
+//! use useless_lang::{Config, Interpreter, Lexer, Parser};
+//!
+//! let mut parser = Parser::new(Lexer::new("let x = 1;\nlet y = await promise(x);").collect());
+//! parser.set_record_spans(true);
+//! let program = parser.parse().unwrap();
+//!
+//! let mut interpreter = Interpreter::with_config(Config { chaos_level: 0, ..Config::default() });
+//! interpreter.set_profiling(true);
+//! interpreter.interpret(program).unwrap();
+//! let profile = interpreter.profile().unwrap();
+//! let slowest = &profile.nodes()[0];
+//! assert_eq!((slowest.kind, slowest.line, slowest.code.as_str()), (NodeKind::Expression, Some(2), "promise(x)"));
+//! println!("{}", profile);
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::json;
+
+/// How many nodes the human report lists before it stops caring.
+const REPORT_LIMIT: usize = 20;
+
+/// The most characters of code the human report shows for a node.
+const REPORT_CODE_WIDTH: usize = 48;
+
+/// What kind of node was timed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeKind {
+    /// A statement, timed from start to finish
+    Statement,
+    /// An expression, timed from start to value
+    Expression,
+}
+
+impl NodeKind {
+    /// The name the JSON report uses.
+    pub fn name(self) -> &'static str {
+        match self {
+            NodeKind::Statement => "statement",
+            NodeKind::Expression => "expression",
+        }
+    }
+}
+
+/// Everything one piece of code cost.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfiledNode {
+    /// Whether it's a statement or an expression
+    pub kind: NodeKind,
+    /// The line of the innermost statement it's in, if the parser was keeping track
+    pub line: Option<usize>,
+    /// The code, formatted (just the first line, for statements with blocks)
+    pub code: String,
+    /// How many times it ran
+    pub calls: usize,
+    /// The time spent in it, not counting the nodes inside it
+    pub own: Duration,
+    /// The time spent in it, everything inside included
+    pub total: Duration,
+}
+
+/// The time a whole run took, node by node, slowest first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    /// Every node that ran, by own time, slowest first
+    nodes: Vec<ProfiledNode>,
+    /// How long profiling has been going on
+    elapsed: Duration,
+}
+
+impl Profile {
+    /// Every node that ran, by own time, slowest first.
+    pub fn nodes(&self) -> &[ProfiledNode] {
+        &self.nodes
+    }
+
+    /// How long profiling has been going on.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// The report as JSON, times in milliseconds.
+    pub fn to_json(&self) -> String {
+        let nodes: Vec<String> = self
+            .nodes
+            .iter()
+            .map(|node| {
+                format!(
+                    "{{\"kind\":{},\"line\":{},\"code\":{},\"calls\":{},\"own_ms\":{},\"total_ms\":{}}}",
+                    json::quote(node.kind.name()),
+                    node.line.map_or_else(|| "null".to_string(), |line| line.to_string()),
+                    json::quote(&node.code),
+                    node.calls,
+                    millis(node.own),
+                    millis(node.total)
+                )
+            })
+            .collect();
+        format!("{{\"elapsed_ms\":{},\"nodes\":[{}]}}", millis(self.elapsed), nodes.join(","))
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "⏱️  Profile report ({:.1}ms)", millis(self.elapsed))?;
+        if self.nodes.is_empty() {
+            return write!(f, "\n  Nothing ran. Fastest program ever written.");
+        }
+        write!(f, "\n  {:>6}  {:>10}  {:>10}  {:>6}  {:>5}  code", "own%", "own ms", "total ms", "calls", "line")?;
+        let elapsed = self.elapsed.as_secs_f64().max(f64::MIN_POSITIVE);
+        for node in self.nodes.iter().take(REPORT_LIMIT) {
+            let mut code: String = node.code.chars().take(REPORT_CODE_WIDTH).collect();
+            if code.len() < node.code.len() {
+                code.push('…');
+            }
+            write!(
+                f,
+                "\n  {:>5.1}%  {:>10.3}  {:>10.3}  {:>6}  {:>5}  {}{}",
+                node.own.as_secs_f64() / elapsed * 100.0,
+                millis(node.own),
+                millis(node.total),
+                node.calls,
+                node.line.map_or_else(|| "?".to_string(), |line| line.to_string()),
+                if node.kind == NodeKind::Statement { "" } else { "  ↳ " },
+                code
+            )?;
+        }
+        if self.nodes.len() > REPORT_LIMIT {
+            write!(f, "\n  ...and {} more that barely mattered", self.nodes.len() - REPORT_LIMIT)?;
+        }
+        Ok(())
+    }
+}
+
+/// A duration in fractional milliseconds.
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// A node that has started and hasn't finished yet.
+struct Open {
+    /// Where its costs go in the profiler's list
+    index: usize,
+    /// When it started
+    started: Instant,
+    /// The time that belongs to something else: the nodes inside it, and the profiler itself
+    elsewhere: Duration,
+}
+
+/// A profiler at work, keeping track of what's running inside what.
+pub(crate) struct Profiler {
+    /// When profiling started
+    started: Instant,
+    /// Every node that has run, in the order they first ran
+    nodes: Vec<ProfiledNode>,
+    /// Where each node is in `nodes`, and how many times it's open right now (recursion)
+    index: HashMap<(NodeKind, Option<usize>, String), (usize, usize)>,
+    /// The nodes that haven't finished, innermost last
+    open: Vec<Open>,
+}
+
+impl Profiler {
+    /// Starts the clock.
+    pub(crate) fn new() -> Self {
+        Self { started: Instant::now(), nodes: Vec::new(), index: HashMap::new(), open: Vec::new() }
+    }
+
+    /// Notes that a node is starting. `began` is when the interpreter started getting it ready
+    /// (formatting its code, mostly), which is the profiler's time rather than the node's.
+    pub(crate) fn enter(&mut self, kind: NodeKind, line: Option<usize>, code: String, began: Instant) {
+        let key = (kind, line, code);
+        let index = match self.index.get_mut(&key) {
+            Some((index, depth)) => {
+                *depth += 1;
+                *index
+            },
+            None => {
+                let (kind, line, code) = key.clone();
+                self.nodes.push(ProfiledNode { kind, line, code, calls: 0, own: Duration::ZERO, total: Duration::ZERO });
+                self.index.insert(key, (self.nodes.len() - 1, 1));
+                self.nodes.len() - 1
+            },
+        };
+        let started = Instant::now();
+        if let Some(parent) = self.open.last_mut() {
+            parent.elsewhere += started - began;
+        }
+        self.open.push(Open { index, started, elsewhere: Duration::ZERO });
+    }
+
+    /// Notes that the innermost node is done, and charges it for its time.
+    pub(crate) fn exit(&mut self) {
+        let Some(open) = self.open.pop() else { return };
+        let spent = open.started.elapsed();
+        if let Some(parent) = self.open.last_mut() {
+            parent.elsewhere += spent;
+        }
+        let node = &mut self.nodes[open.index];
+        node.calls += 1;
+        node.own += spent.saturating_sub(open.elsewhere);
+        let key = (node.kind, node.line, node.code.clone());
+        if let Some((_, depth)) = self.index.get_mut(&key) {
+            *depth -= 1;
+            // Only the outermost call of a recursive node counts towards its total
+            if *depth == 0 {
+                node.total += spent;
+            }
+        }
+    }
+
+    /// Everything so far, slowest first.
+    pub(crate) fn profile(&self) -> Profile {
+        let mut nodes = self.nodes.clone();
+        nodes.sort_by(|a, b| b.own.cmp(&a.own).then(b.total.cmp(&a.total)));
+        Profile { nodes, elapsed: self.started.elapsed() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_is_charged_to_the_innermost_node() {
+        let mut profiler = Profiler::new();
+        profiler.enter(NodeKind::Statement, Some(1), "recurse();".to_string(), Instant::now());
+        profiler.enter(NodeKind::Statement, Some(1), "recurse();".to_string(), Instant::now());
+        profiler.enter(NodeKind::Expression, Some(1), "promise(1, 20)".to_string(), Instant::now());
+        std::thread::sleep(Duration::from_millis(20));
+        profiler.exit();
+        profiler.exit();
+        profiler.exit();
+
+        let profile = profiler.profile();
+        let [nap, statement] = profile.nodes() else { panic!("{:?}", profile) };
+        assert_eq!((nap.code.as_str(), nap.calls), ("promise(1, 20)", 1));
+        assert!(nap.own >= Duration::from_millis(20));
+        assert_eq!(statement.calls, 2);
+        assert!(statement.own < nap.own);
+        // The recursive call is inside the outer one, so it doesn't get counted again
+        assert!(statement.total < nap.own * 2);
+        assert!(profile.to_json().contains("\"code\":\"promise(1, 20)\",\"calls\":1"));
+    }
+}
@@ -0,0 +1,487 @@
+//! # Regex Module
+//!
+//! Regular expressions, for the `matches`, `findAll` and `replaceRegex` builtins.
+//! Patterns are parsed by `regex-syntax` and matched by a small backtracking matcher,
+//! which is exactly as fast as you'd expect. Only compiled with the `regex` feature.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::regex::Regex;
+//!
+//! let regex = Regex::new(r"(?P<who>\w+)@(\d+)").unwrap();
+//! assert!(regex.is_match("send coffee@9").unwrap());
+//!
+//! let found = regex.find_all("a@1 b@22").unwrap();
+//! assert_eq!(found[1].text(0), Some("b@22"));
+//! assert_eq!(found[1].named("who"), Some("b"));
+//! assert_eq!(regex.replace_all("a@1", "$2:${who}").unwrap(), "1:a");
+//! ```
+
+use regex_syntax::hir::{Anchor, Class, GroupKind, Hir, HirKind, Literal, RepetitionKind, RepetitionRange, WordBoundary};
+use thiserror::Error;
+
+/// How many steps a single search may take before the matcher gives up on life
+const MAX_STEPS: usize = 1_000_000;
+
+/// How deeply the matcher may nest before it admits the pattern is too ambitious
+const MAX_DEPTH: usize = 1_000;
+
+/// Everything that can go wrong with a regular expression.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum RegexError {
+    #[error("That pattern is irregular: {0}")]
+    Invalid(String),
+
+    #[error("That pattern took too long to think about, so it stopped thinking")]
+    TooMuchBacktracking,
+}
+
+/// Where each capture group started and ended, in characters
+type Slots = Vec<Option<(usize, usize)>>;
+
+/// A compiled regular expression.
+#[derive(Debug, Clone)]
+pub struct Regex {
+    /// The parsed pattern
+    hir: Hir,
+    /// The name of every capture group, indexed by group number (group 0 is the whole match)
+    names: Vec<Option<String>>,
+}
+
+/// One match, with everything its capture groups caught.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Captures {
+    /// The text of every group, indexed by group number (group 0 is the whole match)
+    groups: Vec<Option<String>>,
+    /// The name of every group, if it has one
+    names: Vec<Option<String>>,
+    /// Where the match starts, in characters
+    start: usize,
+    /// Where the match ends, in characters
+    end: usize,
+}
+
+impl Captures {
+    /// Returns the text a group caught, if it caught anything.
+    pub fn text(&self, index: usize) -> Option<&str> {
+        self.groups.get(index)?.as_deref()
+    }
+
+    /// Returns the text a named group caught, if it caught anything.
+    pub fn named(&self, name: &str) -> Option<&str> {
+        let index = self.names.iter().position(|group| group.as_deref() == Some(name))?;
+        self.text(index)
+    }
+
+    /// Returns the text of every group, starting with the whole match.
+    pub fn groups(&self) -> &[Option<String>] {
+        &self.groups
+    }
+
+    /// Returns the name of every group, starting with the (nameless) whole match.
+    pub fn names(&self) -> &[Option<String>] {
+        &self.names
+    }
+
+    /// Returns where the match starts, in characters.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Returns where the match ends, in characters.
+    pub fn end(&self) -> usize {
+        self.end
+    }
+}
+
+impl Regex {
+    /// Compiles a pattern.
+    pub fn new(pattern: &str) -> Result<Self, RegexError> {
+        let hir = regex_syntax::Parser::new()
+            .parse(pattern)
+            .map_err(|error| RegexError::Invalid(error.to_string()))?;
+        let mut names = vec![None];
+        collect_names(&hir, &mut names);
+        Ok(Self { hir, names })
+    }
+
+    /// Checks whether the pattern matches anywhere in the text.
+    pub fn is_match(&self, text: &str) -> Result<bool, RegexError> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut matcher = Matcher::new(&chars);
+        Ok(self.search(&mut matcher, 0)?.is_some())
+    }
+
+    /// Finds every non-overlapping match in the text.
+    pub fn find_all(&self, text: &str) -> Result<Vec<Captures>, RegexError> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut matcher = Matcher::new(&chars);
+        let mut found = Vec::new();
+        let mut from = 0;
+
+        while from <= chars.len() {
+            let Some(slots) = self.search(&mut matcher, from)? else {
+                break;
+            };
+            let (start, end) = slots[0].expect("the whole match is always recorded");
+            // An empty match has to move along, or we'd find it forever
+            from = if end > start { end } else { end + 1 };
+            found.push(Captures {
+                groups: slots.iter().map(|slot| slot.map(|(start, end)| chars[start..end].iter().collect())).collect(),
+                names: self.names.clone(),
+                start,
+                end,
+            });
+        }
+        Ok(found)
+    }
+
+    /// Replaces every match. `$1` and `${name}` in the replacement refer to capture groups, `$$` is a dollar.
+    pub fn replace_all(&self, text: &str, replacement: &str) -> Result<String, RegexError> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut replaced = String::with_capacity(text.len());
+        let mut last = 0;
+
+        for captures in self.find_all(text)? {
+            replaced.extend(&chars[last..captures.start]);
+            expand(replacement, &captures, &mut replaced);
+            last = captures.end;
+        }
+        replaced.extend(&chars[last..]);
+        Ok(replaced)
+    }
+
+    /// Finds the leftmost match starting at or after a position.
+    fn search(&self, matcher: &mut Matcher, from: usize) -> Result<Option<Slots>, RegexError> {
+        for start in from..=matcher.chars.len() {
+            let mut slots = vec![None; self.names.len()];
+            let found = matcher.at(&self.hir, start, &mut slots, &mut |_, end, slots| {
+                slots[0] = Some((start, end));
+                true
+            });
+            if matcher.exhausted {
+                return Err(RegexError::TooMuchBacktracking);
+            }
+            if found {
+                return Ok(Some(slots));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Escapes a pattern so every character in it means exactly itself.
+pub fn escape(text: &str) -> String {
+    regex_syntax::escape(text)
+}
+
+/// Writes down the name of every capture group in a pattern.
+fn collect_names(hir: &Hir, names: &mut Vec<Option<String>>) {
+    match hir.kind() {
+        HirKind::Group(group) => {
+            let (index, name) = match &group.kind {
+                GroupKind::CaptureIndex(index) => (Some(*index as usize), None),
+                GroupKind::CaptureName { name, index } => (Some(*index as usize), Some(name.clone())),
+                GroupKind::NonCapturing => (None, None),
+            };
+            if let Some(index) = index {
+                if names.len() <= index {
+                    names.resize(index + 1, None);
+                }
+                names[index] = name;
+            }
+            collect_names(&group.hir, names);
+        },
+        HirKind::Repetition(repetition) => collect_names(&repetition.hir, names),
+        HirKind::Concat(items) | HirKind::Alternation(items) => {
+            for item in items {
+                collect_names(item, names);
+            }
+        },
+        _ => (),
+    }
+}
+
+/// Writes a replacement, with its `$` references filled in.
+fn expand(replacement: &str, captures: &Captures, out: &mut String) {
+    let mut rest = replacement;
+    while let Some(dollar) = rest.find('$') {
+        out.push_str(&rest[..dollar]);
+        rest = &rest[dollar + 1..];
+
+        if let Some(after) = rest.strip_prefix('$') {
+            out.push('$');
+            rest = after;
+        } else if let Some((name, after)) = rest.strip_prefix('{').and_then(|inner| inner.split_once('}')) {
+            let text = name.parse().ok().map_or_else(|| captures.named(name), |index| captures.text(index));
+            out.push_str(text.unwrap_or(""));
+            rest = after;
+        } else {
+            let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+            match rest[..digits].parse() {
+                Ok(index) => out.push_str(captures.text(index).unwrap_or("")),
+                Err(_) => out.push('$'),
+            }
+            rest = &rest[digits..];
+        }
+    }
+    out.push_str(rest);
+}
+
+/// Returns the minimum and (optional) maximum number of times a repetition repeats.
+fn bounds(kind: &RepetitionKind) -> (u32, Option<u32>) {
+    match kind {
+        RepetitionKind::ZeroOrOne => (0, Some(1)),
+        RepetitionKind::ZeroOrMore => (0, None),
+        RepetitionKind::OneOrMore => (1, None),
+        RepetitionKind::Range(RepetitionRange::Exactly(n)) => (*n, Some(*n)),
+        RepetitionKind::Range(RepetitionRange::AtLeast(n)) => (*n, None),
+        RepetitionKind::Range(RepetitionRange::Bounded(min, max)) => (*min, Some(*max)),
+    }
+}
+
+/// What to do after part of a pattern matched: gets the position it matched up to
+type Next<'k, 'c> = &'k mut dyn FnMut(&mut Matcher<'c>, usize, &mut Slots) -> bool;
+
+/// A backtracking matcher, one character at a time.
+struct Matcher<'c> {
+    /// The text being searched
+    chars: &'c [char],
+    /// How many steps have been taken so far
+    steps: usize,
+    /// How deeply nested the current attempt is
+    depth: usize,
+    /// Whether the step or depth budget ran out
+    exhausted: bool,
+}
+
+impl<'c> Matcher<'c> {
+    fn new(chars: &'c [char]) -> Self {
+        Self { chars, steps: 0, depth: 0, exhausted: false }
+    }
+
+    /// Counts a step, and returns whether the budget has run out.
+    fn tick(&mut self) -> bool {
+        self.steps += 1;
+        if self.steps > MAX_STEPS || self.depth > MAX_DEPTH {
+            self.exhausted = true;
+        }
+        self.exhausted
+    }
+
+    /// Tries to match part of a pattern at a position, then whatever comes next.
+    fn at(&mut self, hir: &Hir, pos: usize, slots: &mut Slots, next: Next<'_, 'c>) -> bool {
+        if self.tick() {
+            return false;
+        }
+        self.depth += 1;
+        let matched = self.step(hir, pos, slots, next);
+        self.depth -= 1;
+        matched
+    }
+
+    fn step(&mut self, hir: &Hir, pos: usize, slots: &mut Slots, next: Next<'_, 'c>) -> bool {
+        match hir.kind() {
+            HirKind::Empty => next(self, pos, slots),
+            HirKind::Literal(_) | HirKind::Class(_) => match self.single(hir, pos) {
+                Some(end) => next(self, end, slots),
+                None => false,
+            },
+            HirKind::Anchor(anchor) => self.anchored(anchor, pos) && next(self, pos, slots),
+            HirKind::WordBoundary(boundary) => self.at_boundary(boundary, pos) && next(self, pos, slots),
+            HirKind::Group(group) => match &group.kind {
+                GroupKind::NonCapturing => self.at(&group.hir, pos, slots, next),
+                GroupKind::CaptureIndex(index) | GroupKind::CaptureName { index, .. } => {
+                    let index = *index as usize;
+                    self.at(&group.hir, pos, slots, &mut |matcher, end, slots| {
+                        let previous = slots[index].replace((pos, end));
+                        if next(matcher, end, slots) {
+                            return true;
+                        }
+                        slots[index] = previous;
+                        false
+                    })
+                },
+            },
+            HirKind::Concat(items) => self.sequence(items, pos, slots, next),
+            HirKind::Alternation(options) => {
+                for option in options {
+                    if self.at(option, pos, slots, next) {
+                        return true;
+                    }
+                }
+                false
+            },
+            HirKind::Repetition(repetition) => {
+                let (min, max) = bounds(&repetition.kind);
+                self.repeat(&repetition.hir, repetition.greedy, min, max, pos, slots, next)
+            },
+        }
+    }
+
+    /// Matches a single literal or class, returning the position after it.
+    fn single(&self, hir: &Hir, pos: usize) -> Option<usize> {
+        let c = *self.chars.get(pos)?;
+        let hit = match hir.kind() {
+            HirKind::Literal(Literal::Unicode(literal)) => c == *literal,
+            HirKind::Literal(Literal::Byte(byte)) => c as u32 == *byte as u32,
+            HirKind::Class(Class::Unicode(class)) => class.ranges().iter().any(|range| range.start() <= c && c <= range.end()),
+            HirKind::Class(Class::Bytes(class)) => {
+                class.ranges().iter().any(|range| (range.start() as u32..=range.end() as u32).contains(&(c as u32)))
+            },
+            _ => false,
+        };
+        hit.then_some(pos + 1)
+    }
+
+    fn anchored(&self, anchor: &Anchor, pos: usize) -> bool {
+        match anchor {
+            Anchor::StartText => pos == 0,
+            Anchor::EndText => pos == self.chars.len(),
+            Anchor::StartLine => pos == 0 || self.chars[pos - 1] == '\n',
+            Anchor::EndLine => pos == self.chars.len() || self.chars[pos] == '\n',
+        }
+    }
+
+    fn at_boundary(&self, boundary: &WordBoundary, pos: usize) -> bool {
+        let ascii = matches!(boundary, WordBoundary::Ascii | WordBoundary::AsciiNegate);
+        let is_word = |c: &char| if ascii { c.is_ascii_alphanumeric() || *c == '_' } else { c.is_alphanumeric() || *c == '_' };
+        let before = pos > 0 && is_word(&self.chars[pos - 1]);
+        let after = self.chars.get(pos).is_some_and(is_word);
+        let negated = matches!(boundary, WordBoundary::UnicodeNegate | WordBoundary::AsciiNegate);
+        (before != after) != negated
+    }
+
+    /// Matches a list of patterns one after the other.
+    fn sequence(&mut self, items: &[Hir], pos: usize, slots: &mut Slots, next: Next<'_, 'c>) -> bool {
+        match items.split_first() {
+            None => next(self, pos, slots),
+            Some((first, rest)) => self.at(first, pos, slots, &mut |matcher, end, slots| matcher.sequence(rest, end, slots, next)),
+        }
+    }
+
+    /// Matches a pattern between `min` and `max` more times.
+    #[allow(clippy::too_many_arguments)]
+    fn repeat(
+        &mut self,
+        hir: &Hir,
+        greedy: bool,
+        min: u32,
+        max: Option<u32>,
+        pos: usize,
+        slots: &mut Slots,
+        next: Next<'_, 'c>,
+    ) -> bool {
+        if matches!(hir.kind(), HirKind::Literal(_) | HirKind::Class(_)) {
+            return self.repeat_single(hir, greedy, min, max, pos, slots, next);
+        }
+        if greedy && max != Some(0) && self.repeat_once(hir, greedy, min, max, pos, slots, next) {
+            return true;
+        }
+        if min == 0 && next(self, pos, slots) {
+            return true;
+        }
+        !greedy && max != Some(0) && self.repeat_once(hir, greedy, min, max, pos, slots, next)
+    }
+
+    /// Matches one more repetition, then the rest of them.
+    #[allow(clippy::too_many_arguments)]
+    fn repeat_once(
+        &mut self,
+        hir: &Hir,
+        greedy: bool,
+        min: u32,
+        max: Option<u32>,
+        pos: usize,
+        slots: &mut Slots,
+        next: Next<'_, 'c>,
+    ) -> bool {
+        self.at(hir, pos, slots, &mut |matcher, end, slots| {
+            // An optional repetition that matched nothing isn't going anywhere, so stop repeating it
+            if end == pos && min == 0 {
+                return false;
+            }
+            matcher.repeat(hir, greedy, min.saturating_sub(1), max.map(|max| max - 1), end, slots, next)
+        })
+    }
+
+    /// Repeats a single character without recursing once per character, so `.*` survives long strings.
+    #[allow(clippy::too_many_arguments)]
+    fn repeat_single(
+        &mut self,
+        hir: &Hir,
+        greedy: bool,
+        min: u32,
+        max: Option<u32>,
+        pos: usize,
+        slots: &mut Slots,
+        next: Next<'_, 'c>,
+    ) -> bool {
+        // ends[n] is where we'd be after n repetitions
+        let mut ends = vec![pos];
+        while max.is_none_or(|max| ends.len() <= max as usize) {
+            match self.single(hir, ends[ends.len() - 1]) {
+                Some(end) => ends.push(end),
+                None => break,
+            }
+        }
+        if ends.len() <= min as usize {
+            return false;
+        }
+
+        let candidates = &ends[min as usize..];
+        let mut attempt = |matcher: &mut Self, end: usize| !matcher.tick() && next(matcher, end, slots);
+        if greedy {
+            candidates.iter().rev().any(|&end| attempt(self, end))
+        } else {
+            candidates.iter().any(|&end| attempt(self, end))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matched(pattern: &str, text: &str) -> Vec<String> {
+        Regex::new(pattern)
+            .unwrap()
+            .find_all(text)
+            .unwrap()
+            .iter()
+            .map(|captures| captures.text(0).unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_find_all_follows_the_usual_rules() {
+        assert_eq!(matched(r"\d+", "a1 b22 c333"), vec!["1", "22", "333"]);
+        assert_eq!(matched(r"a+?", "aaa"), vec!["a", "a", "a"]);
+        assert_eq!(matched(r"(ab|a)c", "abc ac"), vec!["abc", "ac"]);
+        assert_eq!(matched(r"\bcat\b", "cat concat cat."), vec!["cat", "cat"]);
+        assert_eq!(matched(r"^\w+$", "one\ntwo"), Vec::<String>::new());
+        assert_eq!(matched(r"(?m)^\w+$", "one\ntwo"), vec!["one", "two"]);
+        assert_eq!(matched(r"x*", "ab"), vec!["", "", ""]);
+        assert_eq!(matched(r"(?:ha){2,3}", "hahahahaha"), vec!["hahaha", "haha"]);
+        assert_eq!(matched(r"é.", "café!"), vec!["é!"]);
+    }
+
+    #[test]
+    fn test_captures_and_replacement() {
+        let regex = Regex::new(r"(?P<key>\w+)=(\w+)?").unwrap();
+        let found = regex.find_all("a=1 b=").unwrap();
+        assert_eq!(found[0].groups(), &[Some("a=1".to_string()), Some("a".to_string()), Some("1".to_string())]);
+        assert_eq!(found[1].text(2), None);
+        assert_eq!(found[1].named("key"), Some("b"));
+        assert_eq!(regex.replace_all("a=1 b=2", "$2$$${key}").unwrap(), "1$a 2$b");
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(Regex::new("(unclosed"), Err(RegexError::Invalid(_))));
+        let catastrophic = Regex::new(r"(a*)*b").unwrap();
+        assert_eq!(catastrophic.is_match(&"a".repeat(40)), Err(RegexError::TooMuchBacktracking));
+        assert!(Regex::new(&".".repeat(2_000)).unwrap().is_match(&"x".repeat(3_000)).is_err());
+        assert!(Regex::new(".*").unwrap().is_match(&"x".repeat(100_000)).unwrap());
+    }
+}
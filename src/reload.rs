@@ -0,0 +1,151 @@
+//! # Reload Module
+//!
+//! Hot reloading: swap an edited script into an interpreter that's already running it,
+//! without starting over from scratch.
+//! Top-level functions and modules are compared with the version that's loaded, and only
+//! the ones that changed are executed again. Variables survive, for better or worse.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::{Lexer, Parser};
+//! use useless_lang::reload;
+//!
+//! let parse = |source: &str| Parser::new(Lexer::new(source).collect()).parse().unwrap();
+//! let old = parse("greet(name) { name; } let x = 1;");
+//! let new = parse("greet(name) { print(name); } let x = 2;");
+//!
+//! let plan = reload::plan(&old, &new);
+//! assert_eq!(plan.changed.len(), 1);
+//! assert!(plan.removed.is_empty());
+//! ```
+
+use std::collections::HashMap;
+use std::slice;
+
+use crate::ast::{Program, Statement};
+use crate::fmt;
+use crate::intern::Symbol;
+
+/// What kind of top-level definition something is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DefinitionKind {
+    /// A function (sync or async)
+    Function,
+    /// A module
+    Module,
+}
+
+/// A top-level definition that can be reloaded on its own.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Definition {
+    /// What kind of definition this is
+    pub kind: DefinitionKind,
+    /// The name it was defined with
+    pub name: Symbol,
+}
+
+/// What needs to happen to bring a running program up to date.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReloadPlan {
+    /// Definitions that are new or changed, with the statements that define them
+    pub changed: Vec<(Definition, Statement)>,
+    /// Definitions that no longer exist
+    pub removed: Vec<Definition>,
+    /// How many definitions stayed exactly the same
+    pub unchanged: usize,
+}
+
+/// Returns the definition a top-level statement makes, if it makes one.
+pub fn definition_of(statement: &Statement) -> Option<Definition> {
+    let (kind, name) = match statement {
+        Statement::Function { name, .. } | Statement::AsyncFunction { name, .. } => (DefinitionKind::Function, name),
+        Statement::Module { name, .. } => (DefinitionKind::Module, name),
+        Statement::Attributed { statement, .. } | Statement::Located { statement, .. } => return definition_of(statement),
+        _ => return None,
+    };
+    Some(Definition { kind, name: name.clone() })
+}
+
+/// Compares the definitions in two versions of a program, by what they say rather than where
+/// they're written: a function that only moved down a few lines hasn't changed. (Its errors will
+/// point at where it used to be until it's edited for real, which beats running it again.)
+/// Anything that isn't a function or module is ignored: it already ran, and it's not running again.
+pub fn plan(old: &Program, new: &Program) -> ReloadPlan {
+    let old_definitions: Vec<(Definition, &Statement)> =
+        old.iter().filter_map(|statement| Some((definition_of(statement)?, statement))).collect();
+    let mut previous: HashMap<&Definition, &Statement> =
+        old_definitions.iter().map(|(definition, statement)| (definition, *statement)).collect();
+
+    let mut plan = ReloadPlan::default();
+    for statement in new {
+        let Some(definition) = definition_of(statement) else {
+            continue;
+        };
+        match previous.remove(&definition) {
+            Some(old_statement) if same_definition(old_statement, statement) => plan.unchanged += 1,
+            _ => plan.changed.push((definition, statement.clone())),
+        }
+    }
+
+    plan.removed = old_definitions
+        .iter()
+        .filter(|(definition, _)| previous.contains_key(definition))
+        .map(|(definition, _)| definition.clone())
+        .collect();
+    plan
+}
+
+/// Returns whether two statements say the same thing. They're compared as the formatter would
+/// write them, which leaves out the spans (and the blank lines that shifted them).
+fn same_definition(old: &Statement, new: &Statement) -> bool {
+    old.unlocated() == new.unlocated() || fmt::format_program(slice::from_ref(old)) == fmt::format_program(slice::from_ref(new))
+}
+
+/// What a hot reload actually did.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReloadReport {
+    /// Names of the definitions that were executed again
+    pub reloaded: Vec<String>,
+    /// Names of the definitions that were forgotten
+    pub removed: Vec<String>,
+    /// How many definitions were left alone
+    pub unchanged: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn parse(source: &str) -> Program {
+        Parser::new(Lexer::new(source).collect()).parse().unwrap()
+    }
+
+    #[test]
+    fn test_plan_finds_changed_new_and_removed() {
+        let old = parse("same() { 1; } edited() { 1; } gone() { 1; } mod tools { let a = 1; }");
+        let new = parse("same() { 1; } edited() { 2; } fresh() { 1; } mod tools { let a = 1; } let x = 1;");
+
+        let plan = plan(&old, &new);
+        let changed: Vec<&str> = plan.changed.iter().map(|(definition, _)| definition.name.as_str()).collect();
+        assert_eq!(changed, vec!["edited", "fresh"]);
+        assert_eq!(plan.removed, vec![Definition { kind: DefinitionKind::Function, name: Symbol::intern("gone") }]);
+        assert_eq!(plan.unchanged, 2);
+    }
+
+    #[test]
+    fn test_moving_a_function_is_not_changing_it() {
+        let located = |source: &str| {
+            let mut parser = Parser::new(Lexer::new(source).collect());
+            parser.set_record_spans(true);
+            parser.parse().unwrap()
+        };
+        let old = located("first() {\n  print(1);\n}\nsecond() {\n  print(2);\n}");
+        let new = located("\n\nsecond() {\n  print(2);\n}\n\nfirst() {\n  print(1);\n}");
+
+        let plan = plan(&old, &new);
+        assert!(plan.changed.is_empty(), "{:?}", plan.changed);
+        assert_eq!(plan.unchanged, 2);
+    }
+}
@@ -0,0 +1,542 @@
+//! # REPL Module
+//!
+//! The parts of `useless-lang repl` that aren't just reading lines: knowing when the input isn't
+//! finished yet, and remembering what was typed, even after the REPL is closed.
+//!
+//! Input is unfinished while a bracket, brace or paren is still open, or a string hasn't been
+//! closed. The REPL keeps asking for lines until it isn't (or until an empty line, for when it's
+//! wrong about that).
+//!
+//! History is kept in a file, one entry per line, with the newlines of multi-line entries escaped.
+//! Lines typed into a terminal go through a [`LineEditor`], where Ctrl-R searches the history
+//! backwards as you type (with the `line-editing` feature, on by default). Anything that isn't a
+//! terminal is read a line at a time, like it always was.
+//!
+//! The last input is kept as a [`LastInput`], so `:tokens`, `:ast` and `:chaos` can show what the
+//! lexer made of it, what the parser made of that, and what chaos made of the rest.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::repl::{self, History};
+//!
+//! assert!(repl::needs_more("loop {\n  print(\"hi\");"));
+//! assert!(!repl::needs_more("loop {\n  print(\"hi\");\n}"));
+//!
+//! let mut history = History::new();
+//! history.add("let x = 1;").unwrap();
+//! history.add("print(x);").unwrap();
+//! assert_eq!(history.search("x").collect::<Vec<_>>(), ["print(x);", "let x = 1;"]);
+//! ```
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::ast::{self, Program};
+use crate::chaos::ChaosEvent;
+use crate::lexer::{self, Lexer, Token, TokenKind};
+use crate::parser::{ParseError, Parser};
+
+/// Where the history goes, in the home directory.
+pub const HISTORY_FILE: &str = ".useless_history";
+
+/// The most entries the history keeps. The oldest are forgotten first, as usual.
+pub const HISTORY_LIMIT: usize = 1000;
+
+/// Returns whether the input so far is obviously unfinished: a bracket, brace or paren left open,
+/// or a quote with nothing to close it.
+pub fn needs_more(source: &str) -> bool {
+    let mut depth = 0i64;
+    let mut strings = 0;
+    for token in Lexer::new(source) {
+        match token.kind {
+            TokenKind::LeftParen | TokenKind::LeftBracket | TokenKind::LeftBrace => depth += 1,
+            TokenKind::RightParen | TokenKind::RightBracket | TokenKind::RightBrace => depth -= 1,
+            TokenKind::StringLiteral => strings += 1,
+            _ => {},
+        }
+    }
+    // Quotes that aren't part of a string didn't find their other half
+    depth > 0 || source.matches('"').count() > strings * 2
+}
+
+/// Where the history lives by default, if there's a home to keep it in.
+pub fn default_history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").filter(|home| !home.is_empty()).map(|home| Path::new(&home).join(HISTORY_FILE))
+}
+
+/// Everything typed into the REPL, oldest first.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct History {
+    /// The file it's saved to, if it's saved at all
+    path: Option<PathBuf>,
+    /// The entries, oldest first
+    entries: Vec<String>,
+}
+
+impl History {
+    /// Creates a history that's forgotten when it's dropped.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the history saved in a file, and keeps saving to it. A file that doesn't exist yet is
+    /// just an empty history.
+    pub fn load(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let entries = match fs::read_to_string(&path) {
+            Ok(text) => text.lines().map(unescape).collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        let mut history = Self { path: Some(path), entries };
+        history.forget_the_oldest();
+        Ok(history)
+    }
+
+    /// Every entry, oldest first.
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Remembers an entry, and saves the history if it has a file. Blank entries, and entries the
+    /// same as the one before, aren't worth remembering.
+    pub fn add(&mut self, entry: &str) -> io::Result<()> {
+        let entry = entry.trim_end();
+        if entry.trim().is_empty() || self.entries.last().is_some_and(|last| last == entry) {
+            return Ok(());
+        }
+        self.entries.push(entry.to_string());
+        self.forget_the_oldest();
+        match &self.path {
+            Some(path) => fs::write(path, self.entries.iter().map(|entry| escape(entry) + "\n").collect::<String>()),
+            None => Ok(()),
+        }
+    }
+
+    /// Finds the entries containing some text, newest first.
+    pub fn search<'a>(&'a self, text: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.entries.iter().rev().filter(move |entry| entry.contains(text)).map(String::as_str)
+    }
+
+    /// Drops entries from the front until there are at most [`HISTORY_LIMIT`].
+    fn forget_the_oldest(&mut self) {
+        let excess = self.entries.len().saturating_sub(HISTORY_LIMIT);
+        self.entries.drain(..excess);
+    }
+}
+
+/// A key the line editor understands. Everything else is [`Key::Other`], and ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    /// Something printable
+    Char(char),
+    /// Backspace, for regret
+    Backspace,
+    /// Enter, for commitment
+    Enter,
+    /// Ctrl-R, to search the history (again, for an older match)
+    CtrlR,
+    /// Ctrl-C, to give up on the line
+    CtrlC,
+    /// Ctrl-D, to give up on everything (on an empty line)
+    CtrlD,
+    /// Escape, to stop searching and keep what was found
+    Escape,
+    /// Arrows, function keys and the rest of the keyboard
+    Other,
+}
+
+/// How reading a line ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Edited {
+    /// A line, newline included
+    Line(String),
+    /// Ctrl-C: the line (and whatever came before it) is abandoned
+    Interrupted,
+    /// Ctrl-D or the end of stdin: nobody is typing anymore
+    EndOfInput,
+}
+
+/// A Ctrl-R search in progress.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct ReverseSearch {
+    /// What's been typed since Ctrl-R
+    query: String,
+    /// How many matches to skip, one more for every extra Ctrl-R
+    skip: usize,
+}
+
+/// The line being typed, with the cursor always at the end. Ctrl-R searches the history for what
+/// comes next, newest first, and every Ctrl-R after that goes one match further back.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LineEditor {
+    /// The line so far
+    line: String,
+    /// The search, while there is one
+    search: Option<ReverseSearch>,
+}
+
+impl LineEditor {
+    /// Starts an empty line.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handles a key. Returns how the line ended, or `None` while it's still being typed.
+    pub fn key(&mut self, key: Key, history: &History) -> Option<Edited> {
+        let Some(search) = self.search.as_mut() else {
+            match key {
+                Key::Char(c) => self.line.push(c),
+                Key::Backspace => {
+                    self.line.pop();
+                },
+                Key::Enter => return Some(Edited::Line(std::mem::take(&mut self.line) + "\n")),
+                Key::CtrlR => self.search = Some(ReverseSearch::default()),
+                Key::CtrlC => {
+                    self.line.clear();
+                    return Some(Edited::Interrupted);
+                },
+                Key::CtrlD if self.line.is_empty() => return Some(Edited::EndOfInput),
+                Key::CtrlD | Key::Escape | Key::Other => {},
+            }
+            return None;
+        };
+        match key {
+            Key::Char(c) => {
+                search.query.push(c);
+                search.skip = 0;
+            },
+            Key::Backspace => {
+                search.query.pop();
+                search.skip = 0;
+            },
+            // Past the oldest match there's nothing, so stay on it
+            Key::CtrlR => {
+                if history.search(&search.query).nth(search.skip + 1).is_some() {
+                    search.skip += 1;
+                }
+            },
+            Key::CtrlC => self.search = None,
+            Key::Enter => {
+                self.accept(history);
+                return self.key(Key::Enter, history);
+            },
+            Key::Escape | Key::Other => self.accept(history),
+            Key::CtrlD => {},
+        }
+        None
+    }
+
+    /// What the line looks like right now, after the prompt. Entries that span lines are shown on one.
+    pub fn display(&self, history: &History) -> String {
+        match &self.search {
+            None => self.line.replace('\n', " ⏎ "),
+            Some(search) => match history.search(&search.query).nth(search.skip) {
+                Some(found) => format!("(reverse-i-search)'{}': {}", search.query, found.replace('\n', " ⏎ ")),
+                None => format!("(failed reverse-i-search)'{}': ", search.query),
+            },
+        }
+    }
+
+    /// Ends the search, with whatever it found as the line. Finding nothing leaves the line alone.
+    fn accept(&mut self, history: &History) {
+        if let Some(search) = self.search.take() {
+            if let Some(found) = history.search(&search.query).nth(search.skip) {
+                self.line = found.to_string();
+            }
+        }
+    }
+}
+
+/// Reads a line after showing a prompt. A terminal gets the [`LineEditor`], with Ctrl-R; anything
+/// else is read as it comes.
+#[cfg_attr(not(all(feature = "line-editing", unix)), allow(unused_variables))]
+pub fn read_line(prompt: &str, history: &History) -> io::Result<Edited> {
+    #[cfg(all(feature = "line-editing", unix))]
+    if io::IsTerminal::is_terminal(&io::stdin()) {
+        return terminal::read_line(prompt, history);
+    }
+
+    print!("{}", prompt);
+    io::stdout().flush()?;
+    let mut line = String::new();
+    match io::stdin().read_line(&mut line)? {
+        0 => Ok(Edited::EndOfInput),
+        _ => Ok(Edited::Line(line)),
+    }
+}
+
+/// The terminal in raw mode, where every key arrives the moment it's pressed.
+#[cfg(all(feature = "line-editing", unix))]
+mod terminal {
+    use std::io::{self, Write};
+    use std::mem::MaybeUninit;
+
+    use super::{Edited, History, Key, LineEditor};
+
+    /// How long to wait for the rest of an escape sequence before deciding it was just Escape.
+    const ESCAPE_WAIT_MS: i32 = 25;
+
+    /// Puts the terminal back the way it was found when dropped, even if reading failed.
+    struct RawMode {
+        /// The settings from before
+        original: libc::termios,
+    }
+
+    impl RawMode {
+        fn enter() -> io::Result<Self> {
+            let mut settings = MaybeUninit::<libc::termios>::uninit();
+            // SAFETY: tcgetattr fills in the termios it's given, and says so when it can't
+            let original = unsafe {
+                if libc::tcgetattr(libc::STDIN_FILENO, settings.as_mut_ptr()) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                settings.assume_init()
+            };
+            let mut raw = original;
+            // No echo, no waiting for Enter, and Ctrl-C is a key like any other. Output is left alone
+            raw.c_lflag &= !(libc::ECHO | libc::ICANON | libc::ISIG | libc::IEXTEN);
+            raw.c_iflag &= !(libc::IXON | libc::ICRNL);
+            raw.c_cc[libc::VMIN] = 1;
+            raw.c_cc[libc::VTIME] = 0;
+            // SAFETY: raw is a valid termios, copied from the one tcgetattr gave us
+            if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSAFLUSH, &raw) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self { original })
+        }
+    }
+
+    impl Drop for RawMode {
+        fn drop(&mut self) {
+            // SAFETY: original came from tcgetattr
+            unsafe {
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSAFLUSH, &self.original);
+            }
+        }
+    }
+
+    /// Reads one byte, or `None` if nothing arrives in time (a negative wait means forever).
+    fn read_byte(wait_ms: i32) -> io::Result<Option<u8>> {
+        let mut poll = libc::pollfd { fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 };
+        // SAFETY: one pollfd, and we said there's one
+        if wait_ms >= 0 && unsafe { libc::poll(&mut poll, 1, wait_ms) } <= 0 {
+            return Ok(None);
+        }
+        let mut byte = 0u8;
+        // SAFETY: reading at most one byte into one byte
+        match unsafe { libc::read(libc::STDIN_FILENO, (&mut byte as *mut u8).cast(), 1) } {
+            1 => Ok(Some(byte)),
+            0 => Ok(None),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+
+    /// Reads the next key, or `None` when stdin has ended.
+    fn read_key() -> io::Result<Option<Key>> {
+        let Some(byte) = read_byte(-1)? else {
+            return Ok(None);
+        };
+        let key = match byte {
+            b'\r' | b'\n' => Key::Enter,
+            0x7f | 0x08 => Key::Backspace,
+            0x12 => Key::CtrlR,
+            0x03 => Key::CtrlC,
+            0x04 => Key::CtrlD,
+            0x1b => match read_byte(ESCAPE_WAIT_MS)? {
+                None => Key::Escape,
+                // An arrow or some such: swallow the rest of the sequence
+                Some(b'[' | b'O') => {
+                    while let Some(byte) = read_byte(ESCAPE_WAIT_MS)? {
+                        if (0x40..=0x7e).contains(&byte) {
+                            break;
+                        }
+                    }
+                    Key::Other
+                },
+                Some(_) => Key::Other,
+            },
+            byte if byte < 0x20 => Key::Other,
+            first => {
+                let length = match first {
+                    0xf0.. => 4,
+                    0xe0.. => 3,
+                    0xc0.. => 2,
+                    _ => 1,
+                };
+                let mut bytes = vec![first];
+                while bytes.len() < length {
+                    match read_byte(-1)? {
+                        Some(byte) => bytes.push(byte),
+                        None => break,
+                    }
+                }
+                std::str::from_utf8(&bytes).ok().and_then(|text| text.chars().next()).map_or(Key::Other, Key::Char)
+            },
+        };
+        Ok(Some(key))
+    }
+
+    /// Reads a line with the terminal in raw mode, redrawing it after every key.
+    pub(super) fn read_line(prompt: &str, history: &History) -> io::Result<Edited> {
+        let raw = RawMode::enter()?;
+        let mut editor = LineEditor::new();
+        let mut stdout = io::stdout();
+        let edited = loop {
+            write!(stdout, "\r\x1b[K{}{}", prompt, editor.display(history))?;
+            stdout.flush()?;
+            let Some(key) = read_key()? else {
+                break Edited::EndOfInput;
+            };
+            if let Some(edited) = editor.key(key, history) {
+                break edited;
+            }
+        };
+        drop(raw);
+        if edited == Edited::Interrupted {
+            print!("^C");
+        }
+        println!();
+        Ok(edited)
+    }
+}
+
+/// The last thing typed into the REPL, taken apart.
+#[derive(Debug)]
+pub struct LastInput {
+    /// What the lexer made of it
+    pub tokens: Vec<Token>,
+    /// What the parser made of the tokens
+    pub program: Result<Program, ParseError>,
+    /// The chaos it suffered while it ran (nothing, until it has)
+    pub chaos: Vec<ChaosEvent>,
+}
+
+impl LastInput {
+    /// Lexes and parses some input, ready to be run.
+    pub fn new(source: &str) -> Self {
+        let tokens: Vec<Token> = Lexer::new(source).collect();
+        let program = Parser::new(tokens.clone()).parse();
+        Self { tokens, program, chaos: Vec::new() }
+    }
+
+    /// The tokens, one a line with where they were, or as JSON.
+    pub fn tokens_report(&self, json: bool) -> String {
+        if json {
+            return lexer::tokens_to_json(&self.tokens);
+        }
+        let lines: Vec<String> = self
+            .tokens
+            .iter()
+            .map(|token| format!("{:>3}:{:<3} {:<16} {}", token.span.line, token.span.column, format!("{:?}", token.kind), token.text))
+            .collect();
+        if lines.is_empty() { "No tokens. Nothing to see, nothing to break.".to_string() } else { lines.join("\n") }
+    }
+
+    /// The syntax tree, laid out for people, or as JSON. Input that didn't parse has only an error to show.
+    pub fn ast_report(&self, json: bool) -> String {
+        match &self.program {
+            Ok(program) if json => ast::program_to_json(program),
+            Ok(program) => format!("{:#?}", program),
+            Err(e) => format!("No tree, the parser gave up: {}", e),
+        }
+    }
+
+    /// Every act of chaos the input suffered, in the order it happened.
+    pub fn chaos_report(&self) -> String {
+        if self.chaos.is_empty() {
+            return "No chaos. Suspicious.".to_string();
+        }
+        self.chaos.iter().map(|event| format!("🎲 {}", event)).collect::<Vec<_>>().join("\n")
+    }
+}
+
+/// Puts an entry on a single line: backslashes are doubled, and newlines become `\n`.
+fn escape(entry: &str) -> String {
+    entry.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+/// Undoes [`escape`].
+fn unescape(line: &str) -> String {
+    let mut entry = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            entry.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => entry.push('\n'),
+            Some(other) => entry.push(other),
+            None => entry.push('\\'),
+        }
+    }
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_survives_being_closed() {
+        let path = std::env::temp_dir().join(format!("upl_history_{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut history = History::load(&path).unwrap();
+        history.add("loop {\n  print(\"a \\\\ b\");\n}").unwrap();
+        history.add("   ").unwrap();
+        history.add("let x = 1;").unwrap();
+        history.add("let x = 1;").unwrap();
+
+        let reloaded = History::load(&path).unwrap();
+        assert_eq!(reloaded.entries(), ["loop {\n  print(\"a \\\\ b\");\n}", "let x = 1;"]);
+        assert_eq!(reloaded.search("loop").count(), 1);
+        assert!(needs_more("print(\"unfinished"));
+        assert!(!needs_more("print(\"done\"); // with a ) too many"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_ctrl_r_finds_older_and_older_entries() {
+        let mut history = History::new();
+        for entry in ["let x = 1;", "print(x);", "let y = x;"] {
+            history.add(entry).unwrap();
+        }
+        let mut editor = LineEditor::new();
+        let mut press = |keys: &[Key]| keys.iter().map(|&key| editor.key(key, &history)).last().flatten();
+
+        assert_eq!(press(&[Key::Char('p'), Key::CtrlR, Key::Char('l'), Key::Char('e')]), None);
+        assert_eq!(editor.display(&history), "(reverse-i-search)'le': let y = x;");
+        assert_eq!(editor.key(Key::CtrlR, &history), None);
+        assert_eq!(editor.display(&history), "(reverse-i-search)'le': let x = 1;");
+        assert_eq!(editor.key(Key::CtrlR, &history), None);
+        assert_eq!(editor.display(&history), "(reverse-i-search)'le': let x = 1;");
+        assert_eq!(editor.key(Key::Enter, &history), Some(Edited::Line("let x = 1;\n".to_string())));
+
+        for c in "nope".chars() {
+            editor.key(Key::Char(c), &history);
+        }
+        editor.key(Key::CtrlR, &history);
+        editor.key(Key::Char('?'), &history);
+        assert_eq!(editor.display(&history), "(failed reverse-i-search)'?': ");
+        editor.key(Key::CtrlC, &history);
+        assert_eq!(editor.display(&history), "nope");
+        assert_eq!(editor.key(Key::CtrlC, &history), Some(Edited::Interrupted));
+    }
+
+    #[test]
+    fn test_last_input_comes_apart() {
+        let mut last = LastInput::new("let x = 1;");
+        assert_eq!(last.tokens.len(), 5);
+        assert!(last.tokens_report(false).starts_with("  1:1   Let              let"), "{}", last.tokens_report(false));
+        assert!(last.tokens_report(true).starts_with(r#"[{"kind":"Let""#));
+        assert!(last.ast_report(true).contains(r#""type":"Let""#), "{}", last.ast_report(true));
+        assert_eq!(last.chaos_report(), "No chaos. Suspicious.");
+
+        last.chaos.push(ChaosEvent::new(crate::chaos::ChaosKind::LiteralMutation));
+        assert!(last.chaos_report().starts_with("🎲 "));
+        assert!(LastInput::new("let = 1;").ast_report(false).starts_with("No tree, the parser gave up: Expected a name"));
+    }
+}
@@ -0,0 +1,87 @@
+//! # Report Module
+//!
+//! A full account of a run, for hosts that want more than "it worked" or "it didn't".
+//! [`Interpreter::interpret_with_report`](crate::interpreter::Interpreter::interpret_with_report)
+//! hands one of these back instead of a bare `Result`.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::ast::{Expression, Literal, Statement};
+//! use useless_lang::interpreter::{Interpreter, Value};
+//!
+//! let mut interpreter = Interpreter::new();
+//! interpreter.set_completely_normal(true);
+//! let report = interpreter.interpret_with_report(vec![
+//!     Statement::Expression(Expression::Literal(Literal::Number(42))),
+//! ]);
+//! assert!(report.is_success());
+//! assert_eq!(report.last_value, Value::Number { value: 42 });
+//! ```
+
+use std::time::Duration;
+
+use crate::chaos::ChaosEvent;
+use crate::interpreter::{RuntimeError, Value};
+use crate::parser::ParseError;
+use crate::stats::Stats;
+use crate::warning::Warning;
+
+/// Everything that happened during a single run.
+#[derive(Debug)]
+pub struct RunResult {
+    /// The value of the last expression statement that ran (null if there wasn't one)
+    pub last_value: Value,
+    /// Everything the program printed
+    pub output: String,
+    /// Every error that ended (or tried to end) the run. Calling `exit()` doesn't count as one
+    pub errors: Vec<RuntimeError>,
+    /// Every syntax error, when the program never got as far as running
+    pub parse_errors: Vec<ParseError>,
+    /// The status the program asked to exit with, if it called `exit()` and the interpreter listened
+    pub exit_status: Option<i32>,
+    /// Every act of chaos committed during the run
+    pub chaos_events: Vec<ChaosEvent>,
+    /// Every warning, kept apart from the output
+    pub warnings: Vec<Warning>,
+    /// What the interpreter has been up to, including this run
+    pub stats: Stats,
+    /// What this run used up, for hosts handing out quotas
+    pub usage: ResourceUsage,
+}
+
+/// What a single run used up. Everything is counted from the start of the run.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResourceUsage {
+    /// How many statements were executed (or at least attempted)
+    pub statements_executed: usize,
+    /// How many statements and expressions were evaluated, the thing `max_steps` counts
+    pub steps_taken: usize,
+    /// The most memory the variables took up, estimated between top-level statements
+    pub peak_memory_bytes: usize,
+    /// How long the run spent asleep: promises, dramatic pauses, prayers and the scheduler
+    pub time_slept: Duration,
+    /// Every URL `print` picked, whether or not the browser was allowed to open it
+    pub urls: Vec<String>,
+}
+
+impl RunResult {
+    /// Checks whether the program finished without an error.
+    pub fn is_success(&self) -> bool {
+        self.errors.is_empty() && self.parse_errors.is_empty()
+    }
+
+    /// The result of a program that didn't parse, so nothing ran.
+    pub(crate) fn unparsed(parse_errors: Vec<ParseError>) -> Self {
+        Self {
+            last_value: Value::Null,
+            output: String::new(),
+            errors: Vec::new(),
+            parse_errors,
+            exit_status: None,
+            chaos_events: Vec::new(),
+            warnings: Vec::new(),
+            stats: Stats::default(),
+            usage: ResourceUsage::default(),
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! # Runtime Module
+//!
+//! The tables every interpreter needs and nobody ever changes: the websites `print` might open
+//! instead, and the plugins loaded for everyone. A [`Runtime`] is built once and shared behind an
+//! [`Arc`], so a server spinning up an interpreter per request doesn't rebuild them every time.
+//!
+//! Interpreters made with [`Interpreter::new`](crate::interpreter::Interpreter::new) share the
+//! process-wide [`Runtime::shared`] one. Anything loaded into an interpreter afterwards stays in
+//! that interpreter.
+//!
+//! ## Example
+//! ```rust
+//! use std::sync::Arc;
+//! use useless_lang::runtime::Runtime;
+//! use useless_lang::{Config, Interpreter};
+//!
+//! let runtime = Arc::new(Runtime::new().with_urls(vec!["https://example.com".to_string()]));
+//! let first = Interpreter::with_runtime(Config::default(), runtime.clone());
+//! let second = Interpreter::with_runtime(Config::default(), runtime.clone());
+//! assert_eq!(Arc::strong_count(&runtime), 3);
+//! assert_eq!(runtime.urls(), ["https://example.com"]);
+//! ```
+
+use std::sync::{Arc, OnceLock};
+
+use crate::chaos::ChaosModulator;
+use crate::plugin::{PluginError, Plugins, UplPlugin};
+
+/// Where `print` sends people instead of printing, unless told otherwise.
+pub const DEFAULT_URLS: &[&str] = &[
+    "https://example.com",
+    "https://nyancat.com",
+    "https://zombo.com",
+    "https://crouton.net",
+    "https://theuselessweb.com",
+    "https://cat-bounce.com",
+    "https://pointerpointer.com",
+    "https://findtheinvisiblecow.com",
+    "https://thatsthefinger.com",
+    "https://heeeeeeeey.com",
+];
+
+/// Tables shared by every interpreter made from it.
+#[derive(Clone)]
+pub struct Runtime {
+    urls: Vec<String>,
+    plugins: Plugins,
+    modulators: Vec<Arc<dyn ChaosModulator>>,
+}
+
+impl Runtime {
+    /// Creates a runtime with the default websites and no plugins.
+    pub fn new() -> Self {
+        Self {
+            urls: DEFAULT_URLS.iter().map(|url| url.to_string()).collect(),
+            plugins: Plugins::default(),
+            modulators: Vec::new(),
+        }
+    }
+
+    /// Returns the process-wide runtime, built the first time anyone asks.
+    pub fn shared() -> Arc<Runtime> {
+        static SHARED: OnceLock<Arc<Runtime>> = OnceLock::new();
+        SHARED.get_or_init(|| Arc::new(Runtime::new())).clone()
+    }
+
+    /// Replaces the websites `print` picks from. With none at all, chaotic printing fails with a `BrowserError`.
+    pub fn with_urls(mut self, urls: Vec<String>) -> Self {
+        self.urls = urls;
+        self
+    }
+
+    /// Loads a plugin for every interpreter that will be made from this runtime.
+    pub fn load_plugin(&mut self, plugin: &dyn UplPlugin) -> Result<(), PluginError> {
+        let mut registry = Default::default();
+        plugin.register(&mut registry);
+        let modulators = self.plugins.install(plugin.name(), registry)?;
+        self.modulators.extend(modulators.into_iter().map(Arc::from));
+        Ok(())
+    }
+
+    /// Returns the websites `print` picks from.
+    pub fn urls(&self) -> &[String] {
+        &self.urls
+    }
+
+    /// Returns the plugins loaded for everyone.
+    pub fn plugins(&self) -> &Plugins {
+        &self.plugins
+    }
+
+    /// Returns the chaos modulators the plugins registered, ready for an interpreter to keep.
+    pub(crate) fn modulators(&self) -> impl Iterator<Item = Box<dyn ChaosModulator>> + '_ {
+        self.modulators.iter().map(|modulator| Box::new(modulator.clone()) as Box<dyn ChaosModulator>)
+    }
+}
+
+impl Default for Runtime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chaos::ChaosContext;
+    use crate::interpreter::Interpreter;
+    use crate::plugin::Registry;
+
+    /// Keeps the chaos away, for everyone at once.
+    struct Calm;
+
+    impl ChaosModulator for Calm {
+        fn suppresses_chaos(&self, _context: &ChaosContext) -> bool {
+            true
+        }
+    }
+
+    struct CalmPlugin;
+
+    impl UplPlugin for CalmPlugin {
+        fn name(&self) -> &str {
+            "calm"
+        }
+
+        fn register(&self, registry: &mut Registry) {
+            registry.modulator(Box::new(Calm)).value_type("calm");
+        }
+    }
+
+    #[test]
+    fn test_runtime_plugins_reach_every_interpreter() {
+        let mut runtime = Runtime::new();
+        runtime.load_plugin(&CalmPlugin).unwrap();
+        assert!(matches!(runtime.load_plugin(&CalmPlugin), Err(PluginError::AlreadyLoaded(_))));
+        let runtime = Arc::new(runtime);
+
+        for _ in 0..2 {
+            let mut interpreter = Interpreter::with_runtime(Default::default(), runtime.clone());
+            assert!(interpreter.plugins().is_value_type("calm"));
+            assert!(matches!(interpreter.load_plugin(&CalmPlugin), Err(PluginError::AlreadyLoaded(_))));
+            assert!(interpreter.behaves_normally(), "the runtime's modulator should keep the chaos away");
+        }
+        assert!(Arc::ptr_eq(&Runtime::shared(), &Runtime::shared()));
+    }
+}
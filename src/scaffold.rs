@@ -0,0 +1,118 @@
+//! # Scaffold Module
+//!
+//! Starting a new project without copying the last one and deleting everything.
+//! [`create`] makes a directory with a config file, a hello-world `src/main.upl` and an
+//! `examples` folder. The [`Template`] decides whether the program starts out chaotic, or with
+//! `disable_all_useless_shit` already in place for people who came here to get work done.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::scaffold::{self, Template};
+//!
+//! let dir = std::env::temp_dir().join(format!("upl-scaffold-doc-{}", std::process::id()));
+//! let created = scaffold::create(&dir, Template::Normal).unwrap();
+//! assert!(created.contains(&dir.join("src").join("main.upl")));
+//! assert!(scaffold::create(&dir, Template::Chaos).is_err(), "it doesn't overwrite anything");
+//! # std::fs::remove_dir_all(&dir).unwrap();
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::config::CONFIG_FILE;
+
+/// How a new project starts out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Template {
+    /// The language as intended: everything might go wrong
+    #[default]
+    Chaos,
+    /// `disable_all_useless_shit` from the first line, for projects with deadlines
+    Normal,
+}
+
+impl Template {
+    /// Every template's name, as typed on the command line.
+    pub const NAMES: &'static [&'static str] = &["chaos", "normal"];
+
+    /// Finds a template by name.
+    pub fn from_name(name: &str) -> Option<Template> {
+        match name {
+            "chaos" => Some(Template::Chaos),
+            "normal" => Some(Template::Normal),
+            _ => None,
+        }
+    }
+}
+
+/// Errors that stop a project from being created.
+#[derive(Debug, Error)]
+pub enum ScaffoldError {
+    #[error("{} already exists. Projects don't get overwritten, however useless they are", .0.display())]
+    AlreadyExists(PathBuf),
+
+    #[error("Couldn't write the project: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Creates a project in `dir`, which must not exist yet. Returns every file created.
+pub fn create(dir: &Path, template: Template) -> Result<Vec<PathBuf>, ScaffoldError> {
+    if dir.exists() {
+        return Err(ScaffoldError::AlreadyExists(dir.to_path_buf()));
+    }
+    let name = dir.file_name().map_or_else(|| "project".to_string(), |name| name.to_string_lossy().into_owned());
+
+    let config = match template {
+        Template::Chaos => "# How useless this project gets to be (0 is a normal language, 11 is one louder)\nchaos_level = 10\n",
+        Template::Normal => "# Chaos is off in the code, so printed output can stay readable too\nmangle_output = false\nopen_browser = false\n",
+    };
+    let directive = match template {
+        Template::Chaos => "",
+        Template::Normal => "#[directive(disable_all_useless_shit)]\n",
+    };
+    let files = [
+        (PathBuf::from(CONFIG_FILE), config.to_string()),
+        (Path::new("src").join("main.upl"), format!("{}print(\"Hello from {}!\");\n", directive, name)),
+        (
+            Path::new("examples").join("greet.upl"),
+            format!("{}greet(name) {{\n    print(name);\n}}\n\ngreet(\"world\");\n", directive),
+        ),
+    ];
+
+    let mut created = Vec::with_capacity(files.len());
+    for (path, contents) in files {
+        let path = dir.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, contents)?;
+        created.push(path);
+    }
+    Ok(created)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::testing::SeededChaosHarness;
+
+    #[test]
+    fn test_normal_projects_behave_even_in_chaos_mode() {
+        let dir = std::env::temp_dir().join(format!("upl-scaffold-test-{}", std::process::id())).join("demo");
+        create(&dir, Template::Normal).unwrap();
+
+        assert!(Config::load(dir.join(CONFIG_FILE)).is_ok());
+        let main = fs::read_to_string(dir.join("src").join("main.upl")).unwrap();
+        assert!(main.starts_with("#[directive(disable_all_useless_shit)]"));
+        for file in ["src/main.upl", "examples/greet.upl"] {
+            let result = SeededChaosHarness::new(7).run(&fs::read_to_string(dir.join(file)).unwrap()).unwrap();
+            assert!(result.errors.is_empty(), "{}: {:?}", file, result.errors);
+            assert!(result.chaos_events.is_empty(), "{}: {:?}", file, result.chaos_events);
+        }
+        fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,68 @@
+//! # Scheduler Module
+//!
+//! A scheduler is asked for permission before every statement, and can let it run, make it wait,
+//! hand the thread back for a moment, or end the program. Useful when many interpreters share a
+//! thread (or a time budget) and have to take turns being useless.
+//!
+//! Sleeps go through the interpreter's clock, so under [`Interpreter::run_async`](crate::interpreter::Interpreter::run_async)
+//! they're taken on the host's timers while the program waits, instead of blocking the host.
+//!
+//! ## Example
+//! ```rust
+//! use std::time::Duration;
+//! use useless_lang::interpreter::Interpreter;
+//! use useless_lang::scheduler::TimeBudget;
+//!
+//! let mut interpreter = Interpreter::new();
+//! interpreter.set_scheduler(Box::new(TimeBudget::new(Duration::from_secs(5))));
+//! ```
+
+use std::time::{Duration, Instant};
+
+use crate::ast::Statement;
+
+/// What a scheduler wants to happen before the next statement runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    /// Run the statement
+    Continue,
+    /// Wait this long, then run the statement
+    Sleep(Duration),
+    /// Give other threads a turn, then run the statement
+    Yield,
+    /// Stop the program, for the given reason
+    Abort(String),
+}
+
+/// Something that decides when statements get to run.
+pub trait Scheduler: Send {
+    /// Called before every statement, with how many statements have run so far
+    fn before_statement(&mut self, statement: &Statement, statements_executed: usize) -> Decision;
+}
+
+/// A scheduler that lets a program run until its time is up, starting from its first statement.
+#[derive(Debug, Clone)]
+pub struct TimeBudget {
+    /// How long the program may run
+    budget: Duration,
+    /// When the first statement ran
+    started: Option<Instant>,
+}
+
+impl TimeBudget {
+    /// Creates a budget that starts counting at the first statement.
+    pub fn new(budget: Duration) -> Self {
+        Self { budget, started: None }
+    }
+}
+
+impl Scheduler for TimeBudget {
+    fn before_statement(&mut self, _statement: &Statement, _statements_executed: usize) -> Decision {
+        let started = *self.started.get_or_insert_with(Instant::now);
+        if started.elapsed() > self.budget {
+            Decision::Abort(format!("the {}ms time budget ran out", self.budget.as_millis()))
+        } else {
+            Decision::Continue
+        }
+    }
+}
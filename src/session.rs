@@ -0,0 +1,153 @@
+//! # Session Module
+//!
+//! Several programs, one set of globals, like cells in a notebook. Every program run in a
+//! [`Session`] starts where the previous one left off: variables, functions and modules all
+//! stick around, on purpose.
+//!
+//! Each run can pick how much of that sharing it wants with an [`Isolation`]:
+//!
+//! - [`Isolation::Shared`] reads and writes the session's globals.
+//! - [`Isolation::Sandboxed`] sees a copy of them, and its changes are thrown away afterwards.
+//! - [`Isolation::Fresh`] sees nothing at all, and leaves nothing behind either.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::config::Config;
+//! use useless_lang::session::{Isolation, Session};
+//! use useless_lang::{Interpreter, Value};
+//!
+//! let mut interpreter = Interpreter::with_config(Config { chaos_level: 0, open_browser: false, ..Config::default() });
+//! interpreter.set_completely_normal(true);
+//! let mut session = Session::with_interpreter(interpreter);
+//!
+//! session.run_source("let x = 1;", Isolation::Shared).unwrap();
+//! session.run_source("let x = 2;", Isolation::Sandboxed).unwrap();
+//! let result = session.run_source("x;", Isolation::Shared).unwrap();
+//! assert_eq!(result.last_value, Value::Number { value: 1 });
+//! assert_eq!(session.runs(), 3);
+//! ```
+
+use crate::ast::Program;
+use crate::interpreter::{Globals, Interpreter, Value};
+use crate::lexer::Lexer;
+use crate::parser::{ParseError, Parser};
+use crate::report::RunResult;
+
+/// How much of the session's globals a single run gets to touch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Isolation {
+    /// Everything the run defines or changes is kept for the next run
+    #[default]
+    Shared,
+    /// The run starts with a copy of the globals, and the copy is thrown away afterwards
+    Sandboxed,
+    /// The run starts with no globals, and whatever it defines is thrown away afterwards
+    Fresh,
+}
+
+/// An interpreter that runs program after program against the same globals.
+pub struct Session {
+    interpreter: Interpreter,
+    runs: usize,
+}
+
+impl Session {
+    /// Starts a session on a brand new (and fully chaotic) interpreter.
+    pub fn new() -> Self {
+        Self::with_interpreter(Interpreter::new())
+    }
+
+    /// Starts a session on an interpreter that's already been set up, globals and all.
+    pub fn with_interpreter(interpreter: Interpreter) -> Self {
+        Self { interpreter, runs: 0 }
+    }
+
+    /// Runs a program against the shared globals.
+    pub fn run(&mut self, program: Program) -> RunResult {
+        self.run_with(program, Isolation::Shared)
+    }
+
+    /// Runs a program with the given isolation.
+    pub fn run_with(&mut self, program: Program, isolation: Isolation) -> RunResult {
+        self.runs += 1;
+        let scratch = match isolation {
+            Isolation::Shared => return self.interpreter.interpret_with_report(program),
+            Isolation::Sandboxed => self.interpreter.copy_globals(),
+            Isolation::Fresh => Globals::default(),
+        };
+
+        let shared = self.interpreter.replace_globals(scratch);
+        let result = self.interpreter.interpret_with_report(program);
+        self.interpreter.replace_globals(shared);
+        result
+    }
+
+    /// Parses and runs a program with the given isolation.
+    pub fn run_source(&mut self, source: &str, isolation: Isolation) -> Result<RunResult, ParseError> {
+        let program = Parser::new(Lexer::new(source).collect()).parse()?;
+        Ok(self.run_with(program, isolation))
+    }
+
+    /// Peeks at a shared global without counting it as used.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.interpreter.environment().get(name)
+    }
+
+    /// Returns how many programs have been run so far, failed ones included.
+    pub fn runs(&self) -> usize {
+        self.runs
+    }
+
+    /// Returns the interpreter doing the work, for setting it up between runs.
+    pub fn interpreter_mut(&mut self) -> &mut Interpreter {
+        &mut self.interpreter
+    }
+
+    /// Ends the session, keeping the interpreter and everything it remembers.
+    pub fn into_interpreter(self) -> Interpreter {
+        self.interpreter
+    }
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn session() -> Session {
+        let mut interpreter = Interpreter::with_config(Config { chaos_level: 0, open_browser: false, ..Config::default() });
+        interpreter.set_completely_normal(true);
+        Session::with_interpreter(interpreter)
+    }
+
+    #[test]
+    fn test_shared_runs_build_on_each_other() {
+        let mut session = session();
+        session.run_source("let x = 1; same(n) { n; }", Isolation::Shared).unwrap();
+        let result = session.run_source("let y = same(x); y;", Isolation::Shared).unwrap();
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert_eq!(result.last_value, Value::Number { value: 1 });
+        assert_eq!(session.get("y"), Some(&Value::Number { value: 1 }));
+    }
+
+    #[test]
+    fn test_isolated_runs_leave_the_globals_alone() {
+        let mut session = session();
+        session.run_source("let x = 1;", Isolation::Shared).unwrap();
+
+        let sandboxed = session.run_source("let x = 2; let z = 3; x;", Isolation::Sandboxed).unwrap();
+        assert_eq!(sandboxed.last_value, Value::Number { value: 2 });
+        assert_eq!(session.get("x"), Some(&Value::Number { value: 1 }));
+        assert_eq!(session.get("z"), None);
+
+        let fresh = session.run_source("x;", Isolation::Fresh).unwrap();
+        assert!(!fresh.errors.is_empty(), "a fresh run shouldn't know about x");
+        assert_eq!(session.runs(), 3);
+    }
+}
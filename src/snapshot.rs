@@ -0,0 +1,197 @@
+//! # Snapshot Module
+//!
+//! Golden-file testing for programs whose output is, by design, nobody's idea of correct.
+//! A program runs in a [`SeededChaosHarness`] with a fixed seed, and everything it did (output,
+//! chaos and errors) is compared against a `.snap` file checked in next to it. Since the seed is
+//! fixed, the same chaos happens every time, so any difference means something actually changed.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::snapshot::{self, SnapshotOutcome};
+//!
+//! let dir = std::env::temp_dir().join(format!("upl-snapshot-doc-{}", std::process::id()));
+//! std::fs::create_dir_all(&dir).unwrap();
+//! let program = dir.join("hello.upl");
+//! std::fs::write(&program, "print(\"hello\");").unwrap();
+//!
+//! assert_eq!(snapshot::check(&program, snapshot::DEFAULT_SEED, false).unwrap(), SnapshotOutcome::Missing);
+//! assert_eq!(snapshot::check(&program, snapshot::DEFAULT_SEED, true).unwrap(), SnapshotOutcome::Written);
+//! assert_eq!(snapshot::check(&program, snapshot::DEFAULT_SEED, false).unwrap(), SnapshotOutcome::Matched);
+//! # std::fs::remove_dir_all(&dir).unwrap();
+//! ```
+
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::parser::ParseError;
+use crate::report::RunResult;
+use crate::testing::SeededChaosHarness;
+
+/// The seed snapshots are taken with, unless someone picks another.
+pub const DEFAULT_SEED: u64 = 42;
+
+/// The extension snapshot files get, added after the program's own.
+pub const SNAPSHOT_EXTENSION: &str = "snap";
+
+/// Errors that stop a snapshot from being taken at all.
+#[derive(Debug, Error)]
+pub enum SnapshotError {
+    #[error("Couldn't read or write the snapshot. The golden file has tarnished: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("The program doesn't even parse, so there's nothing to take a picture of: {0}")]
+    Parse(#[from] ParseError),
+}
+
+/// How a program's run compared to its snapshot.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SnapshotOutcome {
+    /// The run did exactly what the snapshot says
+    Matched,
+    /// The snapshot was written (or rewritten) from this run
+    Written,
+    /// There's no snapshot yet, and nobody asked for one to be written
+    Missing,
+    /// The run did something else, and this is how it differs (`-` expected, `+` actual)
+    Mismatch {
+        /// A line-by-line diff of the snapshot and the run
+        diff: String,
+    },
+}
+
+impl SnapshotOutcome {
+    /// Checks whether this outcome should make a test run fail.
+    pub fn is_failure(&self) -> bool {
+        matches!(self, SnapshotOutcome::Missing | SnapshotOutcome::Mismatch { .. })
+    }
+}
+
+/// Returns where a program's snapshot lives: next to it, with `.snap` on the end.
+pub fn snapshot_path(program: &Path) -> PathBuf {
+    let mut path = program.as_os_str().to_owned();
+    path.push(".");
+    path.push(SNAPSHOT_EXTENSION);
+    PathBuf::from(path)
+}
+
+/// Runs the program with the given seed and renders what happened as snapshot text.
+pub fn take(source: &str, seed: u64) -> Result<String, ParseError> {
+    let result = SeededChaosHarness::new(seed).run(source)?;
+    Ok(render(&result, seed))
+}
+
+/// Renders a run as snapshot text: the seed, then the output, the chaos and the errors.
+pub fn render(result: &RunResult, seed: u64) -> String {
+    let mut text = format!("# seed: {}\n## output\n", seed);
+    text.push_str(&result.output);
+    if !result.output.is_empty() && !result.output.ends_with('\n') {
+        text.push('\n');
+    }
+    text.push_str("## chaos\n");
+    for event in &result.chaos_events {
+        let _ = writeln!(text, "- {}", event);
+    }
+    text.push_str("## errors\n");
+    for error in &result.errors {
+        let report = error.report();
+        let _ = writeln!(text, "- {} {}: {}", report.code_name(), error.name(), report.message);
+    }
+    if let Some(status) = result.exit_status {
+        let _ = writeln!(text, "## exit status\n{}", status);
+    }
+    text
+}
+
+/// Runs a program file and compares it with its snapshot. With `update`, the snapshot is
+/// rewritten from this run instead (only if it changed, so timestamps stay put).
+pub fn check(program: &Path, seed: u64, update: bool) -> Result<SnapshotOutcome, SnapshotError> {
+    let actual = take(&fs::read_to_string(program)?, seed)?;
+    let path = snapshot_path(program);
+    let expected = match fs::read_to_string(&path) {
+        Ok(expected) => Some(expected),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+
+    match expected {
+        Some(expected) if expected == actual => Ok(SnapshotOutcome::Matched),
+        _ if update => {
+            fs::write(&path, actual)?;
+            Ok(SnapshotOutcome::Written)
+        },
+        None => Ok(SnapshotOutcome::Missing),
+        Some(expected) => Ok(SnapshotOutcome::Mismatch { diff: diff(&expected, &actual) }),
+    }
+}
+
+/// Diffs two texts line by line. Lines only in `expected` start with `-`, lines only in `actual`
+/// with `+`, and lines in both with two spaces.
+pub fn diff(expected: &str, actual: &str) -> String {
+    let old: Vec<&str> = expected.lines().collect();
+    let new: Vec<&str> = actual.lines().collect();
+
+    // lengths[i][j] is the longest common subsequence of old[i..] and new[j..]
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            let _ = writeln!(diff, "  {}", old[i]);
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lengths[i][j + 1] >= lengths[i + 1][j]) {
+            let _ = writeln!(diff, "+ {}", new[j]);
+            j += 1;
+        } else {
+            let _ = writeln!(diff, "- {}", old[i]);
+            i += 1;
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_marks_changes() {
+        assert_eq!(diff("a\nb\nc\n", "a\nx\nc\nd\n"), "  a\n+ x\n- b\n  c\n+ d\n");
+        assert_eq!(diff("same\n", "same\n"), "  same\n");
+    }
+
+    #[test]
+    fn test_check_finds_mismatches() {
+        let dir = std::env::temp_dir().join(format!("upl-snapshot-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let program = dir.join("program.upl");
+        fs::write(&program, "let x = [1, 2, 3]; print(x); print(\"done\");").unwrap();
+
+        assert_eq!(check(&program, 7, true).unwrap(), SnapshotOutcome::Written);
+        assert_eq!(check(&program, 7, false).unwrap(), SnapshotOutcome::Matched);
+
+        fs::write(snapshot_path(&program), "# seed: 7\n## output\nsomething else\n").unwrap();
+        match check(&program, 7, false).unwrap() {
+            SnapshotOutcome::Mismatch { diff } => assert!(diff.contains("- something else"), "{}", diff),
+            outcome => panic!("expected a mismatch, got {:?}", outcome),
+        }
+
+        fs::write(&program, "let = ;").unwrap();
+        assert!(matches!(check(&program, 7, false), Err(SnapshotError::Parse(_))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,58 @@
+//! # Stats Module
+//!
+//! Numbers that describe how a run went, for post-mortems and performance reviews.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::interpreter::Interpreter;
+//!
+//! let interpreter = Interpreter::new();
+//! let stats = interpreter.stats();
+//! assert_eq!(stats.statements_executed, 0);
+//! println!("{}", stats);
+//! ```
+
+use std::fmt;
+
+/// A snapshot of what the interpreter has been up to.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+    /// How many statements were executed (or at least attempted)
+    pub statements_executed: usize,
+    /// How many acts of chaos were recorded in the chaos audit
+    pub chaos_events: usize,
+    /// How many errors were swallowed by try-catch blocks
+    pub errors_caught: usize,
+    /// How infected the interpreter is from all those swallowed errors (0 means healthy)
+    pub contagion_level: f64,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "📊 Stats report")?;
+        writeln!(f, "  Statements executed: {}", self.statements_executed)?;
+        writeln!(f, "  Chaos events: {}", self.chaos_events)?;
+        writeln!(f, "  Errors swallowed: {}", self.errors_caught)?;
+        write!(f, "  Contagion level: {:.0}%", self.contagion_level * 100.0)?;
+        if self.contagion_level > 0.0 {
+            write!(f, " (this is what happens when you ignore your errors 🦠)")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_shames_contagion() {
+        let healthy = Stats::default().to_string();
+        assert!(healthy.contains("Contagion level: 0%"));
+        assert!(!healthy.contains("🦠"));
+
+        let infected = Stats { errors_caught: 3, contagion_level: 0.3, ..Stats::default() }.to_string();
+        assert!(infected.contains("Errors swallowed: 3"));
+        assert!(infected.contains("Contagion level: 30% (this is what happens"));
+    }
+}
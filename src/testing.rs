@@ -0,0 +1,258 @@
+//! # Testing Module
+//!
+//! Ready-made setups for testing Useless programs, so nobody has to copy-paste the same
+//! lexer, parser, interpreter and captured output dance into every test again.
+//!
+//! Both harnesses capture output instead of printing it, run on a [`ManualClock`] so nothing
+//! actually sleeps, and never open a browser tab.
+//!
+//! - [`NormalHarness`] switches all chaos off, for testing what a program means.
+//! - [`SeededChaosHarness`] keeps the chaos on but seeds it, so the same program misbehaves the
+//!   same way every run.
+//!
+//! Tests can also be written in Useless itself: [`discover_tests`] finds the `*_test.upl` files
+//! under a directory, and [`run_test`] runs one in a fresh harness. A test passes when it gets to
+//! the end without an error, so a failed `assert(...)` fails it.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::testing::{NormalHarness, SeededChaosHarness};
+//! use useless_lang::Value;
+//!
+//! let mut harness = NormalHarness::new();
+//! let result = harness.run("let x = add(1, 2); print(x); x;").unwrap();
+//! assert_eq!(result.last_value, Value::Number { value: 3 });
+//! assert!(result.output.contains('3'));
+//!
+//! let first = SeededChaosHarness::new(42).run("print(\"hi\");").unwrap();
+//! let second = SeededChaosHarness::new(42).run("print(\"hi\");").unwrap();
+//! assert_eq!(first.output, second.output);
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::clock::ManualClock;
+use crate::config::Config;
+use crate::interpreter::{Interpreter, RuntimeError};
+use crate::lexer::Lexer;
+use crate::output::CapturedOutput;
+use crate::parser::{ParseError, Parser};
+use crate::report::RunResult;
+
+/// The parts both harnesses share.
+struct Harness {
+    interpreter: Interpreter,
+    output: CapturedOutput,
+    clock: Arc<ManualClock>,
+}
+
+impl Harness {
+    /// Sets up an interpreter that keeps its output, its time and its browser tabs to itself.
+    fn new(config: Config) -> Self {
+        let output = CapturedOutput::new();
+        let clock = Arc::new(ManualClock::new());
+        let mut interpreter = Interpreter::with_config(Config { open_browser: false, ..config });
+        interpreter.set_output(Box::new(output.clone()));
+        interpreter.set_clock(clock.clone());
+        Self { interpreter, output, clock }
+    }
+
+    /// Parses and runs a program.
+    fn run(&mut self, source: &str) -> Result<RunResult, ParseError> {
+        let program = Parser::new(Lexer::new(source).collect()).parse()?;
+        Ok(self.interpreter.interpret_with_report(program))
+    }
+}
+
+/// A harness where everything behaves like a normal language.
+pub struct NormalHarness(Harness);
+
+impl NormalHarness {
+    /// Creates a harness with the default config and no chaos.
+    pub fn new() -> Self {
+        Self::with_config(Config::default())
+    }
+
+    /// Creates a harness with the given config and no chaos.
+    pub fn with_config(config: Config) -> Self {
+        let mut harness = Harness::new(config);
+        harness.interpreter.set_completely_normal(true);
+        Self(harness)
+    }
+
+    /// Parses and runs a program. Variables and functions stick around for the next run.
+    pub fn run(&mut self, source: &str) -> Result<RunResult, ParseError> {
+        self.0.run(source)
+    }
+
+    /// Returns everything printed by every run so far.
+    pub fn output(&self) -> String {
+        self.0.output.contents()
+    }
+
+    /// Returns the clock, to check how long things slept or to move time along.
+    pub fn clock(&self) -> &ManualClock {
+        &self.0.clock
+    }
+
+    /// Returns the interpreter, for anything the harness doesn't cover.
+    pub fn interpreter(&mut self) -> &mut Interpreter {
+        &mut self.0.interpreter
+    }
+}
+
+impl Default for NormalHarness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A harness with all the chaos, but the same chaos every time.
+pub struct SeededChaosHarness(Harness);
+
+impl SeededChaosHarness {
+    /// Creates a chaotic harness with the default config.
+    pub fn new(seed: u64) -> Self {
+        Self::with_config(seed, Config::default())
+    }
+
+    /// Creates a chaotic harness with the given config.
+    pub fn with_config(seed: u64, config: Config) -> Self {
+        let mut harness = Harness::new(config);
+        harness.interpreter.set_seed(seed);
+        Self(harness)
+    }
+
+    /// Parses and runs a program. Variables and functions stick around for the next run.
+    pub fn run(&mut self, source: &str) -> Result<RunResult, ParseError> {
+        self.0.run(source)
+    }
+
+    /// Returns everything printed by every run so far.
+    pub fn output(&self) -> String {
+        self.0.output.contents()
+    }
+
+    /// Returns the clock, to check how long things slept or to move time along.
+    pub fn clock(&self) -> &ManualClock {
+        &self.0.clock
+    }
+
+    /// Returns the interpreter, for anything the harness doesn't cover.
+    pub fn interpreter(&mut self) -> &mut Interpreter {
+        &mut self.0.interpreter
+    }
+}
+
+/// What a file's name has to end with for [`discover_tests`] to find it.
+pub const TEST_FILE_SUFFIX: &str = "_test.upl";
+
+/// How a test program went.
+#[derive(Debug)]
+pub enum TestOutcome {
+    /// It got to the end without an error
+    Passed,
+    /// It didn't, and this is what it printed on the way
+    Failed {
+        /// The errors that stopped it, or an `Exited` if it left with a non-zero status
+        errors: Vec<RuntimeError>,
+        /// Everything it printed
+        output: String,
+    },
+}
+
+/// Checks whether a file is a test program, going by its name.
+pub fn is_test_file(path: &Path) -> bool {
+    path.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.ends_with(TEST_FILE_SUFFIX))
+}
+
+/// Finds every test program under a directory, in a stable order. Hidden directories and
+/// `target` are skipped, since nothing in there was written by hand.
+pub fn discover_tests(root: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut tests = Vec::new();
+    let mut directories = vec![root.to_path_buf()];
+    while let Some(directory) = directories.pop() {
+        for entry in fs::read_dir(&directory)? {
+            let path = entry?.path();
+            let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+            if path.is_dir() {
+                if !name.starts_with('.') && name != "target" {
+                    directories.push(path);
+                }
+            } else if is_test_file(&path) {
+                tests.push(path);
+            }
+        }
+    }
+    tests.sort();
+    Ok(tests)
+}
+
+/// Runs a test program in a fresh harness: a [`NormalHarness`] if `normal`, otherwise a
+/// [`SeededChaosHarness`] with the given seed.
+pub fn run_test(source: &str, normal: bool, seed: u64) -> Result<TestOutcome, ParseError> {
+    let mut result = if normal {
+        NormalHarness::new().run(source)?
+    } else {
+        SeededChaosHarness::new(seed).run(source)?
+    };
+    if let Some(status) = result.exit_status.filter(|&status| status != 0) {
+        result.errors.push(RuntimeError::Exited(status));
+    }
+    if result.errors.is_empty() {
+        Ok(TestOutcome::Passed)
+    } else {
+        Ok(TestOutcome::Failed { errors: result.errors, output: result.output })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Value;
+
+    #[test]
+    fn test_normal_harness_keeps_state_between_runs() {
+        let mut harness = NormalHarness::new();
+        harness.run("let x = 40;").unwrap();
+        let result = harness.run("add(x, 2);").unwrap();
+        assert_eq!(result.last_value, Value::Number { value: 42 });
+        assert!(harness.run("let = ;").is_err());
+    }
+
+    #[test]
+    fn test_test_programs_pass_or_fail() {
+        assert!(matches!(run_test("assert(equals(add(1, 2), 3));", true, 0), Ok(TestOutcome::Passed)));
+        match run_test("print(\"before\"); assert(false, \"nope\"); print(\"after\");", true, 0) {
+            Ok(TestOutcome::Failed { errors, output }) => {
+                assert!(matches!(&errors[..], [RuntimeError::AssertionFailed(message)] if message == "nope"));
+                assert!(output.contains("before") && !output.contains("after"), "{}", output);
+            },
+            outcome => panic!("expected a failure, got {:?}", outcome),
+        }
+        assert!(matches!(run_test("exit(3);", true, 0), Ok(TestOutcome::Failed { errors, .. }) if matches!(errors[..], [RuntimeError::Exited(3)])));
+
+        let dir = std::env::temp_dir().join(format!("upl-discover-test-{}", std::process::id()));
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::create_dir_all(dir.join(".hidden")).unwrap();
+        for file in ["b_test.upl", "nested/a_test.upl", ".hidden/c_test.upl", "helper.upl"] {
+            fs::write(dir.join(file), "").unwrap();
+        }
+        assert_eq!(discover_tests(&dir).unwrap(), [dir.join("b_test.upl"), dir.join("nested/a_test.upl")]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_seeded_chaos_harness_repeats_itself() {
+        let source = "let x = 1; print(x); print(add(x, 2)); let y = [1, 2, 3]; print(y);";
+        let run = |seed| {
+            let result = SeededChaosHarness::new(seed).run(source).unwrap();
+            (result.output, result.chaos_events, result.errors.len())
+        };
+        assert_eq!(run(7), run(7));
+        assert!(!run(7).1.is_empty(), "a chaos harness with no chaos is just a normal harness");
+    }
+}
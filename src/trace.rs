@@ -0,0 +1,140 @@
+//! # Trace Module
+//!
+//! A running commentary of the program, one statement at a time, for settling whether the
+//! program or the language is at fault. A [`Tracer`] attached to an interpreter hears about every
+//! statement as it starts, then again when it's done, along with whatever chaos it suffered.
+//! Chaos goes to the innermost statement it happened in, so nothing gets blamed twice.
+//!
+//! Spans only come with statements that know where they were written, so programs need to be
+//! parsed with [`Parser::set_record_spans`](crate::parser::Parser::set_record_spans).
+//! `useless-lang --trace` prints the commentary on stderr.
+//!
+//! ## Example
+//! ```rust
+//! use std::sync::{Arc, Mutex};
+//! use useless_lang::chaos::ChaosEvent;
+//! use useless_lang::trace::{TracedStatement, Tracer};
+//! use useless_lang::{Config, Interpreter, Lexer, Parser, RuntimeError};
+//!
+//! /// Writes down which lines ran, and how deep.
+//! struct Lines(Arc<Mutex<Vec<(usize, usize)>>>);
+//!
+//! impl Tracer for Lines {
+//!     fn finished(&mut self, statement: &TracedStatement<'_>, _chaos: &[ChaosEvent], _result: &Result<(), RuntimeError>) {
+//!         self.0.lock().unwrap().push((statement.span.unwrap().line, statement.depth));
+//!     }
+//! }
+//!
+//! let mut parser = Parser::new(Lexer::new("let x = 1;\nif (true) {\n    print(x);\n}").collect());
+//! parser.set_record_spans(true);
+//! let program = parser.parse().unwrap();
+//!
+//! let lines = Arc::new(Mutex::new(Vec::new()));
+//! let mut interpreter = Interpreter::with_config(Config { chaos_level: 0, ..Config::default() });
+//! interpreter.set_completely_normal(true);
+//! interpreter.set_tracer(Box::new(Lines(lines.clone())));
+//! interpreter.interpret(program).unwrap();
+//! assert_eq!(*lines.lock().unwrap(), [(1, 0), (3, 1), (2, 0)]);
+//! ```
+
+use crate::ast::Statement;
+use crate::chaos::ChaosEvent;
+use crate::interpreter::RuntimeError;
+use crate::lexer::Span;
+
+/// A statement being traced.
+#[derive(Debug)]
+pub struct TracedStatement<'a> {
+    /// How many statements it's inside of (0 for the top level)
+    pub depth: usize,
+    /// Where it was written, if the parser was keeping track
+    pub span: Option<Span>,
+    /// The statement itself
+    pub statement: &'a Statement,
+}
+
+/// Something that wants a word about every statement that runs.
+pub trait Tracer: Send {
+    /// Called when a statement starts, before anything inside it runs
+    fn entered(&mut self, _statement: &TracedStatement<'_>) {}
+
+    /// Called when a statement is done, with the chaos that happened in it (not counting the
+    /// statements inside it, which got their own) and how it went
+    fn finished(&mut self, statement: &TracedStatement<'_>, chaos: &[ChaosEvent], result: &Result<(), RuntimeError>);
+}
+
+/// A tracer at work, keeping track of whose chaos is whose.
+pub(crate) struct Tracing {
+    tracer: Box<dyn Tracer>,
+    /// The chaos of every statement that hasn't finished yet, innermost last
+    open: Vec<Vec<ChaosEvent>>,
+    /// How much of the interpreter's chaos audit has been handed out already
+    seen: usize,
+}
+
+impl Tracing {
+    /// Puts a tracer to work.
+    pub(crate) fn new(tracer: Box<dyn Tracer>) -> Self {
+        Self { tracer, open: Vec::new(), seen: 0 }
+    }
+
+    /// Tells the tracer a statement is starting. Chaos so far belongs to the statement around it.
+    pub(crate) fn enter(&mut self, span: Option<Span>, statement: &Statement, audit: &[ChaosEvent]) {
+        self.claim(audit);
+        self.tracer.entered(&TracedStatement { depth: self.open.len(), span, statement });
+        self.open.push(Vec::new());
+    }
+
+    /// Tells the tracer a statement is done, with the chaos that happened since it (or the last
+    /// statement inside it) finished.
+    pub(crate) fn finish(&mut self, span: Option<Span>, statement: &Statement, audit: &[ChaosEvent], result: &Result<(), RuntimeError>) {
+        self.claim(audit);
+        let chaos = self.open.pop().unwrap_or_default();
+        self.tracer.finished(&TracedStatement { depth: self.open.len(), span, statement }, &chaos, result);
+    }
+
+    /// Hands the chaos nobody has claimed yet to the innermost open statement.
+    fn claim(&mut self, audit: &[ChaosEvent]) {
+        let unclaimed = audit.get(self.seen..).unwrap_or_default();
+        if let Some(chaos) = self.open.last_mut() {
+            chaos.extend_from_slice(unclaimed);
+        }
+        self.seen = audit.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chaos::ChaosKind;
+    use std::sync::{Arc, Mutex};
+
+    /// Writes down who got blamed for what.
+    struct Blame(Arc<Mutex<Vec<String>>>);
+
+    impl Tracer for Blame {
+        fn finished(&mut self, statement: &TracedStatement<'_>, chaos: &[ChaosEvent], _result: &Result<(), RuntimeError>) {
+            let chaos: Vec<&str> = chaos.iter().map(|event| event.kind.name()).collect();
+            self.0.lock().unwrap().push(format!("{}: {}", statement.depth, chaos.join(", ")));
+        }
+    }
+
+    #[test]
+    fn test_chaos_is_blamed_on_the_innermost_statement() {
+        let blame = Arc::new(Mutex::new(Vec::new()));
+        let mut tracing = Tracing::new(Box::new(Blame(blame.clone())));
+        let statement = Statement::Directive { name: "murphy".to_string() };
+        let mut audit = vec![ChaosEvent::new(ChaosKind::Teapot)];
+
+        // The teapot happened before anything was traced, so it's nobody's fault
+        tracing.enter(None, &statement, &audit);
+        audit.push(ChaosEvent::new(ChaosKind::LiteralMutation));
+        tracing.enter(None, &statement, &audit);
+        audit.push(ChaosEvent::new(ChaosKind::ArrayVacation));
+        tracing.finish(None, &statement, &audit, &Ok(()));
+        audit.push(ChaosEvent::new(ChaosKind::PerfectlyWrong));
+        tracing.finish(None, &statement, &audit, &Err(RuntimeError::PerfectlyWrong));
+
+        assert_eq!(*blame.lock().unwrap(), ["1: array_vacation", "0: literal_mutation, perfectly_wrong"]);
+    }
+}
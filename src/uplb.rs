@@ -0,0 +1,667 @@
+//! # Uplb Module
+//!
+//! A compact binary format for parsed programs (`.uplb`), so services can cache them and the
+//! CLI can run them without lexing or parsing again. Nothing is compiled: it's the same syntax
+//! tree, just smaller and faster to read back in.
+//!
+//! A file starts with the magic bytes `UPLB` and a little-endian `u16` format version. Files
+//! written by a different version are refused, rather than misread into something even more
+//! chaotic than intended. After the header comes the program: every node is a tag byte followed
+//! by its fields, numbers are LEB128 varints (zigzagged when signed), and strings and lists are
+//! prefixed with their length.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::{uplb, Lexer, Parser};
+//!
+//! let program = Parser::new(Lexer::new("let x = [1, 2, 3]; print(x);").collect()).parse().unwrap();
+//! let bytes = uplb::encode(&program);
+//! assert!(bytes.starts_with(uplb::MAGIC));
+//! assert_eq!(uplb::decode(&bytes).unwrap(), program);
+//! ```
+
+use thiserror::Error;
+
+use crate::ast::{BinaryOp, ErrorBinding, Expression, Literal, Program, Statement};
+use crate::bigint::BigInt;
+use crate::intern::Symbol;
+use crate::lexer::Span;
+
+/// The bytes every `.uplb` file starts with.
+pub const MAGIC: &[u8; 4] = b"UPLB";
+
+/// The version of the format written by [`encode`], and the only one [`decode`] reads.
+/// Bump it whenever the syntax tree (or the encoding) changes shape.
+pub const FORMAT_VERSION: u16 = 1;
+
+/// The extension pre-parsed programs are saved with.
+pub const EXTENSION: &str = "uplb";
+
+/// How deeply statements and expressions may nest inside each other before a file is refused.
+/// Reading recurses, so without a limit a crafted file could spend the whole stack on one program.
+/// There's room for anything the parser accepts with its default limits, spans and all.
+pub const MAX_DEPTH: usize = 128;
+
+/// Errors that stop a `.uplb` file from being read.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum UplbError {
+    #[error("Not a .uplb file. It doesn't even start with UPLB")]
+    NotUplb,
+
+    #[error("This .uplb file is format version {0}, but only version {FORMAT_VERSION} is understood. Parse the source again")]
+    UnsupportedVersion(u16),
+
+    #[error("The .uplb file stops in the middle of a thought")]
+    Truncated,
+
+    #[error("Unknown {0} tag {1}. Either the file is corrupt or it's from the future")]
+    InvalidTag(&'static str, u8),
+
+    #[error("A string in the .uplb file isn't valid UTF-8")]
+    InvalidUtf8,
+
+    #[error("A number in the .uplb file doesn't fit, which is impressive for this language")]
+    InvalidNumber,
+
+    #[error("The program ended, but the .uplb file kept going for {0} more bytes")]
+    TrailingBytes(usize),
+
+    #[error("The .uplb file nests things more than {0} deep. Nobody writes programs like that on purpose")]
+    TooDeep(usize),
+}
+
+/// Writes a program in the binary format, header included.
+pub fn encode(program: &[Statement]) -> Vec<u8> {
+    let mut writer = Writer(MAGIC.to_vec());
+    writer.0.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    writer.block(program);
+    writer.0
+}
+
+/// Reads a program back from the binary format.
+pub fn decode(bytes: &[u8]) -> Result<Program, UplbError> {
+    let body = bytes.strip_prefix(MAGIC.as_slice()).ok_or(UplbError::NotUplb)?;
+    let (version, body) = body.split_first_chunk::<2>().ok_or(UplbError::Truncated)?;
+    let version = u16::from_le_bytes(*version);
+    if version != FORMAT_VERSION {
+        return Err(UplbError::UnsupportedVersion(version));
+    }
+
+    let mut reader = Reader { bytes: body, position: 0, depth: 0 };
+    let program = reader.block()?;
+    match reader.bytes.len() - reader.position {
+        0 => Ok(program),
+        extra => Err(UplbError::TrailingBytes(extra)),
+    }
+}
+
+/// Checks whether some bytes look like a `.uplb` file, without reading the rest.
+pub fn is_uplb(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Appends encoded values to a buffer.
+struct Writer(Vec<u8>);
+
+impl Writer {
+    /// Writes an unsigned number as a LEB128 varint.
+    fn unsigned(&mut self, mut value: u64) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                self.0.push(byte);
+                return;
+            }
+            self.0.push(byte | 0x80);
+        }
+    }
+
+    /// Writes a signed number, zigzagged so small negative numbers stay small.
+    fn signed(&mut self, value: i64) {
+        self.unsigned(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    /// Writes a length or count.
+    fn length(&mut self, length: usize) {
+        self.unsigned(length as u64);
+    }
+
+    /// Writes a string, prefixed with its length in bytes.
+    fn string(&mut self, value: &str) {
+        self.length(value.len());
+        self.0.extend_from_slice(value.as_bytes());
+    }
+
+    /// Writes a list of names.
+    fn names(&mut self, names: &[Symbol]) {
+        self.length(names.len());
+        names.iter().for_each(|name| self.string(name));
+    }
+
+    /// Writes a list of statements.
+    fn block(&mut self, statements: &[Statement]) {
+        self.length(statements.len());
+        statements.iter().for_each(|statement| self.statement(statement));
+    }
+
+    /// Writes an expression that might not be there.
+    fn optional(&mut self, expression: &Option<Box<Expression>>) {
+        match expression {
+            Some(expression) => {
+                self.0.push(1);
+                self.expression(expression);
+            },
+            None => self.0.push(0),
+        }
+    }
+
+    /// Writes a statement.
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Print { value } => {
+                self.0.push(0);
+                self.expression(value);
+            },
+            Statement::Let { name, value } => {
+                self.0.push(1);
+                self.string(name);
+                self.expression(value);
+            },
+            Statement::Expression(expression) => {
+                self.0.push(2);
+                self.expression(expression);
+            },
+            Statement::If { condition, then_branch, else_branch } => {
+                self.0.push(3);
+                self.expression(condition);
+                self.block(then_branch);
+                match else_branch {
+                    Some(else_branch) => {
+                        self.0.push(1);
+                        self.block(else_branch);
+                    },
+                    None => self.0.push(0),
+                }
+            },
+            Statement::Loop { body } => {
+                self.0.push(4);
+                self.block(body);
+            },
+            Statement::ForIn { variable, iterable, body } => {
+                self.0.push(5);
+                self.string(variable);
+                self.expression(iterable);
+                self.block(body);
+            },
+            Statement::Function { name, parameters, body } => {
+                self.0.push(6);
+                self.string(name);
+                self.names(parameters);
+                self.block(body);
+            },
+            Statement::AsyncFunction { name, parameters, body } => {
+                self.0.push(7);
+                self.string(name);
+                self.names(parameters);
+                self.block(body);
+            },
+            Statement::TryCatch { try_block, error_binding, catch_block } => {
+                self.0.push(8);
+                self.block(try_block);
+                match error_binding {
+                    ErrorBinding::Name(name) => {
+                        self.0.push(0);
+                        self.string(name);
+                    },
+                    ErrorBinding::Fields(fields) => {
+                        self.0.push(1);
+                        self.names(fields);
+                    },
+                }
+                self.block(catch_block);
+            },
+            Statement::Pray { attempts, body } => {
+                self.0.push(9);
+                match attempts {
+                    Some(attempts) => {
+                        self.0.push(1);
+                        self.length(*attempts);
+                    },
+                    None => self.0.push(0),
+                }
+                self.block(body);
+            },
+            Statement::Concurrent { body } => {
+                self.0.push(19);
+                self.block(body);
+            },
+            Statement::Sudo { statement } => {
+                self.0.push(10);
+                self.statement(statement);
+            },
+            Statement::Module { name, body } => {
+                self.0.push(11);
+                self.string(name);
+                self.block(body);
+            },
+            Statement::Use { path } => {
+                self.0.push(12);
+                self.string(path);
+            },
+            Statement::UseData { name, path } => {
+                self.0.push(20);
+                self.string(name);
+                self.string(path);
+            },
+            Statement::Directive { name } => {
+                self.0.push(13);
+                self.string(name);
+            },
+            Statement::Save { filename } => {
+                self.0.push(14);
+                self.string(filename);
+            },
+            Statement::Await { expression } => {
+                self.0.push(15);
+                self.expression(expression);
+            },
+            Statement::Attributed { name, statement } => {
+                self.0.push(16);
+                self.string(name);
+                self.statement(statement);
+            },
+            Statement::Macro { name, parameters, body } => {
+                self.0.push(17);
+                self.string(name);
+                self.names(parameters);
+                self.block(body);
+            },
+            Statement::Located { span, statement } => {
+                self.0.push(18);
+                for position in [span.start, span.end, span.line, span.column] {
+                    self.unsigned(position as u64);
+                }
+                self.statement(statement);
+            },
+        }
+    }
+
+    /// Writes an expression.
+    fn expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Literal(literal) => {
+                self.0.push(0);
+                self.literal(literal);
+            },
+            Expression::Identifier(name) => {
+                self.0.push(1);
+                self.string(name);
+            },
+            Expression::BinaryOp { op, left, right } => {
+                self.0.push(2);
+                self.0.push(match op {
+                    BinaryOp::Add => 0,
+                    BinaryOp::Multiply => 1,
+                    BinaryOp::Index => 2,
+                    BinaryOp::Access => 3,
+                    BinaryOp::Equals => 4,
+                    BinaryOp::LessThan => 5,
+                    BinaryOp::NullCoalesce => 6,
+                });
+                self.expression(left);
+                self.expression(right);
+            },
+            Expression::FunctionCall { name, arguments } => {
+                self.0.push(3);
+                self.string(name);
+                self.length(arguments.len());
+                arguments.iter().for_each(|argument| self.expression(argument));
+            },
+            Expression::Access { object, key } => {
+                self.0.push(4);
+                self.expression(object);
+                self.expression(key);
+            },
+            Expression::OptionalAccess { object, key } => {
+                self.0.push(5);
+                self.expression(object);
+                self.expression(key);
+            },
+            Expression::Slice { object, start, end } => {
+                self.0.push(6);
+                self.expression(object);
+                self.optional(start);
+                self.optional(end);
+            },
+            Expression::Promise { value, timeout } => {
+                self.0.push(7);
+                self.expression(value);
+                self.optional(timeout);
+            },
+            Expression::Await { promise } => {
+                self.0.push(8);
+                self.expression(promise);
+            },
+        }
+    }
+
+    /// Writes a literal.
+    fn literal(&mut self, literal: &Literal) {
+        match literal {
+            Literal::String(value) => {
+                self.0.push(0);
+                self.string(value);
+            },
+            Literal::Number(value) => {
+                self.0.push(1);
+                self.signed(*value);
+            },
+            Literal::BigInt(value) => {
+                self.0.push(2);
+                self.string(&value.to_string());
+            },
+            Literal::Float(value) => {
+                self.0.push(7);
+                self.unsigned(value.to_bits());
+            },
+            Literal::Boolean(value) => {
+                self.0.push(3);
+                self.0.push(u8::from(*value));
+            },
+            Literal::Array(elements) => {
+                self.0.push(4);
+                self.length(elements.len());
+                elements.iter().for_each(|element| self.expression(element));
+            },
+            Literal::Object(fields) => {
+                self.0.push(5);
+                self.length(fields.len());
+                for (key, value) in fields {
+                    self.string(key);
+                    self.expression(value);
+                }
+            },
+            Literal::Null => self.0.push(6),
+        }
+    }
+}
+
+/// Reads encoded values out of a buffer, front to back.
+struct Reader<'a> {
+    /// Everything after the header
+    bytes: &'a [u8],
+    /// How far into `bytes` reading has got
+    position: usize,
+    /// How many statements and expressions the one being read is inside of
+    depth: usize,
+}
+
+impl Reader<'_> {
+    /// Reads a single byte.
+    fn byte(&mut self) -> Result<u8, UplbError> {
+        let byte = *self.bytes.get(self.position).ok_or(UplbError::Truncated)?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    /// Reads a yes-or-no flag, as written before optional values.
+    fn flag(&mut self, what: &'static str) -> Result<bool, UplbError> {
+        match self.byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            tag => Err(UplbError::InvalidTag(what, tag)),
+        }
+    }
+
+    /// Reads a LEB128 varint.
+    fn unsigned(&mut self) -> Result<u64, UplbError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7f).checked_shl(shift).ok_or(UplbError::InvalidNumber)?;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(UplbError::InvalidNumber)
+    }
+
+    /// Reads a zigzagged signed number.
+    fn signed(&mut self) -> Result<i64, UplbError> {
+        let value = self.unsigned()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    /// Reads a position in the source, which has nothing to do with how much is left to read.
+    fn position(&mut self) -> Result<usize, UplbError> {
+        usize::try_from(self.unsigned()?).map_err(|_| UplbError::InvalidNumber)
+    }
+
+    /// Reads a length or count. Lengths can't be longer than what's left to read, which keeps a
+    /// corrupt file from asking for all the memory in the world.
+    fn length(&mut self) -> Result<usize, UplbError> {
+        let length = usize::try_from(self.unsigned()?).map_err(|_| UplbError::InvalidNumber)?;
+        if length > self.bytes.len() - self.position {
+            return Err(UplbError::Truncated);
+        }
+        Ok(length)
+    }
+
+    /// Reads a length-prefixed string.
+    fn string(&mut self) -> Result<String, UplbError> {
+        let length = self.length()?;
+        let bytes = &self.bytes[self.position..self.position + length];
+        self.position += length;
+        String::from_utf8(bytes.to_vec()).map_err(|_| UplbError::InvalidUtf8)
+    }
+
+    /// Reads a name, and interns it.
+    fn name(&mut self) -> Result<Symbol, UplbError> {
+        self.string().map(Symbol::from)
+    }
+
+    /// Reads a list of names.
+    fn names(&mut self) -> Result<Vec<Symbol>, UplbError> {
+        (0..self.length()?).map(|_| self.name()).collect()
+    }
+
+    /// Reads a list of statements.
+    fn block(&mut self) -> Result<Vec<Statement>, UplbError> {
+        (0..self.length()?).map(|_| self.statement()).collect()
+    }
+
+    /// Reads a list of expressions.
+    fn expressions(&mut self) -> Result<Vec<Expression>, UplbError> {
+        (0..self.length()?).map(|_| self.expression()).collect()
+    }
+
+    /// Reads an expression that might not be there.
+    fn optional(&mut self) -> Result<Option<Box<Expression>>, UplbError> {
+        Ok(if self.flag("optional")? { Some(Box::new(self.expression()?)) } else { None })
+    }
+
+    /// Reads something one level deeper, refusing to go past [`MAX_DEPTH`].
+    fn nested<T>(&mut self, read: impl FnOnce(&mut Self) -> Result<T, UplbError>) -> Result<T, UplbError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(UplbError::TooDeep(MAX_DEPTH));
+        }
+        self.depth += 1;
+        let result = read(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Reads a statement.
+    fn statement(&mut self) -> Result<Statement, UplbError> {
+        self.nested(Self::bare_statement)
+    }
+
+    /// Reads a statement, without counting how deep it is.
+    fn bare_statement(&mut self) -> Result<Statement, UplbError> {
+        Ok(match self.byte()? {
+            0 => Statement::Print { value: self.expression()? },
+            1 => Statement::Let { name: self.name()?, value: self.expression()? },
+            2 => Statement::Expression(self.expression()?),
+            3 => Statement::If {
+                condition: self.expression()?,
+                then_branch: self.block()?,
+                else_branch: if self.flag("else")? { Some(self.block()?) } else { None },
+            },
+            4 => Statement::Loop { body: self.block()? },
+            5 => Statement::ForIn { variable: self.name()?, iterable: self.expression()?, body: self.block()? },
+            6 => Statement::Function { name: self.name()?, parameters: self.names()?, body: self.block()? },
+            7 => Statement::AsyncFunction { name: self.name()?, parameters: self.names()?, body: self.block()? },
+            8 => Statement::TryCatch {
+                try_block: self.block()?,
+                error_binding: match self.byte()? {
+                    0 => ErrorBinding::Name(self.name()?),
+                    1 => ErrorBinding::Fields(self.names()?),
+                    tag => return Err(UplbError::InvalidTag("error binding", tag)),
+                },
+                catch_block: self.block()?,
+            },
+            9 => Statement::Pray {
+                attempts: if self.flag("attempts")? {
+                    Some(usize::try_from(self.unsigned()?).map_err(|_| UplbError::InvalidNumber)?)
+                } else {
+                    None
+                },
+                body: self.block()?,
+            },
+            10 => Statement::Sudo { statement: Box::new(self.statement()?) },
+            11 => Statement::Module { name: self.name()?, body: self.block()? },
+            12 => Statement::Use { path: self.string()? },
+            13 => Statement::Directive { name: self.string()? },
+            14 => Statement::Save { filename: self.string()? },
+            15 => Statement::Await { expression: self.expression()? },
+            16 => Statement::Attributed { name: self.string()?, statement: Box::new(self.statement()?) },
+            17 => Statement::Macro { name: self.name()?, parameters: self.names()?, body: self.block()? },
+            18 => Statement::Located {
+                span: Span { start: self.position()?, end: self.position()?, line: self.position()?, column: self.position()? },
+                statement: Box::new(self.statement()?),
+            },
+            19 => Statement::Concurrent { body: self.block()? },
+            20 => Statement::UseData { name: self.name()?, path: self.string()? },
+            tag => return Err(UplbError::InvalidTag("statement", tag)),
+        })
+    }
+
+    /// Reads an expression.
+    fn expression(&mut self) -> Result<Expression, UplbError> {
+        self.nested(Self::bare_expression)
+    }
+
+    /// Reads an expression, without counting how deep it is.
+    fn bare_expression(&mut self) -> Result<Expression, UplbError> {
+        Ok(match self.byte()? {
+            0 => Expression::Literal(self.literal()?),
+            1 => Expression::Identifier(self.name()?),
+            2 => Expression::BinaryOp {
+                op: match self.byte()? {
+                    0 => BinaryOp::Add,
+                    1 => BinaryOp::Multiply,
+                    2 => BinaryOp::Index,
+                    3 => BinaryOp::Access,
+                    4 => BinaryOp::Equals,
+                    5 => BinaryOp::LessThan,
+                    6 => BinaryOp::NullCoalesce,
+                    tag => return Err(UplbError::InvalidTag("operator", tag)),
+                },
+                left: Box::new(self.expression()?),
+                right: Box::new(self.expression()?),
+            },
+            3 => Expression::FunctionCall { name: self.name()?, arguments: self.expressions()? },
+            4 => Expression::Access { object: Box::new(self.expression()?), key: Box::new(self.expression()?) },
+            5 => Expression::OptionalAccess { object: Box::new(self.expression()?), key: Box::new(self.expression()?) },
+            6 => Expression::Slice { object: Box::new(self.expression()?), start: self.optional()?, end: self.optional()? },
+            7 => Expression::Promise { value: Box::new(self.expression()?), timeout: self.optional()? },
+            8 => Expression::Await { promise: Box::new(self.expression()?) },
+            tag => return Err(UplbError::InvalidTag("expression", tag)),
+        })
+    }
+
+    /// Reads a literal.
+    fn literal(&mut self) -> Result<Literal, UplbError> {
+        Ok(match self.byte()? {
+            0 => Literal::String(self.string()?),
+            1 => Literal::Number(self.signed()?),
+            2 => Literal::BigInt(BigInt::parse(&self.string()?).ok_or(UplbError::InvalidNumber)?),
+            3 => Literal::Boolean(self.flag("boolean")?),
+            4 => Literal::Array(self.expressions()?.into_iter().map(Box::new).collect()),
+            5 => Literal::Object(
+                (0..self.length()?)
+                    .map(|_| Ok((self.string()?, Box::new(self.expression()?))))
+                    .collect::<Result<_, UplbError>>()?,
+            ),
+            6 => Literal::Null,
+            7 => Literal::Float(f64::from_bits(self.unsigned()?)),
+            tag => return Err(UplbError::InvalidTag("literal", tag)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_round_trip_keeps_everything() {
+        let source = r#"
+            use normal::mode;
+            let things = [1, 2, 3, 99999999999999999999999]; let o = { "a": -5, "b": things[0..2] };
+            greet(name) { print(name?.first ?? "nobody"); }
+            async later(x) { await promise(x, 100); }
+            try { save "x.txt"; } catch { code, message } { print(message); }
+            pray(3) { sudo let y = multiply(2, 3); }
+            for t in things { if (lessThan(t, 2)) { print(t); } else { loop { print(equals(t, true)); } } }
+            macro twice(x) { print(x); print(x); }
+            #[disable_useless] print(index(o ?? null, 0));
+            mod inner { let z = access(o, "a"); }
+            exit(0);
+        "#;
+        let program = Parser::new(Lexer::new(source).collect()).parse().unwrap();
+        let bytes = encode(&program);
+        assert_eq!(decode(&bytes).unwrap(), program);
+        assert!(bytes.len() < source.len(), "{} bytes isn't very compact", bytes.len());
+    }
+
+    #[test]
+    fn test_refuses_strange_files() {
+        let bytes = encode(&[Statement::Print { value: Expression::Literal(Literal::Number(-1)) }]);
+        assert_eq!(decode(b"print(1);"), Err(UplbError::NotUplb));
+        assert_eq!(decode(&bytes[..bytes.len() - 1]), Err(UplbError::Truncated));
+        assert_eq!(decode(&[bytes.as_slice(), &[0]].concat()), Err(UplbError::TrailingBytes(1)));
+
+        let mut future = bytes.clone();
+        future[4..6].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert_eq!(decode(&future), Err(UplbError::UnsupportedVersion(FORMAT_VERSION + 1)));
+
+        let mut corrupt = bytes;
+        corrupt[7] = 200;
+        assert_eq!(decode(&corrupt), Err(UplbError::InvalidTag("statement", 200)));
+    }
+
+    #[test]
+    fn test_refuses_files_nested_too_deep() {
+        // A statement wrapped in a few hundred thousand locations, written by hand since nothing
+        // would ever parse into it
+        let wrappers = 200_000;
+        let mut bytes = [MAGIC.as_slice(), &FORMAT_VERSION.to_le_bytes(), &[1]].concat();
+        for _ in 0..wrappers {
+            bytes.extend_from_slice(&[18, 0, 0, 0, 0]);
+        }
+        bytes.extend_from_slice(&encode(&[Statement::Use { path: "x".to_string() }])[7..]);
+        assert_eq!(decode(&bytes), Err(UplbError::TooDeep(MAX_DEPTH)));
+
+        // Right up to the limit is fine
+        let mut statement = Statement::Use { path: "x".to_string() };
+        for _ in 1..MAX_DEPTH {
+            statement = Statement::Located { span: Span::default(), statement: Box::new(statement) };
+        }
+        let program = vec![statement];
+        assert_eq!(decode(&encode(&program)).unwrap(), program);
+    }
+}
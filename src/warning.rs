@@ -0,0 +1,58 @@
+//! # Warning Module
+//!
+//! Things the interpreter would like to mention without stopping the program over them.
+//! Warnings don't get printed along with the program's output: the interpreter collects them
+//! ([`Interpreter::warnings`](crate::interpreter::Interpreter::warnings), [`RunResult::warnings`](crate::RunResult::warnings)),
+//! so hosts can show them wherever warnings go. The command line puts them on stderr.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::warning::Warning;
+//! use useless_lang::{Config, Interpreter, Lexer, Parser};
+//!
+//! let program = Parser::new(Lexer::new("#[directive(sparkles)]\nlet x = 1;").collect()).parse().unwrap();
+//! let mut interpreter = Interpreter::with_config(Config { chaos_level: 0, ..Config::default() });
+//! interpreter.set_completely_normal(true);
+//! interpreter.interpret(program).unwrap();
+//! assert_eq!(interpreter.warnings(), [Warning::UnknownDirective("sparkles".to_string())]);
+//! assert_eq!(interpreter.warnings()[0].report().code, 201);
+//! ```
+
+use thiserror::Error;
+
+use crate::error::{ErrorKind, ErrorReport, Severity};
+use crate::plugin::BUILTIN_DIRECTIVES;
+
+/// Something that might be a mistake, but wasn't worth stopping the program for.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum Warning {
+    /// A directive nobody (not even a plugin) has heard of, so it did nothing at all
+    #[error("Unknown directive #{0}. It was ignored, which is more than most directives get")]
+    UnknownDirective(String),
+}
+
+impl Warning {
+    /// Returns the warning's stable code. Warnings live in the 200s, away from the errors.
+    pub fn code(&self) -> u16 {
+        match self {
+            Warning::UnknownDirective(_) => 201,
+        }
+    }
+
+    /// Turns the warning into a report, like errors get, but less serious.
+    pub fn report(&self) -> ErrorReport {
+        ErrorReport {
+            code: self.code(),
+            kind: ErrorKind::Program,
+            severity: Severity::Warning,
+            message: self.to_string(),
+            span: None,
+            hint: match self {
+                Warning::UnknownDirective(_) => {
+                    Some(format!("The directives are {}, print_to and frozen_clock, plus whatever plugins add", BUILTIN_DIRECTIVES.join(", ")))
+                },
+            },
+            fixes: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! # Watch Module
+//!
+//! Notices when a file changes, so `useless-lang watch` can run it again the moment it's saved.
+//! It polls the file's modification time and size instead of asking the operating system to
+//! call back, which is slower but works everywhere, including network drives and editors that
+//! save by swapping files around.
+//!
+//! ## Example
+//! ```rust
+//! use useless_lang::watch::FileWatcher;
+//!
+//! let path = std::env::temp_dir().join(format!("upl-watch-doc-{}.upl", std::process::id()));
+//! std::fs::write(&path, "print(1);").unwrap();
+//!
+//! let mut watcher = FileWatcher::new(&path);
+//! assert!(!watcher.has_changed());
+//! std::fs::write(&path, "print(1); print(2);").unwrap();
+//! assert!(watcher.has_changed());
+//! assert!(!watcher.has_changed(), "a change is only reported once");
+//! # std::fs::remove_file(&path).unwrap();
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// How often `useless-lang watch` looks at the file.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// What a file looked like last time: when it was modified and how big it was.
+/// `None` when it couldn't be looked at, e.g. because an editor is halfway through saving it.
+type Fingerprint = Option<(SystemTime, u64)>;
+
+/// Watches a single file for changes.
+pub struct FileWatcher {
+    /// The file being watched
+    path: PathBuf,
+    /// What it looked like the last time anyone asked
+    last_seen: Fingerprint,
+}
+
+impl FileWatcher {
+    /// Starts watching a file. Whatever state it's in now counts as unchanged.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let last_seen = fingerprint(&path);
+        Self { path, last_seen }
+    }
+
+    /// Returns the file being watched.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Checks whether the file changed since the last call (or since watching started).
+    /// A file that disappears doesn't count as changed until it comes back.
+    pub fn has_changed(&mut self) -> bool {
+        let current = fingerprint(&self.path);
+        if current.is_none() || current == self.last_seen {
+            return false;
+        }
+        self.last_seen = current;
+        true
+    }
+}
+
+/// Looks at a file without reading it.
+fn fingerprint(path: &Path) -> Fingerprint {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notices_saves_but_not_deletions() {
+        let path = std::env::temp_dir().join(format!("upl-watch-test-{}.upl", std::process::id()));
+        fs::write(&path, "let x = 1;").unwrap();
+        let mut watcher = FileWatcher::new(&path);
+
+        // Same size, so only the timestamp gives it away
+        let file = fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(60)).unwrap();
+        assert!(watcher.has_changed());
+
+        fs::remove_file(&path).unwrap();
+        assert!(!watcher.has_changed());
+        fs::write(&path, "let x = 2; let y = 3;").unwrap();
+        assert!(watcher.has_changed());
+        fs::remove_file(&path).unwrap();
+    }
+}
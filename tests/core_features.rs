@@ -1,5 +1,8 @@
+// Chaos tests deliberately accept any outcome, so the match arms stay explicit.
+#![allow(clippy::single_match)]
+
 use useless_lang::{
-    ast::{Expression, Literal, Statement, BinaryOp},
+    ast::{Expression, Literal, Statement, BinaryOp, ErrorBinding},
     interpreter::Interpreter,
 };
 
@@ -16,13 +19,13 @@ fn test_array_operations() {
 
     // Store array in variable
     let store_array = Statement::Let {
-        name: "test_array".to_string(),
+        name: "test_array".into(),
         value: array_expr,
     };
 
     // Try to access array (might get random element or vacation error)
     let access_array = Expression::Access {
-        object: Box::new(Expression::Identifier("test_array".to_string())),
+        object: Box::new(Expression::Identifier("test_array".into())),
         key: Box::new(Expression::Literal(Literal::Number(1))),
     };
 
@@ -43,8 +46,8 @@ fn test_async_operations() {
 
     // Create an async function
     let async_fn = Statement::AsyncFunction {
-        name: "test_async".to_string(),
-        parameters: vec!["x".to_string()],
+        name: "test_async".into(),
+        parameters: vec!["x".into()],
         body: vec![
             Statement::Expression(Expression::Literal(Literal::String("async test".to_string()))),
         ],
@@ -81,10 +84,10 @@ fn test_error_handling() {
                 right: Box::new(Expression::Literal(Literal::Number(0))),
             }),
         ],
-        error_var: "error".to_string(),
+        error_binding: ErrorBinding::Name("error".into()),
         catch_block: vec![
             Statement::Print {
-                value: Expression::Identifier("error".to_string()),
+                value: Expression::Identifier("error".into()),
             },
         ],
     };
@@ -160,15 +163,15 @@ fn test_async_features() {
 
     // Test AsyncFunction
     let async_fn = Statement::AsyncFunction {
-        name: "test_async".to_string(),
-        parameters: vec!["x".to_string()],
+        name: "test_async".into(),
+        parameters: vec!["x".into()],
         body: vec![Statement::Expression(await_expr.clone())],
     };
 
     // Test TryCatch
     let try_catch = Statement::TryCatch {
         try_block: vec![Statement::Expression(await_expr)],
-        error_var: "error".to_string(),
+        error_binding: ErrorBinding::Name("error".into()),
         catch_block: vec![],
     };
 
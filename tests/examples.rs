@@ -1,6 +1,6 @@
 use std::fs;
 use useless_lang::{
-    ast::{Program, Statement},
+    ast::Program,
     interpreter::Interpreter,
     lexer::Lexer,
     parser::Parser,